@@ -1,11 +1,13 @@
 mod stats;
 
-use stats::{orderstat_tail, quantile_conf};
+use stats::{orderstat_tail, quantile_conf, RollingStats};
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::process;
 
+use statn::walkforward::WalkForward;
+
 /// Compute optimal short-term and long-term lookbacks
 /// for a primitive moving-average crossover system
 fn opt_params(
@@ -21,28 +23,18 @@ fn opt_params(
         for ishort in 1..ilong {
             let mut total_return = 0.0;
 
-            let mut short_sum = 0.0;
-            let mut long_sum = 0.0;
+            let mut short_ma = RollingStats::new(ishort);
+            let mut long_ma = RollingStats::new(ilong);
+            for &price in &x[..ilong - 1] {
+                short_ma.push(price);
+                long_ma.push(price);
+            }
 
             for i in (ilong - 1)..(ncases - 1) {
-                if i == ilong - 1 {
-                    // Initialize sums for first valid case
-                    short_sum = 0.0;
-                    for j in (i - ishort + 1)..=i {
-                        short_sum += x[j];
-                    }
-                    long_sum = short_sum;
-                    for j in (i - ilong + 1)..=(i - ishort) {
-                        long_sum += x[j];
-                    }
-                } else {
-                    // Update moving averages
-                    short_sum += x[i] - x[i - ishort];
-                    long_sum += x[i] - x[i - ilong];
-                }
-
-                let short_mean = short_sum / ishort as f64;
-                let long_mean = long_sum / ilong as f64;
+                short_ma.push(x[i]);
+                long_ma.push(x[i]);
+                let short_mean = short_ma.mean();
+                let long_mean = long_ma.mean();
 
                 // Take position and cumulate performance
                 let ret = if short_mean > long_mean {
@@ -77,22 +69,18 @@ fn test_system(
     long_term: usize,
 ) -> f64 {
     let mut sum = 0.0;
-    let mut n = ncases;
-
-    let mut i = long_term - 1;
-    while n > 0 {
-        let mut short_mean = 0.0;
-        for j in (i - short_term + 1)..=i {
-            short_mean += x[j];
-        }
-
-        let mut long_mean = short_mean;
-        for j in (i - long_term + 1)..=(i - short_term) {
-            long_mean += x[j];
-        }
+    let mut short_ma = RollingStats::new(short_term);
+    let mut long_ma = RollingStats::new(long_term);
+    for &price in &x[..long_term - 1] {
+        short_ma.push(price);
+        long_ma.push(price);
+    }
 
-        short_mean /= short_term as f64;
-        long_mean /= long_term as f64;
+    for i in long_term - 1..long_term - 1 + ncases {
+        short_ma.push(x[i]);
+        long_ma.push(x[i]);
+        let short_mean = short_ma.mean();
+        let long_mean = long_ma.mean();
 
         // Take position and cumulate return
         if short_mean > long_mean {
@@ -100,9 +88,6 @@ fn test_system(
         } else if short_mean < long_mean {
             sum -= x[i + 1] - x[i]; // Short position
         }
-
-        n -= 1;
-        i += 1;
     }
 
     sum / ncases as f64
@@ -111,8 +96,8 @@ fn test_system(
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 8 {
-        eprintln!("\nUsage: {} max_lookback n_train n_test lower_fail upper_fail p_of_q filename", args[0]);
+    if !(8..=9).contains(&args.len()) {
+        eprintln!("\nUsage: {} max_lookback n_train n_test lower_fail upper_fail p_of_q filename [embargo]", args[0]);
         eprintln!("  max_lookback - Maximum moving-average lookback");
         eprintln!("  n_train - Number of bars in training set (much greater than max_lookback)");
         eprintln!("  n_test - Number of bars in test set");
@@ -120,6 +105,7 @@ fn main() {
         eprintln!("  upper_fail - Upper bound failure rate (often 0.1-0.5)");
         eprintln!("  p_of_q - Probability of bad bound (often 0.01-0.1)");
         eprintln!("  filename - name of market file (YYYYMMDD Price)");
+        eprintln!("  embargo - Bars skipped between training and test sets (default 0)");
         process::exit(1);
     }
 
@@ -130,6 +116,7 @@ fn main() {
     let upper_fail_rate: f64 = args[5].parse().expect("Invalid upper_fail");
     let p_of_q: f64 = args[6].parse().expect("Invalid p_of_q");
     let filename = &args[7];
+    let embargo: usize = args.get(8).map_or(0, |s| s.parse().expect("Invalid embargo"));
 
     if n_train - max_lookback < 10 {
         eprintln!("\nERROR... n_train must be at least 10 greater than max_lookback");
@@ -183,50 +170,45 @@ fn main() {
     let nprices = prices.len();
     println!("\nMarket price history read");
 
-    if n_train + n_test > nprices {
-        eprintln!("\nERROR... n_train + n_test must not exceed n_prices.");
+    if n_train + embargo + n_test > nprices {
+        eprintln!("\nERROR... n_train + embargo + n_test must not exceed n_prices.");
         process::exit(1);
     }
 
-    let mut returns = Vec::new();
-    let mut train_start = 0;
-    let mut total = 0.0;
+    let walkforward = WalkForward {
+        embargo,
+        ..WalkForward::new(n_train, n_test)
+    };
 
     // Do walkforward
-    loop {
-        let (is_perf, short_lookback, long_lookback) = opt_params(
-            n_train,
-            max_lookback,
-            &prices[train_start..train_start + n_train],
-        );
-        let is_annualized = is_perf * 25200.0;
-        println!(
-            "\n\nIS = {:.3} at {}  Lookback={} {}",
-            is_annualized, train_start, short_lookback, long_lookback
-        );
-
-        let mut n = n_test;
-        if n > nprices - train_start - n_train {
-            n = nprices - train_start - n_train;
-        }
-
-        let oos = test_system(
-            n,
-            &prices[train_start + n_train - long_lookback..],
-            short_lookback,
-            long_lookback,
-        );
-        let oos_annualized = oos * 25200.0;
-        println!("OOS = {:.3} at {}", oos_annualized, train_start + n_train);
-
-        returns.push(oos_annualized);
-        total += oos_annualized;
-
-        train_start += n;
-        if train_start + n_train >= nprices {
-            break;
-        }
-    }
+    let folds = walkforward.run(
+        nprices,
+        |fold| {
+            let (is_perf, short_lookback, long_lookback) =
+                opt_params(n_train, max_lookback, &prices[fold.train()]);
+            let is_annualized = is_perf * 25200.0;
+            println!(
+                "\n\nIS = {:.3} at {}  Lookback={} {}",
+                is_annualized, fold.train_start, short_lookback, long_lookback
+            );
+            (short_lookback, long_lookback)
+        },
+        |fold, &(short_lookback, long_lookback)| {
+            // The first test decision is made on the bar before the test set,
+            // which with an embargo lies past the end of the training set
+            let oos = test_system(
+                fold.n_test(),
+                &prices[fold.test_start - long_lookback..],
+                short_lookback,
+                long_lookback,
+            );
+            let oos_annualized = oos * 25200.0;
+            println!("OOS = {:.3} at {}", oos_annualized, fold.test_start);
+            oos_annualized
+        },
+    );
+    let mut returns: Vec<f64> = folds.iter().map(|fold| fold.result).collect();
+    let total: f64 = returns.iter().sum();
 
     let n_returns = returns.len();
     println!("\n\nAll returns are approximately annualized by multiplying by 25200");
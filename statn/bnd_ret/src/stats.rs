@@ -1,4 +1,4 @@
-pub use stats::{orderstat_tail, quantile_conf};
+pub use stats::{orderstat_tail, quantile_conf, RollingStats};
 
 #[cfg(test)]
 mod tests {
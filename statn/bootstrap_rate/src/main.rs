@@ -2,20 +2,45 @@ use std::env;
 use std::f64::consts::PI;
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
-use bootstrap_rate::bootstrap::{boot_conf_pctile, boot_conf_bca};
+use stats::{boot_conf, BootstrapMethod, ConfLimits, Resampler};
 
 // Use log for Profit Factor?
 const USE_LOG: bool = true;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = Vec::new();
+    let mut method = BootstrapMethod::Iid;
+    let mut block_length: Option<f64> = None;
+    let mut argv = env::args();
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--bootstrap-method" => {
+                method = argv.next().unwrap_or_default().parse().unwrap_or_else(|e| {
+                    println!("\n{}", e);
+                    std::process::exit(1);
+                });
+            }
+            "--block-length" => {
+                block_length = match argv.next().and_then(|v| v.parse::<f64>().ok()) {
+                    Some(length) if length >= 1.0 => Some(length),
+                    _ => {
+                        println!("\nblock-length must be a number of at least 1");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            _ => args.push(arg),
+        }
+    }
 
     let (nsamps, nboot, ntries, prob) = if args.len() != 5 {
-        println!("\nUsage: bootstrap_rate  nsamples  nboot  ntries  prob");
+        println!("\nUsage: bootstrap_rate  [--bootstrap-method iid|stationary|block] [--block-length B]  nsamples  nboot  ntries  prob");
         println!("  nsamples - Number of price changes in market history");
         println!("  nboot - Number of bootstrap replications");
         println!("  ntries - Number of trials for generating summary");
         println!("  prob - Probability that a trade will be a win");
+        println!("  --bootstrap-method - iid (default), stationary or block (moving-block) resampling");
+        println!("  --block-length - Block length, the mean for stationary (default: chosen from each sample)");
         // Default values for testing if arguments are missing, or exit?
         // C++ code exits. But also has #if 1 ... #else defaults.
         // I'll exit with usage message.
@@ -44,26 +69,10 @@ fn main() {
 
     let mut x = vec![0.0; nsamps];
     let mut param = vec![0.0; ntries];
-    let mut low2p5_1 = vec![0.0; ntries];
-    let mut high2p5_1 = vec![0.0; ntries];
-    let mut low5_1 = vec![0.0; ntries];
-    let mut high5_1 = vec![0.0; ntries];
-    let mut low10_1 = vec![0.0; ntries];
-    let mut high10_1 = vec![0.0; ntries];
-
-    let mut low2p5_2 = vec![0.0; ntries];
-    let mut high2p5_2 = vec![0.0; ntries];
-    let mut low5_2 = vec![0.0; ntries];
-    let mut high5_2 = vec![0.0; ntries];
-    let mut low10_2 = vec![0.0; ntries];
-    let mut high10_2 = vec![0.0; ntries];
-
-    let mut low2p5_3 = vec![0.0; ntries];
-    let mut high2p5_3 = vec![0.0; ntries];
-    let mut low5_3 = vec![0.0; ntries];
-    let mut high5_3 = vec![0.0; ntries];
-    let mut low10_3 = vec![0.0; ntries];
-    let mut high10_3 = vec![0.0; ntries];
+    let mut pctile = vec![ConfLimits::default(); ntries];
+    let mut bca = vec![ConfLimits::default(); ntries];
+    let mut pivot = vec![ConfLimits::default(); ntries];
+    let mut boot_t = vec![ConfLimits::default(); ntries];
 
     let mut true_sum = 0.0;
     let mut true_sumsq = 0.0;
@@ -94,29 +103,12 @@ fn main() {
 
         param[itry] = param_pf(&x);
 
-        let (l2p5, h2p5, l5, h5, l10, h10) = boot_conf_pctile(&x, param_pf, nboot);
-        low2p5_1[itry] = l2p5;
-        high2p5_1[itry] = h2p5;
-        low5_1[itry] = l5;
-        high5_1[itry] = h5;
-        low10_1[itry] = l10;
-        high10_1[itry] = h10;
-
-        let (l2p5, h2p5, l5, h5, l10, h10) = boot_conf_bca(&x, param_pf, nboot);
-        low2p5_2[itry] = l2p5;
-        high2p5_2[itry] = h2p5;
-        low5_2[itry] = l5;
-        high5_2[itry] = h5;
-        low10_2[itry] = l10;
-        high10_2[itry] = h10;
-
-        // Pivot method
-        low2p5_3[itry] = 2.0 * param[itry] - high2p5_1[itry];
-        high2p5_3[itry] = 2.0 * param[itry] - low2p5_1[itry];
-        low5_3[itry] = 2.0 * param[itry] - high5_1[itry];
-        high5_3[itry] = 2.0 * param[itry] - low5_1[itry];
-        low10_3[itry] = 2.0 * param[itry] - high10_1[itry];
-        high10_3[itry] = 2.0 * param[itry] - low10_1[itry];
+        let resampler = Resampler::new(method, block_length, &x);
+        let result = boot_conf(&x, param_pf, nboot, &resampler, &mut rng);
+        pctile[itry] = result.percentile;
+        bca[itry] = result.bca;
+        pivot[itry] = result.pivot;
+        boot_t[itry] = result.t;
 
         if (itry % divisor == 1) || (itry == ntries - 1) {
             let ndone = itry + 1;
@@ -129,9 +121,10 @@ fn main() {
             };
             println!("\n{}", line1);
 
-            print_stats("Pctile", ndone, true_pf, &low2p5_1, &high2p5_1, &low5_1, &high5_1, &low10_1, &high10_1);
-            print_stats("BCa   ", ndone, true_pf, &low2p5_2, &high2p5_2, &low5_2, &high5_2, &low10_2, &high10_2);
-            print_stats("Pivot ", ndone, true_pf, &low2p5_3, &high2p5_3, &low5_3, &high5_3, &low10_3, &high10_3);
+            print_stats("Pctile", true_pf, &pctile[..ndone]);
+            print_stats("BCa   ", true_pf, &bca[..ndone]);
+            print_stats("Pivot ", true_pf, &pivot[..ndone]);
+            print_stats("Boot t", true_pf, &boot_t[..ndone]);
         }
     }
 
@@ -168,28 +161,12 @@ fn main() {
 
         param[itry] = param_sr(&x);
 
-        let (l2p5, h2p5, l5, h5, l10, h10) = boot_conf_pctile(&x, param_sr, nboot);
-        low2p5_1[itry] = l2p5;
-        high2p5_1[itry] = h2p5;
-        low5_1[itry] = l5;
-        high5_1[itry] = h5;
-        low10_1[itry] = l10;
-        high10_1[itry] = h10;
-
-        let (l2p5, h2p5, l5, h5, l10, h10) = boot_conf_bca(&x, param_sr, nboot);
-        low2p5_2[itry] = l2p5;
-        high2p5_2[itry] = h2p5;
-        low5_2[itry] = l5;
-        high5_2[itry] = h5;
-        low10_2[itry] = l10;
-        high10_2[itry] = h10;
-
-        low2p5_3[itry] = 2.0 * param[itry] - high2p5_1[itry];
-        high2p5_3[itry] = 2.0 * param[itry] - low2p5_1[itry];
-        low5_3[itry] = 2.0 * param[itry] - high5_1[itry];
-        high5_3[itry] = 2.0 * param[itry] - low5_1[itry];
-        low10_3[itry] = 2.0 * param[itry] - high10_1[itry];
-        high10_3[itry] = 2.0 * param[itry] - low10_1[itry];
+        let resampler = Resampler::new(method, block_length, &x);
+        let result = boot_conf(&x, param_sr, nboot, &resampler, &mut rng);
+        pctile[itry] = result.percentile;
+        bca[itry] = result.bca;
+        pivot[itry] = result.pivot;
+        boot_t[itry] = result.t;
 
         if (itry % divisor == 1) || (itry == ntries - 1) {
             if itry == ntries - 1 {
@@ -200,14 +177,21 @@ fn main() {
 
             println!("\nMean sr = {:.5}  true = {:.5}", mean_param, true_sr);
 
-            print_stats("Pctile", ndone, true_sr, &low2p5_1, &high2p5_1, &low5_1, &high5_1, &low10_1, &high10_1);
-            print_stats("BCa   ", ndone, true_sr, &low2p5_2, &high2p5_2, &low5_2, &high5_2, &low10_2, &high10_2);
-            print_stats("Pivot ", ndone, true_sr, &low2p5_3, &high2p5_3, &low5_3, &high5_3, &low10_3, &high10_3);
+            print_stats("Pctile", true_sr, &pctile[..ndone]);
+            print_stats("BCa   ", true_sr, &bca[..ndone]);
+            print_stats("Pivot ", true_sr, &pivot[..ndone]);
+            print_stats("Boot t", true_sr, &boot_t[..ndone]);
         }
     }
 
     // Final summary
     println!("\n\nnsamps={}  nboot={}  ntries={}  prob={:.3}", nsamps, nboot, ntries, prob);
+    if method != BootstrapMethod::Iid {
+        match block_length {
+            Some(length) => println!("bootstrap={}  block_length={:.1}", method, length),
+            None => println!("bootstrap={}  block_length=automatic", method),
+        }
+    }
 }
 
 fn param_pf(x: &[f64]) -> f64 {
@@ -259,56 +243,22 @@ fn normal(rng: &mut StdRng) -> f64 {
     }
 }
 
-fn print_stats(
-    label: &str,
-    ndone: usize,
-    true_val: f64,
-    low2p5: &[f64],
-    high2p5: &[f64],
-    low5: &[f64],
-    high5: &[f64],
-    low10: &[f64],
-    high10: &[f64],
-) {
-
-
-    // Check coverage
-    // C++ logic:
-    // if (low2p5_1[i] > true_pf) ++low2p5 ;
-    // if (high2p5_1[i] < true_pf) ++high2p5 ;
-    // It counts how many times the interval does NOT cover the true value?
-    // "Output of lower 2.5% bound"
-    // If low > true, true is below the interval.
-    // If high < true, true is above the interval.
-    // So it counts failures (misses).
-    // And then prints 100 * count / ndone.
-    // Wait, usually coverage is what we want (95%).
-    // If it prints "2.5: (2.30 2.40)", it means 2.3% below and 2.4% above?
-    // Yes, "Pctile 2.5: (%4.2lf %4.2lf)"
-    // It prints the percentage of times the true value was outside the interval on the low side and high side.
-    // Ideally should be 2.5% each for a 95% CI.
-
-    let mut l2p5 = 0; let mut h2p5 = 0;
-    let mut l5 = 0; let mut h5 = 0;
-    let mut l10 = 0; let mut h10 = 0;
-
-    for i in 0..ndone {
-        if low2p5[i] > true_val { l2p5 += 1; }
-        if high2p5[i] < true_val { h2p5 += 1; }
-        if low5[i] > true_val { l5 += 1; }
-        if high5[i] < true_val { h5 += 1; }
-        if low10[i] > true_val { l10 += 1; }
-        if high10[i] < true_val { h10 += 1; }
-    }
+fn print_stats(label: &str, true_val: f64, limits: &[ConfLimits]) {
+    // Percent of tries whose interval misses the true value on the low side
+    // (lower limit above it) and on the high side, ideally the tail fraction
+    let ndone = limits.len();
+    let percent_missed = |missed: fn(&ConfLimits, f64) -> bool| {
+        100.0 * limits.iter().filter(|l| missed(l, true_val)).count() as f64 / ndone as f64
+    };
 
     println!(
         "{} 2.5: ({:4.2} {:4.2})  5: ({:4.2} {:4.2})  10: ({:5.2} {:5.2})",
         label,
-        100.0 * l2p5 as f64 / ndone as f64,
-        100.0 * h2p5 as f64 / ndone as f64,
-        100.0 * l5 as f64 / ndone as f64,
-        100.0 * h5 as f64 / ndone as f64,
-        100.0 * l10 as f64 / ndone as f64,
-        100.0 * h10 as f64 / ndone as f64
+        percent_missed(|l, t| l.low2p5 > t),
+        percent_missed(|l, t| l.high2p5 < t),
+        percent_missed(|l, t| l.low5 > t),
+        percent_missed(|l, t| l.high5 < t),
+        percent_missed(|l, t| l.low10 > t),
+        percent_missed(|l, t| l.high10 < t)
     );
 }
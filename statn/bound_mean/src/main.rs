@@ -1,13 +1,12 @@
-mod boot_conf;
 mod qsort;
 mod stats;
 mod unifrand;
 
 use clap::Parser;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use statn::prelude::{read_log_prices, BarsFormat, InputKind, WalkForward};
 use std::path::PathBuf;
-use anyhow::{Context, Result};
+use anyhow::Result;
+use crate::stats::{boot_conf, BootstrapMethod, Resampler, RollingStats};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -31,6 +30,29 @@ struct Args {
     /// Name of market file (YYYYMMDD Price)
     #[arg(index = 5)]
     filename: PathBuf,
+
+    /// Bars skipped between each training set and its test set
+    #[arg(index = 6, default_value_t = 0)]
+    embargo: usize,
+
+    /// Market file format, "text" or "parquet" (default: from the extension)
+    #[arg(long)]
+    format: Option<BarsFormat>,
+
+    /// What the file's numbers are, "price", "log-price" or "pct-return"
+    /// (default: detected, an error when unclear)
+    #[arg(long)]
+    input_kind: Option<InputKind>,
+
+    /// How the bootstraps resample the returns: "iid", or "stationary" or
+    /// "block" (moving-block) to keep their autocorrelation
+    #[arg(long, default_value_t = BootstrapMethod::Iid)]
+    bootstrap_method: BootstrapMethod,
+
+    /// Block length of the block methods in returns, the mean length for
+    /// "stationary" (default: chosen from each series of returns)
+    #[arg(long)]
+    block_length: Option<f64>,
 }
 
 fn main() -> Result<()> {
@@ -41,109 +63,109 @@ fn main() -> Result<()> {
     }
 
     println!("Reading market file {:?}...", args.filename);
-    let prices = read_market_file(&args.filename)?;
-    println!("Market price history read. {} records.", prices.len());
+    let (prices, kind) = read_market_file(&args.filename, args.format, args.input_kind)?;
+    println!("Market price history read. {} records of {}.", prices.len(), kind);
 
-    if args.n_train + args.n_test > prices.len() {
-        anyhow::bail!("n_train + n_test must not exceed n_prices");
-    }
+    let walkforward = WalkForward {
+        embargo: args.embargo,
+        ..WalkForward::new(args.n_train, args.n_test)
+    };
+    walkforward.validate(prices.len()).map_err(anyhow::Error::msg)?;
 
     // Initialize for walkforward
     let mut returns_open = Vec::with_capacity(prices.len());
     let mut returns_complete = Vec::with_capacity(prices.len());
     let mut returns_grouped = Vec::with_capacity(prices.len());
 
-    let mut train_start = 0;
     let mut nret_open = 0;
     let mut nret_complete = 0;
     let mut nret_grouped = 0;
 
     // Do walkforward
-    loop {
-        // Train
-        let (lookback, thresh, last_pos, crit) = opt_params(
-            args.n_train,
-            &prices[train_start..],
-            args.max_lookback,
-        );
-
-        println!(
-            " IS at {}  Lookback={}  Thresh={:.3}  Crit={:.3}",
-            train_start, lookback, thresh, crit
-        );
-
-        let mut n = args.n_test;
-        if n > prices.len() - train_start - args.n_train {
-            n = prices.len() - train_start - args.n_train;
-        }
-
-        // Test with each of the three return types
-        let n_returns = comp_return(
-            0,
-            &prices,
-            train_start + args.n_train,
-            n,
-            lookback,
-            thresh,
-            last_pos,
-            &mut returns_grouped,
-        );
-        nret_grouped += n_returns;
-
-        println!(
-            "OOS 0 testing {} from {} had {} returns, total={}",
-            n,
-            train_start + args.n_train,
-            n_returns,
-            nret_grouped
-        );
-
-        let n_returns = comp_return(
-            1,
-            &prices,
-            train_start + args.n_train,
-            n,
-            lookback,
-            thresh,
-            last_pos,
-            &mut returns_open,
-        );
-        nret_open += n_returns;
-
-        println!(
-            "OOS 1 testing {} from {} had {} returns, total={}",
-            n,
-            train_start + args.n_train,
-            n_returns,
-            nret_open
-        );
-
-        let n_returns = comp_return(
-            2,
-            &prices,
-            train_start + args.n_train,
-            n,
-            lookback,
-            thresh,
-            last_pos,
-            &mut returns_complete,
-        );
-        nret_complete += n_returns;
-
-        println!(
-            "OOS 2 testing {} from {} had {} returns, total={}",
-            n,
-            train_start + args.n_train,
-            n_returns,
-            nret_complete
-        );
-
-        // Advance fold window; quit if done
-        train_start += n;
-        if train_start + args.n_train >= prices.len() {
-            break;
-        }
-    }
+    walkforward.run(
+        prices.len(),
+        |fold| {
+            let (lookback, thresh, last_pos, crit) = opt_params(
+                args.n_train,
+                &prices[fold.train_start..],
+                args.max_lookback,
+            );
+
+            println!(
+                " IS at {}  Lookback={}  Thresh={:.3}  Crit={:.3}",
+                fold.train_start, lookback, thresh, crit
+            );
+            (lookback, thresh, last_pos)
+        },
+        |fold, &(lookback, thresh, last_pos)| {
+            // Across an embargo the final training position no longer
+            // applies, so the test starts flat.
+            let last_pos = if args.embargo > 0 { 0 } else { last_pos };
+            let test_start = fold.test_start;
+            let n = fold.n_test();
+
+            // Test with each of the three return types
+            let n_returns = comp_return(
+                0,
+                &prices,
+                test_start,
+                n,
+                lookback,
+                thresh,
+                last_pos,
+                &mut returns_grouped,
+            );
+            nret_grouped += n_returns;
+
+            println!(
+                "OOS 0 testing {} from {} had {} returns, total={}",
+                n,
+                test_start,
+                n_returns,
+                nret_grouped
+            );
+
+            let n_returns = comp_return(
+                1,
+                &prices,
+                test_start,
+                n,
+                lookback,
+                thresh,
+                last_pos,
+                &mut returns_open,
+            );
+            nret_open += n_returns;
+
+            println!(
+                "OOS 1 testing {} from {} had {} returns, total={}",
+                n,
+                test_start,
+                n_returns,
+                nret_open
+            );
+
+            let n_returns = comp_return(
+                2,
+                &prices,
+                test_start,
+                n,
+                lookback,
+                thresh,
+                last_pos,
+                &mut returns_complete,
+            );
+            nret_complete += n_returns;
+
+            println!(
+                "OOS 2 testing {} from {} had {} returns, total={}",
+                n,
+                test_start,
+                n_returns,
+                nret_complete
+            );
+        },
+    );
 
     // Crunch the grouped returns
     let crunch = 10;
@@ -165,11 +187,12 @@ fn main() -> Result<()> {
 
     // Compute and print OOS performance
     println!(
-        "\n\nnprices={}  max_lookback={}  n_train={}  n_test={}",
+        "\n\nnprices={}  max_lookback={}  n_train={}  n_test={}  embargo={}",
         prices.len(),
         args.max_lookback,
         args.n_train,
-        args.n_test
+        args.n_test,
+        args.embargo
     );
 
     analyze_returns("Open posn", &returns_open, 25200.0);
@@ -181,60 +204,25 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    // Do bootstraps
-    println!("\n\nDoing bootstrap 1 of 6...");
-    let (b1_lower_open, _, _, _, _, high_open) = boot_conf::boot_conf_pctile(
-        nret_open,
-        &returns_open,
-        find_mean,
-        args.n_boot,
-    );
-    let mean_open = find_mean(nret_open, &returns_open);
-    let b2_lower_open = 2.0 * mean_open - high_open;
-
-    println!("\nDoing bootstrap 2 of 6...");
-    let (_, _, _, _, b3_lower_open, _) = boot_conf::boot_conf_bca(
-        nret_open,
-        &returns_open,
-        find_mean,
-        args.n_boot,
-    );
-
-    println!("\nDoing bootstrap 3 of 6...");
-    let (_, _, _, _, b1_lower_complete, high_complete) = boot_conf::boot_conf_pctile(
-        nret_complete,
-        &returns_complete,
-        find_mean,
-        args.n_boot,
-    );
-    let mean_complete = find_mean(nret_complete, &returns_complete);
-    let b2_lower_complete = 2.0 * mean_complete - high_complete;
-
-    println!("\nDoing bootstrap 4 of 6...");
-    let (_, _, _, _, b3_lower_complete, _) = boot_conf::boot_conf_bca(
-        nret_complete,
-        &returns_complete,
-        find_mean,
-        args.n_boot,
-    );
+    let resample_open = Resampler::new(args.bootstrap_method, args.block_length, &returns_open);
+    let resample_complete = Resampler::new(args.bootstrap_method, args.block_length, &returns_complete);
+    let resample_grouped = Resampler::new(args.bootstrap_method, args.block_length, &returns_grouped);
+    if args.bootstrap_method != BootstrapMethod::Iid {
+        println!(
+            "\nBootstrap resampling: Open posn {}  Complete {}  Grouped {}",
+            resample_open, resample_complete, resample_grouped
+        );
+    }
 
-    println!("\nDoing bootstrap 5 of 6...");
-    let (_, _, _, _, b1_lower_grouped, high_grouped) = boot_conf::boot_conf_pctile(
-        nret_grouped,
-        &returns_grouped,
-        find_mean,
-        args.n_boot,
-    );
-    let mean_grouped = find_mean(nret_grouped, &returns_grouped);
-    let b2_lower_grouped = 2.0 * mean_grouped - high_grouped;
-
-    println!("\nDoing bootstrap 6 of 6...");
-    let (_, _, _, _, b3_lower_grouped, _) = boot_conf::boot_conf_bca(
-        nret_grouped,
-        &returns_grouped,
-        find_mean,
-        args.n_boot,
-    );
+    // Do bootstraps
+    let mean = |x: &[f64]| find_mean(x.len(), x);
+    let mut rng = rand::thread_rng();
+    println!("\n\nDoing bootstrap 1 of 3...");
+    let boot_open = boot_conf(&returns_open, mean, args.n_boot, &resample_open, &mut rng);
+    println!("\nDoing bootstrap 2 of 3...");
+    let boot_complete = boot_conf(&returns_complete, mean, args.n_boot, &resample_complete, &mut rng);
+    println!("\nDoing bootstrap 3 of 3...");
+    let boot_grouped = boot_conf(&returns_grouped, mean, args.n_boot, &resample_grouped, &mut rng);
 
     // We need t_lower values too, which were computed in analyze_returns but not returned.
     // I should probably refactor analyze_returns to return t_lower.
@@ -251,24 +239,21 @@ fn main() -> Result<()> {
         1000.0 * t_lower_complete,
         25200.0 * t_lower_grouped
     );
-    println!(
-        "Percentile   {:7.4}    {:7.4}    {:7.4}",
-        25200.0 * b1_lower_open,
-        1000.0 * b1_lower_complete,
-        25200.0 * b1_lower_grouped
-    );
-    println!(
-        "Pivot        {:7.4}    {:7.4}    {:7.4}",
-        25200.0 * b2_lower_open,
-        1000.0 * b2_lower_complete,
-        25200.0 * b2_lower_grouped
-    );
-    println!(
-        "BCa          {:7.4}    {:7.4}    {:7.4}",
-        25200.0 * b3_lower_open,
-        1000.0 * b3_lower_complete,
-        25200.0 * b3_lower_grouped
-    );
+    let rows = [
+        ("Bootstrap t ", [boot_open.t, boot_complete.t, boot_grouped.t]),
+        ("Percentile  ", [boot_open.percentile, boot_complete.percentile, boot_grouped.percentile]),
+        ("Pivot       ", [boot_open.pivot, boot_complete.pivot, boot_grouped.pivot]),
+        ("BCa         ", [boot_open.bca, boot_complete.bca, boot_grouped.bca]),
+    ];
+    for (label, [open, complete, grouped]) in rows {
+        println!(
+            "{} {:7.4}    {:7.4}    {:7.4}",
+            label,
+            25200.0 * open.low10,
+            1000.0 * complete.low10,
+            25200.0 * grouped.low10
+        );
+    }
 
     Ok(())
 }
@@ -288,31 +273,17 @@ fn opt_params(
             let mut total_return = 0.0;
             let mut n_trades = 0;
             let mut position = 0;
-            let mut ma_sum = 0.0;
-
-            // Initialize MA sum
-            // The C++ loop: for (i=max_lookback-1 ; i<nprices-1 ; i++)
-            // i is the decision bar index.
-            
-            // We need to be careful with indices.
-            // prices slice starts at train_start.
-            // nprices is n_train.
-            
-            // First valid decision point is at max_lookback - 1.
-            // We need history from i-ilook to i.
-            
-            for i in max_lookback - 1..nprices - 1 {
-                if i == max_lookback - 1 {
-                    ma_sum = 0.0;
-                    for j in (i + 1 - ilook)..=i { // j from i-ilook+1 to i (inclusive) has length ilook
-                         // C++: for (j=i ; j>i-ilook ; j--) -> j goes i, i-1, ..., i-ilook+1. Correct.
-                         ma_sum += prices[j];
-                    }
-                } else {
-                    ma_sum += prices[i] - prices[i - ilook];
-                }
 
-                let ma_mean = ma_sum / ilook as f64;
+            // Moving average of the ilook prices ending at decision bar i,
+            // the first decision bar being max_lookback - 1 for every trial
+            let mut ma = RollingStats::new(ilook);
+            for &price in &prices[max_lookback - ilook..max_lookback - 1] {
+                ma.push(price);
+            }
+
+            for i in max_lookback - 1..nprices - 1 {
+                ma.push(prices[i]);
+                let ma_mean = ma.mean();
                 let trial_thresh = 1.0 + 0.01 * ithresh as f64;
 
                 if prices[i] > trial_thresh * ma_mean {
@@ -366,22 +337,17 @@ fn comp_return(
     let mut prior_position = 0;
     let trial_thresh = 1.0 + thresh;
     let mut open_price = 0.0;
-    let mut ma_sum = 0.0;
 
     // The loop in C++: for (i=istart-1 ; i<istart-1+ntest ; i++)
     // i is the decision bar.
-    
-    for i in istart - 1..istart - 1 + ntest {
-        if i == istart - 1 {
-            ma_sum = 0.0;
-            for j in (i - lookback + 1)..=i {
-                ma_sum += prices[j];
-            }
-        } else {
-            ma_sum += prices[i] - prices[i - lookback];
-        }
+    let mut ma = RollingStats::new(lookback);
+    for &price in &prices[istart - lookback..istart - 1] {
+        ma.push(price);
+    }
 
-        let ma_mean = ma_sum / lookback as f64;
+    for i in istart - 1..istart - 1 + ntest {
+        ma.push(prices[i]);
+        let ma_mean = ma.mean();
 
         if prices[i] > trial_thresh * ma_mean {
             position = 1;
@@ -474,33 +440,10 @@ fn calc_t_lower(returns: &[f64]) -> f64 {
     mean - stddev_val / (n as f64).sqrt() * stats::inverse_t_cdf((n - 1) as i32, 0.9)
 }
 
-fn read_market_file(filename: &PathBuf) -> Result<Vec<f64>> {
-    let file = File::open(filename).context("Cannot open market history file")?;
-    let reader = BufReader::new(file);
-    let mut prices = Vec::new();
-
-    for (line_num, line) in reader.lines().enumerate() {
-        let line = line?;
-        if line.trim().len() < 2 {
-            continue;
-        }
-
-        if line.len() < 10 {
-             continue;
-        }
-
-        let price_str = &line[9..];
-        let price_part = price_str.split_whitespace().next().context(format!("Invalid price format at line {}", line_num + 1))?;
-        
-        let price: f64 = price_part.parse().context(format!("Invalid price value at line {}", line_num + 1))?;
-        
-        if price > 0.0 {
-            prices.push(price.ln());
-        } else {
-             prices.push(price);
-        }
-    }
-
-    Ok(prices)
+fn read_market_file(
+    filename: &PathBuf,
+    format: Option<BarsFormat>,
+    kind: Option<InputKind>,
+) -> Result<(Vec<f64>, InputKind)> {
+    read_log_prices(filename, format, kind).map_err(anyhow::Error::msg)
 }
-
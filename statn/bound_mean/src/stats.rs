@@ -1 +1 @@
-pub use stats::{normal_cdf, inverse_normal_cdf, t_cdf, inverse_t_cdf};
+pub use stats::{t_cdf, inverse_t_cdf, boot_conf, BootstrapMethod, Resampler, RollingStats};
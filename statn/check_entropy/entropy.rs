@@ -1,9 +1,10 @@
 
 
-use matlib::{qsortd, find_slope, range_expansion, jump};
+use matlib::qsortd;
 use stats::entropy;
 use statn::core::data::chart::BarData;
-use finance_tools::atr;
+use indicators::trend::{compute_jump, compute_trend};
+use indicators::volatility::{compute_expansion, compute_volatility};
 
 /*
 Compute indicator statistics
@@ -37,29 +38,17 @@ pub fn compute_indicator_stats(
     );
 }
 
+/*
+The indicators in the version asked for, computed by the indicators crate
+*/
+
 pub fn calculate_trend(
     bars: &BarData,
     lookback: usize,
     full_lookback: usize,
     version: i32,
 ) -> Vec<f64> {
-    let nprices = bars.len();
-    let nind = nprices - full_lookback + 1;
-    let mut trend = Vec::with_capacity(nind);
-
-    for i in 0..nind {
-        let k = full_lookback - 1 + i;
-        let val = if version == 0 {
-            find_slope(lookback, &bars.close, k)
-        } else if version == 1 {
-            find_slope(lookback, &bars.close, k)
-                - find_slope(lookback, &bars.close, k.saturating_sub(lookback))
-        } else {
-            find_slope(lookback, &bars.close, k) - find_slope(full_lookback, &bars.close, k)
-        };
-        trend.push(val);
-    }
-    trend
+    compute_trend(&bars.close, lookback, full_lookback, version as usize)
 }
 
 pub fn calculate_volatility(
@@ -68,30 +57,7 @@ pub fn calculate_volatility(
     full_lookback: usize,
     version: i32,
 ) -> Vec<f64> {
-    let nprices = bars.len();
-    let nind = nprices - full_lookback + 1;
-    let mut volatility = Vec::with_capacity(nind);
-
-    for i in 0..nind {
-        let k = full_lookback - 1 + i;
-        let val = if version == 0 {
-            atr(lookback, &bars.high, &bars.low, &bars.close, k)
-        } else if version == 1 {
-            atr(lookback, &bars.high, &bars.low, &bars.close, k)
-                - atr(
-                    lookback,
-                    &bars.high,
-                    &bars.low,
-                    &bars.close,
-                    k.saturating_sub(lookback),
-                )
-        } else {
-            atr(lookback, &bars.high, &bars.low, &bars.close, k)
-                - atr(full_lookback, &bars.high, &bars.low, &bars.close, k)
-        };
-        volatility.push(val);
-    }
-    volatility
+    compute_volatility(&bars.high, &bars.low, &bars.close, lookback, full_lookback, version as usize)
 }
 
 pub fn calculate_expansion(
@@ -100,24 +66,7 @@ pub fn calculate_expansion(
     full_lookback: usize,
     version: i32,
 ) -> Vec<f64> {
-    let nprices = bars.len();
-    let nind = nprices - full_lookback + 1;
-    let mut expansion = Vec::with_capacity(nind);
-
-    for i in 0..nind {
-        let k = full_lookback - 1 + i;
-        let val = if version == 0 {
-            range_expansion(lookback, k, &bars.close)
-        } else if version == 1 {
-            range_expansion(lookback, k, &bars.close)
-                - range_expansion(lookback, k.saturating_sub(lookback), &bars.close)
-        } else {
-            range_expansion(lookback, k, &bars.close)
-                - range_expansion(full_lookback, k, &bars.close)
-        };
-        expansion.push(val);
-    }
-    expansion
+    compute_expansion(&bars.close, lookback, full_lookback, version as usize)
 }
 
 pub fn calculate_jump(
@@ -126,20 +75,5 @@ pub fn calculate_jump(
     full_lookback: usize,
     version: i32,
 ) -> Vec<f64> {
-    let nprices = bars.len();
-    let nind = nprices - full_lookback + 1;
-    let mut raw_jump = Vec::with_capacity(nind);
-
-    for i in 0..nind {
-        let k = full_lookback - 1 + i;
-        let val = if version == 0 {
-            jump(lookback, k, &bars.close)
-        } else if version == 1 {
-            jump(lookback, k, &bars.close) - jump(lookback, k.saturating_sub(lookback), &bars.close)
-        } else {
-            jump(lookback, k, &bars.close) - jump(full_lookback, k, &bars.close)
-        };
-        raw_jump.push(val);
-    }
-    raw_jump
+    compute_jump(&bars.close, lookback, full_lookback, version as usize)
 }
@@ -1,4 +1,7 @@
-use statn::estimators::sensitivity::sensitivity;
+use std::io;
+use std::path::Path;
+
+use statn::estimators::sensitivity::{sensitivity_sweep, SensitivityConfig};
 
 /// Example criterion function: Sphere function
 /// Optimal at params = [0, 0, 0], maximum value = 0
@@ -36,81 +39,102 @@ fn quadratic_function(params: &[f64], _mintrades: i32) -> f64 {
     result
 }
 
+/// Sweep the parameters of one example and write its curves as a text
+/// histogram (`<stem>.LOG`) and as data (`<stem>.csv`)
+fn run_example<F>(criter: F, config: &SensitivityConfig, stem: &str) -> io::Result<()>
+where
+    F: FnMut(&[f64], i32) -> f64,
+{
+    let result = sensitivity_sweep(criter, config);
+    std::fs::write(Path::new(stem).with_extension("LOG"), result.histogram(config.nres))?;
+    result.write_csv(Path::new(stem).with_extension("csv"))?;
+
+    for sweep in &result.sweeps {
+        let best = sweep
+            .values
+            .iter()
+            .zip(&sweep.criteria)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(&value, _)| value)
+            .unwrap_or(f64::NAN);
+        println!(
+            "  Parameter {}: optimum {:.4}, best swept value {:.4}",
+            sweep.param + 1,
+            sweep.optimum,
+            best
+        );
+    }
+    Ok(())
+}
+
 fn main() {
     println!("Parameter Sensitivity Analysis Tool\n");
     println!("This tool demonstrates the sensitivity analysis function");
     println!("from statn::estimators::sensitivity\n");
 
-    // Example 1: Sphere function with 3 real parameters
+    // Example 1: Sphere Function (3 real parameters)
     println!("=== Example 1: Sphere Function (3 real parameters) ===");
-    let nvars = 3;
-    let nints = 0;
-    let best = vec![0.0, 0.0, 0.0]; // Optimal point
+    let best = vec![0.0, 0.0, 0.0];
     let low_bounds = vec![-5.0, -5.0, -5.0];
     let high_bounds = vec![5.0, 5.0, 5.0];
 
-    let config = statn::estimators::sensitivity::SensitivityConfig {
-        nvars,
-        nints,
-        20,  // npoints
-        60,  // nres (histogram width)
-        10,  // mintrades (not used in this example)
-        &best,
-        &low_bounds,
-        &high_bounds,
-        std::path::Path::new("SENS.LOG"),
-    ) {
-        Ok(_) => println!("✓ Sensitivity analysis completed. Results saved to SENS.LOG\n"),
+    let config = SensitivityConfig {
+        nvars: 3,
+        nints: 0,
+        npoints: 20,
+        nres: 60,
+        mintrades: 10, // not used in this example
+        best: &best,
+        low_bounds: &low_bounds,
+        high_bounds: &high_bounds,
+    };
+    match run_example(sphere_function, &config, "SENS_SPHERE") {
+        Ok(_) => println!("✓ Sensitivity analysis completed. Results saved to SENS_SPHERE.LOG and SENS_SPHERE.csv\n"),
         Err(e) => println!("✗ Error: {}\n", e),
     }
 
-    // Example 2: Quadratic function with mixed integer/real parameters
+    // Example 2: Quadratic Function (1 integer + 2 real parameters)
     println!("=== Example 2: Quadratic Function (1 integer + 2 real parameters) ===");
-    let nvars = 3;
-    let nints = 1; // First parameter is integer
-    let best = vec![2.0, 4.0, 6.0]; // Optimal point
+    let best = vec![2.0, 4.0, 6.0];
     let low_bounds = vec![0.0, 0.0, 0.0];
     let high_bounds = vec![10.0, 10.0, 10.0];
 
-    let config = statn::estimators::sensitivity::SensitivityConfig {
-        nvars,
-        nints,
-        15,  // npoints
-        50,  // nres
-        10,  // mintrades
-        &best,
-        &low_bounds,
-        &high_bounds,
-        std::path::Path::new("SENS.LOG"),
-    ) {
-        Ok(_) => println!("✓ Sensitivity analysis completed. Results saved to SENS.LOG\n"),
+    let config = SensitivityConfig {
+        nvars: 3,
+        nints: 1,
+        npoints: 15,
+        nres: 50,
+        mintrades: 10, // not used in this example
+        best: &best,
+        low_bounds: &low_bounds,
+        high_bounds: &high_bounds,
+    };
+    match run_example(quadratic_function, &config, "SENS_QUADRATIC") {
+        Ok(_) => println!("✓ Sensitivity analysis completed. Results saved to SENS_QUADRATIC.LOG and SENS_QUADRATIC.csv\n"),
         Err(e) => println!("✗ Error: {}\n", e),
     }
 
-    // Example 3: Rosenbrock function
+    // Example 3: Rosenbrock Function (2 real parameters)
     println!("=== Example 3: Rosenbrock Function (2 real parameters) ===");
-    let nvars = 2;
-    let nints = 0;
-    let best = vec![1.0, 1.0]; // Optimal point
+    let best = vec![1.0, 1.0];
     let low_bounds = vec![-2.0, -2.0];
     let high_bounds = vec![3.0, 3.0];
 
-    let config = statn::estimators::sensitivity::SensitivityConfig {
-        nvars,
-        nints,
-        25,  // npoints
-        70,  // nres
-        10,  // mintrades
-        &best,
-        &low_bounds,
-        &high_bounds,
-        std::path::Path::new("SENS.LOG"),
-    ) {
-        Ok(_) => println!("✓ Sensitivity analysis completed. Results saved to SENS.LOG\n"),
+    let config = SensitivityConfig {
+        nvars: 2,
+        nints: 0,
+        npoints: 25,
+        nres: 70,
+        mintrades: 10, // not used in this example
+        best: &best,
+        low_bounds: &low_bounds,
+        high_bounds: &high_bounds,
+    };
+    match run_example(rosenbrock_function, &config, "SENS_ROSENBROCK") {
+        Ok(_) => println!("✓ Sensitivity analysis completed. Results saved to SENS_ROSENBROCK.LOG and SENS_ROSENBROCK.csv\n"),
         Err(e) => println!("✗ Error: {}\n", e),
     }
 
-
     println!("All examples completed!");
-    println!("Check SENS.LOG for detailed sensitivity curves.");
-}
\ No newline at end of file
+    println!("Check the SENS_*.LOG files for detailed sensitivity curves.");
+}
@@ -1,18 +1,29 @@
 use anyhow::Result;
 
 use std::io::Write;
-use crate::criteria::{criterion, CriterionType};
+use std::path::Path;
+use crate::criteria::{default_criteria, SelectionCriterion};
+use crate::history::{Fold, SelectionHistory};
+use crate::liquidity::{best_market, best_of, liquidity_report, market_scores, Liquidity};
 use crate::market_data::{align_dates, convert_to_log_prices, load_markets};
 use crate::permutation::{do_permute, prepare_permute};
 use crate::random::Rng;
 
-const N_CRITERIA: usize = 3;
+pub fn run_chooser(file_list: &str, is_n: usize, oos1_n: usize, nreps: usize) -> Result<()> {
+    run_chooser_with(file_list, is_n, oos1_n, nreps, &default_criteria(), &Liquidity::default(), None)
+}
 
-pub fn run_chooser(
+/// `run_chooser` choosing among `criteria`, with the markets ranked for
+/// liquidity as well, and the selection of every fold of the unpermuted run
+/// written to `history` (see `SelectionHistory::write`)
+pub fn run_chooser_with(
     file_list: &str,
     is_n: usize,
     oos1_n: usize,
     mut nreps: usize,
+    criteria: &[Box<dyn SelectionCriterion>],
+    liquidity: &Liquidity,
+    history: Option<&Path>,
 ) -> Result<()> {
     if nreps < 1 {
         nreps = 1;
@@ -21,6 +32,10 @@ pub fn run_chooser(
     if is_n < 2 || oos1_n < 1 {
         anyhow::bail!("Invalid parameters: IS_n must be >= 2 and OOS1_n must be >= 1");
     }
+    if criteria.is_empty() {
+        anyhow::bail!("No selection criteria");
+    }
+    let n_criteria = criteria.len();
 
     // Open report buffer
     let mut buffer = String::new();
@@ -45,6 +60,7 @@ pub fn run_chooser(
         markets[0].dates[0],
         markets[0].dates[n_cases - 1]
     )?;
+    buffer.push_str(&liquidity_report(&markets, liquidity)?);
 
     // Convert to log prices
     convert_to_log_prices(&mut markets);
@@ -65,7 +81,7 @@ pub fn run_chooser(
     writeln!(buffer, "Mean = {:9.4}", sum / n_markets as f64)?;
 
     // Allocate memory for OOS1 and OOS2
-    let mut oos1 = vec![0.0; N_CRITERIA * n_cases];
+    let mut oos1 = vec![0.0; n_criteria * n_cases];
     let mut oos2 = vec![0.0; n_cases];
 
     // Allocate permutation work arrays if needed
@@ -75,6 +91,14 @@ pub fn run_chooser(
         None
     };
 
+    // Selection of every fold, if asked for
+    let mut selections = history.map(|_| {
+        SelectionHistory::new(
+            criteria.iter().map(|c| c.name()).collect(),
+            markets.iter().map(|m| m.name.clone()).collect(),
+        )
+    });
+
     // Initialize RNG
     let mut rng = Rng::new();
 
@@ -89,9 +113,9 @@ pub fn run_chooser(
     // Monte-Carlo permutation loop
     println!("\n\nComputing");
 
-    let mut crit_count = [0usize; N_CRITERIA];
-    let mut crit_perf = [0.0; N_CRITERIA];
-    let mut crit_pval = [1usize; N_CRITERIA];
+    let mut crit_count = vec![0usize; n_criteria];
+    let mut crit_perf = vec![0.0; n_criteria];
+    let mut crit_pval = vec![1usize; n_criteria];
     let mut final_perf = 0.0;
     let mut final_pval = 1usize;
 
@@ -131,18 +155,8 @@ pub fn run_chooser(
         // Main loop traversing market history
         loop {
             // Evaluate all performance criteria for all markets
-            for icrit in 0..N_CRITERIA {
-                let crit_type = CriterionType::from_index(icrit).unwrap();
-                let mut best_crit = -1.0e60;
-                let mut ibest = 0;
-
-                for (imarket, market) in markets.iter().enumerate() {
-                    let crit = criterion(crit_type, &market.close[is_start..is_start + is_n]);
-                    if crit > best_crit {
-                        best_crit = crit;
-                        ibest = imarket;
-                    }
-                }
+            for icrit in 0..n_criteria {
+                let ibest = best_market(&markets, criteria[icrit].as_ref(), is_start, is_start + is_n, liquidity);
 
                 oos1[icrit * n_cases + oos1_end] =
                     markets[ibest].close[oos1_end] - markets[ibest].close[oos1_end - 1];
@@ -163,12 +177,14 @@ pub fn run_chooser(
             // Find best criterion in OOS1
             let mut best_crit = -1.0e60;
             let mut ibestcrit = 0;
+            let mut oos1_perf = vec![0.0; n_criteria];
 
-            for icrit in 0..N_CRITERIA {
+            for icrit in 0..n_criteria {
                 let mut crit = 0.0;
                 for i in oos1_start..oos1_end {
                     crit += oos1[icrit * n_cases + i];
                 }
+                oos1_perf[icrit] = crit;
                 if crit > best_crit {
                     best_crit = crit;
                     ibestcrit = icrit;
@@ -180,26 +196,29 @@ pub fn run_chooser(
             }
 
             // Use best criterion to select market
-            let crit_type = CriterionType::from_index(ibestcrit).unwrap();
-            best_crit = -1.0e60;
-            let mut ibest = 0;
-
-            for (imarket, market) in markets.iter().enumerate() {
-                let crit = criterion(crit_type, &market.close[oos2_end - is_n..oos2_end]);
-                if crit > best_crit {
-                    best_crit = crit;
-                    ibest = imarket;
-                }
-            }
+                        let scores = market_scores(&markets, criteria[ibestcrit].as_ref(), oos2_end - is_n, oos2_end, liquidity);
+            let ibest = best_of(&scores);
 
             // Record OOS2 return
             oos2[oos2_end] = markets[ibest].close[oos2_end] - markets[ibest].close[oos2_end - 1];
+            if irep == 0 {
+                if let Some(ref mut history) = selections {
+                    history.folds.push(Fold {
+                        date: markets[0].dates[oos2_end],
+                        oos1_perf: oos1_perf.clone(),
+                        criterion: ibestcrit,
+                        scores,
+                        market: ibest,
+                        oos_return: oos2[oos2_end],
+                    });
+                }
+            }
             oos1_start += 1;
             oos2_end += 1;
         }
 
         // Compute criterion performance
-        for i in 0..N_CRITERIA {
+        for i in 0..n_criteria {
             let mut sum = 0.0;
             for j in oos2_start..oos2_end {
                 sum += oos1[i * n_cases + j];
@@ -242,13 +261,12 @@ pub fn run_chooser(
 
     let total_count: usize = crit_count.iter().sum();
 
-    for i in 0..N_CRITERIA {
-        let crit_type = CriterionType::from_index(i).unwrap();
-        if nreps > 1 {
+    for i in 0..n_criteria {
+                if nreps > 1 {
             writeln!(
                 buffer,
                 "{:>15} {:9.4}  p={:.3}  Chosen {:.1} pct",
-                crit_type.name(),
+                criteria[i].name(),
                 crit_perf[i],
                 crit_pval[i] as f64 / nreps as f64,
                 100.0 * crit_count[i] as f64 / total_count as f64
@@ -257,7 +275,7 @@ pub fn run_chooser(
             writeln!(
                 buffer,
                 "{:>15} {:9.4}  Chosen {:.1} pct",
-                crit_type.name(),
+                criteria[i].name(),
                 crit_perf[i],
                 100.0 * crit_count[i] as f64 / total_count as f64
             )?;
@@ -279,6 +297,11 @@ pub fn run_chooser(
         )?;
     }
 
+    if let (Some(path), Some(selections)) = (history, &selections) {
+        selections.write(path)?;
+        println!("\n\nSelection history written to {}", path.display());
+    }
+
     println!("\n\nResults written to CHOOSER.LOG");
 
     statn::core::io::write::write_file("CHOOSER.LOG", buffer)
@@ -1,18 +1,50 @@
 use anyhow::Result;
 
 use std::io::Write;
-use crate::criteria::{criterion, CriterionType};
+use std::path::Path;
+use crate::criteria::{default_criteria, SelectionCriterion};
+use crate::history::{Fold, SelectionHistory};
+use crate::liquidity::{best_market, best_of, liquidity_report, market_scores, Liquidity};
 use crate::drawdown::{drawdown_quantiles, find_quantile};
 use crate::market_data::{align_dates, convert_to_log_prices, load_markets};
 use crate::random::Rng;
 use crate::sort::qsortd;
-
-const N_CRITERIA: usize = 3;
+use ::drawdown::normalize::{scale_drawdown, Horizon};
+
+/// Common horizon for the normalized drawdown table, so bounds can be compared
+/// with strategies evaluated over a different number of trades
+const NORMALIZED_HORIZON: Horizon = Horizon::Trades(100.0);
+
+/// Rescale a percent drawdown (as returned by `drawdown`) between trade counts.
+/// The scaling is done on the underlying log drawdown.
+fn scale_pct_drawdown(pct: f64, mean: f64, sigma: f64, from_trades: f64, to_trades: f64) -> f64 {
+    let log_dd = -(1.0 - pct / 100.0).max(1.0e-300).ln();
+    let scaled = scale_drawdown(log_dd, mean, sigma, from_trades, to_trades);
+    100.0 * (1.0 - (-scaled).exp())
+}
 
 pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()> {
+    run_chooser_dd_with(file_list, is_n, oos1_n, &default_criteria(), &Liquidity::default(), None)
+}
+
+/// `run_chooser_dd` choosing among `criteria`, with the markets ranked for
+/// liquidity as well, and the selection of every fold written to `history`
+/// (see `SelectionHistory::write`)
+pub fn run_chooser_dd_with(
+    file_list: &str,
+    is_n: usize,
+    oos1_n: usize,
+    criteria: &[Box<dyn SelectionCriterion>],
+    liquidity: &Liquidity,
+    history: Option<&Path>,
+) -> Result<()> {
     if is_n < 2 || oos1_n < 1 {
         anyhow::bail!("Invalid parameters: IS_n must be >= 2 and OOS1_n must be >= 1");
     }
+    if criteria.is_empty() {
+        anyhow::bail!("No selection criteria");
+    }
+    let n_criteria = criteria.len();
 
     // User-configurable parameters
     let bootstrap_reps = 2000;
@@ -44,6 +76,7 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
         markets[0].dates[0],
         markets[0].dates[n_cases - 1]
     )?;
+    buffer.push_str(&liquidity_report(&markets, liquidity)?);
 
     // Convert to log prices
     convert_to_log_prices(&mut markets);
@@ -64,7 +97,7 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
     writeln!(buffer, "Mean = {:9.4}", sum / n_markets as f64)?;
 
     // Allocate memory for OOS1 and OOS2
-    let mut oos1 = vec![0.0; N_CRITERIA * n_cases];
+    let mut oos1 = vec![0.0; n_criteria * n_cases];
     let mut oos2 = vec![0.0; n_cases];
 
     // Allocate drawdown work arrays
@@ -76,8 +109,16 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
     let mut q05 = vec![0.0; bootstrap_reps];
     let mut q10 = vec![0.0; bootstrap_reps];
 
+    // Selection of every fold, if asked for
+    let mut selections = history.map(|_| {
+        SelectionHistory::new(
+            criteria.iter().map(|c| c.name()).collect(),
+            markets.iter().map(|m| m.name.clone()).collect(),
+        )
+    });
+
     // Initialize
-    let mut crit_count = [0usize; N_CRITERIA];
+    let mut crit_count = vec![0usize; n_criteria];
 
     let mut is_start = 0;
     let mut oos1_start = is_n;
@@ -90,18 +131,8 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
 
     loop {
         // Evaluate all performance criteria for all markets
-        for icrit in 0..N_CRITERIA {
-            let crit_type = CriterionType::from_index(icrit).unwrap();
-            let mut best_crit = -1.0e60;
-            let mut ibest = 0;
-
-            for (imarket, market) in markets.iter().enumerate() {
-                let crit = criterion(crit_type, &market.close[is_start..is_start + is_n]);
-                if crit > best_crit {
-                    best_crit = crit;
-                    ibest = imarket;
-                }
-            }
+        for icrit in 0..n_criteria {
+            let ibest = best_market(&markets, criteria[icrit].as_ref(), is_start, is_start + is_n, liquidity);
 
             oos1[icrit * n_cases + oos1_end] =
                 markets[ibest].close[oos1_end] - markets[ibest].close[oos1_end - 1];
@@ -122,12 +153,14 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
         // Find best criterion in OOS1
         let mut best_crit = -1.0e60;
         let mut ibestcrit = 0;
+        let mut oos1_perf = vec![0.0; n_criteria];
 
-        for icrit in 0..N_CRITERIA {
+        for icrit in 0..n_criteria {
             let mut crit = 0.0;
             for i in oos1_start..oos1_end {
                 crit += oos1[icrit * n_cases + i];
             }
+            oos1_perf[icrit] = crit;
             if crit > best_crit {
                 best_crit = crit;
                 ibestcrit = icrit;
@@ -137,27 +170,28 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
         crit_count[ibestcrit] += 1;
 
         // Use best criterion to select market
-        let crit_type = CriterionType::from_index(ibestcrit).unwrap();
-        best_crit = -1.0e60;
-        let mut ibest = 0;
-
-        for (imarket, market) in markets.iter().enumerate() {
-            let crit = criterion(crit_type, &market.close[oos2_end - is_n..oos2_end]);
-            if crit > best_crit {
-                best_crit = crit;
-                ibest = imarket;
-            }
-        }
+                let scores = market_scores(&markets, criteria[ibestcrit].as_ref(), oos2_end - is_n, oos2_end, liquidity);
+        let ibest = best_of(&scores);
 
         // Record OOS2 return
         oos2[oos2_end] = markets[ibest].close[oos2_end] - markets[ibest].close[oos2_end - 1];
+        if let Some(ref mut history) = selections {
+            history.folds.push(Fold {
+                date: markets[0].dates[oos2_end],
+                oos1_perf: oos1_perf.clone(),
+                criterion: ibestcrit,
+                scores,
+                market: ibest,
+                oos_return: oos2[oos2_end],
+            });
+        }
         oos1_start += 1;
         oos2_end += 1;
     }
 
     // Compute criterion performance
-    let mut crit_perf = [0.0; N_CRITERIA];
-    for i in 0..N_CRITERIA {
+    let mut crit_perf = vec![0.0; n_criteria];
+    for i in 0..n_criteria {
         let mut sum = 0.0;
         for j in oos2_start..oos2_end {
             sum += oos1[i * n_cases + j];
@@ -180,12 +214,11 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
 
     let total_count: usize = crit_count.iter().sum();
 
-    for i in 0..N_CRITERIA {
-        let crit_type = CriterionType::from_index(i).unwrap();
-        writeln!(
+    for i in 0..n_criteria {
+                writeln!(
             buffer,
             "{:>15} {:9.4}  Chosen {:.1} pct",
-            crit_type.name(),
+            criteria[i].name(),
             crit_perf[i],
             100.0 * crit_count[i] as f64 / total_count as f64
         )?;
@@ -297,6 +330,45 @@ pub fn run_chooser_dd(file_list: &str, is_n: usize, oos1_n: usize) -> Result<()>
         find_quantile(bootstrap_reps, &q10, 0.95)
     )?;
 
+    // Same bounds rescaled to a common trade count, using the OOS2 per-trade
+    // log return statistics
+    let oos2_returns = &oos2[oos2_start..oos2_end];
+    let mean = oos2_returns.iter().sum::<f64>() / n as f64;
+    let sigma = (oos2_returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
+        / (n as f64 - 1.0).max(1.0))
+    .sqrt();
+    let to_trades = NORMALIZED_HORIZON.n_trades(0.0);
+    let scale = |pct: f64| scale_pct_drawdown(pct, mean, sigma, n_trades as f64, to_trades);
+
+    writeln!(
+        buffer,
+        "\n\nDrawdown approximate bounds {} (measured over {} trades).",
+        NORMALIZED_HORIZON.label(),
+        n_trades
+    )?;
+    writeln!(
+        buffer,
+        "          0.5       0.6       0.7       0.8       0.9       0.95"
+    )?;
+    for (label, q) in [("0.001", &q001), ("0.01 ", &q01), ("0.05 ", &q05), ("0.10 ", &q10)] {
+        writeln!(
+            buffer,
+            "{}  {:8.3}  {:8.3}  {:8.3}  {:8.3}  {:8.3}  {:8.3}",
+            label,
+            scale(find_quantile(bootstrap_reps, q, 0.5)),
+            scale(find_quantile(bootstrap_reps, q, 0.6)),
+            scale(find_quantile(bootstrap_reps, q, 0.7)),
+            scale(find_quantile(bootstrap_reps, q, 0.8)),
+            scale(find_quantile(bootstrap_reps, q, 0.9)),
+            scale(find_quantile(bootstrap_reps, q, 0.95))
+        )?;
+    }
+
+    if let (Some(path), Some(selections)) = (history, &selections) {
+        selections.write(path)?;
+        println!("\n\nSelection history written to {}", path.display());
+    }
+
     println!("\n\nResults written to CHOOSER.LOG");
 
     statn::core::io::write::write_file("CHOOSER.LOG", buffer)
@@ -1,3 +1,17 @@
+use std::str::FromStr;
+
+use crate::random::Rng;
+
+/// A way of ranking markets: the chooser picks the market with the highest
+/// score over a window of its log prices
+pub trait SelectionCriterion {
+    /// Name for the log and the selection history
+    fn name(&self) -> String;
+
+    /// Score of a market from its log prices over the window
+    fn score(&self, prices: &[f64]) -> f64;
+}
+
 /// Performance criterion types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CriterionType {
@@ -82,6 +96,139 @@ pub fn criterion(which: CriterionType, prices: &[f64]) -> f64 {
     }
 }
 
+impl SelectionCriterion for CriterionType {
+    fn name(&self) -> String {
+        CriterionType::name(self).to_string()
+    }
+
+    fn score(&self, prices: &[f64]) -> f64 {
+        criterion(*self, prices)
+    }
+}
+
+/// Compute the largest drawdown of log prices, in log units
+pub fn max_drawdown(prices: &[f64]) -> f64 {
+    let mut peak = f64::NEG_INFINITY;
+    let mut max_dd = 0.0;
+    for &p in prices {
+        peak = peak.max(p);
+        max_dd = f64::max(max_dd, peak - p);
+    }
+    max_dd
+}
+
+/// Total return over the largest drawdown of the window
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DrawdownAdjusted;
+
+impl SelectionCriterion for DrawdownAdjusted {
+    fn name(&self) -> String {
+        "Return/drawdown".to_string()
+    }
+
+    fn score(&self, prices: &[f64]) -> f64 {
+        total_return(prices) / (max_drawdown(prices) + 1.0e-60)
+    }
+}
+
+/// Penalty taking a market failing the permutation test below every market
+/// that passes it
+const MCPT_FAIL_PENALTY: f64 = 1.0e6;
+
+/// Seed of the permutation tests of `McptThreshold`
+const MCPT_SEED: u32 = 17;
+
+/// Total return, for markets whose mean return passes a Monte Carlo
+/// permutation test at `max_p`.
+///
+/// The test randomizes the signs of the bar changes (their order carries no
+/// information for the mean), and the p-value is the share of the `reps`
+/// randomizations whose total return is at least the market's. A market
+/// failing the test scores its total return less `MCPT_FAIL_PENALTY`, so it
+/// is chosen only when no market passes. The generator is seeded the same
+/// for every window, so a score depends only on the prices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct McptThreshold {
+    pub max_p: f64,
+    pub reps: usize,
+}
+
+impl McptThreshold {
+    /// P-value of the total return of `prices`, counting the original
+    pub fn p_value(&self, prices: &[f64]) -> f64 {
+        let changes: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+        let original: f64 = changes.iter().sum();
+        let mut rng = Rng::with_seed(MCPT_SEED);
+        let mut count = 1;
+        for _ in 1..self.reps {
+            let randomized: f64 = changes
+                .iter()
+                .map(|&c| if rng.unifrand() < 0.5 { -c } else { c })
+                .sum();
+            if randomized >= original {
+                count += 1;
+            }
+        }
+        count as f64 / self.reps.max(1) as f64
+    }
+}
+
+impl SelectionCriterion for McptThreshold {
+    fn name(&self) -> String {
+        format!("MCPT p<={}", self.max_p)
+    }
+
+    fn score(&self, prices: &[f64]) -> f64 {
+        let ret = total_return(prices);
+        if self.p_value(prices) <= self.max_p {
+            ret
+        } else {
+            ret - MCPT_FAIL_PENALTY
+        }
+    }
+}
+
+/// Criteria of the original CHOOSER: total return, Sharpe ratio and profit
+/// factor
+pub fn default_criteria() -> Vec<Box<dyn SelectionCriterion>> {
+    (0..3)
+        .filter_map(CriterionType::from_index)
+        .map(|c| Box::new(c) as Box<dyn SelectionCriterion>)
+        .collect()
+}
+
+/// Criterion named by a command line spec: "total-return", "sharpe",
+/// "profit-factor", "drawdown-adjusted" or "mcpt:<max_p>[:<reps>]"
+/// (default 100 repetitions)
+pub fn parse_criterion(spec: &str) -> Result<Box<dyn SelectionCriterion>, String> {
+    let spec = spec.trim().to_ascii_lowercase();
+    let mut parts = spec.split(':');
+    match parts.next().unwrap_or("") {
+        "total-return" | "return" => Ok(Box::new(CriterionType::TotalReturn)),
+        "sharpe" => Ok(Box::new(CriterionType::SharpeRatio)),
+        "profit-factor" | "pf" => Ok(Box::new(CriterionType::ProfitFactor)),
+        "drawdown-adjusted" | "return-drawdown" => Ok(Box::new(DrawdownAdjusted)),
+        "mcpt" => {
+            let max_p = parts
+                .next()
+                .ok_or("mcpt needs a p-value threshold, e.g. mcpt:0.05")
+                .and_then(|p| f64::from_str(p).map_err(|_| "Invalid mcpt p-value threshold"))?;
+            let reps = match parts.next() {
+                Some(r) => r.parse::<usize>().map_err(|_| format!("Invalid mcpt repetitions '{}'", r))?,
+                None => 100,
+            };
+            if !(0.0..=1.0).contains(&max_p) || reps < 2 {
+                return Err(format!("Invalid criterion '{}': need 0 <= p <= 1 and at least 2 repetitions", spec));
+            }
+            Ok(Box::new(McptThreshold { max_p, reps }))
+        }
+        _ => Err(format!(
+            "Unknown criterion '{}' (expected total-return, sharpe, profit-factor, drawdown-adjusted or mcpt:<p>[:<reps>])",
+            spec
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,4 +253,27 @@ mod tests {
         let pf = profit_factor(&prices);
         assert!(pf > 1.0);
     }
+
+    #[test]
+    fn test_selection_criteria() {
+        let prices = vec![0.0, 0.2, 0.1, 0.3, 0.25, 0.4];
+        assert!((max_drawdown(&prices) - 0.1).abs() < 1e-12);
+        assert!((DrawdownAdjusted.score(&prices) - 4.0).abs() < 1e-9);
+
+        // A steady rise passes the test, a random walk without drift does not
+        let trend: Vec<f64> = (0..60).map(|i| i as f64 * 0.01 + 0.002 * (i % 3) as f64).collect();
+        let flat: Vec<f64> = (0..60).map(|i| 0.01 * ((i * 7 % 5) as f64 - 2.0)).collect();
+        let mcpt = McptThreshold { max_p: 0.05, reps: 200 };
+        assert!(mcpt.p_value(&trend) <= 0.05);
+        assert!(mcpt.p_value(&flat) > 0.05);
+        assert!(mcpt.score(&trend) > mcpt.score(&flat));
+        assert_eq!(mcpt.score(&trend), mcpt.score(&trend));
+
+        assert_eq!(parse_criterion("Sharpe").unwrap().name(), "Sharpe ratio");
+        assert_eq!(parse_criterion("mcpt:0.1:50").unwrap().name(), "MCPT p<=0.1");
+        assert!(parse_criterion("mcpt").is_err());
+        assert!(parse_criterion("mcpt:2").is_err());
+        assert!(parse_criterion("sortino").is_err());
+        assert_eq!(default_criteria().len(), 3);
+    }
 }
@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// One step of the outer walkforward: the criterion chosen from the OOS1
+/// window, the market it chose and that market's next return (OOS2)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fold {
+    /// Date of the OOS2 bar traded
+    pub date: i32,
+    /// Sum of the OOS1 log returns earned by each criterion's choices
+    pub oos1_perf: Vec<f64>,
+    /// Index of the chosen criterion
+    pub criterion: usize,
+    /// Ranking score of each market under the chosen criterion, adjusted for
+    /// liquidity; `None` for a market below the volume floor
+    pub scores: Vec<Option<f64>>,
+    /// Index of the selected market
+    pub market: usize,
+    /// Log return of the selected market over the OOS2 bar
+    pub oos_return: f64,
+}
+
+/// Every fold of a run, for analyzing how stable the selection is
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct SelectionHistory {
+    pub criteria: Vec<String>,
+    pub markets: Vec<String>,
+    pub folds: Vec<Fold>,
+}
+
+impl SelectionHistory {
+    pub fn new(criteria: Vec<String>, markets: Vec<String>) -> Self {
+        SelectionHistory { criteria, markets, folds: Vec::new() }
+    }
+
+    /// One row per fold: `fold,date,criterion,market,oos_return,cumulative_return`,
+    /// then `oos1_<criterion>` for each criterion and `score_<market>` for
+    /// each market (empty below the volume floor)
+    pub fn to_csv(&self) -> String {
+        let mut csv = "fold,date,criterion,market,oos_return,cumulative_return".to_string();
+        for name in &self.criteria {
+            csv.push_str(&format!(",oos1_{}", column_name(name)));
+        }
+        for name in &self.markets {
+            csv.push_str(&format!(",score_{}", column_name(name)));
+        }
+        csv.push('\n');
+
+        let mut cumulative = 0.0;
+        for (i, fold) in self.folds.iter().enumerate() {
+            cumulative += fold.oos_return;
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}",
+                i, fold.date, self.criteria[fold.criterion], self.markets[fold.market], fold.oos_return, cumulative
+            ));
+            for perf in &fold.oos1_perf {
+                csv.push_str(&format!(",{}", perf));
+            }
+            for score in &fold.scores {
+                match score {
+                    Some(s) => csv.push_str(&format!(",{}", s)),
+                    None => csv.push(','),
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Write the history to `path`, as JSON if it ends in `.json`, else as CSV
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let text = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            self.to_csv()
+        };
+        std::fs::write(path, text).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Name usable in a CSV header: lowercase, runs of other characters as `_`
+fn column_name(name: &str) -> String {
+    let mut column = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            column.push(c.to_ascii_lowercase());
+        } else if !column.ends_with('_') {
+            column.push('_');
+        }
+    }
+    column.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_csv_and_json() {
+        let mut history = SelectionHistory::new(
+            vec!["Total return".to_string(), "Sharpe ratio".to_string()],
+            vec!["ES".to_string(), "NQ".to_string()],
+        );
+        history.folds.push(Fold {
+            date: 20230103,
+            oos1_perf: vec![0.02, -0.01],
+            criterion: 0,
+            scores: vec![Some(0.5), None],
+            market: 0,
+            oos_return: 0.25,
+        });
+        history.folds.push(Fold {
+            date: 20230104,
+            oos1_perf: vec![0.01, 0.03],
+            criterion: 1,
+            scores: vec![Some(0.2), Some(0.4)],
+            market: 1,
+            oos_return: 0.5,
+        });
+
+        let csv = history.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "fold,date,criterion,market,oos_return,cumulative_return,oos1_total_return,oos1_sharpe_ratio,score_es,score_nq"
+        );
+        assert_eq!(lines[1], "0,20230103,Total return,ES,0.25,0.25,0.02,-0.01,0.5,");
+        assert!(lines[2].starts_with("1,20230104,Sharpe ratio,NQ,0.5,0.75,"));
+
+        let json: serde_json::Value = serde_json::from_str(&serde_json::to_string(&history).unwrap()).unwrap();
+        assert_eq!(json["folds"][0]["scores"][1], serde_json::Value::Null);
+        assert_eq!(json["markets"][1], "NQ");
+    }
+}
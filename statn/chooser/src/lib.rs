@@ -2,6 +2,8 @@ pub mod chooser;
 pub mod chooser_dd;
 pub mod criteria;
 pub mod drawdown;
+pub mod history;
+pub mod liquidity;
 pub mod market_data;
 pub mod permutation;
 pub mod random;
@@ -0,0 +1,147 @@
+use crate::criteria::SelectionCriterion;
+use crate::market_data::MarketData;
+
+/// How the liquidity of a market weighs on its ranking.
+///
+/// Liquidity is the mean volume over the same window as the criterion,
+/// relative to the most liquid market in that window. With the defaults the
+/// ranking is the plain criterion.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Liquidity {
+    /// Exponent of the relative volume scaling the criterion: a positive
+    /// criterion is multiplied by it and a negative one divided, so a thin
+    /// market must do better to be chosen (0 = ignore volume)
+    pub weight: f64,
+    /// Markets with a lower mean volume over the window are never chosen
+    /// (0 = no floor)
+    pub min_volume: f64,
+}
+
+impl Liquidity {
+    /// Whether volume plays any part in the ranking
+    pub fn is_active(&self) -> bool {
+        self.weight != 0.0 || self.min_volume > 0.0
+    }
+
+    /// Criterion adjusted for the market's relative volume, `None` if the
+    /// market is below the volume floor
+    pub fn adjust(&self, crit: f64, volume: f64, max_volume: f64) -> Option<f64> {
+        if volume < self.min_volume {
+            return None;
+        }
+        if self.weight == 0.0 {
+            return Some(crit);
+        }
+        let scale = if max_volume > 0.0 { (volume / max_volume).powf(self.weight) } else { 1.0 };
+        if scale <= 0.0 {
+            return None;
+        }
+        Some(if crit >= 0.0 { crit * scale } else { crit / scale })
+    }
+}
+
+/// Mean volume of a window
+pub fn mean_volume(volume: &[f64]) -> f64 {
+    if volume.is_empty() {
+        return 0.0;
+    }
+    volume.iter().sum::<f64>() / volume.len() as f64
+}
+
+/// Lines for the log describing the liquidity settings and each market's
+/// mean volume; an error if volume is needed and a market has none
+pub fn liquidity_report(markets: &[MarketData], liquidity: &Liquidity) -> anyhow::Result<String> {
+    if !liquidity.is_active() {
+        return Ok(String::new());
+    }
+    if let Some(market) = markets.iter().find(|m| !m.has_volume) {
+        anyhow::bail!("Market {} has no volume column, needed for the liquidity weighting", market.name);
+    }
+    let mut report = format!(
+        "\n\nLiquidity weight {:.3}, minimum volume {:.0}. Mean volume of each market...",
+        liquidity.weight, liquidity.min_volume
+    );
+    for market in markets {
+        report.push_str(&format!("\n{:>15} {:15.0}", market.name, mean_volume(&market.volume)));
+    }
+    Ok(report)
+}
+
+/// Ranking score of each market over `start..end`: its criterion adjusted
+/// for liquidity, `None` below the volume floor. If every market is below the
+/// floor, the floor is ignored.
+pub fn market_scores(markets: &[MarketData], criterion: &dyn SelectionCriterion, start: usize, end: usize, liquidity: &Liquidity) -> Vec<Option<f64>> {
+    let crits: Vec<f64> = markets.iter().map(|m| criterion.score(&m.close[start..end])).collect();
+    if !liquidity.is_active() {
+        return crits.into_iter().map(Some).collect();
+    }
+    let volumes: Vec<f64> = markets.iter().map(|m| mean_volume(&m.volume[start..end])).collect();
+    let max_volume = volumes.iter().copied().fold(0.0, f64::max);
+    let scores: Vec<Option<f64>> = crits.iter().zip(&volumes).map(|(&c, &v)| liquidity.adjust(c, v, max_volume)).collect();
+    if scores.iter().any(|s| s.is_some()) {
+        return scores;
+    }
+    let no_floor = Liquidity { min_volume: 0.0, ..*liquidity };
+    crits.iter().zip(&volumes).map(|(&c, &v)| no_floor.adjust(c, v, max_volume)).collect()
+}
+
+/// Market with the best criterion over `start..end`, adjusted for liquidity.
+/// If every market is below the volume floor, the floor is ignored.
+pub fn best_market(markets: &[MarketData], criterion: &dyn SelectionCriterion, start: usize, end: usize, liquidity: &Liquidity) -> usize {
+    best_of(&market_scores(markets, criterion, start, end, liquidity))
+}
+
+/// Index of the best of `scores`, as `best_market` chooses
+pub fn best_of(scores: &[Option<f64>]) -> usize {
+    argmax(scores.iter().copied()).unwrap_or(0)
+}
+
+/// Index of the largest value, the first on a tie; NaN is never chosen
+fn argmax(values: impl Iterator<Item = Option<f64>>) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    let mut best_crit = -1.0e60;
+    for (i, value) in values.enumerate() {
+        if let Some(v) = value {
+            if v > best_crit {
+                best_crit = v;
+                best = Some(i);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::criteria::CriterionType;
+
+    fn market(name: &str, close: Vec<f64>, volume: f64) -> MarketData {
+        let n = close.len();
+        MarketData { name: name.to_string(), dates: (0..n as i32).collect(), close, volume: vec![volume; n], has_volume: true }
+    }
+
+    #[test]
+    fn test_thin_market_must_do_better() {
+        // The thin market has the higher return but a hundredth of the volume
+        let markets = vec![
+            market("thin", vec![0.0, 0.02, 0.04, 0.06], 1_000.0),
+            market("deep", vec![0.0, 0.01, 0.02, 0.03], 100_000.0),
+        ];
+        let plain = Liquidity::default();
+        assert_eq!(best_market(&markets, &CriterionType::TotalReturn, 0, 4, &plain), 0);
+
+        let weighted = Liquidity { weight: 0.5, min_volume: 0.0 };
+        // 0.06 * 0.1 < 0.03
+        assert_eq!(best_market(&markets, &CriterionType::TotalReturn, 0, 4, &weighted), 1);
+        let floor = Liquidity { weight: 0.0, min_volume: 5_000.0 };
+        assert_eq!(best_market(&markets, &CriterionType::TotalReturn, 0, 4, &floor), 1);
+        // A floor above every market is ignored
+        let too_high = Liquidity { weight: 0.0, min_volume: 1.0e9 };
+        assert_eq!(best_market(&markets, &CriterionType::TotalReturn, 0, 4, &too_high), 0);
+
+        // A loss is scaled up, not down
+        assert_eq!(weighted.adjust(-0.01, 25.0, 100.0), Some(-0.02));
+        assert_eq!(weighted.adjust(0.01, 25.0, 100.0), Some(0.005));
+    }
+}
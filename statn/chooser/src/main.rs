@@ -1,8 +1,11 @@
 use anyhow::Result;
 use clap::Parser;
+use std::path::PathBuf;
 
-use chooser::chooser::run_chooser;
-use chooser::chooser_dd::run_chooser_dd;
+use chooser::chooser::run_chooser_with;
+use chooser::chooser_dd::run_chooser_dd_with;
+use chooser::criteria::parse_criterion;
+use chooser::liquidity::Liquidity;
 
 #[derive(Parser, Debug)]
 #[command(name = "chooser")]
@@ -27,19 +30,50 @@ struct Args {
     /// Number of Monte-Carlo replications (only for chooser mode, 1 or 0 for none)
     #[arg(value_name = "NREPS", default_value = "1")]
     nreps: usize,
+
+    /// Weight of liquidity in the ranking of markets: the criterion is scaled
+    /// by the market's mean volume relative to the most liquid market, to this
+    /// power (0 = ignore volume). Needs a volume column after the close
+    #[arg(long, default_value_t = 0.0)]
+    liquidity_weight: f64,
+
+    /// Markets with a lower mean volume over the ranking window are never
+    /// chosen (0 = no floor)
+    #[arg(long, default_value_t = 0.0)]
+    min_volume: f64,
+
+    /// Write every fold's chosen criterion and market, the scores of all
+    /// candidates and the OOS return to this file: JSON if it ends in .json,
+    /// else CSV
+    #[arg(long)]
+    history: Option<PathBuf>,
+
+    /// Selection criteria the system chooses among, comma separated:
+    /// total-return, sharpe, profit-factor, drawdown-adjusted and
+    /// mcpt:<max_p>[:<reps>] (total return of the markets passing a
+    /// permutation test of their mean return)
+    #[arg(long, value_delimiter = ',', default_value = "total-return,sharpe,profit-factor")]
+    criteria: Vec<String>,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let criteria = args
+        .criteria
+        .iter()
+        .map(|spec| parse_criterion(spec))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let liquidity = Liquidity { weight: args.liquidity_weight, min_volume: args.min_volume };
 
     match args.mode.to_lowercase().as_str() {
         "chooser" => {
             println!("Running CHOOSER with Monte Carlo permutation testing...");
-            run_chooser(&args.file_list, args.is_n, args.oos1_n, args.nreps)?;
+            run_chooser_with(&args.file_list, args.is_n, args.oos1_n, args.nreps, &criteria, &liquidity, args.history.as_deref())?;
         }
         "chooser_dd" => {
             println!("Running CHOOSER_DD with drawdown analysis...");
-            run_chooser_dd(&args.file_list, args.is_n, args.oos1_n)?;
+            run_chooser_dd_with(&args.file_list, args.is_n, args.oos1_n, &criteria, &liquidity, args.history.as_deref())?;
         }
         _ => {
             eprintln!("Error: Invalid mode '{}'. Must be 'chooser' or 'chooser_dd'", args.mode);
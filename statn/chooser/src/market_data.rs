@@ -9,6 +9,10 @@ pub struct MarketData {
     pub name: String,
     pub dates: Vec<i32>,
     pub close: Vec<f64>,
+    /// Volume of each record, from a sixth column; 0 where there is none
+    pub volume: Vec<f64>,
+    /// Whether every record had a volume
+    pub has_volume: bool,
 }
 
 impl MarketData {
@@ -17,6 +21,8 @@ impl MarketData {
             name,
             dates: Vec::with_capacity(BLOCK_SIZE),
             close: Vec::with_capacity(BLOCK_SIZE),
+            volume: Vec::with_capacity(BLOCK_SIZE),
+            has_volume: true,
         }
     }
 }
@@ -157,8 +163,12 @@ fn read_market_file(file_path: &str, market_name: &str) -> Result<MarketData> {
                   file_path, line_num + 1);
         }
 
+        let volume = fields.get(5).and_then(|v| v.parse::<f64>().ok());
+        market.has_volume &= volume.is_some();
+
         market.dates.push(full_date);
         market.close.push(close);
+        market.volume.push(volume.unwrap_or(0.0));
     }
 
     if market.dates.is_empty() {
@@ -176,7 +186,7 @@ pub fn align_dates(markets: &mut Vec<MarketData>) -> usize {
 
     let n_markets = markets.len();
     let mut market_indices = vec![0usize; n_markets];
-    let mut aligned_data: Vec<Vec<(i32, f64)>> = vec![Vec::new(); n_markets];
+    let mut aligned_data: Vec<Vec<(i32, f64, f64)>> = vec![Vec::new(); n_markets];
 
     loop {
         // Find max date at current index of each market
@@ -227,7 +237,8 @@ pub fn align_dates(markets: &mut Vec<MarketData>) -> usize {
             for i in 0..n_markets {
                 let date = markets[i].dates[market_indices[i]];
                 let close = markets[i].close[market_indices[i]];
-                aligned_data[i].push((date, close));
+                let volume = markets[i].volume[market_indices[i]];
+                aligned_data[i].push((date, close, volume));
                 market_indices[i] += 1;
             }
         }
@@ -239,10 +250,12 @@ pub fn align_dates(markets: &mut Vec<MarketData>) -> usize {
     for (i, market) in markets.iter_mut().enumerate() {
         market.dates.clear();
         market.close.clear();
+        market.volume.clear();
         
-        for (date, close) in &aligned_data[i] {
+        for (date, close, volume) in &aligned_data[i] {
             market.dates.push(*date);
             market.close.push(*close);
+            market.volume.push(*volume);
         }
     }
 
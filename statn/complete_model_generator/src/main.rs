@@ -10,6 +10,7 @@ mod report;
 use sensitivity::run_sensitivity_analysis;
 use report::{generate_report, ReportData};
 use try_cd_ma::Config;
+use try_cd_ma::{MaType, Slippage, TargetKind};
 
 #[derive(Parser)]
 #[command(name = "complete_model_tester")]
@@ -126,14 +127,34 @@ fn main() -> Result<()> {
         lookback_inc: 2,
         n_long: 6,
         n_short: 5,
+        ma_types: vec![MaType::Sma],
+        target: TargetKind::NextBar,
+        target_horizon: 1,
+        vol_lookback: 20,
+        profit_take: 2.0,
+        stop_loss: 2.0,
+        classify: false,
         alpha: 0.5,
         data_file: abs_price_path.clone(),
+        data_format: None,
         output_path: "results/".to_string(),
         n_test: 252,
         n_folds: 10,
         n_lambdas: 50,
+        one_se: false,
         max_iterations: 1000,
         tolerance: 1e-9,
+        half_life: 0.0,
+        half_life_grid: Vec::new(),
+        walkforward_step: 0,
+        train_window: 0,
+        ensemble_alphas: Vec::new(),
+        ensemble_bootstrap: 0,
+        signal_threshold: 0.0,
+        long_only: false,
+        transaction_cost: 0.1,
+        slippage: Slippage::None,
+        initial_capital: 100_000.0,
     }; 
     let sens_log_path = cli.output_dir.join("SENS.LOG");
     let sensitivity_result = run_sensitivity_analysis(
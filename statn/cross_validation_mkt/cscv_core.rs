@@ -1,5 +1,37 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
 use crate::criter::criter;
 
+/// How the cases are split into training (IS) and test (OOS) sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Splitter {
+    /// Every way of choosing half of the blocks as the training set
+    #[default]
+    Combinatorial,
+    /// Each block in turn is the test set and all the others train
+    PurgedKFold,
+}
+
+impl FromStr for Splitter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cscv" | "combinatorial" => Ok(Splitter::Combinatorial),
+            "kfold" | "purged-kfold" => Ok(Splitter::PurgedKFold),
+            _ => Err(format!("Unknown splitter '{}' (expected cscv or kfold)", s)),
+        }
+    }
+}
+
+/// Role of a case in one split
+const TRAIN: u8 = 1;
+const TEST: u8 = 0;
+const PURGED: u8 = 2;
+
 /// Combinatorially symmetric cross validation core routine
 /// 
 /// # Arguments
@@ -10,24 +42,104 @@ use crate::criter::criter;
 /// 
 /// # Returns
 /// Probability that the best in-sample system is at or below the median out-of-sample performance
+#[allow(dead_code)]
 pub fn cscvcore(
     ncases: usize,
     n_systems: usize,
     n_blocks: usize,
     returns: &[f64],
 ) -> f64 {
-    // Make sure n_blocks is even
-    let n_blocks = (n_blocks / 2) * 2;
+    cscvcore_with_splitter(ncases, n_systems, n_blocks, returns, Splitter::Combinatorial, 0, false)
+}
+
+/// Cross validation with a choice of splitter and an embargo.
+///
+/// Training cases within `embargo` cases of any test case, before or after
+/// it, are purged from the training set. A moving-average system's return at
+/// one bar depends on the prices of the lookback before it, so without the
+/// embargo the training cases next to a test block share information with
+/// it. Test sets are never trimmed.
+///
+/// # Arguments
+/// * `ncases`, `n_systems`, `returns` - As for `cscvcore`
+/// * `n_blocks` - Number of contiguous blocks; made even for `Combinatorial`
+/// * `splitter` - How blocks are assigned to training and test sets
+/// * `embargo` - Training cases purged on each side of every test block
+/// * `progress` - Report the share of splits evaluated on stderr
+///
+/// # Returns
+/// Fraction of splits in which the best in-sample system is at or below the
+/// median out-of-sample performance
+pub fn cscvcore_with_splitter(
+    ncases: usize,
+    n_systems: usize,
+    n_blocks: usize,
+    returns: &[f64],
+    splitter: Splitter,
+    embargo: usize,
+    progress: bool,
+) -> f64 {
+    cscv_report(ncases, n_systems, n_blocks, returns, splitter, embargo, progress).pbo
+}
+
+/// Outcome of one training/test split
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitOutcome {
+    /// System with the best in-sample criterion
+    pub best_system: usize,
+    pub is_criterion: f64,
+    /// Out-of-sample criterion of the best in-sample system
+    pub oos_criterion: f64,
+    /// Relative out-of-sample rank of that system, in (0, 1)
+    pub relative_rank: f64,
+    /// ln(rank / (1 - rank)); at or below 0 when the system is at or below
+    /// the out-of-sample median
+    pub logit: f64,
+}
+
+/// Everything a cross validation measures, split by split
+#[derive(Debug, Clone, PartialEq)]
+pub struct CscvReport {
+    /// Outcome of every split, in the order the splits are enumerated
+    pub splits: Vec<SplitOutcome>,
+    /// Probability of backtest overfitting: the fraction of splits whose logit
+    /// is at or below 0
+    pub pbo: f64,
+    /// Least-squares slope of the out-of-sample on the in-sample criterion
+    /// of the best in-sample system; below 1 as performance degrades
+    pub degradation_slope: f64,
+    pub degradation_intercept: f64,
+}
+
+impl CscvReport {
+    /// Logits of all splits, in increasing order
+    pub fn sorted_logits(&self) -> Vec<f64> {
+        let mut logits: Vec<f64> = self.splits.iter().map(|s| s.logit).collect();
+        logits.sort_by(f64::total_cmp);
+        logits
+    }
+}
+
+/// Cross validation as `cscvcore_with_splitter`, keeping the outcome of each
+/// split as well as the logit distribution and performance-degradation fit
+/// they imply
+pub fn cscv_report(
+    ncases: usize,
+    n_systems: usize,
+    n_blocks: usize,
+    returns: &[f64],
+    splitter: Splitter,
+    embargo: usize,
+    progress: bool,
+) -> CscvReport {
+    let n_blocks = match splitter {
+        Splitter::Combinatorial => (n_blocks / 2) * 2,
+        Splitter::PurgedKFold => n_blocks,
+    };
     
-    // Allocate work vectors
+    // Find the starting index and length of each of the n_blocks submatrices
     let mut indices = vec![0; n_blocks];
     let mut lengths = vec![0; n_blocks];
-    let mut flags = vec![0; n_blocks];
-    let mut work = vec![0.0; ncases];
-    let mut is_crits = vec![0.0; n_systems];
-    let mut oos_crits = vec![0.0; n_systems];
-    
-    // Find the starting index and length of each of the n_blocks submatrices
     let mut istart = 0;
     for i in 0..n_blocks {
         indices[i] = istart;
@@ -35,74 +147,74 @@ pub fn cscvcore(
         istart += lengths[i];
     }
     
-    // Initialize flags: first half are training set (1), second half are test set (0)
-    for i in 0..(n_blocks / 2) {
-        flags[i] = 1;
+    let splits = block_splits(splitter, n_blocks);
+    let ncombo = splits.len();
+    let done = AtomicUsize::new(0);
+    
+    // Each thread reuses its own work vectors across the splits it evaluates
+    let outcomes: Vec<SplitOutcome> = splits
+        .par_iter()
+        .map_init(
+            || (vec![TEST; ncases], vec![0.0; ncases], vec![0.0; n_systems], vec![0.0; n_systems]),
+            |(roles, work, is_crits, oos_crits), flags| {
+                block_roles(flags, &indices, &lengths, embargo, roles);
+                for isys in 0..n_systems {
+                    let row = &returns[isys * ncases..(isys + 1) * ncases];
+                    is_crits[isys] = role_criterion(row, roles, TRAIN, work);
+                    oos_crits[isys] = role_criterion(row, roles, TEST, work);
+                }
+                
+                if progress {
+                    report_progress(done.fetch_add(1, Ordering::Relaxed) + 1, ncombo);
+                }
+                split_outcome(is_crits, oos_crits)
+            },
+        )
+        .collect();
+    
+    let nless = outcomes.iter().filter(|s| s.logit <= 0.0).count();
+    let (degradation_slope, degradation_intercept) = least_squares(
+        &outcomes.iter().map(|s| s.is_criterion).collect::<Vec<_>>(),
+        &outcomes.iter().map(|s| s.oos_criterion).collect::<Vec<_>>(),
+    );
+    CscvReport {
+        pbo: nless as f64 / ncombo as f64,
+        splits: outcomes,
+        degradation_slope,
+        degradation_intercept,
     }
-    for i in (n_blocks / 2)..n_blocks {
-        flags[i] = 0;
+}
+
+/// Slope and intercept of the least-squares line of `y` on `x`; a flat line
+/// through the mean of `y` when `x` does not vary
+fn least_squares(x: &[f64], y: &[f64]) -> (f64, f64) {
+    let n = x.len().max(1) as f64;
+    let x_mean = x.iter().sum::<f64>() / n;
+    let y_mean = y.iter().sum::<f64>() / n;
+    let sxx: f64 = x.iter().map(|xi| (xi - x_mean).powi(2)).sum();
+    let sxy: f64 = x.iter().zip(y).map(|(xi, yi)| (xi - x_mean) * (yi - y_mean)).sum();
+    let slope = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+    (slope, y_mean - slope * x_mean)
+}
+
+/// Block flags (`TRAIN` or `TEST`) of every split
+fn block_splits(splitter: Splitter, n_blocks: usize) -> Vec<Vec<u8>> {
+    if splitter == Splitter::PurgedKFold {
+        return (0..n_blocks)
+            .map(|itest| (0..n_blocks).map(|ic| if ic == itest { TEST } else { TRAIN }).collect())
+            .collect();
     }
     
-    let mut nless = 0; // Count of times OOS of best <= median OOS
-    let mut ncombo = 0; // Count of combinations
+    // Initialize flags: first half are training set (1), second half are test set (0)
+    let mut flags = vec![TEST; n_blocks];
+    for flag in flags.iter_mut().take(n_blocks / 2) {
+        *flag = TRAIN;
+    }
     
-    // Main loop processes all combinations of blocks
+    // Enumerate all combinations of blocks
+    let mut splits = Vec::new();
     loop {
-        // Compute training-set (IS) criterion for each candidate system
-        for isys in 0..n_systems {
-            let mut n = 0;
-            for ic in 0..n_blocks {
-                if flags[ic] == 1 {
-                    // This block is in the training set
-                    for i in indices[ic]..(indices[ic] + lengths[ic]) {
-                        work[n] = returns[isys * ncases + i];
-                        n += 1;
-                    }
-                }
-            }
-            is_crits[isys] = criter(&work[0..n]);
-        }
-        
-        // Compute OOS criterion for each candidate system
-        for isys in 0..n_systems {
-            let mut n = 0;
-            for ic in 0..n_blocks {
-                if flags[ic] == 0 {
-                    // This block is in the OOS set
-                    for i in indices[ic]..(indices[ic] + lengths[ic]) {
-                        work[n] = returns[isys * ncases + i];
-                        n += 1;
-                    }
-                }
-            }
-            oos_crits[isys] = criter(&work[0..n]);
-        }
-        
-        // Determine the relative rank within OOS of the system which had best IS performance
-        let mut best_is = is_crits[0];
-        let mut ibest = 0;
-        for isys in 1..n_systems {
-            if is_crits[isys] > best_is {
-                best_is = is_crits[isys];
-                ibest = isys;
-            }
-        }
-        
-        let best_oos = oos_crits[ibest];
-        let mut n = 0;
-        for isys in 0..n_systems {
-            if isys == ibest || best_oos >= oos_crits[isys] {
-                n += 1;
-            }
-        }
-        
-        let rel_rank = n as f64 / (n_systems + 1) as f64;
-        
-        if rel_rank <= 0.5 {
-            nless += 1;
-        }
-        
-        ncombo += 1;
+        splits.push(flags.clone());
         
         // Move to the next combination
         let mut iradix = 0;
@@ -110,20 +222,20 @@ pub fn cscvcore(
         let mut n_flags = 0;
         
         for ir in 0..(n_blocks - 1) {
-            if flags[ir] == 1 {
+            if flags[ir] == TRAIN {
                 n_flags += 1;
-                if flags[ir + 1] == 0 {
-                    flags[ir] = 0;
-                    flags[ir + 1] = 1;
+                if flags[ir + 1] == TEST {
+                    flags[ir] = TEST;
+                    flags[ir + 1] = TRAIN;
                     
                     // Reset everything below this change point
                     let mut reset_count = n_flags - 1;
-                    for i in 0..ir {
+                    for flag in flags.iter_mut().take(ir) {
                         if reset_count > 0 {
-                            flags[i] = 1;
+                            *flag = TRAIN;
                             reset_count -= 1;
                         } else {
-                            flags[i] = 0;
+                            *flag = TEST;
                         }
                     }
                     
@@ -138,8 +250,91 @@ pub fn cscvcore(
             break;
         }
     }
+    splits
+}
+
+/// Print the share of splits evaluated to stderr at each whole percent
+fn report_progress(done: usize, total: usize) {
+    let percent = done * 100 / total;
+    if done == total || percent != (done - 1) * 100 / total {
+        eprint!("\rEvaluated {} of {} splits ({}%)", done, total, percent);
+        if done == total {
+            eprintln!();
+        }
+    }
+}
+
+/// Role of each case given the role of each block, purging training cases
+/// within `embargo` cases of a test case
+fn block_roles(flags: &[u8], indices: &[usize], lengths: &[usize], embargo: usize, roles: &mut [u8]) {
+    for (ic, &flag) in flags.iter().enumerate() {
+        roles[indices[ic]..indices[ic] + lengths[ic]].fill(flag);
+    }
+    if embargo == 0 {
+        return;
+    }
+    
+    // Distance to the nearest test case before, then after, each case
+    let mut since_test = usize::MAX;
+    let mut near_test = vec![false; roles.len()];
+    for (i, &role) in roles.iter().enumerate() {
+        since_test = if role == TEST { 0 } else { since_test.saturating_add(1) };
+        near_test[i] = since_test <= embargo;
+    }
+    since_test = usize::MAX;
+    for (i, &role) in roles.iter().enumerate().rev() {
+        since_test = if role == TEST { 0 } else { since_test.saturating_add(1) };
+        near_test[i] |= since_test <= embargo;
+    }
+    
+    for (role, near) in roles.iter_mut().zip(near_test) {
+        if *role == TRAIN && near {
+            *role = PURGED;
+        }
+    }
+}
+
+/// Criterion of the cases of a system's returns that have the given role
+fn role_criterion(row: &[f64], roles: &[u8], role: u8, work: &mut [f64]) -> f64 {
+    let mut n = 0;
+    for (&ret, &r) in row.iter().zip(roles) {
+        if r == role {
+            work[n] = ret;
+            n += 1;
+        }
+    }
+    criter(&work[0..n])
+}
+
+/// Best IS system of a split and where its OOS performance ranks
+fn split_outcome(is_crits: &[f64], oos_crits: &[f64]) -> SplitOutcome {
+    // Determine the relative rank within OOS of the system which had best IS performance
+    let n_systems = is_crits.len();
+    let mut best_is = is_crits[0];
+    let mut ibest = 0;
+    for isys in 1..n_systems {
+        if is_crits[isys] > best_is {
+            best_is = is_crits[isys];
+            ibest = isys;
+        }
+    }
+    
+    let best_oos = oos_crits[ibest];
+    let mut n = 0;
+    for isys in 0..n_systems {
+        if isys == ibest || best_oos >= oos_crits[isys] {
+            n += 1;
+        }
+    }
     
-    nless as f64 / ncombo as f64
+    let rel_rank = n as f64 / (n_systems + 1) as f64;
+    SplitOutcome {
+        best_system: ibest,
+        is_criterion: best_is,
+        oos_criterion: best_oos,
+        relative_rank: rel_rank,
+        logit: (rel_rank / (1.0 - rel_rank)).ln(),
+    }
 }
 
 #[cfg(test)]
@@ -164,5 +359,73 @@ mod tests {
         
         // Probability should be between 0 and 1
         assert!(prob >= 0.0 && prob <= 1.0);
+        assert_eq!(prob, cscvcore_with_splitter(ncases, n_systems, 4, &returns, Splitter::Combinatorial, 0, false));
+    }
+    
+    #[test]
+    fn test_cscv_report() {
+        // System 0 is best everywhere; its logit is the top one in every split
+        let (n_systems, ncases) = (3, 40);
+        let returns: Vec<f64> = (0..n_systems * ncases)
+            .map(|k| if k / ncases == 0 { 1.0 + (k % 3) as f64 } else { ((k % 7) as f64 - 3.0) / 10.0 })
+            .collect();
+        let report = cscv_report(ncases, n_systems, 4, &returns, Splitter::Combinatorial, 0, false);
+        assert_eq!(report.splits.len(), 6);
+        assert_eq!(report.pbo, 0.0);
+        for split in &report.splits {
+            assert_eq!(split.best_system, 0);
+            assert_eq!(split.relative_rank, 0.75);
+            assert!((split.logit - 3.0f64.ln()).abs() < 1e-12);
+        }
+        let logits = report.sorted_logits();
+        assert!(logits.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(
+            report.pbo,
+            cscvcore_with_splitter(ncases, n_systems, 4, &returns, Splitter::Combinatorial, 0, false)
+        );
+        
+        assert_eq!(least_squares(&[1.0, 2.0, 3.0], &[3.0, 5.0, 7.0]), (2.0, 1.0));
+        assert_eq!(least_squares(&[1.0, 1.0], &[2.0, 4.0]), (0.0, 3.0));
+    }
+    
+    #[test]
+    fn test_block_roles_embargo() {
+        // Blocks of 4 cases: train, test, train
+        let mut roles = vec![TEST; 12];
+        block_roles(&[TRAIN, TEST, TRAIN], &[0, 4, 8], &[4, 4, 4], 2, &mut roles);
+        assert_eq!(roles, vec![1, 1, 2, 2, 0, 0, 0, 0, 2, 2, 1, 1]);
+        
+        block_roles(&[TRAIN, TEST, TRAIN], &[0, 4, 8], &[4, 4, 4], 0, &mut roles);
+        assert_eq!(roles, vec![1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1]);
+    }
+    
+    #[test]
+    fn test_purged_kfold() {
+        // System 0 is best in every block, so its OOS rank is always top
+        let (n_systems, ncases) = (3, 30);
+        let returns: Vec<f64> = (0..n_systems * ncases)
+            .map(|k| if k / ncases == 0 { 1.0 } else { ((k % 7) as f64 - 3.0) / 10.0 })
+            .collect();
+        for embargo in [0, 3] {
+            let prob = cscvcore_with_splitter(ncases, n_systems, 5, &returns, Splitter::PurgedKFold, embargo, false);
+            assert_eq!(prob, 0.0);
+        }
+        assert_eq!("kfold".parse::<Splitter>(), Ok(Splitter::PurgedKFold));
+        assert_eq!("CSCV".parse::<Splitter>(), Ok(Splitter::Combinatorial));
+        assert!("random".parse::<Splitter>().is_err());
+    }
+    
+    #[test]
+    fn test_block_splits() {
+        // C(6, 3) half/half splits, each with three training blocks
+        let splits = block_splits(Splitter::Combinatorial, 6);
+        assert_eq!(splits.len(), 20);
+        assert!(splits.iter().all(|f| f.iter().filter(|&&b| b == TRAIN).count() == 3));
+        let mut unique = splits.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 20);
+        
+        assert_eq!(block_splits(Splitter::PurgedKFold, 4).len(), 4);
     }
 }
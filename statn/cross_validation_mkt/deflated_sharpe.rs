@@ -0,0 +1,112 @@
+use stats::{inverse_normal_cdf, normal_cdf};
+
+/// Euler-Mascheroni constant
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// Deflated Sharpe ratio of the system selected from several trials
+/// (Bailey and Lopez de Prado, 2014)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeflatedSharpe {
+    /// Sharpe ratio of the selected system, per case
+    pub sharpe: f64,
+    pub skewness: f64,
+    /// Kurtosis (3 for normal returns)
+    pub kurtosis: f64,
+    pub trials: usize,
+    /// Variance of the Sharpe ratios of all trials
+    pub sharpe_variance: f64,
+    /// Expected maximum Sharpe ratio of that many trials without skill
+    pub expected_max: f64,
+    /// Probability that the true Sharpe ratio exceeds `expected_max`
+    pub dsr: f64,
+}
+
+/// Sharpe ratio per case: mean over sample standard deviation, 0 without
+/// variation
+pub fn sharpe(returns: &[f64]) -> f64 {
+    let n = returns.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / n as f64;
+    let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    if var > 0.0 {
+        mean / var.sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Deflate the Sharpe ratio of the `selected` system's returns for having
+/// been the best of trials whose Sharpe ratios are `trial_sharpes`.
+///
+/// The benchmark is the expected maximum of that many Sharpe ratios drawn
+/// around zero with their observed variance; the probability that the
+/// selected system's true Sharpe ratio beats it allows for the sample length
+/// and the skewness and kurtosis of its returns. NaN with fewer than 2
+/// returns.
+pub fn deflated_sharpe(selected: &[f64], trial_sharpes: &[f64]) -> DeflatedSharpe {
+    let n = selected.len();
+    let sr = sharpe(selected);
+    let mean = selected.iter().sum::<f64>() / n.max(1) as f64;
+    let m2 = selected.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n.max(1) as f64;
+    let (skewness, kurtosis) = if m2 > 0.0 {
+        let m3 = selected.iter().map(|r| (r - mean).powi(3)).sum::<f64>() / n as f64;
+        let m4 = selected.iter().map(|r| (r - mean).powi(4)).sum::<f64>() / n as f64;
+        (m3 / m2.powf(1.5), m4 / (m2 * m2))
+    } else {
+        (0.0, 3.0)
+    };
+
+    let trials = trial_sharpes.len();
+    let sharpe_variance = if trials > 1 {
+        let mean = trial_sharpes.iter().sum::<f64>() / trials as f64;
+        trial_sharpes.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (trials - 1) as f64
+    } else {
+        0.0
+    };
+    let expected_max = if trials > 1 {
+        let n = trials as f64;
+        sharpe_variance.sqrt()
+            * ((1.0 - EULER_GAMMA) * inverse_normal_cdf(1.0 - 1.0 / n)
+                + EULER_GAMMA * inverse_normal_cdf(1.0 - 1.0 / (n * std::f64::consts::E)))
+    } else {
+        0.0
+    };
+
+    let denom = 1.0 - skewness * sr + (kurtosis - 1.0) / 4.0 * sr * sr;
+    let dsr = if n < 2 || denom <= 0.0 {
+        f64::NAN
+    } else {
+        normal_cdf((sr - expected_max) * ((n - 1) as f64).sqrt() / denom.sqrt())
+    };
+
+    DeflatedSharpe { sharpe: sr, skewness, kurtosis, trials, sharpe_variance, expected_max, dsr }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deflated_sharpe() {
+        // Symmetric returns with mean 0.1 and standard deviation about 1
+        let selected: Vec<f64> = (0..400).map(|i| if i % 2 == 0 { 1.1 } else { -0.9 }).collect();
+        assert!((sharpe(&selected) - 0.1).abs() < 1e-3);
+
+        // One trial: no deflation, the probabilistic Sharpe ratio against 0
+        let one = deflated_sharpe(&selected, &[0.1]);
+        assert_eq!(one.expected_max, 0.0);
+        assert!(one.skewness.abs() < 1e-9 && (one.kurtosis - 1.0).abs() < 1e-9);
+        assert!(one.dsr > 0.95, "{:?}", one);
+
+        // Many trials spread around zero raise the bar
+        let trials: Vec<f64> = (0..100).map(|i| (i as f64 - 49.5) / 1000.0).collect();
+        let many = deflated_sharpe(&selected, &trials);
+        assert!(many.expected_max > 0.05 && many.expected_max < one.sharpe, "{:?}", many);
+        assert!(many.dsr < one.dsr);
+
+        assert!(deflated_sharpe(&[1.0], &trials).dsr.is_nan());
+        assert_eq!(sharpe(&[0.5; 10]), 0.0);
+    }
+}
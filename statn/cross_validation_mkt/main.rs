@@ -1,24 +1,85 @@
 mod criter;
 mod cscv_core;
+mod deflated_sharpe;
 mod get_returns;
 
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::process;
 
 use criter::criter;
-use cscv_core::cscvcore;
+use cscv_core::{cscv_report, CscvReport, Splitter};
+use deflated_sharpe::{deflated_sharpe, sharpe, DeflatedSharpe};
 use get_returns::get_returns;
 
+fn print_usage() {
+    eprintln!("\nUsage: cross_validation_mkt [--splitter cscv|kfold] [--embargo-bars N] [--results FILE] n_blocks max_lookback filename");
+    eprintln!("  n_blocks - number of blocks into which cases are partitioned");
+    eprintln!("  max_lookback - Maximum moving-average lookback");
+    eprintln!("  filename - name of market file (YYYYMMDD Price)");
+    eprintln!("  --splitter - cscv: all half/half block splits (default); kfold: purged K-fold, each block tested once");
+    eprintln!("  --embargo-bars - training bars dropped on each side of every test block (default 0)");
+    eprintln!("  --results - write the logit of every split, the degradation fit and the deflated Sharpe ratio to FILE");
+}
+
+/// Write the summary and the outcome of every split as CSV
+fn write_results(path: &str, report: &CscvReport, dsr: &DeflatedSharpe, best: usize) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "# pbo = {:.6}", report.pbo)?;
+    writeln!(out, "# degradation_slope = {:.6}", report.degradation_slope)?;
+    writeln!(out, "# degradation_intercept = {:.6}", report.degradation_intercept)?;
+    writeln!(out, "# selected_system = {}", best)?;
+    writeln!(out, "# sharpe = {:.6}", dsr.sharpe)?;
+    writeln!(out, "# skewness = {:.6}", dsr.skewness)?;
+    writeln!(out, "# kurtosis = {:.6}", dsr.kurtosis)?;
+    writeln!(out, "# trials = {}", dsr.trials)?;
+    writeln!(out, "# expected_max_sharpe = {:.6}", dsr.expected_max)?;
+    writeln!(out, "# deflated_sharpe = {:.6}", dsr.dsr)?;
+    writeln!(out, "split,best_system,is_criterion,oos_criterion,relative_rank,logit")?;
+    for (i, split) in report.splits.iter().enumerate() {
+        writeln!(
+            out,
+            "{},{},{:.8},{:.8},{:.6},{:.6}",
+            i, split.best_system, split.is_criterion, split.oos_criterion, split.relative_rank, split.logit
+        )?;
+    }
+    out.flush()
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = Vec::new();
+    let mut splitter = Splitter::Combinatorial;
+    let mut embargo = 0;
+    let mut results: Option<String> = None;
+    let mut argv = env::args();
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--splitter" | "--embargo-bars" | "--results" => {
+                let value = argv.next().unwrap_or_else(|| {
+                    print_usage();
+                    process::exit(1);
+                });
+                if arg == "--results" {
+                    results = Some(value);
+                } else if arg == "--splitter" {
+                    splitter = value.parse().unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        process::exit(1);
+                    });
+                } else {
+                    embargo = value.parse().unwrap_or_else(|_| {
+                        eprintln!("Error: embargo-bars must be a non-negative integer");
+                        process::exit(1);
+                    });
+                }
+            }
+            _ => args.push(arg),
+        }
+    }
     
     if args.len() != 4 {
-        eprintln!("\nUsage: cross_validation_mkt n_blocks max_lookback filename");
-        eprintln!("  n_blocks - number of blocks into which cases are partitioned");
-        eprintln!("  max_lookback - Maximum moving-average lookback");
-        eprintln!("  filename - name of market file (YYYYMMDD Price)");
+        print_usage();
         process::exit(1);
     }
     
@@ -91,38 +152,55 @@ fn main() {
     let n_systems = max_lookback * (max_lookback - 1) / 2;
     
     if nprices < 2 || n_blocks < 2 || max_lookback < 2 || n_returns < n_blocks {
-        eprintln!("\nUsage: cross_validation_mkt n_blocks max_lookback filename");
-        eprintln!("  n_blocks - number of blocks into which cases are partitioned");
-        eprintln!("  max_lookback - Maximum moving-average lookback");
-        eprintln!("  filename - name of market file (YYYYMMDD Price)");
+        print_usage();
         eprintln!("\nError: Invalid parameters or insufficient data");
         eprintln!("  nprices={}, n_blocks={}, max_lookback={}, n_returns={}", 
                  nprices, n_blocks, max_lookback, n_returns);
         process::exit(1);
     }
     
+    // Each training set must keep some cases after the embargo around a test block
+    if 2 * embargo >= n_returns / n_blocks {
+        eprintln!(
+            "\nError: embargo-bars={} leaves no training cases in blocks of about {} returns",
+            embargo,
+            n_returns / n_blocks
+        );
+        process::exit(1);
+    }
+    
     println!(
         "\n\nnprices={}  n_blocks={}  max_lookback={}  n_systems={}  n_returns={}",
         nprices, n_blocks, max_lookback, n_systems, n_returns
     );
+    if splitter != Splitter::Combinatorial || embargo > 0 {
+        println!("splitter={:?}  embargo_bars={}", splitter, embargo);
+    }
     
     // Compute returns matrix
     let returns = get_returns(&prices, max_lookback);
     
     // Perform cross-validation
-    let prob = cscvcore(n_returns, n_systems, n_blocks, &returns);
+    let report = cscv_report(n_returns, n_systems, n_blocks, &returns, splitter, embargo, true);
+    let prob = report.pbo;
     
     // Find return of grand best system
     let mut best_crit = 0.0;
+    let mut best = 0;
     for i in 0..n_systems {
         let start_idx = i * n_returns;
         let end_idx = start_idx + n_returns;
         let crit = criter(&returns[start_idx..end_idx]);
         if i == 0 || crit > best_crit {
             best_crit = crit;
+            best = i;
         }
     }
     
+    // Deflate its Sharpe ratio for having been chosen from all the systems
+    let trial_sharpes: Vec<f64> = returns.chunks(n_returns).map(sharpe).collect();
+    let dsr = deflated_sharpe(&returns[best * n_returns..(best + 1) * n_returns], &trial_sharpes);
+    
     // Print results
     println!(
         "\n\nnprices={}  n_blocks={}  max_lookback={}  n_systems={}  n_returns={}",
@@ -133,4 +211,32 @@ fn main() {
         1000.0 * best_crit,
         prob
     );
+    
+    let logits = report.sorted_logits();
+    let quantile = |q: f64| logits[((logits.len() - 1) as f64 * q).round() as usize];
+    println!(
+        "Logits: min = {:.4}  25% = {:.4}  median = {:.4}  75% = {:.4}  max = {:.4}",
+        quantile(0.0),
+        quantile(0.25),
+        quantile(0.5),
+        quantile(0.75),
+        quantile(1.0)
+    );
+    println!(
+        "Performance degradation (OOS on IS): slope = {:.4}  1000 * intercept = {:.4}",
+        report.degradation_slope,
+        1000.0 * report.degradation_intercept
+    );
+    println!(
+        "Sharpe = {:.4}  Expected max of {} trials = {:.4}  Deflated Sharpe = {:.4}",
+        dsr.sharpe, dsr.trials, dsr.expected_max, dsr.dsr
+    );
+    
+    if let Some(path) = results {
+        if let Err(e) = write_results(&path, &report, &dsr, best) {
+            eprintln!("\nError writing results file {}: {}", path, e);
+            process::exit(1);
+        }
+        println!("Results written to {}", path);
+    }
 }
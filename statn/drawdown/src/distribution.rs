@@ -0,0 +1,297 @@
+use std::fs;
+use std::path::Path;
+
+use backtesting::{journal_trades, read_fills, TradeLog, TradeStats};
+
+use crate::random::{normal, unifrand};
+
+/// Distribution from which the study draws individual trade returns
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeDistribution {
+    /// Standard normal with its sign forced positive with probability `win_prob`.
+    /// This is the distribution of the original study.
+    SignedNormal { win_prob: f64 },
+    /// Resample uniformly from a fixed set of observed trade returns
+    Empirical(Vec<f64>),
+    /// Student's t with `df` degrees of freedom, shifted by `location` and scaled by `scale`
+    StudentT { df: usize, location: f64, scale: f64 },
+}
+
+impl TradeDistribution {
+    /// Draw one trade return using the thread-local RNG
+    pub fn draw(&self) -> f64 {
+        match self {
+            TradeDistribution::SignedNormal { win_prob } => {
+                let val = normal();
+                if unifrand() < *win_prob {
+                    val.abs()
+                } else {
+                    -val.abs()
+                }
+            }
+            TradeDistribution::Empirical(returns) => {
+                let k = (unifrand() * returns.len() as f64) as usize;
+                returns[k.min(returns.len() - 1)]
+            }
+            TradeDistribution::StudentT { df, location, scale } => {
+                // t = Z / sqrt(chi2(df) / df), chi2 built from df squared normals
+                let z = normal();
+                let chi2: f64 = (0..*df).map(|_| {
+                    let x = normal();
+                    x * x
+                }).sum();
+                location + scale * z / (chi2 / *df as f64).sqrt()
+            }
+        }
+    }
+
+    /// Mean and standard deviation of a single trade return
+    pub fn mean_std(&self) -> (f64, f64) {
+        match self {
+            TradeDistribution::SignedNormal { win_prob } => {
+                // E|Z| = sqrt(2 / pi) and E[Z^2] = 1
+                let mean = (2.0 * win_prob - 1.0) * (2.0 / std::f64::consts::PI).sqrt();
+                (mean, (1.0 - mean * mean).sqrt())
+            }
+            TradeDistribution::Empirical(returns) => {
+                let n = returns.len() as f64;
+                let mean = returns.iter().sum::<f64>() / n;
+                let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+                (mean, var.sqrt())
+            }
+            TradeDistribution::StudentT { df, location, scale } => {
+                (*location, scale * (*df as f64 / (*df as f64 - 2.0)).sqrt())
+            }
+        }
+    }
+
+    /// Empirical distribution of the per-trade log returns in a trade log.
+    ///
+    /// `return_pct` is converted to a log return so that trades add up the same
+    /// way the study accumulates its simulated trades.
+    pub fn from_trade_log(trades: &[TradeLog]) -> Result<Self, String> {
+        let returns: Vec<f64> = trades
+            .iter()
+            .map(|t| (1.0 + t.return_pct / 100.0).ln())
+            .filter(|r| r.is_finite())
+            .collect();
+        Self::empirical(returns)
+    }
+
+    /// Empirical distribution of a set of trade returns
+    pub fn empirical(returns: Vec<f64>) -> Result<Self, String> {
+        if returns.len() < 2 {
+            return Err("An empirical trade distribution needs at least 2 trades".to_string());
+        }
+        Ok(TradeDistribution::Empirical(returns))
+    }
+
+    /// Fit a Student's t with `df` degrees of freedom to a sample of trade returns.
+    ///
+    /// The location is the sample mean and the scale is chosen so that the
+    /// fitted variance, scale^2 * df / (df - 2), matches the sample variance.
+    pub fn fit_student_t(returns: &[f64], df: usize) -> Result<Self, String> {
+        if df < 3 {
+            return Err("Student-t degrees of freedom must be at least 3".to_string());
+        }
+        if returns.len() < 2 {
+            return Err("Fitting a Student-t needs at least 2 trades".to_string());
+        }
+
+        let n = returns.len() as f64;
+        let location = returns.iter().sum::<f64>() / n;
+        let var = returns.iter().map(|r| (r - location).powi(2)).sum::<f64>() / (n - 1.0);
+        let scale = (var * (df as f64 - 2.0) / df as f64).sqrt();
+
+        Ok(TradeDistribution::StudentT { df, location, scale })
+    }
+}
+
+/// Read trade returns from a file.
+///
+/// A `.json` file is read as an array of `TradeLog` records (as serialized by the
+/// backtesting package), or the `TradeStats` of a backtest with its trades, and
+/// converted to log returns, a `.csv` file as a journal of executed fills (see
+/// `backtesting::parse_fills`) matched into round trips, and any other file as
+/// whitespace-separated trade returns.
+pub fn load_trade_returns<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, String> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read trade file {}: {}", path.display(), e))?;
+
+    match read_trade_logs(path, &contents) {
+        Some(trades) => match TradeDistribution::from_trade_log(&trades?)? {
+            TradeDistribution::Empirical(returns) => Ok(returns),
+            _ => unreachable!(),
+        },
+        None => parse_numbers(path, &contents),
+    }
+}
+
+/// Read the profit or loss of each trade from a file, in the order traded.
+///
+/// Files are read as by `load_trade_returns`, but trade logs and journals give
+/// the `pnl` of each trade instead of its return; a plain file holds the
+/// P&Ls themselves.
+pub fn load_trade_pnls<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, String> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read trade file {}: {}", path.display(), e))?;
+
+    match read_trade_logs(path, &contents) {
+        Some(trades) => Ok(trades?.iter().map(|t| t.pnl).filter(|p| p.is_finite()).collect()),
+        None => parse_numbers(path, &contents),
+    }
+}
+
+/// Trades of a `.json` trade log or backtest, or of a `.csv` journal of fills;
+/// `None` for any other kind of file
+fn read_trade_logs(path: &Path, contents: &str) -> Option<Result<Vec<TradeLog>, String>> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        Some(
+            serde_json::from_str::<Vec<TradeLog>>(contents)
+                .or_else(|_| serde_json::from_str::<TradeStats>(contents).map(|stats| stats.trades))
+                .map_err(|e| format!("Invalid trade log {}: {}", path.display(), e)),
+        )
+    } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv")) {
+        Some(read_fills(path).map(|fills| journal_trades(&fills)))
+    } else {
+        None
+    }
+}
+
+/// Whitespace-separated numbers
+fn parse_numbers(path: &Path, contents: &str) -> Result<Vec<f64>, String> {
+    contents
+        .split_whitespace()
+        .map(|tok| {
+            tok.parse::<f64>()
+                .map_err(|_| format!("Invalid trade return '{}' in {}", tok, path.display()))
+        })
+        .collect()
+}
+
+/// Parse a distribution spec from the command line.
+///
+/// * `normal` - sign-flipped normal with the given win probability
+/// * `empirical:FILE` - resample the trades in FILE
+/// * `t:DF:FILE` - Student-t with DF degrees of freedom fitted to the trades in FILE
+pub fn parse_distribution(spec: &str, win_prob: f64) -> Result<TradeDistribution, String> {
+    let mut parts = spec.splitn(3, ':');
+    match parts.next().unwrap_or("") {
+        "normal" => Ok(TradeDistribution::SignedNormal { win_prob }),
+        "empirical" => {
+            let file = parts.next().ok_or("empirical needs a trade file: empirical:FILE")?;
+            let file = match parts.next() {
+                Some(rest) => format!("{}:{}", file, rest),
+                None => file.to_string(),
+            };
+            TradeDistribution::empirical(load_trade_returns(file)?)
+        }
+        "t" => {
+            let df = parts
+                .next()
+                .ok_or("t needs degrees of freedom and a trade file: t:DF:FILE")?
+                .parse::<usize>()
+                .map_err(|_| "Invalid Student-t degrees of freedom".to_string())?;
+            let file = parts.next().ok_or("t needs a trade file: t:DF:FILE")?;
+            TradeDistribution::fit_student_t(&load_trade_returns(file)?, df)
+        }
+        other => Err(format!("Unknown trade distribution '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::set_seed;
+
+    #[test]
+    fn test_empirical_draws_from_sample() {
+        set_seed(7);
+        let dist = TradeDistribution::empirical(vec![-1.0, 2.0, 3.5]).unwrap();
+        for _ in 0..100 {
+            let x = dist.draw();
+            assert!(x == -1.0 || x == 2.0 || x == 3.5);
+        }
+    }
+
+    #[test]
+    fn test_fit_student_t_matches_moments() {
+        let returns = vec![-2.0, -1.0, 0.0, 1.0, 2.0, 3.0];
+        let dist = TradeDistribution::fit_student_t(&returns, 5).unwrap();
+        match dist {
+            TradeDistribution::StudentT { df, location, scale } => {
+                assert_eq!(df, 5);
+                assert!((location - 0.5).abs() < 1e-12);
+                // Sample variance is 3.5
+                assert!((scale * scale * 5.0 / 3.0 - 3.5).abs() < 1e-12);
+            }
+            _ => panic!("expected Student-t"),
+        }
+        assert!(TradeDistribution::fit_student_t(&returns, 2).is_err());
+    }
+
+    #[test]
+    fn test_from_trade_log_uses_log_returns() {
+        let trade = TradeLog {
+            entry_index: 0,
+            entry_price: 100.0,
+            exit_index: 5,
+            exit_price: 110.0,
+            trade_type: "LONG".to_string(),
+            pnl: 10.0,
+            return_pct: 10.0,
+            slippage: 0.0,
+            exit_reason: Default::default(),
+        };
+        let dist = TradeDistribution::from_trade_log(&[trade.clone(), trade]).unwrap();
+        assert_eq!(dist, TradeDistribution::Empirical(vec![1.1_f64.ln(), 1.1_f64.ln()]));
+    }
+
+    #[test]
+    fn test_load_trade_pnls_from_backtest() {
+        let trade = TradeLog {
+            entry_index: 0,
+            entry_price: 100.0,
+            exit_index: 5,
+            exit_price: 95.0,
+            trade_type: "LONG".to_string(),
+            pnl: -50.0,
+            return_pct: -5.0,
+            slippage: 0.0,
+            exit_reason: Default::default(),
+        };
+        let path = std::env::temp_dir().join(format!("drawdown_pnls_{}.json", std::process::id()));
+        let json = serde_json::json!({
+            "initial_budget": 1000.0, "final_budget": 950.0, "total_pnl": -50.0, "roi_percent": -5.0,
+            "num_trades": 1, "num_wins": 0, "num_losses": 1, "win_rate": 0.0, "total_costs": 0.0,
+            "max_drawdown": 50.0, "sharpe_ratio": 0.0, "budget_history": [], "position_history": [],
+            "trades": [trade],
+        });
+        fs::write(&path, json.to_string()).unwrap();
+        let pnls = load_trade_pnls(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(pnls.unwrap(), vec![-50.0]);
+    }
+
+    #[test]
+    fn test_mean_std() {
+        let (mean, std) = TradeDistribution::SignedNormal { win_prob: 0.5 }.mean_std();
+        assert!(mean.abs() < 1e-12);
+        assert!((std - 1.0).abs() < 1e-12);
+
+        let (mean, std) = TradeDistribution::Empirical(vec![1.0, 3.0]).mean_std();
+        assert_eq!((mean, std), (2.0, 1.0));
+    }
+
+    #[test]
+    fn test_parse_distribution() {
+        assert_eq!(
+            parse_distribution("normal", 0.6).unwrap(),
+            TradeDistribution::SignedNormal { win_prob: 0.6 }
+        );
+        assert!(parse_distribution("cauchy", 0.5).is_err());
+        assert!(parse_distribution("t:abc:file.txt", 0.5).is_err());
+    }
+}
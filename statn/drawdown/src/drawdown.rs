@@ -1,10 +1,16 @@
-use crate::random::{normal, unifrand};
+use crate::distribution::TradeDistribution;
+use crate::random::unifrand;
+
+pub use statn::core::stats::find_quantile;
 
 /// Generate a set of trades using bootstrap sampling
+///
+/// When `make_changes` is set, a fresh history of `n_changes` trade returns is
+/// first drawn from `dist`.
 pub fn get_trades(
     n_changes: usize,
     n_trades: usize,
-    win_prob: f64,
+    dist: &TradeDistribution,
     make_changes: bool,
     changes: &mut Vec<f64>,
     trades: &mut Vec<f64>,
@@ -12,22 +18,19 @@ pub fn get_trades(
     if make_changes {
         changes.clear();
         for _ in 0..n_changes {
-            let mut val = normal();
-            if unifrand() < win_prob {
-                val = val.abs();
-            } else {
-                val = -val.abs();
-            }
-            changes.push(val);
+            changes.push(dist.draw());
         }
     }
 
-    // Bootstrap sample from changes
-    trades.clear();
-    for _ in 0..n_trades {
-        let k = (unifrand() * n_changes as f64) as usize;
-        let k = k.min(n_changes - 1);
-        trades.push(changes[k]);
+    resample(&changes[..n_changes], n_trades, trades);
+}
+
+/// Replace `sample` with `n` cases drawn with replacement from `source`
+fn resample(source: &[f64], n: usize, sample: &mut Vec<f64>) {
+    sample.clear();
+    for _ in 0..n {
+        let k = (unifrand() * source.len() as f64) as usize;
+        sample.push(source[k.min(source.len() - 1)]);
     }
 }
 
@@ -73,12 +76,7 @@ pub fn drawdown_quantiles(
     work.clear();
 
     for _ in 0..nboot {
-        bootsample.clear();
-        for _ in 0..n_trades {
-            let k = (unifrand() * n_changes as f64) as usize;
-            let k = k.min(n_changes - 1);
-            bootsample.push(b_changes[k]);
-        }
+        resample(&b_changes[..n_changes], n_trades, bootsample);
         work.push(drawdown(bootsample));
     }
 
@@ -92,13 +90,6 @@ pub fn drawdown_quantiles(
     (q001, q01, q05, q10)
 }
 
-/// Find a quantile from sorted data
-pub fn find_quantile(data: &[f64], frac: f64) -> f64 {
-    let k = ((frac * (data.len() + 1) as f64) as usize).saturating_sub(1);
-    let k = k.min(data.len() - 1);
-    data[k]
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,7 +129,8 @@ mod tests {
         let mut changes = Vec::new();
         let mut trades = Vec::new();
         
-        get_trades(100, 50, 0.5, true, &mut changes, &mut trades);
+        let dist = TradeDistribution::SignedNormal { win_prob: 0.5 };
+        get_trades(100, 50, &dist, true, &mut changes, &mut trades);
         
         assert_eq!(changes.len(), 100);
         assert_eq!(trades.len(), 50);
@@ -0,0 +1,158 @@
+//! Expected maximum drawdown of a strategy and the range it normally falls in.
+//!
+//! A realized drawdown alone says little: a system with a modest Sharpe ratio
+//! traded for years will see deep drawdowns without anything being wrong.
+//! Given the mean and volatility per period and the horizon, the drawdowns of
+//! many simulated normal paths give the expected maximum drawdown and its
+//! quantiles, and how unusual a realized drawdown is. The analytic continuous
+//! value of `expected_max_dd` is reported alongside as a cross-check; the
+//! simulation counts only the drawdown between periods, as `drawdown` does.
+
+use matlib::Mwc256;
+use rayon::prelude::*;
+
+use crate::analytic::expected_max_dd;
+use crate::drawdown::{drawdown, find_quantile};
+use crate::study::{rep_seed, DEFAULT_SEED};
+
+/// Returns of a strategy whose maximum drawdown is to be estimated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxDrawdownEstimator {
+    /// Mean return per period
+    pub mean: f64,
+    /// Standard deviation of return per period
+    pub sigma: f64,
+    /// Horizon in periods
+    pub n_periods: usize,
+    /// Number of simulated paths
+    pub reps: usize,
+    pub seed: u32,
+}
+
+/// Distribution of the maximum drawdown over the horizon
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaxDrawdownEstimate {
+    /// Expected maximum drawdown of the continuous path (Brownian motion)
+    pub analytic: f64,
+    /// Mean maximum drawdown of the simulated paths
+    pub expected: f64,
+    /// Maximum drawdown of each simulated path, sorted
+    pub simulated: Vec<f64>,
+}
+
+impl MaxDrawdownEstimator {
+    /// 10000 paths from the default seed
+    pub fn new(mean: f64, sigma: f64, n_periods: usize) -> Self {
+        MaxDrawdownEstimator { mean, sigma, n_periods, reps: 10_000, seed: DEFAULT_SEED }
+    }
+
+    /// From the Sharpe ratio per period (mean over standard deviation) and
+    /// the standard deviation per period
+    pub fn from_sharpe(sharpe: f64, sigma: f64, n_periods: usize) -> Self {
+        Self::new(sharpe * sigma, sigma, n_periods)
+    }
+
+    /// From the changes of an additive equity curve, over as many periods as
+    /// it has. Fails with fewer than 3 values.
+    pub fn from_equity(equity: &[f64]) -> Result<Self, String> {
+        if equity.len() < 3 {
+            return Err(format!("At least 3 equity values are needed, got {}", equity.len()));
+        }
+        let changes: Vec<f64> = equity.windows(2).map(|w| w[1] - w[0]).collect();
+        Ok(Self::from_returns(&changes))
+    }
+
+    /// From a sequence of returns, over as many periods as there are returns
+    pub fn from_returns(returns: &[f64]) -> Self {
+        let n = returns.len();
+        let mean = returns.iter().sum::<f64>() / n.max(1) as f64;
+        let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n.saturating_sub(1).max(1) as f64;
+        Self::new(mean, var.sqrt(), n)
+    }
+
+    /// Simulate the paths, in parallel; each path reseeds its own generator,
+    /// so the estimate depends only on the settings
+    pub fn estimate(&self) -> MaxDrawdownEstimate {
+        let mut simulated: Vec<f64> = (0..self.reps)
+            .into_par_iter()
+            .map_init(
+                || vec![0.0; self.n_periods],
+                |path, irep| {
+                    let mut rng = Mwc256::with_seed(rep_seed(self.seed, irep));
+                    for r in path.iter_mut() {
+                        *r = self.mean + self.sigma * rng.normal();
+                    }
+                    drawdown(path)
+                },
+            )
+            .collect();
+        simulated.sort_by(f64::total_cmp);
+
+        MaxDrawdownEstimate {
+            analytic: expected_max_dd(self.mean, self.sigma, self.n_periods as f64),
+            expected: simulated.iter().sum::<f64>() / simulated.len().max(1) as f64,
+            simulated,
+        }
+    }
+}
+
+impl MaxDrawdownEstimate {
+    /// Maximum drawdown not exceeded by fraction `q` of the paths
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.simulated.is_empty() {
+            return 0.0;
+        }
+        find_quantile(&self.simulated, q)
+    }
+
+    /// Fraction of the paths whose maximum drawdown is at least `realized`;
+    /// a small value means the realized drawdown is unusually deep for the
+    /// strategy's statistics
+    pub fn exceedance(&self, realized: f64) -> f64 {
+        if self.simulated.is_empty() {
+            return 0.0;
+        }
+        let below = self.simulated.partition_point(|&dd| dd < realized);
+        (self.simulated.len() - below) as f64 / self.simulated.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_against_analytic() {
+        // The discrete paths miss the extremes between periods, by roughly
+        // one sigma, so they fall a little below the continuous value
+        let est = MaxDrawdownEstimator { reps: 4000, ..MaxDrawdownEstimator::new(0.05, 1.0, 250) }.estimate();
+        assert!(est.expected < est.analytic && est.expected > est.analytic - 1.5, "{} vs {}", est.expected, est.analytic);
+        assert!(est.quantile(0.5) < est.quantile(0.95) && est.quantile(0.95) < est.quantile(0.99));
+
+        assert!((est.exceedance(est.quantile(0.9)) - 0.1).abs() < 0.01);
+        assert_eq!(est.exceedance(0.0), 1.0);
+        assert_eq!(est.exceedance(f64::INFINITY), 0.0);
+
+        // Independent of the thread count
+        let again = MaxDrawdownEstimator { reps: 4000, ..MaxDrawdownEstimator::new(0.05, 1.0, 250) };
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        assert_eq!(pool.install(|| again.estimate()), est);
+    }
+
+    #[test]
+    fn test_from_equity_and_sharpe() {
+        let equity = [0.0, 1.0, 0.5, 2.0, 1.0, 3.0];
+        let est = MaxDrawdownEstimator::from_equity(&equity).unwrap();
+        assert_eq!(est.n_periods, 5);
+        assert!((est.mean - 0.6).abs() < 1e-12);
+        assert!(MaxDrawdownEstimator::from_equity(&[1.0, 2.0]).is_err());
+
+        let by_sharpe = MaxDrawdownEstimator::from_sharpe(0.1, 2.0, 100);
+        assert_eq!((by_sharpe.mean, by_sharpe.sigma), (0.2, 2.0));
+
+        // A better Sharpe ratio means shallower drawdowns
+        let good = MaxDrawdownEstimator { reps: 1000, ..MaxDrawdownEstimator::from_sharpe(0.2, 1.0, 250) }.estimate();
+        let poor = MaxDrawdownEstimator { reps: 1000, ..MaxDrawdownEstimator::from_sharpe(0.0, 1.0, 250) }.estimate();
+        assert!(good.quantile(0.95) < poor.quantile(0.95));
+    }
+}
@@ -1,7 +1,20 @@
 pub mod random;
+pub mod analytic;
+pub mod distribution;
 pub mod drawdown;
+pub mod expected;
+pub mod normalize;
+pub mod profile;
+pub mod reorder;
+pub mod study;
 
 pub use random::{set_seed, unifrand, normal};
+pub use analytic::expected_max_dd;
+pub use expected::{MaxDrawdownEstimate, MaxDrawdownEstimator};
+pub use profile::{DrawdownEpisode, DrawdownProfile};
+pub use reorder::{ReorderMethod, ReorderOutcome, TradeReorderSim};
+pub use normalize::{Horizon, normalize_drawdown, scale_drawdown, scale_drawdown_sqrt, trades_per_year};
+pub use distribution::{TradeDistribution, load_trade_pnls, load_trade_returns, parse_distribution};
 pub use drawdown::{
     get_trades, mean_return, drawdown as calc_drawdown,
     drawdown_quantiles, find_quantile,
@@ -1,16 +1,28 @@
 use std::env;
 use std::process;
+use std::sync::Mutex;
 
-use ::drawdown::*;
-
-const POP_MULT: usize = 1000;
+use ::drawdown::{
+    calc_drawdown, expected_max_dd, load_trade_pnls, load_trade_returns, parse_distribution, MaxDrawdownEstimator,
+    ReorderMethod, TradeReorderSim,
+};
+use ::drawdown::study::{run_study, CountSnapshot, StudyParams, DEFAULT_SEED, POP_MULT};
 
 fn main() {
     // Parse command line arguments
     let args: Vec<String> = env::args().collect();
     
-    if args.len() != 8 {
-        eprintln!("\nUsage: {} Nchanges Ntrades WinProb BoundConf BootstrapReps QuantileReps TestReps", args[0]);
+    if args.get(1).map(String::as_str) == Some("expected") {
+        run_expected(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("reorder") {
+        run_reorder(&args[2..]);
+        return;
+    }
+    
+    if !(8..=10).contains(&args.len()) {
+        eprintln!("\nUsage: {} Nchanges Ntrades WinProb BoundConf BootstrapReps QuantileReps TestReps [Seed [Dist]]", args[0]);
         eprintln!("  Nchanges - Number of price changes");
         eprintln!("  Ntrades - Number of trades");
         eprintln!("  WinProb - Probability of winning");
@@ -18,6 +30,22 @@ fn main() {
         eprintln!("  BootstrapReps - Number of bootstrap reps");
         eprintln!("  QuantileReps - Number of bootstrap reps for finding drawdown quantiles");
         eprintln!("  TestReps - Number of testing reps for this study");
+        eprintln!("  Seed - Optional master seed (default {}); results are reproducible for a given seed", DEFAULT_SEED);
+        eprintln!("  Dist - Optional trade distribution (default normal):");
+        eprintln!("         normal          sign-flipped normal with WinProb");
+        eprintln!("         empirical:FILE  resample trades from FILE (WinProb ignored)");
+        eprintln!("         t:DF:FILE       Student-t with DF degrees of freedom fitted to FILE (WinProb ignored)");
+        eprintln!("         FILE is a TradeLog .json array, a .csv journal of fills");
+        eprintln!("         (timestamp,side,qty,price,fee) or whitespace-separated trade returns");
+        eprintln!("\n   or: {} expected Mean Sigma Ntrades [Realized]", args[0]);
+        eprintln!("   or: {} expected FILE [Realized]", args[0]);
+        eprintln!("  Expected maximum drawdown and its quantiles for a trade mean and std (or those");
+        eprintln!("  of the trades in FILE), and how unusual a realized drawdown is (by default the");
+        eprintln!("  drawdown of the trades in FILE)");
+        eprintln!("\n   or: {} reorder FILE Capital [Confidence [Method [Reps [Seed]]]]", args[0]);
+        eprintln!("  Ruin probability from Capital, drawdown quantiles and the capital required at");
+        eprintln!("  Confidence (default 0.95) over Reps (default 10000) reorderings of the trade P&Ls");
+        eprintln!("  in FILE; Method is permute (default) or bootstrap");
         process::exit(1);
     }
 
@@ -59,232 +87,281 @@ fn main() {
         process::exit(1);
     }
 
+    let master_seed: u32 = match args.get(8) {
+        Some(arg) => arg.parse().expect("Invalid Seed"),
+        None => DEFAULT_SEED,
+    };
+
+    let dist_spec = args.get(9).map(String::as_str).unwrap_or("normal");
+    let distribution = match parse_distribution(dist_spec, win_prob) {
+        Ok(dist) => dist,
+        Err(e) => {
+            eprintln!("\nERROR... {}", e);
+            process::exit(1);
+        }
+    };
+
+    let params = StudyParams {
+        n_changes,
+        n_trades,
+        distribution,
+        bound_conf,
+        bootstrap_reps,
+        quantile_reps,
+        test_reps,
+    };
+
     // Open output buffer
     let mut buffer = String::new();
 
     use std::fmt::Write;
     writeln!(buffer, "\nChanges = {}", n_changes).unwrap();
     writeln!(buffer, "Trades = {}", n_trades).unwrap();
+    writeln!(buffer, "Trade distribution = {}", dist_spec).unwrap();
     writeln!(buffer, "Win probability = {:.4}", win_prob).unwrap();
     writeln!(buffer, "DD bound confidence = {:.4}", bound_conf).unwrap();
     writeln!(buffer, "Bootstrap reps = {}", bootstrap_reps).unwrap();
     writeln!(buffer, "Quantile reps = {}", quantile_reps).unwrap();
     writeln!(buffer, "Test reps = {}", test_reps).unwrap();
+    writeln!(buffer, "Seed = {}", master_seed).unwrap();
 
-    // Allocate memory
-    let mut changes = Vec::with_capacity(n_changes);
-    let mut bootsample = Vec::with_capacity(n_trades);
-    let mut trades = Vec::with_capacity(n_changes);
-    let mut incorrect_meanrets = Vec::with_capacity(bootstrap_reps);
-    let mut incorrect_drawdowns = Vec::with_capacity(bootstrap_reps);
-    let mut correct_q001 = Vec::with_capacity(bootstrap_reps);
-    let mut correct_q01 = Vec::with_capacity(bootstrap_reps);
-    let mut correct_q05 = Vec::with_capacity(bootstrap_reps);
-    let mut correct_q10 = Vec::with_capacity(bootstrap_reps);
-    let mut work = Vec::with_capacity(quantile_reps);
-
-    // Counters
-    let mut count_incorrect_meanret_001 = 0;
-    let mut count_incorrect_meanret_01 = 0;
-    let mut count_incorrect_meanret_05 = 0;
-    let mut count_incorrect_meanret_10 = 0;
-    let mut count_incorrect_drawdown_001 = 0;
-    let mut count_incorrect_drawdown_01 = 0;
-    let mut count_incorrect_drawdown_05 = 0;
-    let mut count_incorrect_drawdown_10 = 0;
-    let mut count_correct_001 = 0;
-    let mut count_correct_01 = 0;
-    let mut count_correct_05 = 0;
-    let mut count_correct_10 = 0;
+    // Analytic cross-check for the bootstrap quantiles
+    let (trade_mean, trade_std) = params.distribution.mean_std();
+    writeln!(buffer, "Trade mean = {:.5}  std = {:.5}", trade_mean, trade_std).unwrap();
+    writeln!(buffer, "Expected max DD over {} trades (Brownian approximation) = {:.5}",
+             n_trades, expected_max_dd(trade_mean, trade_std, n_trades as f64)).unwrap();
 
-    // Main test loop
-    for itest in 1..=test_reps {
-        // Incorrect method test
-        incorrect_meanrets.clear();
-        incorrect_drawdowns.clear();
+    // Test reps run in parallel; each reseeds its own RNG, so the totals are
+    // identical for any thread count. Progress reports may arrive out of
+    // order, so the log is only rewritten by a report past the last one logged.
+    let last_logged = Mutex::new(0);
+    let counts = run_study(&params, master_seed, |done, counts| {
+        print!("{}", format_progress(done, counts));
 
-        for iboot in 0..bootstrap_reps {
-            let make_changes = iboot == 0;
-            get_trades(n_changes, n_trades, win_prob, make_changes, &mut changes, &mut trades);
-            incorrect_meanrets.push(mean_return(&trades));
-            incorrect_drawdowns.push(calc_drawdown(&trades));
+        if done % 100 == 0 {
+            let mut last_logged = last_logged.lock().unwrap();
+            if done > *last_logged {
+                *last_logged = done;
+                let log = format!("{}{}", buffer, format_log(done, counts));
+                statn::core::io::write::write_file("DRAWDOWN.LOG", log).expect("Failed to write DRAWDOWN.LOG");
+            }
         }
+    });
 
-        // Sort and find quantiles
-        incorrect_meanrets.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let incorrect_meanret_001 = find_quantile(&incorrect_meanrets, 0.001);
-        let incorrect_meanret_01 = find_quantile(&incorrect_meanrets, 0.01);
-        let incorrect_meanret_05 = find_quantile(&incorrect_meanrets, 0.05);
-        let incorrect_meanret_10 = find_quantile(&incorrect_meanrets, 0.1);
+    buffer.push_str(&format_log(test_reps, &counts));
+    statn::core::io::write::write_file("DRAWDOWN.LOG", &buffer).expect("Failed to write DRAWDOWN.LOG");
 
-        incorrect_drawdowns.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let incorrect_drawdown_001 = find_quantile(&incorrect_drawdowns, 0.999);
-        let incorrect_drawdown_01 = find_quantile(&incorrect_drawdowns, 0.99);
-        let incorrect_drawdown_05 = find_quantile(&incorrect_drawdowns, 0.95);
-        let incorrect_drawdown_10 = find_quantile(&incorrect_drawdowns, 0.9);
+    println!("\nResults written to DRAWDOWN.LOG");
+}
 
-        // Correct method test
-        correct_q001.clear();
-        correct_q01.clear();
-        correct_q05.clear();
-        correct_q10.clear();
+/// Estimate the expected maximum drawdown from `Mean Sigma Ntrades` or a
+/// trade file, and place a realized drawdown in its distribution
+fn run_expected(args: &[String]) {
+    let parse = |arg: &String, name: &str| -> f64 {
+        arg.parse().unwrap_or_else(|_| {
+            eprintln!("\nERROR... Invalid {}", name);
+            process::exit(1);
+        })
+    };
 
-        for iboot in 0..bootstrap_reps {
-            let make_changes = iboot == 0;
-            get_trades(n_changes, n_changes, win_prob, make_changes, &mut changes, &mut trades);
-            let (q001, q01, q05, q10) = drawdown_quantiles(
-                n_changes,
-                n_trades,
-                &trades,
-                quantile_reps,
-                &mut bootsample,
-                &mut work,
-            );
-            correct_q001.push(q001);
-            correct_q01.push(q01);
-            correct_q05.push(q05);
-            correct_q10.push(q10);
+    let (estimator, realized) = match args.len() {
+        1 | 2 if args[0].parse::<f64>().is_err() => {
+            let trades = load_trade_returns(&args[0]).unwrap_or_else(|e| {
+                eprintln!("\nERROR... {}", e);
+                process::exit(1);
+            });
+            if trades.len() < 2 {
+                eprintln!("\nERROR... {} has fewer than 2 trades", args[0]);
+                process::exit(1);
+            }
+            let realized = args.get(1).map_or_else(|| calc_drawdown(&trades), |arg| parse(arg, "Realized"));
+            (MaxDrawdownEstimator::from_returns(&trades), Some(realized))
+        }
+        3 | 4 => {
+            let n_trades = parse(&args[2], "Ntrades");
+            if n_trades < 2.0 || n_trades.fract() != 0.0 {
+                eprintln!("\nERROR... Ntrades must be an integer of at least 2");
+                process::exit(1);
+            }
+            let estimator = MaxDrawdownEstimator::new(parse(&args[0], "Mean"), parse(&args[1], "Sigma"), n_trades as usize);
+            (estimator, args.get(3).map(|arg| parse(arg, "Realized")))
+        }
+        _ => {
+            eprintln!("\nUsage: drawdown expected Mean Sigma Ntrades [Realized]");
+            eprintln!("   or: drawdown expected FILE [Realized]");
+            process::exit(1);
         }
+    };
+    if estimator.sigma < 0.0 {
+        eprintln!("\nERROR... Sigma must not be negative");
+        process::exit(1);
+    }
 
-        // Sort and find bounds
-        correct_q001.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        correct_q01.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        correct_q05.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        correct_q10.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let estimate = estimator.estimate();
+    println!(
+        "\nTrade mean = {:.5}  std = {:.5}  Sharpe per trade = {:.4}  trades = {}",
+        estimator.mean,
+        estimator.sigma,
+        if estimator.sigma > 0.0 { estimator.mean / estimator.sigma } else { 0.0 },
+        estimator.n_periods
+    );
+    println!("Expected max DD (Brownian approximation) = {:.5}", estimate.analytic);
+    println!("Expected max DD ({} simulated paths) = {:.5}", estimator.reps, estimate.expected);
+    for q in [0.5, 0.9, 0.95, 0.99] {
+        println!("  {:4.1}% of paths stay within {:.5}", 100.0 * q, estimate.quantile(q));
+    }
 
-        let correct_q001_bound = find_quantile(&correct_q001, 1.0 - (1.0 - bound_conf) / 2.0);
-        let correct_q01_bound = find_quantile(&correct_q01, 1.0 - (1.0 - bound_conf) / 2.0);
-        let correct_q05_bound = find_quantile(&correct_q05, bound_conf);
-        let correct_q10_bound = find_quantile(&correct_q10, bound_conf);
+    if let Some(realized) = realized {
+        let exceedance = estimate.exceedance(realized);
+        println!(
+            "\nRealized max DD = {:.5}: {:.2}% of paths go at least this deep",
+            realized,
+            100.0 * exceedance
+        );
+        if exceedance < 0.05 {
+            println!("Unusually deep for these statistics: the strategy may have changed");
+        } else {
+            println!("Within the normal range for these statistics");
+        }
+    }
+}
 
-        // Population test
-        for _ in 0..POP_MULT {
-            trades.clear();
-            for _ in 0..n_trades {
-                let mut val = normal();
-                if unifrand() < win_prob {
-                    val = val.abs();
-                } else {
-                    val = -val.abs();
-                }
-                trades.push(val);
-            }
+/// Reorder the real trades of a backtest and report the risk of the
+/// starting capital
+fn run_reorder(args: &[String]) {
+    if !(2..=6).contains(&args.len()) {
+        eprintln!("\nUsage: drawdown reorder FILE Capital [Confidence [Method [Reps [Seed]]]]");
+        eprintln!("  FILE - TradeLog .json array or backtest .json, .csv journal of fills, or trade P&Ls");
+        eprintln!("  Capital - Starting capital, in the units of the trade P&Ls");
+        eprintln!("  Confidence - Confidence of the required capital (default 0.95)");
+        eprintln!("  Method - permute (default) or bootstrap");
+        eprintln!("  Reps - Number of simulated paths (default 10000)");
+        eprintln!("  Seed - Master seed (default {})", DEFAULT_SEED);
+        process::exit(1);
+    }
+    let fail = |msg: String| -> ! {
+        eprintln!("\nERROR... {}", msg);
+        process::exit(1);
+    };
 
-            // Test mean return
-            let crit = mean_return(&trades);
-            if crit < incorrect_meanret_001 {
-                count_incorrect_meanret_001 += 1;
-            }
-            if crit < incorrect_meanret_01 {
-                count_incorrect_meanret_01 += 1;
-            }
-            if crit < incorrect_meanret_05 {
-                count_incorrect_meanret_05 += 1;
-            }
-            if crit < incorrect_meanret_10 {
-                count_incorrect_meanret_10 += 1;
-            }
+    let pnls = load_trade_pnls(&args[0]).unwrap_or_else(|e| fail(e));
+    if pnls.len() < 2 {
+        fail(format!("{} has fewer than 2 trades", args[0]));
+    }
+    let capital: f64 = args[1].parse().unwrap_or_else(|_| fail("Invalid Capital".to_string()));
+    let confidence: f64 = match args.get(2) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| fail("Invalid Confidence".to_string())),
+        None => 0.95,
+    };
+    if !(0.0..1.0).contains(&confidence) {
+        fail("Confidence must be at least 0 and below 1".to_string());
+    }
+    let mut sim = TradeReorderSim::new(pnls);
+    if let Some(arg) = args.get(3) {
+        sim.method = arg.parse::<ReorderMethod>().unwrap_or_else(|e| fail(e));
+    }
+    if let Some(arg) = args.get(4) {
+        sim.reps = arg.parse().unwrap_or_else(|_| fail("Invalid Reps".to_string()));
+        if sim.reps < 10 {
+            fail("Reps must be at least 10".to_string());
+        }
+    }
+    if let Some(arg) = args.get(5) {
+        sim.seed = arg.parse().unwrap_or_else(|_| fail("Invalid Seed".to_string()));
+    }
 
-            // Test drawdown (incorrect method)
-            let crit = calc_drawdown(&trades);
-            if crit > incorrect_drawdown_001 {
-                count_incorrect_drawdown_001 += 1;
-            }
-            if crit > incorrect_drawdown_01 {
-                count_incorrect_drawdown_01 += 1;
-            }
-            if crit > incorrect_drawdown_05 {
-                count_incorrect_drawdown_05 += 1;
-            }
-            if crit > incorrect_drawdown_10 {
-                count_incorrect_drawdown_10 += 1;
-            }
+    let outcome = sim.run();
+    let (realized_dd, realized_loss, total) = sim.realized();
+    println!("\nTrades = {}  total P&L = {:.2}  method = {}  paths = {}", sim.pnls.len(), total, sim.method, sim.reps);
+    println!("Backtest max DD = {:.2}  deepest loss below start = {:.2}", realized_dd, realized_loss);
+    println!("\nMax drawdown of the reordered trades");
+    for q in [0.5, 0.9, 0.95, 0.99] {
+        println!("  {:4.1}% of paths stay within {:.2}", 100.0 * q, outcome.drawdown_quantile(q));
+    }
+    if sim.method == ReorderMethod::Bootstrap {
+        println!("Total P&L 5% / 50% / 95% = {:.2} / {:.2} / {:.2}",
+                 outcome.final_quantile(0.05), outcome.final_quantile(0.5), outcome.final_quantile(0.95));
+    }
+    println!("\nProbability of ruin from capital {:.2} = {:.4}", capital, outcome.ruin_probability(capital));
+    println!("Capital required at {:.1}% confidence = {:.2}", 100.0 * confidence, outcome.required_capital(confidence));
+}
 
-            // Test drawdown (correct method)
-            if crit > correct_q001_bound {
-                count_correct_001 += 1;
-            }
-            if crit > correct_q01_bound {
-                count_correct_01 += 1;
-            }
-            if crit > correct_q05_bound {
-                count_correct_05 += 1;
-            }
-            if crit > correct_q10_bound {
-                count_correct_10 += 1;
-            }
-        }
+/// Format the screen progress table after `done` test reps
+fn format_progress(done: usize, counts: &CountSnapshot) -> String {
+    use std::fmt::Write;
+    let n = (POP_MULT * done) as f64;
+    let mut out = String::new();
 
-        // Print progress to screen
-        println!("\n\n{}", itest);
-        println!("Mean return");
-        println!("  Actual    Incorrect");
-        println!("   0.001   {:.5}", count_incorrect_meanret_001 as f64 / (POP_MULT * itest) as f64);
-        println!("   0.01    {:.5}", count_incorrect_meanret_01 as f64 / (POP_MULT * itest) as f64);
-        println!("   0.05    {:.5}", count_incorrect_meanret_05 as f64 / (POP_MULT * itest) as f64);
-        println!("   0.1     {:.5}", count_incorrect_meanret_10 as f64 / (POP_MULT * itest) as f64);
+    writeln!(out, "\n\n{}", done).unwrap();
+    writeln!(out, "Mean return").unwrap();
+    writeln!(out, "  Actual    Incorrect").unwrap();
+    writeln!(out, "   0.001   {:.5}", counts.incorrect_meanret[0] as f64 / n).unwrap();
+    writeln!(out, "   0.01    {:.5}", counts.incorrect_meanret[1] as f64 / n).unwrap();
+    writeln!(out, "   0.05    {:.5}", counts.incorrect_meanret[2] as f64 / n).unwrap();
+    writeln!(out, "   0.1     {:.5}", counts.incorrect_meanret[3] as f64 / n).unwrap();
 
-        println!("\nDrawdown");
-        println!("  Actual    Incorrect  Correct");
-        println!("   0.001   {:.5}  {:.5}",
-                 count_incorrect_drawdown_001 as f64 / (POP_MULT * itest) as f64,
-                 count_correct_001 as f64 / (POP_MULT * itest) as f64);
-        println!("   0.01    {:.5}  {:.5}",
-                 count_incorrect_drawdown_01 as f64 / (POP_MULT * itest) as f64,
-                 count_correct_01 as f64 / (POP_MULT * itest) as f64);
-        println!("   0.05    {:.5}  {:.5}",
-                 count_incorrect_drawdown_05 as f64 / (POP_MULT * itest) as f64,
-                 count_correct_05 as f64 / (POP_MULT * itest) as f64);
-        println!("   0.1     {:.5}  {:.5}",
-                 count_incorrect_drawdown_10 as f64 / (POP_MULT * itest) as f64,
-                 count_correct_10 as f64 / (POP_MULT * itest) as f64);
+    writeln!(out, "\nDrawdown").unwrap();
+    writeln!(out, "  Actual    Incorrect  Correct").unwrap();
+    writeln!(out, "   0.001   {:.5}  {:.5}",
+             counts.incorrect_drawdown[0] as f64 / n,
+             counts.correct[0] as f64 / n).unwrap();
+    writeln!(out, "   0.01    {:.5}  {:.5}",
+             counts.incorrect_drawdown[1] as f64 / n,
+             counts.correct[1] as f64 / n).unwrap();
+    writeln!(out, "   0.05    {:.5}  {:.5}",
+             counts.incorrect_drawdown[2] as f64 / n,
+             counts.correct[2] as f64 / n).unwrap();
+    writeln!(out, "   0.1     {:.5}  {:.5}",
+             counts.incorrect_drawdown[3] as f64 / n,
+             counts.correct[3] as f64 / n).unwrap();
 
-        // Write results to buffer
-        if itest % 100 == 0 || itest == test_reps {
-            writeln!(buffer, "\n\n").unwrap();
-            writeln!(buffer, "\nMean return worse (Ratio)").unwrap();
-            writeln!(buffer, "  Actual       Incorrect").unwrap();
-            writeln!(buffer, "   0.001   {:.5} ({:.2})",
-                     count_incorrect_meanret_001 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_meanret_001 as f64 / (POP_MULT * itest) as f64) / 0.001).unwrap();
-            writeln!(buffer, "   0.01    {:.5} ({:.2})",
-                     count_incorrect_meanret_01 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_meanret_01 as f64 / (POP_MULT * itest) as f64) / 0.01).unwrap();
-            writeln!(buffer, "   0.05    {:.5} ({:.2})",
-                     count_incorrect_meanret_05 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_meanret_05 as f64 / (POP_MULT * itest) as f64) / 0.05).unwrap();
-            writeln!(buffer, "   0.1     {:.5} ({:.2})",
-                     count_incorrect_meanret_10 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_meanret_10 as f64 / (POP_MULT * itest) as f64) / 0.1).unwrap();
+    out
+}
 
-            writeln!(buffer, "\nDrawdown worse (Ratio)").unwrap();
-            writeln!(buffer, "  Actual     Incorrect          Correct").unwrap();
-            writeln!(buffer, "   0.001   {:.5} ({:.2})  {:.5} ({:.2})",
-                     count_incorrect_drawdown_001 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_drawdown_001 as f64 / (POP_MULT * itest) as f64) / 0.001,
-                     count_correct_001 as f64 / (POP_MULT * itest) as f64,
-                     (count_correct_001 as f64 / (POP_MULT * itest) as f64) / 0.001).unwrap();
-            writeln!(buffer, "   0.01    {:.5} ({:.2})  {:.5} ({:.2})",
-                     count_incorrect_drawdown_01 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_drawdown_01 as f64 / (POP_MULT * itest) as f64) / 0.01,
-                     count_correct_01 as f64 / (POP_MULT * itest) as f64,
-                     (count_correct_01 as f64 / (POP_MULT * itest) as f64) / 0.01).unwrap();
-            writeln!(buffer, "   0.05    {:.5} ({:.2})  {:.5} ({:.2})",
-                     count_incorrect_drawdown_05 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_drawdown_05 as f64 / (POP_MULT * itest) as f64) / 0.05,
-                     count_correct_05 as f64 / (POP_MULT * itest) as f64,
-                     (count_correct_05 as f64 / (POP_MULT * itest) as f64) / 0.05).unwrap();
-            writeln!(buffer, "   0.1     {:.5} ({:.2})  {:.5} ({:.2})",
-                     count_incorrect_drawdown_10 as f64 / (POP_MULT * itest) as f64,
-                     (count_incorrect_drawdown_10 as f64 / (POP_MULT * itest) as f64) / 0.1,
-                     count_correct_10 as f64 / (POP_MULT * itest) as f64,
-                     (count_correct_10 as f64 / (POP_MULT * itest) as f64) / 0.10).unwrap();
-            
-            // Write to file (overwrite with current buffer)
-            statn::core::io::write::write_file("DRAWDOWN.LOG", &buffer).expect("Failed to write DRAWDOWN.LOG");
-        }
-    }
+/// Format the DRAWDOWN.LOG results section after `done` test reps
+fn format_log(done: usize, counts: &CountSnapshot) -> String {
+    use std::fmt::Write;
+    let n = (POP_MULT * done) as f64;
+    let mut out = String::new();
 
-    println!("\nResults written to DRAWDOWN.LOG");
+    writeln!(out, "\n\n").unwrap();
+    writeln!(out, "\nMean return worse (Ratio)").unwrap();
+    writeln!(out, "  Actual       Incorrect").unwrap();
+    writeln!(out, "   0.001   {:.5} ({:.2})",
+             counts.incorrect_meanret[0] as f64 / n,
+             (counts.incorrect_meanret[0] as f64 / n) / 0.001).unwrap();
+    writeln!(out, "   0.01    {:.5} ({:.2})",
+             counts.incorrect_meanret[1] as f64 / n,
+             (counts.incorrect_meanret[1] as f64 / n) / 0.01).unwrap();
+    writeln!(out, "   0.05    {:.5} ({:.2})",
+             counts.incorrect_meanret[2] as f64 / n,
+             (counts.incorrect_meanret[2] as f64 / n) / 0.05).unwrap();
+    writeln!(out, "   0.1     {:.5} ({:.2})",
+             counts.incorrect_meanret[3] as f64 / n,
+             (counts.incorrect_meanret[3] as f64 / n) / 0.1).unwrap();
+
+    writeln!(out, "\nDrawdown worse (Ratio)").unwrap();
+    writeln!(out, "  Actual     Incorrect          Correct").unwrap();
+    writeln!(out, "   0.001   {:.5} ({:.2})  {:.5} ({:.2})",
+             counts.incorrect_drawdown[0] as f64 / n,
+             (counts.incorrect_drawdown[0] as f64 / n) / 0.001,
+             counts.correct[0] as f64 / n,
+             (counts.correct[0] as f64 / n) / 0.001).unwrap();
+    writeln!(out, "   0.01    {:.5} ({:.2})  {:.5} ({:.2})",
+             counts.incorrect_drawdown[1] as f64 / n,
+             (counts.incorrect_drawdown[1] as f64 / n) / 0.01,
+             counts.correct[1] as f64 / n,
+             (counts.correct[1] as f64 / n) / 0.01).unwrap();
+    writeln!(out, "   0.05    {:.5} ({:.2})  {:.5} ({:.2})",
+             counts.incorrect_drawdown[2] as f64 / n,
+             (counts.incorrect_drawdown[2] as f64 / n) / 0.05,
+             counts.correct[2] as f64 / n,
+             (counts.correct[2] as f64 / n) / 0.05).unwrap();
+    writeln!(out, "   0.1     {:.5} ({:.2})  {:.5} ({:.2})",
+             counts.incorrect_drawdown[3] as f64 / n,
+             (counts.incorrect_drawdown[3] as f64 / n) / 0.1,
+             counts.correct[3] as f64 / n,
+             (counts.correct[3] as f64 / n) / 0.10).unwrap();
 
+    out
 }
@@ -0,0 +1,125 @@
+//! Rescaling drawdowns to a common horizon.
+//!
+//! A drawdown quantile is only meaningful together with the number of trades it
+//! was computed over: a system that trades ten times as often will show deeper
+//! drawdowns over the same calendar span even with identical per-trade
+//! statistics. These helpers move a drawdown from the horizon it was measured on
+//! to a common one (a fixed number of trades, or a fixed number of years) so
+//! strategies with very different trade frequencies can be compared.
+
+use crate::analytic::expected_max_dd;
+
+/// Common horizon to express drawdowns in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Horizon {
+    /// A fixed number of trades, e.g. `Trades(100.0)` for "per 100 trades"
+    Trades(f64),
+    /// A fixed calendar span in years; needs the strategy's trade frequency
+    Years(f64),
+}
+
+impl Horizon {
+    /// Number of trades this horizon spans for a strategy making
+    /// `trades_per_year` trades per year
+    pub fn n_trades(&self, trades_per_year: f64) -> f64 {
+        match *self {
+            Horizon::Trades(n) => n,
+            Horizon::Years(years) => years * trades_per_year,
+        }
+    }
+
+    /// Short label for report headings
+    pub fn label(&self) -> String {
+        match *self {
+            Horizon::Trades(n) => format!("per {} trades", n),
+            Horizon::Years(1.0) => "per year".to_string(),
+            Horizon::Years(years) => format!("per {} years", years),
+        }
+    }
+}
+
+/// Annual trade frequency of a strategy that made `n_trades` trades over
+/// `n_bars` bars, with `bars_per_year` bars in a year (252 for daily data)
+pub fn trades_per_year(n_trades: usize, n_bars: usize, bars_per_year: f64) -> f64 {
+    if n_bars == 0 {
+        return 0.0;
+    }
+    n_trades as f64 * bars_per_year / n_bars as f64
+}
+
+/// Rescale a drawdown by the square root of the trade-count ratio.
+///
+/// Exact for a driftless random walk, and a reasonable default when the trade
+/// statistics are unknown.
+pub fn scale_drawdown_sqrt(dd: f64, from_trades: f64, to_trades: f64) -> f64 {
+    if from_trades <= 0.0 {
+        return dd;
+    }
+    dd * (to_trades / from_trades).max(0.0).sqrt()
+}
+
+/// Rescale a drawdown measured over `from_trades` trades to `to_trades` trades.
+///
+/// The scale factor is the ratio of expected maximum drawdowns of a Brownian
+/// motion with the given per-trade `mean` and `sigma`, so a profitable system's
+/// drawdown grows more slowly than the square root of the horizon and a losing
+/// system's grows faster. Falls back to square-root scaling when the expected
+/// drawdown at the source horizon is zero.
+pub fn scale_drawdown(dd: f64, mean: f64, sigma: f64, from_trades: f64, to_trades: f64) -> f64 {
+    let from = expected_max_dd(mean, sigma, from_trades);
+    if from <= 0.0 {
+        return scale_drawdown_sqrt(dd, from_trades, to_trades);
+    }
+    dd * expected_max_dd(mean, sigma, to_trades) / from
+}
+
+/// Express a drawdown measured over `n_trades` trades on a common `horizon`.
+///
+/// `trades_per_year` is only used for `Horizon::Years`.
+pub fn normalize_drawdown(
+    dd: f64,
+    n_trades: usize,
+    trades_per_year: f64,
+    mean: f64,
+    sigma: f64,
+    horizon: Horizon,
+) -> f64 {
+    scale_drawdown(dd, mean, sigma, n_trades as f64, horizon.n_trades(trades_per_year))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqrt_scaling() {
+        assert!((scale_drawdown_sqrt(10.0, 100.0, 400.0) - 20.0).abs() < 1e-12);
+        assert_eq!(scale_drawdown_sqrt(10.0, 0.0, 400.0), 10.0);
+    }
+
+    #[test]
+    fn test_zero_drift_matches_sqrt() {
+        let a = scale_drawdown(5.0, 0.0, 1.0, 50.0, 200.0);
+        assert!((a - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_drift_changes_scaling() {
+        let sqrt = scale_drawdown_sqrt(5.0, 50.0, 200.0);
+        assert!(scale_drawdown(5.0, 0.2, 1.0, 50.0, 200.0) < sqrt);
+        assert!(scale_drawdown(5.0, -0.2, 1.0, 50.0, 200.0) > sqrt);
+    }
+
+    #[test]
+    fn test_horizon_years() {
+        let tpy = trades_per_year(50, 504, 252.0);
+        assert!((tpy - 25.0).abs() < 1e-12);
+        assert_eq!(Horizon::Years(2.0).n_trades(tpy), 50.0);
+        assert_eq!(Horizon::Years(1.0).label(), "per year");
+        assert_eq!(Horizon::Trades(100.0).label(), "per 100 trades");
+
+        // A year at this frequency is 25 trades, so a 50-trade drawdown shrinks
+        let dd = normalize_drawdown(4.0, 50, tpy, 0.0, 1.0, Horizon::Years(1.0));
+        assert!((dd - 4.0 / 2.0_f64.sqrt()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,215 @@
+//! Path-dependent drawdown metrics of an equity curve.
+//!
+//! Maximum drawdown describes one point of the curve. How often the curve
+//! falls, for how long, and how slowly it climbs back matter as much to
+//! anyone holding the system. A `DrawdownProfile` splits the curve into
+//! drawdown episodes, each from a peak to the first value back at or above
+//! it, and summarizes them.
+//!
+//! Equity is additive, as in `drawdown`: cumulative log returns or money, so
+//! a drawdown is the peak less the value.
+
+/// One drawdown, from a peak to the recovery of that peak
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawdownEpisode {
+    /// Index of the peak the equity fell from
+    pub peak: usize,
+    /// Index of the lowest value before recovery
+    pub trough: usize,
+    /// Index of the first value at or above the peak, `None` if the curve
+    /// ends under water
+    pub recovery: Option<usize>,
+    /// Peak less trough
+    pub depth: f64,
+    /// Bars below the peak
+    pub duration: usize,
+}
+
+impl DrawdownEpisode {
+    /// Bars from the trough to the recovery, `None` while unrecovered
+    pub fn time_to_recovery(&self) -> Option<usize> {
+        self.recovery.map(|r| r - self.trough)
+    }
+}
+
+/// Drawdown metrics of an equity curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawdownProfile {
+    /// Every drawdown episode, in time order
+    pub episodes: Vec<DrawdownEpisode>,
+    /// Drawdown from the running peak at each value (0 at a new high)
+    pub underwater: Vec<f64>,
+    pub max_drawdown: f64,
+    /// Mean depth of the episodes
+    pub average_drawdown: f64,
+    /// Longest episode, in bars below the peak
+    pub max_time_under_water: usize,
+    /// Mean bars from trough to recovery of the recovered episodes
+    pub mean_time_to_recovery: f64,
+    /// Longest trough-to-recovery time of the recovered episodes
+    pub max_time_to_recovery: usize,
+}
+
+impl DrawdownProfile {
+    /// Profile of an additive equity curve
+    pub fn from_equity(equity: &[f64]) -> Self {
+        let mut episodes = Vec::new();
+        let mut underwater = Vec::with_capacity(equity.len());
+        let mut peak = 0;
+        let mut open: Option<DrawdownEpisode> = None;
+
+        for (i, &value) in equity.iter().enumerate() {
+            if value >= equity[peak] {
+                if let Some(mut episode) = open.take() {
+                    episode.recovery = Some(i);
+                    episodes.push(episode);
+                }
+                peak = i;
+                underwater.push(0.0);
+                continue;
+            }
+
+            let depth = equity[peak] - value;
+            underwater.push(depth);
+            let episode = open.get_or_insert(DrawdownEpisode {
+                peak,
+                trough: i,
+                recovery: None,
+                depth,
+                duration: 0,
+            });
+            episode.duration += 1;
+            if depth > episode.depth {
+                episode.depth = depth;
+                episode.trough = i;
+            }
+        }
+        episodes.extend(open);
+
+        let max_drawdown = episodes.iter().map(|e| e.depth).fold(0.0, f64::max);
+        let average_drawdown = if episodes.is_empty() {
+            0.0
+        } else {
+            episodes.iter().map(|e| e.depth).sum::<f64>() / episodes.len() as f64
+        };
+        let recoveries: Vec<usize> = episodes.iter().filter_map(DrawdownEpisode::time_to_recovery).collect();
+        let mean_time_to_recovery = if recoveries.is_empty() {
+            0.0
+        } else {
+            recoveries.iter().sum::<usize>() as f64 / recoveries.len() as f64
+        };
+
+        DrawdownProfile {
+            max_time_under_water: episodes.iter().map(|e| e.duration).max().unwrap_or(0),
+            max_time_to_recovery: recoveries.iter().copied().max().unwrap_or(0),
+            episodes,
+            underwater,
+            max_drawdown,
+            average_drawdown,
+            mean_time_to_recovery,
+        }
+    }
+
+    /// Profile of the equity curve of a sequence of trade returns, cumulated
+    /// from the first trade as in `drawdown`
+    pub fn from_trades(trades: &[f64]) -> Self {
+        let equity: Vec<f64> = trades
+            .iter()
+            .scan(0.0, |sum, &t| {
+                *sum += t;
+                Some(*sum)
+            })
+            .collect();
+        Self::from_equity(&equity)
+    }
+
+    /// Durations of the episodes in bars below the peak, sorted
+    pub fn durations(&self) -> Vec<usize> {
+        let mut durations: Vec<usize> = self.episodes.iter().map(|e| e.duration).collect();
+        durations.sort_unstable();
+        durations
+    }
+
+    /// Duration at fraction `q` of the sorted durations (nearest rank), 0
+    /// without episodes
+    pub fn duration_quantile(&self, q: f64) -> usize {
+        let durations = self.durations();
+        if durations.is_empty() {
+            return 0;
+        }
+        let k = (q.clamp(0.0, 1.0) * durations.len() as f64).ceil() as usize;
+        durations[k.saturating_sub(1)]
+    }
+
+    /// Conditional drawdown at risk: mean of the drawdowns at the worst
+    /// `1 - alpha` of the values of the curve (at least one), e.g. alpha 0.95
+    /// for the worst 5 percent. Equals `max_drawdown` as alpha approaches 1
+    /// and the mean drawdown at alpha 0.
+    pub fn cdar(&self, alpha: f64) -> f64 {
+        if self.underwater.is_empty() {
+            return 0.0;
+        }
+        let mut sorted = self.underwater.clone();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+        let n_tail = (((1.0 - alpha.clamp(0.0, 1.0)) * sorted.len() as f64).ceil() as usize).max(1);
+        sorted[..n_tail].iter().sum::<f64>() / n_tail as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawdown::drawdown;
+
+    #[test]
+    fn test_profile_episodes() {
+        // Peak 3 at index 1, trough 0 at 3, recovered at 5; then peak 5 at 6
+        // falling to 3 and still under water at the end
+        let equity = [1.0, 3.0, 2.0, 0.0, 2.5, 3.0, 5.0, 4.0, 3.0, 4.0];
+        let p = DrawdownProfile::from_equity(&equity);
+
+        assert_eq!(p.episodes.len(), 2);
+        assert_eq!(
+            p.episodes[0],
+            DrawdownEpisode { peak: 1, trough: 3, recovery: Some(5), depth: 3.0, duration: 3 }
+        );
+        assert_eq!(
+            p.episodes[1],
+            DrawdownEpisode { peak: 6, trough: 8, recovery: None, depth: 2.0, duration: 3 }
+        );
+        assert_eq!(p.episodes[0].time_to_recovery(), Some(2));
+        assert_eq!(p.episodes[1].time_to_recovery(), None);
+
+        assert_eq!(p.max_drawdown, 3.0);
+        assert_eq!(p.average_drawdown, 2.5);
+        assert_eq!(p.max_time_under_water, 3);
+        assert_eq!((p.mean_time_to_recovery, p.max_time_to_recovery), (2.0, 2));
+        assert_eq!(p.underwater, vec![0.0, 0.0, 1.0, 3.0, 0.5, 0.0, 0.0, 1.0, 2.0, 1.0]);
+        assert_eq!(p.durations(), vec![3, 3]);
+        assert_eq!(p.duration_quantile(0.5), 3);
+    }
+
+    #[test]
+    fn test_cdar() {
+        let p = DrawdownProfile::from_equity(&[1.0, 3.0, 2.0, 0.0, 2.5, 3.0, 5.0, 4.0, 3.0, 4.0]);
+        // Worst 20 percent of 10 values: 3 and 2
+        assert_eq!(p.cdar(0.8), 2.5);
+        // Worst half: 3, 2, 1, 1, 1
+        assert!((p.cdar(0.5) - 1.6).abs() < 1e-12);
+        assert!((p.cdar(0.0) - 0.85).abs() < 1e-12);
+        assert_eq!(p.cdar(1.0), p.max_drawdown);
+    }
+
+    #[test]
+    fn test_profile_matches_drawdown() {
+        let trades = [0.5, -1.0, 0.2, -0.4, 1.5, 0.3, -0.2, -0.9, 0.1];
+        let p = DrawdownProfile::from_trades(&trades);
+        assert!((p.max_drawdown - drawdown(&trades)).abs() < 1e-12);
+
+        let empty = DrawdownProfile::from_equity(&[]);
+        assert_eq!((empty.max_drawdown, empty.cdar(0.95), empty.duration_quantile(0.9)), (0.0, 0.0, 0));
+        let rising = DrawdownProfile::from_equity(&[1.0, 2.0, 3.0]);
+        assert!(rising.episodes.is_empty());
+        assert_eq!(rising.max_time_under_water, 0);
+    }
+}
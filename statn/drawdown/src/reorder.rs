@@ -0,0 +1,242 @@
+//! Monte Carlo reordering of the trades of a real backtest.
+//!
+//! The drawdown of a backtest is a single draw: the same trades taken in
+//! another order would have gone deeper or stayed shallower. Shuffling the
+//! P&Ls of the actual trades, or resampling them with replacement (which
+//! also varies the total), gives the range of drawdowns a trader could have
+//! met with those trades, the probability that a starting capital is lost on
+//! the way, and the capital needed to survive at a chosen confidence.
+//!
+//! Each path starts from zero P&L before its first trade, so a losing first
+//! trade counts as a drawdown from the starting capital.
+
+use std::fmt;
+use std::str::FromStr;
+
+use matlib::Mwc256;
+use rayon::prelude::*;
+
+use crate::drawdown::find_quantile;
+use crate::study::{rep_seed, DEFAULT_SEED};
+
+/// How the trades of each simulated path are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReorderMethod {
+    /// Every trade exactly once, in random order; the total P&L is that of
+    /// the backtest
+    #[default]
+    Permute,
+    /// As many trades as the backtest, drawn with replacement
+    Bootstrap,
+}
+
+impl FromStr for ReorderMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "permute" | "shuffle" => Ok(ReorderMethod::Permute),
+            "bootstrap" => Ok(ReorderMethod::Bootstrap),
+            _ => Err(format!("Unknown reorder method '{}' (expected permute or bootstrap)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ReorderMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReorderMethod::Permute => "permute",
+            ReorderMethod::Bootstrap => "bootstrap",
+        })
+    }
+}
+
+/// The trades of a backtest to be reordered
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeReorderSim {
+    /// Profit or loss of each trade
+    pub pnls: Vec<f64>,
+    pub method: ReorderMethod,
+    /// Number of simulated paths
+    pub reps: usize,
+    pub seed: u32,
+}
+
+/// Outcomes of the simulated paths, each sorted ascending
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReorderOutcome {
+    /// Maximum drawdown from the running peak of each path
+    pub max_drawdowns: Vec<f64>,
+    /// Deepest loss below the starting equity of each path, 0 for a path
+    /// that never falls below it
+    pub max_losses: Vec<f64>,
+    /// Total P&L of each path
+    pub final_pnls: Vec<f64>,
+}
+
+impl TradeReorderSim {
+    /// 10000 permutations of `pnls` from the default seed
+    pub fn new(pnls: Vec<f64>) -> Self {
+        TradeReorderSim { pnls, method: ReorderMethod::Permute, reps: 10_000, seed: DEFAULT_SEED }
+    }
+
+    /// Simulate the paths, in parallel; each path reseeds its own generator,
+    /// so the outcome depends only on the settings
+    pub fn run(&self) -> ReorderOutcome {
+        let paths: Vec<(f64, f64, f64)> = (0..self.reps)
+            .into_par_iter()
+            .map_init(
+                || self.pnls.clone(),
+                |order, irep| {
+                    let mut rng = Mwc256::with_seed(rep_seed(self.seed, irep));
+                    self.draw(order, &mut rng);
+                    path_outcome(order)
+                },
+            )
+            .collect();
+
+        let sorted = |mut v: Vec<f64>| {
+            v.sort_by(f64::total_cmp);
+            v
+        };
+        ReorderOutcome {
+            max_drawdowns: sorted(paths.iter().map(|p| p.0).collect()),
+            max_losses: sorted(paths.iter().map(|p| p.1).collect()),
+            final_pnls: sorted(paths.iter().map(|p| p.2).collect()),
+        }
+    }
+
+    /// Maximum drawdown, deepest loss and total P&L of the trades in their
+    /// original order
+    pub fn realized(&self) -> (f64, f64, f64) {
+        path_outcome(&self.pnls)
+    }
+
+    /// Fill `order` with the trades of one path
+    fn draw(&self, order: &mut [f64], rng: &mut Mwc256) {
+        let n = self.pnls.len();
+        match self.method {
+            ReorderMethod::Permute => {
+                // Fisher-Yates from the original order, so that a path does
+                // not depend on the one run before it on the same thread
+                order.copy_from_slice(&self.pnls);
+                for i in (1..n).rev() {
+                    let j = ((rng.unifrand() * (i + 1) as f64) as usize).min(i);
+                    order.swap(i, j);
+                }
+            }
+            ReorderMethod::Bootstrap => {
+                for trade in order.iter_mut() {
+                    let k = ((rng.unifrand() * n as f64) as usize).min(n - 1);
+                    *trade = self.pnls[k];
+                }
+            }
+        }
+    }
+}
+
+/// Maximum drawdown, deepest loss below the start and total of a sequence
+/// of P&Ls starting from zero
+fn path_outcome(pnls: &[f64]) -> (f64, f64, f64) {
+    let (mut equity, mut peak, mut low, mut dd) = (0.0_f64, 0.0_f64, 0.0_f64, 0.0_f64);
+    for &pnl in pnls {
+        equity += pnl;
+        peak = peak.max(equity);
+        low = low.min(equity);
+        dd = dd.max(peak - equity);
+    }
+    (dd, -low, equity)
+}
+
+impl ReorderOutcome {
+    /// Maximum drawdown not exceeded by fraction `q` of the paths
+    pub fn drawdown_quantile(&self, q: f64) -> f64 {
+        quantile(&self.max_drawdowns, q)
+    }
+
+    /// Total P&L exceeded by fraction `1 - q` of the paths
+    pub fn final_quantile(&self, q: f64) -> f64 {
+        quantile(&self.final_pnls, q)
+    }
+
+    /// Fraction of the paths that lose more than `capital` below the starting
+    /// equity, i.e. are ruined trading from that capital. For ruin at a
+    /// partial loss, such as half the account, pass that part of the capital.
+    pub fn ruin_probability(&self, capital: f64) -> f64 {
+        if self.max_losses.is_empty() {
+            return 0.0;
+        }
+        let below = self.max_losses.partition_point(|&loss| loss <= capital);
+        (self.max_losses.len() - below) as f64 / self.max_losses.len() as f64
+    }
+
+    /// Smallest starting capital that leaves no more than fraction
+    /// `1 - confidence` of the paths ruined
+    pub fn required_capital(&self, confidence: f64) -> f64 {
+        quantile(&self.max_losses, confidence)
+    }
+}
+
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    find_quantile(sorted, q)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trades() -> Vec<f64> {
+        (0..60).map(|i| if i % 3 == 0 { -120.0 } else { 100.0 + i as f64 }).collect()
+    }
+
+    #[test]
+    fn test_path_outcome() {
+        // Down 50 at once, up to 30, down to -10: drawdown 50 from the start
+        // and 40 from the later peak
+        assert_eq!(path_outcome(&[-50.0, 80.0, -40.0]), (50.0, 50.0, -10.0));
+        assert_eq!(path_outcome(&[10.0, 20.0]), (0.0, 0.0, 30.0));
+        assert_eq!(path_outcome(&[]), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_permute_keeps_total() {
+        let sim = TradeReorderSim { reps: 2000, ..TradeReorderSim::new(trades()) };
+        let outcome = sim.run();
+        let total: f64 = trades().iter().sum();
+        assert!(outcome.final_pnls.iter().all(|&f| (f - total).abs() < 1e-9));
+
+        // The backtest's own order, losers spread out, is among the mildest
+        let (realized_dd, _, _) = sim.realized();
+        assert_eq!(realized_dd, 120.0);
+        assert!(outcome.drawdown_quantile(0.5) > realized_dd);
+        assert!(outcome.drawdown_quantile(0.5) <= outcome.drawdown_quantile(0.95));
+
+        // Capital to survive 95% of the paths ruins no more than 5% of them
+        let capital = outcome.required_capital(0.95);
+        assert!(outcome.ruin_probability(capital) <= 0.05);
+        assert!(outcome.ruin_probability(capital - 1e-9) > 0.05);
+        assert!(outcome.ruin_probability(0.0) > outcome.ruin_probability(capital - 1e-9));
+        assert_eq!(outcome.ruin_probability(f64::INFINITY), 0.0);
+
+        // Independent of the thread count
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        assert_eq!(pool.install(|| sim.run()), outcome);
+    }
+
+    #[test]
+    fn test_bootstrap_varies_total() {
+        let sim = TradeReorderSim { method: ReorderMethod::Bootstrap, reps: 2000, ..TradeReorderSim::new(trades()) };
+        let outcome = sim.run();
+        assert!(outcome.final_quantile(0.05) < outcome.final_quantile(0.95));
+        // Drawing with replacement adds risk beyond reordering
+        let permuted = TradeReorderSim { reps: 2000, ..TradeReorderSim::new(trades()) }.run();
+        assert!(outcome.required_capital(0.99) > permuted.required_capital(0.99));
+
+        assert_eq!("Shuffle".parse::<ReorderMethod>(), Ok(ReorderMethod::Permute));
+        assert_eq!(ReorderMethod::Bootstrap.to_string().parse(), Ok(ReorderMethod::Bootstrap));
+        assert!("sort".parse::<ReorderMethod>().is_err());
+    }
+}
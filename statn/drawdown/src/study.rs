@@ -0,0 +1,249 @@
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+use crate::distribution::TradeDistribution;
+use crate::drawdown::{drawdown, drawdown_quantiles, find_quantile, get_trades, mean_return};
+use crate::random::set_seed;
+
+/// Number of population trade sets drawn per test rep
+pub const POP_MULT: usize = 1000;
+
+/// Nominal quantiles reported by the study, in counter order
+pub const QUANTILES: [f64; 4] = [0.001, 0.01, 0.05, 0.1];
+
+/// Default master seed when none is given on the command line
+pub const DEFAULT_SEED: u32 = 123456789;
+
+/// Parameters of the drawdown bootstrap study
+#[derive(Debug, Clone)]
+pub struct StudyParams {
+    pub n_changes: usize,
+    pub n_trades: usize,
+    /// Distribution of individual trade returns, for both history and population
+    pub distribution: TradeDistribution,
+    pub bound_conf: f64,
+    pub bootstrap_reps: usize,
+    pub quantile_reps: usize,
+    pub test_reps: usize,
+}
+
+/// Failure counts of one test rep, or summed across reps.
+///
+/// Each array is indexed like `QUANTILES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CountSnapshot {
+    pub incorrect_meanret: [usize; 4],
+    pub incorrect_drawdown: [usize; 4],
+    pub correct: [usize; 4],
+}
+
+impl CountSnapshot {
+    /// Add the counts of `other` to these
+    pub fn add(&mut self, other: &CountSnapshot) {
+        for i in 0..QUANTILES.len() {
+            self.incorrect_meanret[i] += other.incorrect_meanret[i];
+            self.incorrect_drawdown[i] += other.incorrect_drawdown[i];
+            self.correct[i] += other.correct[i];
+        }
+    }
+}
+
+/// Derive the RNG seed for one test rep from the master seed.
+///
+/// Every rep reseeds its thread's RNG with this value before drawing anything,
+/// so results do not depend on which thread runs the rep or in what order.
+pub fn rep_seed(master_seed: u32, itest: usize) -> u32 {
+    master_seed.wrapping_add((itest as u32).wrapping_mul(0x9E37_79B9))
+}
+
+/// Run a single test rep and return its failure counts
+pub fn run_test_rep(params: &StudyParams, seed: u32) -> CountSnapshot {
+    set_seed(seed as i32);
+
+    let n_changes = params.n_changes;
+    let n_trades = params.n_trades;
+    let bootstrap_reps = params.bootstrap_reps;
+
+    let mut changes = Vec::with_capacity(n_changes);
+    let mut bootsample = Vec::with_capacity(n_trades);
+    let mut trades = Vec::with_capacity(n_changes);
+    let mut work = Vec::with_capacity(params.quantile_reps);
+
+    // Incorrect method test
+    let mut incorrect_meanrets = Vec::with_capacity(bootstrap_reps);
+    let mut incorrect_drawdowns = Vec::with_capacity(bootstrap_reps);
+
+    for iboot in 0..bootstrap_reps {
+        let make_changes = iboot == 0;
+        get_trades(n_changes, n_trades, &params.distribution, make_changes, &mut changes, &mut trades);
+        incorrect_meanrets.push(mean_return(&trades));
+        incorrect_drawdowns.push(drawdown(&trades));
+    }
+
+    incorrect_meanrets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let incorrect_meanret_bounds = QUANTILES.map(|q| find_quantile(&incorrect_meanrets, q));
+
+    incorrect_drawdowns.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let incorrect_drawdown_bounds =
+        [0.999, 0.99, 0.95, 0.9].map(|q| find_quantile(&incorrect_drawdowns, q));
+
+    // Correct method test
+    let mut correct_q: [Vec<f64>; 4] = Default::default();
+    for q in correct_q.iter_mut() {
+        q.reserve(bootstrap_reps);
+    }
+
+    for iboot in 0..bootstrap_reps {
+        let make_changes = iboot == 0;
+        get_trades(n_changes, n_changes, &params.distribution, make_changes, &mut changes, &mut trades);
+        let (q001, q01, q05, q10) = drawdown_quantiles(
+            n_changes,
+            n_trades,
+            &trades,
+            params.quantile_reps,
+            &mut bootsample,
+            &mut work,
+        );
+        correct_q[0].push(q001);
+        correct_q[1].push(q01);
+        correct_q[2].push(q05);
+        correct_q[3].push(q10);
+    }
+
+    for q in correct_q.iter_mut() {
+        q.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    }
+
+    let two_sided = 1.0 - (1.0 - params.bound_conf) / 2.0;
+    let correct_bounds = [
+        find_quantile(&correct_q[0], two_sided),
+        find_quantile(&correct_q[1], two_sided),
+        find_quantile(&correct_q[2], params.bound_conf),
+        find_quantile(&correct_q[3], params.bound_conf),
+    ];
+
+    // Population test
+    let mut local = CountSnapshot::default();
+    for _ in 0..POP_MULT {
+        trades.clear();
+        for _ in 0..n_trades {
+            trades.push(params.distribution.draw());
+        }
+
+        let crit = mean_return(&trades);
+        for (i, &bound) in incorrect_meanret_bounds.iter().enumerate() {
+            if crit < bound {
+                local.incorrect_meanret[i] += 1;
+            }
+        }
+
+        let crit = drawdown(&trades);
+        for i in 0..QUANTILES.len() {
+            if crit > incorrect_drawdown_bounds[i] {
+                local.incorrect_drawdown[i] += 1;
+            }
+            if crit > correct_bounds[i] {
+                local.correct[i] += 1;
+            }
+        }
+    }
+
+    local
+}
+
+/// Run all test reps in parallel.
+///
+/// `progress` is called after each rep completes with the number of reps done so
+/// far and the totals over exactly those reps. The totals are copied out of
+/// their lock before `progress` runs, so a slow callback does not hold up the
+/// other reps; calls may overlap and arrive out of order of `done`.
+/// The final counts depend only on `params` and `master_seed`, not on the
+/// number of threads.
+pub fn run_study<P>(params: &StudyParams, master_seed: u32, progress: P) -> CountSnapshot
+where
+    P: Fn(usize, &CountSnapshot) + Sync,
+{
+    let totals = Mutex::new((0usize, CountSnapshot::default()));
+
+    (1..=params.test_reps).into_par_iter().for_each(|itest| {
+        let local = run_test_rep(params, rep_seed(master_seed, itest));
+        let (done, counts) = {
+            let mut totals = totals.lock().unwrap();
+            totals.0 += 1;
+            totals.1.add(&local);
+            *totals
+        };
+        progress(done, &counts);
+    });
+
+    totals.into_inner().unwrap().1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_params() -> StudyParams {
+        StudyParams {
+            n_changes: 30,
+            n_trades: 20,
+            distribution: TradeDistribution::SignedNormal { win_prob: 0.5 },
+            bound_conf: 0.8,
+            bootstrap_reps: 20,
+            quantile_reps: 20,
+            test_reps: 6,
+        }
+    }
+
+    #[test]
+    fn test_study_reproducible_across_thread_counts() {
+        let params = small_params();
+        let one = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let four = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+        let a = one.install(|| run_study(&params, 42, |_, _| {}));
+        let b = four.install(|| run_study(&params, 42, |_, _| {}));
+        assert_eq!(a, b);
+
+        let c = four.install(|| run_study(&params, 43, |_, _| {}));
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_study_progress_is_consistent() {
+        let params = StudyParams { test_reps: 8, ..small_params() };
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let seen = Mutex::new(Vec::new());
+
+        let counts = pool.install(|| {
+            run_study(&params, 7, |done, counts| seen.lock().unwrap().push((done, *counts)))
+        });
+
+        // Reports may arrive in any order, but there is one per rep
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort_by_key(|&(done, _)| done);
+        let dones: Vec<usize> = seen.iter().map(|&(done, _)| done).collect();
+        assert_eq!(dones, (1..=params.test_reps).collect::<Vec<_>>());
+        // Each report holds the counts of exactly `done` reps, POP_MULT trials
+        // each, and the counts only grow with `done`
+        for (i, &(done, c)) in seen.iter().enumerate() {
+            assert!(c.correct.iter().all(|&n| n <= done * POP_MULT));
+            if i > 0 {
+                assert!(c.correct.iter().zip(&seen[i - 1].1.correct).all(|(n, prev)| n >= prev));
+            }
+        }
+        assert_eq!(seen.last().unwrap().1, counts);
+    }
+
+    #[test]
+    fn test_study_with_empirical_distribution() {
+        let params = StudyParams {
+            distribution: TradeDistribution::empirical(vec![-1.5, -0.5, 0.25, 0.75, 1.0, 2.0]).unwrap(),
+            ..small_params()
+        };
+        let a = run_study(&params, 11, |_, _| {});
+        let b = run_study(&params, 11, |_, _| {});
+        assert_eq!(a, b);
+    }
+}
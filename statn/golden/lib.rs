@@ -0,0 +1,269 @@
+//! Golden-file regression checks of the command line tools.
+//!
+//! Most tools in this workspace are ports of C++ programs. A reference file
+//! lists cases: a tool, its arguments on a bundled dataset, and the values it
+//! must print, each with a tolerance. `run_case` runs the tool and compares
+//! the numbers found in its output with the stored ones.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Where a reference value comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Recorded from the Rust port, guarding against regressions.
+    #[default]
+    Rust,
+    /// Taken from a run of the original C++ program.
+    Cpp,
+}
+
+/// One number a tool must print.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Expected {
+    /// Name shown in the report.
+    pub name: String,
+    /// Text before the value; the first number after it is taken.
+    pub label: String,
+    /// Text the search for `label` starts after, to pick one of several
+    /// sections printing the same label.
+    #[serde(default)]
+    pub after: Option<String>,
+    pub value: f64,
+    /// Absolute tolerance.
+    #[serde(default)]
+    pub tol: f64,
+    /// Tolerance relative to `value`; the larger of the two applies.
+    #[serde(default)]
+    pub rel_tol: f64,
+}
+
+/// A tool run and the values it must print.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Case {
+    pub name: String,
+    /// Binary name, looked up in the binary directory.
+    pub tool: String,
+    /// Arguments; `{data}`, `{golden}` and `{tmp}` are replaced by the data
+    /// directory, this crate's directory and a scratch directory.
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub source: Source,
+    pub values: Vec<Expected>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ReferenceFile {
+    #[serde(default, rename = "case")]
+    cases: Vec<Case>,
+}
+
+/// Directories substituted into case arguments.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub data: PathBuf,
+    pub golden: PathBuf,
+    pub tmp: PathBuf,
+}
+
+/// Comparison of one expected value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueCheck {
+    pub name: String,
+    pub expected: f64,
+    /// Value found in the output, `None` if the label was not found.
+    pub actual: Option<f64>,
+    pub passed: bool,
+}
+
+/// Result of `run_case`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseOutcome {
+    pub name: String,
+    pub source: Source,
+    pub checks: Vec<ValueCheck>,
+    /// Set when the tool could not be run or failed.
+    pub error: Option<String>,
+}
+
+impl CaseOutcome {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Load the cases of every `.toml` file in `dir`, in file name order.
+pub fn load_references(dir: &Path) -> Result<Vec<Case>, String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+
+    let mut cases = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+        let parsed: ReferenceFile =
+            toml::from_str(&text).map_err(|e| format!("Invalid reference file {}: {}", file.display(), e))?;
+        cases.extend(parsed.cases);
+    }
+    Ok(cases)
+}
+
+/// First number after `label` in `output`, searching after `after` if given.
+///
+/// Spaces and a `$` between the label and the number are skipped.
+pub fn extract(output: &str, after: Option<&str>, label: &str) -> Option<f64> {
+    let start = match after {
+        Some(anchor) => output.find(anchor)? + anchor.len(),
+        None => 0,
+    };
+    let rest = &output[start..];
+    let rest = &rest[rest.find(label)? + label.len()..];
+    let rest = rest.trim_start_matches([' ', '\t', '$']);
+
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')))
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// Whether `actual` is within `tol` of `expected`, or within `rel_tol` of it
+/// relatively, whichever is larger.
+pub fn within(actual: f64, expected: f64, tol: f64, rel_tol: f64) -> bool {
+    (actual - expected).abs() <= tol.max(rel_tol * expected.abs())
+}
+
+/// Replace the `{data}`, `{golden}` and `{tmp}` placeholders of an argument.
+pub fn expand(arg: &str, paths: &Paths) -> String {
+    arg.replace("{data}", &paths.data.to_string_lossy())
+        .replace("{golden}", &paths.golden.to_string_lossy())
+        .replace("{tmp}", &paths.tmp.to_string_lossy())
+}
+
+/// Compare the values of a case with a tool's output.
+pub fn check_output(case: &Case, output: &str) -> Vec<ValueCheck> {
+    case.values
+        .iter()
+        .map(|expected| {
+            let actual = extract(output, expected.after.as_deref(), &expected.label);
+            ValueCheck {
+                name: expected.name.clone(),
+                expected: expected.value,
+                actual,
+                passed: actual.is_some_and(|a| within(a, expected.value, expected.tol, expected.rel_tol)),
+            }
+        })
+        .collect()
+}
+
+/// Run the tool of a case from `bin_dir` and check its standard output.
+///
+/// The tool runs in `paths.tmp`, so files it writes to its working directory
+/// stay out of the tree. Standard input is closed, so tools that wait for
+/// Enter before exiting do not block.
+pub fn run_case(case: &Case, bin_dir: &Path, paths: &Paths) -> CaseOutcome {
+    let mut outcome = CaseOutcome {
+        name: case.name.clone(),
+        source: case.source,
+        checks: Vec::new(),
+        error: None,
+    };
+
+    let tool = bin_dir.join(&case.tool);
+    let args: Vec<String> = case.args.iter().map(|a| expand(a, paths)).collect();
+    let output = fs::create_dir_all(&paths.tmp)
+        .map_err(|e| format!("Cannot create {}: {}", paths.tmp.display(), e))
+        .and_then(|_| {
+            Command::new(&tool)
+                .args(&args)
+                .current_dir(&paths.tmp)
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| format!("Cannot run {}: {}", tool.display(), e))
+        });
+
+    match output {
+        Ok(output) if output.status.success() => {
+            outcome.checks = check_output(case, &String::from_utf8_lossy(&output.stdout));
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            outcome.error = Some(format!(
+                "{} exited with {}: {}",
+                case.tool,
+                output.status,
+                stderr.lines().next().unwrap_or("")
+            ));
+        }
+        Err(e) => outcome.error = Some(e),
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+Trend  min=-0.0232  max=0.0166  0.500 quantile=0.0003
+
+Gap analysis for trend with lookback=20
+     8      42
+    16     106
+
+Volatility  min=0.0072  max=0.0857  0.500 quantile=0.0220
+Final Budget:      $9766.26
+Tiny: 1.5e-7
+";
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(extract(OUTPUT, None, "min="), Some(-0.0232));
+        assert_eq!(extract(OUTPUT, Some("Volatility"), "min="), Some(0.0072));
+        assert_eq!(extract(OUTPUT, Some("Gap analysis for trend"), "    16"), Some(106.0));
+        assert_eq!(extract(OUTPUT, None, "Final Budget:"), Some(9766.26));
+        assert_eq!(extract(OUTPUT, None, "Tiny:"), Some(1.5e-7));
+        assert_eq!(extract(OUTPUT, None, "Sharpe Ratio:"), None);
+        assert_eq!(extract(OUTPUT, Some("Expansion"), "min="), None);
+    }
+
+    #[test]
+    fn test_check_output() {
+        let case: ReferenceFile = toml::from_str(
+            r#"
+            [[case]]
+            name = "trend"
+            tool = "stationary_test"
+            source = "cpp"
+
+            [[case.values]]
+            name = "trend min"
+            label = "min="
+            value = -0.0232
+            tol = 5e-5
+
+            [[case.values]]
+            name = "volatility max"
+            after = "Volatility"
+            label = "max="
+            value = 0.0850
+            rel_tol = 0.001
+            "#,
+        )
+        .unwrap();
+        let case = &case.cases[0];
+        assert_eq!(case.source, Source::Cpp);
+
+        let checks = check_output(case, OUTPUT);
+        assert!(checks[0].passed);
+        assert!(!checks[1].passed);
+        assert_eq!(checks[1].actual, Some(0.0857));
+        assert!(within(100.05, 100.0, 0.0, 0.001));
+    }
+}
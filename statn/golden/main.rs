@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use golden::{load_references, run_case, Paths, Source};
+use std::path::PathBuf;
+
+/// Golden-file regression checks of the ported tools
+#[derive(Parser, Debug)]
+#[command(name = "golden")]
+#[command(about = "Compare the outputs of the ported tools with stored reference values", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Run every reference case and compare its values within tolerances
+    Verify {
+        /// Directory of reference files (default: the bundled references)
+        #[arg(short, long)]
+        references: Option<PathBuf>,
+
+        /// Directory of the tool binaries (default: the directory of this binary)
+        #[arg(short, long)]
+        bin_dir: Option<PathBuf>,
+
+        /// Directory of the datasets, substituted for {data} (default: the repository's data/)
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+
+        /// Only run cases whose name contains this text
+        #[arg(short, long)]
+        filter: Option<String>,
+
+        /// Print every value, not only the failures
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// List the reference cases
+    List {
+        /// Directory of reference files (default: the bundled references)
+        #[arg(short, long)]
+        references: Option<PathBuf>,
+    },
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Verify {
+            references,
+            bin_dir,
+            data_dir,
+            filter,
+            verbose,
+        } => {
+            let references = references.unwrap_or_else(|| golden_dir().join("references"));
+            let cases = load_references(&references).map_err(|e| anyhow!(e))?;
+            let bin_dir = match bin_dir {
+                Some(dir) => dir,
+                None => std::env::current_exe()?
+                    .parent()
+                    .ok_or_else(|| anyhow!("Cannot find the binary directory"))?
+                    .to_path_buf(),
+            };
+            let data = data_dir.unwrap_or_else(|| golden_dir().join("../../data"));
+
+            let mut run = 0;
+            let mut failed = 0;
+            for case in cases
+                .iter()
+                .filter(|c| filter.as_ref().is_none_or(|f| c.name.contains(f.as_str())))
+            {
+                let paths = Paths {
+                    data: data.clone(),
+                    golden: golden_dir(),
+                    tmp: std::env::temp_dir().join(format!("golden-{}-{}", std::process::id(), case.name)),
+                };
+                let outcome = run_case(case, &bin_dir, &paths);
+                let _ = std::fs::remove_dir_all(&paths.tmp);
+                run += 1;
+
+                let source = match outcome.source {
+                    Source::Rust => "rust",
+                    Source::Cpp => "c++",
+                };
+                let status = if outcome.passed() { "PASS" } else { "FAIL" };
+                println!("{} {} ({} reference)", status, outcome.name, source);
+                if let Some(error) = &outcome.error {
+                    println!("    {}", error);
+                }
+                for check in &outcome.checks {
+                    if verbose || !check.passed {
+                        let actual = check.actual.map_or("not found".to_string(), |a| a.to_string());
+                        println!("    {:<28} expected {:<14} got {}", check.name, check.expected, actual);
+                    }
+                }
+                if !outcome.passed() {
+                    failed += 1;
+                }
+            }
+
+            println!("\n{} of {} cases passed", run - failed, run);
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::List { references } => {
+            let references = references.unwrap_or_else(|| golden_dir().join("references"));
+            for case in load_references(&references).map_err(|e| anyhow!(e))? {
+                println!("{:<32} {:<16} {} values", case.name, case.tool, case.values.len());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+/// Optimized system of one MCPT replication
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Replication {
+    /// Optimized criterion (total log return)
+    pub criterion: f64,
+    /// Optimized parameters: (rise, drop) thresholds for bars,
+    /// (short, long) lookbacks for trend
+    pub params: (f64, f64),
+    pub nlong: usize,
+    pub nshort: usize,
+    /// Part of the criterion explained by the trend of the market
+    pub trend_component: f64,
+}
+
+impl Replication {
+    /// Criterion less its trend component
+    pub fn training_bias(&self) -> f64 {
+        self.criterion - self.trend_component
+    }
+}
+
+/// Replications of an MCPT run; the first is the unpermuted market
+#[derive(Debug, Clone, PartialEq)]
+pub struct McptResult {
+    pub replications: Vec<Replication>,
+    /// Log price change over the evaluated bars
+    pub total_trend: f64,
+}
+
+impl McptResult {
+    /// Replication on the unpermuted market
+    pub fn original(&self) -> &Replication {
+        &self.replications[0]
+    }
+
+    /// Replications on permuted markets
+    pub fn permuted(&self) -> &[Replication] {
+        &self.replications[1..]
+    }
+
+    /// Original criterion and its null distribution
+    pub fn distribution(&self) -> NullDistribution {
+        NullDistribution {
+            original: self.original().criterion,
+            permuted: self.permuted().iter().map(|r| r.criterion).collect(),
+        }
+    }
+
+    /// Mean over the permutations of criterion less trend component
+    pub fn training_bias(&self) -> f64 {
+        self.permuted().iter().map(|r| r.training_bias()).sum::<f64>() / self.permuted().len() as f64
+    }
+
+    /// Original criterion less training bias
+    pub fn unbiased_return(&self) -> f64 {
+        self.original().criterion - self.training_bias()
+    }
+
+    /// Unbiased return less the original trend component
+    pub fn skill(&self) -> f64 {
+        self.unbiased_return() - self.original().trend_component
+    }
+}
+
+/// Criterion of the original market and of each permutation
+#[derive(Debug, Clone, PartialEq)]
+pub struct NullDistribution {
+    pub original: f64,
+    pub permuted: Vec<f64>,
+}
+
+impl NullDistribution {
+    /// Fraction of all replications, the original included, whose criterion
+    /// is at least the original's
+    pub fn p_value(&self) -> f64 {
+        let count = 1 + self.permuted.iter().filter(|&&c| c >= self.original).count();
+        count as f64 / (self.permuted.len() + 1) as f64
+    }
+
+    /// Quantile `q` (0 to 1) of the permuted criteria, interpolated between
+    /// order statistics. NaN if there are no permutations.
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.permuted.is_empty() {
+            return f64::NAN;
+        }
+        let mut sorted = self.permuted.clone();
+        sorted.sort_by(f64::total_cmp);
+        let pos = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+        let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+        sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+    }
+
+    /// Distribution of the criterion summed over markets, replication by
+    /// replication. Every market must have the same number of permutations
+    /// (run with the same `nreps`).
+    pub fn combine(markets: &[NullDistribution]) -> Result<NullDistribution, String> {
+        let first = markets.first().ok_or("No distributions to combine")?;
+        if markets.iter().any(|m| m.permuted.len() != first.permuted.len()) {
+            return Err("Distributions to combine must have the same number of permutations".to_string());
+        }
+        Ok(NullDistribution {
+            original: markets.iter().map(|m| m.original).sum(),
+            permuted: (0..first.permuted.len())
+                .map(|i| markets.iter().map(|m| m.permuted[i]).sum())
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_p_value_and_quantile() {
+        let dist = NullDistribution {
+            original: 3.0,
+            permuted: vec![4.0, 1.0, 2.0, 3.0, 0.0],
+        };
+        // The original, 4.0 and the tie 3.0 of 6 replications
+        assert!((dist.p_value() - 3.0 / 6.0).abs() < 1e-12);
+        assert_eq!(dist.quantile(0.0), 0.0);
+        assert_eq!(dist.quantile(0.5), 2.0);
+        assert_eq!(dist.quantile(0.875), 3.5);
+        assert!(NullDistribution { original: 1.0, permuted: vec![] }.quantile(0.5).is_nan());
+    }
+
+    #[test]
+    fn test_combine() {
+        let a = NullDistribution { original: 1.0, permuted: vec![0.5, 1.5] };
+        let b = NullDistribution { original: 2.0, permuted: vec![-1.0, 0.0] };
+        let combined = NullDistribution::combine(&[a.clone(), b]).unwrap();
+        assert_eq!(combined.original, 3.0);
+        assert_eq!(combined.permuted, vec![-0.5, 1.5]);
+
+        let short = NullDistribution { original: 0.0, permuted: vec![0.0] };
+        assert!(NullDistribution::combine(&[a, short]).is_err());
+        assert!(NullDistribution::combine(&[]).is_err());
+    }
+
+    #[test]
+    fn test_mcpt_result_statistics() {
+        let rep = |criterion, trend_component| Replication {
+            criterion,
+            params: (0.0, 0.0),
+            nlong: 0,
+            nshort: 0,
+            trend_component,
+        };
+        let result = McptResult {
+            replications: vec![rep(2.0, 0.5), rep(1.0, 0.25), rep(0.5, 0.25)],
+            total_trend: 1.0,
+        };
+        assert_eq!(result.training_bias(), 0.5);
+        assert_eq!(result.unbiased_return(), 1.5);
+        assert_eq!(result.skill(), 1.0);
+        assert_eq!(result.distribution().permuted, vec![1.0, 0.5]);
+        assert!((result.distribution().p_value() - 1.0 / 3.0).abs() < 1e-12);
+    }
+}
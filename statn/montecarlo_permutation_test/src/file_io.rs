@@ -1,2 +1,2 @@
 // Re-export from shared I/O module
-pub use statn::core::io::{read_price_file, read_ohlc_file};
+pub use statn::prelude::{read_bars_with_format, BarsFormat};
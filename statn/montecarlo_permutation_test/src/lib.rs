@@ -0,0 +1,16 @@
+//! Monte Carlo permutation tests of two primitive trading systems, and of
+//! positions generated elsewhere.
+//!
+//! `mcpt_bars::run_mcpt_bars`, `mcpt_trend::run_mcpt_trend` and
+//! `mcpt_signals::run_mcpt_signals` return every replication, so callers can take their own quantiles of the null
+//! distribution, plot it, or combine it across markets.
+
+pub mod distribution;
+pub mod file_io;
+pub mod mcpt_bars;
+pub mod mcpt_signals;
+pub mod mcpt_trend;
+pub mod meta;
+pub mod random;
+
+pub use distribution::{McptResult, NullDistribution, Replication};
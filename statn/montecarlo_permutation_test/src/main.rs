@@ -1,10 +1,11 @@
-mod random;
-mod file_io;
-mod mcpt_bars;
-mod mcpt_trend;
-
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use montecarlo_permutation_test::file_io::{self, BarsFormat};
+use montecarlo_permutation_test::meta::{combine_p_values, Dependence, Method};
+use montecarlo_permutation_test::mcpt_signals::{self, SignalKind};
+use montecarlo_permutation_test::random::DEFAULT_SEED;
+use montecarlo_permutation_test::{mcpt_bars, mcpt_trend, McptResult};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "mcpt")]
@@ -12,6 +13,10 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Threads running the replications (default: one per core)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
 }
 
 #[derive(Subcommand)]
@@ -29,6 +34,19 @@ enum Commands {
         /// Market file (YYYYMMDD Open High Low Close)
         #[arg(value_name = "FILENAME")]
         filename: PathBuf,
+
+        /// Master seed of the permutations; the same seed gives the same
+        /// result on any number of threads
+        #[arg(long, default_value_t = DEFAULT_SEED)]
+        seed: u32,
+
+        /// Market file format, "text" or "parquet" (default: from the extension)
+        #[arg(long)]
+        format: Option<BarsFormat>,
+
+        /// Write the criterion of every replication to this CSV file
+        #[arg(long)]
+        distribution: Option<PathBuf>,
     },
     
     /// Moving average crossover system
@@ -44,34 +62,274 @@ enum Commands {
         /// Market file (YYYYMMDD Price)
         #[arg(value_name = "FILENAME")]
         filename: PathBuf,
+
+        /// Permute price changes in circular blocks of this many bars, keeping
+        /// their short-term serial dependence (1 = single changes)
+        #[arg(long, default_value_t = 1)]
+        block: usize,
+
+        /// Master seed of the permutations; the same seed gives the same
+        /// result on any number of threads
+        #[arg(long, default_value_t = DEFAULT_SEED)]
+        seed: u32,
+
+        /// Market file format, "text" or "parquet" (default: from the extension)
+        #[arg(long)]
+        format: Option<BarsFormat>,
+
+        /// Write the criterion of every replication to this CSV file
+        #[arg(long)]
+        distribution: Option<PathBuf>,
+    },
+
+    /// Positions or returns of a system built elsewhere, aligned by date
+    Signals {
+        /// Signals file (YYYYMMDD Value); bars without a signal are flat
+        #[arg(value_name = "SIGNALS")]
+        signals: PathBuf,
+
+        /// Number of MCPT replications (hundreds or thousands)
+        #[arg(value_name = "NREPS")]
+        nreps: usize,
+
+        /// Market file (YYYYMMDD Price)
+        #[arg(value_name = "FILENAME")]
+        filename: PathBuf,
+
+        /// What the signal values are, "positions" or "returns" (log returns
+        /// of the system)
+        #[arg(long, default_value = "positions")]
+        kind: SignalKind,
+
+        /// Market file format, "text" or "parquet" (default: from the extension)
+        #[arg(long)]
+        format: Option<BarsFormat>,
+
+        /// Write the criterion of every replication to this CSV file
+        #[arg(long)]
+        distribution: Option<PathBuf>,
+    },
+
+    /// Combine the p-values of MCPT runs on several markets (Fisher and Stouffer)
+    Combine {
+        /// CSV file of `symbol,p_value` lines (a header line is skipped)
+        #[arg(value_name = "FILENAME")]
+        filename: Option<PathBuf>,
+
+        /// Comma-separated p-values, instead of or in addition to the file
+        #[arg(long, value_delimiter = ',')]
+        p_values: Vec<f64>,
+
+        /// Mean correlation between the markets' results (0 = independent)
+        #[arg(long, default_value_t = 0.0)]
+        correlation: f64,
+
+        /// Significance level of the universe-level conclusion
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
     },
 }
 
+/// Print the summary shared by both systems; only the trend system has
+/// short positions
+fn print_summary(result: &McptResult, with_nshort: bool) {
+    let original = result.original();
+    println!(
+        "\np-value for null hypothesis that system is worthless = {:.4}",
+        result.distribution().p_value()
+    );
+    println!("Total trend = {:.4}", result.total_trend);
+    if with_nshort {
+        println!("Original nshort = {}", original.nshort);
+    }
+    println!("Original nlong = {}", original.nlong);
+    println!("Original return = {:.4}", original.criterion);
+    println!("Trend component = {:.4}", original.trend_component);
+    println!("Training bias = {:.4}", result.training_bias());
+    println!("Skill = {:.4}", result.skill());
+    println!("Unbiased return = {:.4}", result.unbiased_return());
+}
+
+/// Print the replications per second of a run and the threads it ran on
+fn print_throughput(nreps: usize, elapsed: Duration, seed: u32) {
+    println!(
+        "{:.2} s, {:.1} replications/s on {} threads (seed {})",
+        elapsed.as_secs_f64(),
+        nreps as f64 / elapsed.as_secs_f64().max(1e-9),
+        rayon::current_num_threads(),
+        seed
+    );
+}
+
+/// Write `replication,criterion,trend_component` rows; replication 0 is the
+/// original market
+fn write_distribution(path: &Path, result: &McptResult) -> Result<(), String> {
+    let mut csv = String::from("replication,criterion,trend_component\n");
+    for (irep, rep) in result.replications.iter().enumerate() {
+        csv.push_str(&format!("{},{},{}\n", irep, rep.criterion, rep.trend_component));
+    }
+    std::fs::write(path, csv).map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+    println!("\nReplications written to {}", path.display());
+    Ok(())
+}
+
+/// Read `symbol,p_value` lines, skipping blank lines, `#` comments and a
+/// header whose p-value does not parse
+fn read_p_values(path: &Path) -> Result<Vec<(String, f64)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let mut p_values = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (symbol, p) = line.rsplit_once(',').unwrap_or(("", line));
+        match p.trim().parse::<f64>() {
+            Ok(p) => p_values.push((symbol.trim().to_string(), p)),
+            Err(_) if p_values.is_empty() && i == 0 => {}
+            Err(_) => return Err(format!("Invalid p-value on line {} of {}", i + 1, path.display())),
+        }
+    }
+    Ok(p_values)
+}
+
 fn main() -> Result<(), String> {
     let cli = Cli::parse();
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|e| format!("Error starting {} threads: {}", threads, e))?;
+    }
     
     match cli.command {
-        Commands::Bars { lookback, nreps, filename } => {
+        Commands::Bars { lookback, nreps, filename, seed, format, distribution } => {
             println!("\nReading market file...");
-            let data = file_io::read_ohlc_file(&filename)
-                .map_err(|e| format!("Error reading file: {}", e))?;
+            let bars = file_io::read_bars_with_format(&filename, format)
+                .map_err(|e| format!("Error reading file: {}", e))?
+                .to_log();
+            let nprices = bars.open.len();
             
-            mcpt_bars::run_mcpt_bars(
+            let start = Instant::now();
+            let result = mcpt_bars::run_mcpt_bars_with(
                 lookback,
                 nreps,
-                data.open,
-                data.high,
-                data.low,
-                data.close,
-            )
+                seed,
+                bars.open,
+                bars.high,
+                bars.low,
+                bars.close,
+            )?;
+            let elapsed = start.elapsed();
+
+            println!("Market price history read");
+            for (irep, rep) in result.replications.iter().enumerate() {
+                println!(
+                    "{:5}: Ret = {:.3}  Rise, drop= {:.4} {:.4}  NL={}  TrndComp={:.4}  TrnBias={:.4}",
+                    irep, rep.criterion, rep.params.0, rep.params.1, rep.nlong, rep.trend_component,
+                    rep.training_bias()
+                );
+            }
+            println!("\n{} prices were read, {} MCP replications with lookback = {}",
+                     nprices, nreps, lookback);
+            print_throughput(nreps, elapsed, seed);
+            print_summary(&result, false);
+
+            if let Some(path) = distribution {
+                write_distribution(&path, &result)?;
+            }
+            Ok(())
         }
         
-        Commands::Trend { max_lookback, nreps, filename } => {
+        Commands::Trend { max_lookback, nreps, filename, block, seed, format, distribution } => {
             println!("\nReading market file...");
-            let prices = file_io::read_price_file(&filename)
-                .map_err(|e| format!("Error reading file: {}", e))?;
+            let prices = file_io::read_bars_with_format(&filename, format)
+                .map_err(|e| format!("Error reading file: {}", e))?
+                .log_close();
+            let nprices = prices.len();
             
-            mcpt_trend::run_mcpt_trend(max_lookback, nreps, prices)
+            let start = Instant::now();
+            let result = mcpt_trend::run_mcpt_trend_with(max_lookback, nreps, block, seed, prices)?;
+            let elapsed = start.elapsed();
+
+            println!("Market price history read");
+            for (irep, rep) in result.replications.iter().enumerate() {
+                println!(
+                    "{:5}: Ret = {:.3}  Lookback={} {}  NS, NL={} {}  TrndComp={:.4}  TrnBias={:.4}",
+                    irep, rep.criterion, rep.params.0, rep.params.1, rep.nshort, rep.nlong,
+                    rep.trend_component, rep.training_bias()
+                );
+            }
+            println!("\n{} prices were read, {} MCP replications with max lookback = {}",
+                     nprices, nreps, max_lookback);
+            print_throughput(nreps, elapsed, seed);
+            if block > 1 {
+                println!("Price changes permuted in circular blocks of {}", block);
+            }
+            print_summary(&result, true);
+
+            if let Some(path) = distribution {
+                write_distribution(&path, &result)?;
+            }
+            Ok(())
+        }
+
+        Commands::Signals { signals, nreps, filename, kind, format, distribution } => {
+            println!("\nReading market file...");
+            let bars = file_io::read_bars_with_format(&filename, format)
+                .map_err(|e| format!("Error reading file: {}", e))?;
+            let prices = bars.log_close();
+            let nprices = prices.len();
+            let signals = mcpt_signals::read_signals(&signals)?;
+            let positions = mcpt_signals::align_positions(&bars.dates(), &prices, &signals, kind)?;
+
+            let result = mcpt_signals::run_mcpt_signals(nreps, &positions, prices)?;
+
+            println!("Market price history read");
+            for (irep, rep) in result.replications.iter().enumerate() {
+                println!("{:5}: Ret = {:.3}  TrndComp={:.4}", irep, rep.criterion, rep.trend_component);
+            }
+            println!("\n{} prices were read, {} signals, {} MCP replications",
+                     nprices, signals.len(), nreps);
+            print_summary(&result, true);
+
+            if let Some(path) = distribution {
+                write_distribution(&path, &result)?;
+            }
+            Ok(())
+        }
+
+        Commands::Combine { filename, p_values, correlation, alpha } => {
+            let mut markets = match &filename {
+                Some(path) => read_p_values(path)?,
+                None => Vec::new(),
+            };
+            markets.extend(p_values.iter().enumerate().map(|(i, &p)| (format!("#{}", i + 1), p)));
+            let dependence = if correlation == 0.0 {
+                Dependence::Independent
+            } else {
+                Dependence::MeanCorrelation(correlation)
+            };
+
+            println!("\n{:<16} {:>10}", "Market", "p-value");
+            for (symbol, p) in &markets {
+                println!("{:<16} {:>10.4}", symbol, p);
+            }
+            println!("\n{} markets, mean correlation = {:.3}", markets.len(), correlation);
+
+            let p: Vec<f64> = markets.iter().map(|(_, p)| *p).collect();
+            for method in [Method::Fisher, Method::Stouffer] {
+                let result = combine_p_values(&p, method, &dependence)?;
+                println!(
+                    "{:?}: statistic = {:.4}  effective markets = {:.2}  p-value = {:.4}  {}",
+                    method,
+                    result.statistic,
+                    result.effective_markets,
+                    result.p_value,
+                    if result.p_value <= alpha { "edge somewhere in the universe" } else { "no evidence of edge" }
+                );
+            }
+            Ok(())
         }
     }
 }
@@ -1,4 +1,7 @@
-use crate::random::Rand32M;
+use rayon::prelude::*;
+
+use crate::distribution::{McptResult, Replication};
+use crate::random::{rep_seed, Rand32M, DEFAULT_SEED};
 
 /// Compute optimal long-term rise and short-term drop thresholds
 /// for a primitive mean reversion long-only system
@@ -113,30 +116,47 @@ pub fn do_permute(
     }
 }
 
-/// Run the MCPT bars analysis
+/// Run the MCPT bars analysis: optimize the system on the market, then on
+/// `nreps - 1` permutations of it.
 pub fn run_mcpt_bars(
     lookback: usize,
     nreps: usize,
-    mut open: Vec<f64>,
-    mut high: Vec<f64>,
-    mut low: Vec<f64>,
-    mut close: Vec<f64>,
-) -> Result<(), String> {
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+) -> Result<McptResult, String> {
+    run_mcpt_bars_with(lookback, nreps, DEFAULT_SEED, open, high, low, close)
+}
+
+/// `run_mcpt_bars` with the replications seeded from `seed`.
+///
+/// Replications run in parallel on the rayon thread pool. Each permutes the
+/// original market with its own generator (see `rep_seed`), so the result
+/// depends on the seed only, not on the number of threads.
+pub fn run_mcpt_bars_with(
+    lookback: usize,
+    nreps: usize,
+    seed: u32,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+) -> Result<McptResult, String> {
     let nprices = open.len();
     
     if nprices - lookback < 10 {
         return Err("Number of prices must be at least 10 greater than lookback".to_string());
     }
     
-    println!("Market price history read");
-    
     // Allocate work arrays
     let mut rel_open = vec![0.0; nprices];
     let mut rel_high = vec![0.0; nprices];
     let mut rel_low = vec![0.0; nprices];
     let mut rel_close = vec![0.0; nprices];
     
-    let trend_per_return = (open[nprices - 1] - open[lookback + 1]) / (nprices - lookback - 2) as f64;
+    let total_trend = open[nprices - 1] - open[lookback + 1];
+    let trend_per_return = total_trend / (nprices - lookback - 2) as f64;
     
     // Prepare for permutation
     let eval_start = lookback;
@@ -152,68 +172,69 @@ pub fn run_mcpt_bars(
         &mut rel_low,
         &mut rel_close,
     );
+    let market = [open, high, low, close];
+    let rel = [rel_open, rel_high, rel_low, rel_close];
     
-    let mut rng = Rand32M::default();
-    let mut original = 0.0;
-    let mut original_trend_component = 0.0;
-    let mut original_nlong = 0;
-    let mut count = 1;
-    let mut mean_training_bias = 0.0;
+    // Do MCPT, with a permuted market and changes per thread
+    let replications = (0..nreps)
+        .into_par_iter()
+        .map_init(
+            || (market.clone(), rel.clone()),
+            |(permuted, shuffled), irep| {
+                if irep > 0 {
+                    let mut rng = Rand32M::with_seed(rep_seed(seed, irep));
+                    shuffled.clone_from(&rel);
+                    let [open, high, low, close] = permuted;
+                    let [rel_open, rel_high, rel_low, rel_close] = shuffled;
+                    do_permute(
+                        eval_len,
+                        true,
+                        &mut open[eval_start..],
+                        &mut high[eval_start..],
+                        &mut low[eval_start..],
+                        &mut close[eval_start..],
+                        rel_open,
+                        rel_high,
+                        rel_low,
+                        rel_close,
+                        &mut rng,
+                    );
+                }
+                let [open, _, _, close] = if irep == 0 { &market } else { &*permuted };
+                
+                let (opt_return, opt_rise, opt_drop, nlong) = opt_params(nprices, lookback, open, close);
+                Replication {
+                    criterion: opt_return,
+                    params: (opt_rise, opt_drop),
+                    nlong,
+                    nshort: 0,
+                    trend_component: nlong as f64 * trend_per_return,
+                }
+            },
+        )
+        .collect();
     
-    // Do MCPT
-    for irep in 0..nreps {
-        if irep > 0 {
-            do_permute(
-                eval_len,
-                true,
-                &mut open[eval_start..],
-                &mut high[eval_start..],
-                &mut low[eval_start..],
-                &mut close[eval_start..],
-                &mut rel_open,
-                &mut rel_high,
-                &mut rel_low,
-                &mut rel_close,
-                &mut rng,
-            );
-        }
-        
-        let (opt_return, opt_rise, opt_drop, nlong) = opt_params(nprices, lookback, &open, &close);
-        let trend_component = nlong as f64 * trend_per_return;
-        
-        println!(
-            "{:5}: Ret = {:.3}  Rise, drop= {:.4} {:.4}  NL={}  TrndComp={:.4}  TrnBias={:.4}",
-            irep, opt_return, opt_rise, opt_drop, nlong, trend_component, opt_return - trend_component
-        );
-        
-        if irep == 0 {
-            original = opt_return;
-            original_trend_component = trend_component;
-            original_nlong = nlong;
-        } else {
-            let training_bias = opt_return - trend_component;
-            mean_training_bias += training_bias;
-            if opt_return >= original {
-                count += 1;
-            }
-        }
+    Ok(McptResult { replications, total_trend })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_mcpt_bars_with_is_reproducible() {
+        let close: Vec<f64> = (0..80).map(|i| (i as f64 * 0.4).sin() * 0.03 + 0.002 * i as f64).collect();
+        let open: Vec<f64> = close.iter().enumerate().map(|(i, c)| c - 0.001 * (i % 3) as f64).collect();
+        let high: Vec<f64> = open.iter().zip(&close).map(|(o, c)| o.max(*c) + 0.002).collect();
+        let low: Vec<f64> = open.iter().zip(&close).map(|(o, c)| o.min(*c) - 0.002).collect();
+        let run = |seed| run_mcpt_bars_with(10, 12, seed, open.clone(), high.clone(), low.clone(), close.clone()).unwrap();
+
+        let result = run(DEFAULT_SEED);
+        assert_eq!(result.replications.len(), 12);
+        assert_eq!(run_mcpt_bars(10, 12, open.clone(), high.clone(), low.clone(), close.clone()).unwrap(), result);
+        // The seed alone fixes the result, whatever the number of threads
+        let one_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        assert_eq!(one_thread.install(|| run(DEFAULT_SEED)), result);
+        assert_ne!(run(7).permuted(), result.permuted());
     }
-    
-    mean_training_bias /= (nreps - 1) as f64;
-    let unbiased_return = original - mean_training_bias;
-    let skill = unbiased_return - original_trend_component;
-    
-    println!("\n{} prices were read, {} MCP replications with lookback = {}", 
-             nprices, nreps, lookback);
-    println!("\np-value for null hypothesis that system is worthless = {:.4}", 
-             count as f64 / nreps as f64);
-    println!("Total trend = {:.4}", open[nprices - 1] - open[lookback + 1]);
-    println!("Original nlong = {}", original_nlong);
-    println!("Original return = {:.4}", original);
-    println!("Trend component = {:.4}", original_trend_component);
-    println!("Training bias = {:.4}", mean_training_bias);
-    println!("Skill = {:.4}", skill);
-    println!("Unbiased return = {:.4}", unbiased_return);
-    
-    Ok(())
 }
@@ -0,0 +1,199 @@
+//! Permutation test of positions generated outside this crate.
+//!
+//! The positions are fixed and only the market is permuted, so the test asks
+//! whether the positions were better timed than on a market with the same
+//! price changes in random order. Nothing is optimized on the permutations,
+//! so the training bias is near zero and the skill is the original return
+//! less its trend component.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::distribution::{McptResult, Replication};
+use crate::mcpt_trend::{do_permute, prepare_permute};
+use crate::random::Rand32M;
+
+/// What the values of a signals file are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignalKind {
+    /// Position held from the dated bar to the next: 1 long, -1 short, 0
+    /// flat, or any fraction
+    #[default]
+    Positions,
+    /// Log return of the strategy earned from the previous bar to the dated
+    /// one; the position is recovered as return over market change
+    Returns,
+}
+
+impl FromStr for SignalKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "positions" | "position" => Ok(SignalKind::Positions),
+            "returns" | "return" => Ok(SignalKind::Returns),
+            _ => Err(format!("Unknown signal kind '{}' (expected positions or returns)", s)),
+        }
+    }
+}
+
+/// Read `YYYYMMDD value` lines separated by spaces, tabs or a comma, skipping
+/// blank lines, `#` comments and a header whose value does not parse
+pub fn read_signals(path: &Path) -> Result<Vec<(u32, f64)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let mut signals = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect();
+        let parsed = match fields.as_slice() {
+            [date, value] => date.parse::<u32>().ok().zip(value.parse::<f64>().ok()),
+            _ => None,
+        };
+        match parsed {
+            Some(signal) => signals.push(signal),
+            None if signals.is_empty() && i == 0 => {}
+            None => return Err(format!("Invalid signal on line {} of {}", i + 1, path.display())),
+        }
+    }
+    Ok(signals)
+}
+
+/// Position held at each bar of `log_prices`, from signals keyed by the bars'
+/// `dates`. Bars without a signal are flat. Fails on a signal dated outside
+/// the market, a repeated date, or a return dated on the first bar.
+pub fn align_positions(
+    dates: &[u32],
+    log_prices: &[f64],
+    signals: &[(u32, f64)],
+    kind: SignalKind,
+) -> Result<Vec<f64>, String> {
+    let mut positions = vec![0.0; log_prices.len()];
+    let mut seen = vec![false; log_prices.len()];
+    for &(date, value) in signals {
+        let bar = dates
+            .iter()
+            .position(|&d| d == date)
+            .ok_or_else(|| format!("Signal dated {} is not in the market file", date))?;
+        if seen[bar] {
+            return Err(format!("Repeated signal date {}", date));
+        }
+        seen[bar] = true;
+        match kind {
+            SignalKind::Positions => positions[bar] = value,
+            SignalKind::Returns => {
+                if bar == 0 {
+                    return Err(format!("Return dated {} has no previous bar", date));
+                }
+                let change = log_prices[bar] - log_prices[bar - 1];
+                positions[bar - 1] = if change == 0.0 { 0.0 } else { value / change };
+            }
+        }
+    }
+    Ok(positions)
+}
+
+/// Total log return of `positions` on `prices`: each bar's position times the
+/// change to the next bar
+fn total_return(positions: &[f64], prices: &[f64]) -> f64 {
+    prices.windows(2).zip(positions).map(|(w, pos)| pos * (w[1] - w[0])).sum()
+}
+
+/// Run the MCPT of fixed `positions`, one per bar of the log `prices`: their
+/// return on the market, then on `nreps - 1` permutations of it.
+pub fn run_mcpt_signals(nreps: usize, positions: &[f64], mut prices: Vec<f64>) -> Result<McptResult, String> {
+    let nprices = prices.len();
+    if nprices < 10 {
+        return Err("Number of prices must be at least 10".to_string());
+    }
+    if positions.len() != nprices {
+        return Err(format!("{} positions for {} prices", positions.len(), nprices));
+    }
+
+    let mut changes = vec![0.0; nprices];
+    let total_trend = prices[nprices - 1] - prices[0];
+    let trend_per_return = total_trend / (nprices - 1) as f64;
+    // Whatever the order of the changes, the positions earn this trend
+    let net_position: f64 = positions[..nprices - 1].iter().sum();
+    let nlong = positions[..nprices - 1].iter().filter(|&&p| p > 0.0).count();
+    let nshort = positions[..nprices - 1].iter().filter(|&&p| p < 0.0).count();
+
+    prepare_permute(nprices, &prices, &mut changes);
+
+    let mut rng = Rand32M::default();
+    let mut replications = Vec::with_capacity(nreps);
+    for irep in 0..nreps {
+        if irep > 0 {
+            do_permute(nprices, &mut prices, &mut changes, &mut rng);
+        }
+        replications.push(Replication {
+            criterion: total_return(positions, &prices),
+            params: (0.0, 0.0),
+            nlong,
+            nshort,
+            trend_component: net_position * trend_per_return,
+        });
+    }
+
+    Ok(McptResult { replications, total_trend })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_align_positions() {
+        let dates = [20240102, 20240103, 20240104, 20240105];
+        let prices = [0.0, 0.1, 0.1, -0.1];
+        let positions = align_positions(&dates, &prices, &[(20240103, 1.0), (20240104, -0.5)], SignalKind::Positions);
+        assert_eq!(positions, Ok(vec![0.0, 1.0, -0.5, 0.0]));
+
+        // Short 1 from the 2nd bar to the 4th earns 0.2
+        let returns = [(20240103, 0.05), (20240104, 0.0), (20240105, 0.2)];
+        let positions = align_positions(&dates, &prices, &returns, SignalKind::Returns).unwrap();
+        assert!((positions[0] - 0.5).abs() < 1e-12);
+        assert_eq!(positions[1], 0.0);
+        assert!((positions[2] + 1.0).abs() < 1e-12);
+
+        assert!(align_positions(&dates, &prices, &[(20240106, 1.0)], SignalKind::Positions).is_err());
+        assert!(align_positions(&dates, &prices, &[(20240102, 1.0)], SignalKind::Returns).is_err());
+        assert!(align_positions(&dates, &prices, &[(20240103, 1.0), (20240103, 0.0)], SignalKind::Positions).is_err());
+        assert_eq!("returns".parse::<SignalKind>(), Ok(SignalKind::Returns));
+    }
+
+    #[test]
+    fn test_read_signals() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "date,position\n20240102,1\n\n# flat\n20240103 0\n20240104\t-0.5").unwrap();
+        let signals = read_signals(file.path()).unwrap();
+        assert_eq!(signals, vec![(20240102, 1.0), (20240103, 0.0), (20240104, -0.5)]);
+
+        writeln!(file, "20240105,long").unwrap();
+        assert!(read_signals(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_run_mcpt_signals() {
+        // Alternating market; positions that anticipate every change
+        let prices: Vec<f64> = (0..40).map(|i| if i % 2 == 0 { 0.0 } else { 0.01 } + 0.001 * i as f64).collect();
+        let positions: Vec<f64> = (0..40).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        let result = run_mcpt_signals(200, &positions, prices.clone()).unwrap();
+        assert_eq!(result.replications.len(), 200);
+        assert!((result.original().criterion - total_return(&positions, &prices)).abs() < 1e-12);
+        assert!(result.distribution().p_value() < 0.05);
+        assert!(result.training_bias().abs() < 0.01);
+        assert_eq!((result.original().nlong, result.original().nshort), (20, 19));
+
+        // Always long earns the trend on every permutation
+        let long = vec![1.0; 40];
+        let result = run_mcpt_signals(50, &long, prices.clone()).unwrap();
+        assert!((result.original().criterion - result.total_trend).abs() < 1e-12);
+        assert!(result.skill().abs() < 1e-9);
+
+        assert!(run_mcpt_signals(10, &long[..5], prices).is_err());
+    }
+}
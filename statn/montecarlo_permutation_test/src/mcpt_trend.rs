@@ -1,4 +1,7 @@
-use crate::random::Rand32M;
+use rayon::prelude::*;
+
+use crate::distribution::{McptResult, Replication};
+use crate::random::{rep_seed, Rand32M, DEFAULT_SEED};
 
 /// Compute optimal short-term and long-term lookbacks
 /// for a primitive moving-average crossover system
@@ -26,11 +29,11 @@ pub fn opt_params(
                 if i == max_lookback - 1 {
                     // Initialize moving averages for first case
                     short_sum = 0.0;
-                    for j in (i - ishort + 1..=i).rev() {
+                    for j in (i + 1 - ishort..=i).rev() {
                         short_sum += x[j];
                     }
                     long_sum = short_sum;
-                    for j in (i - ilong + 1..i - ishort + 1).rev() {
+                    for j in (i + 1 - ilong..i + 1 - ishort).rev() {
                         long_sum += x[j];
                     }
                 } else {
@@ -92,84 +95,159 @@ pub fn do_permute(nc: usize, data: &mut [f64], changes: &mut [f64], rng: &mut Ra
     }
 }
 
-/// Run the MCPT trend analysis
+/// Permute the price changes in circular blocks of `block_len` and rebuild
+/// the prices. The changes, read as a circle, are rotated by a random offset
+/// so block boundaries fall anywhere, cut into blocks (the last may be
+/// shorter) and the blocks shuffled. Changes keep their order within a block,
+/// and with it their short-term serial dependence. Each call permutes
+/// `changes` afresh, which is left as it was.
+pub fn do_block_permute(nc: usize, data: &mut [f64], changes: &[f64], block_len: usize, rng: &mut Rand32M) {
+    let n = nc - 1;
+    if n == 0 {
+        return;
+    }
+    let offset = ((rng.unifrand() * n as f64) as usize).min(n - 1);
+    let block_len = block_len.clamp(1, n);
+    let nblocks = n.div_ceil(block_len);
+
+    let mut order: Vec<usize> = (0..nblocks).collect();
+    for i in (1..nblocks).rev() {
+        let j = ((rng.unifrand() * (i + 1) as f64) as usize).min(i);
+        order.swap(i, j);
+    }
+
+    let mut icase = 1;
+    for &block in &order {
+        for k in block * block_len..((block + 1) * block_len).min(n) {
+            data[icase] = data[icase - 1] + changes[(k + offset) % n];
+            icase += 1;
+        }
+    }
+}
+
+/// Run the MCPT trend analysis: optimize the system on the market, then on
+/// `nreps - 1` permutations of it.
 pub fn run_mcpt_trend(
     max_lookback: usize,
     nreps: usize,
-    mut prices: Vec<f64>,
-) -> Result<(), String> {
+    prices: Vec<f64>,
+) -> Result<McptResult, String> {
+    run_mcpt_trend_with(max_lookback, nreps, 1, DEFAULT_SEED, prices)
+}
+
+/// `run_mcpt_trend` permuting the price changes in circular blocks of
+/// `block_len` (see `do_block_permute`), so the null distribution keeps the
+/// market's serial dependence up to about that many bars, with the
+/// replications seeded from `seed`. A block length of 1 shuffles single
+/// changes, as `run_mcpt_trend` does.
+///
+/// Replications run in parallel on the rayon thread pool. Each permutes the
+/// original market with its own generator (see `rep_seed`), so the result
+/// depends on the seed only, not on the number of threads.
+pub fn run_mcpt_trend_with(
+    max_lookback: usize,
+    nreps: usize,
+    block_len: usize,
+    seed: u32,
+    prices: Vec<f64>,
+) -> Result<McptResult, String> {
     let nprices = prices.len();
     
+    if block_len == 0 {
+        return Err("Block length must be at least 1".to_string());
+    }
     if nprices - max_lookback < 10 {
         return Err("Number of prices must be at least 10 greater than max_lookback".to_string());
     }
     
-    println!("Market price history read");
-    
     // Allocate work array
     let mut changes = vec![0.0; nprices];
     
-    let trend_per_return = (prices[nprices - 1] - prices[max_lookback - 1]) / (nprices - max_lookback) as f64;
+    let total_trend = prices[nprices - 1] - prices[max_lookback - 1];
+    let trend_per_return = total_trend / (nprices - max_lookback) as f64;
     
     // Prepare for permutation
     let eval_start = max_lookback - 1;
     let eval_len = nprices - max_lookback + 1;
     prepare_permute(eval_len, &prices[eval_start..], &mut changes);
     
-    let mut rng = Rand32M::default();
-    let mut original = 0.0;
-    let mut original_trend_component = 0.0;
-    let mut original_nshort = 0;
-    let mut original_nlong = 0;
-    let mut count = 1;
-    let mut mean_training_bias = 0.0;
+    // Do MCPT, with a permuted market and changes per thread
+    let replications = (0..nreps)
+        .into_par_iter()
+        .map_init(
+            || (prices.clone(), changes.clone()),
+            |(market, shuffled), irep| {
+                let market: &[f64] = if irep == 0 {
+                    &prices
+                } else {
+                    let mut rng = Rand32M::with_seed(rep_seed(seed, irep));
+                    if block_len == 1 {
+                        shuffled.copy_from_slice(&changes);
+                        do_permute(eval_len, &mut market[eval_start..], shuffled, &mut rng);
+                    } else {
+                        do_block_permute(eval_len, &mut market[eval_start..], &changes, block_len, &mut rng);
+                    }
+                    market
+                };
+
+                let (opt_return, short_lookback, long_lookback, nshort, nlong) =
+                    opt_params(nprices, max_lookback, market);
+                Replication {
+                    criterion: opt_return,
+                    params: (short_lookback as f64, long_lookback as f64),
+                    nlong,
+                    nshort,
+                    trend_component: (nlong as f64 - nshort as f64) * trend_per_return,
+                }
+            },
+        )
+        .collect();
     
-    // Do MCPT
-    for irep in 0..nreps {
-        if irep > 0 {
-            do_permute(eval_len, &mut prices[eval_start..], &mut changes, &mut rng);
-        }
-        
-        let (opt_return, short_lookback, long_lookback, nshort, nlong) = 
-            opt_params(nprices, max_lookback, &prices);
-        let trend_component = (nlong as f64 - nshort as f64) * trend_per_return;
-        
-        println!(
-            "{:5}: Ret = {:.3}  Lookback={} {}  NS, NL={} {}  TrndComp={:.4}  TrnBias={:.4}",
-            irep, opt_return, short_lookback, long_lookback, nshort, nlong, 
-            trend_component, opt_return - trend_component
-        );
-        
-        if irep == 0 {
-            original = opt_return;
-            original_trend_component = trend_component;
-            original_nshort = nshort;
-            original_nlong = nlong;
-        } else {
-            let training_bias = opt_return - trend_component;
-            mean_training_bias += training_bias;
-            if opt_return >= original {
-                count += 1;
-            }
-        }
+    Ok(McptResult { replications, total_trend })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_do_block_permute() {
+        let nc = 41;
+        let changes: Vec<f64> = (0..nc - 1).map(|i| i as f64).collect();
+        let mut data = vec![0.0; nc];
+        let mut rng = Rand32M::default();
+        do_block_permute(nc, &mut data, &changes, 4, &mut rng);
+
+        let mut permuted: Vec<f64> = data.windows(2).map(|w| w[1] - w[0]).collect();
+        // Runs of consecutive changes (around the circle) survive within blocks
+        let kept = permuted.windows(2).filter(|w| w[1] == (w[0] + 1.0) % 40.0).count();
+        assert!(kept >= 40 - 40 / 4 - 1, "{} adjacent pairs kept", kept);
+        // Same changes, so the same last price
+        assert_eq!(data[nc - 1], changes.iter().sum::<f64>());
+        permuted.sort_by(f64::total_cmp);
+        assert_eq!(permuted, changes);
+
+        // One block is a rotation
+        do_block_permute(nc, &mut data, &changes, 40, &mut rng);
+        let rotated: Vec<f64> = data.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(rotated.windows(2).all(|w| w[1] == (w[0] + 1.0) % 40.0));
+    }
+
+    #[test]
+    fn test_run_mcpt_trend_with() {
+        let prices: Vec<f64> = (0..60).map(|i| (i as f64 * 0.3).sin() * 0.05 + 0.001 * i as f64).collect();
+        let single = run_mcpt_trend(10, 20, prices.clone()).unwrap();
+        assert_eq!(run_mcpt_trend_with(10, 20, 1, DEFAULT_SEED, prices.clone()).unwrap(), single);
+
+        let blocks = run_mcpt_trend_with(10, 20, 5, DEFAULT_SEED, prices.clone()).unwrap();
+        assert_eq!(blocks.replications.len(), 20);
+        assert_eq!(blocks.original(), single.original());
+        assert_ne!(blocks.permuted(), single.permuted());
+        assert!(run_mcpt_trend_with(10, 20, 0, DEFAULT_SEED, prices.clone()).is_err());
+
+        // The seed alone fixes the result, whatever the number of threads
+        let one_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        assert_eq!(one_thread.install(|| run_mcpt_trend_with(10, 20, 5, DEFAULT_SEED, prices.clone())).unwrap(), blocks);
+        assert_ne!(run_mcpt_trend_with(10, 20, 5, 7, prices).unwrap().permuted(), blocks.permuted());
     }
-    
-    mean_training_bias /= (nreps - 1) as f64;
-    let unbiased_return = original - mean_training_bias;
-    let skill = unbiased_return - original_trend_component;
-    
-    println!("\n{} prices were read, {} MCP replications with max lookback = {}", 
-             nprices, nreps, max_lookback);
-    println!("\np-value for null hypothesis that system is worthless = {:.4}", 
-             count as f64 / nreps as f64);
-    println!("Total trend = {:.4}", prices[nprices - 1] - prices[max_lookback - 1]);
-    println!("Original nshort = {}", original_nshort);
-    println!("Original nlong = {}", original_nlong);
-    println!("Original return = {:.4}", original);
-    println!("Trend component = {:.4}", original_trend_component);
-    println!("Training bias = {:.4}", mean_training_bias);
-    println!("Skill = {:.4}", skill);
-    println!("Unbiased return = {:.4}", unbiased_return);
-    
-    Ok(())
 }
@@ -0,0 +1,226 @@
+//! Combination of MCPT p-values across a universe of markets.
+//!
+//! Each market's permutation test asks whether the system has an edge in that
+//! market. Combining their p-values asks whether the system family has an
+//! edge anywhere in the universe, which single tests miss when each market
+//! alone is inconclusive.
+
+use std::str::FromStr;
+
+use statn::core::stats::{igamma, inverse_normal_cdf, normal_cdf};
+
+/// How p-values are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// `-2 * sum(ln p)`, chi-square with `2k` degrees of freedom under the
+    /// null. Sensitive to a few very small p-values.
+    Fisher,
+    /// `sum(z) / sqrt(k)` with `z = Phi^-1(1 - p)`, standard normal under the
+    /// null. Sensitive to a consistent edge across many markets.
+    Stouffer,
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fisher" => Ok(Method::Fisher),
+            "stouffer" => Ok(Method::Stouffer),
+            _ => Err(format!("Unknown combination method '{}' (expected fisher or stouffer)", s)),
+        }
+    }
+}
+
+/// Dependence between the markets' test statistics
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Dependence {
+    /// Independent tests
+    #[default]
+    Independent,
+    /// The same correlation between every pair of markets
+    MeanCorrelation(f64),
+    /// Full correlation matrix, one row per market
+    Correlation(Vec<Vec<f64>>),
+}
+
+impl Dependence {
+    /// Pearson correlation matrix of aligned return series, one per market
+    pub fn from_returns(returns: &[&[f64]]) -> Result<Dependence, String> {
+        let n = returns.first().map_or(0, |r| r.len());
+        if n < 2 || returns.iter().any(|r| r.len() != n) {
+            return Err("Return series must have the same length of at least 2".to_string());
+        }
+        let stats: Vec<(f64, f64)> = returns
+            .iter()
+            .map(|r| {
+                let mean = r.iter().sum::<f64>() / n as f64;
+                let ss = r.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+                (mean, ss.sqrt())
+            })
+            .collect();
+
+        let k = returns.len();
+        let mut matrix = vec![vec![1.0; k]; k];
+        for i in 0..k {
+            for j in i + 1..k {
+                let ((mi, si), (mj, sj)) = (stats[i], stats[j]);
+                let cross: f64 = returns[i].iter().zip(returns[j]).map(|(x, y)| (x - mi) * (y - mj)).sum();
+                let rho = if si > 0.0 && sj > 0.0 { cross / (si * sj) } else { 0.0 };
+                matrix[i][j] = rho;
+                matrix[j][i] = rho;
+            }
+        }
+        Ok(Dependence::Correlation(matrix))
+    }
+
+    /// Correlation between markets `i` and `j`
+    fn rho(&self, i: usize, j: usize) -> f64 {
+        match self {
+            _ if i == j => 1.0,
+            Dependence::Independent => 0.0,
+            Dependence::MeanCorrelation(rho) => *rho,
+            Dependence::Correlation(matrix) => matrix[i][j],
+        }
+    }
+}
+
+/// Universe-level result of `combine_p_values`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetaResult {
+    pub method: Method,
+    pub n_markets: usize,
+    /// Fisher's chi-square or Stouffer's z
+    pub statistic: f64,
+    /// Probability of a statistic at least this extreme if the system has no
+    /// edge in any market
+    pub p_value: f64,
+    /// Number of independent markets the evidence is worth, given the
+    /// dependence (equals `n_markets` for independent tests)
+    pub effective_markets: f64,
+}
+
+/// Combine one-sided p-values of the same system tested on several markets.
+///
+/// Positive dependence makes the markets agree more often than independent
+/// tests would, so the combined evidence is discounted: Stouffer's z is
+/// divided by the standard deviation of the correlated sum, and Fisher's
+/// statistic is compared with the scaled chi-square of Brown's method, using
+/// the Kost and McDermott approximation of the covariance of `-2 ln p`.
+pub fn combine_p_values(p_values: &[f64], method: Method, dependence: &Dependence) -> Result<MetaResult, String> {
+    let k = p_values.len();
+    if k == 0 {
+        return Err("No p-values to combine".to_string());
+    }
+    if let Some(p) = p_values.iter().find(|p| !(**p > 0.0 && **p <= 1.0)) {
+        return Err(format!("Invalid p-value {}: must be in (0, 1]", p));
+    }
+    if let Dependence::Correlation(matrix) = dependence {
+        if matrix.len() != k || matrix.iter().any(|row| row.len() != k) {
+            return Err(format!("Correlation matrix must be {} by {}", k, k));
+        }
+    }
+    if let Dependence::MeanCorrelation(rho) = dependence {
+        if !(-1.0 / k as f64..=1.0).contains(rho) && k > 1 {
+            return Err(format!("Mean correlation {} is not possible for {} markets", rho, k));
+        }
+    }
+
+    let pairs = (0..k).flat_map(|i| (i + 1..k).map(move |j| (i, j)));
+
+    let result = match method {
+        Method::Stouffer => {
+            // Keep z finite for p = 1
+            let z: f64 = p_values.iter().map(|&p| inverse_normal_cdf(1.0 - p.min(1.0 - 1e-12))).sum();
+            let variance = k as f64 + 2.0 * pairs.map(|(i, j)| dependence.rho(i, j)).sum::<f64>();
+            let statistic = z / variance.max(1e-12).sqrt();
+            MetaResult {
+                method,
+                n_markets: k,
+                statistic,
+                p_value: 1.0 - normal_cdf(statistic),
+                effective_markets: (k * k) as f64 / variance.max(1e-12),
+            }
+        }
+        Method::Fisher => {
+            let statistic = -2.0 * p_values.iter().map(|p| p.ln()).sum::<f64>();
+            let mean = 2.0 * k as f64;
+            let covariance: f64 = pairs
+                .map(|(i, j)| {
+                    let rho = dependence.rho(i, j);
+                    3.263 * rho + 0.710 * rho * rho + 0.027 * rho * rho * rho
+                })
+                .sum();
+            let variance = (4.0 * k as f64 + 2.0 * covariance).max(1e-12);
+            // Scaled chi-square c * chi2(f) with the same mean and variance
+            let scale = variance / (2.0 * mean);
+            let df = 2.0 * mean * mean / variance;
+            MetaResult {
+                method,
+                n_markets: k,
+                statistic,
+                p_value: 1.0 - igamma(0.5 * df, 0.5 * statistic / scale),
+                effective_markets: df / 2.0,
+            }
+        }
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_independent() {
+        // One market: both methods return its own p-value
+        for method in [Method::Fisher, Method::Stouffer] {
+            let r = combine_p_values(&[0.2], method, &Dependence::Independent).unwrap();
+            assert!((r.p_value - 0.2).abs() < 2e-3, "{:?} {}", method, r.p_value);
+        }
+
+        // Fisher with two markets: P(chi2(4) > x) = exp(-x/2) (1 + x/2)
+        let r = combine_p_values(&[0.05, 0.1], Method::Fisher, &Dependence::Independent).unwrap();
+        let x = -2.0 * (0.05_f64.ln() + 0.1_f64.ln());
+        assert!((r.statistic - x).abs() < 1e-12);
+        assert!((r.p_value - (-x / 2.0).exp() * (1.0 + x / 2.0)).abs() < 1e-6);
+        assert_eq!(r.effective_markets, 2.0);
+
+        // Many weak results add up
+        let r = combine_p_values(&[0.1; 10], Method::Stouffer, &Dependence::Independent).unwrap();
+        assert!(r.p_value < 0.001);
+    }
+
+    #[test]
+    fn test_dependence_discounts_evidence() {
+        let p = [0.1; 10];
+        for method in [Method::Fisher, Method::Stouffer] {
+            let independent = combine_p_values(&p, method, &Dependence::Independent).unwrap();
+            let dependent = combine_p_values(&p, method, &Dependence::MeanCorrelation(0.5)).unwrap();
+            assert!(dependent.p_value > independent.p_value);
+            assert!(dependent.effective_markets < 10.0);
+
+            // Perfectly correlated markets are worth one market
+            let same = combine_p_values(&p, method, &Dependence::MeanCorrelation(1.0)).unwrap();
+            assert!((same.effective_markets - 1.0).abs() < 0.05, "{:?}", same);
+        }
+    }
+
+    #[test]
+    fn test_from_returns_and_errors() {
+        let a = [1.0, 2.0, 3.0, 4.0];
+        let b = [2.0, 4.0, 6.0, 8.0];
+        let c = [4.0, 3.0, 2.0, 1.0];
+        let Dependence::Correlation(m) = Dependence::from_returns(&[&a, &b, &c]).unwrap() else {
+            panic!("expected a correlation matrix");
+        };
+        assert!((m[0][1] - 1.0).abs() < 1e-12);
+        assert!((m[0][2] + 1.0).abs() < 1e-12);
+        assert!(Dependence::from_returns(&[&a, &b[..3]]).is_err());
+
+        assert!(combine_p_values(&[], Method::Fisher, &Dependence::Independent).is_err());
+        assert!(combine_p_values(&[0.0], Method::Fisher, &Dependence::Independent).is_err());
+        assert!(combine_p_values(&[0.1, 0.2], Method::Fisher, &Dependence::Correlation(vec![vec![1.0]])).is_err());
+        assert_eq!("Fisher".parse::<Method>(), Ok(Method::Fisher));
+    }
+}
@@ -2,3 +2,14 @@ use matlib::Mwc256;
 
 // Re-export Mwc256 as Rand32M to maintain compatibility
 pub type Rand32M = Mwc256;
+
+/// Default master seed of the replications, the seed of `Rand32M::new`
+pub const DEFAULT_SEED: u32 = 123456789;
+
+/// Derive the RNG seed of one replication from the master seed.
+///
+/// Every replication permutes with its own generator seeded with this value,
+/// so results do not depend on which thread runs it or in what order.
+pub fn rep_seed(master_seed: u32, irep: usize) -> u32 {
+    master_seed.wrapping_add((irep as u32).wrapping_mul(0x9E37_79B9))
+}
@@ -31,6 +31,7 @@ struct Args {
 }
 
 use matlib::{Mwc256, qsortd, ind_targ, find_beta};
+use statn::walkforward::WalkForward;
 
 fn main() {
     let mut args = Args::parse();
@@ -43,6 +44,7 @@ fn main() {
         || args.lookback < 2
         || args.lookahead < 1
         || args.ntrain < 2
+        || args.omit >= args.ntrain
         || args.ntest < 1
     {
         eprintln!("Error: Invalid parameters");
@@ -61,6 +63,12 @@ fn main() {
         args.nprices, args.lookback, args.lookahead, args.ntrain, args.ntest, args.omit, args.extra
     );
 
+    let walkforward = WalkForward {
+        step: args.ntest + args.extra,
+        embargo: args.omit,
+        ..WalkForward::new(args.ntrain - args.omit, args.ntest)
+    };
+
     let mut rng = Mwc256::with_seed(123456789);
     let mut save_t = vec![0.0; args.nreps];
     let mut p1_count = 0;
@@ -81,45 +89,25 @@ fn main() {
 
         let ncases = data.len();
 
-        // Perform walkforward validation
+        // Perform walkforward validation. The last `omit` training cases are
+        // an embargo before each test set, and `extra` cases are skipped
+        // between folds.
         let mut oos = Vec::new();
-        let mut trn_start = 0;
-        let mut istart = args.ntrain;
-
-        loop {
-            let test_start = trn_start + args.ntrain;
-            if test_start >= ncases {
-                break;
-            }
-
-            // Train on ntrain - omit cases
-            let train_data = &data[trn_start..(trn_start + args.ntrain - args.omit)];
-            let (beta, constant) = find_beta(train_data);
-
-            // Test on ntest cases (or fewer if at end)
-            let mut nt = args.ntest;
-            if nt > ncases - istart {
-                nt = ncases - istart;
-            }
-
-            for itest in 0..nt {
-                let test_idx = test_start + itest;
-                if test_idx >= ncases {
-                    break;
+        walkforward.run(
+            ncases,
+            |fold| find_beta(&data[fold.train()]),
+            |fold, &(beta, constant)| {
+                for &(ind, targ) in &data[fold.test()] {
+                    let pred = beta * ind + constant;
+
+                    if pred > 0.0 {
+                        oos.push(targ);
+                    } else {
+                        oos.push(-targ);
+                    }
                 }
-                let (ind, targ) = data[test_idx];
-                let pred = beta * ind + constant;
-
-                if pred > 0.0 {
-                    oos.push(targ);
-                } else {
-                    oos.push(-targ);
-                }
-            }
-
-            istart += nt + args.extra;
-            trn_start += nt + args.extra;
-        }
+            },
+        );
 
         // Analyze results
         let n_oos = oos.len();
@@ -149,36 +137,9 @@ fn main() {
     if !save_t.is_empty() {
         qsortd(0, save_t.len() - 1, &mut save_t);
     }
-    let n_oos = {
-        // Recalculate n_oos for the last replication (they should all be the same)
-        let mut x = vec![0.0; args.nprices];
-        for i in 1..args.nprices {
-            x[i] = x[i - 1] + rng.unifrand() + rng.unifrand() - rng.unifrand() - rng.unifrand();
-        }
-        let mut data = Vec::new();
-        for i in 0..(args.nprices - args.lookback - args.lookahead + 1) {
-            let (ind, targ) = ind_targ(args.lookback, args.lookahead, &x, i + args.lookback - 1);
-            data.push((ind, targ));
-        }
-        let ncases = data.len();
-        let mut count = 0;
-        let mut istart = args.ntrain;
-        let mut trn_start = 0;
-        loop {
-            let test_start = trn_start + args.ntrain;
-            if test_start >= ncases {
-                break;
-            }
-            let mut nt = args.ntest;
-            if nt > ncases - istart {
-                nt = ncases - istart;
-            }
-            count += nt;
-            istart += nt + args.extra;
-            trn_start += nt + args.extra;
-        }
-        count
-    };
+    // Every replication has the same number of cases and so the same folds
+    let ncases = args.nprices - args.lookback - args.lookahead + 1;
+    let n_oos: usize = walkforward.windows(ncases).iter().map(|fold| fold.n_test()).sum();
 
     println!(
         "\nn OOS = {}  Median t = {:.4}  Fraction with p<= 0.1 = {:.3}",
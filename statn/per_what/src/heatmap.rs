@@ -0,0 +1,164 @@
+use std::fmt::Write as _;
+use std::fs;
+
+use crate::walkforward::{oos_performance, run_walkforward, WalkforwardConfig};
+
+/// OOS performance of the walkforward for every (n_train, n_test) pair
+#[derive(Debug, Clone)]
+pub struct HeatMap {
+    pub train_grid: Vec<usize>,
+    pub test_grid: Vec<usize>,
+    /// `perf[i][j]` is for `train_grid[i]` and `test_grid[j]`; `None` if the
+    /// combination is invalid for this history or produced no returns
+    pub perf: Vec<Vec<Option<f64>>>,
+    /// Number of pooled OOS returns behind each cell
+    pub nret: Vec<Vec<usize>>,
+}
+
+/// Run the walkforward for every combination of training and testing lengths.
+///
+/// `base` supplies everything except the window lengths.
+pub fn heat_map(
+    prices: &[f64],
+    base: &WalkforwardConfig,
+    train_grid: &[usize],
+    test_grid: &[usize],
+) -> HeatMap {
+    let mut perf = Vec::with_capacity(train_grid.len());
+    let mut nret = Vec::with_capacity(train_grid.len());
+
+    for &n_train in train_grid {
+        let mut perf_row = Vec::with_capacity(test_grid.len());
+        let mut nret_row = Vec::with_capacity(test_grid.len());
+        for &n_test in test_grid {
+            let config = WalkforwardConfig { n_train, n_test, ..*base };
+            if config.validate(prices.len()).is_err() {
+                perf_row.push(None);
+                nret_row.push(0);
+                continue;
+            }
+            let result = run_walkforward(prices, &config);
+            perf_row.push(oos_performance(config.which_crit, &result.returns));
+            nret_row.push(result.returns.len());
+        }
+        perf.push(perf_row);
+        nret.push(nret_row);
+    }
+
+    HeatMap {
+        train_grid: train_grid.to_vec(),
+        test_grid: test_grid.to_vec(),
+        perf,
+        nret,
+    }
+}
+
+impl HeatMap {
+    /// Cell with the best OOS performance as (n_train, n_test, perf)
+    pub fn best(&self) -> Option<(usize, usize, f64)> {
+        let mut best: Option<(usize, usize, f64)> = None;
+        for (i, row) in self.perf.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let Some(p) = *cell {
+                    if best.is_none_or(|(_, _, b)| p > b) {
+                        best = Some((self.train_grid[i], self.test_grid[j], p));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Fixed-width table with n_train down the side and n_test across the top.
+    /// Performance is multiplied by `mult` for display.
+    pub fn to_table(&self, mult: f64) -> String {
+        let mut out = String::new();
+        let _ = write!(out, "{:>10}", "Train\\Test");
+        for n_test in &self.test_grid {
+            let _ = write!(out, " {:>10}", n_test);
+        }
+        out.push('\n');
+
+        for (i, row) in self.perf.iter().enumerate() {
+            let _ = write!(out, "{:>10}", self.train_grid[i]);
+            for cell in row {
+                match cell {
+                    Some(p) => {
+                        let _ = write!(out, " {:>10.4}", mult * p);
+                    }
+                    None => {
+                        let _ = write!(out, " {:>10}", "-");
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// CSV matrix with a header row of n_test values; invalid cells are empty
+    pub fn to_csv(&self, mult: f64) -> String {
+        let mut out = String::from("n_train");
+        for n_test in &self.test_grid {
+            let _ = write!(out, ",{}", n_test);
+        }
+        out.push('\n');
+
+        for (i, row) in self.perf.iter().enumerate() {
+            let _ = write!(out, "{}", self.train_grid[i]);
+            for cell in row {
+                match cell {
+                    Some(p) => {
+                        let _ = write!(out, ",{}", mult * p);
+                    }
+                    None => out.push(','),
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn write_csv(&self, path: &str, mult: f64) -> std::io::Result<()> {
+        fs::write(path, self.to_csv(mult))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{OptimizationCriterion, ReturnType};
+    use crate::walkforward::WindowMode;
+
+    #[test]
+    fn test_heat_map_shape_and_invalid_cells() {
+        let prices: Vec<f64> = (0..300)
+            .map(|i| 0.001 * i as f64 + 0.02 * ((i as f64) * 0.3).sin())
+            .collect();
+        let base = WalkforwardConfig {
+            which_crit: OptimizationCriterion::SharpeRatio,
+            all_bars: true,
+            ret_type: ReturnType::AllBars,
+            max_lookback: 20,
+            n_train: 0,
+            n_test: 0,
+            window: WindowMode::Rolling,
+            embargo: 0,
+            half_life: 0.0,
+            vol_norm: None,
+            penalty: Default::default(),
+        };
+
+        let map = heat_map(&prices, &base, &[25, 100, 250], &[50, 100]);
+        assert_eq!(map.perf.len(), 3);
+        // n_train=25 is too short for the lookback, 250 + 100 exceeds the history
+        assert_eq!(map.perf[0], vec![None, None]);
+        assert!(map.perf[1].iter().all(|c| c.is_some()));
+        assert!(map.perf[2][0].is_some() && map.perf[2][1].is_none());
+        assert_eq!(map.nret[1][0], 200);
+
+        let csv = map.to_csv(1.0);
+        assert!(csv.starts_with("n_train,50,100\n25,,\n"));
+        assert!(map.best().is_some());
+    }
+}
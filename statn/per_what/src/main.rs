@@ -2,10 +2,18 @@ use clap::Parser;
 use anyhow::Result;
 
 
+mod heatmap;
 mod market;
+mod pooling;
 mod system;
+mod volatility;
+mod walkforward;
 
 use system::{OptimizationCriterion, ReturnType};
+use market::MarketHistory;
+use volatility::VolEstimator;
+use statn::estimators::criteria::{CompositeCriterion, TradeCountPenalty};
+use walkforward::{break_even, oos_performance, WalkforwardConfig, WalkforwardResult, WindowMode};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -34,9 +42,67 @@ struct Args {
     #[arg(long, default_value_t = 1000)]
     n_test: usize,
 
+    /// Training window: 0=rolling; 1=anchored (expanding); 2=compare both
+    #[arg(long, default_value_t = 0)]
+    window: i32,
+
+    /// Bars skipped between each training set and its test set
+    #[arg(long, default_value_t = 0)]
+    embargo: usize,
+
+    /// Half-life in bars of exponential time-decay weights on training bars
+    /// (0 = equal weights)
+    #[arg(long, default_value_t = 0.0)]
+    half_life: f64,
+
+    /// Divide training returns by a volatility forecast before computing the
+    /// criterion: 0=no; 1=rolling RMS of --vol-lookback returns; 2=GARCH(1,1)
+    #[arg(long, default_value_t = 0)]
+    vol_norm: i32,
+
+    /// Number of returns in the rolling volatility estimate
+    #[arg(long, default_value_t = 20)]
+    vol_lookback: usize,
+
+    /// Minimum number of entries in a training set; 0 = no minimum
+    #[arg(long, default_value_t = 0)]
+    min_trades: i32,
+
+    /// Weight of a smooth quadratic penalty for missing --min-trades
+    /// (0 = reject parameters below the minimum)
+    #[arg(long, default_value_t = 0.0)]
+    trade_penalty: f64,
+
+    /// Penalty on the training criterion per unit of turnover (position
+    /// changes per bar), in units of the criterion
+    #[arg(long, default_value_t = 0.0)]
+    turnover_penalty: f64,
+
     /// Market file (YYYYMMDD Price)
     #[arg(long)]
     filename: String,
+
+    /// File listing basket markets, one market file per line. Parameters are
+    /// trained on --filename and tested on every market in the basket.
+    #[arg(long)]
+    universe: Option<String>,
+
+    /// With --universe: fit each fold's parameters to the pooled, volatility
+    /// normalized returns of all markets and compare with per-market fits
+    #[arg(long)]
+    pool: bool,
+
+    /// Comma-separated training lengths for a heat map study, e.g. 500,1000,2000
+    #[arg(long, value_delimiter = ',')]
+    train_grid: Vec<usize>,
+
+    /// Comma-separated testing lengths for a heat map study, e.g. 100,250,500
+    #[arg(long, value_delimiter = ',')]
+    test_grid: Vec<usize>,
+
+    /// Output file for the heat map matrix
+    #[arg(long, default_value = "HEATMAP.CSV")]
+    heatmap_file: String,
 }
 
 fn main() -> Result<()> {
@@ -48,19 +114,19 @@ fn main() -> Result<()> {
     let max_lookback = args.max_lookback;
     let n_train = args.n_train;
     let n_test = args.n_test;
-    let filename = args.filename;
+    let heat_map_study = !args.train_grid.is_empty() || !args.test_grid.is_empty();
 
-    if n_train < max_lookback + 10 {
+    if !heat_map_study && n_train < max_lookback + 10 {
         anyhow::bail!("n_train must be at least 10 greater than max_lookback");
     }
 
     println!("Reading market file...");
-    let prices = market::read_market_prices(&filename)?;
+    let prices = market::read_market_prices(&args.filename)?;
     let nprices = prices.len();
     println!("Market price history read: {} prices", nprices);
 
-    if n_train + n_test > nprices {
-        anyhow::bail!("n_train + n_test must not exceed n_prices");
+    if !heat_map_study && n_train + args.embargo + n_test > nprices {
+        anyhow::bail!("n_train + embargo + n_test must not exceed n_prices");
     }
 
     let mult = if which_crit == OptimizationCriterion::MeanReturn {
@@ -70,122 +136,400 @@ fn main() -> Result<()> {
         1.0
     };
 
-    let mut train_start = 0;
-    let mut nret = 0;
-    let mut all_returns = Vec::new();
-
-    loop {
-        // Train
-        // We pass a slice of prices starting at train_start
-        // The length of the slice should be n_train
-        // But opt_params expects to be able to look back from the end of the slice?
-        // No, opt_params iterates from max_lookback-1 to nprices-1.
-        // So we should pass exactly the training set.
-        
-        let train_prices = &prices[train_start..train_start + n_train];
-        
-        let (lookback, thresh, last_pos, crit) = system::opt_params(
-            which_crit,
-            all_bars,
-            train_prices,
-            max_lookback,
-        );
+    let vol_norm = match args.vol_norm {
+        0 => None,
+        1 => Some(VolEstimator::Rolling(args.vol_lookback)),
+        2 => Some(VolEstimator::Garch),
+        v => anyhow::bail!("--vol-norm must be 0, 1 or 2, got {}", v),
+    };
+    if args.vol_lookback == 0 {
+        anyhow::bail!("--vol-lookback must be positive");
+    }
+
+    let penalty = CompositeCriterion {
+        min_trades: args.min_trades,
+        trade_penalty: if args.trade_penalty > 0.0 {
+            TradeCountPenalty::Quadratic(args.trade_penalty)
+        } else {
+            TradeCountPenalty::Hard
+        },
+        turnover_lambda: args.turnover_penalty,
+    };
+
+    let config = WalkforwardConfig {
+        which_crit,
+        all_bars,
+        ret_type,
+        max_lookback,
+        n_train,
+        n_test,
+        window: walkforward::window_mode(args.window),
+        embargo: args.embargo,
+        half_life: args.half_life,
+        vol_norm,
+        penalty,
+    };
+
+    if heat_map_study {
+        return run_heat_map(&args, &prices, &config, mult);
+    }
+
+    if let Some(universe) = &args.universe {
+        if args.pool {
+            return run_pooling(&args.filename, universe, &config, mult);
+        }
+        return run_basket(&args.filename, universe, &config, mult);
+    }
+    if args.pool {
+        anyhow::bail!("--pool needs a basket of markets given with --universe");
+    }
+
+    if args.window == 2 {
+        compare_windows(&prices, &config, mult);
+        return Ok(());
+    }
 
+    let result = walkforward::run_walkforward(&prices, &config);
+    let mut nret = 0;
+    for fold in &result.folds {
         println!(
             " IS at {}  Lookback={}  Thresh={:.3}  Crit={:.3}",
-            train_start,
-            lookback,
-            thresh,
-            mult * crit
+            fold.train_start,
+            fold.lookback,
+            fold.thresh,
+            mult * fold.crit
         );
+        nret += fold.n_returns;
+        println!(
+            "OOS testing {} from {} had {} returns, total={}",
+            fold.n_tested, fold.test_start, fold.n_returns, nret
+        );
+    }
+
+    println!(
+        "\n\nnprices={}  max_lookback={}  which_crit={:?}  all_bars={}  ret_type={:?}  n_train={}  n_test={}  window={:?}  embargo={}  half_life={}  vol_norm={:?}",
+        nprices, max_lookback, args.which_crit, args.all_bars, args.ret_type, n_train, n_test, config.window, args.embargo,
+        args.half_life, config.vol_norm
+    );
+
+    match oos_performance(which_crit, &result.returns) {
+        Some(perf) => match which_crit {
+            OptimizationCriterion::MeanReturn => println!(
+                "\n\nOOS mean return per open-trade bar (times 25200) = {:.5}  nret={}",
+                25200.0 * perf,
+                nret
+            ),
+            OptimizationCriterion::ProfitFactor => {
+                println!("\n\nOOS profit factor = {:.5}  nret={}", perf, nret)
+            }
+            OptimizationCriterion::SharpeRatio => {
+                println!("\n\nOOS raw Sharpe ratio = {:.5}  nret={}", perf, nret)
+            }
+        },
+        None => println!("\n\nNo returns generated."),
+    }
+
+    Ok(())
+}
 
-        let mut n = n_test;
-        if n > nprices - train_start - n_train {
-            n = nprices - train_start - n_train;
+/// Sweep the (n_train, n_test) grid and report the OOS performance matrix.
+/// A grid that was not given falls back to the single --n-train/--n-test value.
+fn run_heat_map(args: &Args, prices: &[f64], config: &WalkforwardConfig, mult: f64) -> Result<()> {
+    let train_grid = if args.train_grid.is_empty() { vec![args.n_train] } else { args.train_grid.clone() };
+    let test_grid = if args.test_grid.is_empty() { vec![args.n_test] } else { args.test_grid.clone() };
+
+    println!(
+        "\nWalkforward heat map: {} training lengths x {} testing lengths",
+        train_grid.len(),
+        test_grid.len()
+    );
+
+    let map = heatmap::heat_map(prices, config, &train_grid, &test_grid);
+
+    println!("\nOOS {:?} (rows n_train, columns n_test; - = invalid)\n", config.which_crit);
+    print!("{}", map.to_table(mult));
+
+    println!("\nNumber of OOS returns behind each cell\n");
+    print!("{:>10}", "Train\\Test");
+    for n_test in &test_grid {
+        print!(" {:>10}", n_test);
+    }
+    println!();
+    for (n_train, row) in train_grid.iter().zip(&map.nret) {
+        print!("{:>10}", n_train);
+        for n in row {
+            print!(" {:>10}", n);
         }
-        
-        if n == 0 {
-            break;
+        println!();
+    }
+
+    match map.best() {
+        Some((n_train, n_test, perf)) => println!(
+            "\nBest OOS performance {:.5} at n_train={}  n_test={}",
+            mult * perf,
+            n_train,
+            n_test
+        ),
+        None => println!("\nNo valid (n_train, n_test) combination"),
+    }
+
+    map.write_csv(&args.heatmap_file, mult)?;
+    println!("Heat map written to {}", args.heatmap_file);
+
+    Ok(())
+}
+
+/// Run the rolling and anchored walkforwards on the same data and report them
+/// side by side. Both test exactly the same OOS bars, so the per-fold
+/// differences isolate the effect of the extra (older) training history.
+fn compare_windows(prices: &[f64], config: &WalkforwardConfig, mult: f64) {
+    let rolling = walkforward::run_walkforward(prices, &WalkforwardConfig { window: WindowMode::Rolling, ..*config });
+    let anchored = walkforward::run_walkforward(prices, &WalkforwardConfig { window: WindowMode::Anchored, ..*config });
+
+    println!(
+        "\nRolling vs anchored training window (n_train={}  n_test={}  embargo={})\n",
+        config.n_train, config.n_test, config.embargo
+    );
+    println!(
+        "{:>8} {:>8} | {:>8} {:>7} {:>10} | {:>8} {:>7} {:>10}",
+        "OOS at", "Bars", "Roll LB", "Thresh", "Roll OOS", "Anch LB", "Thresh", "Anch OOS"
+    );
+
+    let fmt_perf = |returns: &[f64]| match oos_performance(config.which_crit, returns) {
+        Some(p) => format!("{:.4}", mult * p),
+        None => "-".to_string(),
+    };
+
+    let mut n_better = 0;
+    let mut n_compared = 0;
+    let rolling_returns = rolling.fold_returns();
+    let anchored_returns = anchored.fold_returns();
+    for (i, (r, a)) in rolling.folds.iter().zip(&anchored.folds).enumerate() {
+        println!(
+            "{:>8} {:>8} | {:>8} {:>7.3} {:>10} | {:>8} {:>7.3} {:>10}",
+            r.test_start,
+            r.n_tested,
+            r.lookback,
+            r.thresh,
+            fmt_perf(rolling_returns[i]),
+            a.lookback,
+            a.thresh,
+            fmt_perf(anchored_returns[i])
+        );
+        if let (Some(rp), Some(ap)) = (
+            oos_performance(config.which_crit, rolling_returns[i]),
+            oos_performance(config.which_crit, anchored_returns[i]),
+        ) {
+            n_compared += 1;
+            if ap > rp {
+                n_better += 1;
+            }
         }
+    }
 
-        // Test
-        // comp_return_full needs the full prices array (or at least enough context)
-        // and the index where the test set starts.
-        // The test set starts at train_start + n_train.
-        
-        let test_start_idx = train_start + n_train;
-        
-        let returns = system::comp_return_full(
-            ret_type,
-            &prices,
-            test_start_idx,
-            n,
-            lookback,
-            thresh,
-            last_pos,
+    let summary = |name: &str, result: &WalkforwardResult| {
+        let changes = result
+            .folds
+            .windows(2)
+            .filter(|w| w[0].lookback != w[1].lookback || w[0].thresh != w[1].thresh)
+            .count();
+        println!(
+            "{:<9} pooled OOS = {:>10}  nret={}  parameter changes={}",
+            name,
+            fmt_perf(&result.returns),
+            result.returns.len(),
+            changes
         );
+    };
+
+    println!();
+    summary("Rolling", &rolling);
+    summary("Anchored", &anchored);
+    println!(
+        "\nAnchored beat rolling in {} of {} folds with returns in both",
+        n_better, n_compared
+    );
+}
+
+/// Read the reference market and the basket, aligned to their common dates
+fn read_markets(reference_file: &str, universe: &str) -> Result<Vec<MarketHistory>> {
+    let mut markets: Vec<MarketHistory> = vec![market::read_market_history(reference_file)?];
+    markets.extend(market::read_universe(universe)?);
+    market::align_dates(&mut markets);
+    Ok(markets)
+}
+
+/// Train on the reference market and test on every market of the basket
+fn run_basket(reference_file: &str, universe: &str, config: &WalkforwardConfig, mult: f64) -> Result<()> {
+    let markets = read_markets(reference_file, universe)?;
+
+    let nprices = markets[0].prices.len();
+    println!(
+        "\nBasket of {} markets aligned to {} common dates; training on {}",
+        markets.len() - 1,
+        nprices,
+        markets[0].name
+    );
+    config.validate(nprices).map_err(anyhow::Error::msg)?;
 
-        let n_returns = returns.len();
-        nret += n_returns;
-        all_returns.extend(returns);
+    let basket: Vec<&[f64]> = markets[1..].iter().map(|m| m.prices.as_slice()).collect();
+    let result = walkforward::run_basket_walkforward(&markets[0].prices, &basket, config);
 
+    for fold in &result.reference.folds {
         println!(
-            "OOS testing {} from {} had {} returns, total={}",
-            n, test_start_idx, n_returns, nret
+            " IS at {}  Lookback={}  Thresh={:.3}  Crit={:.3}  OOS {} bars from {}",
+            fold.train_start,
+            fold.lookback,
+            fold.thresh,
+            mult * fold.crit,
+            fold.n_tested,
+            fold.test_start
         );
+    }
 
-        // Advance fold window
-        train_start += n;
-        if train_start + n_train >= nprices {
-            break;
-        }
+    let reference_returns = &result.reference.returns;
+    let reference_perf = oos_performance(config.which_crit, reference_returns);
+
+    println!("\n{:<16} {:>8} {:>12}", "Market", "nret", "OOS");
+    let fmt_perf = |perf: Option<f64>| match perf {
+        Some(p) => format!("{:.4}", mult * p),
+        None => "-".to_string(),
+    };
+    println!(
+        "{:<16} {:>8} {:>12}  (reference)",
+        markets[0].name,
+        reference_returns.len(),
+        fmt_perf(reference_perf)
+    );
+
+    let mut perfs = Vec::new();
+    for (market, returns) in markets[1..].iter().zip(&result.returns) {
+        let perf = oos_performance(config.which_crit, returns);
+        println!("{:<16} {:>8} {:>12}", market.name, returns.len(), fmt_perf(perf));
+        perfs.extend(perf);
+    }
+
+    println!("\nCross-market generalization");
+    if perfs.is_empty() {
+        println!("No basket market produced OOS returns");
+        return Ok(());
+    }
+    let n_profitable = perfs.iter().filter(|&&p| p > break_even(config.which_crit)).count();
+    perfs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = if perfs.len() % 2 == 1 {
+        perfs[perfs.len() / 2]
+    } else {
+        0.5 * (perfs[perfs.len() / 2 - 1] + perfs[perfs.len() / 2])
+    };
+    println!(
+        "  Profitable OOS in {} of {} markets with returns",
+        n_profitable,
+        perfs.len()
+    );
+    println!("  Median basket OOS = {:.4}", mult * median);
+    println!(
+        "  Worst / best basket OOS = {:.4} / {:.4}",
+        mult * perfs[0],
+        mult * perfs[perfs.len() - 1]
+    );
+    if let Some(r) = reference_perf {
+        let n_below = perfs.iter().filter(|&&p| p < r).count();
+        println!(
+            "  Reference OOS {:.4} beats {} of {} basket markets",
+            mult * r,
+            n_below,
+            perfs.len()
+        );
     }
 
+    Ok(())
+}
+
+/// Fit parameters to all markets pooled and compare with fitting each market alone
+fn run_pooling(reference_file: &str, universe: &str, config: &WalkforwardConfig, mult: f64) -> Result<()> {
+    let markets = read_markets(reference_file, universe)?;
+    let nprices = markets[0].prices.len();
     println!(
-        "\n\nnprices={}  max_lookback={}  which_crit={:?}  all_bars={}  ret_type={:?}  n_train={}  n_test={}",
-        nprices, max_lookback, args.which_crit, args.all_bars, args.ret_type, n_train, n_test
+        "\nPooling {} markets over {} common dates",
+        markets.len(),
+        nprices
     );
+    config.validate(nprices).map_err(anyhow::Error::msg)?;
 
-    if nret > 0 {
-        match which_crit {
-            OptimizationCriterion::MeanReturn => {
-                let sum: f64 = all_returns.iter().sum();
-                let mean = sum / nret as f64;
-                println!(
-                    "\n\nOOS mean return per open-trade bar (times 25200) = {:.5}  nret={}",
-                    25200.0 * mean,
-                    nret
-                );
-            }
-            OptimizationCriterion::ProfitFactor => {
-                let mut win_sum = 1.0e-60;
-                let mut lose_sum = 1.0e-60;
-                for &r in &all_returns {
-                    if r > 0.0 {
-                        win_sum += r;
-                    } else if r < 0.0 {
-                        lose_sum -= r;
-                    }
-                }
-                let pf = win_sum / lose_sum;
-                println!("\n\nOOS profit factor = {:.5}  nret={}", pf, nret);
-            }
-            OptimizationCriterion::SharpeRatio => {
-                let sum: f64 = all_returns.iter().sum();
-                let sum_sq: f64 = all_returns.iter().map(|&r| r * r).sum();
-                let mean = sum / nret as f64;
-                let mean_sq = sum_sq / nret as f64;
-                let mut variance = mean_sq - mean * mean;
-                if variance < 1.0e-20 {
-                    variance = 1.0e-20;
-                }
-                let sr = mean / variance.sqrt();
-                println!("\n\nOOS raw Sharpe ratio = {:.5}  nret={}", sr, nret);
+    let series: Vec<&[f64]> = markets.iter().map(|m| m.prices.as_slice()).collect();
+    let comparison = pooling::compare_pooling(&series, config);
+
+    for fold in &comparison.pooled_folds {
+        println!(
+            " Pooled IS at {}  Lookback={}  Thresh={:.3}  Crit={:.3}",
+            fold.train_start,
+            fold.lookback,
+            fold.thresh,
+            mult * fold.crit
+        );
+    }
+
+    let fmt_perf = |returns: &[f64]| match oos_performance(config.which_crit, returns) {
+        Some(p) => format!("{:.4}", mult * p),
+        None => "-".to_string(),
+    };
+
+    println!(
+        "\n{:<16} {:>12} {:>12} {:>10} {:>10}",
+        "Market", "Own fit", "Pooled fit", "Changes", "Avg |dLB|"
+    );
+    let mut n_pooled_better = 0;
+    let mut n_compared = 0;
+    for (i, market) in markets.iter().enumerate() {
+        let own = &comparison.individual_returns[i];
+        let pooled = &comparison.pooled_returns[i];
+        println!(
+            "{:<16} {:>12} {:>12} {:>10} {:>10.2}",
+            market.name,
+            fmt_perf(own),
+            fmt_perf(pooled),
+            pooling::parameter_changes(&comparison.individual_folds[i]),
+            pooling::mean_lookback_change(&comparison.individual_folds[i])
+        );
+        if let (Some(o), Some(p)) = (
+            oos_performance(config.which_crit, own),
+            oos_performance(config.which_crit, pooled),
+        ) {
+            n_compared += 1;
+            if p > o {
+                n_pooled_better += 1;
             }
         }
-    } else {
-        println!("\n\nNo returns generated.");
     }
 
+    let n_folds = comparison.pooled_folds.len();
+    let mean_changes = comparison
+        .individual_folds
+        .iter()
+        .map(|f| pooling::parameter_changes(f) as f64)
+        .sum::<f64>()
+        / markets.len() as f64;
+    let mean_dlb = comparison
+        .individual_folds
+        .iter()
+        .map(|f| pooling::mean_lookback_change(f))
+        .sum::<f64>()
+        / markets.len() as f64;
+
+    println!("\nParameter stability over {} folds", n_folds);
+    println!(
+        "  Pooled fit:   {} parameter changes, mean |change in lookback| = {:.2}",
+        pooling::parameter_changes(&comparison.pooled_folds),
+        pooling::mean_lookback_change(&comparison.pooled_folds)
+    );
+    println!(
+        "  Own fits:     {:.2} parameter changes, mean |change in lookback| = {:.2} (average over markets)",
+        mean_changes, mean_dlb
+    );
+    println!(
+        "\nPooled parameters beat each market's own fit OOS in {} of {} markets",
+        n_pooled_better, n_compared
+    );
+
     Ok(())
 }
@@ -3,13 +3,33 @@ use std::io::{BufRead, BufReader};
 use std::path::Path;
 use anyhow::{Context, Result};
 
+/// Price history of one market
+#[derive(Debug, Clone)]
+pub struct MarketHistory {
+    pub name: String,
+    pub dates: Vec<u32>,
+    /// Log prices
+    pub prices: Vec<f64>,
+}
+
 /// Reads market prices from a file.
 /// Expected format: YYYYMMDD Price
 /// Returns a vector of log prices.
 pub fn read_market_prices(filename: &str) -> Result<Vec<f64>> {
+    Ok(read_market_history(filename)?.prices)
+}
+
+/// Reads dates and log prices from a market file (YYYYMMDD Price).
+/// The market is named after the file stem.
+pub fn read_market_history(filename: &str) -> Result<MarketHistory> {
     let path = Path::new(filename);
     let file = File::open(path).with_context(|| format!("Cannot open market history file {}", filename))?;
     let reader = BufReader::new(file);
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| filename.to_string());
+    let mut dates = Vec::new();
     let mut prices = Vec::new();
 
     for (line_num, line) in reader.lines().enumerate() {
@@ -24,12 +44,14 @@ pub fn read_market_prices(filename: &str) -> Result<Vec<f64>> {
         if line.len() < 8 || !line[..8].chars().all(|c| c.is_ascii_digit()) {
              return Err(anyhow::anyhow!("Invalid date reading line {} of file {}", line_num + 1, filename));
         }
+        let date: u32 = line[..8].parse()?;
 
         // Parse price
         // Find the start of the price (skip date and delimiters)
         let price_str = line[8..].trim_start_matches([' ', '\t', ',']);
         
         if let Ok(price) = price_str.parse::<f64>() {
+            dates.push(date);
             if price > 0.0 {
                 prices.push(price.ln());
             } else {
@@ -48,5 +70,72 @@ pub fn read_market_prices(filename: &str) -> Result<Vec<f64>> {
         }
     }
 
-    Ok(prices)
+    Ok(MarketHistory { name, dates, prices })
+}
+
+/// Reads every market named in a list file, one market file per line
+pub fn read_universe(list_file: &str) -> Result<Vec<MarketHistory>> {
+    let contents = std::fs::read_to_string(list_file)
+        .with_context(|| format!("Cannot open market list file {}", list_file))?;
+    let markets = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(read_market_history)
+        .collect::<Result<Vec<_>>>()?;
+    if markets.is_empty() {
+        anyhow::bail!("No markets listed in {}", list_file);
+    }
+    Ok(markets)
+}
+
+/// Keep only the dates present in every market, so that the same index refers
+/// to the same day in all of them
+pub fn align_dates(markets: &mut [MarketHistory]) {
+    let Some(first) = markets.first() else {
+        return;
+    };
+    let mut common = first.dates.clone();
+    for market in &markets[1..] {
+        common.retain(|d| market.dates.binary_search(d).is_ok());
+    }
+
+    for market in markets.iter_mut() {
+        let (dates, prices) = market
+            .dates
+            .iter()
+            .zip(&market.prices)
+            .filter(|(d, _)| common.binary_search(d).is_ok())
+            .map(|(&d, &p)| (d, p))
+            .unzip();
+        market.dates = dates;
+        market.prices = prices;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(name: &str, dates: &[u32]) -> MarketHistory {
+        MarketHistory {
+            name: name.to_string(),
+            dates: dates.to_vec(),
+            prices: dates.iter().map(|&d| d as f64).collect(),
+        }
+    }
+
+    #[test]
+    fn test_align_dates() {
+        let mut markets = vec![
+            market("A", &[20200101, 20200102, 20200103, 20200106]),
+            market("B", &[20200102, 20200103, 20200106, 20200107]),
+            market("C", &[20200101, 20200102, 20200106]),
+        ];
+        align_dates(&mut markets);
+        for m in &markets {
+            assert_eq!(m.dates, vec![20200102, 20200106]);
+            assert_eq!(m.prices, vec![20200102.0, 20200106.0]);
+        }
+    }
 }
@@ -0,0 +1,344 @@
+use statn::core::stats::RollingStats;
+use statn::estimators::criteria::CompositeCriterion;
+
+use crate::system::{self, OptimizationCriterion};
+use crate::walkforward::{run_walkforward, training_vol, Fold, WalkforwardConfig};
+
+/// One (lookback, threshold) trial over a training set
+struct Trial {
+    /// Bar returns exactly as `system::opt_params` counts them, including
+    /// division by `vol`
+    returns: Vec<f64>,
+    /// Time-decay weight of each return
+    weights: Vec<f64>,
+    /// Position at the end
+    position: i32,
+    n_entries: i32,
+    n_changes: usize,
+    n_bars: usize,
+}
+
+fn trial_returns(
+    prices: &[f64],
+    max_lookback: usize,
+    ilook: usize,
+    thresh: f64,
+    all_bars: bool,
+    half_life: f64,
+    vol: Option<&[f64]>,
+) -> Trial {
+    let mut returns = Vec::with_capacity(prices.len());
+    let mut weights = Vec::with_capacity(prices.len());
+    let mut position = 0;
+    let mut n_entries = 0;
+    let mut n_changes = 0;
+    let start_idx = max_lookback - 1;
+    let trial_thresh = 1.0 + thresh;
+
+    let mut ma = RollingStats::new(ilook);
+    for &price in &prices[start_idx + 1 - ilook..start_idx] {
+        ma.push(price);
+    }
+    for i in start_idx..prices.len() - 1 {
+        ma.push(prices[i]);
+        let ma_mean = ma.mean();
+
+        let prior_position = position;
+        if prices[i] > trial_thresh * ma_mean {
+            position = 1;
+        } else if prices[i] < ma_mean {
+            position = 0;
+        }
+        if position != prior_position {
+            n_changes += 1;
+            if position == 1 {
+                n_entries += 1;
+            }
+        }
+
+        if all_bars || position == 1 {
+            let ret = if position == 1 { prices[i + 1] - prices[i] } else { 0.0 };
+            returns.push(match vol {
+                Some(v) => ret / v[i],
+                None => ret,
+            });
+            weights.push(system::decay_weight(prices.len() - 2 - i, half_life));
+        }
+    }
+
+    Trial {
+        returns,
+        weights,
+        position,
+        n_entries,
+        n_changes,
+        n_bars: prices.len() - 1 - start_idx,
+    }
+}
+
+/// Optimization criterion of weighted returns, as computed by `system::opt_params`
+fn weighted_criterion(which_crit: OptimizationCriterion, returns: &[f64], weights: &[f64]) -> f64 {
+    let mut wsum = 0.0;
+    let mut total = 0.0;
+    let mut sum_squares = 1.0e-60;
+    let mut win_sum = 1.0e-60;
+    let mut lose_sum = 1.0e-60;
+    for (&r, &w) in returns.iter().zip(weights) {
+        wsum += w;
+        total += w * r;
+        sum_squares += w * r * r;
+        if r > 0.0 {
+            win_sum += w * r;
+        } else {
+            lose_sum -= w * r;
+        }
+    }
+
+    match which_crit {
+        OptimizationCriterion::MeanReturn => total / (wsum + 1.0e-30),
+        OptimizationCriterion::ProfitFactor => win_sum / lose_sum,
+        OptimizationCriterion::SharpeRatio => {
+            let mean = total / (wsum + 1.0e-30);
+            let variance = (sum_squares / (wsum + 1.0e-30) - mean * mean).max(1.0e-20);
+            mean / variance.sqrt()
+        }
+    }
+}
+
+/// Standard deviation of bar-to-bar log price changes
+fn return_std(prices: &[f64]) -> f64 {
+    let n = prices.len().saturating_sub(1);
+    if n < 2 {
+        return 1.0;
+    }
+    let changes = prices.windows(2).map(|w| w[1] - w[0]);
+    let mean = (prices[n] - prices[0]) / n as f64;
+    let var = changes.map(|d| (d - mean) * (d - mean)).sum::<f64>() / (n - 1) as f64;
+    if var > 0.0 { var.sqrt() } else { 1.0 }
+}
+
+/// Optimize one (lookback, threshold) for several markets at once.
+///
+/// Each market's trial returns are divided by the standard deviation of its
+/// bar returns over the training set, so that volatile markets do not dominate,
+/// and the criterion is computed on the pooled normalized returns, with the
+/// same time-decay weighting as `system::opt_params`. If `vols` gives per-bar
+/// volatility forecasts for every market, those replace the constant scale.
+/// The trade count and turnover given to `penalty` are the totals over all
+/// markets.
+///
+/// Returns (lookback, thresh, last position in each market, criterion).
+pub fn opt_params_pooled(
+    which_crit: OptimizationCriterion,
+    all_bars: bool,
+    markets: &[&[f64]],
+    max_lookback: usize,
+    half_life: f64,
+    vols: Option<&[Vec<f64>]>,
+    penalty: &CompositeCriterion,
+) -> (usize, f64, Vec<i32>, f64) {
+    let scales: Vec<f64> = match vols {
+        Some(_) => vec![1.0; markets.len()],
+        None => markets.iter().map(|p| 1.0 / return_std(p)).collect(),
+    };
+    let mut best = (0, 0.0, vec![0; markets.len()], -1.0e60);
+
+    for ilook in 2..=max_lookback {
+        for ithresh in 1..=10 {
+            let thresh = 0.01 * ithresh as f64;
+            let mut pooled = Vec::new();
+            let mut pooled_weights = Vec::new();
+            let mut last_pos = Vec::with_capacity(markets.len());
+            let (mut n_entries, mut n_changes, mut n_bars) = (0, 0, 0);
+            for (i, (prices, scale)) in markets.iter().zip(&scales).enumerate() {
+                let vol = vols.map(|v| v[i].as_slice());
+                let trial = trial_returns(prices, max_lookback, ilook, thresh, all_bars, half_life, vol);
+                pooled.extend(trial.returns.iter().map(|r| r * scale));
+                pooled_weights.extend(trial.weights);
+                last_pos.push(trial.position);
+                n_entries += trial.n_entries;
+                n_changes += trial.n_changes;
+                n_bars += trial.n_bars;
+            }
+
+            let perf = weighted_criterion(which_crit, &pooled, &pooled_weights);
+            let perf = penalty.apply(perf, n_entries, n_changes as f64 / n_bars as f64);
+            if perf > best.3 {
+                best = (ilook, thresh, last_pos, perf);
+            }
+        }
+    }
+
+    best
+}
+
+/// Per-market OOS returns of a walkforward whose parameters are fitted to the
+/// pooled training sets of all markets. Markets must be aligned by date.
+pub fn run_pooled_walkforward(markets: &[&[f64]], config: &WalkforwardConfig) -> (Vec<Fold>, Vec<Vec<f64>>) {
+    let mut all_returns = vec![Vec::new(); markets.len()];
+
+    let folds = config.layout().run(
+        markets[0].len(),
+        |window| {
+            let train_sets: Vec<&[f64]> = markets.iter().map(|p| &p[window.train()]).collect();
+            let vols: Option<Vec<Vec<f64>>> = markets
+                .iter()
+                .map(|p| training_vol(p, window.train_start, window.train_end, config))
+                .collect();
+            opt_params_pooled(
+                config.which_crit,
+                config.all_bars,
+                &train_sets,
+                config.max_lookback,
+                config.half_life,
+                vols.as_deref(),
+                &config.penalty,
+            )
+        },
+        |window, (lookback, thresh, last_pos, _)| {
+            let mut n_returns = 0;
+            for (i, prices) in markets.iter().enumerate() {
+                let start_pos = if config.embargo > 0 { 0 } else { last_pos[i] };
+                let returns = system::comp_return_full(
+                    config.ret_type,
+                    prices,
+                    window.test_start,
+                    window.n_test(),
+                    *lookback,
+                    *thresh,
+                    start_pos,
+                );
+                n_returns += returns.len();
+                all_returns[i].extend(returns);
+            }
+            n_returns
+        },
+    );
+
+    let folds = folds
+        .into_iter()
+        .map(|fold| {
+            let (lookback, thresh, _, crit) = fold.model;
+            Fold::new(&fold.window, lookback, thresh, crit, fold.result)
+        })
+        .collect();
+    (folds, all_returns)
+}
+
+/// Number of folds whose parameters differ from the previous fold's
+pub fn parameter_changes(folds: &[Fold]) -> usize {
+    folds
+        .windows(2)
+        .filter(|w| w[0].lookback != w[1].lookback || w[0].thresh != w[1].thresh)
+        .count()
+}
+
+/// Mean absolute change in lookback from one fold to the next
+pub fn mean_lookback_change(folds: &[Fold]) -> f64 {
+    if folds.len() < 2 {
+        return 0.0;
+    }
+    let total: usize = folds.windows(2).map(|w| w[0].lookback.abs_diff(w[1].lookback)).sum();
+    total as f64 / (folds.len() - 1) as f64
+}
+
+/// Pooled vs. per-market fits of the same markets
+#[derive(Debug, Clone)]
+pub struct PoolingComparison {
+    pub pooled_folds: Vec<Fold>,
+    pub pooled_returns: Vec<Vec<f64>>,
+    pub individual_folds: Vec<Vec<Fold>>,
+    pub individual_returns: Vec<Vec<f64>>,
+}
+
+/// Run the pooled walkforward and a separate walkforward for each market
+pub fn compare_pooling(markets: &[&[f64]], config: &WalkforwardConfig) -> PoolingComparison {
+    let (pooled_folds, pooled_returns) = run_pooled_walkforward(markets, config);
+    let (individual_folds, individual_returns) = markets
+        .iter()
+        .map(|prices| {
+            let result = run_walkforward(prices, config);
+            (result.folds, result.returns)
+        })
+        .unzip();
+
+    PoolingComparison {
+        pooled_folds,
+        pooled_returns,
+        individual_folds,
+        individual_returns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::ReturnType;
+    use crate::walkforward::WindowMode;
+    use statn::estimators::criteria::TradeCountPenalty;
+
+    fn prices(n: usize, phase: f64, scale: f64) -> Vec<f64> {
+        (0..n)
+            .map(|i| scale * (0.001 * i as f64 + 0.02 * ((i as f64) * 0.3 + phase).sin()))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_market_pooling_matches_opt_params() {
+        let p = prices(300, 0.0, 1.0);
+        let penalties = [
+            CompositeCriterion::default(),
+            CompositeCriterion {
+                min_trades: 15,
+                trade_penalty: TradeCountPenalty::Quadratic(0.5),
+                turnover_lambda: 2.0,
+            },
+        ];
+        for crit in [OptimizationCriterion::ProfitFactor, OptimizationCriterion::SharpeRatio] {
+            for half_life in [0.0, 50.0] {
+                for penalty in &penalties {
+                    let (look, thresh, _, perf) = system::opt_params(crit, false, &p, 20, half_life, None, penalty);
+                    let (plook, pthresh, _, pperf) = opt_params_pooled(crit, false, &[&p], 20, half_life, None, penalty);
+                    assert_eq!((look, thresh), (plook, pthresh));
+                    assert!((perf - pperf).abs() < 1e-9 * perf.abs().max(1.0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalization_removes_scale() {
+        let a = prices(300, 0.0, 1.0);
+        let b = prices(300, 1.0, 1.0);
+        let b_big = prices(300, 1.0, 50.0);
+        let small = opt_params_pooled(OptimizationCriterion::SharpeRatio, true, &[&a, &b], 20, 0.0, None, &Default::default());
+        let big = opt_params_pooled(OptimizationCriterion::SharpeRatio, true, &[&a, &b_big], 20, 0.0, None, &Default::default());
+        assert_eq!((small.0, small.1), (big.0, big.1));
+    }
+
+    #[test]
+    fn test_pooled_walkforward_shape() {
+        let a = prices(400, 0.0, 1.0);
+        let b = prices(400, 2.0, 1.0);
+        let config = WalkforwardConfig {
+            which_crit: OptimizationCriterion::ProfitFactor,
+            all_bars: false,
+            ret_type: ReturnType::AllBars,
+            max_lookback: 20,
+            n_train: 100,
+            n_test: 70,
+            window: WindowMode::Rolling,
+            embargo: 0,
+            half_life: 0.0,
+            vol_norm: None,
+            penalty: Default::default(),
+        };
+        let comparison = compare_pooling(&[&a, &b], &config);
+        assert_eq!(comparison.pooled_returns.len(), 2);
+        assert_eq!(comparison.pooled_returns[0].len(), 300);
+        assert_eq!(comparison.pooled_folds.len(), comparison.individual_folds[0].len());
+        // Fold counts cover all markets
+        assert_eq!(comparison.pooled_folds[0].n_returns, 140);
+        assert!(parameter_changes(&comparison.pooled_folds) < comparison.pooled_folds.len());
+    }
+}
@@ -1,3 +1,6 @@
+use statn::core::stats::RollingStats;
+use statn::estimators::criteria::CompositeCriterion;
+
 
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -36,12 +39,34 @@ impl From<i32> for ReturnType {
     }
 }
 
-/// Computes optimal lookback and breakout threshold
+/// Weight of a bar `age` bars before the most recent one under exponential
+/// time decay with the given half-life. A half-life of 0 disables decay.
+pub fn decay_weight(age: usize, half_life: f64) -> f64 {
+    if half_life > 0.0 {
+        0.5_f64.powf(age as f64 / half_life)
+    } else {
+        1.0
+    }
+}
+
+/// Computes optimal lookback and breakout threshold.
+///
+/// With a positive `half_life` each bar's return is weighted by
+/// `decay_weight`, so recent bars count more in the criterion. With `vol`
+/// (one forecast per price, see `volatility::conditional_vol`) each bar's
+/// return is divided by the volatility forecast for that bar before it enters
+/// the criterion.
+///
+/// The criterion is then penalized by `penalty`, with the number of entries as
+/// the trade count and position changes per bar as the turnover.
 pub fn opt_params(
     which_crit: OptimizationCriterion,
     all_bars: bool,
     prices: &[f64],
     max_lookback: usize,
+    half_life: f64,
+    vol: Option<&[f64]>,
+    penalty: &CompositeCriterion,
 ) -> (usize, f64, i32, Double) {
     let nprices = prices.len();
     let mut best_perf = -1.0e60;
@@ -55,71 +80,73 @@ pub fn opt_params(
             let mut win_sum = 1.0e-60;
             let mut lose_sum = 1.0e-60;
             let mut sum_squares = 1.0e-60;
-            let mut n_trades = 0;
+            let mut n_trades = 0.0;
             let mut position = 0;
+            let mut n_entries = 0;
+            let mut n_changes = 0;
 
-            // We need at least ilook history for the first MA calculation.
-            // But the loop in C++ starts at max_lookback-1 to make all trials comparable.
-            
+            // Every trial makes its first decision at max_lookback - 1, so
+            // that all of them are compared over the same bars
             let start_idx = max_lookback - 1;
-            
-            // We need to calculate MA ending at 'i'.
-            // MA is sum(prices[i-ilook+1..=i]) / ilook?
-            // C++:
-            // if (i == max_lookback-1) {
-            //    for (j=i ; j>i-ilook ; j--) MA_sum += prices[j] ;
-            // }
-            // This sums prices[i], prices[i-1] ... prices[i-ilook+1]. Correct.
-            
-            let mut ma_sum = 0.0;
-            // Initialize MA sum for the first iteration
-            for j in (start_idx + 1 - ilook)..=start_idx {
-                 ma_sum += prices[j];
+            let mut ma = RollingStats::new(ilook);
+            for &price in &prices[start_idx + 1 - ilook..start_idx] {
+                ma.push(price);
             }
 
             for i in start_idx..nprices - 1 {
-                if i > start_idx {
-                    ma_sum += prices[i] - prices[i - ilook];
-                }
-
-                let ma_mean = ma_sum / ilook as f64;
+                ma.push(prices[i]);
+                let ma_mean = ma.mean();
                 let trial_thresh = 1.0 + 0.01 * ithresh as f64;
 
+                let prior_position = position;
                 if prices[i] > trial_thresh * ma_mean {
                     position = 1;
                 } else if prices[i] < ma_mean {
                     position = 0;
                 }
+                if position != prior_position {
+                    n_changes += 1;
+                    if position == 1 {
+                        n_entries += 1;
+                    }
+                }
 
                 let ret = if position == 1 {
                     prices[i + 1] - prices[i]
                 } else {
                     0.0
                 };
+                let ret = match vol {
+                    Some(v) => ret / v[i],
+                    None => ret,
+                };
 
                 if all_bars || position == 1 {
-                    n_trades += 1;
-                    total_return += ret;
-                    sum_squares += ret * ret;
+                    let w = decay_weight(nprices - 2 - i, half_life);
+                    n_trades += w;
+                    total_return += w * ret;
+                    sum_squares += w * ret * ret;
                     if ret > 0.0 {
-                        win_sum += ret;
+                        win_sum += w * ret;
                     } else {
-                        lose_sum -= ret;
+                        lose_sum -= w * ret;
                     }
                 }
             }
 
             let perf = match which_crit {
-                OptimizationCriterion::MeanReturn => total_return / (n_trades as f64 + 1.0e-30),
+                OptimizationCriterion::MeanReturn => total_return / (n_trades + 1.0e-30),
                 OptimizationCriterion::ProfitFactor => win_sum / lose_sum,
                 OptimizationCriterion::SharpeRatio => {
-                    let mean_ret = total_return / (n_trades as f64 + 1.0e-30);
-                    let mean_sq = sum_squares / (n_trades as f64 + 1.0e-30);
+                    let mean_ret = total_return / (n_trades + 1.0e-30);
+                    let mean_sq = sum_squares / (n_trades + 1.0e-30);
                     let variance = mean_sq - mean_ret * mean_ret;
                     let safe_variance = if variance < 1.0e-20 { 1.0e-20 } else { variance };
                     mean_ret / safe_variance.sqrt()
                 }
             };
+            let turnover = n_changes as f64 / (nprices - start_idx - 1) as f64;
+            let perf = penalty.apply(perf, n_entries, turnover);
 
             if perf > best_perf {
                 best_perf = perf;
@@ -159,18 +186,14 @@ pub fn comp_return_full(
     let start_decision_idx = test_start_idx - 1;
     let end_decision_idx = start_decision_idx + n_test;
     
-    let mut ma_sum = 0.0;
-    // Initialize MA for the first decision point
-    for j in (start_decision_idx + 1 - lookback)..=start_decision_idx {
-        ma_sum += prices[j];
+    let mut ma = RollingStats::new(lookback);
+    for &price in &prices[start_decision_idx + 1 - lookback..start_decision_idx] {
+        ma.push(price);
     }
 
     for i in start_decision_idx..end_decision_idx {
-        if i > start_decision_idx {
-            ma_sum += prices[i] - prices[i - lookback];
-        }
-        
-        let ma_mean = ma_sum / lookback as f64;
+        ma.push(prices[i]);
+        let ma_mean = ma.mean();
         
         if prices[i] > trial_thresh * ma_mean {
             position = 1;
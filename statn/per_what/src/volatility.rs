@@ -0,0 +1,178 @@
+/// Estimator of the volatility of the next bar's return
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolEstimator {
+    /// Root mean square of the most recent `n` bar returns
+    Rolling(usize),
+    /// GARCH(1,1) with variance targeting; alpha and beta are fitted by
+    /// maximum likelihood on a grid
+    Garch,
+}
+
+/// GARCH(1,1) coefficients: h[t+1] = omega + alpha * r[t]^2 + beta * h[t]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GarchParams {
+    pub omega: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+/// Smallest variance allowed, so a run of unchanged prices cannot blow up
+/// normalized returns
+const MIN_VARIANCE: f64 = 1.0e-20;
+
+/// Volatility forecast for each bar of a log price series.
+///
+/// `vol[i]` forecasts the standard deviation of `prices[i + 1] - prices[i]`
+/// from the returns up to and including bar `i`, so it is known when the
+/// position for that return is decided. Bar 0 has no return behind it and
+/// takes the forecast of bar 1.
+pub fn conditional_vol(prices: &[f64], estimator: VolEstimator) -> Vec<f64> {
+    let returns: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+    if returns.is_empty() {
+        return vec![1.0; prices.len()];
+    }
+
+    let mut vol = Vec::with_capacity(prices.len());
+    vol.push(0.0);
+    match estimator {
+        VolEstimator::Rolling(n) => {
+            let n = n.max(1);
+            let mut sum_squares = 0.0;
+            for (t, r) in returns.iter().enumerate() {
+                sum_squares += r * r;
+                if t >= n {
+                    sum_squares -= returns[t - n] * returns[t - n];
+                }
+                let count = (t + 1).min(n);
+                vol.push((sum_squares / count as f64).max(MIN_VARIANCE).sqrt());
+            }
+        }
+        VolEstimator::Garch => {
+            let params = fit_garch(&returns);
+            let mut h = mean_square(&returns);
+            for r in &returns {
+                h = params.omega + params.alpha * r * r + params.beta * h;
+                vol.push(h.max(MIN_VARIANCE).sqrt());
+            }
+        }
+    }
+    vol.truncate(prices.len());
+    vol[0] = if vol.len() > 1 { vol[1] } else { 1.0 };
+    vol
+}
+
+/// Fit GARCH(1,1) to bar returns by Gaussian maximum likelihood.
+///
+/// The unconditional variance is targeted at the mean square return, which
+/// fixes omega, and (alpha, beta) are chosen from a grid with alpha + beta < 1.
+pub fn fit_garch(returns: &[f64]) -> GarchParams {
+    let target = mean_square(returns).max(MIN_VARIANCE);
+    let mut best = GarchParams { omega: target, alpha: 0.0, beta: 0.0 };
+    let mut best_ll = garch_log_likelihood(returns, &best, target);
+
+    for ia in 1..=20 {
+        let alpha = 0.01 * ia as f64;
+        for ib in 0..=49 {
+            let beta = 0.50 + 0.01 * ib as f64;
+            if alpha + beta >= 0.999 {
+                break;
+            }
+            let params = GarchParams { omega: target * (1.0 - alpha - beta), alpha, beta };
+            let ll = garch_log_likelihood(returns, &params, target);
+            if ll > best_ll {
+                best_ll = ll;
+                best = params;
+            }
+        }
+    }
+    best
+}
+
+/// Gaussian log likelihood (up to a constant) with the variance started at `h0`
+fn garch_log_likelihood(returns: &[f64], params: &GarchParams, h0: f64) -> f64 {
+    let mut h = h0;
+    let mut ll = 0.0;
+    for r in returns {
+        let var = h.max(MIN_VARIANCE);
+        ll -= var.ln() + r * r / var;
+        h = params.omega + params.alpha * r * r + params.beta * h;
+    }
+    ll
+}
+
+fn mean_square(returns: &[f64]) -> f64 {
+    if returns.is_empty() {
+        return 1.0;
+    }
+    returns.iter().map(|r| r * r).sum::<f64>() / returns.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{opt_params, OptimizationCriterion};
+
+    /// Log prices with a fixed pattern of shocks scaled by `sigma[i]`
+    fn prices_with_vol(sigma: &[f64]) -> Vec<f64> {
+        let mut p = vec![0.0];
+        for (i, s) in sigma.iter().enumerate() {
+            let shock = if (i * 7919) % 3 == 0 { 1.2 } else { -0.9 };
+            p.push(p[i] + s * shock + 0.0002);
+        }
+        p
+    }
+
+    #[test]
+    fn test_no_lookahead() {
+        let sigma: Vec<f64> = (0..300).map(|i| if i < 150 { 0.01 } else { 0.03 }).collect();
+        let prices = prices_with_vol(&sigma);
+
+        // Later prices cannot change an earlier rolling forecast
+        let full = conditional_vol(&prices, VolEstimator::Rolling(20));
+        let early = conditional_vol(&prices[..100], VolEstimator::Rolling(20));
+        assert_eq!(full.len(), prices.len());
+        assert_eq!(&full[..100], &early[..]);
+
+        // Both estimators follow the regime change once it has happened
+        for estimator in [VolEstimator::Rolling(20), VolEstimator::Garch] {
+            let vol = conditional_vol(&prices, estimator);
+            assert!(vol[280] > 2.0 * vol[140]);
+        }
+    }
+
+    #[test]
+    fn test_rolling_window() {
+        let prices = [0.0, 0.1, 0.1, 0.4];
+        let vol = conditional_vol(&prices, VolEstimator::Rolling(2));
+        assert!((vol[1] - 0.1).abs() < 1e-12);
+        assert!((vol[2] - (0.01_f64 / 2.0).sqrt()).abs() < 1e-12);
+        assert!((vol[3] - (0.09_f64 / 2.0).sqrt()).abs() < 1e-12);
+        assert_eq!(vol[0], vol[1]);
+    }
+
+    #[test]
+    fn test_garch_fit_clustering() {
+        // Volatility clustering should be picked up as a persistent process
+        let sigma: Vec<f64> = (0..2000)
+            .map(|i| 0.01 * (1.0 + 0.8 * ((i as f64) / 60.0).sin()))
+            .collect();
+        let prices = prices_with_vol(&sigma);
+        let returns: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+        let params = fit_garch(&returns);
+        assert!(params.alpha + params.beta > 0.8);
+        assert!(params.omega > 0.0);
+    }
+
+    #[test]
+    fn test_constant_vol_leaves_selection_unchanged() {
+        let prices: Vec<f64> = (0..300)
+            .map(|i| 0.001 * i as f64 + 0.02 * ((i as f64) * 0.3).sin())
+            .collect();
+        let flat = vec![0.02; prices.len()];
+        for crit in [OptimizationCriterion::ProfitFactor, OptimizationCriterion::SharpeRatio] {
+            let raw = opt_params(crit, true, &prices, 20, 0.0, None, &Default::default());
+            let scaled = opt_params(crit, true, &prices, 20, 0.0, Some(&flat), &Default::default());
+            assert_eq!((raw.0, raw.1), (scaled.0, scaled.1));
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use statn::estimators::criteria::CompositeCriterion;
+
+use statn::walkforward::{FoldWindow, WalkForward};
+
+use crate::system::{self, OptimizationCriterion, ReturnType};
+use crate::volatility::{self, VolEstimator};
+
+pub use statn::walkforward::WindowMode;
+
+/// Settings for one walkforward run
+#[derive(Debug, Clone, Copy)]
+pub struct WalkforwardConfig {
+    pub which_crit: OptimizationCriterion,
+    pub all_bars: bool,
+    pub ret_type: ReturnType,
+    pub max_lookback: usize,
+    pub n_train: usize,
+    pub n_test: usize,
+    pub window: WindowMode,
+    /// Bars skipped between the end of each training set and the start of its
+    /// test set, so indicator lookbacks in the test do not reach into training
+    pub embargo: usize,
+    /// Half-life in bars of the time-decay weighting of training returns;
+    /// 0 weights all training bars equally
+    pub half_life: f64,
+    /// If given, training returns are divided by this estimator's volatility
+    /// forecast before the criterion is computed. OOS returns stay raw.
+    pub vol_norm: Option<VolEstimator>,
+    /// Trade-count and turnover penalties on the training criterion
+    pub penalty: CompositeCriterion,
+}
+
+/// One train/test fold of a walkforward run
+#[derive(Debug, Clone)]
+pub struct Fold {
+    pub train_start: usize,
+    pub lookback: usize,
+    pub thresh: f64,
+    /// In-sample criterion of the chosen parameters
+    pub crit: f64,
+    pub test_start: usize,
+    /// Number of OOS bars actually tested (the last fold may be short)
+    pub n_tested: usize,
+    pub n_returns: usize,
+}
+
+/// Folds and pooled OOS returns of a walkforward run
+#[derive(Debug, Clone, Default)]
+pub struct WalkforwardResult {
+    pub folds: Vec<Fold>,
+    pub returns: Vec<f64>,
+}
+
+/// Window mode of the `--window` option: 1 is anchored, anything else rolling
+pub fn window_mode(v: i32) -> WindowMode {
+    match v {
+        1 => WindowMode::Anchored,
+        _ => WindowMode::Rolling,
+    }
+}
+
+impl Fold {
+    /// Fold record of a window and the parameters trained on it
+    pub(crate) fn new(window: &FoldWindow, lookback: usize, thresh: f64, crit: f64, n_returns: usize) -> Self {
+        Fold {
+            train_start: window.train_start,
+            lookback,
+            thresh,
+            crit,
+            test_start: window.test_start,
+            n_tested: window.n_test(),
+            n_returns,
+        }
+    }
+}
+
+impl WalkforwardResult {
+    /// OOS returns of each fold, in fold order
+    pub fn fold_returns(&self) -> Vec<&[f64]> {
+        let mut start = 0;
+        self.folds
+            .iter()
+            .map(|fold| {
+                let slice = &self.returns[start..start + fold.n_returns];
+                start += fold.n_returns;
+                slice
+            })
+            .collect()
+    }
+}
+
+impl WalkforwardConfig {
+    /// Check that the windows fit the lookback and the price history
+    pub fn validate(&self, nprices: usize) -> Result<(), String> {
+        if self.n_train < self.max_lookback + 10 {
+            return Err("n_train must be at least 10 greater than max_lookback".to_string());
+        }
+        if self.n_test == 0 {
+            return Err("n_test must be positive".to_string());
+        }
+        if self.n_train + self.embargo + self.n_test > nprices {
+            return Err("n_train + embargo + n_test must not exceed n_prices".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fold layout of the walkforward
+    pub fn layout(&self) -> WalkForward {
+        WalkForward {
+            n_train: self.n_train,
+            n_test: self.n_test,
+            step: 0,
+            window: self.window,
+            embargo: self.embargo,
+        }
+    }
+}
+
+/// Walkforward: optimize on the training window, skip `embargo` bars, test the
+/// next `n_test` bars, then move the test window forward by the number of bars
+/// tested. A rolling training window moves with it; an anchored one grows.
+///
+/// With an embargo the position at the end of training says nothing about the
+/// position at the start of the test, so each test starts flat.
+pub fn run_walkforward(prices: &[f64], config: &WalkforwardConfig) -> WalkforwardResult {
+    let (folds, mut returns) = walk(prices, &[prices], config);
+    WalkforwardResult {
+        folds,
+        returns: returns.pop().unwrap_or_default(),
+    }
+}
+
+/// Parameters trained on one market, OOS returns on each market of a basket
+#[derive(Debug, Clone, Default)]
+pub struct BasketResult {
+    /// Folds and pooled OOS returns of the reference market itself
+    pub reference: WalkforwardResult,
+    /// Pooled OOS returns of each basket market, in basket order
+    pub returns: Vec<Vec<f64>>,
+}
+
+/// Walkforward that trains on `reference` and tests the trained parameters on
+/// every market in `basket` over the same OOS bars.
+///
+/// All series must be aligned by date and of equal length. The basket markets
+/// always start each test flat since the final training position belongs to
+/// the reference.
+pub fn run_basket_walkforward(
+    reference: &[f64],
+    basket: &[&[f64]],
+    config: &WalkforwardConfig,
+) -> BasketResult {
+    let mut tests = Vec::with_capacity(basket.len() + 1);
+    tests.push(reference);
+    tests.extend_from_slice(basket);
+    let (folds, mut returns) = walk(reference, &tests, config);
+    let reference_returns = returns.remove(0);
+    BasketResult {
+        reference: WalkforwardResult { folds, returns: reference_returns },
+        returns,
+    }
+}
+
+/// Shared walkforward loop. Trains on `train`, then tests every series in
+/// `tests` on the same bars. `tests[0]` must be `train`: it is the only one that
+/// inherits the final training position, and fold return counts refer to it.
+fn walk(train: &[f64], tests: &[&[f64]], config: &WalkforwardConfig) -> (Vec<Fold>, Vec<Vec<f64>>) {
+    let mut all_returns = vec![Vec::new(); tests.len()];
+
+    let folds = config.layout().run(
+        train.len(),
+        |window| {
+            let vol = training_vol(train, window.train_start, window.train_end, config);
+            system::opt_params(
+                config.which_crit,
+                config.all_bars,
+                &train[window.train()],
+                config.max_lookback,
+                config.half_life,
+                vol.as_deref(),
+                &config.penalty,
+            )
+        },
+        |window, &(lookback, thresh, last_pos, _)| {
+            let last_pos = if config.embargo > 0 { 0 } else { last_pos };
+            let mut n_returns = 0;
+            for (i, prices) in tests.iter().enumerate() {
+                let returns = system::comp_return_full(
+                    config.ret_type,
+                    prices,
+                    window.test_start,
+                    window.n_test(),
+                    lookback,
+                    thresh,
+                    if i == 0 { last_pos } else { 0 },
+                );
+                if i == 0 {
+                    n_returns = returns.len();
+                }
+                all_returns[i].extend(returns);
+            }
+            n_returns
+        },
+    );
+
+    let folds = folds
+        .into_iter()
+        .map(|fold| {
+            let (lookback, thresh, _, crit) = fold.model;
+            Fold::new(&fold.window, lookback, thresh, crit, fold.result)
+        })
+        .collect();
+    (folds, all_returns)
+}
+
+/// Volatility forecasts for the bars of `prices[train_start..train_end]`, if
+/// the config normalizes returns. The estimator runs from the first bar so a
+/// rolling window's forecasts have their full history behind them, and stops at
+/// the end of training so no test bar influences them.
+pub(crate) fn training_vol(
+    prices: &[f64],
+    train_start: usize,
+    train_end: usize,
+    config: &WalkforwardConfig,
+) -> Option<Vec<f64>> {
+    config
+        .vol_norm
+        .map(|estimator| volatility::conditional_vol(&prices[..train_end], estimator).split_off(train_start))
+}
+
+/// Performance at which a system neither makes nor loses money
+pub fn break_even(which_crit: OptimizationCriterion) -> f64 {
+    match which_crit {
+        OptimizationCriterion::ProfitFactor => 1.0,
+        OptimizationCriterion::MeanReturn | OptimizationCriterion::SharpeRatio => 0.0,
+    }
+}
+
+/// OOS performance of pooled returns, measured with the optimization criterion.
+///
+/// Returns `None` if there are no returns.
+pub fn oos_performance(which_crit: OptimizationCriterion, returns: &[f64]) -> Option<f64> {
+    if returns.is_empty() {
+        return None;
+    }
+    let nret = returns.len() as f64;
+
+    let perf = match which_crit {
+        OptimizationCriterion::MeanReturn => returns.iter().sum::<f64>() / nret,
+        OptimizationCriterion::ProfitFactor => {
+            let mut win_sum = 1.0e-60;
+            let mut lose_sum = 1.0e-60;
+            for &r in returns {
+                if r > 0.0 {
+                    win_sum += r;
+                } else if r < 0.0 {
+                    lose_sum -= r;
+                }
+            }
+            win_sum / lose_sum
+        }
+        OptimizationCriterion::SharpeRatio => {
+            let mean = returns.iter().sum::<f64>() / nret;
+            let mean_sq = returns.iter().map(|&r| r * r).sum::<f64>() / nret;
+            let variance = (mean_sq - mean * mean).max(1.0e-20);
+            mean / variance.sqrt()
+        }
+    };
+
+    Some(perf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trending_prices(n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| 0.001 * i as f64 + 0.02 * ((i as f64) * 0.3).sin())
+            .collect()
+    }
+
+    fn config(n_train: usize, n_test: usize) -> WalkforwardConfig {
+        WalkforwardConfig {
+            which_crit: OptimizationCriterion::ProfitFactor,
+            all_bars: false,
+            ret_type: ReturnType::AllBars,
+            max_lookback: 20,
+            n_train,
+            n_test,
+            window: WindowMode::Rolling,
+            embargo: 0,
+            half_life: 0.0,
+            vol_norm: None,
+            penalty: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_folds_cover_history() {
+        let prices = trending_prices(400);
+        let result = run_walkforward(&prices, &config(100, 70));
+
+        assert_eq!(result.folds[0].test_start, 100);
+        let tested: usize = result.folds.iter().map(|f| f.n_tested).sum();
+        assert_eq!(tested, 300);
+        // All-bars returns give one return per tested bar
+        assert_eq!(result.returns.len(), 300);
+        assert_eq!(result.folds.last().unwrap().n_tested, 20);
+    }
+
+    #[test]
+    fn test_anchored_window_expands() {
+        let prices = trending_prices(400);
+        let rolling = run_walkforward(&prices, &config(100, 70));
+        let anchored = run_walkforward(
+            &prices,
+            &WalkforwardConfig { window: WindowMode::Anchored, ..config(100, 70) },
+        );
+
+        // Same OOS bars either way, only the training sets differ
+        assert_eq!(anchored.folds.len(), rolling.folds.len());
+        for (a, r) in anchored.folds.iter().zip(&rolling.folds) {
+            assert_eq!(a.train_start, 0);
+            assert_eq!(a.test_start, r.test_start);
+            assert_eq!(a.n_tested, r.n_tested);
+        }
+        assert_eq!(rolling.folds[1].train_start, 70);
+
+        let per_fold = anchored.fold_returns();
+        assert_eq!(per_fold.len(), anchored.folds.len());
+        assert_eq!(per_fold.iter().map(|r| r.len()).sum::<usize>(), anchored.returns.len());
+    }
+
+    #[test]
+    fn test_embargo_shifts_test_windows() {
+        let prices = trending_prices(400);
+        let result = run_walkforward(&prices, &WalkforwardConfig { embargo: 30, ..config(100, 70) });
+
+        for fold in &result.folds {
+            assert_eq!(fold.test_start, fold.train_start + 100 + 30);
+        }
+        // The embargoed bars at the start are never tested
+        let tested: usize = result.folds.iter().map(|f| f.n_tested).sum();
+        assert_eq!(tested, 270);
+        assert!(WalkforwardConfig { embargo: 30, ..config(300, 100) }.validate(400).is_err());
+    }
+
+    #[test]
+    fn test_basket_walkforward() {
+        let reference = trending_prices(400);
+        let other: Vec<f64> = reference.iter().map(|p| -p).collect();
+        let cfg = config(100, 70);
+
+        let single = run_walkforward(&reference, &cfg);
+        let basket = run_basket_walkforward(&reference, &[&reference, &other], &cfg);
+
+        // Same folds and reference returns as training on the reference alone
+        assert_eq!(basket.reference.returns, single.returns);
+        assert_eq!(basket.returns.len(), 2);
+        for (b, s) in basket.reference.folds.iter().zip(&single.folds) {
+            assert_eq!((b.lookback, b.test_start), (s.lookback, s.test_start));
+        }
+        assert_eq!(basket.returns[0].len(), 300);
+        assert_eq!(basket.returns[1].len(), 300);
+    }
+
+    #[test]
+    fn test_vol_norm_keeps_folds() {
+        let prices = trending_prices(400);
+        let raw = run_walkforward(&prices, &config(100, 70));
+        for estimator in [VolEstimator::Rolling(20), VolEstimator::Garch] {
+            let normalized =
+                run_walkforward(&prices, &WalkforwardConfig { vol_norm: Some(estimator), ..config(100, 70) });
+            // Same OOS bars, and OOS returns are never normalized
+            assert_eq!(normalized.returns.len(), raw.returns.len());
+            assert_eq!(normalized.folds.len(), raw.folds.len());
+        }
+        let cfg = WalkforwardConfig { vol_norm: Some(VolEstimator::Rolling(20)), ..config(100, 70) };
+        assert_eq!(training_vol(&prices, 70, 170, &cfg).unwrap().len(), 100);
+        assert!(training_vol(&prices, 70, 170, &config(100, 70)).is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config(25, 10).validate(400).is_err());
+        assert!(config(100, 0).validate(400).is_err());
+        assert!(config(300, 200).validate(400).is_err());
+        assert!(config(300, 100).validate(400).is_ok());
+    }
+
+    #[test]
+    fn test_oos_performance() {
+        assert_eq!(oos_performance(OptimizationCriterion::MeanReturn, &[]), None);
+        let pf = oos_performance(OptimizationCriterion::ProfitFactor, &[2.0, -1.0, 1.0]).unwrap();
+        assert!((pf - 3.0).abs() < 1e-9);
+    }
+}
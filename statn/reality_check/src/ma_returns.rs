@@ -0,0 +1,65 @@
+/// One-bar returns of a primitive moving-average crossover system for every
+/// pair of lookbacks `1 <= short < long <= max_lookback`, long when the short
+/// moving average of the log prices is above the long one and short when
+/// below.
+///
+/// The matrix has `max_lookback * (max_lookback - 1) / 2` rows, one per
+/// system in order of the long then the short lookback, and
+/// `prices.len() - max_lookback` columns, one per decision bar, changing
+/// fastest: `returns[system * n_returns + bar]`, as in
+/// `cross_validation_mkt`.
+pub fn ma_returns(prices: &[f64], max_lookback: usize) -> Vec<f64> {
+    let n_returns = prices.len().saturating_sub(max_lookback);
+    let mut returns = Vec::with_capacity(max_lookback * max_lookback.saturating_sub(1) / 2 * n_returns);
+
+    for ilong in 2..=max_lookback {
+        for ishort in 1..ilong {
+            for i in max_lookback - 1..max_lookback - 1 + n_returns {
+                let short_mean = prices[i + 1 - ishort..=i].iter().sum::<f64>() / ishort as f64;
+                let long_mean = prices[i + 1 - ilong..=i].iter().sum::<f64>() / ilong as f64;
+                let change = prices[i + 1] - prices[i];
+                returns.push(if short_mean > long_mean {
+                    change
+                } else if short_mean < long_mean {
+                    -change
+                } else {
+                    0.0
+                });
+            }
+        }
+    }
+    returns
+}
+
+/// (short, long) lookbacks of row `system` of `ma_returns`
+pub fn lookbacks(system: usize) -> (usize, usize) {
+    let mut ilong = 2;
+    let mut first = 0;
+    while first + ilong - 1 <= system {
+        first += ilong - 1;
+        ilong += 1;
+    }
+    (system - first + 1, ilong)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ma_returns() {
+        let prices = [0.0, 0.1, 0.3, 0.2, 0.4];
+        let returns = ma_returns(&prices, 3);
+        // Systems (1, 2), (1, 3), (2, 3) over decision bars 2 and 3
+        assert_eq!(returns.len(), 3 * 2);
+        // (1, 2) at bar 2: 0.3 above (0.1 + 0.3) / 2, long into -0.1
+        assert!((returns[0] + 0.1).abs() < 1e-12);
+        // (1, 2) at bar 3: 0.2 below 0.25, short into +0.2
+        assert!((returns[1] + 0.2).abs() < 1e-12);
+        // (2, 3) at bar 3: 0.25 above 0.2, long into +0.2
+        assert!((returns[5] - 0.2).abs() < 1e-12);
+        assert!(ma_returns(&prices[..2], 3).is_empty());
+
+        assert_eq!((0..4).map(lookbacks).collect::<Vec<_>>(), vec![(1, 2), (1, 3), (2, 3), (1, 4)]);
+    }
+}
@@ -0,0 +1,106 @@
+mod ma_returns;
+
+use clap::Parser;
+use statn::core::stats::RealityCheck;
+use statn::prelude::read_bars_with_format;
+use std::path::{Path, PathBuf};
+
+use ma_returns::{lookbacks, ma_returns};
+
+#[derive(Parser)]
+#[command(name = "reality_check")]
+#[command(about = "White's Reality Check and Hansen's SPA test of the best of a family of systems", long_about = None)]
+struct Cli {
+    /// Returns matrix, one line per system of returns separated by commas or
+    /// whitespace; with --max-lookback, a market file (YYYYMMDD Price)
+    #[arg(value_name = "FILENAME")]
+    filename: PathBuf,
+
+    /// Test the moving-average crossover systems of every pair of lookbacks
+    /// up to this one on the market file
+    #[arg(long)]
+    max_lookback: Option<usize>,
+
+    /// Number of bootstrap resamples
+    #[arg(long, default_value_t = 1000)]
+    reps: usize,
+
+    /// Mean block length of the stationary bootstrap, in cases
+    #[arg(long, default_value_t = 10.0)]
+    block: f64,
+
+    /// Seed of the bootstrap
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+/// Read one system per line, skipping blank lines, `#` comments and a header
+/// that does not parse; every system must have the same number of returns
+fn read_matrix(path: &Path) -> Result<(Vec<f64>, usize), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let mut returns = Vec::new();
+    let mut n_systems = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let row: Result<Vec<f64>, _> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .map(str::parse::<f64>)
+            .collect();
+        match row {
+            Ok(row) => {
+                if n_systems > 0 && row.len() * n_systems != returns.len() {
+                    return Err(format!("Line {} of {} has {} returns, not {}", i + 1, path.display(), row.len(), returns.len() / n_systems));
+                }
+                returns.extend(row);
+                n_systems += 1;
+            }
+            Err(_) if n_systems == 0 && i == 0 => {}
+            Err(_) => return Err(format!("Invalid return on line {} of {}", i + 1, path.display())),
+        }
+    }
+    Ok((returns, n_systems))
+}
+
+fn main() -> Result<(), String> {
+    let cli = Cli::parse();
+
+    let (returns, n_systems) = match cli.max_lookback {
+        Some(max_lookback) => {
+            if max_lookback < 2 {
+                return Err("max_lookback must be at least 2".to_string());
+            }
+            println!("\nReading market file...");
+            let prices = read_bars_with_format(&cli.filename, None)
+                .map_err(|e| format!("Error reading file: {}", e))?
+                .log_close();
+            println!("{} prices read; systems are MA crossovers with lookbacks up to {}", prices.len(), max_lookback);
+            (ma_returns(&prices, max_lookback), max_lookback * (max_lookback - 1) / 2)
+        }
+        None => read_matrix(&cli.filename)?,
+    };
+
+    let test = RealityCheck { reps: cli.reps, mean_block: cli.block, seed: cli.seed };
+    let result = test.test(&returns, n_systems)?;
+
+    println!(
+        "\n{} systems, {} cases, {} resamples with mean block {}",
+        result.n_systems, result.n_cases, test.reps, test.mean_block
+    );
+    match cli.max_lookback {
+        Some(_) => {
+            let (short, long) = lookbacks(result.best);
+            println!("Best system = lookbacks {} and {}, mean return = {:.6}", short, long, result.best_mean);
+        }
+        None => println!("Best system = row {}, mean return = {:.6}", result.best + 1, result.best_mean),
+    }
+    println!("\nWhite's Reality Check: statistic = {:.4}  p-value = {:.4}", result.white_statistic, result.white_p_value);
+    println!(
+        "Hansen's SPA:          statistic = {:.4}  p-value = {:.4} (lower {:.4}, upper {:.4})",
+        result.spa_statistic, result.spa_p_consistent, result.spa_p_lower, result.spa_p_upper
+    );
+    Ok(())
+}
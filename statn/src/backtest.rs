@@ -0,0 +1,133 @@
+//! Backtesting through a stable front to the `backtesting` crate.
+//!
+//! `Backtester` holds the budget and options of a backtest and runs it on
+//! signals; `Metric` names a figure of the resulting `TradeStats`, so tools
+//! can take the metric to report or optimize as an argument.
+
+use std::fmt;
+use std::str::FromStr;
+
+use backtesting::{backtest_signals_with, BacktestOptions, SignalResult, TradeStats};
+
+/// Budget and options of a backtest
+#[derive(Debug, Clone)]
+pub struct Backtester<'a> {
+    pub budget: f64,
+    pub options: BacktestOptions<'a>,
+}
+
+impl<'a> Backtester<'a> {
+    /// Backtester with `budget` and the default options (close fills, no
+    /// costs)
+    pub fn new(budget: f64) -> Self {
+        Backtester { budget, options: BacktestOptions::default() }
+    }
+
+    pub fn with_options(self, options: BacktestOptions<'a>) -> Self {
+        Backtester { options, ..self }
+    }
+
+    /// Backtest `signals`, the options' per-bar series aligned with them
+    pub fn run(&self, signals: &SignalResult) -> TradeStats {
+        backtest_signals_with(signals, self.budget, &self.options)
+    }
+}
+
+/// A figure of a backtest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Metric {
+    /// Return on investment, in percent
+    #[default]
+    Roi,
+    Sharpe,
+    Sortino,
+    Calmar,
+    /// Maximum drawdown, in percent
+    MaxDrawdown,
+    /// Ulcer index, in percent
+    UlcerIndex,
+    /// Share of winning trades, in percent
+    WinRate,
+}
+
+impl Metric {
+    /// Value of the metric in `stats`
+    pub fn of(&self, stats: &TradeStats) -> f64 {
+        match self {
+            Metric::Roi => stats.roi_percent,
+            Metric::Sharpe => stats.sharpe_ratio,
+            Metric::Sortino => stats.sortino_ratio,
+            Metric::Calmar => stats.calmar_ratio,
+            Metric::MaxDrawdown => stats.max_drawdown,
+            Metric::UlcerIndex => stats.ulcer_index,
+            Metric::WinRate => stats.win_rate,
+        }
+    }
+
+    /// Whether a larger value is better; false for the drawdown measures
+    pub fn higher_is_better(&self) -> bool {
+        !matches!(self, Metric::MaxDrawdown | Metric::UlcerIndex)
+    }
+}
+
+impl fmt::Display for Metric {
+    /// The name `from_str` parses
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Metric::Roi => "roi",
+            Metric::Sharpe => "sharpe",
+            Metric::Sortino => "sortino",
+            Metric::Calmar => "calmar",
+            Metric::MaxDrawdown => "max-drawdown",
+            Metric::UlcerIndex => "ulcer",
+            Metric::WinRate => "win-rate",
+        })
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "roi" | "return" => Ok(Metric::Roi),
+            "sharpe" => Ok(Metric::Sharpe),
+            "sortino" => Ok(Metric::Sortino),
+            "calmar" => Ok(Metric::Calmar),
+            "max-drawdown" | "drawdown" => Ok(Metric::MaxDrawdown),
+            "ulcer" => Ok(Metric::UlcerIndex),
+            "win-rate" => Ok(Metric::WinRate),
+            _ => Err(format!(
+                "Unknown metric '{}' (expected roi, sharpe, sortino, calmar, max-drawdown, ulcer or win-rate)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backtester_and_metric() {
+        let prices: Vec<f64> = [100.0, 110.0, 121.0].iter().map(|p: &f64| p.ln()).collect();
+        let signals = SignalResult {
+            prices,
+            signals: vec![1, 0, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        let stats = Backtester::new(1000.0).run(&signals);
+        assert!((Metric::Roi.of(&stats) - 21.0).abs() < 1e-9);
+        assert_eq!(Metric::WinRate.of(&stats), 100.0);
+
+        for metric in [Metric::Roi, Metric::MaxDrawdown, Metric::WinRate] {
+            assert_eq!(metric.to_string().parse::<Metric>(), Ok(metric));
+        }
+        assert!(!Metric::MaxDrawdown.higher_is_better());
+        assert!("profit".parse::<Metric>().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Periods returns are measured over, for a Sharpe ratio or a drawdown.
+///
+/// The choice matters: a trade-based Sharpe ratio ignores the swings of open
+/// positions and the time between trades, which a mark-to-market one sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountingBasis {
+    /// One return per trade, realized at its exit.
+    #[default]
+    Trade,
+    /// Every bar marked to market.
+    Bar,
+    /// Marked to market at the last bar of each calendar day.
+    Daily,
+}
+
+impl AccountingBasis {
+    /// Description for reports
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountingBasis::Trade => "per trade",
+            AccountingBasis::Bar => "bar mark-to-market",
+            AccountingBasis::Daily => "daily mark-to-market",
+        }
+    }
+}
+
+impl fmt::Display for AccountingBasis {
+    /// The name `from_str` parses
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AccountingBasis::Trade => "trade",
+            AccountingBasis::Bar => "bar",
+            AccountingBasis::Daily => "daily",
+        })
+    }
+}
+
+impl FromStr for AccountingBasis {
+    type Err = String;
+
+    /// Parse `trade`, `bar` or `daily`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trade" => Ok(AccountingBasis::Trade),
+            "bar" | "mtm" => Ok(AccountingBasis::Bar),
+            "daily" | "day" => Ok(AccountingBasis::Daily),
+            _ => Err(format!("Invalid accounting basis '{}' (expected trade, bar or daily)", s)),
+        }
+    }
+}
+
+/// Index of the last of `n` bars on each calendar day, the day being the
+/// first eight digits of the bar's date. Bars past the end of `dates` fall on
+/// its last day; without dates every bar is a day of its own.
+pub fn day_ends(n: usize, dates: Option<&[String]>) -> Vec<usize> {
+    let day = |i: usize| {
+        dates
+            .and_then(|d| d.get(i).or(d.last()))
+            .map(|d| d.chars().filter(|c| c.is_ascii_digit()).take(8).collect::<String>())
+    };
+    (0..n).filter(|&i| i + 1 == n || day(i).is_none() || day(i) != day(i + 1)).collect()
+}
+
+/// Additive (log) returns per bar regrouped into the periods of `basis`.
+///
+/// `positions` is the position held over each bar. A trade is a run of bars
+/// with the same nonzero position, its return counted when the position
+/// changes; the returns of a day are summed at its last bar (see
+/// `day_ends`, with `dates` aligned with `returns`).
+pub fn period_returns(returns: &[f64], positions: &[f64], dates: Option<&[String]>, basis: AccountingBasis) -> Vec<f64> {
+    match basis {
+        AccountingBasis::Bar => returns.to_vec(),
+        AccountingBasis::Trade => {
+            let mut trades = Vec::new();
+            let mut open: Option<(f64, f64)> = None; // Position and return so far
+            for (&ret, &pos) in returns.iter().zip(positions) {
+                if let Some((held, sum)) = open {
+                    if held != pos {
+                        trades.push(sum);
+                        open = None;
+                    }
+                }
+                if pos != 0.0 {
+                    open.get_or_insert((pos, 0.0)).1 += ret;
+                }
+            }
+            trades.extend(open.map(|(_, sum)| sum));
+            trades
+        }
+        AccountingBasis::Daily => {
+            let mut start = 0;
+            day_ends(returns.len(), dates)
+                .into_iter()
+                .map(|end| {
+                    let sum = returns[start..=end].iter().sum();
+                    start = end + 1;
+                    sum
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_returns() {
+        assert_eq!("daily".parse::<AccountingBasis>(), Ok(AccountingBasis::Daily));
+        assert_eq!(AccountingBasis::Bar.to_string().parse::<AccountingBasis>(), Ok(AccountingBasis::Bar));
+        assert!("weekly".parse::<AccountingBasis>().is_err());
+
+        let returns = [1.0, 2.0, -1.0, 0.0, 3.0, -2.0];
+        let positions = [1.0, 1.0, -1.0, 0.0, 1.0, 1.0];
+        assert_eq!(period_returns(&returns, &positions, None, AccountingBasis::Bar), returns.to_vec());
+        assert_eq!(period_returns(&returns, &positions, None, AccountingBasis::Trade), vec![3.0, -1.0, 1.0]);
+
+        let dates: Vec<String> = ["2024-01-02 10:00", "2024-01-02 11:00", "2024-01-03 10:00", "2024-01-03 11:00", "2024-01-04", "2024-01-05"]
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(day_ends(6, Some(&dates)), vec![1, 3, 4, 5]);
+        assert_eq!(day_ends(3, Some(&dates[..1])), vec![2]);
+        assert_eq!(period_returns(&returns, &positions, Some(&dates), AccountingBasis::Daily), vec![3.0, -1.0, 3.0, -2.0]);
+        assert_eq!(period_returns(&returns, &positions, None, AccountingBasis::Daily), returns.to_vec());
+    }
+}
@@ -0,0 +1,329 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::RiskSnapshot;
+
+/// Condition watched on the stream of snapshots of a live loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertRule {
+    /// Drawdown past `max`, or past the snapshot's projected bound if `None`.
+    Drawdown(Option<f64>),
+    /// No snapshot for this long.
+    Stale(Duration),
+    /// The position changed sign (long, flat, short).
+    SignalFlip,
+    /// PnL fell below this amount.
+    PnlBelow(f64),
+    /// PnL rose above this amount.
+    PnlAbove(f64),
+}
+
+impl AlertRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertRule::Drawdown(_) => "drawdown",
+            AlertRule::Stale(_) => "stale",
+            AlertRule::SignalFlip => "flip",
+            AlertRule::PnlBelow(_) => "pnl-below",
+            AlertRule::PnlAbove(_) => "pnl-above",
+        }
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = String;
+
+    /// `drawdown` (projected bound), `drawdown:<fraction>`, `stale:<minutes>`,
+    /// `flip`, `pnl-below:<amount>` or `pnl-above:<amount>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (s, None),
+        };
+        let number = || -> Result<f64, String> {
+            let value = value.ok_or_else(|| format!("Alert rule '{}' needs a value, e.g. {}:10", name, name))?;
+            value.trim().parse().map_err(|_| format!("Invalid value '{}' in alert rule '{}'", value, s))
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "drawdown" => Ok(AlertRule::Drawdown(value.map(|_| number()).transpose()?)),
+            "stale" => {
+                let minutes = number()?;
+                if minutes.is_nan() || minutes <= 0.0 {
+                    return Err("Stale alert needs a positive number of minutes".to_string());
+                }
+                Ok(AlertRule::Stale(Duration::from_secs_f64(minutes * 60.0)))
+            }
+            "flip" => Ok(AlertRule::SignalFlip),
+            "pnl-below" => Ok(AlertRule::PnlBelow(number()?)),
+            "pnl-above" => Ok(AlertRule::PnlAbove(number()?)),
+            _ => Err(format!(
+                "Unknown alert rule '{}' (expected drawdown, stale, flip, pnl-below or pnl-above)",
+                name
+            )),
+        }
+    }
+}
+
+/// A rule that fired.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule: String,
+    /// Timestamp of the last snapshot seen.
+    pub timestamp: String,
+    pub bar: usize,
+    pub message: String,
+}
+
+/// Destination of alerts.
+pub trait AlertSink {
+    fn send(&mut self, alert: &Alert) -> io::Result<()>;
+}
+
+/// Prints each alert as a line of JSON on stdout.
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl AlertSink for StdoutSink {
+    fn send(&mut self, alert: &Alert) -> io::Result<()> {
+        println!("{}", serde_json::to_string(alert)?);
+        Ok(())
+    }
+}
+
+/// Runs a shell command for each alert with the alert's JSON on its standard
+/// input, e.g. `mail -s "strategy alert" ops@example.com`.
+#[derive(Debug, Clone)]
+pub struct CommandSink {
+    command: String,
+}
+
+impl CommandSink {
+    pub fn new(command: &str) -> Self {
+        CommandSink { command: command.to_string() }
+    }
+
+    /// Posts the alert's JSON to a webhook URL with `curl`.
+    pub fn webhook(url: &str) -> Self {
+        let quoted = format!("'{}'", url.replace('\'', r"'\''"));
+        CommandSink::new(&format!(
+            "curl -sS -X POST -H 'Content-Type: application/json' --data-binary @- {}",
+            quoted
+        ))
+    }
+}
+
+impl AlertSink for CommandSink {
+    fn send(&mut self, alert: &Alert) -> io::Result<()> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(serde_json::to_string(alert)?.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("Alert command '{}' failed: {}", self.command, status)));
+        }
+        Ok(())
+    }
+}
+
+/// Sink from a specification: `stdout`, `webhook:<url>` or `cmd:<shell
+/// command>`.
+pub fn parse_sink(spec: &str) -> Result<Box<dyn AlertSink>, String> {
+    if spec.eq_ignore_ascii_case("stdout") {
+        Ok(Box::new(StdoutSink))
+    } else if let Some(url) = spec.strip_prefix("webhook:") {
+        Ok(Box::new(CommandSink::webhook(url)))
+    } else if let Some(command) = spec.strip_prefix("cmd:") {
+        Ok(Box::new(CommandSink::new(command)))
+    } else {
+        Err(format!("Unknown alert sink '{}' (expected stdout, webhook:<url> or cmd:<command>)", spec))
+    }
+}
+
+/// Evaluates alert rules on the snapshots of a live loop and sends what fires
+/// to every sink.
+///
+/// A rule fires when its condition becomes true and stays quiet until the
+/// condition has cleared, so a drawdown that lasts a week is one alert.
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    sinks: Vec<Box<dyn AlertSink>>,
+    /// Whether each rule's condition held at the last evaluation
+    active: Vec<bool>,
+    last: Option<(Instant, String, usize)>,
+    last_direction: Option<i8>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>, sinks: Vec<Box<dyn AlertSink>>) -> Self {
+        AlertEngine {
+            active: vec![false; rules.len()],
+            rules,
+            sinks,
+            last: None,
+            last_direction: None,
+        }
+    }
+
+    /// Evaluate the rules on a new snapshot received at `now`.
+    pub fn on_snapshot(&mut self, snapshot: &RiskSnapshot, now: Instant) -> Vec<Alert> {
+        let direction = if snapshot.position > 0.0 {
+            1
+        } else if snapshot.position < 0.0 {
+            -1
+        } else {
+            0
+        };
+        let flipped = self.last_direction.is_some_and(|d| d != direction);
+        self.last_direction = Some(direction);
+        self.last = Some((now, snapshot.timestamp.clone(), snapshot.bar));
+
+        let mut fired = Vec::new();
+        for i in 0..self.rules.len() {
+            let message = match self.rules[i] {
+                AlertRule::Drawdown(max) => max.or(snapshot.drawdown_bound).and_then(|bound| {
+                    (snapshot.drawdown > bound).then(|| {
+                        format!("Drawdown {:.2}% past the bound of {:.2}%", 100.0 * snapshot.drawdown, 100.0 * bound)
+                    })
+                }),
+                // A snapshot just came in
+                AlertRule::Stale(_) => None,
+                AlertRule::SignalFlip => {
+                    flipped.then(|| format!("Position changed to {} at {}", snapshot.position, snapshot.price))
+                }
+                AlertRule::PnlBelow(amount) => (snapshot.pnl < amount)
+                    .then(|| format!("PnL {:.2} below {:.2}", snapshot.pnl, amount)),
+                AlertRule::PnlAbove(amount) => (snapshot.pnl > amount)
+                    .then(|| format!("PnL {:.2} above {:.2}", snapshot.pnl, amount)),
+            };
+            // A flip is an event, not a state, so each one fires
+            let edge = matches!(self.rules[i], AlertRule::SignalFlip) || !self.active[i];
+            self.active[i] = message.is_some();
+            if let Some(message) = message.filter(|_| edge) {
+                fired.push(self.alert(i, message));
+            }
+        }
+        self.dispatch(&fired);
+        fired
+    }
+
+    /// Check for missing ticks; the live loop calls this on a timer, since a
+    /// feed that has stopped produces no snapshots to evaluate.
+    pub fn check_stale(&mut self, now: Instant) -> Vec<Alert> {
+        let Some((last, ..)) = &self.last else {
+            return Vec::new();
+        };
+        let silence = now.saturating_duration_since(*last);
+        let mut fired = Vec::new();
+        for i in 0..self.rules.len() {
+            if let AlertRule::Stale(limit) = self.rules[i] {
+                let stale = silence >= limit;
+                if stale && !self.active[i] {
+                    let message = format!("No ticks for {:.1} minutes", silence.as_secs_f64() / 60.0);
+                    fired.push(self.alert(i, message));
+                }
+                self.active[i] = stale;
+            }
+        }
+        self.dispatch(&fired);
+        fired
+    }
+
+    fn alert(&self, rule: usize, message: String) -> Alert {
+        let (timestamp, bar) = self.last.as_ref().map_or((String::new(), 0), |(_, t, b)| (t.clone(), *b));
+        Alert {
+            rule: self.rules[rule].name().to_string(),
+            timestamp,
+            bar,
+            message,
+        }
+    }
+
+    /// Send to every sink; a failing sink is reported on stderr and does not
+    /// stop the loop or the other sinks.
+    fn dispatch(&mut self, alerts: &[Alert]) {
+        for alert in alerts {
+            for sink in &mut self.sinks {
+                if let Err(e) = sink.send(alert) {
+                    eprintln!("Alert not delivered: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::RiskMonitor;
+    use std::sync::{Arc, Mutex};
+
+    struct Collect(Arc<Mutex<Vec<Alert>>>);
+
+    impl AlertSink for Collect {
+        fn send(&mut self, alert: &Alert) -> io::Result<()> {
+            self.0.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rules_fire_on_transitions() {
+        let rules = ["drawdown", "flip", "pnl-below:-50", "stale:5"]
+            .iter()
+            .map(|r| r.parse().unwrap())
+            .collect();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = AlertEngine::new(rules, vec![Box::new(Collect(sent.clone()))]);
+        let mut monitor = RiskMonitor::new(1000.0, 10, Some(0.05)).unwrap();
+        let start = Instant::now();
+
+        let mut step = |price, position, minute: u64| {
+            let snapshot = monitor.update("t", price, position);
+            engine
+                .on_snapshot(&snapshot, start + Duration::from_secs(60 * minute))
+                .into_iter()
+                .map(|a| a.rule)
+                .collect::<Vec<_>>()
+        };
+        assert!(step(100.0, 10.0, 0).is_empty());
+        // Down 6%: drawdown and PnL rules fire once
+        assert_eq!(step(94.0, 10.0, 1), vec!["drawdown", "pnl-below"]);
+        assert!(step(93.0, 10.0, 2).is_empty());
+        assert_eq!(step(93.0, -10.0, 3), vec!["flip"]);
+        assert_eq!(step(93.0, 0.0, 4), vec!["flip"]);
+
+        assert!(engine.check_stale(start + Duration::from_secs(60 * 8)).is_empty());
+        assert_eq!(engine.check_stale(start + Duration::from_secs(60 * 10))[0].rule, "stale");
+        assert!(engine.check_stale(start + Duration::from_secs(60 * 11)).is_empty());
+        assert_eq!(sent.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_parse_rules_and_sinks() {
+        assert_eq!("drawdown:0.1".parse(), Ok(AlertRule::Drawdown(Some(0.1))));
+        assert_eq!("STALE:1.5".parse(), Ok(AlertRule::Stale(Duration::from_secs(90))));
+        assert_eq!("pnl-above:250".parse(), Ok(AlertRule::PnlAbove(250.0)));
+        assert!("pnl-below".parse::<AlertRule>().is_err());
+        assert!("stale:0".parse::<AlertRule>().is_err());
+        assert!("volume".parse::<AlertRule>().is_err());
+
+        assert!(parse_sink("stdout").is_ok());
+        assert!(parse_sink("cmd:cat > /dev/null").unwrap().send(&Alert {
+            rule: "flip".to_string(),
+            timestamp: "t".to_string(),
+            bar: 1,
+            message: "m".to_string(),
+        }).is_ok());
+        assert!(parse_sink("smtp:x").is_err());
+    }
+}
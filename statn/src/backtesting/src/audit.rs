@@ -0,0 +1,196 @@
+use std::thread;
+
+use crate::models::{TradeLog, TradeStats};
+
+/// Outcome of `audit_determinism`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditReport {
+    /// Number of runs compared with the reference run.
+    pub runs: usize,
+    /// One entry per run that differed: the run and the first difference.
+    pub mismatches: Vec<String>,
+}
+
+impl AuditReport {
+    /// Whether every run reproduced the reference bit for bit.
+    pub fn is_deterministic(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Run the same backtest under different execution conditions and check that
+/// every run returns bit-identical `TradeStats`.
+///
+/// The reference run is on the calling thread. It is then repeated:
+/// - on the calling thread with the heap shifted by a live padding allocation,
+/// - on a fresh thread with a large stack,
+/// - on `threads` threads at once, which exposes shared global state such as
+///   a global RNG.
+///
+/// Floats are compared by their bits, so even a different rounding of a sum
+/// is reported.
+pub fn audit_determinism<F>(backtest: F, threads: usize) -> AuditReport
+where
+    F: Fn() -> TradeStats + Sync,
+{
+    let reference = backtest();
+    let mut runs: Vec<(String, TradeStats)> = Vec::new();
+
+    // Live allocations of odd sizes move later allocations to other addresses
+    let padding: Vec<Vec<u8>> = (1..=7).map(|i| vec![0u8; 4093 * i]).collect();
+    runs.push(("shifted heap".to_string(), backtest()));
+    drop(padding);
+
+    thread::scope(|s| {
+        let stats = thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn_scoped(s, &backtest)
+            .expect("failed to spawn audit thread")
+            .join()
+            .expect("backtest panicked in audit thread");
+        runs.push(("fresh thread".to_string(), stats));
+
+        let handles: Vec<_> = (0..threads.max(1)).map(|_| s.spawn(&backtest)).collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let stats = handle.join().expect("backtest panicked in audit thread");
+            runs.push((format!("concurrent thread {} of {}", i + 1, threads.max(1)), stats));
+        }
+    });
+
+    let mismatches = runs
+        .iter()
+        .filter_map(|(label, stats)| {
+            first_difference(&reference, stats).map(|diff| format!("{}: {}", label, diff))
+        })
+        .collect();
+
+    AuditReport { runs: runs.len(), mismatches }
+}
+
+/// First field in which two `TradeStats` differ bit for bit, if any.
+pub fn first_difference(a: &TradeStats, b: &TradeStats) -> Option<String> {
+    let scalars = [
+        ("initial_budget", a.initial_budget, b.initial_budget),
+        ("final_budget", a.final_budget, b.final_budget),
+        ("total_pnl", a.total_pnl, b.total_pnl),
+        ("roi_percent", a.roi_percent, b.roi_percent),
+        ("win_rate", a.win_rate, b.win_rate),
+        ("total_costs", a.total_costs, b.total_costs),
+        ("total_slippage", a.total_slippage, b.total_slippage),
+        ("max_drawdown", a.max_drawdown, b.max_drawdown),
+        ("sharpe_ratio", a.sharpe_ratio, b.sharpe_ratio),
+        ("sortino_ratio", a.sortino_ratio, b.sortino_ratio),
+        ("calmar_ratio", a.calmar_ratio, b.calmar_ratio),
+        ("mar_ratio", a.mar_ratio, b.mar_ratio),
+        ("ulcer_index", a.ulcer_index, b.ulcer_index),
+        ("avg_drawdown_duration", a.avg_drawdown_duration, b.avg_drawdown_duration),
+    ];
+    for (name, x, y) in scalars {
+        if x.to_bits() != y.to_bits() {
+            return Some(format!("{} {:e} != {:e}", name, x, y));
+        }
+    }
+
+    let counts = [
+        ("num_trades", a.num_trades, b.num_trades),
+        ("num_wins", a.num_wins, b.num_wins),
+        ("num_losses", a.num_losses, b.num_losses),
+        ("trades.len()", a.trades.len(), b.trades.len()),
+        ("budget_history.len()", a.budget_history.len(), b.budget_history.len()),
+    ];
+    for (name, x, y) in counts {
+        if x != y {
+            return Some(format!("{} {} != {}", name, x, y));
+        }
+    }
+
+    if let Some(i) = a
+        .budget_history
+        .iter()
+        .zip(&b.budget_history)
+        .position(|(x, y)| x.to_bits() != y.to_bits())
+    {
+        return Some(format!(
+            "budget_history[{}] {:e} != {:e}",
+            i, a.budget_history[i], b.budget_history[i]
+        ));
+    }
+    if a.position_history != b.position_history {
+        return Some("position_history differs".to_string());
+    }
+
+    a.trades
+        .iter()
+        .zip(&b.trades)
+        .enumerate()
+        .find_map(|(i, (x, y))| trade_difference(x, y).map(|diff| format!("trades[{}].{}", i, diff)))
+}
+
+fn trade_difference(a: &TradeLog, b: &TradeLog) -> Option<String> {
+    if a.entry_index != b.entry_index || a.exit_index != b.exit_index || a.trade_type != b.trade_type {
+        return Some(format!(
+            "{} {}..{} != {} {}..{}",
+            a.trade_type, a.entry_index, a.exit_index, b.trade_type, b.entry_index, b.exit_index
+        ));
+    }
+    if a.exit_reason != b.exit_reason {
+        return Some(format!("exit_reason {:?} != {:?}", a.exit_reason, b.exit_reason));
+    }
+    let fields = [
+        ("entry_price", a.entry_price, b.entry_price),
+        ("exit_price", a.exit_price, b.exit_price),
+        ("pnl", a.pnl, b.pnl),
+        ("return_pct", a.return_pct, b.return_pct),
+        ("slippage", a.slippage, b.slippage),
+    ];
+    fields
+        .into_iter()
+        .find(|(_, x, y)| x.to_bits() != y.to_bits())
+        .map(|(name, x, y)| format!("{} {:e} != {:e}", name, x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::backtest_signals;
+    use crate::models::SignalResult;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn signals() -> SignalResult {
+        let prices: Vec<f64> = (0..300).map(|i| (100.0 + (i as f64 * 0.1).sin() * 5.0).ln()).collect();
+        let signals = (0..300).map(|i| [1, 0, 0, -1, 0][i % 5]).collect();
+        SignalResult {
+            prices,
+            signals,
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_deterministic_backtest_passes() {
+        let result = signals();
+        let report = audit_determinism(|| backtest_signals(&result, 10000.0, 0.1), 4);
+        assert_eq!(report.runs, 6);
+        assert!(report.is_deterministic(), "{:?}", report.mismatches);
+    }
+
+    #[test]
+    fn test_shared_state_is_caught() {
+        // Each call sees a different value of a global counter, as with a
+        // global RNG
+        let result = signals();
+        let calls = AtomicUsize::new(0);
+        let report = audit_determinism(
+            || {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                backtest_signals(&result, 10000.0 + n as f64, 0.1)
+            },
+            2,
+        );
+        assert!(!report.is_deterministic());
+        assert!(report.mismatches[0].starts_with("shifted heap: initial_budget"));
+    }
+}
@@ -1,4 +1,67 @@
+use crate::accounting::{day_ends, AccountingBasis};
+use crate::fees::{FeeSchedule, Liquidity};
+use crate::fills::FillModel;
 use crate::models::{SignalResult, TradeLog, TradeStats};
+use crate::risk::risk_metrics;
+use crate::slippage::Slippage;
+use crate::stops::{average_true_range, ExitReason, StopLevel, Stops};
+use std::ops::Range;
+
+/// Trading frictions and restrictions of a backtest
+#[derive(Debug, Clone, Default)]
+pub struct BacktestOptions<'a> {
+    /// Transaction cost as a percentage (e.g., 0.1 for 0.1%)
+    pub transaction_cost_pct: f64,
+    /// Maker and taker fees, replacing `transaction_cost_pct` when given
+    pub fees: Option<&'a FeeSchedule>,
+    pub slippage: Slippage,
+    /// Units traded per bar, aligned with `result.prices`, for
+    /// `Slippage::VolumeDependent`
+    pub volumes: Option<&'a [f64]>,
+    /// Whether an order may fill at each bar, aligned with `result.prices`
+    /// (e.g. from a `SessionFilter` of statn); orders that would fill on
+    /// other bars are ignored and an open position is carried through them.
+    /// `None` fills on every bar
+    pub tradable: Option<&'a [bool]>,
+    /// Stop-loss and take-profit of every position
+    pub stops: Stops,
+    /// High and low of each bar, as log prices aligned with `result.prices`,
+    /// so stops are checked intrabar; without them stops are checked on the
+    /// closes
+    pub highs: Option<&'a [f64]>,
+    pub lows: Option<&'a [f64]>,
+    /// How orders fill
+    pub fill: FillModel,
+    /// Open of each bar, as log prices aligned with `result.prices`, for the
+    /// fill models filling on the next bar
+    pub opens: Option<&'a [f64]>,
+    /// Periods the Sharpe ratio is computed over
+    pub accounting: AccountingBasis,
+    /// Date or time of each bar, aligned with `result.prices`, for the daily
+    /// basis; without them every bar counts as a day
+    pub dates: Option<&'a [String]>,
+    /// Whether the signals are target positions: a 0 on a tradable bar
+    /// closes the open position instead of holding it
+    pub target_positions: bool,
+}
+
+impl<'a> BacktestOptions<'a> {
+    /// Options of the bars in `range`, with the per-bar series cut to it
+    pub fn slice(&self, range: Range<usize>) -> BacktestOptions<'a> {
+        fn cut<'a, T>(series: Option<&'a [T]>, range: &Range<usize>) -> Option<&'a [T]> {
+            series.map(|s| &s[range.start.min(s.len())..range.end.min(s.len())])
+        }
+        BacktestOptions {
+            volumes: cut(self.volumes, &range),
+            tradable: cut(self.tradable, &range),
+            highs: cut(self.highs, &range),
+            lows: cut(self.lows, &range),
+            opens: cut(self.opens, &range),
+            dates: cut(self.dates, &range),
+            ..self.clone()
+        }
+    }
+}
 
 /// Backtest a trading strategy based on generated signals.
 ///
@@ -18,13 +81,81 @@ pub fn backtest_signals(
     initial_budget: f64,
     transaction_cost_pct: f64,
 ) -> TradeStats {
+    backtest_signals_with_slippage(result, initial_budget, transaction_cost_pct, &Slippage::None, None)
+}
+
+/// Backtest a trading strategy with slippage at every entry and exit.
+///
+/// Orders fill at `slippage.fill_price`, so trade prices and P&L are net of
+/// slippage. The P&L lost to slippage is reported in `TradeStats::total_slippage`
+/// and per trade, separately from the transaction costs.
+///
+/// # Arguments
+/// * `result` - The signal result containing prices and signals
+/// * `initial_budget` - Starting capital for trading
+/// * `transaction_cost_pct` - Transaction cost as a percentage (e.g., 0.1 for 0.1%)
+/// * `slippage` - Slippage model
+/// * `volumes` - Units traded per bar, aligned with `result.prices`, for
+///   `Slippage::VolumeDependent`
+pub fn backtest_signals_with_slippage(
+    result: &SignalResult,
+    initial_budget: f64,
+    transaction_cost_pct: f64,
+    slippage: &Slippage,
+    volumes: Option<&[f64]>,
+) -> TradeStats {
+    let options = BacktestOptions { transaction_cost_pct, slippage: *slippage, volumes, ..Default::default() };
+    backtest_signals_with(result, initial_budget, &options)
+}
+
+/// Backtest a trading strategy with the frictions and restrictions of
+/// `options`.
+///
+/// The position left open at the end is closed on the last bar whether or
+/// not it is tradable.
+///
+/// With a fee schedule, limit-order fills and take-profits pay the maker
+/// fee and every other fill the taker fee.
+///
+/// Stops are set from the entry fill and checked from the bar after the
+/// entry, before that bar's signal. A bar reaching both levels is assumed
+/// to hit the stop-loss. The exit fills at the level, or at the nearest
+/// extreme of a bar that gapped past it, then slips like any other fill.
+/// After a protective exit, signals in the direction of the closed position
+/// are ignored until the signal changes.
+///
+/// With `target_positions` a 0 signal is an order to go flat, filled like any
+/// other order; otherwise it holds the position.
+///
+/// Under a fill model filling on the next bar, the order of a signal fills
+/// at that bar's open or within it, before its protective exits, which are
+/// skipped when the order fills. An order reversing a position fills both
+/// legs at one price.
+pub fn backtest_signals_with(result: &SignalResult, initial_budget: f64, options: &BacktestOptions) -> TradeStats {
+    let BacktestOptions {
+        transaction_cost_pct,
+        fees,
+        ref slippage,
+        volumes,
+        tradable,
+        stops,
+        highs,
+        lows,
+        fill,
+        opens,
+        accounting,
+        dates,
+        target_positions,
+    } = *options;
     let mut budget = initial_budget;
     let mut position: i32 = 0; // 0 = flat, 1 = long, -1 = short
-    let mut entry_price = 0.0;
+    let mut entry_price = 0.0; // Fill price
+    let mut entry_mid = 0.0; // Signal price at entry
     let mut num_trades = 0;
     let mut num_wins = 0;
     let mut num_losses = 0;
     let mut total_costs = 0.0;
+    let mut total_slippage = 0.0;
     let mut peak_budget = initial_budget;
     let mut max_drawdown = 0.0;
     
@@ -35,36 +166,201 @@ pub fn backtest_signals(
     
     // Track trade entry details
     let mut current_entry_idx = 0;
+    // Stop-loss and take-profit prices of the open position
+    let mut stop_price: Option<f64> = None;
+    let mut target_price: Option<f64> = None;
+    // Best price since the entry and the ATR at the entry, for the trailing stop
+    let mut trail_extreme = 0.0;
+    let mut trail_atr: Option<f64> = None;
+    // Direction of the position last closed by a stop, while its signal lasts
+    let mut stopped_out = 0;
+
+    let volume_at = |i: usize| volumes.and_then(|v| v.get(i).copied());
+    let fee_pct = |liquidity: Liquidity| fees.map_or(transaction_cost_pct, |f| f.rate_pct(liquidity));
+    let exit_liquidity = |reason: ExitReason| if reason == ExitReason::TakeProfit { Liquidity::Maker } else { Liquidity::Taker };
+    let order_fee_pct = fee_pct(if matches!(fill, FillModel::Limit { .. }) { Liquidity::Maker } else { Liquidity::Taker });
+    let bar_range = |i: usize| match (highs, lows) {
+        (Some(h), Some(l)) => h.get(i).zip(l.get(i)).map(|(h, l)| (h.exp(), l.exp())),
+        _ => None,
+    };
+    let atr_of = |level: Option<StopLevel>| match level {
+        Some(StopLevel::Atr { period, .. }) => {
+            let exp = |v: &[f64]| v.iter().map(|p| p.exp()).collect::<Vec<f64>>();
+            let (highs, lows) = (highs.map(exp), lows.map(exp));
+            average_true_range(&exp(&result.prices), highs.as_deref(), lows.as_deref(), period)
+        }
+        _ => Vec::new(),
+    };
+    let (stop_atr, target_atr) = (atr_of(stops.stop_loss), atr_of(stops.take_profit));
+    let trailing_atr = atr_of(stops.trailing);
+
+    // Close the open position at bar `i`: the fill price, the P&L and the
+    // part of the P&L lost to slippage at entry and exit
+    let close = |position: i32, entry_price: f64, entry_mid: f64, price: f64, budget: f64, i: usize| {
+        let exit_price = slippage.fill_price(price, position == -1, budget, volume_at(i));
+        let (pnl, mid_pnl) = if position == 1 {
+            (budget * (exit_price / entry_price - 1.0), budget * (price / entry_mid - 1.0))
+        } else {
+            (budget * (entry_price / exit_price - 1.0), budget * (entry_mid / price - 1.0))
+        };
+        (exit_price, pnl, mid_pnl - pnl)
+    };
 
     for i in 0..result.prices.len() {
         // The original code assumes prices are in log space and converts them.
         // We should probably make this configurable or document it clearly.
         // For now, I'll keep the .exp() to match the original behavior exactly.
         let price = result.prices[i].exp(); 
-        let signal = result.signals[i];
+        // Signal of the order filling on this bar
+        let ordered = if !fill.is_deferred() {
+            result.signals[i]
+        } else if i > 0 {
+            result.signals[i - 1]
+        } else {
+            0
+        };
+        let in_session = tradable.is_none_or(|tradable| tradable.get(i).copied().unwrap_or(true));
+        let mut signal = if in_session { ordered } else { 0 };
+        // An order to close the position without opening another
+        let mut flatten = target_positions && in_session && ordered == 0 && position != 0;
+        // Fill price of an order changing the position; a limit or stop
+        // order not reached lapses
+        let mut fill_price = price;
+        if (signal != 0 && signal != position) || flatten {
+            let placed_at = if i > 0 { result.prices[i - 1].exp() } else { price };
+            let open = opens.and_then(|o| o.get(i)).map(|o| o.exp());
+            let buy = if flatten { position == -1 } else { signal == 1 };
+            match fill.fill_price(buy, placed_at, open, bar_range(i), price) {
+                Some(filled) => fill_price = filled,
+                None => {
+                    signal = 0;
+                    flatten = false;
+                }
+            }
+        }
+        let order_first = fill.is_deferred() && ((signal != 0 && signal != position) || flatten);
         
         // Record current state
         budget_history.push(budget);
         position_history.push(position);
+
+        // Protective exits of a position opened on an earlier bar
+        if position != 0 && i > current_entry_idx && !order_first {
+            // The tighter of the stop-loss and the trailing stop, which trails
+            // the best price up to the previous bar
+            let trail_price = stops
+                .trailing
+                .and_then(|t| t.distance(trail_extreme, trail_atr))
+                .map(|d| if position == 1 { trail_extreme - d } else { trail_extreme + d });
+            let stop = match (stop_price, trail_price) {
+                (Some(s), Some(t)) if (t - s) * position as f64 > 0.0 => Some((t, ExitReason::TrailingStop)),
+                (Some(s), _) => Some((s, ExitReason::StopLoss)),
+                (None, t) => t.map(|t| (t, ExitReason::TrailingStop)),
+            };
+            if let Some((exit_reason, level)) = protective_exit(position, stop, target_price, price, bar_range(i)) {
+                let (exit_price, pnl, slip) = close(position, entry_price, entry_mid, level, budget, i);
+                let cost = budget * fee_pct(exit_liquidity(exit_reason)) / 100.0;
+                budget += pnl - cost;
+                total_costs += cost;
+                total_slippage += slip;
+
+                if pnl > 0.0 {
+                    num_wins += 1;
+                } else {
+                    num_losses += 1;
+                }
+                returns.push(pnl / budget);
+
+                trades.push(TradeLog {
+                    entry_index: current_entry_idx,
+                    entry_price,
+                    exit_index: i,
+                    exit_price,
+                    trade_type: if position == 1 { "LONG".to_string() } else { "SHORT".to_string() },
+                    pnl,
+                    return_pct: if position == 1 {
+                        (exit_price / entry_price - 1.0) * 100.0
+                    } else {
+                        (entry_price / exit_price - 1.0) * 100.0
+                    },
+                    slippage: slip,
+                    exit_reason,
+                });
+
+                num_trades += 1;
+                stopped_out = position;
+                position = 0;
+                budget_history[i] = budget;
+            } else if stops.trailing.is_some() {
+                let (high, low) = bar_range(i).unwrap_or((price, price));
+                trail_extreme = if position == 1 { trail_extreme.max(high) } else { trail_extreme.min(low) };
+            }
+        }
+        if stopped_out != 0 {
+            if signal == stopped_out {
+                signal = 0;
+            } else {
+                stopped_out = 0;
+            }
+        }
         
+        // Close the position on an order to go flat, unless a protective
+        // exit closed it already
+        if flatten && position != 0 {
+            let (exit_price, pnl, slip) = close(position, entry_price, entry_mid, fill_price, budget, i);
+            let cost = budget * order_fee_pct / 100.0;
+            budget += pnl - cost;
+            total_costs += cost;
+            total_slippage += slip;
+
+            if pnl > 0.0 {
+                num_wins += 1;
+            } else {
+                num_losses += 1;
+            }
+            returns.push(pnl / budget);
+
+            trades.push(TradeLog {
+                entry_index: current_entry_idx,
+                entry_price,
+                exit_index: i,
+                exit_price,
+                trade_type: if position == 1 { "LONG".to_string() } else { "SHORT".to_string() },
+                pnl,
+                return_pct: if position == 1 {
+                    (exit_price / entry_price - 1.0) * 100.0
+                } else {
+                    (entry_price / exit_price - 1.0) * 100.0
+                },
+                slippage: slip,
+                exit_reason: ExitReason::Signal,
+            });
+
+            num_trades += 1;
+            position = 0;
+            budget_history[i] = budget;
+        }
+
         // Process signal
         match (position, signal) {
             // Currently flat, got BUY signal -> go long
             (0, 1) => {
-                let cost = budget * transaction_cost_pct / 100.0;
+                let cost = budget * order_fee_pct / 100.0;
                 total_costs += cost;
                 budget -= cost;
-                entry_price = price;
+                entry_price = slippage.fill_price(fill_price, true, budget, volume_at(i));
+                entry_mid = fill_price;
                 current_entry_idx = i;
                 position = 1;
                 num_trades += 1;
             }
             // Currently flat, got SELL signal -> go short
             (0, -1) => {
-                let cost = budget * transaction_cost_pct / 100.0;
+                let cost = budget * order_fee_pct / 100.0;
                 total_costs += cost;
                 budget -= cost;
-                entry_price = price;
+                entry_price = slippage.fill_price(fill_price, false, budget, volume_at(i));
+                entry_mid = fill_price;
                 current_entry_idx = i;
                 position = -1;
                 num_trades += 1;
@@ -72,10 +368,11 @@ pub fn backtest_signals(
             // Currently long, got SELL signal -> close long and go short
             (1, -1) => {
                 // Close long position
-                let pnl = budget * (price / entry_price - 1.0);
-                let cost = budget * transaction_cost_pct / 100.0;
+                let (exit_price, pnl, slip) = close(position, entry_price, entry_mid, fill_price, budget, i);
+                let cost = budget * order_fee_pct / 100.0;
                 budget += pnl - cost;
                 total_costs += cost;
+                total_slippage += slip;
                 
                 if pnl > 0.0 {
                     num_wins += 1;
@@ -89,17 +386,20 @@ pub fn backtest_signals(
                     entry_index: current_entry_idx,
                     entry_price,
                     exit_index: i,
-                    exit_price: price,
+                    exit_price,
                     trade_type: "LONG".to_string(),
                     pnl,
-                    return_pct: (price / entry_price - 1.0) * 100.0,
+                    return_pct: (exit_price / entry_price - 1.0) * 100.0,
+                    slippage: slip,
+                    exit_reason: ExitReason::Signal,
                 });
 
                 // Open short position
-                let cost2 = budget * transaction_cost_pct / 100.0;
+                let cost2 = budget * order_fee_pct / 100.0;
                 total_costs += cost2;
                 budget -= cost2;
-                entry_price = price;
+                entry_price = slippage.fill_price(fill_price, false, budget, volume_at(i));
+                entry_mid = fill_price;
                 current_entry_idx = i;
                 position = -1;
                 num_trades += 2;
@@ -107,10 +407,11 @@ pub fn backtest_signals(
             // Currently short, got BUY signal -> close short and go long
             (-1, 1) => {
                 // Close short position
-                let pnl = budget * (entry_price / price - 1.0);
-                let cost = budget * transaction_cost_pct / 100.0;
+                let (exit_price, pnl, slip) = close(position, entry_price, entry_mid, fill_price, budget, i);
+                let cost = budget * order_fee_pct / 100.0;
                 budget += pnl - cost;
                 total_costs += cost;
+                total_slippage += slip;
                 
                 if pnl > 0.0 {
                     num_wins += 1;
@@ -124,37 +425,49 @@ pub fn backtest_signals(
                     entry_index: current_entry_idx,
                     entry_price,
                     exit_index: i,
-                    exit_price: price,
+                    exit_price,
                     trade_type: "SHORT".to_string(),
                     pnl,
-                    return_pct: (entry_price / price - 1.0) * 100.0,
+                    return_pct: (entry_price / exit_price - 1.0) * 100.0,
+                    slippage: slip,
+                    exit_reason: ExitReason::Signal,
                 });
 
                 // Open long position
-                let cost2 = budget * transaction_cost_pct / 100.0;
+                let cost2 = budget * order_fee_pct / 100.0;
                 total_costs += cost2;
                 budget -= cost2;
-                entry_price = price;
+                entry_price = slippage.fill_price(fill_price, true, budget, volume_at(i));
+                entry_mid = fill_price;
                 current_entry_idx = i;
                 position = 1;
                 num_trades += 2;
             }
-            // Currently long, got HOLD -> update unrealized P&L
-            (1, 0) => {
-                // Mark-to-market (unrealized)
-                let unrealized_pnl = budget * (price / entry_price - 1.0);
-                let current_value = budget + unrealized_pnl;
-                budget_history[i] = current_value;
-            }
-            // Currently short, got HOLD -> update unrealized P&L
-            (-1, 0) => {
-                // Mark-to-market (unrealized)
-                let unrealized_pnl = budget * (entry_price / price - 1.0);
-                let current_value = budget + unrealized_pnl;
-                budget_history[i] = current_value;
-            }
             _ => {} // No action needed
         }
+
+        // Mark a position held from an earlier bar to market (unrealized
+        // P&L), whether a 0 or a repeated signal kept it
+        if position != 0 && current_entry_idx < i {
+            let unrealized_pnl = if position == 1 {
+                budget * (price / entry_price - 1.0)
+            } else {
+                budget * (entry_price / price - 1.0)
+            };
+            budget_history[i] = budget + unrealized_pnl;
+        }
+
+        // Levels of a position opened on this bar
+        if position != 0 && current_entry_idx == i && stops.is_active() {
+            let level = |stop: Option<StopLevel>, atr: &[f64], against: bool| {
+                stop.and_then(|s| s.distance(entry_price, atr.get(i).copied()))
+                    .map(|d| if (position == 1) == against { entry_price - d } else { entry_price + d })
+            };
+            stop_price = level(stops.stop_loss, &stop_atr, true);
+            target_price = level(stops.take_profit, &target_atr, false);
+            trail_extreme = entry_price;
+            trail_atr = trailing_atr.get(i).copied();
+        }
         
         // Track drawdown
         if budget_history[i] > peak_budget {
@@ -168,15 +481,13 @@ pub fn backtest_signals(
     
     // Close any open position at the end
     if position != 0 {
-        let final_price = result.prices[result.prices.len() - 1].exp();
-        let pnl = if position == 1 {
-            budget * (final_price / entry_price - 1.0)
-        } else {
-            budget * (entry_price / final_price - 1.0)
-        };
-        let cost = budget * transaction_cost_pct / 100.0;
+        let last = result.prices.len() - 1;
+        let final_price = result.prices[last].exp();
+        let (exit_price, pnl, slip) = close(position, entry_price, entry_mid, final_price, budget, last);
+        let cost = budget * fee_pct(Liquidity::Taker) / 100.0;
         budget += pnl - cost;
         total_costs += cost;
+        total_slippage += slip;
         
         if pnl > 0.0 {
             num_wins += 1;
@@ -188,15 +499,17 @@ pub fn backtest_signals(
         trades.push(TradeLog {
             entry_index: current_entry_idx,
             entry_price,
-            exit_index: result.prices.len() - 1,
-            exit_price: final_price,
+            exit_index: last,
+            exit_price,
             trade_type: if position == 1 { "LONG".to_string() } else { "SHORT".to_string() },
             pnl,
             return_pct: if position == 1 { 
-                (final_price / entry_price - 1.0) * 100.0 
+                (exit_price / entry_price - 1.0) * 100.0 
             } else { 
-                (entry_price / final_price - 1.0) * 100.0 
+                (entry_price / exit_price - 1.0) * 100.0 
             },
+            slippage: slip,
+            exit_reason: ExitReason::EndOfData,
         });
         
         num_trades += 1;
@@ -210,22 +523,20 @@ pub fn backtest_signals(
         0.0
     };
     
-    // Calculate Sharpe ratio (annualized, assuming daily data)
-    let sharpe_ratio = if !returns.is_empty() {
-        let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-        let variance = returns.iter()
-            .map(|r| (r - mean_return).powi(2))
-            .sum::<f64>() / returns.len() as f64;
-        let std_dev = variance.sqrt();
-        if std_dev > 0.0 {
-            (mean_return / std_dev) * (252.0_f64).sqrt() // Annualized
-        } else {
-            0.0
+    // Risk metrics of the per-bar equity curve, ending with the closed-out budget
+    let mut equity = budget_history.clone();
+    equity.push(budget);
+    let risk = risk_metrics(&equity);
+
+    let sharpe_ratio = match accounting {
+        AccountingBasis::Trade => trade_sharpe(&returns),
+        AccountingBasis::Bar => trade_sharpe(&simple_returns(&equity)),
+        AccountingBasis::Daily => {
+            let marks: Vec<f64> = day_ends(equity.len(), dates).into_iter().map(|i| equity[i]).collect();
+            trade_sharpe(&simple_returns(&[&[initial_budget], marks.as_slice()].concat()))
         }
-    } else {
-        0.0
     };
-    
+
     TradeStats {
         initial_budget,
         final_budget: budget,
@@ -236,14 +547,76 @@ pub fn backtest_signals(
         num_losses,
         win_rate,
         total_costs,
+        total_slippage,
         max_drawdown: max_drawdown * 100.0, // Convert to percentage
         sharpe_ratio,
+        accounting_basis: accounting,
+        sortino_ratio: risk.sortino_ratio,
+        calmar_ratio: risk.calmar_ratio,
+        mar_ratio: risk.mar_ratio,
+        ulcer_index: risk.ulcer_index,
+        avg_drawdown_duration: risk.avg_drawdown_duration,
         budget_history,
         position_history,
         trades,
     }
 }
 
+/// Protective exit of `position` on a bar closing at `close`, with its high
+/// and low if known: the reason and the price it fills at before slippage.
+/// `stop` is the stop price with the reason it exits for.
+fn protective_exit(
+    position: i32,
+    stop: Option<(f64, ExitReason)>,
+    target: Option<f64>,
+    close: f64,
+    range: Option<(f64, f64)>,
+) -> Option<(ExitReason, f64)> {
+    let long = position == 1;
+    let (high, low) = range.unwrap_or((close, close));
+    // The adverse side first, as the order within the bar is unknown
+    if let Some((stop, reason)) = stop {
+        if long && low <= stop {
+            return Some((reason, stop.min(high)));
+        }
+        if !long && high >= stop {
+            return Some((reason, stop.max(low)));
+        }
+    }
+    if let Some(target) = target {
+        if long && high >= target {
+            return Some((ExitReason::TakeProfit, target.max(low)));
+        }
+        if !long && low <= target {
+            return Some((ExitReason::TakeProfit, target.min(high)));
+        }
+    }
+    None
+}
+
+/// Returns from each value of `equity` to the next
+fn simple_returns(equity: &[f64]) -> Vec<f64> {
+    equity.windows(2).map(|w| w[1] / w[0] - 1.0).collect()
+}
+
+/// Sharpe ratio of per-trade returns, annualized as if there were one trade
+/// per day (0 without returns or variation)
+pub(crate) fn trade_sharpe(returns: &[f64]) -> f64 {
+    if returns.is_empty() {
+        return 0.0;
+    }
+    let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter()
+        .map(|r| (r - mean_return).powi(2))
+        .sum::<f64>() / returns.len() as f64;
+    let std_dev = variance.sqrt();
+    if std_dev > 0.0 {
+        (mean_return / std_dev) * (252.0_f64).sqrt() // Annualized
+    } else {
+        0.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,4 +657,300 @@ mod tests {
         assert_eq!(stats.num_trades, 4); // Counts transactions: Open Long, Close Long, Open Short, Close Short
         assert_eq!(stats.num_wins, 1);
     }
+
+    #[test]
+    fn test_backtest_slippage() {
+        let result = SignalResult {
+            prices: vec![(100.0_f64).ln(), (105.0_f64).ln(), (110.0_f64).ln()],
+            signals: vec![1, 0, -1],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+
+        let frictionless = backtest_signals(&result, 1000.0, 0.0);
+        assert_eq!(frictionless.total_slippage, 0.0);
+
+        let slippage = Slippage::Percent { pct: 1.0 };
+        let stats = backtest_signals_with_slippage(&result, 1000.0, 0.0, &slippage, None);
+
+        // Long bought at 101, sold at 108.9
+        let long = &stats.trades[0];
+        assert!((long.entry_price - 101.0).abs() < 1e-9);
+        assert!((long.exit_price - 108.9).abs() < 1e-9);
+        assert!((long.pnl - 1000.0 * (108.9 / 101.0 - 1.0)).abs() < 1e-9);
+        assert!((long.slippage - (100.0 - long.pnl)).abs() < 1e-9);
+
+        // Slippage is all the P&L lost relative to the frictionless run
+        let total: f64 = stats.trades.iter().map(|t| t.slippage).sum();
+        assert!((stats.total_slippage - total).abs() < 1e-9);
+        assert!(
+            (frictionless.final_budget - stats.final_budget - stats.total_slippage).abs()
+                < 1e-6 * stats.total_slippage
+        );
+        assert_eq!(stats.total_costs, 0.0);
+    }
+
+    #[test]
+    fn test_backtest_tradable_bars() {
+        let result = SignalResult {
+            prices: [100.0_f64, 105.0, 110.0, 120.0].iter().map(|p| p.ln()).collect(),
+            signals: vec![1, -1, 0, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        // The reversal at bar 1 falls outside the session: the long is
+        // carried, marked to market, and closed at the end
+        let tradable = [true, false, true, true];
+        let options = BacktestOptions { tradable: Some(&tradable), ..Default::default() };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        assert_eq!(stats.trades.len(), 1);
+        assert_eq!((stats.trades[0].entry_index, stats.trades[0].exit_index), (0, 3));
+        assert!((stats.final_budget - 1200.0).abs() < 1e-6);
+        assert!((stats.budget_history[1] - 1050.0).abs() < 1e-6);
+
+        let unrestricted = backtest_signals_with(&result, 1000.0, &BacktestOptions::default());
+        assert_eq!(unrestricted.trades[0].exit_index, 1);
+    }
+
+    #[test]
+    fn test_backtest_target_positions() {
+        let result = SignalResult {
+            prices: [100.0_f64, 110.0, 121.0, 110.0, 100.0].iter().map(|p| p.ln()).collect(),
+            signals: vec![1, 0, 0, -1, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        // As orders, the 0s hold the long until the reversal at bar 3
+        let held = backtest_signals_with(&result, 1000.0, &BacktestOptions::default());
+        assert_eq!((held.trades[0].exit_index, held.trades[0].exit_reason), (3, ExitReason::Signal));
+
+        // As positions, the long is closed at bar 1 and the short at bar 4,
+        // each by its signal
+        let options = BacktestOptions { target_positions: true, ..Default::default() };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        let exits: Vec<(usize, usize, ExitReason)> =
+            stats.trades.iter().map(|t| (t.entry_index, t.exit_index, t.exit_reason)).collect();
+        assert_eq!(exits, vec![(0, 1, ExitReason::Signal), (3, 4, ExitReason::Signal)]);
+        assert_eq!(stats.position_history, vec![0, 1, 0, 0, -1]);
+        assert!((stats.budget_history[2] - 1100.0).abs() < 1e-6);
+        assert!((stats.final_budget - 1100.0 * 1.1).abs() < 1e-6);
+
+        // A 0 outside the session does not close the position
+        let tradable = [true, false, false, true, true];
+        let options = BacktestOptions { tradable: Some(&tradable), ..options };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        assert_eq!(stats.trades[0].exit_index, 3);
+
+        // Deferred, the order to go flat fills on the next bar
+        let options = BacktestOptions { target_positions: true, fill: FillModel::NextOpen, ..Default::default() };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        assert_eq!((stats.trades[0].entry_index, stats.trades[0].exit_index), (1, 2));
+    }
+
+    #[test]
+    fn test_backtest_accounting_basis() {
+        let result = SignalResult {
+            prices: [100.0_f64, 110.0, 99.0, 99.0, 108.9].iter().map(|p| p.ln()).collect(),
+            signals: vec![1, 0, 0, 0, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        // One winning trade: no variation per trade, but marked to market
+        // the equity goes up 10%, down 10%, flat and up 10%
+        let trade = backtest_signals_with(&result, 1000.0, &BacktestOptions::default());
+        assert_eq!((trade.sharpe_ratio, trade.accounting_basis), (0.0, AccountingBasis::Trade));
+
+        let bar = backtest_signals_with(&result, 1000.0, &BacktestOptions { accounting: AccountingBasis::Bar, ..Default::default() });
+        let bar_returns = [0.1, -0.1, 0.0, 0.1, 0.0];
+        assert!((bar.sharpe_ratio - trade_sharpe(&bar_returns)).abs() < 1e-9);
+        assert!(bar.sharpe_ratio > 0.0);
+
+        // Marked at the close of each day, the drop is netted out
+        let dates: Vec<String> = ["20240102", "20240102", "20240102", "20240103", "20240103"].iter().map(|d| d.to_string()).collect();
+        let options = BacktestOptions { accounting: AccountingBasis::Daily, dates: Some(&dates), ..Default::default() };
+        let daily = backtest_signals_with(&result, 1000.0, &options);
+        assert!((daily.sharpe_ratio - trade_sharpe(&[-0.01, 0.1])).abs() < 1e-9);
+        assert_eq!(daily.accounting_basis, AccountingBasis::Daily);
+
+        // A long held by repeated signals is marked to market too, whether
+        // the last 0 holds it or, as a target position, closes it
+        let result = SignalResult {
+            prices: [100.0_f64, 110.0, 80.0, 120.0, 130.0].iter().map(|p| p.ln()).collect(),
+            signals: vec![1, 1, 1, 1, 0],
+            ..result
+        };
+        for target_positions in [false, true] {
+            let options = BacktestOptions { accounting: AccountingBasis::Bar, target_positions, ..Default::default() };
+            let stats = backtest_signals_with(&result, 1000.0, &options);
+            for (value, expected) in stats.budget_history.iter().zip([1000.0, 1100.0, 800.0, 1200.0, 1300.0]) {
+                assert!((value - expected).abs() < 1e-6, "{:?}", stats.budget_history);
+            }
+            assert!((stats.max_drawdown - 100.0 * 300.0 / 1100.0).abs() < 1e-9);
+            let bar_returns = [0.1, -300.0 / 1100.0, 0.5, 100.0 / 1200.0, 0.0];
+            assert!((stats.sharpe_ratio - trade_sharpe(&bar_returns)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_backtest_stops() {
+        let ln = |v: &[f64]| v.iter().map(|p| p.ln()).collect::<Vec<f64>>();
+        let result = SignalResult {
+            prices: ln(&[100.0, 99.0, 97.0, 98.0, 104.0, 103.0]),
+            signals: vec![1, 1, 1, 0, 1, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        let stops = Stops {
+            stop_loss: Some(StopLevel::Percent { pct: 2.0 }),
+            take_profit: Some(StopLevel::Percent { pct: 5.0 }),
+            trailing: None,
+        };
+
+        // On closes: stopped at the close of bar 2, kept out while the signal
+        // stays long, re-entered at 104 after it changes
+        let on_closes = backtest_signals_with(&result, 1000.0, &BacktestOptions { stops, ..Default::default() });
+        let reasons: Vec<ExitReason> = on_closes.trades.iter().map(|t| t.exit_reason).collect();
+        assert_eq!(reasons, vec![ExitReason::StopLoss, ExitReason::EndOfData]);
+        assert_eq!(on_closes.trades[0].exit_index, 2);
+        assert!((on_closes.trades[0].exit_price - 97.0).abs() < 1e-9);
+        assert_eq!(on_closes.trades[1].entry_index, 4);
+        assert!((on_closes.budget_history[2] - 970.0).abs() < 1e-9);
+
+        // Intrabar: bar 1 trades down to 97.5 and fills at the 98 stop
+        let (highs, lows) = (ln(&[100.0, 100.0, 98.0, 98.5, 104.5, 110.0]), ln(&[99.5, 97.5, 96.0, 97.5, 103.0, 102.0]));
+        let options = BacktestOptions { stops, highs: Some(&highs), lows: Some(&lows), ..Default::default() };
+        let intrabar = backtest_signals_with(&result, 1000.0, &options);
+        assert_eq!(intrabar.trades[0].exit_reason, ExitReason::StopLoss);
+        assert_eq!(intrabar.trades[0].exit_index, 1);
+        assert!((intrabar.trades[0].exit_price - 98.0).abs() < 1e-9);
+        // Re-entered at 104 on bar 4; bar 5 trades up to the 109.2 target
+        let target = &intrabar.trades[1];
+        assert_eq!((target.entry_index, target.exit_index, target.exit_reason), (4, 5, ExitReason::TakeProfit));
+        assert!((target.exit_price - 109.2).abs() < 1e-9);
+
+        // A short stopped by an ATR of 1 over two bars
+        let short = SignalResult { signals: vec![0, 0, -1, 0, 0, 0], ..result.clone() };
+        let atr_stop = Stops { stop_loss: Some(StopLevel::Atr { period: 2, multiple: 1.0 }), ..Default::default() };
+        let stats = backtest_signals_with(&short, 1000.0, &BacktestOptions { stops: atr_stop, ..Default::default() });
+        // ATR at bar 2 is (1 + 2) / 2 = 1.5: stop at 98.5, not hit by 98
+        // and hit by 104, where it fills at the close of a bar without range
+        assert_eq!((stats.trades[0].exit_index, stats.trades[0].exit_reason), (4, ExitReason::StopLoss));
+        assert!((stats.trades[0].exit_price - 104.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_backtest_trailing_stop() {
+        let ln = |v: &[f64]| v.iter().map(|p| p.ln()).collect::<Vec<f64>>();
+        let result = SignalResult {
+            prices: ln(&[100.0, 104.0, 110.0, 107.0, 104.0, 112.0]),
+            signals: vec![1, 1, 1, 1, 1, 1],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        // 5% below the best close: 104.5 once 110 is reached, hit by the 104
+        // close on bar 4 where the fixed 10% stop at 90 is not
+        let stops = Stops {
+            stop_loss: Some(StopLevel::Percent { pct: 10.0 }),
+            trailing: Some(StopLevel::Percent { pct: 5.0 }),
+            ..Default::default()
+        };
+        let stats = backtest_signals_with(&result, 1000.0, &BacktestOptions { stops, ..Default::default() });
+        assert_eq!(stats.trades[0].exit_reason, ExitReason::TrailingStop);
+        assert_eq!(stats.trades[0].exit_index, 4);
+        assert!((stats.trades[0].exit_price - 104.0).abs() < 1e-9);
+
+        // Intrabar, the high of 112 on bar 2 raises the stop to 106.4, which
+        // bar 3 trades through
+        let highs = ln(&[100.0, 105.0, 112.0, 109.0, 106.0, 112.0]);
+        let lows = ln(&[99.0, 103.0, 108.0, 106.0, 103.0, 105.0]);
+        let options = BacktestOptions { stops, highs: Some(&highs), lows: Some(&lows), ..Default::default() };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        assert_eq!((stats.trades[0].exit_index, stats.trades[0].exit_reason), (3, ExitReason::TrailingStop));
+        assert!((stats.trades[0].exit_price - 106.4).abs() < 1e-9);
+
+        // A short trailed by twice a one-bar ATR of 4 at the entry
+        let short = SignalResult { prices: ln(&[100.0, 104.0, 96.0, 92.0, 99.0, 100.0]), signals: vec![0, -1, 0, 0, 0, 0], ..result };
+        let stops = Stops { trailing: Some(StopLevel::Atr { period: 1, multiple: 2.0 }), ..Default::default() };
+        let stats = backtest_signals_with(&short, 1000.0, &BacktestOptions { stops, ..Default::default() });
+        // The best price of 92 on bar 3 brings the stop down to 100, which
+        // the 99 of bar 4 stays below and the 100 of bar 5 reaches
+        assert_eq!((stats.trades[0].exit_index, stats.trades[0].exit_reason), (5, ExitReason::TrailingStop));
+    }
+
+    #[test]
+    fn test_backtest_fill_models() {
+        let ln = |v: &[f64]| v.iter().map(|p| p.ln()).collect::<Vec<f64>>();
+        let result = SignalResult {
+            prices: ln(&[100.0, 102.0, 105.0, 103.0, 101.0, 104.0]),
+            signals: vec![0, 1, 0, -1, 0, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        let opens = ln(&[100.0, 101.0, 104.0, 104.0, 102.0, 103.0]);
+        let highs = ln(&[101.0, 103.0, 106.0, 105.0, 103.0, 105.0]);
+        let lows = ln(&[99.0, 100.0, 103.0, 102.0, 100.0, 101.0]);
+        let run = |fill: FillModel| {
+            let options = BacktestOptions { fill, opens: Some(&opens), highs: Some(&highs), lows: Some(&lows), ..Default::default() };
+            backtest_signals_with(&result, 1000.0, &options)
+        };
+
+        let stats = run(FillModel::SameClose);
+        assert_eq!((stats.trades[0].entry_index, stats.trades[0].exit_index), (1, 3));
+        assert!((stats.trades[0].entry_price - 102.0).abs() < 1e-9);
+
+        // The long fills at the open of bar 2 and reverses at the open of bar 4
+        let stats = run(FillModel::NextOpen);
+        assert_eq!((stats.trades[0].entry_index, stats.trades[0].exit_index), (2, 4));
+        assert!((stats.trades[0].entry_price - 104.0).abs() < 1e-9);
+        assert!((stats.trades[0].exit_price - 102.0).abs() < 1e-9);
+        assert!((stats.trades[1].entry_price - 102.0).abs() < 1e-9);
+
+        let stats = run(FillModel::NextMid);
+        assert!((stats.trades[0].entry_price - 104.5).abs() < 1e-9);
+
+        // A buy limit 1% below 102 is not reached by bar 2, nor the sell
+        // limit 1% above 103 by bar 4
+        let stats = run(FillModel::Limit { pct: 1.0 });
+        assert!(stats.trades.is_empty());
+
+        // A buy stop at 103.02 fills at the gap open of 104
+        let stats = run(FillModel::Stop { pct: 1.0 });
+        assert!((stats.trades[0].entry_price - 104.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_backtest_fees() {
+        let result = SignalResult {
+            prices: vec![100.0_f64.ln(); 4],
+            signals: vec![1, 0, 0, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        let fees = FeeSchedule { name: "test".to_string(), maker_pct: 0.02, taker_pct: 0.05 };
+        // The schedule replaces the flat cost: a taker entry and a taker exit
+        let options = BacktestOptions { transaction_cost_pct: 1.0, fees: Some(&fees), ..Default::default() };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        assert!((stats.total_costs - (0.5 + 999.5 * 0.0005)).abs() < 1e-9);
+
+        // A limit order at the close fills on the next bar as a maker
+        let options = BacktestOptions { fees: Some(&fees), fill: FillModel::Limit { pct: 0.0 }, ..Default::default() };
+        let stats = backtest_signals_with(&result, 1000.0, &options);
+        assert!((stats.total_costs - (0.2 + 999.8 * 0.0005)).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,210 @@
+//! Anonymized export of a backtest for sharing.
+//!
+//! The equity curve becomes a series of simple returns and an equity index
+//! starting at 1, the dates are shifted to start on an arbitrary anchor date,
+//! and the trades keep only their side, bars and return. Prices, currency
+//! amounts, bar indices into the original data and provenance are dropped,
+//! so a third party can check the performance without learning the market
+//! or the period it was earned in.
+
+use crate::models::TradeStats;
+
+/// Default first date of a date-shifted export (a Monday, so weekdays stay
+/// weekdays when the original dates are shifted by whole weeks)
+pub const DEFAULT_ANCHOR: &str = "20000103";
+
+/// A trade of the anonymized series
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnonymizedTrade {
+    /// Bar of the entry, counted from the first bar of the export
+    pub entry_bar: usize,
+    /// Bar of the exit, counted from the first bar of the export
+    pub exit_bar: usize,
+    /// 1 for long, -1 for short
+    pub side: i32,
+    /// Return of the trade in percent
+    pub return_pct: f64,
+}
+
+/// Normalized, date-shifted returns of a backtest
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnonymizedSeries {
+    /// Shifted date of each bar as YYYYMMDD, empty if exported without dates
+    pub dates: Vec<String>,
+    /// Simple return of each bar, 0 on the first
+    pub returns: Vec<f64>,
+    /// Equity relative to the initial budget, 1 on the first bar
+    pub equity: Vec<f64>,
+    pub trades: Vec<AnonymizedTrade>,
+}
+
+impl AnonymizedSeries {
+    /// One row per bar: bar, shifted date (if any), return, equity
+    pub fn returns_csv(&self) -> String {
+        let dated = !self.dates.is_empty();
+        let mut csv = String::from(if dated { "bar,date,return,equity\n" } else { "bar,return,equity\n" });
+        for (i, (r, e)) in self.returns.iter().zip(&self.equity).enumerate() {
+            if dated {
+                csv.push_str(&format!("{},{},{:.10},{:.10}\n", i, self.dates[i], r, e));
+            } else {
+                csv.push_str(&format!("{},{:.10},{:.10}\n", i, r, e));
+            }
+        }
+        csv
+    }
+
+    /// One row per trade: entry and exit bar, side, return in percent
+    pub fn trades_csv(&self) -> String {
+        let mut csv = String::from("entry_bar,exit_bar,side,return_pct\n");
+        for t in &self.trades {
+            let side = if t.side > 0 { "long" } else { "short" };
+            csv.push_str(&format!("{},{},{},{:.6}\n", t.entry_bar, t.exit_bar, side, t.return_pct));
+        }
+        csv
+    }
+}
+
+/// Anonymize the equity curve and trades of a backtest.
+///
+/// `dates` are the dates of the backtested bars (YYYYMMDD, any separators
+/// ignored); they are shifted by a whole number of weeks so the first lands
+/// on or just after `anchor`, keeping the gaps between bars. Without dates
+/// the bars are only numbered.
+pub fn anonymize(stats: &TradeStats, dates: Option<&[String]>, anchor: &str) -> Result<AnonymizedSeries, String> {
+    let history = &stats.budget_history;
+    let base = history.first().copied().unwrap_or(stats.initial_budget);
+    if !(base.is_finite() && base > 0.0) {
+        return Err("Cannot normalize an equity curve that does not start positive".to_string());
+    }
+    let equity: Vec<f64> = history.iter().map(|b| b / base).collect();
+    let returns = std::iter::once(0.0)
+        .chain(equity.windows(2).map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { 0.0 }))
+        .take(equity.len())
+        .collect();
+
+    let dates = match dates {
+        Some(dates) if !history.is_empty() => {
+            if dates.len() != history.len() {
+                return Err(format!("{} dates for {} bars", dates.len(), history.len()));
+            }
+            let days = dates.iter().map(|d| parse_day(d)).collect::<Result<Vec<i64>, String>>()?;
+            let anchor = parse_day(anchor)?;
+            // Whole weeks, so weekdays (and weekend gaps) are unchanged
+            let shift = (anchor - days[0] + 6).div_euclid(7) * 7;
+            days.iter().map(|d| format_day(d + shift)).collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let trades = stats
+        .trades
+        .iter()
+        .map(|t| AnonymizedTrade {
+            entry_bar: t.entry_index,
+            exit_bar: t.exit_index,
+            side: if t.trade_type == "SHORT" { -1 } else { 1 },
+            return_pct: t.return_pct,
+        })
+        .collect();
+
+    Ok(AnonymizedSeries { dates, returns, equity, trades })
+}
+
+/// Days since 1970-01-01 of a YYYYMMDD date
+fn parse_day(date: &str) -> Result<i64, String> {
+    let digits: String = date.chars().filter(|c| c.is_ascii_digit()).take(8).collect();
+    let invalid = || format!("Invalid date '{}', expected YYYYMMDD", date);
+    if digits.len() != 8 {
+        return Err(invalid());
+    }
+    let (y, m, d): (i64, i64, i64) =
+        (digits[..4].parse().unwrap(), digits[4..6].parse().unwrap(), digits[6..].parse().unwrap());
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return Err(invalid());
+    }
+    // Howard Hinnant's days_from_civil
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Ok(era * 146097 + doe - 719468)
+}
+
+/// YYYYMMDD of a day counted from 1970-01-01
+fn format_day(days: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}", y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TradeLog;
+
+    #[test]
+    fn test_anonymize() {
+        let stats = TradeStats {
+            initial_budget: 5000.0,
+            final_budget: 5500.0,
+            total_pnl: 500.0,
+            roi_percent: 10.0,
+            num_trades: 1,
+            num_wins: 1,
+            num_losses: 0,
+            win_rate: 100.0,
+            total_costs: 0.0,
+            total_slippage: 0.0,
+            max_drawdown: 0.0,
+            sharpe_ratio: 0.0,
+            accounting_basis: Default::default(),
+            sortino_ratio: 0.0,
+            calmar_ratio: 0.0,
+            mar_ratio: 0.0,
+            ulcer_index: 0.0,
+            avg_drawdown_duration: 0.0,
+            budget_history: vec![5000.0, 4000.0, 5000.0, 5500.0],
+            position_history: vec![0, -1, -1, 0],
+            trades: vec![TradeLog {
+                entry_index: 1,
+                entry_price: 1234.5,
+                exit_index: 3,
+                exit_price: 1100.0,
+                trade_type: "SHORT".to_string(),
+                pnl: 500.0,
+                return_pct: 10.0,
+                slippage: 0.0,
+                exit_reason: Default::default(),
+            }],
+        };
+        // Wednesday 2015-12-30 to Monday 2016-01-04, across the new year
+        let dates: Vec<String> = ["2015-12-30", "2015-12-31", "2016-01-01", "2016-01-04"].iter().map(|d| d.to_string()).collect();
+        let series = anonymize(&stats, Some(&dates), DEFAULT_ANCHOR).unwrap();
+        assert_eq!(series.equity, vec![1.0, 0.8, 1.0, 1.1]);
+        assert_eq!(series.returns.len(), 4);
+        assert!((series.returns[1] + 0.2).abs() < 1e-12 && (series.returns[2] - 0.25).abs() < 1e-12);
+        // Wednesday 2015-12-30 moves to the first Wednesday on or after the anchor
+        assert_eq!(series.dates, vec!["20000105", "20000106", "20000107", "20000110"]);
+        assert_eq!(series.trades, vec![AnonymizedTrade { entry_bar: 1, exit_bar: 3, side: -1, return_pct: 10.0 }]);
+        let csv = series.returns_csv() + &series.trades_csv();
+        assert!(!csv.contains("1234") && !csv.contains("2015"));
+        assert!(series.trades_csv().contains("1,3,short,10.000000"));
+
+        let undated = anonymize(&stats, None, DEFAULT_ANCHOR).unwrap();
+        assert!(undated.dates.is_empty());
+        assert!(undated.returns_csv().starts_with("bar,return,equity\n0,"));
+        assert!(anonymize(&stats, Some(&dates[..2]), DEFAULT_ANCHOR).is_err());
+
+        assert_eq!(format_day(parse_day("20240229").unwrap()), "20240229");
+        assert_eq!(parse_day("19700101"), Ok(0));
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Side of the book a fill takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    /// A resting limit order, filled by someone else's.
+    Maker,
+    /// An order filled at once against the book.
+    Taker,
+}
+
+/// Maker and taker fees of a venue and instrument, as percentages of the
+/// traded value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub name: String,
+    pub maker_pct: f64,
+    pub taker_pct: f64,
+}
+
+/// Built-in schedules: name, maker and taker fee in percent. Bybit's
+/// published base and VIP 1-3 tiers; venues change them, so check before
+/// relying on them.
+const PRESETS: &[(&str, f64, f64)] = &[
+    ("bybit-spot", 0.1, 0.1),
+    ("bybit-spot-vip1", 0.0675, 0.08),
+    ("bybit-spot-vip2", 0.065, 0.0775),
+    ("bybit-spot-vip3", 0.0625, 0.075),
+    ("bybit-perp", 0.02, 0.055),
+    ("bybit-perp-vip1", 0.018, 0.04),
+    ("bybit-perp-vip2", 0.016, 0.0375),
+    ("bybit-perp-vip3", 0.014, 0.035),
+];
+
+/// Schedules of a TOML file, one `[[schedule]]` table each
+#[derive(Debug, Deserialize)]
+struct ScheduleFile {
+    #[serde(default)]
+    schedule: Vec<FeeSchedule>,
+}
+
+impl FeeSchedule {
+    /// Fee in percent of a fill taking `liquidity`
+    pub fn rate_pct(&self, liquidity: Liquidity) -> f64 {
+        match liquidity {
+            Liquidity::Maker => self.maker_pct,
+            Liquidity::Taker => self.taker_pct,
+        }
+    }
+
+    /// Built-in schedule called `name`
+    pub fn preset(name: &str) -> Option<FeeSchedule> {
+        let name = name.trim().to_ascii_lowercase();
+        PRESETS.iter().find(|(n, _, _)| *n == name).map(|&(name, maker_pct, taker_pct)| FeeSchedule {
+            name: name.to_string(),
+            maker_pct,
+            taker_pct,
+        })
+    }
+
+    /// Names of the built-in schedules
+    pub fn preset_names() -> Vec<&'static str> {
+        PRESETS.iter().map(|(n, _, _)| *n).collect()
+    }
+
+    /// Schedules defined in TOML `text`:
+    ///
+    /// ```toml
+    /// [[schedule]]
+    /// name = "my-broker"
+    /// maker_pct = 0.02
+    /// taker_pct = 0.05
+    /// ```
+    ///
+    /// A negative fee is a rebate.
+    pub fn parse_schedules(text: &str) -> Result<Vec<FeeSchedule>, String> {
+        let file: ScheduleFile = toml::from_str(text).map_err(|e| format!("Invalid fee schedules: {}", e))?;
+        for s in &file.schedule {
+            if !s.maker_pct.is_finite() || !s.taker_pct.is_finite() {
+                return Err(format!("Fee schedule '{}' has an invalid fee", s.name));
+            }
+        }
+        Ok(file.schedule)
+    }
+
+    /// Schedule called `name`, looked up in the schedules file `custom`
+    /// first, then among the presets
+    pub fn resolve(name: &str, custom: Option<&Path>) -> Result<FeeSchedule, String> {
+        if let Some(path) = custom {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read fee schedules {}: {}", path.display(), e))?;
+            if let Some(found) = Self::parse_schedules(&text)?.into_iter().find(|s| s.name.eq_ignore_ascii_case(name.trim())) {
+                return Ok(found);
+            }
+        }
+        Self::preset(name).ok_or_else(|| {
+            format!("Unknown fee schedule '{}' (presets: {})", name, Self::preset_names().join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_and_custom() {
+        let perp = FeeSchedule::preset("Bybit-Perp").unwrap();
+        assert_eq!((perp.rate_pct(Liquidity::Maker), perp.rate_pct(Liquidity::Taker)), (0.02, 0.055));
+        assert!(FeeSchedule::preset("unknown").is_none());
+
+        let custom = FeeSchedule::parse_schedules(
+            "[[schedule]]\nname = \"broker\"\nmaker_pct = -0.01\ntaker_pct = 0.04\n",
+        )
+        .unwrap();
+        assert_eq!(custom, vec![FeeSchedule { name: "broker".to_string(), maker_pct: -0.01, taker_pct: 0.04 }]);
+        assert!(FeeSchedule::parse_schedules("[[schedule]]\nname = \"x\"\n").is_err());
+        assert!(FeeSchedule::resolve("nowhere", None).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Price an order fills at, before slippage.
+///
+/// Every model but `SameClose` fills the order of a bar's signal on the
+/// next bar, the first one it could trade on.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum FillModel {
+    /// At the close of the signal bar.
+    #[default]
+    SameClose,
+    /// At the open of the next bar.
+    NextOpen,
+    /// Halfway between the high and the low of the next bar.
+    NextMid,
+    /// A limit order `pct` percent better than the signal bar's close,
+    /// filled if the next bar's range reaches it; otherwise the order lapses.
+    Limit { pct: f64 },
+    /// A stop order `pct` percent beyond the signal bar's close, filled if
+    /// the next bar's range reaches it; otherwise the order lapses.
+    Stop { pct: f64 },
+}
+
+impl FillModel {
+    /// Whether orders fill on the bar after their signal
+    pub fn is_deferred(&self) -> bool {
+        !matches!(self, FillModel::SameClose)
+    }
+
+    /// Fill of a buy (or sell) order placed at the close `placed_at` on a
+    /// bar with `open`, high and low (`range`) and `close`, or `None` if a
+    /// limit or stop order is not reached.
+    ///
+    /// Without an open, `NextOpen` fills at the close, and a gap through a
+    /// limit or stop level fills at the nearest extreme of the bar; without
+    /// a range the bar is its close alone.
+    pub fn fill_price(&self, buy: bool, placed_at: f64, open: Option<f64>, range: Option<(f64, f64)>, close: f64) -> Option<f64> {
+        let (high, low) = range.unwrap_or((close, close));
+        match *self {
+            FillModel::SameClose => Some(close),
+            FillModel::NextOpen => Some(open.unwrap_or(close)),
+            FillModel::NextMid => Some(range.map_or(close, |(high, low)| (high + low) / 2.0)),
+            FillModel::Limit { pct } => {
+                if buy {
+                    let level = placed_at * (1.0 - pct / 100.0);
+                    (low <= level).then(|| level.min(open.unwrap_or(high)))
+                } else {
+                    let level = placed_at * (1.0 + pct / 100.0);
+                    (high >= level).then(|| level.max(open.unwrap_or(low)))
+                }
+            }
+            FillModel::Stop { pct } => {
+                if buy {
+                    let level = placed_at * (1.0 + pct / 100.0);
+                    (high >= level).then(|| level.max(open.unwrap_or(low)))
+                } else {
+                    let level = placed_at * (1.0 - pct / 100.0);
+                    (low <= level).then(|| level.min(open.unwrap_or(high)))
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for FillModel {
+    type Err = String;
+
+    /// Parse `close`, `open`, `mid`, `limit:<pct>` or `stop:<pct>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let model = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let values = parts
+            .map(|p| p.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("Invalid fill model '{}': {}", s, e))?;
+        if values.iter().any(|&v| v < 0.0) {
+            return Err(format!("Invalid fill model '{}': values must not be negative", s));
+        }
+
+        match (model.as_str(), values.as_slice()) {
+            ("close" | "same-close", []) => Ok(FillModel::SameClose),
+            ("open" | "next-open", []) => Ok(FillModel::NextOpen),
+            ("mid" | "next-mid", []) => Ok(FillModel::NextMid),
+            ("limit", &[pct]) => Ok(FillModel::Limit { pct }),
+            ("stop", &[pct]) => Ok(FillModel::Stop { pct }),
+            _ => Err(format!("Invalid fill model '{}' (expected close, open, mid, limit:<pct> or stop:<pct>)", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_prices() {
+        assert_eq!("open".parse::<FillModel>(), Ok(FillModel::NextOpen));
+        assert_eq!("limit:0.5".parse::<FillModel>(), Ok(FillModel::Limit { pct: 0.5 }));
+        assert!("stop:-1".parse::<FillModel>().is_err());
+        assert!("vwap".parse::<FillModel>().is_err());
+        assert!(!FillModel::SameClose.is_deferred() && FillModel::NextMid.is_deferred());
+
+        let range = Some((104.0, 98.0));
+        assert_eq!(FillModel::SameClose.fill_price(true, 100.0, Some(101.0), range, 103.0), Some(103.0));
+        assert_eq!(FillModel::NextOpen.fill_price(true, 100.0, Some(101.0), range, 103.0), Some(101.0));
+        assert_eq!(FillModel::NextOpen.fill_price(true, 100.0, None, range, 103.0), Some(103.0));
+        assert_eq!(FillModel::NextMid.fill_price(false, 100.0, None, range, 103.0), Some(101.0));
+
+        // Limits fill at their level, or at the open of a bar gapping through it
+        let limit = FillModel::Limit { pct: 1.0 };
+        assert_eq!(limit.fill_price(true, 100.0, Some(101.0), range, 103.0), Some(99.0));
+        assert_eq!(limit.fill_price(true, 100.0, Some(98.5), range, 103.0), Some(98.5));
+        assert_eq!(limit.fill_price(false, 100.0, Some(101.0), range, 103.0), Some(101.0));
+        assert_eq!(limit.fill_price(true, 100.0, None, None, 103.0), None);
+
+        // Stops fill at their level, or at the open beyond it
+        let stop = FillModel::Stop { pct: 2.0 };
+        assert_eq!(stop.fill_price(true, 100.0, Some(101.0), range, 103.0), Some(102.0));
+        assert_eq!(stop.fill_price(false, 100.0, Some(101.0), range, 103.0), Some(98.0));
+        assert_eq!(stop.fill_price(false, 100.0, Some(101.0), Some((104.0, 99.0)), 103.0), None);
+    }
+}
@@ -0,0 +1,360 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::accounting::AccountingBasis;
+use crate::core::trade_sharpe;
+use crate::models::{TradeLog, TradeStats};
+use crate::risk::risk_metrics;
+use crate::stops::ExitReason;
+
+/// Direction of an executed order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "buy" | "b" => Ok(Side::Buy),
+            "sell" | "s" => Ok(Side::Sell),
+            _ => Err(format!("Unknown side '{}' (expected buy or sell)", s)),
+        }
+    }
+}
+
+/// One executed trade from a broker's journal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    /// Execution time, as written in the journal.
+    pub timestamp: String,
+    pub side: Side,
+    /// Units filled, always positive.
+    pub qty: f64,
+    pub price: f64,
+    /// Commission and fees paid, in the account currency.
+    pub fee: f64,
+}
+
+/// Parse a trade journal in CSV form.
+///
+/// The header names the columns, in any order and any case: `timestamp`,
+/// `side`, `qty` (or `quantity`), `price` and an optional `fee` (or
+/// `commission`). Blank lines and lines starting with `#` are skipped. Fills
+/// must be in execution order.
+pub fn parse_fills(text: &str) -> Result<Vec<Fill>, String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let (_, header) = lines.next().ok_or("Trade journal is empty")?;
+    let names: Vec<String> = header.split(',').map(|h| h.trim().to_ascii_lowercase()).collect();
+    let column = |aliases: &[&str]| names.iter().position(|n| aliases.contains(&n.as_str()));
+    let required = |aliases: &[&str]| column(aliases).ok_or(format!("Trade journal has no '{}' column", aliases[0]));
+    let i_time = required(&["timestamp", "time", "date"])?;
+    let i_side = required(&["side"])?;
+    let i_qty = required(&["qty", "quantity"])?;
+    let i_price = required(&["price"])?;
+    let i_fee = column(&["fee", "fees", "commission"]);
+
+    lines
+        .map(|(line_no, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |i: usize| {
+                fields
+                    .get(i)
+                    .copied()
+                    .ok_or(format!("Line {}: expected {} fields", line_no + 1, names.len()))
+            };
+            let number = |i: usize| -> Result<f64, String> {
+                let text = field(i)?;
+                text.parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite())
+                    .ok_or(format!("Line {}: invalid number '{}'", line_no + 1, text))
+            };
+
+            let fill = Fill {
+                timestamp: field(i_time)?.to_string(),
+                side: field(i_side)?.parse().map_err(|e| format!("Line {}: {}", line_no + 1, e))?,
+                qty: number(i_qty)?,
+                price: number(i_price)?,
+                fee: match i_fee {
+                    Some(i) if !field(i)?.is_empty() => number(i)?,
+                    _ => 0.0,
+                },
+            };
+            if fill.qty <= 0.0 || fill.price <= 0.0 || fill.fee < 0.0 {
+                return Err(format!(
+                    "Line {}: quantity and price must be positive and the fee not negative",
+                    line_no + 1
+                ));
+            }
+            Ok(fill)
+        })
+        .collect()
+}
+
+/// Read a CSV trade journal (see `parse_fills`).
+pub fn read_fills<P: AsRef<Path>>(path: P) -> Result<Vec<Fill>, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read trade journal {}: {}", path.display(), e))?;
+    parse_fills(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Account state after each fill of a journal, independent of the budget.
+struct Replay {
+    trades: Vec<TradeLog>,
+    /// Realized P&L less fees to date, plus the open position marked at the fill.
+    pnl_history: Vec<f64>,
+    /// Realized P&L less fees to date, at the close of each trade.
+    closed_pnl: Vec<f64>,
+    position_history: Vec<i32>,
+    total_fees: f64,
+}
+
+/// Replay the fills, matching them into round trips from flat to flat.
+///
+/// A fill that reverses the position closes the trade and opens the next one
+/// with what remains at the same price.
+fn replay(fills: &[Fill]) -> Replay {
+    let mut position: f64 = 0.0; // Signed units
+    let mut entry_value: f64 = 0.0; // Units opened times their prices
+    let mut entry_qty = 0.0;
+    let mut exit_value = 0.0;
+    let mut exit_qty = 0.0;
+    let mut trade_pnl = 0.0;
+    let mut entry_index = 0;
+    let mut realized = 0.0;
+    let mut total_fees = 0.0;
+    let mut out = Replay {
+        trades: Vec::new(),
+        pnl_history: Vec::with_capacity(fills.len()),
+        closed_pnl: Vec::new(),
+        position_history: Vec::with_capacity(fills.len()),
+        total_fees: 0.0,
+    };
+
+    for (i, fill) in fills.iter().enumerate() {
+        total_fees += fill.fee;
+        let signed = if fill.side == Side::Buy { fill.qty } else { -fill.qty };
+        let mut remaining = signed;
+
+        // Part of the fill that reduces the open position
+        if position != 0.0 && position.signum() != signed.signum() {
+            let closed = signed.abs().min(position.abs());
+            let avg_entry = entry_value / entry_qty;
+            let pnl = closed * (fill.price - avg_entry) * position.signum();
+            trade_pnl += pnl;
+            realized += pnl;
+            exit_value += closed * fill.price;
+            exit_qty += closed;
+            position += closed * signed.signum();
+            remaining -= closed * signed.signum();
+
+            if position.abs() < 1e-12 {
+                let long = signed < 0.0;
+                let exit_price = exit_value / exit_qty;
+                out.trades.push(TradeLog {
+                    entry_index,
+                    entry_price: avg_entry,
+                    exit_index: i,
+                    exit_price,
+                    trade_type: if long { "LONG" } else { "SHORT" }.to_string(),
+                    pnl: trade_pnl,
+                    return_pct: if long {
+                        (exit_price / avg_entry - 1.0) * 100.0
+                    } else {
+                        (avg_entry / exit_price - 1.0) * 100.0
+                    },
+                    slippage: 0.0,
+                    exit_reason: ExitReason::Signal,
+                });
+                out.closed_pnl.push(realized - total_fees);
+                position = 0.0;
+                entry_value = 0.0;
+                entry_qty = 0.0;
+                exit_value = 0.0;
+                exit_qty = 0.0;
+                trade_pnl = 0.0;
+            }
+        }
+
+        // Part of the fill that opens or adds to the position
+        if remaining.abs() > 1e-12 {
+            if position == 0.0 {
+                entry_index = i;
+            }
+            position += remaining;
+            entry_value += remaining.abs() * fill.price;
+            entry_qty += remaining.abs();
+        }
+
+        let unrealized = if entry_qty > 0.0 {
+            position * (fill.price - entry_value / entry_qty)
+        } else {
+            0.0
+        };
+        out.pnl_history.push(realized - total_fees + unrealized);
+        out.position_history.push(if position > 0.0 { 1 } else if position < 0.0 { -1 } else { 0 });
+    }
+
+    out.total_fees = total_fees;
+    out
+}
+
+/// Round trips of a journal as trade logs, for tools that only need the
+/// trades (their returns) and not an account size.
+pub fn journal_trades(fills: &[Fill]) -> Vec<TradeLog> {
+    replay(fills).trades
+}
+
+/// Statistics of real executed trades, in the same form as a backtest.
+///
+/// Fills are matched into round trips from flat to flat, at the average
+/// price of their entries and exits. Each `TradeLog` indexes the fills, and
+/// its `pnl` is before fees, which are reported in `total_costs`. The equity
+/// curve has one value per fill, with the open position marked at the fill
+/// price. A position still open after the last fill is not closed: it counts
+/// in the final budget at the last fill's price but not in the trades.
+pub fn journal_stats(fills: &[Fill], initial_budget: f64) -> Result<TradeStats, String> {
+    if fills.is_empty() {
+        return Err("Trade journal has no fills".to_string());
+    }
+    if initial_budget <= 0.0 {
+        return Err("Initial budget must be positive".to_string());
+    }
+
+    let replay = replay(fills);
+    let budget_history: Vec<f64> = replay.pnl_history.iter().map(|pnl| initial_budget + pnl).collect();
+    let final_budget = *budget_history.last().unwrap_or(&initial_budget);
+
+    let mut peak = initial_budget;
+    let mut max_drawdown: f64 = 0.0;
+    for &equity in &budget_history {
+        peak = peak.max(equity);
+        max_drawdown = max_drawdown.max((peak - equity) / peak);
+    }
+
+    let returns: Vec<f64> = replay
+        .trades
+        .iter()
+        .zip(&replay.closed_pnl)
+        .map(|(trade, closed)| trade.pnl / (initial_budget + closed))
+        .collect();
+    let num_wins = replay.trades.iter().filter(|t| t.pnl > 0.0).count();
+    let num_trades = replay.trades.len();
+    let total_pnl = final_budget - initial_budget;
+
+    let mut equity = vec![initial_budget];
+    equity.extend_from_slice(&budget_history);
+    let risk = risk_metrics(&equity);
+
+    Ok(TradeStats {
+        initial_budget,
+        final_budget,
+        total_pnl,
+        roi_percent: total_pnl / initial_budget * 100.0,
+        num_trades,
+        num_wins,
+        num_losses: num_trades - num_wins,
+        win_rate: if num_trades > 0 { num_wins as f64 / num_trades as f64 * 100.0 } else { 0.0 },
+        total_costs: replay.total_fees,
+        total_slippage: 0.0,
+        max_drawdown: max_drawdown * 100.0,
+        sharpe_ratio: trade_sharpe(&returns),
+        accounting_basis: AccountingBasis::Trade,
+        sortino_ratio: risk.sortino_ratio,
+        calmar_ratio: risk.calmar_ratio,
+        mar_ratio: risk.mar_ratio,
+        ulcer_index: risk.ulcer_index,
+        avg_drawdown_duration: risk.avg_drawdown_duration,
+        budget_history,
+        position_history: replay.position_history,
+        trades: replay.trades,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOURNAL: &str = "\
+# Exported fills
+Timestamp,Side,Qty,Price,Fee
+2024-01-02 09:30,BUY,10,100,1
+2024-01-02 10:00,buy,10,110,1
+2024-01-03 15:00,sell,20,120,2
+
+2024-01-04 09:30,sell,5,120,0.5
+2024-01-05 09:30,buy,10,100,1
+2024-01-06 09:30,sell,5,90,
+";
+
+    #[test]
+    fn test_parse_fills() {
+        let fills = parse_fills(JOURNAL).unwrap();
+        assert_eq!(fills.len(), 6);
+        assert_eq!(fills[0].timestamp, "2024-01-02 09:30");
+        assert_eq!(fills[2].side, Side::Sell);
+        assert_eq!(fills[5].fee, 0.0);
+
+        // Columns in another order, without fees
+        let fills = parse_fills("price,quantity,side,time\n1.5,2,S,t0\n").unwrap();
+        assert_eq!(fills[0], Fill { timestamp: "t0".into(), side: Side::Sell, qty: 2.0, price: 1.5, fee: 0.0 });
+
+        assert!(parse_fills("timestamp,side,price\n").is_err());
+        assert!(parse_fills("timestamp,side,qty,price\nt,hold,1,1\n").is_err());
+        assert!(parse_fills("timestamp,side,qty,price\nt,buy,-1,1\n").is_err());
+        assert!(parse_fills("timestamp,side,qty,price\nt,buy,1\n").is_err());
+    }
+
+    #[test]
+    fn test_journal_stats() {
+        let fills = parse_fills(JOURNAL).unwrap();
+        let stats = journal_stats(&fills, 10_000.0).unwrap();
+
+        // Long 20 at an average of 105, sold at 120; then short 5 at 120
+        // reversed to long 5 at 100, closed at 90
+        assert_eq!(stats.trades.len(), 3);
+        let long = &stats.trades[0];
+        assert_eq!((long.entry_index, long.exit_index), (0, 2));
+        assert_eq!(long.trade_type, "LONG");
+        assert!((long.entry_price - 105.0).abs() < 1e-12);
+        assert!((long.pnl - 300.0).abs() < 1e-9);
+        let short = &stats.trades[1];
+        assert_eq!(short.trade_type, "SHORT");
+        assert!((short.pnl - 100.0).abs() < 1e-9);
+        assert!((short.return_pct - 20.0).abs() < 1e-9);
+        assert_eq!((stats.trades[2].entry_index, stats.trades[2].exit_index), (4, 5));
+        assert!((stats.trades[2].pnl + 50.0).abs() < 1e-9);
+
+        assert_eq!((stats.num_wins, stats.num_losses), (2, 1));
+        assert!((stats.total_costs - 5.5).abs() < 1e-12);
+        assert!((stats.final_budget - (10_000.0 + 350.0 - 5.5)).abs() < 1e-9);
+        assert_eq!(stats.position_history, vec![1, 1, 0, -1, 1, 0]);
+        // Marked to market: 10 units bought at 100 are worth 110 at the second fill
+        assert!((stats.budget_history[1] - (10_000.0 + 100.0 - 2.0)).abs() < 1e-9);
+        assert!(stats.max_drawdown > 0.0);
+
+        assert_eq!(journal_trades(&fills).len(), 3);
+        assert!(journal_stats(&[], 10_000.0).is_err());
+    }
+
+    #[test]
+    fn test_open_position_is_not_a_trade() {
+        let fills = parse_fills("timestamp,side,qty,price\nt0,buy,1,100\nt1,buy,1,120\n").unwrap();
+        let stats = journal_stats(&fills, 1000.0).unwrap();
+        assert!(stats.trades.is_empty());
+        assert_eq!(stats.final_budget, 1020.0);
+        assert_eq!(stats.win_rate, 0.0);
+    }
+}
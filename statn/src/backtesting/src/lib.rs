@@ -1,5 +1,33 @@
+pub mod accounting;
+pub mod alerts;
+pub mod audit;
 pub mod core;
+pub mod export;
+pub mod fees;
+pub mod fills;
+pub mod journal;
 pub mod models;
+pub mod monitor;
+pub mod outliers;
+pub mod portfolio;
+pub mod reconcile;
+pub mod risk;
+pub mod slippage;
+pub mod stops;
 
-pub use core::backtest_signals;
+pub use accounting::{day_ends, period_returns, AccountingBasis};
+pub use alerts::{parse_sink, Alert, AlertEngine, AlertRule, AlertSink, CommandSink, StdoutSink};
+pub use audit::{audit_determinism, AuditReport};
+pub use core::{backtest_signals, backtest_signals_with, backtest_signals_with_slippage, BacktestOptions};
+pub use export::{anonymize, AnonymizedSeries, AnonymizedTrade, DEFAULT_ANCHOR};
+pub use fees::{FeeSchedule, Liquidity};
+pub use fills::FillModel;
+pub use journal::{journal_stats, journal_trades, parse_fills, read_fills, Fill, Side};
+pub use reconcile::{reconcile, Reconciliation};
 pub use models::{SignalResult, TradeLog, TradeStats};
+pub use monitor::{RiskMonitor, RiskSnapshot, SnapshotEmitter, SnapshotSink};
+pub use outliers::{outlier_analysis, OutlierCase, OutlierReport, Removal, BOOTSTRAP_REPS, BOOTSTRAP_SEED};
+pub use portfolio::{backtest_book, Allocation, BookReport, BookStats};
+pub use risk::{risk_metrics, RiskMetrics};
+pub use slippage::Slippage;
+pub use stops::{average_true_range, ExitReason, StopLevel, Stops};
@@ -1,15 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+use crate::accounting::AccountingBasis;
+use crate::stops::ExitReason;
+
 /// Detailed information about a single trade.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeLog {
     /// Index where the trade was opened.
     pub entry_index: usize,
-    /// Price at which the trade was opened.
+    /// Price at which the trade was opened (the fill, after slippage).
     pub entry_price: f64,
     /// Index where the trade was closed.
     pub exit_index: usize,
-    /// Price at which the trade was closed.
+    /// Price at which the trade was closed (the fill, after slippage).
     pub exit_price: f64,
     /// Type of trade: "LONG" or "SHORT".
     pub trade_type: String,
@@ -17,6 +20,12 @@ pub struct TradeLog {
     pub pnl: f64,
     /// Return percentage for this trade.
     pub return_pct: f64,
+    /// P&L lost to slippage at entry and exit (included in `pnl`).
+    #[serde(default)]
+    pub slippage: f64,
+    /// Why the trade was closed.
+    #[serde(default)]
+    pub exit_reason: ExitReason,
 }
 
 /// Statistics from backtesting a trading strategy.
@@ -40,10 +49,31 @@ pub struct TradeStats {
     pub win_rate: f64,
     /// Total transaction costs paid.
     pub total_costs: f64,
+    /// Total P&L lost to slippage, not included in `total_costs`.
+    #[serde(default)]
+    pub total_slippage: f64,
     /// Maximum drawdown experienced.
     pub max_drawdown: f64,
-    /// Sharpe ratio (if applicable).
+    /// Sharpe ratio (if applicable), over the periods of `accounting_basis`.
     pub sharpe_ratio: f64,
+    /// Periods of the Sharpe ratio.
+    #[serde(default)]
+    pub accounting_basis: AccountingBasis,
+    /// Sortino ratio, annualized.
+    #[serde(default)]
+    pub sortino_ratio: f64,
+    /// Calmar ratio over the last three years.
+    #[serde(default)]
+    pub calmar_ratio: f64,
+    /// MAR ratio (Calmar over the whole history).
+    #[serde(default)]
+    pub mar_ratio: f64,
+    /// Ulcer index, in percent.
+    #[serde(default)]
+    pub ulcer_index: f64,
+    /// Mean drawdown duration in bars.
+    #[serde(default)]
+    pub avg_drawdown_duration: f64,
     /// History of budget over time.
     pub budget_history: Vec<f64>,
     /// History of positions (1 = long, -1 = short, 0 = flat).
@@ -0,0 +1,248 @@
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::risk::BARS_PER_YEAR;
+
+/// State of a running strategy at one bar, for external dashboards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskSnapshot {
+    /// Time of the bar, as given by the caller.
+    pub timestamp: String,
+    /// Bars seen so far, this one included.
+    pub bar: usize,
+    /// Units held; negative when short.
+    pub position: f64,
+    pub price: f64,
+    /// Market value of the position over equity; negative when short.
+    pub exposure: f64,
+    /// Marked-to-market account value.
+    pub equity: f64,
+    /// Equity less the initial equity.
+    pub pnl: f64,
+    /// Annualized Sharpe ratio of the bar returns in the rolling window (0
+    /// until two returns are in, or without variation).
+    pub rolling_sharpe: f64,
+    /// Fractional drawdown of the equity from its running peak.
+    pub drawdown: f64,
+    /// Drawdown the strategy is not expected to exceed, e.g. a bootstrap
+    /// quantile from the `drawdown` tool.
+    pub drawdown_bound: Option<f64>,
+    /// Whether the drawdown is past the bound.
+    pub bound_breached: bool,
+}
+
+/// Marks a position to market bar by bar and tracks the risk figures of
+/// `RiskSnapshot`.
+#[derive(Debug, Clone)]
+pub struct RiskMonitor {
+    initial_equity: f64,
+    window: usize,
+    drawdown_bound: Option<f64>,
+    equity: f64,
+    peak: f64,
+    last_price: Option<f64>,
+    position: f64,
+    returns: VecDeque<f64>,
+    bar: usize,
+}
+
+impl RiskMonitor {
+    /// # Arguments
+    /// * `initial_equity` - Account value before the first bar
+    /// * `window` - Bars in the rolling Sharpe ratio
+    /// * `drawdown_bound` - Projected drawdown bound (fraction), if any
+    pub fn new(initial_equity: f64, window: usize, drawdown_bound: Option<f64>) -> Result<Self, String> {
+        if initial_equity <= 0.0 {
+            return Err("Initial equity must be positive".to_string());
+        }
+        if window < 2 {
+            return Err("Rolling window must be at least 2 bars".to_string());
+        }
+        Ok(RiskMonitor {
+            initial_equity,
+            window,
+            drawdown_bound,
+            equity: initial_equity,
+            peak: initial_equity,
+            last_price: None,
+            position: 0.0,
+            returns: VecDeque::with_capacity(window),
+            bar: 0,
+        })
+    }
+
+    /// Take a new bar: the position held since the previous bar earns the
+    /// price change, then `position` is held from this bar on.
+    pub fn update(&mut self, timestamp: &str, price: f64, position: f64) -> RiskSnapshot {
+        if let Some(last) = self.last_price {
+            let before = self.equity;
+            self.equity += self.position * (price - last);
+            if self.returns.len() == self.window {
+                self.returns.pop_front();
+            }
+            self.returns.push_back(if before > 0.0 { self.equity / before - 1.0 } else { 0.0 });
+        }
+        self.last_price = Some(price);
+        self.position = position;
+        self.peak = self.peak.max(self.equity);
+        self.bar += 1;
+        self.snapshot(timestamp)
+    }
+
+    fn snapshot(&self, timestamp: &str) -> RiskSnapshot {
+        let price = self.last_price.unwrap_or(0.0);
+        let drawdown = if self.peak > 0.0 { (self.peak - self.equity) / self.peak } else { 0.0 };
+        RiskSnapshot {
+            timestamp: timestamp.to_string(),
+            bar: self.bar,
+            position: self.position,
+            price,
+            exposure: if self.equity > 0.0 { self.position * price / self.equity } else { 0.0 },
+            equity: self.equity,
+            pnl: self.equity - self.initial_equity,
+            rolling_sharpe: sharpe(&self.returns),
+            drawdown,
+            drawdown_bound: self.drawdown_bound,
+            bound_breached: self.drawdown_bound.is_some_and(|bound| drawdown > bound),
+        }
+    }
+}
+
+fn sharpe(returns: &VecDeque<f64>) -> f64 {
+    let n = returns.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / n as f64;
+    let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    if var > 0.0 { mean / var.sqrt() * BARS_PER_YEAR.sqrt() } else { 0.0 }
+}
+
+/// Where snapshots go.
+#[derive(Debug)]
+pub enum SnapshotSink {
+    /// Latest snapshot only, replaced atomically, for dashboards that poll a
+    /// file.
+    File(PathBuf),
+    /// Every snapshot, one JSON object per line, appended.
+    Lines(PathBuf),
+    /// Every snapshot, one JSON object per line, sent over TCP.
+    Tcp(TcpStream),
+}
+
+impl SnapshotSink {
+    /// Sink from a specification: `tcp://host:port`, a path ending in
+    /// `.jsonl` (appended lines) or any other path (latest snapshot).
+    pub fn open(spec: &str) -> io::Result<Self> {
+        match spec.strip_prefix("tcp://") {
+            Some(addr) => Ok(SnapshotSink::Tcp(TcpStream::connect(addr)?)),
+            None if Path::new(spec).extension().is_some_and(|e| e == "jsonl") => {
+                Ok(SnapshotSink::Lines(PathBuf::from(spec)))
+            }
+            None => Ok(SnapshotSink::File(PathBuf::from(spec))),
+        }
+    }
+
+    pub fn write(&mut self, snapshot: &RiskSnapshot) -> io::Result<()> {
+        let json = serde_json::to_string(snapshot)?;
+        match self {
+            SnapshotSink::File(path) => {
+                let tmp = path.with_extension("tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, path)
+            }
+            SnapshotSink::Lines(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", json)
+            }
+            SnapshotSink::Tcp(stream) => writeln!(stream, "{}", json),
+        }
+    }
+}
+
+/// Sends every `every`-th snapshot of a live loop to a sink.
+///
+/// The loop calls `RiskMonitor::update` on each bar and passes the snapshot
+/// to `emit`.
+#[derive(Debug)]
+pub struct SnapshotEmitter {
+    sink: SnapshotSink,
+    every: usize,
+    seen: usize,
+}
+
+impl SnapshotEmitter {
+    /// Emitter writing one snapshot in `every` (at least 1).
+    pub fn new(sink: SnapshotSink, every: usize) -> Self {
+        SnapshotEmitter { sink, every: every.max(1), seen: 0 }
+    }
+
+    /// Write the snapshot if its turn has come; returns whether it was
+    /// written.
+    pub fn emit(&mut self, snapshot: &RiskSnapshot) -> io::Result<bool> {
+        self.seen += 1;
+        if !(self.seen - 1).is_multiple_of(self.every) {
+            return Ok(false);
+        }
+        self.sink.write(snapshot)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_tracks_pnl_and_drawdown() {
+        let mut monitor = RiskMonitor::new(1000.0, 3, Some(0.05)).unwrap();
+        let first = monitor.update("d1", 100.0, 10.0);
+        assert_eq!((first.pnl, first.exposure), (0.0, 1.0));
+
+        let up = monitor.update("d2", 110.0, 10.0);
+        assert_eq!(up.pnl, 100.0);
+        assert_eq!(up.drawdown, 0.0);
+
+        // 1100 down to 1000 after the drop
+        let down = monitor.update("d3", 100.0, -5.0);
+        assert!((down.drawdown - 100.0 / 1100.0).abs() < 1e-12);
+        assert!(down.bound_breached);
+        assert!((down.exposure + 0.5).abs() < 1e-12);
+        assert!(down.rolling_sharpe.abs() > 0.0);
+
+        // Short profits from the fall
+        let short = monitor.update("d4", 90.0, 0.0);
+        assert_eq!(short.equity, 1050.0);
+        assert_eq!(short.bar, 4);
+
+        assert!(RiskMonitor::new(0.0, 3, None).is_err());
+        assert!(RiskMonitor::new(1.0, 1, None).is_err());
+    }
+
+    #[test]
+    fn test_emitter_sinks() {
+        let dir = std::env::temp_dir().join(format!("monitor_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut monitor = RiskMonitor::new(1000.0, 5, None).unwrap();
+
+        let latest = dir.join("risk.json");
+        let mut emitter = SnapshotEmitter::new(SnapshotSink::open(latest.to_str().unwrap()).unwrap(), 2);
+        let lines = dir.join("risk.jsonl");
+        let mut all = SnapshotEmitter::new(SnapshotSink::open(lines.to_str().unwrap()).unwrap(), 1);
+        for (i, price) in [100.0, 101.0, 99.0].into_iter().enumerate() {
+            let snapshot = monitor.update(&format!("t{}", i), price, 1.0);
+            assert_eq!(emitter.emit(&snapshot).unwrap(), i % 2 == 0);
+            all.emit(&snapshot).unwrap();
+        }
+
+        let last: RiskSnapshot = serde_json::from_str(&std::fs::read_to_string(&latest).unwrap()).unwrap();
+        assert_eq!(last.timestamp, "t2");
+        assert_eq!(std::fs::read_to_string(&lines).unwrap().lines().count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::models::TradeLog;
+
+/// Bootstrap resamples of the trades behind each confidence interval.
+pub const BOOTSTRAP_REPS: usize = 1000;
+
+/// Seed of the bootstrap, so the report is the same on every run.
+pub const BOOTSTRAP_SEED: u64 = 0x5eed;
+
+/// Which trades a counterfactual leaves out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Removal {
+    /// The k trades with the highest returns, as if they were missed.
+    MissBest,
+    /// The k trades with the lowest returns, as if they were skipped.
+    SkipWorst,
+}
+
+impl Removal {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Removal::MissBest => "miss best",
+            Removal::SkipWorst => "skip worst",
+        }
+    }
+}
+
+/// Compounded return of the trades left after a removal, with its bootstrap
+/// confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OutlierCase {
+    pub removal: Removal,
+    pub k: usize,
+    /// Compounded return of the remaining trades, in percent.
+    pub return_pct: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+/// How much the performance of a backtest depends on its outlying trades.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutlierReport {
+    pub num_trades: usize,
+    /// Compounded return of all trades, in percent.
+    pub return_pct: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+    /// Two-sided confidence of the intervals, e.g. 0.9.
+    pub confidence: f64,
+    pub cases: Vec<OutlierCase>,
+}
+
+/// Compounded return in percent of trade returns in percent, sorted
+/// ascending, without the lowest `skip_low` and the highest `skip_high`.
+fn compounded(sorted: &[f64], skip_low: usize, skip_high: usize) -> f64 {
+    let kept = &sorted[skip_low..sorted.len() - skip_high];
+    100.0 * (kept.iter().map(|r| 1.0 + r / 100.0).product::<f64>() - 1.0)
+}
+
+/// Value at fraction `q` of sorted values, by linear interpolation.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+/// Recompute the compounded return of `trades` without their best and without
+/// their worst `k` trades, for each `k` of `ks` below the number of trades.
+///
+/// The intervals are percentile bootstraps at `confidence`: the trades are
+/// resampled with replacement `reps` times from a generator seeded with
+/// `seed`, and each resample has its own best and worst trades removed.
+pub fn outlier_analysis(trades: &[TradeLog], ks: &[usize], confidence: f64, reps: usize, seed: u64) -> OutlierReport {
+    let mut returns: Vec<f64> = trades.iter().map(|t| t.return_pct).collect();
+    returns.sort_by(f64::total_cmp);
+    let n = returns.len();
+
+    let mut ks: Vec<usize> = ks.iter().copied().filter(|&k| k > 0 && k < n).collect();
+    ks.sort_unstable();
+    ks.dedup();
+    let removals: Vec<(Removal, usize)> = [Removal::MissBest, Removal::SkipWorst]
+        .iter()
+        .flat_map(|&r| ks.iter().map(move |&k| (r, k)))
+        .collect();
+    let value = |sorted: &[f64], removal: Option<(Removal, usize)>| match removal {
+        None => compounded(sorted, 0, 0),
+        Some((Removal::MissBest, k)) => compounded(sorted, 0, k),
+        Some((Removal::SkipWorst, k)) => compounded(sorted, k, 0),
+    };
+
+    // One bootstrap distribution for all trades, then one per removal
+    let mut samples: Vec<Vec<f64>> = vec![Vec::with_capacity(reps); removals.len() + 1];
+    if n > 1 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut resample = vec![0.0; n];
+        for _ in 0..reps {
+            for r in resample.iter_mut() {
+                *r = returns[rng.gen_range(0..n)];
+            }
+            resample.sort_by(f64::total_cmp);
+            samples[0].push(value(&resample, None));
+            for (i, &removal) in removals.iter().enumerate() {
+                samples[i + 1].push(value(&resample, Some(removal)));
+            }
+        }
+    }
+    let alpha = (1.0 - confidence) / 2.0;
+    let interval = |sample: &mut Vec<f64>, point: f64| {
+        if sample.is_empty() {
+            return (point, point);
+        }
+        sample.sort_by(f64::total_cmp);
+        (quantile(sample, alpha), quantile(sample, 1.0 - alpha))
+    };
+
+    let return_pct = value(&returns, None);
+    let (ci_low, ci_high) = interval(&mut samples[0], return_pct);
+    let cases = removals
+        .iter()
+        .enumerate()
+        .map(|(i, &(removal, k))| {
+            let return_pct = value(&returns, Some((removal, k)));
+            let (ci_low, ci_high) = interval(&mut samples[i + 1], return_pct);
+            OutlierCase { removal, k, return_pct, ci_low, ci_high }
+        })
+        .collect();
+
+    OutlierReport { num_trades: n, return_pct, ci_low, ci_high, confidence, cases }
+}
+
+impl fmt::Display for OutlierReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>10}   {:.0}% CI ({} trades)",
+            "Trades",
+            "Return",
+            100.0 * self.confidence,
+            self.num_trades
+        )?;
+        writeln!(f, "{:<16} {:>9.2}%   [{:.2}%, {:.2}%]", "all", self.return_pct, self.ci_low, self.ci_high)?;
+        for case in &self.cases {
+            writeln!(
+                f,
+                "{:<16} {:>9.2}%   [{:.2}%, {:.2}%]",
+                format!("{} {}", case.removal.label(), case.k),
+                case.return_pct,
+                case.ci_low,
+                case.ci_high
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stops::ExitReason;
+
+    fn trade(return_pct: f64) -> TradeLog {
+        TradeLog {
+            entry_index: 0,
+            entry_price: 100.0,
+            exit_index: 1,
+            exit_price: 100.0 + return_pct,
+            trade_type: "LONG".to_string(),
+            pnl: return_pct,
+            return_pct,
+            slippage: 0.0,
+            exit_reason: ExitReason::Signal,
+        }
+    }
+
+    #[test]
+    fn test_outlier_analysis() {
+        // One large winner carries an otherwise losing record
+        let trades: Vec<TradeLog> = [50.0, -2.0, 1.0, -1.0, 10.0, -5.0].iter().map(|&r| trade(r)).collect();
+        let report = outlier_analysis(&trades, &[1, 2, 6, 1], 0.9, 500, 7);
+        assert_eq!(report.num_trades, 6);
+        assert!((report.return_pct - 100.0 * (1.5 * 0.98 * 1.01 * 0.99 * 1.1 * 0.95 - 1.0)).abs() < 1e-9);
+        // k of 6 leaves no trade and is dropped; the repeated 1 once
+        assert_eq!(report.cases.len(), 4);
+
+        let miss_best = report.cases[0];
+        assert_eq!((miss_best.removal, miss_best.k), (Removal::MissBest, 1));
+        assert!((miss_best.return_pct - 100.0 * (0.98 * 1.01 * 0.99 * 1.1 * 0.95 - 1.0)).abs() < 1e-9);
+        let skip_worst = report.cases[3];
+        assert_eq!((skip_worst.removal, skip_worst.k), (Removal::SkipWorst, 2));
+        assert!((skip_worst.return_pct - 100.0 * (1.5 * 1.01 * 0.99 * 1.1 - 1.0)).abs() < 1e-9);
+
+        for case in &report.cases {
+            assert!(case.ci_low <= case.ci_high);
+        }
+        assert!(report.ci_low < report.return_pct && report.return_pct < report.ci_high);
+        // Seeded: the same report every run
+        assert_eq!(outlier_analysis(&trades, &[1, 2], 0.9, 500, 7).cases, report.cases);
+
+        let empty = outlier_analysis(&[], &[1], 0.9, 500, 7);
+        assert_eq!((empty.return_pct, empty.cases.len()), (0.0, 0));
+        assert!(report.to_string().contains("miss best 1"));
+    }
+}
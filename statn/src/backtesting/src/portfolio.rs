@@ -0,0 +1,358 @@
+//! Allocation of capital across several optimized systems.
+//!
+//! Each system (a strategy on a market) contributes a series of out-of-sample
+//! returns per bar, aligned on the same bars. The weights are estimated from
+//! the covariance of the returns over a first window and the combined book,
+//! rebalanced to the weights every bar, is backtested over the rest.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::risk::{risk_metrics, RiskMetrics, BARS_PER_YEAR};
+
+/// Risk aversion of `Allocation::MeanVariance` when not given
+pub const DEFAULT_RISK_AVERSION: f64 = 10.0;
+
+/// How capital is split across the systems. Weights are long-only and sum
+/// to one; a system without return variance gets no weight.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Allocation {
+    /// The same weight for every system
+    Equal,
+    /// Every system contributes the same share of the book's variance
+    /// (risk parity), so correlated systems share one risk budget
+    #[default]
+    EqualRisk,
+    /// Maximize mean return less `risk_aversion / 2` times the variance of
+    /// the book, per bar
+    MeanVariance { risk_aversion: f64 },
+}
+
+impl FromStr for Allocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "equal" => Ok(Allocation::Equal),
+            "equal-risk" | "equal_risk" | "risk-parity" => Ok(Allocation::EqualRisk),
+            "mean-variance" | "mean_variance" => Ok(Allocation::MeanVariance { risk_aversion: DEFAULT_RISK_AVERSION }),
+            _ => Err(format!("Unknown allocation '{}' (expected equal, equal-risk or mean-variance)", s)),
+        }
+    }
+}
+
+/// Sample covariance matrix of return series of equal length
+pub fn covariance(returns: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, String> {
+    let n = returns.first().map_or(0, Vec::len);
+    if n < 2 || returns.iter().any(|r| r.len() != n) {
+        return Err("Covariance needs return series of the same length, at least 2".to_string());
+    }
+    let means: Vec<f64> = returns.iter().map(|r| r.iter().sum::<f64>() / n as f64).collect();
+    let cov = |a: usize, b: usize| {
+        (0..n).map(|t| (returns[a][t] - means[a]) * (returns[b][t] - means[b])).sum::<f64>() / (n - 1) as f64
+    };
+    Ok((0..returns.len()).map(|a| (0..returns.len()).map(|b| cov(a, b)).collect()).collect())
+}
+
+/// Correlation matrix of a covariance matrix; a series without variance has
+/// correlation 0 with every other series and 1 with itself
+pub fn correlation(cov: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let sd: Vec<f64> = (0..cov.len()).map(|i| cov[i][i].max(0.0).sqrt()).collect();
+    (0..cov.len())
+        .map(|a| {
+            (0..cov.len())
+                .map(|b| match (a == b, sd[a] * sd[b] > 0.0) {
+                    (true, _) => 1.0,
+                    (false, true) => cov[a][b] / (sd[a] * sd[b]),
+                    (false, false) => 0.0,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Weights of the systems, estimated from their returns
+pub fn allocate(returns: &[Vec<f64>], allocation: Allocation) -> Result<Vec<f64>, String> {
+    let cov = covariance(returns)?;
+    let active: Vec<usize> = (0..cov.len()).filter(|&i| cov[i][i] > 0.0).collect();
+    if active.is_empty() {
+        return Err("No system has any return variance".to_string());
+    }
+    let sub: Vec<Vec<f64>> = active.iter().map(|&a| active.iter().map(|&b| cov[a][b]).collect()).collect();
+
+    let sub_weights = match allocation {
+        Allocation::Equal => vec![1.0 / active.len() as f64; active.len()],
+        Allocation::EqualRisk => equal_risk(&sub),
+        Allocation::MeanVariance { risk_aversion } => {
+            if risk_aversion.is_nan() || risk_aversion <= 0.0 {
+                return Err("Risk aversion must be positive".to_string());
+            }
+            let n = returns[0].len() as f64;
+            let means: Vec<f64> = active.iter().map(|&i| returns[i].iter().sum::<f64>() / n).collect();
+            mean_variance(&means, &sub, risk_aversion)
+        }
+    };
+
+    let mut weights = vec![0.0; returns.len()];
+    for (&i, w) in active.iter().zip(sub_weights) {
+        weights[i] = w;
+    }
+    Ok(weights)
+}
+
+/// Risk parity weights by cyclical coordinate descent on
+/// `y'Σy / 2 - Σ ln(y_i) / n` (Griveau-Billion, Richard and Roncalli),
+/// normalized to sum to one
+fn equal_risk(cov: &[Vec<f64>]) -> Vec<f64> {
+    let n = cov.len();
+    let budget = 1.0 / n as f64;
+    let mut y: Vec<f64> = (0..n).map(|i| 1.0 / cov[i][i].sqrt()).collect();
+    for _ in 0..1000 {
+        let mut change = 0.0_f64;
+        for i in 0..n {
+            let others: f64 = (0..n).filter(|&j| j != i).map(|j| cov[i][j] * y[j]).sum();
+            let yi = (-others + (others * others + 4.0 * cov[i][i] * budget).sqrt()) / (2.0 * cov[i][i]);
+            change = change.max((yi - y[i]).abs() / yi);
+            y[i] = yi;
+        }
+        if change < 1e-12 {
+            break;
+        }
+    }
+    let total: f64 = y.iter().sum();
+    y.iter().map(|v| v / total).collect()
+}
+
+/// Long-only, fully invested mean-variance weights by projected gradient
+/// ascent on the simplex
+fn mean_variance(means: &[f64], cov: &[Vec<f64>], risk_aversion: f64) -> Vec<f64> {
+    let n = means.len();
+    // Step size from a bound on the largest eigenvalue of the Hessian
+    let lipschitz = risk_aversion * cov.iter().map(|row| row.iter().map(|c| c.abs()).sum::<f64>()).fold(0.0, f64::max);
+    let step = 1.0 / lipschitz;
+    let mut w = vec![1.0 / n as f64; n];
+    for _ in 0..100_000 {
+        let target: Vec<f64> = (0..n)
+            .map(|i| w[i] + step * (means[i] - risk_aversion * (0..n).map(|j| cov[i][j] * w[j]).sum::<f64>()))
+            .collect();
+        let next = project_to_simplex(&target);
+        let change = next.iter().zip(&w).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+        w = next;
+        if change < 1e-13 {
+            break;
+        }
+    }
+    w
+}
+
+/// Euclidean projection onto the weights that are non-negative and sum to one
+fn project_to_simplex(v: &[f64]) -> Vec<f64> {
+    let mut sorted = v.to_vec();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let mut cumulative = 0.0;
+    let mut shift = 0.0;
+    for (k, &u) in sorted.iter().enumerate() {
+        cumulative += u;
+        let t = (cumulative - 1.0) / (k + 1) as f64;
+        if u - t > 0.0 {
+            shift = t;
+        }
+    }
+    v.iter().map(|x| (x - shift).max(0.0)).collect()
+}
+
+/// Performance of a return series over the backtest window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookStats {
+    /// Compounded return over the window
+    pub total_return: f64,
+    /// Annualized mean return over standard deviation
+    pub sharpe_ratio: f64,
+    /// Largest fractional drop of the compounded equity from its peak
+    pub max_drawdown: f64,
+    pub risk: RiskMetrics,
+}
+
+impl BookStats {
+    /// Statistics of simple returns per bar
+    pub fn of_returns(returns: &[f64]) -> Self {
+        if returns.is_empty() {
+            return BookStats::default();
+        }
+        let mut equity = Vec::with_capacity(returns.len() + 1);
+        equity.push(1.0);
+        for r in returns {
+            equity.push(equity[equity.len() - 1] * (1.0 + r));
+        }
+        let mut peak = f64::NEG_INFINITY;
+        let max_drawdown = equity.iter().fold(0.0_f64, |worst, &e| {
+            peak = peak.max(e);
+            worst.max((peak - e) / peak)
+        });
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let sd = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+        BookStats {
+            total_return: equity[equity.len() - 1] - 1.0,
+            sharpe_ratio: if sd > 0.0 { mean / sd * BARS_PER_YEAR.sqrt() } else { 0.0 },
+            max_drawdown,
+            risk: risk_metrics(&equity),
+        }
+    }
+}
+
+/// Weights of the systems and the backtest of the combined book
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookReport {
+    pub names: Vec<String>,
+    pub allocation: Allocation,
+    /// Bars the weights and correlations were estimated on
+    pub estimate_bars: usize,
+    pub weights: Vec<f64>,
+    /// Correlation of the systems' returns over the estimation window
+    pub correlation: Vec<Vec<f64>>,
+    /// Return of the book at each bar of the backtest window
+    pub returns: Vec<f64>,
+    /// Each system alone over the backtest window
+    pub systems: Vec<BookStats>,
+    pub book: BookStats,
+}
+
+/// Estimate weights on the first `estimate_bars` returns of every system
+/// and backtest the book, rebalanced to the weights every bar, on the rest.
+///
+/// # Arguments
+/// * `names` - Name of each system
+/// * `returns` - Simple returns per bar of each system, aligned on the same bars
+pub fn backtest_book(
+    names: &[String],
+    returns: &[Vec<f64>],
+    allocation: Allocation,
+    estimate_bars: usize,
+) -> Result<BookReport, String> {
+    if returns.is_empty() || names.len() != returns.len() {
+        return Err("A book needs one name per return series, at least one".to_string());
+    }
+    let n = returns[0].len();
+    if returns.iter().any(|r| r.len() != n) {
+        return Err("Return series of a book must be aligned on the same bars".to_string());
+    }
+    if estimate_bars < 2 || estimate_bars >= n {
+        return Err(format!("Estimation window of {} bars leaves nothing to backtest in {} bars", estimate_bars, n));
+    }
+
+    let estimation: Vec<Vec<f64>> = returns.iter().map(|r| r[..estimate_bars].to_vec()).collect();
+    let weights = allocate(&estimation, allocation)?;
+    let book: Vec<f64> = (estimate_bars..n).map(|t| weights.iter().zip(returns).map(|(w, r)| w * r[t]).sum()).collect();
+
+    Ok(BookReport {
+        names: names.to_vec(),
+        allocation,
+        estimate_bars,
+        weights,
+        correlation: correlation(&covariance(&estimation)?),
+        systems: returns.iter().map(|r| BookStats::of_returns(&r[estimate_bars..])).collect(),
+        book: BookStats::of_returns(&book),
+        returns: book,
+    })
+}
+
+impl fmt::Display for BookReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Allocation: {:?}, estimated on {} bars, backtested on {}", self.allocation, self.estimate_bars, self.returns.len())?;
+        writeln!(f, "\n{:<24} {:>8} {:>10} {:>8} {:>8}", "System", "Weight", "Return", "Sharpe", "MaxDD")?;
+        for ((name, w), s) in self.names.iter().zip(&self.weights).zip(&self.systems) {
+            writeln!(f, "{:<24} {:>8.4} {:>9.2}% {:>8.3} {:>7.2}%", name, w, 100.0 * s.total_return, s.sharpe_ratio, 100.0 * s.max_drawdown)?;
+        }
+        let b = &self.book;
+        writeln!(f, "{:<24} {:>8.4} {:>9.2}% {:>8.3} {:>7.2}%", "Book", 1.0, 100.0 * b.total_return, b.sharpe_ratio, 100.0 * b.max_drawdown)?;
+
+        writeln!(f, "\nCorrelation:")?;
+        for (name, row) in self.names.iter().zip(&self.correlation) {
+            write!(f, "{:<24}", name)?;
+            for c in row {
+                write!(f, " {:>6.3}", c)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("s{}", i)).collect()
+    }
+
+    #[test]
+    fn test_equal_risk_contributions() {
+        // Two correlated systems and one independent, more volatile one
+        let base: Vec<f64> = (0..200).map(|t| ((t * 37 % 101) as f64 / 101.0 - 0.5) * 0.01).collect();
+        let other: Vec<f64> = (0..200).map(|t| ((t * 53 % 97) as f64 / 97.0 - 0.5) * 0.03).collect();
+        let returns = vec![base.clone(), base.iter().enumerate().map(|(t, r)| r + other[(t + 7) % 200] * 0.1).collect(), other];
+
+        let weights = allocate(&returns, Allocation::EqualRisk).unwrap();
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        let cov = covariance(&returns).unwrap();
+        let contributions: Vec<f64> =
+            (0..3).map(|i| weights[i] * (0..3).map(|j| cov[i][j] * weights[j]).sum::<f64>()).collect();
+        for c in &contributions {
+            assert!((c / contributions[0] - 1.0).abs() < 1e-8, "{:?}", contributions);
+        }
+        // Correlation shares the risk budget: each of the pair gets less than
+        // inverse volatility alone would give it
+        let inverse: Vec<f64> = (0..3).map(|i| 1.0 / cov[i][i].sqrt()).collect();
+        let total: f64 = inverse.iter().sum();
+        assert!(weights[0] < inverse[0] / total);
+
+        let flat = vec![vec![0.0; 200], returns[0].clone()];
+        assert_eq!(allocate(&flat, Allocation::EqualRisk).unwrap(), vec![0.0, 1.0]);
+        assert_eq!("risk-parity".parse::<Allocation>(), Ok(Allocation::EqualRisk));
+    }
+
+    #[test]
+    fn test_mean_variance_weights() {
+        // Uncorrelated: w_i proportional to mean / variance when none is clamped
+        let a: Vec<f64> = (0..100).map(|t| 0.002 + if t % 2 == 0 { 0.01 } else { -0.01 }).collect();
+        let b: Vec<f64> = (0..100).map(|t| 0.001 + if t % 4 < 2 { 0.02 } else { -0.02 }).collect();
+        let returns = vec![a, b];
+        let cov = covariance(&returns).unwrap();
+        assert!(cov[0][1].abs() < 1e-15);
+
+        let weights = allocate(&returns, Allocation::MeanVariance { risk_aversion: 20.0 }).unwrap();
+        let (va, vb) = (cov[0][0], cov[1][1]);
+        // Stationarity on the simplex: equal marginal utility
+        let marginal = |i: usize, m: f64, v: f64| m - 20.0 * v * weights[i];
+        assert!((marginal(0, 0.002, va) - marginal(1, 0.001, vb)).abs() < 1e-9, "{:?}", weights);
+        assert!(weights[0] > weights[1]);
+
+        // A system that loses money gets nothing
+        let losing = vec![returns[0].clone(), returns[1].iter().map(|r| r - 0.5).collect()];
+        let weights = allocate(&losing, Allocation::MeanVariance { risk_aversion: 20.0 }).unwrap();
+        assert_eq!(weights, vec![1.0, 0.0]);
+        assert!(allocate(&losing, Allocation::MeanVariance { risk_aversion: 0.0 }).is_err());
+    }
+
+    #[test]
+    fn test_backtest_book() {
+        // Two systems that offset each other: the book is flat
+        let a: Vec<f64> = (0..50).map(|t| if t % 2 == 0 { 0.01 } else { -0.01 }).collect();
+        let b: Vec<f64> = a.iter().map(|r| -r).collect();
+        let report = backtest_book(&names(2), &[a.clone(), b], Allocation::Equal, 20).unwrap();
+        assert_eq!(report.weights, vec![0.5, 0.5]);
+        assert!((report.correlation[0][1] + 1.0).abs() < 1e-12);
+        assert_eq!(report.returns.len(), 30);
+        assert!(report.returns.iter().all(|r| r.abs() < 1e-15));
+        assert!(report.book.max_drawdown < 1e-12);
+        assert!(report.systems[0].max_drawdown > 0.0);
+        assert!(report.to_string().contains("Book"));
+
+        assert!(backtest_book(&names(1), std::slice::from_ref(&a), Allocation::Equal, 50).is_err());
+        assert!(backtest_book(&names(2), &[a.clone(), a[1..].to_vec()], Allocation::Equal, 10).is_err());
+    }
+}
@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+
+use crate::journal::{Fill, Side};
+use crate::models::SignalResult;
+
+/// An order the signals called for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelOrder {
+    /// Bar of the signal.
+    pub bar: usize,
+    pub side: Side,
+    /// Price of the bar (the model fills at it).
+    pub price: f64,
+    /// Whether the order reverses the position rather than opening one from
+    /// flat.
+    pub reversal: bool,
+}
+
+/// Fills of one side on one bar, combined into one order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Execution {
+    pub bar: usize,
+    pub side: Side,
+    /// Timestamp of the first fill.
+    pub timestamp: String,
+    pub qty: f64,
+    /// Average fill price, weighted by quantity.
+    pub price: f64,
+    pub fee: f64,
+}
+
+/// A model order and the execution that implemented it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchedOrder {
+    pub model: ModelOrder,
+    pub execution: Execution,
+    /// Bars from the signal to the execution; negative if executed early.
+    pub lag: i64,
+    /// Execution price against the model price, in basis points, positive
+    /// when worse for the trader.
+    pub slippage_bps: f64,
+    /// `qty` times the adverse price difference, in the account currency.
+    pub slippage_cost: f64,
+}
+
+/// Divergence between the signals of a system and the trades actually made.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Reconciliation {
+    pub matched: Vec<MatchedOrder>,
+    /// Signals that were never executed.
+    pub missed: Vec<ModelOrder>,
+    /// Executions without a signal within the allowed lag.
+    pub unexpected: Vec<Execution>,
+    /// Fills dated before the first bar, left out.
+    pub skipped_fills: usize,
+    /// Fees of all the executions.
+    pub total_fees: f64,
+}
+
+/// Orders implied by the signals, with the position logic of
+/// `backtest_signals`: a signal opens a position from flat or reverses an
+/// opposite one, and HOLD keeps it. The backtest's close of a position still
+/// open at the end is not a signal and gives no order.
+pub fn model_orders(result: &SignalResult) -> Vec<ModelOrder> {
+    let mut position = 0;
+    let mut orders = Vec::new();
+    for (bar, (&price, &signal)) in result.prices.iter().zip(&result.signals).enumerate() {
+        if signal == 0 || signal == position {
+            continue;
+        }
+        orders.push(ModelOrder {
+            bar,
+            side: if signal > 0 { Side::Buy } else { Side::Sell },
+            price: price.exp(), // Log prices, as in the backtest
+            reversal: position != 0,
+        });
+        position = signal;
+    }
+    orders
+}
+
+/// Digits of a timestamp, so "2024-01-02 09:30" and "20240102" compare.
+fn time_key(timestamp: &str) -> String {
+    timestamp.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+/// Combine the fills into one execution per bar and side.
+///
+/// A fill belongs to the last bar whose time is not after its own, comparing
+/// the digits of both timestamps up to the length of the bar's (a daily bar
+/// "20240102" holds every fill of that day).
+pub fn executions(fills: &[Fill], bar_times: &[String]) -> Result<(Vec<Execution>, usize), String> {
+    let keys: Vec<String> = bar_times.iter().map(|t| time_key(t)).collect();
+    if keys.is_empty() || keys.iter().any(|k| k.is_empty()) {
+        return Err("Every bar needs a date or time".to_string());
+    }
+    if keys.windows(2).any(|w| w[1] < w[0]) {
+        return Err("Bar times must be in increasing order".to_string());
+    }
+
+    let mut executions: Vec<Execution> = Vec::new();
+    let mut skipped = 0;
+    for fill in fills {
+        let key = time_key(&fill.timestamp);
+        if key.is_empty() {
+            return Err(format!("Fill timestamp '{}' has no date", fill.timestamp));
+        }
+        let n_before = keys.partition_point(|k| k.as_str() <= &key[..k.len().min(key.len())]);
+        if n_before == 0 {
+            skipped += 1;
+            continue;
+        }
+        let bar = n_before - 1;
+
+        match executions.iter_mut().find(|e| e.bar == bar && e.side == fill.side) {
+            Some(e) => {
+                e.price = (e.price * e.qty + fill.price * fill.qty) / (e.qty + fill.qty);
+                e.qty += fill.qty;
+                e.fee += fill.fee;
+            }
+            None => executions.push(Execution {
+                bar,
+                side: fill.side,
+                timestamp: fill.timestamp.clone(),
+                qty: fill.qty,
+                price: fill.price,
+                fee: fill.fee,
+            }),
+        }
+    }
+    executions.sort_by_key(|e| e.bar);
+    Ok((executions, skipped))
+}
+
+/// Reconcile real fills with the signals the system generated.
+///
+/// Each model order is matched, in order, with the unmatched execution of the
+/// same side closest in time and at most `max_lag` bars away from it. Only
+/// the signals from `max_lag` bars before the first execution to `max_lag`
+/// bars after the last are reconciled.
+///
+/// # Arguments
+/// * `result` - Signals and (log) prices, as backtested
+/// * `bar_times` - Date or time of each bar of `result`
+/// * `fills` - Executed trades, in order
+/// * `max_lag` - Largest timing difference, in bars, still counted as the
+///   execution of a signal
+pub fn reconcile(
+    result: &SignalResult,
+    bar_times: &[String],
+    fills: &[Fill],
+    max_lag: usize,
+) -> Result<Reconciliation, String> {
+    if bar_times.len() != result.prices.len() {
+        return Err(format!(
+            "{} bar times for {} prices",
+            bar_times.len(),
+            result.prices.len()
+        ));
+    }
+    let (executions, skipped_fills) = executions(fills, bar_times)?;
+    let mut used = vec![false; executions.len()];
+    let mut out = Reconciliation {
+        skipped_fills,
+        total_fees: executions.iter().map(|e| e.fee).sum(),
+        ..Reconciliation::default()
+    };
+
+    // Signals outside the span of the journal say nothing about its trades
+    let span = match (executions.first(), executions.last()) {
+        (Some(first), Some(last)) => first.bar.saturating_sub(max_lag)..=last.bar + max_lag,
+        _ => return Ok(out),
+    };
+
+    for order in model_orders(result).into_iter().filter(|o| span.contains(&o.bar)) {
+        let best = executions
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| !used[*i] && e.side == order.side && e.bar.abs_diff(order.bar) <= max_lag)
+            .min_by_key(|(_, e)| e.bar.abs_diff(order.bar));
+        let Some((i, execution)) = best else {
+            out.missed.push(order);
+            continue;
+        };
+        used[i] = true;
+
+        let sign = if order.side == Side::Buy { 1.0 } else { -1.0 };
+        let adverse = sign * (execution.price - order.price);
+        out.matched.push(MatchedOrder {
+            lag: execution.bar as i64 - order.bar as i64,
+            slippage_bps: adverse / order.price * 1.0e4,
+            slippage_cost: adverse * execution.qty,
+            model: order,
+            execution: execution.clone(),
+        });
+    }
+
+    out.unexpected = executions
+        .into_iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(e, _)| e)
+        .collect();
+    Ok(out)
+}
+
+impl Reconciliation {
+    /// Mean lag in bars of the matched orders (0 if none).
+    pub fn mean_lag(&self) -> f64 {
+        mean(self.matched.iter().map(|m| m.lag as f64))
+    }
+
+    /// Mean slippage in basis points of the matched orders (0 if none).
+    pub fn mean_slippage_bps(&self) -> f64 {
+        mean(self.matched.iter().map(|m| m.slippage_bps))
+    }
+
+    /// Total cost of slippage against the model prices.
+    pub fn slippage_cost(&self) -> f64 {
+        self.matched.iter().fold(0.0, |sum, m| sum + m.slippage_cost)
+    }
+
+    /// Slippage cost plus fees. The opportunity cost of missed trades is not
+    /// included; `missed` lists them.
+    pub fn implementation_shortfall(&self) -> f64 {
+        self.slippage_cost() + self.total_fees
+    }
+
+    /// Text report of the divergence.
+    pub fn report(&self) -> String {
+        let n_model = self.matched.len() + self.missed.len();
+        let n_exec = self.matched.len() + self.unexpected.len();
+        let mut out = String::new();
+        out.push_str("=== RECONCILIATION ===\n");
+        out.push_str(&format!("Model orders:      {}\n", n_model));
+        out.push_str(&format!("Executions:        {}\n", n_exec));
+        out.push_str(&format!("Matched:           {}\n", self.matched.len()));
+        out.push_str(&format!("Missed trades:     {}\n", self.missed.len()));
+        out.push_str(&format!("Unexpected trades: {}\n", self.unexpected.len()));
+        if self.skipped_fills > 0 {
+            out.push_str(&format!("Fills before the first bar (skipped): {}\n", self.skipped_fills));
+        }
+        out.push_str(&format!("Mean timing lag:   {:.2} bars\n", self.mean_lag()));
+        out.push_str(&format!("Mean slippage:     {:.2} bps (positive = worse than model)\n", self.mean_slippage_bps()));
+        out.push_str(&format!("Slippage cost:     ${:.2}\n", self.slippage_cost()));
+        out.push_str(&format!("Fees:              ${:.2}\n", self.total_fees));
+        out.push_str(&format!(
+            "Implementation shortfall: ${:.2} (slippage + fees, missed trades excluded)\n",
+            self.implementation_shortfall()
+        ));
+
+        if !self.matched.is_empty() {
+            out.push_str(&format!(
+                "\n{:<6} {:<5} {:>12} {:>12} {:>5} {:>10} {:>12}\n",
+                "Bar", "Side", "Model", "Executed", "Lag", "Slip bps", "Slip cost"
+            ));
+            for m in &self.matched {
+                out.push_str(&format!(
+                    "{:<6} {:<5} {:>12.4} {:>12.4} {:>5} {:>10.2} {:>12.2}\n",
+                    m.model.bar,
+                    side_name(m.model.side),
+                    m.model.price,
+                    m.execution.price,
+                    m.lag,
+                    m.slippage_bps,
+                    m.slippage_cost
+                ));
+            }
+        }
+        if !self.missed.is_empty() {
+            out.push_str("\nMissed (signal, no execution):\n");
+            for o in &self.missed {
+                out.push_str(&format!("  bar {:<6} {:<5} at {:.4}\n", o.bar, side_name(o.side), o.price));
+            }
+        }
+        if !self.unexpected.is_empty() {
+            out.push_str("\nUnexpected (execution, no signal):\n");
+            for e in &self.unexpected {
+                out.push_str(&format!(
+                    "  bar {:<6} {:<5} {} x {:.4} ({})\n",
+                    e.bar,
+                    side_name(e.side),
+                    e.qty,
+                    e.price,
+                    e.timestamp
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "BUY",
+        Side::Sell => "SELL",
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, n) = values.fold((0.0, 0), |(sum, n), v| (sum + v, n + 1));
+    if n > 0 { sum / n as f64 } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signals(prices: &[f64], signals: &[i32]) -> SignalResult {
+        SignalResult {
+            prices: prices.iter().map(|p| p.ln()).collect(),
+            signals: signals.to_vec(),
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        }
+    }
+
+    fn fill(timestamp: &str, side: Side, qty: f64, price: f64) -> Fill {
+        Fill { timestamp: timestamp.to_string(), side, qty, price, fee: 1.0 }
+    }
+
+    #[test]
+    fn test_model_orders() {
+        let result = signals(&[10.0, 11.0, 12.0, 13.0, 14.0], &[1, 1, 0, -1, 0]);
+        let orders = model_orders(&result);
+        assert_eq!(orders.len(), 2);
+        assert_eq!((orders[0].bar, orders[0].side, orders[0].reversal), (0, Side::Buy, false));
+        assert_eq!((orders[1].bar, orders[1].side, orders[1].reversal), (3, Side::Sell, true));
+        assert!((orders[1].price - 13.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let times: Vec<String> = ["20240102", "20240103", "20240104", "20240105", "20240108"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let result = signals(&[100.0, 101.0, 102.0, 103.0, 104.0], &[1, 0, -1, 0, 1]);
+        let fills = vec![
+            fill("2023-12-29 15:00", Side::Sell, 1.0, 99.0),
+            // Buy signalled on the 2nd, split in two fills a day late
+            fill("2024-01-03 09:30", Side::Buy, 1.0, 101.0),
+            fill("2024-01-03 09:31", Side::Buy, 1.0, 102.0),
+            // Sell signalled on the 4th, filled the same day
+            fill("2024-01-04 16:00", Side::Sell, 2.0, 101.0),
+            // No sell signal near the 5th
+            fill("2024-01-05 10:00", Side::Sell, 1.0, 103.0),
+        ];
+        let rec = reconcile(&result, &times, &fills, 1).unwrap();
+
+        assert_eq!(rec.skipped_fills, 1);
+        assert_eq!(rec.matched.len(), 2);
+        let buy = &rec.matched[0];
+        assert_eq!((buy.lag, buy.execution.qty), (1, 2.0));
+        assert!((buy.execution.price - 101.5).abs() < 1e-9);
+        assert!((buy.slippage_bps - 150.0).abs() < 1e-6);
+        assert!((buy.slippage_cost - 3.0).abs() < 1e-9);
+        // Sold 1 below the model price
+        assert!((rec.matched[1].slippage_cost - 2.0).abs() < 1e-9);
+
+        // The buy signalled on the 8th was never executed
+        assert_eq!(rec.missed.len(), 1);
+        assert_eq!(rec.missed[0].bar, 4);
+        assert_eq!(rec.unexpected.len(), 1);
+        assert_eq!(rec.unexpected[0].bar, 3);
+        assert!((rec.mean_lag() - 0.5).abs() < 1e-12);
+        assert!((rec.implementation_shortfall() - (5.0 + 4.0)).abs() < 1e-9);
+        assert!(rec.report().contains("Matched:           2"));
+
+        assert!(reconcile(&result, &times[..4], &fills, 1).is_err());
+    }
+}
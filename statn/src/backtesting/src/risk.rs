@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// Bars per year used to annualize (daily data).
+pub const BARS_PER_YEAR: f64 = 252.0;
+
+/// Bars in the trailing window of the Calmar ratio (three years).
+pub const CALMAR_BARS: usize = 3 * 252;
+
+/// Risk metrics of a per-bar equity curve.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskMetrics {
+    /// Annualized mean return over downside deviation (returns below 0).
+    pub sortino_ratio: f64,
+    /// Annualized return over max drawdown, both over the last three years.
+    pub calmar_ratio: f64,
+    /// Annualized return over max drawdown, both over the whole curve.
+    pub mar_ratio: f64,
+    /// Root mean square of the percentage drawdown at each bar.
+    pub ulcer_index: f64,
+    /// Mean length in bars of the drawdowns, from a peak to the next new high
+    /// (a drawdown still open at the end counts with its length so far).
+    pub avg_drawdown_duration: f64,
+}
+
+/// Compute the risk metrics of an equity curve with one value per bar.
+///
+/// Ratios whose denominator is zero (no losing bars, no drawdown) are 0, as
+/// for the Sharpe ratio in `TradeStats`.
+pub fn risk_metrics(equity: &[f64]) -> RiskMetrics {
+    if equity.len() < 2 {
+        return RiskMetrics::default();
+    }
+
+    let returns: Vec<f64> = equity
+        .windows(2)
+        .map(|w| if w[0] > 0.0 { w[1] / w[0] - 1.0 } else { 0.0 })
+        .collect();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64).sqrt();
+    let sortino_ratio = if downside > 0.0 {
+        mean / downside * BARS_PER_YEAR.sqrt()
+    } else {
+        0.0
+    };
+
+    let drawdowns = drawdowns(equity);
+    let ulcer_index =
+        (drawdowns.iter().map(|d| (100.0 * d).powi(2)).sum::<f64>() / drawdowns.len() as f64).sqrt();
+
+    let recent = &equity[equity.len().saturating_sub(CALMAR_BARS + 1)..];
+
+    RiskMetrics {
+        sortino_ratio,
+        calmar_ratio: return_over_drawdown(recent),
+        mar_ratio: return_over_drawdown(equity),
+        ulcer_index,
+        avg_drawdown_duration: average_duration(&drawdowns),
+    }
+}
+
+/// Fractional drawdown from the running peak at each bar.
+fn drawdowns(equity: &[f64]) -> Vec<f64> {
+    let mut peak = f64::NEG_INFINITY;
+    equity
+        .iter()
+        .map(|&e| {
+            peak = peak.max(e);
+            if peak > 0.0 { (peak - e) / peak } else { 0.0 }
+        })
+        .collect()
+}
+
+/// Compound annual growth rate over the max drawdown of a curve.
+fn return_over_drawdown(equity: &[f64]) -> f64 {
+    let (first, last) = (equity[0], equity[equity.len() - 1]);
+    let max_drawdown = drawdowns(equity).into_iter().fold(0.0, f64::max);
+    if first <= 0.0 || last <= 0.0 || max_drawdown <= 0.0 {
+        return 0.0;
+    }
+    let years = (equity.len() - 1) as f64 / BARS_PER_YEAR;
+    let cagr = (last / first).powf(1.0 / years) - 1.0;
+    cagr / max_drawdown
+}
+
+fn average_duration(drawdowns: &[f64]) -> f64 {
+    let mut lengths = Vec::new();
+    let mut current = 0usize;
+    for &d in drawdowns {
+        if d > 0.0 {
+            current += 1;
+        } else if current > 0 {
+            lengths.push(current);
+            current = 0;
+        }
+    }
+    if current > 0 {
+        lengths.push(current);
+    }
+    if lengths.is_empty() {
+        0.0
+    } else {
+        lengths.iter().sum::<usize>() as f64 / lengths.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_risk_metrics() {
+        // Two drawdowns: 3 bars (110 -> 99, 104.5, back above) and 1 open bar
+        let equity = [100.0, 110.0, 99.0, 104.5, 121.0, 132.0, 118.8];
+        let m = risk_metrics(&equity);
+
+        assert_eq!(m.avg_drawdown_duration, 1.5);
+
+        let dd = [0.0, 0.0, 0.1, 0.05, 0.0, 0.0, 0.1];
+        let ulcer = (dd.iter().map(|d: &f64| (100.0 * d).powi(2)).sum::<f64>() / 7.0).sqrt();
+        assert!((m.ulcer_index - ulcer).abs() < 1e-9);
+
+        let cagr = (118.8_f64 / 100.0).powf(252.0 / 6.0) - 1.0;
+        assert!((m.mar_ratio - cagr / 0.1).abs() < 1e-6 * m.mar_ratio);
+        // The whole curve is within the Calmar window
+        assert_eq!(m.calmar_ratio, m.mar_ratio);
+
+        let returns: Vec<f64> = equity.windows(2).map(|w| w[1] / w[0] - 1.0).collect();
+        let mean = returns.iter().sum::<f64>() / 6.0;
+        let downside = ((0.1_f64.powi(2) + 0.1_f64.powi(2)) / 6.0).sqrt();
+        assert!((m.sortino_ratio - mean / downside * 252.0_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calmar_uses_recent_years() {
+        // An early crash followed by four years of steady growth
+        let mut equity = vec![100.0, 50.0];
+        for i in 0..4 * 252 {
+            equity.push(50.0 * 1.001_f64.powi(i + 1) * if i % 2 == 0 { 1.0 } else { 0.999 });
+        }
+        let m = risk_metrics(&equity);
+        assert!(m.calmar_ratio > 10.0 * m.mar_ratio);
+    }
+
+    #[test]
+    fn test_flat_curve() {
+        assert_eq!(risk_metrics(&[100.0; 10]), RiskMetrics::default());
+        assert_eq!(risk_metrics(&[100.0]), RiskMetrics::default());
+    }
+}
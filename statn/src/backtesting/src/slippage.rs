@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How far fills land from the signal price, against the trader.
+///
+/// Buys fill above the price and sells below it, at entries and exits alike.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Slippage {
+    /// Fills at the signal price.
+    #[default]
+    None,
+    /// A fixed number of ticks per fill.
+    FixedTicks { ticks: f64, tick_size: f64 },
+    /// A percentage of the price per fill.
+    Percent { pct: f64 },
+    /// Square-root market impact, as a percentage of the price:
+    /// `base_pct + impact_pct * sqrt(order value / traded value of the bar)`.
+    /// Bars without a (positive) volume get `base_pct` alone.
+    VolumeDependent { base_pct: f64, impact_pct: f64 },
+}
+
+impl Slippage {
+    /// Slippage of one fill, in price units.
+    ///
+    /// # Arguments
+    /// * `price` - Signal price
+    /// * `order_value` - Value of the order, in the same currency as the price
+    /// * `volume` - Units traded in the bar, if known
+    pub fn offset(&self, price: f64, order_value: f64, volume: Option<f64>) -> f64 {
+        let offset = match *self {
+            Slippage::None => 0.0,
+            Slippage::FixedTicks { ticks, tick_size } => ticks * tick_size,
+            Slippage::Percent { pct } => price * pct / 100.0,
+            Slippage::VolumeDependent { base_pct, impact_pct } => {
+                let participation = match volume {
+                    Some(volume) if volume > 0.0 => order_value.abs() / (volume * price),
+                    _ => 0.0,
+                };
+                price * (base_pct + impact_pct * participation.sqrt()) / 100.0
+            }
+        };
+        offset.max(0.0)
+    }
+
+    /// Price an order fills at: above `price` for a buy, below for a sell.
+    pub fn fill_price(&self, price: f64, buy: bool, order_value: f64, volume: Option<f64>) -> f64 {
+        let offset = self.offset(price, order_value, volume);
+        if buy {
+            price + offset
+        } else {
+            (price - offset).max(0.0)
+        }
+    }
+}
+
+impl FromStr for Slippage {
+    type Err = String;
+
+    /// Parse `none`, `ticks:<ticks>:<tick_size>`, `pct:<pct>` or
+    /// `volume:<base_pct>:<impact_pct>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let model = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let values = parts
+            .map(|p| p.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("Invalid slippage '{}': {}", s, e))?;
+        if values.iter().any(|&v| v < 0.0) {
+            return Err(format!("Invalid slippage '{}': values must not be negative", s));
+        }
+
+        match (model.as_str(), values.as_slice()) {
+            ("none", []) => Ok(Slippage::None),
+            ("ticks", &[ticks, tick_size]) => Ok(Slippage::FixedTicks { ticks, tick_size }),
+            ("pct" | "percent", &[pct]) => Ok(Slippage::Percent { pct }),
+            ("volume", &[base_pct, impact_pct]) => Ok(Slippage::VolumeDependent { base_pct, impact_pct }),
+            _ => Err(format!(
+                "Invalid slippage '{}' (expected none, ticks:<ticks>:<tick_size>, pct:<pct> or volume:<base_pct>:<impact_pct>)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_prices() {
+        let ticks = Slippage::FixedTicks { ticks: 2.0, tick_size: 0.25 };
+        assert_eq!(ticks.fill_price(100.0, true, 1000.0, None), 100.5);
+        assert_eq!(ticks.fill_price(100.0, false, 1000.0, None), 99.5);
+
+        let pct = Slippage::Percent { pct: 0.1 };
+        assert!((pct.fill_price(200.0, true, 1000.0, None) - 200.2).abs() < 1e-12);
+
+        // Order of 1% of the bar's traded value: 0.05% + 1% * 0.1
+        let volume = Slippage::VolumeDependent { base_pct: 0.05, impact_pct: 1.0 };
+        assert!((volume.offset(100.0, 1000.0, Some(1000.0)) - 0.15).abs() < 1e-12);
+        assert!((volume.offset(100.0, 1000.0, None) - 0.05).abs() < 1e-12);
+        assert_eq!(Slippage::None.fill_price(100.0, true, 1000.0, None), 100.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("none".parse::<Slippage>(), Ok(Slippage::None));
+        assert_eq!(
+            "ticks:1:0.01".parse::<Slippage>(),
+            Ok(Slippage::FixedTicks { ticks: 1.0, tick_size: 0.01 })
+        );
+        assert_eq!("pct:0.05".parse::<Slippage>(), Ok(Slippage::Percent { pct: 0.05 }));
+        assert_eq!(
+            "volume:0.02:0.5".parse::<Slippage>(),
+            Ok(Slippage::VolumeDependent { base_pct: 0.02, impact_pct: 0.5 })
+        );
+        assert!("pct".parse::<Slippage>().is_err());
+        assert!("pct:-1".parse::<Slippage>().is_err());
+        assert!("spread:1".parse::<Slippage>().is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Distance of a protective exit from the entry fill.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum StopLevel {
+    /// A percentage of the entry price.
+    Percent { pct: f64 },
+    /// A multiple of the average true range of the `period` bars up to the
+    /// entry.
+    Atr { period: usize, multiple: f64 },
+}
+
+impl StopLevel {
+    /// Distance from `entry_price`, in price units; `None` for an ATR level
+    /// without enough history at the entry.
+    pub fn distance(&self, entry_price: f64, atr: Option<f64>) -> Option<f64> {
+        match *self {
+            StopLevel::Percent { pct } => Some(entry_price * pct / 100.0),
+            StopLevel::Atr { multiple, .. } => atr.filter(|a| a.is_finite()).map(|a| a * multiple),
+        }
+    }
+}
+
+impl FromStr for StopLevel {
+    type Err = String;
+
+    /// Parse `pct:<pct>` or `atr:<period>:<multiple>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let model = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let values = parts
+            .map(|p| p.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("Invalid stop '{}': {}", s, e))?;
+        if values.iter().any(|&v| v <= 0.0) {
+            return Err(format!("Invalid stop '{}': values must be positive", s));
+        }
+
+        match (model.as_str(), values.as_slice()) {
+            ("pct" | "percent", &[pct]) => Ok(StopLevel::Percent { pct }),
+            ("atr", &[period, multiple]) if period.fract() == 0.0 => {
+                Ok(StopLevel::Atr { period: period as usize, multiple })
+            }
+            _ => Err(format!("Invalid stop '{}' (expected pct:<pct> or atr:<period>:<multiple>)", s)),
+        }
+    }
+}
+
+/// Protective exits of every position, each optional.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Stops {
+    /// Exit when the price moves this far against the position.
+    pub stop_loss: Option<StopLevel>,
+    /// Exit when the price moves this far in favour of the position.
+    pub take_profit: Option<StopLevel>,
+    /// Exit when the price falls back this far from the best price since
+    /// the entry (a percentage of that price, or an ATR multiple fixed at
+    /// the entry).
+    #[serde(default)]
+    pub trailing: Option<StopLevel>,
+}
+
+impl Stops {
+    /// Whether any exit is set
+    pub fn is_active(&self) -> bool {
+        self.stop_loss.is_some() || self.take_profit.is_some() || self.trailing.is_some()
+    }
+}
+
+/// Why a trade was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    /// An opposite signal.
+    #[default]
+    Signal,
+    StopLoss,
+    TakeProfit,
+    TrailingStop,
+    /// Closed on the last bar.
+    EndOfData,
+}
+
+impl ExitReason {
+    /// Short label for trade logs
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExitReason::Signal => "signal",
+            ExitReason::StopLoss => "stop",
+            ExitReason::TakeProfit => "target",
+            ExitReason::TrailingStop => "trail",
+            ExitReason::EndOfData => "end",
+        }
+    }
+}
+
+/// Average true range of each bar over the `period` bars ending at it, NaN
+/// while there are fewer.
+///
+/// The true range is the bar's high-low range widened to the previous close;
+/// without highs and lows it is the absolute change of the close.
+pub fn average_true_range(closes: &[f64], highs: Option<&[f64]>, lows: Option<&[f64]>, period: usize) -> Vec<f64> {
+    let ranges: Vec<f64> = (1..closes.len())
+        .map(|i| {
+            let prev = closes[i - 1];
+            match (highs.and_then(|h| h.get(i)), lows.and_then(|l| l.get(i))) {
+                (Some(&high), Some(&low)) => (high - low).max((high - prev).abs()).max((low - prev).abs()),
+                _ => (closes[i] - prev).abs(),
+            }
+        })
+        .collect();
+
+    let mut atr = vec![f64::NAN; closes.len()];
+    if period == 0 {
+        return atr;
+    }
+    for i in period..closes.len() {
+        atr[i] = ranges[i - period..i].iter().sum::<f64>() / period as f64;
+    }
+    atr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_atr() {
+        assert_eq!("pct:2".parse::<StopLevel>(), Ok(StopLevel::Percent { pct: 2.0 }));
+        assert_eq!("atr:14:2.5".parse::<StopLevel>(), Ok(StopLevel::Atr { period: 14, multiple: 2.5 }));
+        assert!("atr:1.5:2".parse::<StopLevel>().is_err());
+        assert!("pct:-1".parse::<StopLevel>().is_err());
+        assert!("ticks:3".parse::<StopLevel>().is_err());
+
+        let stops = Stops { stop_loss: Some(StopLevel::Atr { period: 3, multiple: 1.0 }), ..Default::default() };
+        assert!(stops.is_active() && !Stops::default().is_active());
+        assert_eq!(StopLevel::Percent { pct: 2.0 }.distance(50.0, None), Some(1.0));
+        assert_eq!(stops.stop_loss.unwrap().distance(50.0, Some(f64::NAN)), None);
+
+        let closes = [10.0, 11.0, 10.0, 12.0, 12.0];
+        let atr = average_true_range(&closes, None, None, 2);
+        assert!(atr[1].is_nan());
+        assert_eq!(&atr[2..], &[1.0, 1.5, 1.0]);
+        // A gap above the bar widens its range to the previous close
+        let (highs, lows) = ([10.5, 11.5, 10.5, 12.5, 12.5], [9.5, 10.5, 9.5, 11.5, 11.0]);
+        let atr = average_true_range(&closes, Some(&highs), Some(&lows), 2);
+        assert_eq!(atr[4], (2.5 + 1.5) / 2.0);
+    }
+}
@@ -0,0 +1,103 @@
+//! Percentile bootstrap of a statistic of a sample.
+//!
+//! The sample is resampled with replacement from a seeded generator, so an
+//! interval is the same on every run with the same seed.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Settings of a bootstrap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bootstrap {
+    /// Number of resamples
+    pub reps: usize,
+    /// Two-sided confidence of the interval, e.g. 0.9
+    pub confidence: f64,
+    pub seed: u64,
+}
+
+impl Default for Bootstrap {
+    fn default() -> Self {
+        Bootstrap { reps: 1000, confidence: 0.9, seed: 0 }
+    }
+}
+
+/// A statistic of the sample and its bootstrap confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub estimate: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Bootstrap {
+    /// `statistic` of each resample of `sample`, in the order drawn
+    pub fn replicates<F: Fn(&[f64]) -> f64>(&self, sample: &[f64], statistic: F) -> Vec<f64> {
+        let n = sample.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut resample = vec![0.0; n];
+        (0..self.reps)
+            .map(|_| {
+                for r in resample.iter_mut() {
+                    *r = sample[rng.gen_range(0..n)];
+                }
+                statistic(&resample)
+            })
+            .collect()
+    }
+
+    /// `statistic` of `sample` with the percentile interval of its resamples.
+    ///
+    /// Fails on an empty sample, fewer than 2 resamples or a confidence
+    /// outside (0, 1).
+    pub fn interval<F: Fn(&[f64]) -> f64>(&self, sample: &[f64], statistic: F) -> Result<Interval, String> {
+        if sample.is_empty() {
+            return Err("Empty sample".to_string());
+        }
+        if self.reps < 2 || !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(format!(
+                "Invalid bootstrap: {} resamples at confidence {}",
+                self.reps, self.confidence
+            ));
+        }
+        let mut replicates = self.replicates(sample, &statistic);
+        replicates.sort_by(f64::total_cmp);
+        let alpha = (1.0 - self.confidence) / 2.0;
+        Ok(Interval {
+            estimate: statistic(sample),
+            low: quantile(&replicates, alpha),
+            high: quantile(&replicates, 1.0 - alpha),
+        })
+    }
+}
+
+/// Value at fraction `q` of sorted values, by linear interpolation
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap_interval() {
+        let sample: Vec<f64> = (0..200).map(|i| ((i * 37) % 101) as f64 / 100.0).collect();
+        let mean = |x: &[f64]| x.iter().sum::<f64>() / x.len() as f64;
+        let boot = Bootstrap { reps: 500, confidence: 0.9, seed: 3 };
+        let ci = boot.interval(&sample, mean).unwrap();
+        assert!(ci.low < ci.estimate && ci.estimate < ci.high);
+        // Roughly 1.645 standard errors of the mean either side
+        assert!(ci.high - ci.low < 0.15);
+        assert_eq!(boot.interval(&sample, mean), Ok(ci));
+
+        assert_eq!(boot.replicates(&sample, mean).len(), 500);
+        assert!(boot.interval(&[], mean).is_err());
+        assert!(Bootstrap { confidence: 1.0, ..boot }.interval(&sample, mean).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::transforms::ReturnKind;
+
+/// One OHLCV bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    /// YYYYMMDDHHMMSS; daily bars have a time of 000000
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl Candle {
+    /// Date part of the timestamp as YYYYMMDD
+    pub fn date(&self) -> u32 {
+        (self.timestamp / 1_000_000) as u32
+    }
+}
+
+/// OHLCV price history, one entry per bar in time order.
+///
+/// Prices are stored as read from the file (not log-transformed); use
+/// `log_close` or `to_log` for the log prices most tools work with. Volume is
+/// 0 when the file has none.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bars {
+    /// YYYYMMDDHHMMSS; daily bars have a time of 000000
+    pub timestamp: Vec<u64>,
+    pub open: Vec<f64>,
+    pub high: Vec<f64>,
+    pub low: Vec<f64>,
+    pub close: Vec<f64>,
+    pub volume: Vec<f64>,
+}
+
+impl Bars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamp.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamp.is_empty()
+    }
+
+    pub fn push(&mut self, candle: Candle) {
+        self.timestamp.push(candle.timestamp);
+        self.open.push(candle.open);
+        self.high.push(candle.high);
+        self.low.push(candle.low);
+        self.close.push(candle.close);
+        self.volume.push(candle.volume);
+    }
+
+    /// Append a bar after checking that its timestamp follows the last one,
+    /// its prices are positive and its high and low bracket its open and close
+    pub fn push_checked(&mut self, candle: Candle) -> Result<(), String> {
+        if self.timestamp.last().is_some_and(|&prior| candle.timestamp <= prior) {
+            return Err("Date failed to increase".to_string());
+        }
+        if candle.open <= 0.0 || candle.high <= 0.0 || candle.low <= 0.0 || candle.close <= 0.0 {
+            return Err("Non-positive price".to_string());
+        }
+        if candle.low > candle.open.min(candle.close) || candle.high < candle.open.max(candle.close) {
+            return Err("Invalid open/high/low/close relationship".to_string());
+        }
+        self.push(candle);
+        Ok(())
+    }
+
+    /// Bar `i`, or `None` if out of range
+    pub fn get(&self, i: usize) -> Option<Candle> {
+        (i < self.len()).then(|| Candle {
+            timestamp: self.timestamp[i],
+            open: self.open[i],
+            high: self.high[i],
+            low: self.low[i],
+            close: self.close[i],
+            volume: self.volume[i],
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Candle> + '_ {
+        (0..self.len()).filter_map(|i| self.get(i))
+    }
+
+    /// Dates of the bars as YYYYMMDD
+    pub fn dates(&self) -> Vec<u32> {
+        self.timestamp.iter().map(|&t| (t / 1_000_000) as u32).collect()
+    }
+
+    /// Log of the close prices
+    pub fn log_close(&self) -> Vec<f64> {
+        self.close.iter().map(|c| c.ln()).collect()
+    }
+
+    /// Bar-to-bar log returns of the close, one fewer than the number of bars
+    pub fn log_returns(&self) -> Vec<f64> {
+        self.close.windows(2).map(|w| ReturnKind::Log.between(w[0], w[1])).collect()
+    }
+
+    /// Bar-to-bar simple returns of the close, one fewer than the number of bars
+    pub fn simple_returns(&self) -> Vec<f64> {
+        self.close.windows(2).map(|w| ReturnKind::Simple.between(w[0], w[1])).collect()
+    }
+
+    /// Copy with the logs of all four prices; volume is unchanged
+    pub fn to_log(&self) -> Bars {
+        let ln = |v: &[f64]| v.iter().map(|p| p.ln()).collect();
+        Bars {
+            timestamp: self.timestamp.clone(),
+            open: ln(&self.open),
+            high: ln(&self.high),
+            low: ln(&self.low),
+            close: ln(&self.close),
+            volume: self.volume.clone(),
+        }
+    }
+
+    /// Bars `range` by index
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Bars {
+        Bars {
+            timestamp: self.timestamp[range.clone()].to_vec(),
+            open: self.open[range.clone()].to_vec(),
+            high: self.high[range.clone()].to_vec(),
+            low: self.low[range.clone()].to_vec(),
+            close: self.close[range.clone()].to_vec(),
+            volume: self.volume[range].to_vec(),
+        }
+    }
+
+    /// Bars dated from `first` through `last` inclusive (both YYYYMMDD)
+    pub fn slice_dates(&self, first: u32, last: u32) -> Bars {
+        let start = self.timestamp.partition_point(|&t| ((t / 1_000_000) as u32) < first);
+        let end = self.timestamp.partition_point(|&t| ((t / 1_000_000) as u32) <= last);
+        self.slice(start..end.max(start))
+    }
+}
+
+/// Parse a leading date or date-time field into YYYYMMDDHHMMSS.
+///
+/// Accepts YYYYMMDD, YYYYMMDDHHMM and YYYYMMDDHHMMSS.
+pub(crate) fn parse_timestamp(field: &str) -> Option<u64> {
+    if !field.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value: u64 = field.parse().ok()?;
+    let timestamp = match field.len() {
+        8 => value * 1_000_000,
+        12 => value * 100,
+        14 => value,
+        _ => return None,
+    };
+    let date = timestamp / 1_000_000;
+    let (month, day) = ((date / 100) % 100, date % 100);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(timestamp)
+}
+
+/// Read a market file into `Bars`.
+///
+/// Each line holds a date (YYYYMMDD, optionally followed directly by HHMM or
+/// HHMMSS) and then either a single price, used for all four prices, or
+/// open, high, low and close, optionally followed by volume. Fields may be
+/// separated by spaces, tabs or commas. Dates must increase, prices must be
+/// positive, and the high and low must bracket the open and close.
+pub fn read_bars<P: AsRef<Path>>(filename: P) -> Result<Bars, String> {
+    let file = File::open(filename.as_ref()).map_err(|e| {
+        format!("Cannot open market history file {}: {}", filename.as_ref().display(), e)
+    })?;
+
+    let reader = BufReader::new(file);
+    let mut bars = Bars::new();
+
+    for (line_num, line_result) in reader.lines().enumerate() {
+        let line = line_result.map_err(|e| format!("Error reading line {}: {}", line_num + 1, e))?;
+        let mut fields = line.split([' ', '\t', ',']).filter(|s| !s.is_empty());
+
+        let Some(first) = fields.next() else {
+            continue;
+        };
+        let timestamp =
+            parse_timestamp(first).ok_or_else(|| format!("Invalid date on line {}", line_num + 1))?;
+
+        let values = fields
+            .map(|f| f.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid number on line {}", line_num + 1))?;
+
+        let candle = match *values.as_slice() {
+            [price] => Candle { timestamp, open: price, high: price, low: price, close: price, volume: 0.0 },
+            [open, high, low, close] => Candle { timestamp, open, high, low, close, volume: 0.0 },
+            [open, high, low, close, volume] => Candle { timestamp, open, high, low, close, volume },
+            _ => {
+                return Err(format!(
+                    "Line {} has {} values; expected a price, OHLC or OHLCV",
+                    line_num + 1,
+                    values.len()
+                ))
+            }
+        };
+
+        bars.push_checked(candle)
+            .map_err(|e| format!("{} on line {}", e, line_num + 1))?;
+    }
+
+    if bars.is_empty() {
+        return Err("No valid data found in file".to_string());
+    }
+
+    Ok(bars)
+}
+
+/// File format of a market history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarsFormat {
+    /// Whitespace- or comma-separated text, as read by `read_bars`
+    Text,
+    /// Parquet, as read by `read_bars_parquet` (needs the `parquet` feature)
+    Parquet,
+}
+
+impl BarsFormat {
+    /// Parquet for a `.parquet` or `.pq` extension, otherwise text
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("parquet") || ext.eq_ignore_ascii_case("pq") => {
+                BarsFormat::Parquet
+            }
+            _ => BarsFormat::Text,
+        }
+    }
+}
+
+impl std::str::FromStr for BarsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(BarsFormat::Text),
+            "parquet" | "pq" => Ok(BarsFormat::Parquet),
+            _ => Err(format!("Unknown market file format '{}' (expected text or parquet)", s)),
+        }
+    }
+}
+
+/// Read a market file in the given format, or the format given by its
+/// extension if `format` is `None`
+pub fn read_bars_with_format<P: AsRef<Path>>(
+    filename: P,
+    format: Option<BarsFormat>,
+) -> Result<Bars, String> {
+    match format.unwrap_or_else(|| BarsFormat::from_path(&filename)) {
+        BarsFormat::Text => read_bars(filename),
+        #[cfg(feature = "parquet")]
+        BarsFormat::Parquet => super::parquet::read_bars_parquet(filename),
+        #[cfg(not(feature = "parquet"))]
+        BarsFormat::Parquet => Err("Parquet support requires the `parquet` feature of statn".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_lines(lines: &[&str]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        for line in lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+        file
+    }
+
+    #[test]
+    fn test_read_formats() {
+        let file = write_lines(&["20200101 100.0", "", "20200102 101.5"]);
+        let bars = read_bars(file.path()).unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars.high[1], 101.5);
+        assert_eq!(bars.dates(), vec![20200101, 20200102]);
+
+        let file = write_lines(&["20200101 100 102 99 101 5000", "202001020930,101,103,100.5,102.5,6000"]);
+        let bars = read_bars(file.path()).unwrap();
+        assert_eq!(bars.timestamp, vec![20200101000000, 20200102093000]);
+        assert_eq!(bars.get(1).unwrap().volume, 6000.0);
+        assert_eq!(bars.get(1).unwrap().date(), 20200102);
+        assert!(bars.get(2).is_none());
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(read_bars(write_lines(&["2020010 100"]).path()).is_err());
+        assert!(read_bars(write_lines(&["20200102 100", "20200101 100"]).path()).is_err());
+        assert!(read_bars(write_lines(&["20200101 100 99 101 100"]).path()).is_err());
+        assert!(read_bars(write_lines(&["20200101 -1"]).path()).is_err());
+        assert!(read_bars(write_lines(&["20200101 1 2"]).path()).is_err());
+        assert!(read_bars(write_lines(&[""]).path()).is_err());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let mut bars = Bars::new();
+        for (i, close) in [100.0, 110.0, 99.0, 120.0].into_iter().enumerate() {
+            bars.push(Candle {
+                timestamp: (20200101 + i as u64) * 1_000_000,
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 0.0,
+            });
+        }
+
+        assert_eq!(bars.log_close()[1], 110.0_f64.ln());
+        let returns = bars.log_returns();
+        assert_eq!(returns.len(), 3);
+        assert!((returns[0] - (110.0_f64 / 100.0).ln()).abs() < 1e-12);
+        assert!((bars.simple_returns()[0] - 0.1).abs() < 1e-12);
+        assert_eq!(bars.to_log().high, bars.log_close());
+
+        let middle = bars.slice_dates(20200102, 20200103);
+        assert_eq!(middle.close, vec![110.0, 99.0]);
+        assert!(bars.slice_dates(20210101, 20211231).is_empty());
+        assert_eq!(bars.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_format_detection() {
+        assert_eq!(BarsFormat::from_path("data/ES.parquet"), BarsFormat::Parquet);
+        assert_eq!(BarsFormat::from_path("data/ES.PQ"), BarsFormat::Parquet);
+        assert_eq!(BarsFormat::from_path("data/ES.txt"), BarsFormat::Text);
+        assert_eq!(BarsFormat::from_path("data/ES"), BarsFormat::Text);
+        assert_eq!("Parquet".parse::<BarsFormat>(), Ok(BarsFormat::Parquet));
+        assert!("csv".parse::<BarsFormat>().is_err());
+
+        let file = write_lines(&["20200101 100.0"]);
+        let bars = read_bars_with_format(file.path(), Some(BarsFormat::Text)).unwrap();
+        assert_eq!(bars.close, vec![100.0]);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::core::transforms::ReturnKind;
+
 /// Training and test data split
 #[derive(Debug, Clone)]
 pub struct DataSplit {
@@ -84,7 +86,7 @@ pub fn compute_targets(prices: &[f64], start_idx: usize, n_cases: usize) -> Vec<
 /// Vector of simple returns: (price[i+1] - price[i]) / price[i]
 pub fn compute_returns(prices: &[f64]) -> Vec<f64> {
     prices.windows(2)
-        .map(|w| (w[1] - w[0]) / w[0])
+        .map(|w| ReturnKind::Simple.between(w[0], w[1]))
         .collect()
 }
 
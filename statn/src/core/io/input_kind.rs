@@ -0,0 +1,242 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::bars::{parse_timestamp, read_bars_with_format, BarsFormat};
+
+/// Fewest values `detect_input_kind` decides on
+pub const MIN_DETECT_VALUES: usize = 20;
+
+/// Mean absolute change over the standard deviation below which a series is
+/// a level (a random walk of 20 bars is about 0.3) and above which it is a
+/// series of returns (about 1.13 for independent returns)
+const LEVEL_ROUGHNESS: f64 = 0.5;
+const RETURNS_ROUGHNESS: f64 = 0.8;
+
+/// Largest log price taken for one (a price of about 5e8)
+const MAX_LOG_PRICE: f64 = 20.0;
+
+/// What the numbers of a market file are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// Prices, all positive
+    Price,
+    /// Natural logarithms of prices
+    LogPrice,
+    /// Returns in percent from one bar to the next
+    PercentReturn,
+}
+
+impl fmt::Display for InputKind {
+    /// The name `from_str` parses
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            InputKind::Price => "price",
+            InputKind::LogPrice => "log-price",
+            InputKind::PercentReturn => "pct-return",
+        })
+    }
+}
+
+impl FromStr for InputKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "price" | "raw" => Ok(InputKind::Price),
+            "log-price" | "log" => Ok(InputKind::LogPrice),
+            "pct-return" | "return" | "returns" => Ok(InputKind::PercentReturn),
+            _ => Err(format!("Unknown input kind '{}' (expected price, log-price or pct-return)", s)),
+        }
+    }
+}
+
+/// Kind of the numbers in `values`, or an error when it cannot be told.
+///
+/// Prices and log prices move little from bar to bar compared with their
+/// spread; returns move by about their spread. A level with a value of zero
+/// or less is a log price, and one above 20 a price; a level entirely
+/// between 0 and 20 could be either and is an error, as is a series that
+/// is neither clearly a level nor clearly returns, or shorter than
+/// `MIN_DETECT_VALUES`.
+pub fn detect_input_kind(values: &[f64]) -> Result<InputKind, String> {
+    let n = values.len();
+    if n < MIN_DETECT_VALUES {
+        return Err(format!("Too few values ({}) to detect the input kind; give it explicitly", n));
+    }
+    if let Some(k) = values.iter().position(|v| !v.is_finite()) {
+        return Err(format!("Value {} is not a finite number", k + 1));
+    }
+
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64).sqrt();
+    if std == 0.0 {
+        return Err("Constant values: cannot detect the input kind; give it explicitly".to_string());
+    }
+    let mean_change = values.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (n - 1) as f64;
+    let roughness = mean_change / std;
+
+    let (min, max) = values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    if roughness > RETURNS_ROUGHNESS {
+        if min <= -100.0 {
+            return Err(format!("Values look like returns, but {} is not a percent return; give the input kind explicitly", min));
+        }
+        Ok(InputKind::PercentReturn)
+    } else if roughness < LEVEL_ROUGHNESS {
+        if min <= 0.0 {
+            Ok(InputKind::LogPrice)
+        } else if max > MAX_LOG_PRICE {
+            Ok(InputKind::Price)
+        } else {
+            Err(format!(
+                "Values from {} to {} could be prices or log prices; give the input kind explicitly",
+                min, max
+            ))
+        }
+    } else {
+        Err(format!(
+            "Values are neither clearly prices nor returns (mean change {:.3} of their spread); give the input kind explicitly",
+            roughness
+        ))
+    }
+}
+
+/// Log prices of `values` of `kind`. Percent returns are compounded from a
+/// log price of 0 before the first return, so value k is the log price
+/// after return k.
+pub fn to_log_prices(values: &[f64], kind: InputKind) -> Result<Vec<f64>, String> {
+    if let Some(k) = values.iter().position(|v| !v.is_finite()) {
+        return Err(format!("Value {} is not a finite number", k + 1));
+    }
+    match kind {
+        InputKind::Price => match values.iter().position(|&v| v <= 0.0) {
+            Some(k) => Err(format!("Non-positive price {} at value {}", values[k], k + 1)),
+            None => Ok(values.iter().map(|v| v.ln()).collect()),
+        },
+        InputKind::LogPrice => Ok(values.to_vec()),
+        InputKind::PercentReturn => {
+            let mut log_price = 0.0;
+            values
+                .iter()
+                .enumerate()
+                .map(|(k, &r)| {
+                    if r <= -100.0 {
+                        return Err(format!("Return of {}% at value {}", r, k + 1));
+                    }
+                    log_price += (1.0 + r / 100.0).ln();
+                    Ok(log_price)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Read the closes of a market file (see `read_bars`; a text file's numbers
+/// may be of any sign) and normalize them to log prices. The kind is
+/// detected unless `kind` gives it; the kind used is returned with the
+/// prices.
+pub fn read_log_prices<P: AsRef<Path>>(
+    filename: P,
+    format: Option<BarsFormat>,
+    kind: Option<InputKind>,
+) -> Result<(Vec<f64>, InputKind), String> {
+    let values = match format.unwrap_or_else(|| BarsFormat::from_path(&filename)) {
+        BarsFormat::Text => read_text_closes(filename)?,
+        BarsFormat::Parquet => read_bars_with_format(filename, format)?.close,
+    };
+    let kind = match kind {
+        Some(kind) => kind,
+        None => detect_input_kind(&values)?,
+    };
+    Ok((to_log_prices(&values, kind)?, kind))
+}
+
+/// Closes of a text market file without the checks prices get
+fn read_text_closes<P: AsRef<Path>>(filename: P) -> Result<Vec<f64>, String> {
+    let file = File::open(filename.as_ref()).map_err(|e| {
+        format!("Cannot open market history file {}: {}", filename.as_ref().display(), e)
+    })?;
+
+    let mut closes = Vec::new();
+    for (line_num, line_result) in BufReader::new(file).lines().enumerate() {
+        let line = line_result.map_err(|e| format!("Error reading line {}: {}", line_num + 1, e))?;
+        let mut fields = line.split([' ', '\t', ',']).filter(|s| !s.is_empty());
+        let Some(first) = fields.next() else {
+            continue;
+        };
+        parse_timestamp(first).ok_or_else(|| format!("Invalid date on line {}", line_num + 1))?;
+        let values = fields
+            .map(|f| f.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid number on line {}", line_num + 1))?;
+        match *values.as_slice() {
+            [value] | [_, _, _, value] | [_, _, _, value, _] => closes.push(value),
+            _ => {
+                return Err(format!(
+                    "Line {} has {} values; expected a value, OHLC or OHLCV",
+                    line_num + 1,
+                    values.len()
+                ))
+            }
+        }
+    }
+
+    if closes.is_empty() {
+        return Err("No valid data found in file".to_string());
+    }
+    Ok(closes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn walk(start: f64, n: usize) -> Vec<f64> {
+        (0..n).map(|i| start + 0.02 * i as f64 + 0.01 * (i as f64 * 0.7).sin()).collect()
+    }
+
+    #[test]
+    fn test_detect_input_kind() {
+        let log_prices = walk(4.6, 40);
+        let prices: Vec<f64> = log_prices.iter().map(|p| p.exp()).collect();
+        assert_eq!(detect_input_kind(&prices), Ok(InputKind::Price));
+        // Log prices of a price below 1 go negative
+        assert_eq!(detect_input_kind(&walk(-0.5, 40)), Ok(InputKind::LogPrice));
+        // A level between 0 and 20 is ambiguous
+        assert!(detect_input_kind(&log_prices).is_err());
+
+        let returns: Vec<f64> = (0..40).map(|i| if i % 3 == 0 { 1.2 } else { -0.7 + 0.1 * (i % 5) as f64 }).collect();
+        assert_eq!(detect_input_kind(&returns), Ok(InputKind::PercentReturn));
+        assert!(detect_input_kind(&returns[..10]).is_err());
+        assert!(detect_input_kind(&[5.0; 30]).is_err());
+    }
+
+    #[test]
+    fn test_to_log_prices() {
+        assert_eq!(to_log_prices(&[1.0, std::f64::consts::E], InputKind::Price), Ok(vec![0.0, 1.0]));
+        assert!(to_log_prices(&[1.0, -2.0], InputKind::Price).is_err());
+        assert_eq!(to_log_prices(&[-0.5, 0.2], InputKind::LogPrice), Ok(vec![-0.5, 0.2]));
+        let compounded = to_log_prices(&[10.0, -10.0], InputKind::PercentReturn).unwrap();
+        assert!((compounded[1] - (1.1_f64 * 0.9).ln()).abs() < 1e-12);
+        assert!(to_log_prices(&[-100.0], InputKind::PercentReturn).is_err());
+        assert_eq!("log".parse::<InputKind>(), Ok(InputKind::LogPrice));
+        assert_eq!(InputKind::PercentReturn.to_string().parse::<InputKind>(), Ok(InputKind::PercentReturn));
+    }
+
+    #[test]
+    fn test_read_log_prices() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for (i, r) in [0.5, -0.3, 1.1, -0.8, 0.2].iter().enumerate() {
+            writeln!(file, "2024010{} {}", i + 1, r).unwrap();
+        }
+        let (prices, kind) = read_log_prices(file.path(), None, Some(InputKind::PercentReturn)).unwrap();
+        assert_eq!((prices.len(), kind), (5, InputKind::PercentReturn));
+        assert!((prices[0] - 1.005_f64.ln()).abs() < 1e-12);
+        // Too short to detect, and negative values are not prices
+        assert!(read_log_prices(file.path(), None, None).is_err());
+        assert!(read_log_prices(file.path(), None, Some(InputKind::Price)).is_err());
+    }
+}
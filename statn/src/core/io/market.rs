@@ -169,6 +169,7 @@ fn read_ohlc_file_impl<P: AsRef<Path>>(filename: P, use_log: bool) -> Result<Ohl
             high.push(h.ln());
             low.push(l.ln());
             close.push(c.ln());
+        } else {
             open.push(o);
             high.push(h);
             low.push(l);
@@ -1,10 +1,24 @@
+mod bars;
 mod read;
 mod market;
 mod data;
+mod session;
+mod input_kind;
+mod stitch;
+mod provenance;
+#[cfg(feature = "parquet")]
+mod parquet;
 
+pub use bars::*;
 pub use read::*;
 pub use market::*;
 pub use data::*;
+pub use session::*;
+pub use input_kind::*;
+pub use stitch::*;
+pub use provenance::*;
+#[cfg(feature = "parquet")]
+pub use parquet::{read_bars_parquet, write_bars_parquet};
 
 pub mod write;
 pub use write::*;
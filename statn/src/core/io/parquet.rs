@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use parquet::basic::Compression;
+use parquet::data_type::{DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::writer::SerializedFileWriter;
+use parquet::record::Field;
+use parquet::schema::parser::parse_message_type;
+
+use super::bars::{parse_timestamp, Bars, Candle};
+
+/// Schema written by `write_bars_parquet`
+const BARS_SCHEMA: &str = "
+    message bars {
+        REQUIRED INT64 timestamp;
+        REQUIRED DOUBLE open;
+        REQUIRED DOUBLE high;
+        REQUIRED DOUBLE low;
+        REQUIRED DOUBLE close;
+        REQUIRED DOUBLE volume;
+    }
+";
+
+/// Write bars to a Parquet file (Snappy compressed, one row group).
+///
+/// The timestamp is stored as an INT64 in YYYYMMDDHHMMSS form, the prices and
+/// volume as DOUBLE, with the column names of `Bars`.
+pub fn write_bars_parquet<P: AsRef<Path>>(filename: P, bars: &Bars) -> Result<(), String> {
+    let path = filename.as_ref();
+    let schema = Arc::new(parse_message_type(BARS_SCHEMA).map_err(|e| e.to_string())?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let file = File::create(path)
+        .map_err(|e| format!("Cannot create Parquet file {}: {}", path.display(), e))?;
+    let write_err = |e: parquet::errors::ParquetError| {
+        format!("Error writing Parquet file {}: {}", path.display(), e)
+    };
+
+    let mut writer = SerializedFileWriter::new(file, schema, props).map_err(write_err)?;
+    let mut row_group = writer.next_row_group().map_err(write_err)?;
+
+    let timestamps: Vec<i64> = bars.timestamp.iter().map(|&t| t as i64).collect();
+    let columns = [&bars.open, &bars.high, &bars.low, &bars.close, &bars.volume];
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column().map_err(write_err)? {
+        if index == 0 {
+            column
+                .typed::<Int64Type>()
+                .write_batch(&timestamps, None, None)
+                .map_err(write_err)?;
+        } else {
+            column
+                .typed::<DoubleType>()
+                .write_batch(columns[index - 1], None, None)
+                .map_err(write_err)?;
+        }
+        column.close().map_err(write_err)?;
+        index += 1;
+    }
+    row_group.close().map_err(write_err)?;
+    writer.close().map_err(write_err)?;
+    Ok(())
+}
+
+/// What a column of a Parquet file holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Timestamp,
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+fn column_role(name: &str) -> Option<Role> {
+    match name.to_ascii_lowercase().as_str() {
+        "timestamp" | "date" | "time" | "datetime" => Some(Role::Timestamp),
+        "open" => Some(Role::Open),
+        "high" => Some(Role::High),
+        "low" => Some(Role::Low),
+        "close" | "price" => Some(Role::Close),
+        "volume" => Some(Role::Volume),
+        _ => None,
+    }
+}
+
+/// Read bars from a Parquet file.
+///
+/// Columns are matched by name, ignoring case: `timestamp` (or `date`, `time`,
+/// `datetime`), `open`, `high`, `low`, `close` (or `price`) and `volume`. Only
+/// the timestamp and close are required; a missing open, high or low takes the
+/// close and a missing volume is 0. The timestamp may be an integer or string
+/// of YYYYMMDD[HHMM[SS]] digits, a Parquet DATE or a Parquet TIMESTAMP (UTC).
+/// Rows are checked as in `read_bars`.
+pub fn read_bars_parquet<P: AsRef<Path>>(filename: P) -> Result<Bars, String> {
+    let path = filename.as_ref();
+    let file = File::open(path)
+        .map_err(|e| format!("Cannot open Parquet file {}: {}", path.display(), e))?;
+    let reader = SerializedFileReader::new(file)
+        .map_err(|e| format!("Error reading Parquet file {}: {}", path.display(), e))?;
+
+    let fields = reader.metadata().file_metadata().schema().get_fields();
+    let roles: Vec<Option<Role>> = fields.iter().map(|f| column_role(f.name())).collect();
+    for (role, name) in [(Role::Timestamp, "timestamp"), (Role::Close, "close")] {
+        if !roles.contains(&Some(role)) {
+            return Err(format!("Parquet file {} has no {} column", path.display(), name));
+        }
+    }
+
+    let rows = reader
+        .get_row_iter(None)
+        .map_err(|e| format!("Error reading Parquet file {}: {}", path.display(), e))?;
+
+    let mut bars = Bars::new();
+    for (row_num, row) in rows.enumerate() {
+        let row = row.map_err(|e| format!("Error reading row {}: {}", row_num + 1, e))?;
+        let mut timestamp = None;
+        let mut prices = [None; 4];
+        let mut volume = 0.0;
+
+        for ((_, field), role) in row.get_column_iter().zip(&roles) {
+            let Some(role) = role else {
+                continue;
+            };
+            let invalid = || format!("Invalid {:?} value in row {}", role, row_num + 1);
+            match role {
+                Role::Timestamp => timestamp = Some(field_timestamp(field).ok_or_else(invalid)?),
+                Role::Volume => volume = field_number(field).ok_or_else(invalid)?,
+                Role::Open => prices[0] = Some(field_number(field).ok_or_else(invalid)?),
+                Role::High => prices[1] = Some(field_number(field).ok_or_else(invalid)?),
+                Role::Low => prices[2] = Some(field_number(field).ok_or_else(invalid)?),
+                Role::Close => prices[3] = Some(field_number(field).ok_or_else(invalid)?),
+            }
+        }
+
+        let (Some(timestamp), Some(close)) = (timestamp, prices[3]) else {
+            return Err(format!("Missing timestamp or close in row {}", row_num + 1));
+        };
+        let candle = Candle {
+            timestamp,
+            open: prices[0].unwrap_or(close),
+            high: prices[1].unwrap_or(close),
+            low: prices[2].unwrap_or(close),
+            close,
+            volume,
+        };
+        bars.push_checked(candle)
+            .map_err(|e| format!("{} in row {}", e, row_num + 1))?;
+    }
+
+    if bars.is_empty() {
+        return Err("No valid data found in file".to_string());
+    }
+
+    Ok(bars)
+}
+
+fn field_number(field: &Field) -> Option<f64> {
+    match *field {
+        Field::Double(v) => Some(v),
+        Field::Float(v) => Some(v as f64),
+        Field::Long(v) => Some(v as f64),
+        Field::Int(v) => Some(v as f64),
+        Field::ULong(v) => Some(v as f64),
+        Field::UInt(v) => Some(v as f64),
+        _ => None,
+    }
+}
+
+fn field_timestamp(field: &Field) -> Option<u64> {
+    match field {
+        Field::Long(v) => parse_timestamp(&v.to_string()),
+        Field::Int(v) => parse_timestamp(&v.to_string()),
+        Field::ULong(v) => parse_timestamp(&v.to_string()),
+        Field::UInt(v) => parse_timestamp(&v.to_string()),
+        Field::Str(s) => parse_timestamp(&s.replace(['-', ':', ' ', 'T'], "")),
+        Field::Date(days) => Some(civil_timestamp(*days as i64 * 86_400)),
+        Field::TimestampMillis(ms) => Some(civil_timestamp(ms.div_euclid(1_000))),
+        Field::TimestampMicros(us) => Some(civil_timestamp(us.div_euclid(1_000_000))),
+        _ => None,
+    }
+}
+
+/// YYYYMMDDHHMMSS of a time in seconds since 1970-01-01 UTC
+fn civil_timestamp(seconds: i64) -> u64 {
+    let days = seconds.div_euclid(86_400);
+    let secs = seconds.rem_euclid(86_400);
+
+    // Civil date from day number (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let date = (year * 10_000 + month * 100 + day) as u64;
+    let time = (secs / 3600 * 10_000 + secs % 3600 / 60 * 100 + secs % 60) as u64;
+    date * 1_000_000 + time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_round_trip() {
+        let mut bars = Bars::new();
+        for i in 0..50u64 {
+            let close = 100.0 + i as f64;
+            bars.push(Candle {
+                timestamp: 20240101093000 + 100 * i,
+                open: close - 0.5,
+                high: close + 1.0,
+                low: close - 1.0,
+                close,
+                volume: 1000.0 * i as f64,
+            });
+        }
+        let file = NamedTempFile::new().unwrap();
+        write_bars_parquet(file.path(), &bars).unwrap();
+        assert_eq!(read_bars_parquet(file.path()).unwrap(), bars);
+    }
+
+    #[test]
+    fn test_foreign_schema() {
+        // A close-only file with a DATE column, as written by other tools
+        let schema = Arc::new(
+            parse_message_type(
+                "message prices { REQUIRED INT32 Date (DATE); REQUIRED DOUBLE Price; OPTIONAL BINARY note (UTF8); }",
+            )
+            .unwrap(),
+        );
+        let file = NamedTempFile::new().unwrap();
+        let mut writer =
+            SerializedFileWriter::new(file.reopen().unwrap(), schema, Default::default()).unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        // 2024-02-28, 2024-02-29, 2024-03-01
+        column
+            .typed::<parquet::data_type::Int32Type>()
+            .write_batch(&[19781, 19782, 19783], None, None)
+            .unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column.typed::<DoubleType>().write_batch(&[10.0, 11.0, 12.0], None, None).unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<parquet::data_type::ByteArrayType>()
+            .write_batch(&[], Some(&[0, 0, 0]), None)
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let bars = read_bars_parquet(file.path()).unwrap();
+        assert_eq!(bars.dates(), vec![20240228, 20240229, 20240301]);
+        assert_eq!(bars.low, vec![10.0, 11.0, 12.0]);
+        assert_eq!(bars.volume, vec![0.0; 3]);
+    }
+
+    #[test]
+    fn test_civil_timestamp() {
+        assert_eq!(civil_timestamp(0), 19700101000000);
+        assert_eq!(civil_timestamp(951_782_400 + 3_723), 20000229010203);
+        assert_eq!(civil_timestamp(-86_400), 19691231000000);
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::bars::Bars;
+
+/// Where a result's input data came from: the symbol, source file, date
+/// range, length and a hash of the content, so a result can be traced to the
+/// exact data it was computed on.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Symbol, the stem of the source file name unless given
+    pub symbol: String,
+    /// File (or other source) the data was read from
+    pub source: String,
+    /// Date or time of the first and last value; empty if the data has none
+    pub first: String,
+    pub last: String,
+    /// Number of values or bars
+    pub count: usize,
+    /// FNV-1a hash of the values and their dates, 16 hex digits
+    pub hash: String,
+}
+
+impl Provenance {
+    /// Provenance of bars read from `source`. The hash covers the
+    /// timestamps and all five values of every bar.
+    pub fn of_bars(bars: &Bars, source: &str) -> Self {
+        let mut hasher = Fnv1a::new();
+        for bar in bars.iter() {
+            hasher.write(&bar.timestamp.to_le_bytes());
+            for value in [bar.open, bar.high, bar.low, bar.close, bar.volume] {
+                hasher.write(&value.to_bits().to_le_bytes());
+            }
+        }
+        let label = |t: Option<&u64>| t.map(|t| t.to_string()).unwrap_or_default();
+        Provenance {
+            symbol: symbol_of(source),
+            source: source.to_string(),
+            first: label(bars.timestamp.first()),
+            last: label(bars.timestamp.last()),
+            count: bars.len(),
+            hash: hasher.hex(),
+        }
+    }
+
+    /// Provenance of a series read from `source`, with the date of each
+    /// value in `dates` (empty if the file has none). The hash covers the
+    /// exact bits of the values, so a different transformation of the same
+    /// file (such as log prices) hashes differently.
+    pub fn of_series(values: &[f64], dates: &[String], source: &str) -> Self {
+        let mut hasher = Fnv1a::new();
+        for (i, value) in values.iter().enumerate() {
+            if let Some(date) = dates.get(i) {
+                hasher.write(date.as_bytes());
+                hasher.write(&[0]);
+            }
+            hasher.write(&value.to_bits().to_le_bytes());
+        }
+        Provenance {
+            symbol: symbol_of(source),
+            source: source.to_string(),
+            first: dates.first().cloned().unwrap_or_default(),
+            last: dates.last().cloned().unwrap_or_default(),
+            count: values.len(),
+            hash: hasher.hex(),
+        }
+    }
+
+    /// Replace the symbol taken from the file name
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.symbol = symbol.to_string();
+        self
+    }
+
+    /// Comment line for the top of a text or CSV output file
+    pub fn comment(&self) -> String {
+        format!("# data: {}", self)
+    }
+
+    /// Read back a line written by `comment` or `Display`
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let line = line.strip_prefix("# data:").unwrap_or(line).trim_start();
+        // The source comes last and may contain spaces
+        let (fields, source) = line.split_once(" source=")?;
+        let mut provenance = Provenance { source: source.to_string(), ..Default::default() };
+        for field in fields.split_whitespace() {
+            let (key, value) = field.split_once('=')?;
+            match key {
+                "hash" => provenance.hash = value.to_string(),
+                "symbol" => provenance.symbol = value.to_string(),
+                "count" => provenance.count = value.parse().ok()?,
+                "range" => {
+                    let (first, last) = value.split_once("..")?;
+                    provenance.first = first.to_string();
+                    provenance.last = last.to_string();
+                }
+                _ => return None,
+            }
+        }
+        Some(provenance)
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "hash={} symbol={} range={}..{} count={} source={}",
+            self.hash, self.symbol, self.first, self.last, self.count, self.source
+        )
+    }
+}
+
+/// Provenance in the leading comment lines of a file written with
+/// `Provenance::comment`, if any
+pub fn read_provenance<P: AsRef<Path>>(path: P) -> Option<Provenance> {
+    let text = std::fs::read_to_string(path).ok()?;
+    text.lines()
+        .take_while(|line| line.starts_with('#'))
+        .find_map(|line| line.starts_with("# data:").then(|| Provenance::parse(line)).flatten())
+}
+
+fn symbol_of(source: &str) -> String {
+    Path::new(source)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(source)
+        .replace(char::is_whitespace, "_")
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases (unlike the standard
+/// library's hasher)
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::io::Candle;
+
+    #[test]
+    fn test_hash_tracks_content() {
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.hex(), "af63dc4c8601ec8c");
+
+        let dates: Vec<String> = vec!["20240102".into(), "20240103".into()];
+        let p = Provenance::of_series(&[1.0, 2.0], &dates, "data/XAG USD.txt");
+        assert_eq!((p.symbol.as_str(), p.first.as_str(), p.last.as_str(), p.count), ("XAG_USD", "20240102", "20240103", 2));
+        assert_eq!(p, Provenance::of_series(&[1.0, 2.0], &dates, "data/XAG USD.txt"));
+        assert_ne!(p.hash, Provenance::of_series(&[1.0, 2.000001], &dates, "x").hash);
+        assert_ne!(p.hash, Provenance::of_series(&[2.0, 1.0], &dates, "x").hash);
+        assert_ne!(p.hash, Provenance::of_series(&[1.0, 2.0], &[], "x").hash);
+
+        let mut bars = Bars::new();
+        bars.push(Candle { timestamp: 20240102000000, open: 1.0, high: 1.0, low: 1.0, close: 1.0, volume: 0.0 });
+        let q = Provenance::of_bars(&bars, "gold.csv").with_symbol("XAU");
+        assert_eq!((q.symbol.as_str(), q.first.as_str(), q.count), ("XAU", "20240102000000", 1));
+        bars.volume[0] = 5.0;
+        assert_ne!(q.hash, Provenance::of_bars(&bars, "gold.csv").hash);
+    }
+
+    #[test]
+    fn test_comment_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let p = Provenance::of_series(&[1.0], &["20240102".into()], "/data/my prices.txt");
+        assert_eq!(Provenance::parse(&p.comment()), Some(p.clone()));
+
+        let path = dir.path().join("result.csv");
+        std::fs::write(&path, format!("# written by a tool\n{}\na,b\n1,2\n", p.comment())).unwrap();
+        assert_eq!(read_provenance(&path), Some(p));
+        std::fs::write(&path, "a,b\n# data: hash=1 source=x\n").unwrap();
+        assert_eq!(read_provenance(&path), None);
+        assert_eq!(Provenance::parse("hash=1 bogus=2 source=x"), None);
+    }
+}
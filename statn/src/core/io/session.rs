@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::bars::{parse_timestamp, Bars, Candle};
+
+/// One symbol's trading day, built from its ticks
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SymbolDay {
+    pub ticks: usize,
+    pub volume: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Finished bars of the day, in order
+    pub bars: Bars,
+    /// Strategy PnL of the day, as last reported with `set_pnl`
+    pub pnl: f64,
+}
+
+/// Statistics of a closed session
+#[derive(Debug, Clone, PartialEq)]
+pub struct DaySummary {
+    /// YYYYMMDD
+    pub date: u32,
+    pub symbols: BTreeMap<String, SymbolDay>,
+}
+
+impl DaySummary {
+    pub fn total_ticks(&self) -> usize {
+        self.symbols.values().map(|s| s.ticks).sum()
+    }
+
+    pub fn total_pnl(&self) -> f64 {
+        self.symbols.values().map(|s| s.pnl).sum()
+    }
+
+    /// Append one CSV line per symbol to a history file, writing the header
+    /// if the file is new
+    pub fn append_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let is_new = !path.exists() || std::fs::metadata(path)?.len() == 0;
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if is_new {
+            writeln!(file, "date,symbol,ticks,bars,volume,open,high,low,close,pnl")?;
+        }
+        for (symbol, day) in &self.symbols {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{},{}",
+                self.date,
+                symbol,
+                day.ticks,
+                day.bars.len(),
+                day.volume,
+                day.open,
+                day.high,
+                day.low,
+                day.close,
+                day.pnl
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds fixed-length bars per symbol from the ticks of one session.
+#[derive(Debug, Clone)]
+pub struct SessionTracker {
+    bar_seconds: u32,
+    date: Option<u32>,
+    days: BTreeMap<String, SymbolDay>,
+    /// Bar still being built, per symbol
+    open_bars: BTreeMap<String, Candle>,
+}
+
+impl SessionTracker {
+    /// Tracker building bars of `bar_seconds` (which must divide a day)
+    pub fn new(bar_seconds: u32) -> Result<Self, String> {
+        if bar_seconds == 0 || 86_400 % bar_seconds != 0 {
+            return Err(format!("Bar length {} s does not divide a day", bar_seconds));
+        }
+        Ok(SessionTracker {
+            bar_seconds,
+            date: None,
+            days: BTreeMap::new(),
+            open_bars: BTreeMap::new(),
+        })
+    }
+
+    /// Date (YYYYMMDD) of the session in progress, if it has ticks
+    pub fn date(&self) -> Option<u32> {
+        self.date
+    }
+
+    /// Take a tick. Returns the symbol's previous bar when the tick starts a
+    /// new one. Ticks of a later date must wait for `end_of_day`.
+    pub fn on_tick(&mut self, symbol: &str, timestamp: u64, price: f64, volume: f64) -> Result<Option<Candle>, String> {
+        let date = (timestamp / 1_000_000) as u32;
+        match self.date {
+            Some(current) if current != date => {
+                return Err(format!("Tick of {} before the end of day of {}", date, current));
+            }
+            _ => self.date = Some(date),
+        }
+
+        let day = self.days.entry(symbol.to_string()).or_default();
+        if day.ticks == 0 {
+            (day.open, day.high, day.low) = (price, price, price);
+        }
+        day.ticks += 1;
+        day.volume += volume;
+        day.high = day.high.max(price);
+        day.low = day.low.min(price);
+        day.close = price;
+
+        let second = seconds_of_day(timestamp) / self.bar_seconds * self.bar_seconds;
+        let start = date as u64 * 1_000_000 + hhmmss(second) as u64;
+        let mut finished = None;
+        match self.open_bars.get_mut(symbol) {
+            Some(bar) if bar.timestamp == start => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+                bar.volume += volume;
+            }
+            Some(bar) if start < bar.timestamp => {
+                return Err(format!("Tick at {} before the bar of {} for {}", timestamp, bar.timestamp, symbol));
+            }
+            _ => {
+                let bar = Candle { timestamp: start, open: price, high: price, low: price, close: price, volume };
+                if let Some(prior) = self.open_bars.insert(symbol.to_string(), bar) {
+                    day.bars.push(prior);
+                    finished = Some(prior);
+                }
+            }
+        }
+        Ok(finished)
+    }
+
+    /// Record the strategy's PnL of the day for a symbol
+    pub fn set_pnl(&mut self, symbol: &str, pnl: f64) {
+        self.days.entry(symbol.to_string()).or_default().pnl = pnl;
+    }
+
+    /// Finish the open bars and return the day's statistics, leaving the
+    /// tracker empty for the next session. `None` if no tick came in.
+    pub fn end_of_day(&mut self) -> Option<DaySummary> {
+        let date = self.date.take()?;
+        for (symbol, bar) in std::mem::take(&mut self.open_bars) {
+            if let Some(day) = self.days.get_mut(&symbol) {
+                day.bars.push(bar);
+            }
+        }
+        Some(DaySummary { date, symbols: std::mem::take(&mut self.days) })
+    }
+}
+
+/// End-of-day job: at the session close, summarize the day, append it to a
+/// history file and rotate the logs.
+///
+/// A session loop calls `due` with the time of each tick or timer event and
+/// `run` when it returns true.
+#[derive(Debug, Clone)]
+pub struct EndOfDayJob {
+    /// Session close, HHMMSS
+    pub close: u32,
+    /// CSV file the summaries are appended to
+    pub history: PathBuf,
+    /// Log files rotated after each session
+    pub logs: Vec<PathBuf>,
+    /// Rotated copies kept per log; 0 keeps all
+    pub keep_logs: usize,
+}
+
+impl EndOfDayJob {
+    /// Whether the session of `tracker` should be closed at `now`
+    /// (YYYYMMDDHHMMSS): its close has passed, or a later day has begun
+    pub fn due(&self, tracker: &SessionTracker, now: u64) -> bool {
+        let today = (now / 1_000_000) as u32;
+        tracker
+            .date()
+            .is_some_and(|date| today > date || (now % 1_000_000) as u32 >= self.close)
+    }
+
+    /// Close the session: finish its bars, append its statistics to the
+    /// history and rotate the logs
+    pub fn run(&self, tracker: &mut SessionTracker) -> io::Result<Option<DaySummary>> {
+        let Some(summary) = tracker.end_of_day() else {
+            return Ok(None);
+        };
+        summary.append_to(&self.history)?;
+        rotate_logs(&self.logs, summary.date, self.keep_logs)?;
+        Ok(Some(summary))
+    }
+}
+
+/// Rename each existing log to `<name>.<date>` and delete the oldest rotated
+/// copies past `keep` (0 keeps all)
+pub fn rotate_logs(logs: &[PathBuf], date: u32, keep: usize) -> io::Result<()> {
+    for log in logs {
+        let Some(name) = log.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if log.exists() {
+            std::fs::rename(log, log.with_file_name(format!("{}.{}", name, date)))?;
+        }
+        if keep == 0 {
+            continue;
+        }
+
+        let dir = match log.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let prefix = format!("{}.", name);
+        let mut rotated: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_prefix(&prefix))
+                    .is_some_and(|suffix| suffix.len() == 8 && suffix.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .collect();
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(keep);
+        for old in &rotated[..excess] {
+            std::fs::remove_file(old)?;
+        }
+    }
+    Ok(())
+}
+
+/// Times a market may be traded, for intraday bars.
+///
+/// Parsed from a comma-separated list of `HHMM-HHMM` windows (colons
+/// allowed), e.g. `0945-1545` to skip the first and last 15 minutes of a
+/// 09:30-16:00 session.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SessionFilter {
+    /// Allowed (start, end) times of day as HHMM, the start included and the
+    /// end excluded; a window ending before it starts runs over midnight.
+    /// Empty allows the whole day
+    pub windows: Vec<(u32, u32)>,
+    /// Disallow Saturdays and Sundays
+    pub skip_weekends: bool,
+}
+
+impl SessionFilter {
+    /// Whether the filter disallows anything
+    pub fn is_active(&self) -> bool {
+        !self.windows.is_empty() || self.skip_weekends
+    }
+
+    /// Whether a bar at `timestamp` (YYYYMMDDHHMMSS) may trade
+    pub fn allows(&self, timestamp: u64) -> bool {
+        if self.skip_weekends && weekday((timestamp / 1_000_000) as u32) >= 5 {
+            return false;
+        }
+        let hhmm = (timestamp % 1_000_000 / 100) as u32;
+        self.windows.is_empty()
+            || self.windows.iter().any(|&(start, end)| {
+                if start <= end { (start..end).contains(&hhmm) } else { hhmm >= start || hhmm < end }
+            })
+    }
+
+    /// Whether each bar may trade, from its date and time field (YYYYMMDD
+    /// with an optional HHMM or HHMMSS; other characters such as `-`, `:`
+    /// and spaces are ignored).
+    ///
+    /// An error if a field cannot be read, or if there are time windows and
+    /// every bar is at midnight, as daily bars are.
+    pub fn mask(&self, dates: &[String]) -> Result<Vec<bool>, String> {
+        let timestamps = dates
+            .iter()
+            .map(|d| {
+                let digits: String = d.chars().filter(|c| c.is_ascii_digit()).collect();
+                parse_timestamp(&digits).ok_or_else(|| format!("Cannot read the date and time '{}'", d))
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+        if !self.windows.is_empty() && timestamps.iter().all(|t| t % 1_000_000 == 0) {
+            return Err("Session windows need intraday timestamps (YYYYMMDDHHMM)".to_string());
+        }
+        Ok(timestamps.iter().map(|&t| self.allows(t)).collect())
+    }
+}
+
+impl FromStr for SessionFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let time = |t: &str| {
+            let digits = t.trim().replace(':', "");
+            match digits.parse::<u32>() {
+                Ok(hhmm) if digits.len() == 4 && hhmm / 100 < 24 && hhmm % 100 < 60 => Ok(hhmm),
+                _ => Err(format!("Invalid time '{}' in session '{}', expected HHMM", t.trim(), s)),
+            }
+        };
+        let windows = s
+            .split(',')
+            .map(|w| match w.split_once('-') {
+                Some((start, end)) => Ok((time(start)?, time(end)?)),
+                None => Err(format!("Invalid session window '{}', expected HHMM-HHMM", w.trim())),
+            })
+            .collect::<Result<Vec<(u32, u32)>, String>>()?;
+        Ok(SessionFilter { windows, skip_weekends: false })
+    }
+}
+
+/// Day of the week of a YYYYMMDD date, 0 for Monday to 6 for Sunday
+fn weekday(date: u32) -> u32 {
+    let (mut y, m, d) = (date / 10_000, date / 100 % 100, date % 100);
+    // Sakamoto's method, which counts from Sunday
+    const OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    if m < 3 {
+        y -= 1;
+    }
+    let sunday_based = (y + y / 4 - y / 100 + y / 400 + OFFSETS[(m as usize).clamp(1, 12) - 1] + d) % 7;
+    (sunday_based + 6) % 7
+}
+
+fn seconds_of_day(timestamp: u64) -> u32 {
+    let t = (timestamp % 1_000_000) as u32;
+    t / 10_000 * 3600 + t / 100 % 100 * 60 + t % 100
+}
+
+fn hhmmss(seconds: u32) -> u32 {
+    seconds / 3600 * 10_000 + seconds / 60 % 60 * 100 + seconds % 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_builds_bars_and_summary() {
+        let mut tracker = SessionTracker::new(300).unwrap();
+        assert!(tracker.on_tick("EURUSD", 20240102093001, 1.10, 2.0).unwrap().is_none());
+        tracker.on_tick("EURUSD", 20240102093459, 1.12, 1.0).unwrap();
+        tracker.on_tick("XAGUSD", 20240102093100, 23.0, 5.0).unwrap();
+        let bar = tracker.on_tick("EURUSD", 20240102093500, 1.09, 1.0).unwrap().unwrap();
+        assert_eq!(bar.timestamp, 20240102093000);
+        assert_eq!((bar.open, bar.high, bar.close, bar.volume), (1.10, 1.12, 1.12, 3.0));
+        assert!(tracker.on_tick("EURUSD", 20240103000000, 1.0, 1.0).is_err());
+        tracker.set_pnl("EURUSD", 12.5);
+
+        let job = EndOfDayJob { close: 170000, history: PathBuf::new(), logs: vec![], keep_logs: 0 };
+        assert!(!job.due(&tracker, 20240102165959));
+        assert!(job.due(&tracker, 20240102170000));
+        assert!(job.due(&tracker, 20240103010000));
+
+        let summary = tracker.end_of_day().unwrap();
+        assert_eq!(summary.date, 20240102);
+        assert_eq!(summary.total_ticks(), 4);
+        assert_eq!(summary.total_pnl(), 12.5);
+        let eur = &summary.symbols["EURUSD"];
+        assert_eq!(eur.bars.len(), 2);
+        assert_eq!((eur.open, eur.high, eur.low, eur.close, eur.volume), (1.10, 1.12, 1.09, 1.09, 4.0));
+        assert!(tracker.end_of_day().is_none());
+        assert!(!job.due(&tracker, 20240103170000));
+        assert!(SessionTracker::new(7).is_err());
+    }
+
+    #[test]
+    fn test_session_filter() {
+        let rth: SessionFilter = "0945-1545".parse().unwrap();
+        assert!(!rth.allows(20240102093000));
+        assert!(rth.allows(20240102094500));
+        assert!(rth.allows(20240102154459));
+        assert!(!rth.allows(20240102154500));
+        // Saturday 2024-01-06, allowed unless weekends are skipped
+        assert!(rth.allows(20240106120000));
+        let weekdays = SessionFilter { skip_weekends: true, ..rth.clone() };
+        assert!(!weekdays.allows(20240106120000) && !weekdays.allows(20240107120000));
+        assert!(weekdays.allows(20240108120000));
+
+        let overnight: SessionFilter = "18:00-17:00".parse().unwrap();
+        assert!(overnight.allows(20240102230000) && overnight.allows(20240102100000));
+        assert!(!overnight.allows(20240102173000));
+        assert!("0945".parse::<SessionFilter>().is_err() && "2500-0100".parse::<SessionFilter>().is_err());
+
+        let dates: Vec<String> = ["2024-01-02 09:30", "2024-01-02 10:00", "2024-01-06 10:00"].iter().map(|d| d.to_string()).collect();
+        assert_eq!(weekdays.mask(&dates), Ok(vec![false, true, false]));
+        assert!(rth.mask(&["20240102".to_string(), "20240103".to_string()]).is_err());
+        assert_eq!(SessionFilter { skip_weekends: true, windows: vec![] }.mask(&["20240106".to_string()]), Ok(vec![false]));
+        assert_eq!(weekday(19700101), 3);
+    }
+
+    #[test]
+    fn test_job_appends_history_and_rotates_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("trader.log");
+        let job = EndOfDayJob {
+            close: 160000,
+            history: dir.path().join("history.csv"),
+            logs: vec![log.clone()],
+            keep_logs: 2,
+        };
+        let mut tracker = SessionTracker::new(60).unwrap();
+        for date in [20240102u64, 20240103, 20240104] {
+            std::fs::write(&log, format!("log of {}", date)).unwrap();
+            tracker.on_tick("XAGUSD", date * 1_000_000 + 100000, 23.0, 1.0).unwrap();
+            assert!(job.run(&mut tracker).unwrap().is_some());
+        }
+
+        let history = std::fs::read_to_string(&job.history).unwrap();
+        assert_eq!(history.lines().count(), 4);
+        assert!(history.lines().nth(3).unwrap().starts_with("20240104,XAGUSD,1,1,"));
+        assert!(!log.exists());
+        assert!(!dir.path().join("trader.log.20240102").exists());
+        let kept = std::fs::read_to_string(dir.path().join("trader.log.20240104")).unwrap();
+        assert_eq!(kept, "log of 20240104");
+        assert!(job.run(&mut tracker).unwrap().is_none());
+    }
+}
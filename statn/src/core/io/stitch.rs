@@ -0,0 +1,215 @@
+use std::str::FromStr;
+
+use super::bars::Bars;
+
+/// Which source wins when history and live data both have a bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Prefer {
+    /// Live bars replace downloaded ones (the feed is the reference)
+    #[default]
+    Live,
+    /// Downloaded bars stay (vendor history is cleaned or adjusted)
+    History,
+    /// The bar with the larger volume, live on a tie (the other one is
+    /// likely partial)
+    Volume,
+}
+
+impl FromStr for Prefer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "live" => Ok(Prefer::Live),
+            "history" => Ok(Prefer::History),
+            "volume" => Ok(Prefer::Volume),
+            _ => Err(format!("Unknown preference '{}' (expected live, history or volume)", s)),
+        }
+    }
+}
+
+/// Options of `stitch`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StitchOptions {
+    pub prefer: Prefer,
+    /// Relative close difference between the two sources on the same bar
+    /// above which the bar is reported as a mismatch
+    pub tolerance: f64,
+    /// Spacing between bars, as a multiple of the median spacing, above
+    /// which a gap is reported
+    pub gap_factor: f64,
+}
+
+impl Default for StitchOptions {
+    fn default() -> Self {
+        StitchOptions { prefer: Prefer::Live, tolerance: 0.001, gap_factor: 5.0 }
+    }
+}
+
+/// Bar both sources have, with closes that disagree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mismatch {
+    pub timestamp: u64,
+    pub history_close: f64,
+    pub live_close: f64,
+}
+
+/// How a stitched series was put together
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StitchReport {
+    pub from_history: usize,
+    pub from_live: usize,
+    /// Timestamps found in both sources
+    pub overlapping: usize,
+    pub mismatches: Vec<Mismatch>,
+    /// Consecutive timestamps further apart than `gap_factor` times the
+    /// median spacing
+    pub gaps: Vec<(u64, u64)>,
+}
+
+impl StitchReport {
+    /// Whether the sources agree on the overlap and the series has no gaps
+    pub fn is_continuous(&self) -> bool {
+        self.mismatches.is_empty() && self.gaps.is_empty()
+    }
+}
+
+/// Merge downloaded history with live (or backfilled) bars of the same
+/// symbol into one series in time order.
+///
+/// Bars present in both sources are resolved by `options.prefer`; their
+/// closes are compared and disagreements reported, as they point to a
+/// different price adjustment or a bad tick. The merged bars are checked as
+/// `Bars::push_checked` does.
+pub fn stitch(history: &Bars, live: &Bars, options: &StitchOptions) -> Result<(Bars, StitchReport), String> {
+    let mut out = Bars::new();
+    let mut report = StitchReport::default();
+    let (mut h, mut l) = (history.iter().peekable(), live.iter().peekable());
+
+    loop {
+        let (bar, from_live) = match (h.peek().copied(), l.peek().copied()) {
+            (None, None) => break,
+            (Some(a), None) => {
+                h.next();
+                (a, false)
+            }
+            (None, Some(b)) => {
+                l.next();
+                (b, true)
+            }
+            (Some(a), Some(b)) if a.timestamp < b.timestamp => {
+                h.next();
+                (a, false)
+            }
+            (Some(a), Some(b)) if b.timestamp < a.timestamp => {
+                l.next();
+                (b, true)
+            }
+            (Some(a), Some(b)) => {
+                h.next();
+                l.next();
+                report.overlapping += 1;
+                if (a.close - b.close).abs() > options.tolerance * a.close.abs() {
+                    report.mismatches.push(Mismatch { timestamp: a.timestamp, history_close: a.close, live_close: b.close });
+                }
+                let live_wins = match options.prefer {
+                    Prefer::Live => true,
+                    Prefer::History => false,
+                    Prefer::Volume => b.volume >= a.volume,
+                };
+                if live_wins { (b, true) } else { (a, false) }
+            }
+        };
+        out.push_checked(bar)
+            .map_err(|e| format!("{} at {} ({})", e, bar.timestamp, if from_live { "live" } else { "history" }))?;
+        if from_live {
+            report.from_live += 1;
+        } else {
+            report.from_history += 1;
+        }
+    }
+
+    report.gaps = gaps(&out, options.gap_factor);
+    Ok((out, report))
+}
+
+/// Consecutive bars further apart than `factor` times the median spacing
+fn gaps(bars: &Bars, factor: f64) -> Vec<(u64, u64)> {
+    let seconds: Vec<i64> = bars.timestamp.iter().map(|&t| seconds_since_epoch(t)).collect();
+    let mut spacing: Vec<i64> = seconds.windows(2).map(|w| w[1] - w[0]).collect();
+    if spacing.is_empty() {
+        return Vec::new();
+    }
+    spacing.sort_unstable();
+    let limit = factor * spacing[spacing.len() / 2] as f64;
+    seconds
+        .windows(2)
+        .zip(bars.timestamp.windows(2))
+        .filter(|(s, _)| (s[1] - s[0]) as f64 > limit)
+        .map(|(_, t)| (t[0], t[1]))
+        .collect()
+}
+
+/// Seconds since 1970-01-01 of a YYYYMMDDHHMMSS timestamp
+fn seconds_since_epoch(timestamp: u64) -> i64 {
+    let date = (timestamp / 1_000_000) as i64;
+    let (y, m, d) = (date / 10_000, date / 100 % 100, date % 100);
+    // Days from civil (Howard Hinnant's algorithm)
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let t = (timestamp % 1_000_000) as i64;
+    days * 86_400 + t / 10_000 * 3600 + t / 100 % 100 * 60 + t % 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::io::Candle;
+
+    fn bars(rows: &[(u64, f64, f64)]) -> Bars {
+        let mut bars = Bars::new();
+        for &(date, close, volume) in rows {
+            bars.push(Candle { timestamp: date * 1_000_000, open: close, high: close, low: close, close, volume });
+        }
+        bars
+    }
+
+    #[test]
+    fn test_stitch_resolves_overlap() {
+        let history = bars(&[(20240102, 100.0, 10.0), (20240103, 101.0, 10.0), (20240104, 102.0, 10.0)]);
+        let live = bars(&[(20240104, 102.0, 4.0), (20240105, 103.0, 5.0), (20240108, 104.0, 5.0)]);
+
+        let (series, report) = stitch(&history, &live, &StitchOptions::default()).unwrap();
+        assert_eq!(series.close, vec![100.0, 101.0, 102.0, 103.0, 104.0]);
+        assert_eq!(series.volume[2], 4.0);
+        assert_eq!((report.from_history, report.from_live, report.overlapping), (2, 3, 1));
+        // A weekend is not a gap
+        assert!(report.is_continuous());
+
+        let by_volume = StitchOptions { prefer: Prefer::Volume, ..StitchOptions::default() };
+        let (series, report) = stitch(&history, &live, &by_volume).unwrap();
+        assert_eq!(series.volume[2], 10.0);
+        assert_eq!((report.from_history, report.from_live), (3, 2));
+        assert_eq!("History".parse::<Prefer>(), Ok(Prefer::History));
+    }
+
+    #[test]
+    fn test_stitch_reports_discontinuities() {
+        let history = bars(&[(20240102, 100.0, 1.0), (20240103, 101.0, 1.0), (20240104, 102.0, 1.0)]);
+        // Adjusted differently, and a month missing
+        let live = bars(&[(20240104, 51.0, 1.0), (20240205, 52.0, 1.0), (20240206, 53.0, 1.0)]);
+        let (series, report) = stitch(&history, &live, &StitchOptions::default()).unwrap();
+        assert_eq!(series.len(), 5);
+        assert_eq!(report.mismatches, vec![Mismatch { timestamp: 20240104000000, history_close: 102.0, live_close: 51.0 }]);
+        assert_eq!(report.gaps, vec![(20240104000000, 20240205000000)]);
+        assert!(!report.is_continuous());
+
+        let bad = bars(&[(20240105, -1.0, 1.0)]);
+        assert!(stitch(&history, &bad, &StitchOptions::default()).is_err());
+        assert_eq!(seconds_since_epoch(19700102000100), 86_460);
+    }
+}
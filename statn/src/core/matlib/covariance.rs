@@ -0,0 +1,228 @@
+/// Common observations of two series: the series are aligned on their last
+/// value (the most recent bar), and bars where either is NaN are skipped.
+fn pair_overlap<'a>(a: &'a [f64], b: &'a [f64]) -> impl Iterator<Item = (f64, f64)> + 'a {
+    let m = a.len().min(b.len());
+    a[a.len() - m..]
+        .iter()
+        .zip(&b[b.len() - m..])
+        .filter(|(x, y)| !x.is_nan() && !y.is_nan())
+        .map(|(&x, &y)| (x, y))
+}
+
+/// Sample covariance and variances of two series over their common
+/// observations, with the number of observations
+fn pair_moments(a: &[f64], b: &[f64]) -> (f64, f64, f64, usize) {
+    let (mut n, mut mean_a, mut mean_b) = (0, 0.0, 0.0);
+    for (x, y) in pair_overlap(a, b) {
+        n += 1;
+        mean_a += x;
+        mean_b += y;
+    }
+    if n < 2 {
+        return (f64::NAN, f64::NAN, f64::NAN, n);
+    }
+    mean_a /= n as f64;
+    mean_b /= n as f64;
+
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in pair_overlap(a, b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    let denom = (n - 1) as f64;
+    (cov / denom, var_a / denom, var_b / denom, n)
+}
+
+/// Sample covariance matrix of several return series, each pair over the
+/// observations both have (pairwise complete).
+///
+/// Series of unequal lengths are aligned on their last value; NaN marks a
+/// missing observation. Returned row-major: element `(i, j)` is
+/// `[i * n + j]` for `n` series. A pairwise matrix need not be positive
+/// semi-definite; `ledoit_wolf` gives one that is.
+pub fn covariance_matrix<S: AsRef<[f64]>>(series: &[S]) -> Result<Vec<f64>, String> {
+    let n = series.len();
+    let mut cov = vec![0.0; n * n];
+    for i in 0..n {
+        for j in i..n {
+            let (c, _, _, m) = pair_moments(series[i].as_ref(), series[j].as_ref());
+            if m < 2 {
+                return Err(format!("Series {} and {} have {} common observations; need at least 2", i, j, m));
+            }
+            cov[i * n + j] = c;
+            cov[j * n + i] = c;
+        }
+    }
+    Ok(cov)
+}
+
+/// Correlation matrix of several return series, pairwise complete as in
+/// `covariance_matrix`; each correlation uses the variances over the
+/// pair's own common observations.
+pub fn correlation_matrix<S: AsRef<[f64]>>(series: &[S]) -> Result<Vec<f64>, String> {
+    let n = series.len();
+    let mut corr = vec![0.0; n * n];
+    for i in 0..n {
+        corr[i * n + i] = 1.0;
+        for j in i + 1..n {
+            let (c, var_i, var_j, m) = pair_moments(series[i].as_ref(), series[j].as_ref());
+            if m < 2 {
+                return Err(format!("Series {} and {} have {} common observations; need at least 2", i, j, m));
+            }
+            if var_i <= 0.0 || var_j <= 0.0 {
+                return Err(format!("Series {} or {} is constant over their common observations", i, j));
+            }
+            let r = (c / (var_i * var_j).sqrt()).clamp(-1.0, 1.0);
+            corr[i * n + j] = r;
+            corr[j * n + i] = r;
+        }
+    }
+    Ok(corr)
+}
+
+/// Correlation matrix of the row-major `n` by `n` covariance matrix `cov`;
+/// a variable with no variance has zero correlation with the others
+pub fn cov_to_corr(cov: &[f64], n: usize) -> Vec<f64> {
+    let sd: Vec<f64> = (0..n).map(|i| cov[i * n + i].max(0.0).sqrt()).collect();
+    let mut corr = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..n {
+            corr[i * n + j] = if i == j {
+                1.0
+            } else if sd[i] > 0.0 && sd[j] > 0.0 {
+                (cov[i * n + j] / (sd[i] * sd[j])).clamp(-1.0, 1.0)
+            } else {
+                0.0
+            };
+        }
+    }
+    corr
+}
+
+/// Covariance matrix shrunk toward a multiple of the identity
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShrunkCovariance {
+    /// Row-major `n` by `n` matrix
+    pub covariance: Vec<f64>,
+    /// Weight of the target, 0 (the sample covariance) to 1 (the target)
+    pub intensity: f64,
+    /// Observations used: the bars every series has
+    pub n_obs: usize,
+}
+
+/// Ledoit-Wolf shrinkage of the covariance matrix of several return series
+/// toward the identity scaled by their average variance (Ledoit and Wolf,
+/// "A well-conditioned estimator for large-dimensional covariance
+/// matrices", 2004).
+///
+/// The estimator needs complete rows, so it uses the last bars all series
+/// have, less those where any is NaN. The sample covariance divides by the
+/// number of observations, as in the paper. The result is positive definite
+/// whenever the intensity is above 0, however few the observations.
+pub fn ledoit_wolf<S: AsRef<[f64]>>(series: &[S]) -> Result<ShrunkCovariance, String> {
+    let n = series.len();
+    if n == 0 {
+        return Err("No series".to_string());
+    }
+    let len = series.iter().map(|s| s.as_ref().len()).min().unwrap_or(0);
+    let rows: Vec<Vec<f64>> = (0..len)
+        .map(|t| series.iter().map(|s| s.as_ref()[s.as_ref().len() - len + t]).collect::<Vec<f64>>())
+        .filter(|row| row.iter().all(|v| !v.is_nan()))
+        .collect();
+    let n_obs = rows.len();
+    if n_obs < 2 {
+        return Err(format!("{} complete observations; need at least 2", n_obs));
+    }
+
+    // Demeaned observations and their sample covariance
+    let means: Vec<f64> = (0..n).map(|i| rows.iter().map(|r| r[i]).sum::<f64>() / n_obs as f64).collect();
+    let x: Vec<Vec<f64>> = rows.iter().map(|r| r.iter().zip(&means).map(|(v, m)| v - m).collect()).collect();
+    let mut sample = vec![0.0; n * n];
+    for r in &x {
+        for i in 0..n {
+            for j in 0..n {
+                sample[i * n + j] += r[i] * r[j];
+            }
+        }
+    }
+    sample.iter_mut().for_each(|s| *s /= n_obs as f64);
+
+    // Target mu * I, its distance to the sample covariance, and the
+    // variance of the sample covariance (norms scaled by 1 / n)
+    let mu = (0..n).map(|i| sample[i * n + i]).sum::<f64>() / n as f64;
+    let d2 = (0..n * n)
+        .map(|k| {
+            let target = if k / n == k % n { mu } else { 0.0 };
+            (sample[k] - target).powi(2)
+        })
+        .sum::<f64>()
+        / n as f64;
+    let b2_bar = x
+        .iter()
+        .map(|r| (0..n * n).map(|k| (r[k / n] * r[k % n] - sample[k]).powi(2)).sum::<f64>() / n as f64)
+        .sum::<f64>()
+        / (n_obs as f64).powi(2);
+    let b2 = b2_bar.min(d2);
+    let intensity = if d2 > 0.0 { b2 / d2 } else { 1.0 };
+
+    let covariance = (0..n * n)
+        .map(|k| {
+            let target = if k / n == k % n { mu } else { 0.0 };
+            intensity * target + (1.0 - intensity) * sample[k]
+        })
+        .collect();
+    Ok(ShrunkCovariance { covariance, intensity, n_obs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairwise_matrices() {
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = vec![2.0, 4.0, 6.0, 8.0, 10.0];
+        // Aligned on the last value, c overlaps the last four of a and b, less
+        // its missing first value
+        let c = vec![f64::NAN, 3.0, 2.0, 1.0];
+        let cov = covariance_matrix(&[a.clone(), b.clone(), c.clone()]).unwrap();
+        assert!((cov[0] - 2.5).abs() < 1e-12);
+        assert!((cov[1] - 5.0).abs() < 1e-12 && (cov[3] - 5.0).abs() < 1e-12);
+        // a and c over a = 3, 4, 5 and c = 3, 2, 1
+        assert!((cov[2] + 1.0).abs() < 1e-12);
+        assert!((cov[8] - 1.0).abs() < 1e-12);
+
+        let corr = correlation_matrix(&[a.clone(), b, c.clone()]).unwrap();
+        assert!((corr[1] - 1.0).abs() < 1e-12 && (corr[2] + 1.0).abs() < 1e-12);
+        assert_eq!(corr[4], 1.0);
+
+        assert!(covariance_matrix(&[a.clone(), vec![f64::NAN, 1.0, f64::NAN]]).is_err());
+        assert!(correlation_matrix(&[a, vec![3.0; 5]]).is_err());
+
+        let from_cov = cov_to_corr(&[4.0, 2.0, 2.0, 9.0], 2);
+        assert!((from_cov[1] - 1.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_ledoit_wolf() {
+        let a: Vec<f64> = (0..50).map(|t| (t as f64 * 0.7).sin()).collect();
+        let b: Vec<f64> = (0..50).map(|t| (t as f64 * 0.7).sin() * 0.5 + (t as f64 * 1.3).cos() * 0.5).collect();
+        let c: Vec<f64> = (0..40).map(|t| (t as f64 * 2.1).cos()).collect();
+        let shrunk = ledoit_wolf(&[a, b, c]).unwrap();
+        assert_eq!(shrunk.n_obs, 40);
+        assert!(shrunk.intensity > 0.0 && shrunk.intensity <= 1.0);
+        // Symmetric
+        let s = &shrunk.covariance;
+        assert!((s[1] - s[3]).abs() < 1e-15);
+
+        // Many series and few observations: the sample covariance is
+        // singular, the shrunk one is not
+        let few: Vec<Vec<f64>> = (0..6).map(|i| (0..4).map(|t| ((i * 4 + t) as f64 * 0.9).sin()).collect()).collect();
+        let shrunk = ledoit_wolf(&few).unwrap();
+        assert!(shrunk.intensity > 0.0);
+        assert!((0..6).all(|i| shrunk.covariance[i * 6 + i] > 0.0));
+
+        assert!(ledoit_wolf(&[vec![1.0], vec![2.0]]).is_err());
+    }
+}
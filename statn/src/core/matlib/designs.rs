@@ -0,0 +1,309 @@
+use std::str::FromStr;
+
+use super::rands::unifrand;
+
+/// How points in the unit hypercube are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Design {
+    /// Independent uniform random points
+    #[default]
+    Uniform,
+    /// Latin hypercube: each dimension is split into as many equal strata as
+    /// there are points, and each stratum holds exactly one point
+    LatinHypercube,
+    /// Sobol low-discrepancy sequence with a random digital shift
+    Sobol,
+}
+
+impl FromStr for Design {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "uniform" | "random" => Ok(Design::Uniform),
+            "lhs" | "latin" | "latin_hypercube" => Ok(Design::LatinHypercube),
+            "sobol" => Ok(Design::Sobol),
+            _ => Err(format!("Unknown design '{}' (expected uniform, lhs or sobol)", s)),
+        }
+    }
+}
+
+/// Latin hypercube sample of `npoints` points in `ndim` dimensions.
+///
+/// Returned row-major: point `i` is `[i * ndim..(i + 1) * ndim]`.
+pub fn latin_hypercube(npoints: usize, ndim: usize) -> Vec<f64> {
+    latin_hypercube_with(npoints, ndim, &mut unifrand)
+}
+
+/// `latin_hypercube` drawing its uniform numbers from `unif`
+pub fn latin_hypercube_with(npoints: usize, ndim: usize, unif: &mut impl FnMut() -> f64) -> Vec<f64> {
+    let mut points = vec![0.0; npoints * ndim];
+    let mut strata: Vec<usize> = (0..npoints).collect();
+    for d in 0..ndim {
+        shuffle(&mut strata, unif);
+        for (i, &stratum) in strata.iter().enumerate() {
+            points[i * ndim + d] = (stratum as f64 + unif()) / npoints as f64;
+        }
+    }
+    points
+}
+
+/// Fisher-Yates shuffle
+fn shuffle(values: &mut [usize], unif: &mut impl FnMut() -> f64) {
+    for i in (1..values.len()).rev() {
+        let j = ((unif() * (i + 1) as f64) as usize).min(i);
+        values.swap(i, j);
+    }
+}
+
+/// Primitive polynomials and initial direction numbers (Joe and Kuo) for
+/// dimensions 2 and up: (degree, coefficients, m)
+const SOBOL_PARAMS: [(u32, u32, &[u32]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+/// Largest dimension supported by `Sobol`
+pub const SOBOL_MAX_DIM: usize = SOBOL_PARAMS.len() + 1;
+
+const SOBOL_BITS: usize = 32;
+
+/// Sobol sequence generator (Gray-code ordering, 32-bit precision)
+#[derive(Debug, Clone)]
+pub struct Sobol {
+    directions: Vec<[u32; SOBOL_BITS]>,
+    state: Vec<u32>,
+    shift: Vec<u32>,
+    index: u32,
+}
+
+impl Sobol {
+    /// Unshifted sequence, starting at the origin
+    pub fn new(ndim: usize) -> Result<Self, String> {
+        if ndim == 0 || ndim > SOBOL_MAX_DIM {
+            return Err(format!(
+                "Sobol sequence supports 1 to {} dimensions, not {}",
+                SOBOL_MAX_DIM, ndim
+            ));
+        }
+
+        let mut directions = vec![[0u32; SOBOL_BITS]; ndim];
+        for (k, v) in directions[0].iter_mut().enumerate() {
+            *v = 1 << (SOBOL_BITS - 1 - k);
+        }
+        for (d, &(s, a, m)) in SOBOL_PARAMS.iter().take(ndim - 1).enumerate() {
+            let s = s as usize;
+            let v = &mut directions[d + 1];
+            for k in 0..s {
+                v[k] = m[k] << (SOBOL_BITS - 1 - k);
+            }
+            for k in s..SOBOL_BITS {
+                let mut value = v[k - s] ^ (v[k - s] >> s);
+                for i in 1..s {
+                    if (a >> (s - 1 - i)) & 1 == 1 {
+                        value ^= v[k - i];
+                    }
+                }
+                v[k] = value;
+            }
+        }
+
+        Ok(Self {
+            directions,
+            state: vec![0; ndim],
+            shift: vec![0; ndim],
+            index: 0,
+        })
+    }
+
+    /// Sequence with a random digital shift, so repeated runs see different
+    /// points with the same uniformity
+    pub fn shifted(ndim: usize) -> Result<Self, String> {
+        Self::shifted_with(ndim, &mut unifrand)
+    }
+
+    /// `shifted` drawing the shift from `unif`
+    pub fn shifted_with(ndim: usize, unif: &mut impl FnMut() -> f64) -> Result<Self, String> {
+        let mut sobol = Self::new(ndim)?;
+        for shift in sobol.shift.iter_mut() {
+            *shift = (unif() * 4_294_967_296.0) as u32;
+        }
+        Ok(sobol)
+    }
+
+    /// Write the next point into `point`, which has one entry per dimension
+    pub fn fill(&mut self, point: &mut [f64]) {
+        for (d, p) in point.iter_mut().enumerate() {
+            *p = (self.state[d] ^ self.shift[d]) as f64 / 4_294_967_296.0;
+        }
+        // Gray code: flip the direction number of the lowest zero bit of the index
+        let bit = ((!self.index).trailing_zeros() as usize).min(SOBOL_BITS - 1);
+        self.index = self.index.wrapping_add(1);
+        for (state, directions) in self.state.iter_mut().zip(&self.directions) {
+            *state ^= directions[bit];
+        }
+    }
+}
+
+/// Endless source of points from a design.
+///
+/// Latin hypercube points come in batches of `batch` points; each batch is a
+/// complete design, so the first `batch` points are stratified however many
+/// more are drawn.
+#[derive(Debug, Clone)]
+pub struct DesignSampler {
+    design: Design,
+    ndim: usize,
+    batch: usize,
+    sobol: Option<Sobol>,
+    pending: Vec<f64>,
+    next: usize,
+}
+
+impl DesignSampler {
+    pub fn new(design: Design, ndim: usize, batch: usize) -> Result<Self, String> {
+        Self::new_with(design, ndim, batch, &mut unifrand)
+    }
+
+    /// `new` drawing the Sobol shift from `unif`
+    pub fn new_with(design: Design, ndim: usize, batch: usize, unif: &mut impl FnMut() -> f64) -> Result<Self, String> {
+        let sobol = match design {
+            Design::Sobol => Some(Sobol::shifted_with(ndim, unif)?),
+            _ => None,
+        };
+        Ok(Self {
+            design,
+            ndim,
+            batch: batch.max(1),
+            sobol,
+            pending: Vec::new(),
+            next: 0,
+        })
+    }
+
+    /// Write the next point into `point`, which has `ndim` entries
+    pub fn fill(&mut self, point: &mut [f64]) {
+        self.fill_with(point, &mut unifrand)
+    }
+
+    /// `fill` drawing its uniform numbers from `unif`
+    pub fn fill_with(&mut self, point: &mut [f64], unif: &mut impl FnMut() -> f64) {
+        match self.design {
+            Design::Uniform => point.iter_mut().for_each(|p| *p = unif()),
+            Design::Sobol => {
+                if let Some(sobol) = self.sobol.as_mut() {
+                    sobol.fill(point);
+                }
+            }
+            Design::LatinHypercube => {
+                if self.next * self.ndim >= self.pending.len() {
+                    self.pending = latin_hypercube_with(self.batch, self.ndim, unif);
+                    self.next = 0;
+                }
+                let start = self.next * self.ndim;
+                point.copy_from_slice(&self.pending[start..start + self.ndim]);
+                self.next += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every one of `n` equal strata of every dimension holds one point
+    fn stratified(points: &[f64], npoints: usize, ndim: usize) -> bool {
+        (0..ndim).all(|d| {
+            let mut counts = vec![0; npoints];
+            for i in 0..npoints {
+                counts[(points[i * ndim + d] * npoints as f64) as usize] += 1;
+            }
+            counts.iter().all(|&c| c == 1)
+        })
+    }
+
+    #[test]
+    fn test_latin_hypercube() {
+        let points = latin_hypercube(25, 4);
+        assert_eq!(points.len(), 100);
+        assert!(points.iter().all(|&p| (0.0..1.0).contains(&p)));
+        assert!(stratified(&points, 25, 4));
+    }
+
+    #[test]
+    fn test_sobol_known_points() {
+        let mut sobol = Sobol::new(3).unwrap();
+        let mut point = [0.0; 3];
+        let expected = [
+            [0.0, 0.0, 0.0],
+            [0.5, 0.5, 0.5],
+            [0.75, 0.25, 0.25],
+            [0.25, 0.75, 0.75],
+            [0.375, 0.375, 0.625],
+        ];
+        for want in expected {
+            sobol.fill(&mut point);
+            assert_eq!(point, want);
+        }
+        assert!(Sobol::new(SOBOL_MAX_DIM + 1).is_err());
+    }
+
+    #[test]
+    fn test_sobol_stratified() {
+        // The first 2^k points fill each 1/2^k stratum once
+        for shifted in [false, true] {
+            let ndim = SOBOL_MAX_DIM;
+            let mut sobol = if shifted { Sobol::shifted(ndim) } else { Sobol::new(ndim) }.unwrap();
+            let mut points = vec![0.0; 64 * ndim];
+            for i in 0..64 {
+                sobol.fill(&mut points[i * ndim..(i + 1) * ndim]);
+            }
+            assert!(stratified(&points, 64, ndim));
+        }
+    }
+
+    #[test]
+    fn test_sampler_batches() {
+        let mut sampler = DesignSampler::new(Design::LatinHypercube, 2, 10).unwrap();
+        let mut points = vec![0.0; 40];
+        for i in 0..20 {
+            sampler.fill(&mut points[i * 2..(i + 1) * 2]);
+        }
+        assert!(stratified(&points[..20], 10, 2));
+        assert!(stratified(&points[20..], 10, 2));
+
+        // The same source of uniform numbers gives the same points
+        let draw = |design| {
+            let mut state = 0.3_f64;
+            let mut unif = || {
+                state = (state * 997.0).fract();
+                state
+            };
+            let mut sampler = DesignSampler::new_with(design, 3, 8, &mut unif).unwrap();
+            let mut points = vec![0.0; 24];
+            for i in 0..8 {
+                sampler.fill_with(&mut points[i * 3..(i + 1) * 3], &mut unif);
+            }
+            points
+        };
+        for design in [Design::Uniform, Design::LatinHypercube, Design::Sobol] {
+            assert_eq!(draw(design), draw(design));
+        }
+        assert_eq!("Sobol".parse::<Design>(), Ok(Design::Sobol));
+        assert!("grid".parse::<Design>().is_err());
+    }
+}
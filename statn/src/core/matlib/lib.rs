@@ -11,4 +11,7 @@ pub use qsorts::*;
 pub use rands::*;
 pub use linalg::*;
 mod mwc256;
-pub use mwc256::*;
\ No newline at end of file
+pub use mwc256::*;mod covariance;
+pub use covariance::*;
+mod portfolio;
+pub use portfolio::*;
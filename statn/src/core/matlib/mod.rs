@@ -1,5 +1,8 @@
+pub mod designs;
 pub mod overlap;
 pub mod paramcor;
 pub mod qsorts;
 pub mod rands;
-pub mod linalg;
\ No newline at end of file
+pub mod linalg;
+pub mod mwc256;pub mod covariance;
+pub mod portfolio;
@@ -30,6 +30,22 @@ impl Mwc256 {
         }
     }
     
+    /// Internal state (lag table, carry, position), to continue the same
+    /// sequence later with `from_state`
+    pub fn state(&self) -> (Vec<u32>, u32, u8) {
+        (self.q.to_vec(), self.carry, self.i)
+    }
+
+    /// Generator continuing from a state returned by `state`; `None` unless
+    /// `q` holds 256 values
+    pub fn from_state(q: &[u32], carry: u32, i: u8) -> Option<Self> {
+        Some(Self {
+            q: q.try_into().ok()?,
+            carry,
+            i,
+        })
+    }
+
     /// Generate a random u32
     pub fn rand32(&mut self) -> u32 {
         const A: u64 = 809430660;
@@ -89,6 +105,20 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_state_round_trip() {
+        let mut rng = Mwc256::with_seed(7);
+        for _ in 0..300 {
+            rng.rand32();
+        }
+        let (q, carry, i) = rng.state();
+        let mut restored = Mwc256::from_state(&q, carry, i).unwrap();
+        for _ in 0..300 {
+            assert_eq!(rng.rand32(), restored.rand32());
+        }
+        assert!(Mwc256::from_state(&q[1..], carry, i).is_none());
+    }
+
     #[test]
     fn test_different_seeds() {
         let mut rng1 = Mwc256::with_seed(42);
@@ -11,7 +11,7 @@ pub fn ind_targ(
     x_idx: usize, // Index into x array for current price
 ) -> (f64, f64) {
     let start_idx = if x_idx >= lookback - 1 {
-        x_idx - lookback + 1
+        x_idx + 1 - lookback
     } else {
         0
     };
@@ -0,0 +1,225 @@
+use std::str::FromStr;
+
+use super::paramcor::gauss_elimination;
+
+/// How portfolio weights are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WeightMethod {
+    /// Least variance of the portfolio return
+    #[default]
+    MinVariance,
+    /// Greatest expected excess return per unit of risk (the tangency
+    /// portfolio)
+    MaxSharpe,
+    /// Every asset contributes the same share of the portfolio variance
+    RiskParity,
+}
+
+impl FromStr for WeightMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "min-variance" | "min-var" | "minvar" => Ok(WeightMethod::MinVariance),
+            "max-sharpe" | "tangency" => Ok(WeightMethod::MaxSharpe),
+            "risk-parity" | "erc" => Ok(WeightMethod::RiskParity),
+            _ => Err(format!("Unknown weight method '{}' (expected min-variance, max-sharpe or risk-parity)", s)),
+        }
+    }
+}
+
+/// Constraints and inputs of the weight solvers besides the covariance
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WeightOptions {
+    /// No short positions. Risk parity is always long only
+    pub long_only: bool,
+    /// Risk-free return per period, in the units of the expected returns,
+    /// for `MaxSharpe`
+    pub risk_free: f64,
+}
+
+/// Weights summing to 1 of the assets of the row-major covariance matrix
+/// `cov`, with the expected return of each asset in `expected` (used only
+/// by `MaxSharpe`; it may be empty for the other methods).
+pub fn portfolio_weights(method: WeightMethod, cov: &[f64], expected: &[f64], options: &WeightOptions) -> Result<Vec<f64>, String> {
+    match method {
+        WeightMethod::MinVariance => min_variance_weights(cov, options.long_only),
+        WeightMethod::MaxSharpe => max_sharpe_weights(cov, expected, options.risk_free, options.long_only),
+        WeightMethod::RiskParity => risk_parity_weights(cov),
+    }
+}
+
+/// Minimum-variance weights of the assets of the row-major covariance
+/// matrix `cov`
+pub fn min_variance_weights(cov: &[f64], long_only: bool) -> Result<Vec<f64>, String> {
+    let n = matrix_size(cov)?;
+    min_quadratic(cov, &vec![1.0; n], long_only)
+}
+
+/// Weights of the portfolio with the greatest Sharpe ratio, from the
+/// row-major covariance matrix `cov` and the expected returns `expected`
+/// in excess of `risk_free`.
+///
+/// Fails when no portfolio (of long positions, if `long_only`) has a
+/// positive expected excess return.
+pub fn max_sharpe_weights(cov: &[f64], expected: &[f64], risk_free: f64, long_only: bool) -> Result<Vec<f64>, String> {
+    let n = matrix_size(cov)?;
+    if expected.len() != n {
+        return Err(format!("{} expected returns for {} assets", expected.len(), n));
+    }
+    let excess: Vec<f64> = expected.iter().map(|r| r - risk_free).collect();
+    if long_only && excess.iter().all(|&e| e <= 0.0) {
+        return Err("No asset has an expected return above the risk-free rate".to_string());
+    }
+
+    // Least variance for a unit excess return, scaled to weights summing to 1
+    let y = min_quadratic(cov, &excess, long_only)?;
+    let total: f64 = y.iter().sum();
+    if total <= 0.0 {
+        return Err("The tangency portfolio has a negative expected excess return".to_string());
+    }
+    Ok(y.iter().map(|v| v / total).collect())
+}
+
+/// Risk-parity (equal risk contribution) weights of the assets of the
+/// row-major covariance matrix `cov`, by cyclical coordinate descent.
+pub fn risk_parity_weights(cov: &[f64]) -> Result<Vec<f64>, String> {
+    let n = matrix_size(cov)?;
+    if let Some(i) = (0..n).find(|&i| cov[i * n + i] <= 0.0) {
+        return Err(format!("Asset {} has no variance", i));
+    }
+
+    // Each weight solves w_i (cov w)_i = 1 / n given the others
+    let budget = 1.0 / n as f64;
+    let mut w: Vec<f64> = (0..n).map(|i| 1.0 / cov[i * n + i].sqrt()).collect();
+    for _ in 0..MAX_ITERATIONS {
+        let mut change: f64 = 0.0;
+        for i in 0..n {
+            let var = cov[i * n + i];
+            let c: f64 = (0..n).filter(|&j| j != i).map(|j| cov[i * n + j] * w[j]).sum();
+            let updated = (-c + (c * c + 4.0 * var * budget).sqrt()) / (2.0 * var);
+            change = change.max((updated - w[i]).abs() / updated);
+            w[i] = updated;
+        }
+        if change < 1e-12 {
+            let total: f64 = w.iter().sum();
+            return Ok(w.iter().map(|v| v / total).collect());
+        }
+    }
+    Err("Risk parity weights did not converge".to_string())
+}
+
+/// Share of the portfolio variance from each asset: `w_i (cov w)_i / w'cov w`
+pub fn risk_contributions(cov: &[f64], weights: &[f64]) -> Vec<f64> {
+    let n = weights.len();
+    let marginal: Vec<f64> = (0..n).map(|i| (0..n).map(|j| cov[i * n + j] * weights[j]).sum()).collect();
+    let variance: f64 = weights.iter().zip(&marginal).map(|(w, m)| w * m).sum();
+    weights.iter().zip(&marginal).map(|(w, m)| w * m / variance).collect()
+}
+
+const MAX_ITERATIONS: usize = 10_000;
+
+/// Size of a square row-major matrix
+fn matrix_size(cov: &[f64]) -> Result<usize, String> {
+    let n = (cov.len() as f64).sqrt().round() as usize;
+    if n == 0 || n * n != cov.len() {
+        return Err(format!("Covariance matrix has {} elements, not a square number", cov.len()));
+    }
+    Ok(n)
+}
+
+/// Minimum of `x'cov x` subject to `a'x = 1`, and `x >= 0` if `long_only`.
+///
+/// Without bounds this is `cov^-1 a / a'cov^-1 a`. With them, an active set:
+/// the most negative free variable is fixed at 0, and a fixed variable is
+/// freed again when the optimality condition `(cov x)_i >= lambda a_i` fails
+/// for it.
+fn min_quadratic(cov: &[f64], a: &[f64], long_only: bool) -> Result<Vec<f64>, String> {
+    let n = a.len();
+    let mut free = vec![true; n];
+    for _ in 0..4 * n + 4 {
+        let idx: Vec<usize> = (0..n).filter(|&i| free[i]).collect();
+        let m = idx.len();
+        let sub: Vec<f64> = idx.iter().flat_map(|&i| idx.iter().map(move |&j| cov[i * n + j])).collect();
+        let a_sub: Vec<f64> = idx.iter().map(|&i| a[i]).collect();
+        let z = gauss_elimination(&sub, &a_sub, m).map_err(|e| format!("Covariance matrix: {}", e))?;
+        let scale: f64 = a_sub.iter().zip(&z).map(|(a, z)| a * z).sum();
+        if scale <= 0.0 {
+            return Err("No portfolio reaches the constraint".to_string());
+        }
+
+        let mut x = vec![0.0; n];
+        for (k, &i) in idx.iter().enumerate() {
+            x[i] = z[k] / scale;
+        }
+        if !long_only {
+            return Ok(x);
+        }
+
+        // Fix the most negative weight at zero
+        let negative = idx.iter().copied().filter(|&i| x[i] < -1e-12).min_by(|&i, &j| x[i].total_cmp(&x[j]));
+        if let Some(i) = negative {
+            free[i] = false;
+            if free.iter().all(|f| !f) {
+                return Err("No long-only portfolio reaches the constraint".to_string());
+            }
+            continue;
+        }
+
+        // Free the fixed weight whose increase lowers the objective most
+        let lambda = 1.0 / scale;
+        let violated = (0..n)
+            .filter(|&i| !free[i])
+            .map(|i| (i, (0..n).map(|j| cov[i * n + j] * x[j]).sum::<f64>() - lambda * a[i]))
+            .filter(|&(_, slack)| slack < -1e-12)
+            .min_by(|p, q| p.1.total_cmp(&q.1));
+        match violated {
+            Some((i, _)) => free[i] = true,
+            None => return Ok(x.iter().map(|v| v.max(0.0)).collect()),
+        }
+    }
+    Err("Long-only weights did not converge".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_variance_and_max_sharpe() {
+        // Uncorrelated assets: weights inversely proportional to variance
+        let cov = [1.0, 0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0];
+        let w = min_variance_weights(&cov, false).unwrap();
+        assert!((w[0] - 2.0 / 3.0).abs() < 1e-12 && (w[1] - 1.0 / 6.0).abs() < 1e-12);
+
+        // Uncorrelated: weights proportional to excess return over variance
+        let w = max_sharpe_weights(&cov, &[0.03, 0.05, 0.01], 0.01, false).unwrap();
+        assert!((w[0] - 2.0 / 3.0).abs() < 1e-12 && (w[1] - 1.0 / 3.0).abs() < 1e-12 && w[2].abs() < 1e-12);
+
+        // A hedge: the unconstrained solution shorts the second asset
+        let cov = [1.0, 0.9, 0.9, 1.0];
+        let w = max_sharpe_weights(&cov, &[0.10, 0.02], 0.0, false).unwrap();
+        assert!(w[1] < 0.0);
+        let w = max_sharpe_weights(&cov, &[0.10, 0.02], 0.0, true).unwrap();
+        assert!((w[0] - 1.0).abs() < 1e-12 && w[1] == 0.0);
+        assert!(max_sharpe_weights(&cov, &[-0.1, -0.2], 0.0, true).is_err());
+
+        let cov = [1.0, 0.5, 0.5, 4.0];
+        let w = min_variance_weights(&cov, true).unwrap();
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12 && w.iter().all(|&v| v >= 0.0));
+        assert!(min_variance_weights(&[1.0, 2.0, 3.0], false).is_err());
+        assert_eq!("risk-parity".parse::<WeightMethod>(), Ok(WeightMethod::RiskParity));
+    }
+
+    #[test]
+    fn test_risk_parity() {
+        let cov = [0.04, 0.006, 0.0, 0.006, 0.09, 0.01, 0.0, 0.01, 0.01];
+        let w = risk_parity_weights(&cov).unwrap();
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        for rc in risk_contributions(&cov, &w) {
+            assert!((rc - 1.0 / 3.0).abs() < 1e-9);
+        }
+        let options = WeightOptions { long_only: true, ..Default::default() };
+        assert_eq!(portfolio_weights(WeightMethod::RiskParity, &cov, &[], &options), Ok(w));
+    }
+}
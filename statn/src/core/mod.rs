@@ -1,5 +1,7 @@
+pub mod bootstrap;
 pub mod data;
 pub mod io;
 pub mod matlib;
 pub mod stats;
+pub mod transforms;
 
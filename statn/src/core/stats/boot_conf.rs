@@ -0,0 +1,247 @@
+//! Bootstrap confidence intervals of any statistic of a sample.
+//!
+//! One set of resamples gives the percentile, pivot, BCa and bootstrap-t
+//! limits at once; the statistic is any closure of the cases. Limits follow
+//! the original C++ convention: the lower limit at fraction p of the sorted
+//! replicates is element `(p (nboot + 1)) - 1`, the upper one the same
+//! distance from the top.
+
+use rand::Rng;
+
+use super::resample::Resampler;
+use super::stats::{inverse_normal_cdf, inverse_t_cdf, normal_cdf};
+
+/// Two-sided limits at 2.5, 5 and 10 percent in each tail
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ConfLimits {
+    pub low2p5: f64,
+    pub high2p5: f64,
+    pub low5: f64,
+    pub high5: f64,
+    pub low10: f64,
+    pub high10: f64,
+}
+
+impl ConfLimits {
+    /// Limits from the (lower, upper) pair at each tail fraction
+    fn from_fn(mut limits: impl FnMut(f64) -> (f64, f64)) -> Self {
+        let (low2p5, high2p5) = limits(0.025);
+        let (low5, high5) = limits(0.05);
+        let (low10, high10) = limits(0.10);
+        ConfLimits { low2p5, high2p5, low5, high5, low10, high10 }
+    }
+
+    /// The limits as a tuple (low2p5, high2p5, low5, high5, low10, high10)
+    pub fn as_tuple(&self) -> (f64, f64, f64, f64, f64, f64) {
+        (self.low2p5, self.high2p5, self.low5, self.high5, self.low10, self.high10)
+    }
+}
+
+/// A statistic of a sample with every kind of bootstrap confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BootstrapResult {
+    /// The statistic of the full sample
+    pub estimate: f64,
+    /// Standard deviation of the bootstrap replicates
+    pub std_error: f64,
+    pub percentile: ConfLimits,
+    /// Percentile limits reflected about the estimate
+    pub pivot: ConfLimits,
+    /// Bias-corrected and accelerated limits
+    pub bca: ConfLimits,
+    /// Estimate plus or minus Student's t with n - 1 degrees of freedom
+    /// times the bootstrap standard error
+    pub t: ConfLimits,
+}
+
+/// Percentile, pivot, BCa and t limits of `user_t` from `nboot` resamples of
+/// `x` drawn by `resampler`. Needs at least 2 cases and 1 resample.
+pub fn boot_conf<R: Rng + ?Sized>(
+    x: &[f64],
+    user_t: impl Fn(&[f64]) -> f64,
+    nboot: usize,
+    resampler: &Resampler,
+    rng: &mut R,
+) -> BootstrapResult {
+    let theta_hat = user_t(x);
+    let work2 = replicates(x, &user_t, nboot, resampler, rng);
+    let percentile = percentile_limits(&work2);
+
+    let mean = work2.iter().sum::<f64>() / nboot as f64;
+    let var = work2.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (nboot.max(2) - 1) as f64;
+    let std_error = var.sqrt();
+    let ndf = (x.len() - 1) as i32;
+
+    BootstrapResult {
+        estimate: theta_hat,
+        std_error,
+        percentile,
+        pivot: ConfLimits {
+            low2p5: 2.0 * theta_hat - percentile.high2p5,
+            high2p5: 2.0 * theta_hat - percentile.low2p5,
+            low5: 2.0 * theta_hat - percentile.high5,
+            high5: 2.0 * theta_hat - percentile.low5,
+            low10: 2.0 * theta_hat - percentile.high10,
+            high10: 2.0 * theta_hat - percentile.low10,
+        },
+        bca: bca_limits(x, &user_t, theta_hat, &work2),
+        t: ConfLimits::from_fn(|alpha| {
+            let half = inverse_t_cdf(ndf, 1.0 - alpha) * std_error;
+            (theta_hat - half, theta_hat + half)
+        }),
+    }
+}
+
+/// Percentile limits of `user_t` from `nboot` resamples of `x` drawn by
+/// `resampler`
+pub fn boot_conf_pctile<R: Rng + ?Sized>(
+    x: &[f64],
+    user_t: impl Fn(&[f64]) -> f64,
+    nboot: usize,
+    resampler: &Resampler,
+    rng: &mut R,
+) -> ConfLimits {
+    percentile_limits(&replicates(x, &user_t, nboot, resampler, rng))
+}
+
+/// BCa limits of `user_t` from `nboot` resamples of `x` drawn by
+/// `resampler`; the acceleration comes from the delete-one jackknife
+pub fn boot_conf_bca<R: Rng + ?Sized>(
+    x: &[f64],
+    user_t: impl Fn(&[f64]) -> f64,
+    nboot: usize,
+    resampler: &Resampler,
+    rng: &mut R,
+) -> ConfLimits {
+    let work2 = replicates(x, &user_t, nboot, resampler, rng);
+    bca_limits(x, &user_t, user_t(x), &work2)
+}
+
+/// `user_t` of each of `nboot` resamples, sorted
+fn replicates<R: Rng + ?Sized>(
+    x: &[f64],
+    user_t: &impl Fn(&[f64]) -> f64,
+    nboot: usize,
+    resampler: &Resampler,
+    rng: &mut R,
+) -> Vec<f64> {
+    let n = x.len();
+    let mut indices = vec![0; n];
+    let mut xwork = vec![0.0; n];
+    let mut work2: Vec<f64> = (0..nboot)
+        .map(|_| {
+            resampler.resample(x, &mut indices, &mut xwork, rng);
+            user_t(&xwork)
+        })
+        .collect();
+    work2.sort_by(f64::total_cmp);
+    work2
+}
+
+/// Index of the lower limit at tail fraction `p` of `nboot` sorted replicates
+fn tail_index(p: f64, nboot: usize) -> usize {
+    let k = (p * (nboot as f64 + 1.0)) as isize - 1;
+    (k.max(0) as usize).min(nboot - 1)
+}
+
+fn percentile_limits(work2: &[f64]) -> ConfLimits {
+    let nboot = work2.len();
+    ConfLimits::from_fn(|p| {
+        let k = tail_index(p, nboot);
+        (work2[k], work2[nboot - 1 - k])
+    })
+}
+
+fn bca_limits(x: &[f64], user_t: &impl Fn(&[f64]) -> f64, theta_hat: f64, work2: &[f64]) -> ConfLimits {
+    let n = x.len();
+    let nboot = work2.len();
+
+    let z0_count = work2.iter().filter(|&&param| param < theta_hat).count().clamp(1, nboot.max(2) - 1);
+    let z0 = inverse_normal_cdf(z0_count as f64 / nboot as f64);
+
+    // Jackknife for accel: replacing case i by the last case, the first n - 1
+    // cases are all but case i
+    let mut x_jk = x.to_vec();
+    let xlast = x[n - 1];
+    let jk_params: Vec<f64> = (0..n)
+        .map(|i| {
+            x_jk[i] = xlast;
+            let param = user_t(&x_jk[0..n - 1]);
+            x_jk[i] = x[i];
+            param
+        })
+        .collect();
+    let theta_dot = jk_params.iter().sum::<f64>() / n as f64;
+    let mut numer = 0.0;
+    let mut denom = 0.0;
+    for param in &jk_params {
+        let diff = theta_dot - param;
+        let xtemp = diff * diff;
+        denom += xtemp;
+        numer += xtemp * diff;
+    }
+    let accel = numer / (6.0 * denom.sqrt().powi(3) + 1e-60);
+
+    ConfLimits::from_fn(|alpha| {
+        let zlo = inverse_normal_cdf(alpha);
+        let zhi = inverse_normal_cdf(1.0 - alpha);
+        let alo = normal_cdf(z0 + (z0 + zlo) / (1.0 - accel * (z0 + zlo)));
+        let ahi = normal_cdf(z0 + (z0 + zhi) / (1.0 - accel * (z0 + zhi)));
+        (work2[tail_index(alo, nboot)], work2[nboot - 1 - tail_index(1.0 - ahi, nboot)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::resample::BootstrapMethod;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn mean(x: &[f64]) -> f64 {
+        x.iter().sum::<f64>() / x.len() as f64
+    }
+
+    #[test]
+    fn test_boot_conf_mean() {
+        let x: Vec<f64> = (0..400).map(|i| ((i * 37) % 101) as f64 / 100.0).collect();
+        let result = boot_conf(&x, mean, 1000, &Resampler::default(), &mut StdRng::seed_from_u64(1));
+        assert_eq!(result.estimate, mean(&x));
+
+        // All four intervals nest by confidence and roughly agree for a mean
+        for limits in [result.percentile, result.pivot, result.bca, result.t] {
+            assert!(limits.low2p5 < limits.low5 && limits.low5 < limits.low10);
+            assert!(limits.low10 < result.estimate && result.estimate < limits.high10);
+            assert!(limits.high10 < limits.high5 && limits.high5 < limits.high2p5);
+            assert!((limits.high5 - limits.low5 - 2.0 * 1.645 * result.std_error).abs() < 0.01, "{:?}", limits);
+        }
+        // The standard error of the mean of a near-uniform sample
+        assert!((result.std_error - 0.29 / 20.0).abs() < 0.003, "{}", result.std_error);
+
+        let pctile = boot_conf_pctile(&x, mean, 1000, &Resampler::default(), &mut StdRng::seed_from_u64(1));
+        assert_eq!(pctile, result.percentile);
+        let bca = boot_conf_bca(&x, mean, 1000, &Resampler::default(), &mut StdRng::seed_from_u64(1));
+        assert_eq!(bca, result.bca);
+    }
+
+    #[test]
+    fn test_boot_conf_closure_and_blocks() {
+        // A closure capturing a threshold; blocks widen the interval of an
+        // autocorrelated series
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut level = 0.0;
+        let x: Vec<f64> = (0..500)
+            .map(|_| {
+                level = 0.9 * level + rng.r#gen::<f64>() - 0.5;
+                level
+            })
+            .collect();
+        let threshold = 0.2;
+        let share_above = |s: &[f64]| s.iter().filter(|&&v| v > threshold).count() as f64 / s.len() as f64;
+        let iid = boot_conf(&x, share_above, 500, &Resampler::default(), &mut StdRng::seed_from_u64(2));
+        let blocks = Resampler::new(BootstrapMethod::Stationary, None, &x);
+        let dependent = boot_conf(&x, share_above, 500, &blocks, &mut StdRng::seed_from_u64(2));
+        assert!(dependent.std_error > 1.5 * iid.std_error, "{} vs {}", dependent.std_error, iid.std_error);
+        assert_eq!(dependent.percentile.as_tuple().0, dependent.percentile.low2p5);
+    }
+}
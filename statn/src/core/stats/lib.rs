@@ -1,3 +1,9 @@
+mod boot_conf;
+mod resample;
+mod rolling;
 mod stats;
 
+pub use boot_conf::*;
+pub use resample::*;
+pub use rolling::*;
 pub use stats::*;
\ No newline at end of file
@@ -1,4 +1,15 @@
+pub mod boot_conf;
+pub mod reality_check;
+pub mod resample;
+pub mod rolling;
 #[allow(clippy::module_inception)]
 pub mod stats;
 
-pub use stats::*;
\ No newline at end of file
+pub use boot_conf::{BootstrapResult, ConfLimits, boot_conf, boot_conf_bca, boot_conf_pctile};
+pub use reality_check::{RealityCheck, RealityCheckResult};
+pub use resample::{BootstrapMethod, Resampler, auto_block_length};
+pub use rolling::{
+    RollingMinMax, RollingQuantile, RollingStats, rolling_max, rolling_mean, rolling_min, rolling_quantile, rolling_std,
+    rolling_zscore,
+};
+pub use stats::*;
@@ -0,0 +1,203 @@
+//! Tests of the best of many systems that account for the search.
+//!
+//! Optimizing over a family of systems and testing only the winner
+//! overstates its significance: the best of many worthless systems looks
+//! good by chance. White's Reality Check and Hansen's Superior Predictive
+//! Ability (SPA) test ask instead whether any system of the family beats the
+//! benchmark, with the null distribution of the best performance drawn by a
+//! stationary bootstrap of all systems' returns at once, which keeps their
+//! correlation and short-term serial dependence.
+//!
+//! Returns are excess returns over the benchmark (for a system that is
+//! otherwise flat, its own returns), laid out as in `cross_validation_mkt`:
+//! one row per system, the cases changing fastest.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use super::resample::stationary_indices;
+
+/// Settings of the bootstrap of a Reality Check or SPA test
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RealityCheck {
+    /// Number of bootstrap resamples
+    pub reps: usize,
+    /// Mean length of the stationary bootstrap's blocks, in cases; 1 resamples
+    /// single cases
+    pub mean_block: f64,
+    pub seed: u64,
+}
+
+impl Default for RealityCheck {
+    fn default() -> Self {
+        RealityCheck { reps: 1000, mean_block: 10.0, seed: 0 }
+    }
+}
+
+/// Outcome of a Reality Check and SPA test over a family of systems
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RealityCheckResult {
+    pub n_systems: usize,
+    pub n_cases: usize,
+    /// System with the highest mean return
+    pub best: usize,
+    pub best_mean: f64,
+    /// White's statistic, the largest mean return times sqrt(n_cases)
+    pub white_statistic: f64,
+    /// White's Reality Check p-value
+    pub white_p_value: f64,
+    /// Hansen's statistic, the largest studentized mean return (at least 0)
+    pub spa_statistic: f64,
+    /// SPA p-values with the null recentered at each system's mean return
+    /// floored at zero (lower), at zero for clearly bad systems only
+    /// (consistent) and at the mean return itself (upper). The consistent
+    /// one is the test; the others bound it.
+    pub spa_p_lower: f64,
+    pub spa_p_consistent: f64,
+    pub spa_p_upper: f64,
+}
+
+impl RealityCheck {
+    /// Test whether the best of `n_systems` systems beats the benchmark.
+    /// `returns` holds `n_systems` rows of the same number of cases.
+    ///
+    /// Fails on an empty or ragged matrix, fewer than 10 cases, fewer than 2
+    /// resamples or a mean block shorter than 1.
+    pub fn test(&self, returns: &[f64], n_systems: usize) -> Result<RealityCheckResult, String> {
+        if n_systems == 0 || returns.is_empty() || !returns.len().is_multiple_of(n_systems) {
+            return Err(format!("{} returns do not form {} equal rows", returns.len(), n_systems));
+        }
+        let n_cases = returns.len() / n_systems;
+        if n_cases < 10 {
+            return Err(format!("At least 10 cases are needed, got {}", n_cases));
+        }
+        if self.reps < 2 || self.mean_block.is_nan() || self.mean_block < 1.0 {
+            return Err(format!(
+                "Invalid bootstrap: {} resamples with mean block {}",
+                self.reps, self.mean_block
+            ));
+        }
+
+        let row = |k: usize| &returns[k * n_cases..(k + 1) * n_cases];
+        let means: Vec<f64> = (0..n_systems).map(|k| row(k).iter().sum::<f64>() / n_cases as f64).collect();
+        let root_n = (n_cases as f64).sqrt();
+
+        // sqrt(n) times each resample's mean less the system's mean
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut indices = vec![0; n_cases];
+        let mut centered = vec![0.0; self.reps * n_systems];
+        for rep in 0..self.reps {
+            stationary_indices(&mut indices, self.mean_block, &mut rng);
+            for (k, &mean) in means.iter().enumerate() {
+                let r = row(k);
+                let resampled = indices.iter().map(|&i| r[i]).sum::<f64>() / n_cases as f64;
+                centered[rep * n_systems + k] = root_n * (resampled - mean);
+            }
+        }
+
+        // White: the largest mean against the largest recentered resample
+        let (best, &best_mean) = means.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+        let white_statistic = root_n * best_mean;
+        let white_exceed = centered
+            .chunks(n_systems)
+            .filter(|rep| rep.iter().copied().fold(f64::NEG_INFINITY, f64::max) >= white_statistic)
+            .count();
+
+        // Hansen: studentized by the bootstrap standard deviation; systems
+        // whose returns never vary carry no information and are left out
+        let omega: Vec<f64> = (0..n_systems)
+            .map(|k| {
+                let ss: f64 = centered.chunks(n_systems).map(|rep| rep[k] * rep[k]).sum();
+                (ss / self.reps as f64).sqrt()
+            })
+            .collect();
+        let spa_statistic = (0..n_systems)
+            .filter(|&k| omega[k] > 0.0)
+            .map(|k| root_n * means[k] / omega[k])
+            .fold(0.0, f64::max);
+        let threshold = -(2.0 * (n_cases as f64).ln().ln()).sqrt();
+        let spa_p = |shift: &dyn Fn(usize) -> f64| {
+            let exceed = centered
+                .chunks(n_systems)
+                .filter(|rep| {
+                    let t = (0..n_systems)
+                        .filter(|&k| omega[k] > 0.0)
+                        .map(|k| (rep[k] + shift(k)) / omega[k])
+                        .fold(0.0, f64::max);
+                    t >= spa_statistic
+                })
+                .count();
+            exceed as f64 / self.reps as f64
+        };
+        // The resamples are recentered at g(mean) instead of the mean by
+        // adding sqrt(n) (mean - g(mean))
+        let spa_p_lower = spa_p(&|k| root_n * means[k].min(0.0));
+        let spa_p_consistent = spa_p(&|k| {
+            if omega[k] > 0.0 && root_n * means[k] / omega[k] < threshold {
+                root_n * means[k]
+            } else {
+                0.0
+            }
+        });
+        let spa_p_upper = spa_p(&|_| 0.0);
+
+        Ok(RealityCheckResult {
+            n_systems,
+            n_cases,
+            best,
+            best_mean,
+            white_statistic,
+            white_p_value: white_exceed as f64 / self.reps as f64,
+            spa_statistic,
+            spa_p_lower,
+            spa_p_consistent,
+            spa_p_upper,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    /// `n_systems` rows of zero-mean noise, the first shifted by `edge`
+    fn family(n_systems: usize, n_cases: usize, edge: f64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(11);
+        (0..n_systems * n_cases)
+            .map(|i| rng.r#gen::<f64>() - 0.5 + if i < n_cases { edge } else { 0.0 })
+            .collect()
+    }
+
+    #[test]
+    fn test_reality_check() {
+        let rc = RealityCheck { reps: 500, mean_block: 4.0, seed: 5 };
+
+        // One system with a real edge among noise
+        let result = rc.test(&family(20, 250, 0.1), 20).unwrap();
+        assert_eq!((result.best, result.n_systems, result.n_cases), (0, 20, 250));
+        assert!(result.white_p_value < 0.05, "{:?}", result);
+        assert!(result.spa_p_consistent < 0.05, "{:?}", result);
+        assert!(result.spa_p_lower <= result.spa_p_consistent && result.spa_p_consistent <= result.spa_p_upper);
+
+        // The best of pure noise is not significant once the search is counted
+        let noise = rc.test(&family(20, 250, 0.0), 20).unwrap();
+        assert!(noise.white_p_value > 0.1 && noise.spa_p_consistent > 0.1, "{:?}", noise);
+        assert_eq!(rc.test(&family(20, 250, 0.0), 20), Ok(noise));
+    }
+
+    #[test]
+    fn test_reality_check_errors_and_flat_systems() {
+        let rc = RealityCheck::default();
+        assert!(rc.test(&[0.0; 25], 2).is_err());
+        assert!(rc.test(&[0.0; 18], 2).is_err());
+        assert!(rc.test(&[], 1).is_err());
+        assert!(RealityCheck { mean_block: 0.5, ..rc }.test(&family(2, 50, 0.0), 2).is_err());
+
+        // A system that is always flat is ignored by SPA
+        let mut returns = family(2, 50, 0.0);
+        returns[..50].fill(0.0);
+        let result = rc.test(&returns, 2).unwrap();
+        assert!(result.spa_statistic.is_finite());
+    }
+}
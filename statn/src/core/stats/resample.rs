@@ -0,0 +1,268 @@
+//! Bootstrap resampling of serially dependent samples.
+//!
+//! Drawing cases independently destroys the autocorrelation of daily
+//! returns and understates the variance of their statistics. The stationary
+//! bootstrap (Politis and Romano, 1994) and the moving-block bootstrap
+//! (Kunsch, 1989) resample whole runs of consecutive cases instead, wrapping
+//! around the end of the sample. Their block length can be chosen from the
+//! sample itself (Politis and White, 2004, as corrected by Patton, Politis
+//! and White, 2009).
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+
+/// How cases are drawn for a bootstrap resample
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BootstrapMethod {
+    /// Each case independently, the classical bootstrap
+    #[default]
+    Iid,
+    /// Blocks of geometric length, each starting at a random case
+    Stationary,
+    /// Blocks of fixed length, each starting at a random case
+    MovingBlock,
+}
+
+impl FromStr for BootstrapMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "iid" => Ok(BootstrapMethod::Iid),
+            "stationary" => Ok(BootstrapMethod::Stationary),
+            "block" | "moving-block" => Ok(BootstrapMethod::MovingBlock),
+            _ => Err(format!("Unknown bootstrap method '{}' (expected iid, stationary or block)", s)),
+        }
+    }
+}
+
+impl fmt::Display for BootstrapMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BootstrapMethod::Iid => "iid",
+            BootstrapMethod::Stationary => "stationary",
+            BootstrapMethod::MovingBlock => "block",
+        })
+    }
+}
+
+/// A bootstrap method with its block length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resampler {
+    pub method: BootstrapMethod,
+    /// Mean block length of the stationary bootstrap, or the block length of
+    /// the moving-block bootstrap (rounded), in cases; unused by `Iid`
+    pub block_length: f64,
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Resampler { method: BootstrapMethod::Iid, block_length: 1.0 }
+    }
+}
+
+impl fmt::Display for Resampler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method {
+            BootstrapMethod::Iid => write!(f, "{}", self.method),
+            _ => write!(f, "{} (block length {:.1})", self.method, self.block_length),
+        }
+    }
+}
+
+impl Resampler {
+    /// `method` with the given block length, or the automatic one for
+    /// `sample` when `block_length` is `None`
+    pub fn new(method: BootstrapMethod, block_length: Option<f64>, sample: &[f64]) -> Self {
+        let block_length = match (method, block_length) {
+            (BootstrapMethod::Iid, _) => 1.0,
+            (_, Some(length)) => length.max(1.0),
+            (_, None) => auto_block_length(sample, method),
+        };
+        Resampler { method, block_length }
+    }
+
+    /// Fill `indices` with the cases of one resample of `0..indices.len()`
+    pub fn indices<R: Rng + ?Sized>(&self, indices: &mut [usize], rng: &mut R) {
+        match self.method {
+            BootstrapMethod::Iid => {
+                let n = indices.len();
+                for index in indices.iter_mut() {
+                    *index = rng.gen_range(0..n);
+                }
+            }
+            BootstrapMethod::Stationary => stationary_indices(indices, self.block_length, rng),
+            BootstrapMethod::MovingBlock => {
+                moving_block_indices(indices, self.block_length.round().max(1.0) as usize, rng)
+            }
+        }
+    }
+
+    /// Fill `resample` with one resample of `sample`, of the same length;
+    /// `indices` is scratch space of that length
+    pub fn resample<R: Rng + ?Sized>(&self, sample: &[f64], indices: &mut [usize], resample: &mut [f64], rng: &mut R) {
+        self.indices(indices, rng);
+        for (r, &i) in resample.iter_mut().zip(indices.iter()) {
+            *r = sample[i];
+        }
+    }
+}
+
+/// Fill `indices` with a stationary bootstrap resample of `0..indices.len()`:
+/// circular blocks of geometric length with mean `mean_block`, each starting
+/// at a random case
+pub fn stationary_indices<R: Rng + ?Sized>(indices: &mut [usize], mean_block: f64, rng: &mut R) {
+    let n = indices.len();
+    if n == 0 {
+        return;
+    }
+    let p_new = 1.0 / mean_block;
+    let mut current = rng.gen_range(0..n);
+    for (i, index) in indices.iter_mut().enumerate() {
+        if i > 0 {
+            current = if rng.r#gen::<f64>() < p_new { rng.gen_range(0..n) } else { (current + 1) % n };
+        }
+        *index = current;
+    }
+}
+
+/// Fill `indices` with a moving-block bootstrap resample of
+/// `0..indices.len()`: circular blocks of `block` cases, each starting at a
+/// random case, the last one cut short
+pub fn moving_block_indices<R: Rng + ?Sized>(indices: &mut [usize], block: usize, rng: &mut R) {
+    let n = indices.len();
+    if n == 0 {
+        return;
+    }
+    for chunk in indices.chunks_mut(block.max(1)) {
+        let start = rng.gen_range(0..n);
+        for (j, index) in chunk.iter_mut().enumerate() {
+            *index = (start + j) % n;
+        }
+    }
+}
+
+/// Block length for `method` that minimizes the mean squared error of the
+/// bootstrap variance of the sample mean, estimated from the sample's
+/// autocovariances with a flat-top lag window. It lies between 1 and the
+/// smaller of 3 sqrt(n) and n / 3; 1 for `Iid`, fewer than 10 cases or a
+/// constant sample.
+pub fn auto_block_length(sample: &[f64], method: BootstrapMethod) -> f64 {
+    let n = sample.len();
+    if method == BootstrapMethod::Iid || n < 10 {
+        return 1.0;
+    }
+    let mean = sample.iter().sum::<f64>() / n as f64;
+    let autocov = |k: usize| {
+        (0..n - k).map(|t| (sample[t] - mean) * (sample[t + k] - mean)).sum::<f64>() / n as f64
+    };
+    let var = autocov(0);
+    if var <= 0.0 {
+        return 1.0;
+    }
+
+    // The first lag followed by k_n insignificant autocorrelations
+    let nf = n as f64;
+    let k_n = 5.max(nf.log10().sqrt().ceil() as usize);
+    let max_lag = (nf.sqrt().ceil() as usize + k_n).min(n - 1);
+    let bound = 2.0 * (nf.log10() / nf).sqrt();
+    let rho: Vec<f64> = (0..=max_lag).map(|k| autocov(k) / var).collect();
+    let insignificant = |m: usize| (m + 1..=(m + k_n).min(max_lag)).all(|k| rho[k].abs() < bound);
+    let m_hat = (0..=max_lag).find(|&m| insignificant(m)).unwrap_or(max_lag);
+    let big_m = (2 * m_hat).min(max_lag);
+
+    // Flat-top weighted sums of the autocovariances, both sides of lag 0
+    let flat_top = |t: f64| {
+        if t <= 0.5 {
+            1.0
+        } else if t <= 1.0 {
+            2.0 * (1.0 - t)
+        } else {
+            0.0
+        }
+    };
+    let (mut g, mut long_run) = (0.0, var);
+    for k in 1..=big_m {
+        let weighted = flat_top(k as f64 / big_m as f64) * autocov(k);
+        g += 2.0 * k as f64 * weighted;
+        long_run += 2.0 * weighted;
+    }
+    let d = match method {
+        BootstrapMethod::MovingBlock => 4.0 / 3.0 * long_run * long_run,
+        _ => 2.0 * long_run * long_run,
+    };
+    if d <= 0.0 {
+        return 1.0;
+    }
+
+    let b = (2.0 * g * g / d).cbrt() * nf.cbrt();
+    b.min(3.0 * nf.sqrt()).min(nf / 3.0).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// AR(1) series with coefficient `phi`
+    fn ar1(n: usize, phi: f64, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut x = 0.0;
+        (0..n)
+            .map(|_| {
+                x = phi * x + rng.r#gen::<f64>() - 0.5;
+                x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_indices() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut indices = vec![0; 1000];
+        stationary_indices(&mut indices, 5.0, &mut rng);
+        assert!(indices.iter().all(|&i| i < 1000));
+        // About one new block in five cases
+        let starts = indices.windows(2).filter(|w| w[1] != (w[0] + 1) % 1000).count();
+        assert!((120..280).contains(&starts), "{} block starts", starts);
+
+        let mut indices = vec![0; 23];
+        moving_block_indices(&mut indices, 5, &mut rng);
+        for chunk in indices.chunks(5) {
+            assert!(chunk.windows(2).all(|w| w[1] == (w[0] + 1) % 23));
+        }
+
+        let resampler = Resampler::new(BootstrapMethod::MovingBlock, Some(4.0), &[]);
+        let sample: Vec<f64> = (0..20).map(|i| i as f64).collect();
+        let (mut idx, mut out) = (vec![0; 20], vec![0.0; 20]);
+        resampler.resample(&sample, &mut idx, &mut out, &mut rng);
+        assert!(out.iter().zip(&idx).all(|(&v, &i)| v == i as f64));
+    }
+
+    #[test]
+    fn test_auto_block_length() {
+        let white = auto_block_length(&ar1(2000, 0.0, 3), BootstrapMethod::Stationary);
+        let persistent = auto_block_length(&ar1(2000, 0.8, 3), BootstrapMethod::Stationary);
+        assert!(white < 3.0, "{}", white);
+        assert!(persistent > 8.0 && persistent <= 3.0 * 2000f64.sqrt(), "{}", persistent);
+        // Fixed blocks are longer than the stationary bootstrap's mean block
+        let moving = auto_block_length(&ar1(2000, 0.8, 3), BootstrapMethod::MovingBlock);
+        assert!(moving > persistent);
+
+        assert_eq!(auto_block_length(&[1.0; 50], BootstrapMethod::Stationary), 1.0);
+        assert_eq!(auto_block_length(&ar1(2000, 0.8, 3), BootstrapMethod::Iid), 1.0);
+        assert_eq!(Resampler::new(BootstrapMethod::Iid, Some(7.0), &[]).block_length, 1.0);
+    }
+
+    #[test]
+    fn test_bootstrap_method_parse() {
+        assert_eq!("Stationary".parse::<BootstrapMethod>(), Ok(BootstrapMethod::Stationary));
+        assert_eq!("block".parse::<BootstrapMethod>(), Ok(BootstrapMethod::MovingBlock));
+        assert_eq!("iid".parse::<BootstrapMethod>(), Ok(BootstrapMethod::Iid));
+        assert!("jackknife".parse::<BootstrapMethod>().is_err());
+        assert_eq!(BootstrapMethod::MovingBlock.to_string().parse(), Ok(BootstrapMethod::MovingBlock));
+    }
+}
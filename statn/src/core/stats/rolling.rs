@@ -0,0 +1,327 @@
+//! Statistics of a moving window, updated one value at a time.
+//!
+//! Every window holds the last `window` values pushed; until it fills, the
+//! statistics are of the values pushed so far. `RollingStats` keeps the mean
+//! and variance in O(1) per value, `RollingMinMax` the extremes in amortized
+//! O(1), and `RollingQuantile` a sorted copy of the window, found by binary
+//! search. The `rolling_*` functions apply them to a whole series and return
+//! one value per full window: element k is the statistic of
+//! `x[k..k + window]`, so the value for the window ending at bar i is element
+//! `i + 1 - window`.
+
+use std::collections::VecDeque;
+
+use super::stats::find_quantile;
+
+/// Mean, variance and z-score of a moving window
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    window: usize,
+    values: VecDeque<f64>,
+    // Sums of deviations from a shift near the mean, so that a large level
+    // does not swamp the variance
+    shift: f64,
+    sum: f64,
+    sum2: f64,
+    since_resum: usize,
+}
+
+impl RollingStats {
+    /// A window of `window` values (at least 1)
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        RollingStats {
+            window,
+            values: VecDeque::with_capacity(window),
+            shift: 0.0,
+            sum: 0.0,
+            sum2: 0.0,
+            since_resum: 0,
+        }
+    }
+
+    /// Add a value, dropping the oldest once the window is full
+    pub fn push(&mut self, x: f64) {
+        if self.values.is_empty() {
+            self.shift = x;
+        }
+        if self.values.len() == self.window {
+            let old = self.values.pop_front().unwrap() - self.shift;
+            self.sum -= old;
+            self.sum2 -= old * old;
+        }
+        self.values.push_back(x);
+        let dev = x - self.shift;
+        self.sum += dev;
+        self.sum2 += dev * dev;
+
+        // Resum once per turn of the window, about its current mean, so that
+        // rounding in the running sums cannot build up over a long series
+        self.since_resum += 1;
+        if self.since_resum >= self.window {
+            self.shift = self.values.iter().sum::<f64>() / self.values.len() as f64;
+            self.sum = self.values.iter().map(|v| v - self.shift).sum();
+            self.sum2 = self.values.iter().map(|v| (v - self.shift).powi(2)).sum();
+            self.since_resum = 0;
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Number of values in the window
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.window
+    }
+
+    /// Mean of the window, 0 when empty
+    pub fn mean(&self) -> f64 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        self.shift + self.sum / self.values.len() as f64
+    }
+
+    /// Sample variance (divisor n - 1) of the window, 0 with fewer than 2
+    /// values
+    pub fn variance(&self) -> f64 {
+        let n = self.values.len();
+        if n < 2 {
+            return 0.0;
+        }
+        let dev = self.sum / n as f64;
+        ((self.sum2 - n as f64 * dev * dev) / (n - 1) as f64).max(0.0)
+    }
+
+    pub fn std(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Standard deviations of `x` from the mean of the window, 0 when the
+    /// window does not vary
+    pub fn zscore(&self, x: f64) -> f64 {
+        let std = self.std();
+        if std > 0.0 {
+            (x - self.mean()) / std
+        } else {
+            0.0
+        }
+    }
+
+    /// Empty the window
+    pub fn clear(&mut self) {
+        *self = RollingStats::new(self.window);
+    }
+}
+
+/// Minimum and maximum of a moving window
+#[derive(Debug, Clone)]
+pub struct RollingMinMax {
+    window: usize,
+    pushed: usize,
+    // (index, value) of the candidates for the extreme, the current one first
+    mins: VecDeque<(usize, f64)>,
+    maxs: VecDeque<(usize, f64)>,
+}
+
+impl RollingMinMax {
+    /// A window of `window` values (at least 1)
+    pub fn new(window: usize) -> Self {
+        RollingMinMax { window: window.max(1), pushed: 0, mins: VecDeque::new(), maxs: VecDeque::new() }
+    }
+
+    /// Add a value, dropping the oldest once the window is full
+    pub fn push(&mut self, x: f64) {
+        let i = self.pushed;
+        self.pushed += 1;
+        while self.mins.back().is_some_and(|&(_, v)| v >= x) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((i, x));
+        while self.maxs.back().is_some_and(|&(_, v)| v <= x) {
+            self.maxs.pop_back();
+        }
+        self.maxs.push_back((i, x));
+
+        let oldest = self.pushed.saturating_sub(self.window);
+        while self.mins.front().is_some_and(|&(j, _)| j < oldest) {
+            self.mins.pop_front();
+        }
+        while self.maxs.front().is_some_and(|&(j, _)| j < oldest) {
+            self.maxs.pop_front();
+        }
+    }
+
+    /// Smallest value in the window, `None` when empty
+    pub fn min(&self) -> Option<f64> {
+        self.mins.front().map(|&(_, v)| v)
+    }
+
+    /// Largest value in the window, `None` when empty
+    pub fn max(&self) -> Option<f64> {
+        self.maxs.front().map(|&(_, v)| v)
+    }
+}
+
+/// Quantiles of a moving window
+#[derive(Debug, Clone)]
+pub struct RollingQuantile {
+    window: usize,
+    values: VecDeque<f64>,
+    sorted: Vec<f64>,
+}
+
+impl RollingQuantile {
+    /// A window of `window` values (at least 1)
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        RollingQuantile { window, values: VecDeque::with_capacity(window), sorted: Vec::with_capacity(window) }
+    }
+
+    /// Add a value, dropping the oldest once the window is full
+    pub fn push(&mut self, x: f64) {
+        if self.values.len() == self.window {
+            let old = self.values.pop_front().unwrap();
+            let k = self.sorted.partition_point(|v| v.total_cmp(&old).is_lt());
+            self.sorted.remove(k);
+        }
+        self.values.push_back(x);
+        let k = self.sorted.partition_point(|v| v.total_cmp(&x).is_lt());
+        self.sorted.insert(k, x);
+    }
+
+    /// Value at fraction `q` of the window, as `find_quantile` picks it; 0
+    /// when empty
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.sorted.is_empty() {
+            return 0.0;
+        }
+        find_quantile(&self.sorted, q)
+    }
+
+    /// The window in ascending order
+    pub fn sorted(&self) -> &[f64] {
+        &self.sorted
+    }
+}
+
+/// Apply `stat` to each full window of `x`; empty if `x` is shorter than the
+/// window
+fn per_window<S>(x: &[f64], window: usize, mut state: S, push: impl Fn(&mut S, f64), stat: impl Fn(&S, f64) -> f64) -> Vec<f64> {
+    let window = window.max(1);
+    let mut out = Vec::with_capacity((x.len() + 1).saturating_sub(window));
+    for (i, &v) in x.iter().enumerate() {
+        push(&mut state, v);
+        if i + 1 >= window {
+            out.push(stat(&state, v));
+        }
+    }
+    out
+}
+
+/// Mean of each full window of `x`
+pub fn rolling_mean(x: &[f64], window: usize) -> Vec<f64> {
+    per_window(x, window, RollingStats::new(window), RollingStats::push, |s, _| s.mean())
+}
+
+/// Sample standard deviation of each full window of `x`
+pub fn rolling_std(x: &[f64], window: usize) -> Vec<f64> {
+    per_window(x, window, RollingStats::new(window), RollingStats::push, |s, _| s.std())
+}
+
+/// Z-score of the last value of each full window of `x` within that window
+pub fn rolling_zscore(x: &[f64], window: usize) -> Vec<f64> {
+    per_window(x, window, RollingStats::new(window), RollingStats::push, |s, v| s.zscore(v))
+}
+
+/// Minimum of each full window of `x`
+pub fn rolling_min(x: &[f64], window: usize) -> Vec<f64> {
+    per_window(x, window, RollingMinMax::new(window), RollingMinMax::push, |s, _| s.min().unwrap())
+}
+
+/// Maximum of each full window of `x`
+pub fn rolling_max(x: &[f64], window: usize) -> Vec<f64> {
+    per_window(x, window, RollingMinMax::new(window), RollingMinMax::push, |s, _| s.max().unwrap())
+}
+
+/// Value at fraction `q` of each full window of `x`
+pub fn rolling_quantile(x: &[f64], window: usize, q: f64) -> Vec<f64> {
+    per_window(x, window, RollingQuantile::new(window), RollingQuantile::push, |s, _| s.quantile(q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series() -> Vec<f64> {
+        (0..200).map(|i| 100.0 + ((i * 37) % 23) as f64 - 0.01 * i as f64).collect()
+    }
+
+    #[test]
+    fn test_rolling_matches_direct() {
+        let x = series();
+        let w = 7;
+        let means = rolling_mean(&x, w);
+        let stds = rolling_std(&x, w);
+        let mins = rolling_min(&x, w);
+        let maxs = rolling_max(&x, w);
+        let medians = rolling_quantile(&x, w, 0.5);
+        assert_eq!(means.len(), x.len() - w + 1);
+
+        for (k, window) in x.windows(w).enumerate() {
+            let mean = window.iter().sum::<f64>() / w as f64;
+            let var = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (w - 1) as f64;
+            let mut sorted = window.to_vec();
+            sorted.sort_by(f64::total_cmp);
+            assert!((means[k] - mean).abs() < 1e-9);
+            assert!((stds[k] - var.sqrt()).abs() < 1e-9);
+            assert_eq!(mins[k], sorted[0]);
+            assert_eq!(maxs[k], sorted[w - 1]);
+            assert_eq!(medians[k], find_quantile(&sorted, 0.5));
+        }
+
+        let z = rolling_zscore(&x, w);
+        assert!((z[5] - (x[5 + w - 1] - means[5]) / stds[5]).abs() < 1e-9);
+        assert!(rolling_mean(&x[..3], 5).is_empty());
+    }
+
+    #[test]
+    fn test_rolling_stats_partial_and_flat() {
+        let mut stats = RollingStats::new(3);
+        assert_eq!((stats.mean(), stats.variance()), (0.0, 0.0));
+        stats.push(2.0);
+        stats.push(4.0);
+        assert!(!stats.is_full());
+        assert_eq!((stats.len(), stats.mean(), stats.variance()), (2, 3.0, 2.0));
+        for _ in 0..10 {
+            stats.push(0.1);
+        }
+        assert!(stats.is_full());
+        assert_eq!((stats.variance(), stats.zscore(5.0)), (0.0, 0.0));
+        stats.clear();
+        assert!(stats.is_empty());
+
+        // A large level does not swamp the variance
+        let mut level = RollingStats::new(3);
+        for i in 0..100_000 {
+            level.push(1e9 + (i % 3) as f64);
+        }
+        assert!((level.variance() - 1.0).abs() < 1e-6);
+
+        let mut extremes = RollingMinMax::new(2);
+        assert_eq!(extremes.min(), None);
+        for v in [3.0, 1.0, 2.0, 5.0] {
+            extremes.push(v);
+        }
+        assert_eq!((extremes.min(), extremes.max()), (Some(2.0), Some(5.0)));
+    }
+}
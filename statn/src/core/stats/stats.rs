@@ -1,5 +1,7 @@
 use std::f64::consts::PI;
 
+use super::rolling::RollingStats;
+
 const QCEPS: f64 = 1e-10;
 const FPMIN: f64 = 1e-30;
 
@@ -1115,6 +1117,106 @@ impl OnlineStats {
     }
 }
 
+// ============================================================================
+// Online Equity Risk
+// ============================================================================
+
+/// Running drawdown and Sharpe ratio of a strategy, updated one return at a
+/// time. Memory is bounded by the rolling window, whatever the length of the
+/// history.
+///
+/// Equity is additive, the sum of the returns so far (log returns or money)
+/// starting from 0, and a drawdown is the running maximum less the equity.
+/// Sharpe ratios are per period; multiply by the square root of the periods
+/// per year to annualize them.
+#[derive(Debug, Clone)]
+pub struct OnlineEquityStats {
+    n: usize,
+    equity: f64,
+    running_max: f64,
+    max_drawdown: f64,
+    mean: f64,
+    sum2: f64,
+    recent: RollingStats,
+}
+
+impl OnlineEquityStats {
+    /// `window` is the number of returns in the rolling Sharpe ratio (at
+    /// least 2)
+    pub fn new(window: usize) -> Self {
+        OnlineEquityStats {
+            n: 0,
+            equity: 0.0,
+            running_max: 0.0,
+            max_drawdown: 0.0,
+            mean: 0.0,
+            sum2: 0.0,
+            recent: RollingStats::new(window.max(2)),
+        }
+    }
+
+    pub fn update(&mut self, ret: f64) {
+        self.n += 1;
+        self.equity += ret;
+        self.running_max = self.running_max.max(self.equity);
+        self.max_drawdown = self.max_drawdown.max(self.running_max - self.equity);
+
+        let delta = ret - self.mean;
+        self.mean += delta / self.n as f64;
+        self.sum2 += delta * (ret - self.mean);
+        self.recent.push(ret);
+    }
+
+    /// Number of returns seen
+    pub fn get_count(&self) -> usize {
+        self.n
+    }
+
+    pub fn get_equity(&self) -> f64 {
+        self.equity
+    }
+
+    /// Highest equity so far, 0 (the start) if never above it
+    pub fn get_running_max(&self) -> f64 {
+        self.running_max
+    }
+
+    pub fn get_drawdown(&self) -> f64 {
+        self.running_max - self.equity
+    }
+
+    pub fn get_max_drawdown(&self) -> f64 {
+        self.max_drawdown
+    }
+
+    /// Mean over standard deviation of all returns so far, 0 with fewer than
+    /// 2 returns or no variation
+    pub fn get_sharpe(&self) -> f64 {
+        if self.n < 2 {
+            return 0.0;
+        }
+        sharpe_of(self.mean, self.sum2 / (self.n - 1) as f64)
+    }
+
+    /// Sharpe ratio of the last `window` returns (all of them until the
+    /// window fills), 0 with fewer than 2 returns or no variation
+    pub fn get_rolling_sharpe(&self) -> f64 {
+        if self.recent.len() < 2 {
+            return 0.0;
+        }
+        sharpe_of(self.recent.mean(), self.recent.variance())
+    }
+}
+
+fn sharpe_of(mean: f64, var: f64) -> f64 {
+    // Treat a variance at the level of rounding in the mean as none
+    if var <= 1e-20 * mean * mean {
+        0.0
+    } else {
+        mean / var.sqrt()
+    }
+}
+
 
 
 /*
@@ -1192,4 +1294,44 @@ mod tests {
         let mean = stats.get_mean();
         assert!((mean[0] - 3.0).abs() < 1e-10);
     }
+
+    #[test]
+    fn test_online_equity_stats() {
+        let returns = [1.0, 2.0, -1.5, -2.0, 1.0, 3.0, -0.5, 0.5];
+        let mut stats = OnlineEquityStats::new(4);
+        for &r in &returns {
+            stats.update(r);
+        }
+        // Equity 1, 3, 1.5, -0.5, 0.5, 3.5, 3, 3.5
+        assert_eq!(stats.get_count(), 8);
+        assert!((stats.get_equity() - 3.5).abs() < 1e-12);
+        assert!((stats.get_running_max() - 3.5).abs() < 1e-12);
+        assert!((stats.get_max_drawdown() - 3.5).abs() < 1e-12);
+        assert!(stats.get_drawdown().abs() < 1e-12);
+
+        let sharpe = |x: &[f64]| {
+            let m = x.iter().sum::<f64>() / x.len() as f64;
+            let v = x.iter().map(|r| (r - m) * (r - m)).sum::<f64>() / (x.len() - 1) as f64;
+            m / v.sqrt()
+        };
+        assert!((stats.get_sharpe() - sharpe(&returns)).abs() < 1e-12);
+        assert!((stats.get_rolling_sharpe() - sharpe(&returns[4..])).abs() < 1e-12);
+
+        // Stays exact over a long stream with a large offset
+        let mut long = OnlineEquityStats::new(3);
+        for i in 0..100_000 {
+            long.update(1e6 + (i % 3) as f64);
+        }
+        let expected = sharpe(&[1e6, 1e6 + 1.0, 1e6 + 2.0]);
+        assert!((long.get_rolling_sharpe() - expected).abs() < 1e-9 * expected);
+
+        let mut flat = OnlineEquityStats::new(5);
+        flat.update(1.0);
+        assert_eq!((flat.get_sharpe(), flat.get_rolling_sharpe()), (0.0, 0.0));
+        let mut tenths = OnlineEquityStats::new(3);
+        for _ in 0..10 {
+            tenths.update(0.1);
+        }
+        assert_eq!((tenths.get_sharpe(), tenths.get_rolling_sharpe()), (0.0, 0.0));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+//! Returns constructed the same way everywhere.
+//!
+//! Return k of a series of n prices is the return from price k to price
+//! k + 1, so there are n - 1 of them. A risk-free (funding) series gives the
+//! simple rate earned per bar, aligned with the prices: `risk_free[k]` is
+//! the rate from bar k to bar k + 1, and excess returns subtract it in the
+//! units of the returns (`ln(1 + rf)` from a log return).
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How a return is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReturnKind {
+    /// `ln(p1 / p0)`, additive over time
+    #[default]
+    Log,
+    /// `p1 / p0 - 1`, additive across positions
+    Simple,
+}
+
+impl ReturnKind {
+    /// Return from price `from` to price `to`
+    pub fn between(&self, from: f64, to: f64) -> f64 {
+        match self {
+            ReturnKind::Log => (to / from).ln(),
+            ReturnKind::Simple => to / from - 1.0,
+        }
+    }
+
+    /// `ret` less the simple risk-free rate `rate` of the same period
+    pub fn excess(&self, ret: f64, rate: f64) -> f64 {
+        match self {
+            ReturnKind::Log => ret - rate.ln_1p(),
+            ReturnKind::Simple => ret - rate,
+        }
+    }
+
+    /// Convention of returns of this kind, for the headers of outputs
+    pub fn convention(&self, excess: bool) -> String {
+        let kind = match self {
+            ReturnKind::Log => "log returns ln(p[k+1]/p[k])",
+            ReturnKind::Simple => "simple returns p[k+1]/p[k]-1",
+        };
+        if excess {
+            format!("{} in excess of the risk-free rate", kind)
+        } else {
+            kind.to_string()
+        }
+    }
+}
+
+impl fmt::Display for ReturnKind {
+    /// The name `from_str` parses
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ReturnKind::Log => "log",
+            ReturnKind::Simple => "simple",
+        })
+    }
+}
+
+impl FromStr for ReturnKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "log" => Ok(ReturnKind::Log),
+            "simple" | "pct" => Ok(ReturnKind::Simple),
+            _ => Err(format!("Unknown return kind '{}' (expected log or simple)", s)),
+        }
+    }
+}
+
+/// Returns of the prices `series`, in excess of `risk_free` if given (see
+/// the module documentation for the alignment).
+///
+/// Fails on a price that is not positive, or a risk-free series of another
+/// length than `series` or with a rate of -100% or less.
+pub fn returns(series: &[f64], kind: ReturnKind, risk_free: Option<&[f64]>) -> Result<Vec<f64>, String> {
+    if let Some(k) = series.iter().position(|&p| !(p > 0.0 && p.is_finite())) {
+        return Err(format!("Price {} at bar {} is not positive", series[k], k + 1));
+    }
+    if let Some(rates) = risk_free {
+        if rates.len() != series.len() {
+            return Err(format!("Risk-free series has {} values for {} prices", rates.len(), series.len()));
+        }
+        if let Some(k) = rates.iter().position(|&r| !(r > -1.0 && r.is_finite())) {
+            return Err(format!("Risk-free rate {} at bar {} is invalid", rates[k], k + 1));
+        }
+    }
+
+    Ok(series
+        .windows(2)
+        .enumerate()
+        .map(|(k, w)| {
+            let ret = kind.between(w[0], w[1]);
+            risk_free.map_or(ret, |rates| kind.excess(ret, rates[k]))
+        })
+        .collect())
+}
+
+/// Simple rate per period of an annual rate in percent, compounded over
+/// `periods_per_year`
+pub fn periodic_rate(annual_pct: f64, periods_per_year: f64) -> f64 {
+    (1.0 + annual_pct / 100.0).powf(1.0 / periods_per_year) - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_returns() {
+        let prices = [100.0, 110.0, 99.0];
+        let log = returns(&prices, ReturnKind::Log, None).unwrap();
+        assert!((log[0] - 1.1_f64.ln()).abs() < 1e-12 && (log[1] - 0.9_f64.ln()).abs() < 1e-12);
+        let simple = returns(&prices, ReturnKind::Simple, None).unwrap();
+        assert!((simple[0] - 0.1).abs() < 1e-12 && (simple[1] + 0.1).abs() < 1e-12);
+
+        let rates = [0.01, 0.02, 0.5];
+        let excess = returns(&prices, ReturnKind::Simple, Some(&rates)).unwrap();
+        assert!((excess[0] - 0.09).abs() < 1e-12 && (excess[1] + 0.12).abs() < 1e-12);
+        let excess = returns(&prices, ReturnKind::Log, Some(&rates)).unwrap();
+        assert!((excess[0] - (1.1_f64 / 1.01).ln()).abs() < 1e-12);
+
+        assert!(returns(&[100.0, 0.0], ReturnKind::Log, None).is_err());
+        assert!(returns(&prices, ReturnKind::Log, Some(&rates[..2])).is_err());
+        assert!(returns(&[1.0], ReturnKind::Log, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_kind_and_rate() {
+        assert_eq!("simple".parse::<ReturnKind>(), Ok(ReturnKind::Simple));
+        assert_eq!(ReturnKind::Log.to_string().parse::<ReturnKind>(), Ok(ReturnKind::Log));
+        assert!("excess".parse::<ReturnKind>().is_err());
+        assert!(ReturnKind::Log.convention(true).ends_with("in excess of the risk-free rate"));
+        assert!((periodic_rate(10.0, 1.0) - 0.1).abs() < 1e-12);
+        assert!(((1.0 + periodic_rate(5.0, 252.0)).powi(252) - 1.05).abs() < 1e-12);
+    }
+}
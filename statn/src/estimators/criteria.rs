@@ -0,0 +1,229 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Value returned for a rejected parameter set, as in the hard `mintrades` gate
+pub const REJECTED: f64 = -1.0e20;
+
+/// Value `CriterionGuard` substitutes for a NaN or infinite criterion: that
+/// of a rejected parameter set, so it never wins a comparison (a NaN loses
+/// every comparison, which lets an optimizer keep a candidate it should drop,
+/// and +inf wins every one)
+pub const NON_FINITE: f64 = REJECTED;
+
+/// How a system with fewer than the minimum number of trades is treated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TradeCountPenalty {
+    /// Reject it outright: the criterion becomes `REJECTED`
+    Hard,
+    /// Subtract `weight * shortfall^2`, where shortfall is the fraction of the
+    /// minimum that is missing. The criterion then still ranks candidates
+    /// below the minimum, so an optimizer can climb toward it.
+    Quadratic(f64),
+}
+
+/// Criterion adjusted for trading activity.
+///
+/// The raw criterion is penalized by `turnover_lambda` per unit of turnover and,
+/// if the system trades fewer than `min_trades` times, by `trade_penalty`.
+/// Both penalties are in the units of the raw criterion, so the caller decides
+/// what "turnover" means (total position change for a total-return criterion,
+/// position change per bar for a per-bar criterion such as a Sharpe ratio).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompositeCriterion {
+    pub min_trades: i32,
+    pub trade_penalty: TradeCountPenalty,
+    pub turnover_lambda: f64,
+}
+
+impl Default for CompositeCriterion {
+    /// No minimum and no turnover penalty: the raw criterion is unchanged
+    fn default() -> Self {
+        Self {
+            min_trades: 0,
+            trade_penalty: TradeCountPenalty::Hard,
+            turnover_lambda: 0.0,
+        }
+    }
+}
+
+impl CompositeCriterion {
+    /// Same penalties with a different minimum number of trades, for criterion
+    /// functions whose minimum is supplied by the optimizer
+    pub fn with_min_trades(&self, min_trades: i32) -> Self {
+        Self { min_trades, ..*self }
+    }
+
+    /// Penalty for making only `ntrades` trades; 0 if the minimum is met
+    pub fn trade_count_penalty(&self, ntrades: i32) -> f64 {
+        if self.min_trades <= 0 || ntrades >= self.min_trades {
+            return 0.0;
+        }
+        match self.trade_penalty {
+            TradeCountPenalty::Hard => f64::INFINITY,
+            TradeCountPenalty::Quadratic(weight) => {
+                let shortfall = (self.min_trades - ntrades.max(0)) as f64 / self.min_trades as f64;
+                weight * shortfall * shortfall
+            }
+        }
+    }
+
+    /// Penalized criterion
+    pub fn apply(&self, value: f64, ntrades: i32, turnover: f64) -> f64 {
+        let trade_penalty = self.trade_count_penalty(ntrades);
+        if trade_penalty.is_infinite() {
+            return REJECTED;
+        }
+        value - self.turnover_lambda * turnover - trade_penalty
+    }
+}
+
+/// Counts of the values seen by a `CriterionGuard`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GuardCounts {
+    pub evaluations: usize,
+    pub nan: usize,
+    pub pos_inf: usize,
+    pub neg_inf: usize,
+}
+
+impl GuardCounts {
+    /// Evaluations replaced by `NON_FINITE`
+    pub fn non_finite(&self) -> usize {
+        self.nan + self.pos_inf + self.neg_inf
+    }
+}
+
+impl fmt::Display for GuardCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} evaluations, {} non-finite", self.evaluations, self.non_finite())?;
+        if self.non_finite() > 0 {
+            write!(
+                f,
+                " ({} NaN, {} +inf, {} -inf) replaced by {:e}",
+                self.nan, self.pos_inf, self.neg_inf, NON_FINITE
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Guard between a criterion and an optimizer: NaN and infinite values
+/// (a zero-variance Sharpe ratio, an overflowing product) become
+/// `NON_FINITE` and are counted, so they are reported instead of silently
+/// steering the search. Safe to share across threads.
+#[derive(Debug, Default)]
+pub struct CriterionGuard {
+    evaluations: AtomicUsize,
+    nan: AtomicUsize,
+    pos_inf: AtomicUsize,
+    neg_inf: AtomicUsize,
+}
+
+impl CriterionGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `value` if finite, otherwise `NON_FINITE`, counting it either way
+    pub fn check(&self, value: f64) -> f64 {
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        let counter = if value.is_nan() {
+            &self.nan
+        } else if value == f64::INFINITY {
+            &self.pos_inf
+        } else if value == f64::NEG_INFINITY {
+            &self.neg_inf
+        } else {
+            return value;
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        NON_FINITE
+    }
+
+    /// `criter` with every value passed through `check`, for any optimizer
+    /// taking a `Fn(&[f64], i32) -> f64` criterion. A parallel optimizer,
+    /// which needs a `Sync` criterion, can call `check` in its own closure.
+    pub fn wrap<'a, F>(&'a self, criter: F) -> impl Fn(&[f64], i32) -> f64 + Copy + 'a
+    where
+        F: Fn(&[f64], i32) -> f64 + Copy + 'a,
+    {
+        move |params: &[f64], mintrades: i32| self.check(criter(params, mintrades))
+    }
+
+    pub fn counts(&self) -> GuardCounts {
+        GuardCounts {
+            evaluations: self.evaluations.load(Ordering::Relaxed),
+            nan: self.nan.load(Ordering::Relaxed),
+            pos_inf: self.pos_inf.load(Ordering::Relaxed),
+            neg_inf: self.neg_inf.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Total absolute change in position over a series of positions, starting flat
+pub fn turnover(positions: &[f64]) -> f64 {
+    let mut prior = 0.0;
+    let mut total = 0.0;
+    for &pos in positions {
+        total += (pos - prior).abs();
+        prior = pos;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_identity() {
+        let crit = CompositeCriterion::default();
+        assert_eq!(crit.apply(1.5, 0, 10.0), 1.5);
+    }
+
+    #[test]
+    fn test_hard_gate() {
+        let crit = CompositeCriterion { min_trades: 10, ..Default::default() };
+        assert_eq!(crit.apply(1.5, 9, 0.0), REJECTED);
+        assert_eq!(crit.apply(1.5, 10, 0.0), 1.5);
+    }
+
+    #[test]
+    fn test_quadratic_penalty_is_smooth() {
+        let crit = CompositeCriterion {
+            min_trades: 10,
+            trade_penalty: TradeCountPenalty::Quadratic(2.0),
+            turnover_lambda: 0.0,
+        };
+        let values: Vec<f64> = (0..=10).map(|n| crit.apply(1.0, n, 0.0)).collect();
+        assert!((values[0] + 1.0).abs() < 1e-12);
+        assert!((values[5] - 0.5).abs() < 1e-12);
+        assert_eq!(values[10], 1.0);
+        // More trades never score worse
+        assert!(values.windows(2).all(|w| w[1] >= w[0]));
+        assert_eq!(crit.with_min_trades(0).apply(1.0, 0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_turnover() {
+        assert_eq!(turnover(&[0.0, 1.0, 1.0, -1.0, 0.0]), 4.0);
+        let crit = CompositeCriterion { turnover_lambda: 0.25, ..Default::default() };
+        assert_eq!(crit.apply(2.0, 0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn test_guard_replaces_non_finite() {
+        let guard = CriterionGuard::new();
+        // Sharpe ratio of a flat equity curve: 0 / 0
+        let sharpe = |p: &[f64], _: i32| p[0] / p[1];
+        let guarded = guard.wrap(sharpe);
+        assert_eq!(guarded(&[1.0, 2.0], 0), 0.5);
+        assert_eq!(guarded(&[0.0, 0.0], 0), NON_FINITE);
+        assert_eq!(guarded(&[1.0, 0.0], 0), NON_FINITE);
+        assert_eq!(guarded(&[-1.0, 0.0], 0), NON_FINITE);
+        assert_eq!(guard.counts(), GuardCounts { evaluations: 4, nan: 1, pos_inf: 1, neg_inf: 1 });
+        assert_eq!(guard.counts().non_finite(), 3);
+        assert!(guard.counts().to_string().starts_with("4 evaluations, 3 non-finite (1 NaN, 1 +inf, 1 -inf)"));
+        assert_eq!(CriterionGuard::new().counts().to_string(), "0 evaluations, 0 non-finite");
+    }
+}
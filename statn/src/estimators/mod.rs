@@ -1,5 +1,8 @@
 pub mod stochastic_bias;
 pub use stochastic_bias::StocBias;
-pub mod brentmax;
-pub mod glob_max;
+#[cfg(feature = "optimizer")]
+pub(crate) mod brentmax;
+pub mod criteria;
+#[cfg(feature = "optimizer")]
+pub(crate) mod glob_max;
 pub mod sensitivity;
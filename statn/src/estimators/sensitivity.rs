@@ -1,5 +1,11 @@
+use std::fmt::Write as _;
 use std::io;
+use std::path::Path;
 
+use serde::Serialize;
+
+use crate::core::io::write::write_file;
+use crate::core::io::Provenance;
 
 /// Configuration for sensitivity analysis
 pub struct SensitivityConfig<'a> {
@@ -13,11 +19,167 @@ pub struct SensitivityConfig<'a> {
     pub high_bounds: &'a [f64],
 }
 
+/// Criterion along one parameter, the others held at their optimal values
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamSweep {
+    /// Index of the parameter
+    pub param: usize,
+    pub integer: bool,
+    /// Optimal value of the parameter
+    pub optimum: f64,
+    /// Parameter values tried, from the lower bound to the upper
+    pub values: Vec<f64>,
+    /// Criterion at each value
+    pub criteria: Vec<f64>,
+}
+
+/// Sensitivity curves of all parameters
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SensitivityResult {
+    pub sweeps: Vec<ParamSweep>,
+    /// Data the criterion was computed on, written with the result if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Evaluate how the criterion varies as each parameter is varied across its
+/// range while the other parameters are held at their optimal values.
+///
+/// Each parameter is tried at `npoints` equally spaced values. For an integer
+/// parameter the values are whole numbers spread over the range. The sweep
+/// draws no random numbers, so a seeded optimization followed by its sweep
+/// replays exactly.
+pub fn sensitivity_sweep<F>(mut criter: F, config: &SensitivityConfig) -> SensitivityResult
+where
+    F: FnMut(&[f64], i32) -> f64,
+{
+    let SensitivityConfig {
+        nvars,
+        nints,
+        npoints,
+        mintrades,
+        best,
+        low_bounds,
+        high_bounds,
+        ..
+    } = *config;
+    let mut params = best.to_vec();
+    let mut sweeps = Vec::with_capacity(nvars);
+
+    for ivar in 0..nvars {
+        // Reset params to optimal values
+        params[..nvars].copy_from_slice(&best[..nvars]);
+
+        let integer = ivar < nints;
+        let values: Vec<f64> = if integer {
+            let label_frac =
+                (high_bounds[ivar] - low_bounds[ivar] + 0.99999999) / (npoints as f64 - 1.0);
+            (0..npoints)
+                .map(|ipoint| ((low_bounds[ivar] + ipoint as f64 * label_frac) as i32) as f64)
+                .collect()
+        } else {
+            let label_frac = (high_bounds[ivar] - low_bounds[ivar]) / (npoints as f64 - 1.0);
+            (0..npoints)
+                .map(|ipoint| low_bounds[ivar] + ipoint as f64 * label_frac)
+                .collect()
+        };
+
+        let criteria = values
+            .iter()
+            .map(|&value| {
+                params[ivar] = value;
+                criter(&params, mintrades)
+            })
+            .collect();
+
+        sweeps.push(ParamSweep {
+            param: ivar,
+            integer,
+            optimum: best[ivar],
+            values,
+            criteria,
+        });
+    }
+
+    SensitivityResult { sweeps, provenance: None }
+}
+
+impl SensitivityResult {
+    /// Curves as text histograms `nres` characters wide at the largest
+    /// criterion, in the format of SENS.LOG
+    pub fn histogram(&self, nres: usize) -> String {
+        let mut buffer = self.provenance.as_ref().map(|p| p.comment()).unwrap_or_default();
+        for sweep in &self.sweeps {
+            if sweep.integer {
+                writeln!(
+                    buffer,
+                    "\n\nSensitivity curve for integer parameter {} (optimum={})",
+                    sweep.param + 1,
+                    (sweep.optimum + 1.0e-10) as i32
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    buffer,
+                    "\n\nSensitivity curve for real parameter {} (optimum={:.4})",
+                    sweep.param + 1,
+                    sweep.optimum
+                )
+                .unwrap();
+            }
+
+            let maxval = sweep.criteria.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let hist_frac = (nres as f64 + 0.9999999) / maxval.abs().max(1.0e-9);
+
+            for (&value, &criterion) in sweep.values.iter().zip(&sweep.criteria) {
+                if sweep.integer {
+                    write!(buffer, "\n{:6}|", value as i32).unwrap();
+                } else {
+                    write!(buffer, "\n{:10.3}|", value).unwrap();
+                }
+                let k = (criterion * hist_frac) as i32;
+                for _ in 0..k {
+                    buffer.push('*');
+                }
+            }
+        }
+        buffer
+    }
+
+    /// One row per point: `param,integer,optimum,value,criterion`, after
+    /// the provenance comment if set
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.provenance.as_ref().map(|p| p.comment() + "\n").unwrap_or_default();
+        csv.push_str("param,integer,optimum,value,criterion\n");
+        for sweep in &self.sweeps {
+            for (value, criterion) in sweep.values.iter().zip(&sweep.criteria) {
+                writeln!(
+                    csv,
+                    "{},{},{},{},{}",
+                    sweep.param, sweep.integer, sweep.optimum, value, criterion
+                )
+                .unwrap();
+            }
+        }
+        csv
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write_file(path, self.to_csv())
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_file(path, json)
+    }
+}
+
 /// Compute and print parameter sensitivity curves
 ///
 /// This function evaluates how the criterion function varies as each parameter
 /// is varied across its range while holding other parameters at their optimal values.
-/// Results are written to SENS.LOG as ASCII histograms.
+/// Results are written to SENS.LOG as ASCII histograms. Use `sensitivity_sweep`
+/// to get the curves as data.
 ///
 /// # Arguments
 /// * `criter` - Criterion function to evaluate. Takes parameters and mintrades.
@@ -25,8 +187,9 @@ pub struct SensitivityConfig<'a> {
 ///
 /// # Returns
 /// `Ok(())` on success, or an IO error if file writing fails
+#[allow(clippy::too_many_arguments)]
 pub fn sensitivity<F>(
-    mut criter: F,
+    criter: F,
     nvars: usize,
     nints: usize,
     npoints: usize,
@@ -40,7 +203,7 @@ pub fn sensitivity<F>(
 where
     F: FnMut(&[f64], i32) -> f64,
 {
-    let SensitivityConfig {
+    let config = SensitivityConfig {
         nvars,
         nints,
         npoints,
@@ -49,87 +212,50 @@ where
         best,
         low_bounds,
         high_bounds,
-    } = config;
-
-    let mut buffer = String::new();
-    let mut params = best.to_vec();
-    let mut vals = vec![0.0; npoints];
-
-    for ivar in 0..nvars {
-        // Reset params to optimal values
-        params[..nvars].copy_from_slice(&best[..nvars]);
-
-        let mut maxval = -1.0e60;
-
-        if ivar < nints {
-            // Integer parameter
-            use std::fmt::Write;
-            writeln!(
-                buffer,
-                "\n\nSensitivity curve for integer parameter {} (optimum={})",
-                ivar + 1,
-                (best[ivar] + 1.0e-10) as i32
-            ).unwrap();
-
-            let label_frac =
-                (high_bounds[ivar] - low_bounds[ivar] + 0.99999999) / (npoints as f64 - 1.0);
+    };
+    let result = sensitivity_sweep(criter, &config);
+    write_file(output_file, result.histogram(nres))
+}
 
-            // Evaluate criterion at each point
-            for (ipoint, val) in vals.iter_mut().enumerate().take(npoints) {
-                let ival = (low_bounds[ivar] + ipoint as f64 * label_frac) as i32;
-                params[ivar] = ival as f64;
-                *val = criter(&params, mintrades);
-                if ipoint == 0 || *val > maxval {
-                    maxval = *val;
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Print histogram
-            let hist_frac = (nres as f64 + 0.9999999) / maxval.abs().max(1.0e-9);
+    #[test]
+    fn test_sweep() {
+        let config = SensitivityConfig {
+            nvars: 2,
+            nints: 1,
+            npoints: 5,
+            nres: 10,
+            mintrades: 0,
+            best: &[4.0, 0.5],
+            low_bounds: &[2.0, 0.0],
+            high_bounds: &[6.0, 1.0],
+        };
+        let result = sensitivity_sweep(|p: &[f64], _| 10.0 - (p[0] - 4.0).abs() - p[1], &config);
 
-            for (ipoint, &val) in vals.iter().enumerate().take(npoints) {
-                let ival = (low_bounds[ivar] + ipoint as f64 * label_frac) as i32;
-                write!(buffer, "\n{:6}|", ival).unwrap();
-                let k = (val * hist_frac) as i32;
-                for _ in 0..k {
-                    write!(buffer, "*").unwrap();
-                }
-            }
-        } else {
-            // Real parameter
-            use std::fmt::Write;
-            writeln!(
-                buffer,
-                "\n\nSensitivity curve for real parameter {} (optimum={:.4})",
-                ivar + 1,
-                best[ivar]
-            ).unwrap();
+        let lookback = &result.sweeps[0];
+        assert!(lookback.integer);
+        assert_eq!(lookback.values, vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(lookback.criteria, vec![7.5, 8.5, 9.5, 8.5, 7.5]);
 
-            let label_frac = (high_bounds[ivar] - low_bounds[ivar]) / (npoints as f64 - 1.0);
+        let real = &result.sweeps[1];
+        assert_eq!(real.values, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(real.criteria, vec![10.0, 9.75, 9.5, 9.25, 9.0]);
 
-            // Evaluate criterion at each point
-            for (ipoint, val) in vals.iter_mut().enumerate().take(npoints) {
-                let rval = low_bounds[ivar] + ipoint as f64 * label_frac;
-                params[ivar] = rval;
-                *val = criter(&params, mintrades);
-                if ipoint == 0 || *val > maxval {
-                    maxval = *val;
-                }
-            }
+        let csv = result.to_csv();
+        assert_eq!(csv.lines().count(), 11);
+        assert_eq!(csv.lines().nth(1), Some("0,true,4,2,7.5"));
 
-            // Print histogram
-            let hist_frac = (nres as f64 + 0.9999999) / maxval.abs().max(1.0e-9);
+        // The largest criterion gets the full width
+        let histogram = result.histogram(10);
+        assert!(histogram.contains("Sensitivity curve for integer parameter 1 (optimum=4)"));
+        assert!(histogram.contains("\n     4|**********"));
+        assert!(histogram.contains("\n     0.000|**********"));
 
-            for (ipoint, &val) in vals.iter().enumerate().take(npoints) {
-                let rval = low_bounds[ivar] + ipoint as f64 * label_frac;
-                write!(buffer, "\n{:10.3}|", rval).unwrap();
-                let k = (val * hist_frac) as i32;
-                for _ in 0..k {
-                    write!(buffer, "*").unwrap();
-                }
-            }
-        }
+        let traced = SensitivityResult { provenance: Some(Provenance::of_series(&[1.0], &[], "x.txt")), ..result };
+        assert!(traced.to_csv().starts_with("# data: hash="));
+        assert_eq!(traced.to_csv().lines().nth(1), Some("param,integer,optimum,value,criterion"));
     }
-
-    crate::core::io::write::write_file(output_file, buffer)
 }
@@ -0,0 +1,171 @@
+//! Common interface of the indicators, for batch and streaming use.
+//!
+//! An `Indicator` takes one bar at a time and returns its value at that bar,
+//! `None` until it has seen enough bars. The same state machine computes a
+//! whole series (`compute`) and follows a live feed (`update`), so the two
+//! cannot drift apart. Indicators of closes alone read only `Bar::close`.
+
+use std::collections::VecDeque;
+
+/// One bar of market data
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Bar {
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+impl Bar {
+    pub fn new(high: f64, low: f64, close: f64) -> Self {
+        Bar { high, low, close }
+    }
+
+    /// A bar with only a close, its high and low equal to it
+    pub fn from_close(close: f64) -> Self {
+        Bar { high: close, low: close, close }
+    }
+}
+
+impl From<f64> for Bar {
+    fn from(close: f64) -> Self {
+        Bar::from_close(close)
+    }
+}
+
+/// Bars of a series of highs, lows and closes of the same length
+pub fn bars(highs: &[f64], lows: &[f64], closes: &[f64]) -> Vec<Bar> {
+    highs
+        .iter()
+        .zip(lows)
+        .zip(closes)
+        .map(|((&high, &low), &close)| Bar { high, low, close })
+        .collect()
+}
+
+/// An indicator updated one bar at a time
+pub trait Indicator {
+    /// Take the next bar and return the indicator at that bar, `None` until
+    /// enough bars have arrived
+    fn update(&mut self, bar: Bar) -> Option<f64>;
+
+    /// Forget every bar, as if newly created
+    fn reset(&mut self);
+
+    /// The indicator at every bar of `bars`, starting afresh, NaN where it
+    /// has no value yet
+    fn compute(&mut self, bars: &[Bar]) -> Vec<f64> {
+        self.reset();
+        bars.iter().map(|&bar| self.update(bar).unwrap_or(f64::NAN)).collect()
+    }
+
+    /// `compute` on a series of closes
+    fn compute_closes(&mut self, closes: &[f64]) -> Vec<f64> {
+        self.reset();
+        closes.iter().map(|&close| self.update(Bar::from_close(close)).unwrap_or(f64::NAN)).collect()
+    }
+}
+
+/// Bars of history a versioned indicator needs: the lookback for the raw
+/// statistic (version 0), twice it for current less prior (version 1), and
+/// `version` times it for current less longer (version > 1)
+pub fn full_lookback(lookback: usize, version: usize) -> usize {
+    match version {
+        0 => lookback,
+        1 => 2 * lookback,
+        _ => version * lookback,
+    }
+}
+
+/// An indicator in one of the versions of the book's studies: the raw value
+/// (0), its change from `lookback` bars earlier (1), or its difference from
+/// the same indicator over the full lookback (greater than 1). The first
+/// value comes at bar `full_lookback(lookback, version) - 1`.
+#[derive(Debug, Clone)]
+pub struct Versioned<I> {
+    current: I,
+    longer: Option<I>,
+    lag: usize,
+    history: VecDeque<f64>,
+}
+
+impl<I: Indicator> Versioned<I> {
+    /// `make(n)` builds the indicator over `n` bars
+    pub fn new(make: impl Fn(usize) -> I, lookback: usize, version: usize) -> Self {
+        Versioned {
+            current: make(lookback),
+            longer: (version > 1).then(|| make(full_lookback(lookback, version))),
+            lag: if version == 1 { lookback } else { 0 },
+            history: VecDeque::with_capacity(lookback + 1),
+        }
+    }
+}
+
+impl<I: Indicator> Indicator for Versioned<I> {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        let current = self.current.update(bar);
+        if let Some(longer) = self.longer.as_mut() {
+            let longer = longer.update(bar);
+            return current.zip(longer).map(|(c, l)| c - l);
+        }
+        let current = current?;
+        if self.lag == 0 {
+            return Some(current);
+        }
+        self.history.push_back(current);
+        if self.history.len() <= self.lag {
+            return None;
+        }
+        let prior = self.history.pop_front().unwrap();
+        Some(current - prior)
+    }
+
+    fn reset(&mut self) {
+        self.current.reset();
+        if let Some(longer) = self.longer.as_mut() {
+            longer.reset();
+        }
+        self.history.clear();
+    }
+}
+
+/// The values of a versioned indicator from its first, at bar
+/// `full_lookback - 1`, to the end of `bars`
+pub fn compute_versioned<I: Indicator>(
+    make: impl Fn(usize) -> I,
+    bars: &[Bar],
+    lookback: usize,
+    version: usize,
+) -> Vec<f64> {
+    let mut indicator = Versioned::new(make, lookback, version);
+    bars.iter().filter_map(|&bar| indicator.update(bar)).collect()
+}
+
+/// Last `capacity` items pushed, oldest first
+#[derive(Debug, Clone)]
+pub(crate) struct Window<T> {
+    capacity: usize,
+    items: VecDeque<T>,
+}
+
+impl<T: Copy> Window<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Window { capacity, items: VecDeque::with_capacity(capacity) }
+    }
+
+    /// Add an item; true once the window holds `capacity` items
+    pub(crate) fn push(&mut self, item: T) -> bool {
+        if self.items.len() == self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back(item);
+        self.capacity > 0 && self.items.len() == self.capacity
+    }
+
+    pub(crate) fn get(&self, i: usize) -> T {
+        self.items[i]
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.items.clear();
+    }
+}
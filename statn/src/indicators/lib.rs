@@ -1,4 +1,7 @@
+pub mod indicator;
 pub mod trend;
 pub mod volatility;
 pub mod oscillators;
 pub mod specs;
+
+pub use indicator::{Bar, Indicator, Versioned, bars, compute_versioned, full_lookback};
@@ -1,5 +1,8 @@
 use std::f64;
 
+use crate::indicator::{Bar, Indicator};
+use crate::trend::ma::Ema;
+
 /// MACD (Moving Average Convergence Divergence) configuration
 #[derive(Debug, Clone, Copy)]
 pub struct MacdConfig {
@@ -45,23 +48,7 @@ pub fn ema(data: &[f64], period: usize) -> Vec<f64> {
         return vec![f64::NAN; data.len()];
     }
 
-    let mut ema_values = vec![f64::NAN; data.len()];
-    let multiplier = 2.0 / (period as f64 + 1.0);
-
-    // Calculate initial SMA for the first EMA value
-    let mut sum = 0.0;
-    for i in 0..period {
-        sum += data[i];
-    }
-    let initial_ema = sum / period as f64;
-    ema_values[period - 1] = initial_ema;
-
-    // Calculate subsequent EMA values
-    for i in period..data.len() {
-        ema_values[i] = (data[i] - ema_values[i - 1]) * multiplier + ema_values[i - 1];
-    }
-
-    ema_values
+    Ema::new(period).compute_closes(data)
 }
 
 /// Calculate MACD indicator
@@ -135,6 +122,40 @@ pub fn macd_histogram(data: &[f64], config: MacdConfig) -> Vec<f64> {
     macd(data, config).histogram
 }
 
+/// MACD histogram one bar at a time, as `macd_histogram` computes it
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(config: MacdConfig) -> Self {
+        Macd {
+            fast: Ema::new(config.fast_period),
+            slow: Ema::new(config.slow_period),
+            signal: Ema::new(config.signal_period),
+        }
+    }
+}
+
+impl Indicator for Macd {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        let fast = self.fast.update(bar);
+        let slow = self.slow.update(bar);
+        let line = fast? - slow?;
+        let signal = self.signal.update(Bar::from_close(line))?;
+        Some(line - signal)
+    }
+
+    fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+        self.signal.reset();
+    }
+}
+
 /// Calculate MACD with default parameters (12, 26, 9)
 ///
 /// # Arguments
@@ -226,6 +247,18 @@ mod tests {
         assert!(valid_count > 0);
     }
 
+    #[test]
+    fn test_macd_streaming() {
+        let prices: Vec<f64> = (0..80).map(|i| 100.0 + (i as f64 * 0.3).sin() * 4.0).collect();
+        let config = MacdConfig { fast_period: 5, slow_period: 12, signal_period: 4 };
+        let batch = macd_histogram(&prices, config);
+        let mut streaming = Macd::new(config);
+        for (i, &p) in prices.iter().enumerate() {
+            let value = streaming.update(Bar::from_close(p));
+            assert_eq!(value.unwrap_or(f64::NAN).to_bits(), batch[i].to_bits());
+        }
+    }
+
     #[test]
     fn test_macd_crossover() {
         // Create data that crosses: down then up
@@ -1,2 +1,3 @@
 pub mod rsi;
 pub mod macd;
+pub mod stochastic;
@@ -1,5 +1,7 @@
 use std::f64;
 
+use crate::indicator::{Bar, Indicator};
+
 /// Calculates the Relative Strength Index (RSI) for a given data slice and period.
 ///
 /// # Arguments
@@ -15,52 +17,68 @@ pub fn rsi(data: &[f64], period: usize) -> Vec<f64> {
         return vec![f64::NAN; data.len()];
     }
 
-    let mut rsi_values = vec![f64::NAN; data.len()];
-    let mut gains = 0.0;
-    let mut losses = 0.0;
-
-    // Calculate initial average gain/loss
-    for i in 1..=period {
-        let change = data[i] - data[i - 1];
-        if change > 0.0 {
-            gains += change;
-        } else {
-            losses -= change;
-        }
-    }
+    Rsi::new(period).compute_closes(data)
+}
 
-    let mut avg_gain = gains / period as f64;
-    let mut avg_loss = losses / period as f64;
+/// Relative Strength Index with Wilder's smoothing, first available after
+/// `period` changes, i.e. at bar `period`
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    prior_close: Option<f64>,
+    changes: usize,
+    avg_gain: f64,
+    avg_loss: f64,
+}
 
-    // First RSI value
-    if avg_loss == 0.0 {
-        rsi_values[period] = 100.0;
-    } else {
-        let rs = avg_gain / avg_loss;
-        rsi_values[period] = 100.0 - (100.0 / (1.0 + rs));
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Rsi { period, prior_close: None, changes: 0, avg_gain: 0.0, avg_loss: 0.0 }
     }
+}
 
-    // Subsequent RSI values using Wilder's Smoothing
-    for i in (period + 1)..data.len() {
-        let change = data[i] - data[i - 1];
-        let (gain, loss) = if change > 0.0 {
-            (change, 0.0)
+impl Indicator for Rsi {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        let prior = self.prior_close.replace(bar.close)?;
+        if self.period == 0 {
+            return None;
+        }
+        let change = bar.close - prior;
+        let p = self.period as f64;
+        if self.changes < self.period {
+            // Sum the first `period` gains and losses, then average them
+            self.changes += 1;
+            if change > 0.0 {
+                self.avg_gain += change;
+            } else {
+                self.avg_loss -= change;
+            }
+            if self.changes < self.period {
+                return None;
+            }
+            self.avg_gain /= p;
+            self.avg_loss /= p;
         } else {
-            (0.0, -change)
-        };
-
-        avg_gain = ((avg_gain * (period as f64 - 1.0)) + gain) / period as f64;
-        avg_loss = ((avg_loss * (period as f64 - 1.0)) + loss) / period as f64;
+            let (gain, loss) = if change > 0.0 {
+                (change, 0.0)
+            } else {
+                (0.0, -change)
+            };
+            self.avg_gain = ((self.avg_gain * (p - 1.0)) + gain) / p;
+            self.avg_loss = ((self.avg_loss * (p - 1.0)) + loss) / p;
+        }
 
-        if avg_loss == 0.0 {
-            rsi_values[i] = 100.0;
+        if self.avg_loss == 0.0 {
+            Some(100.0)
         } else {
-            let rs = avg_gain / avg_loss;
-            rsi_values[i] = 100.0 - (100.0 / (1.0 + rs));
+            let rs = self.avg_gain / self.avg_loss;
+            Some(100.0 - (100.0 / (1.0 + rs)))
         }
     }
 
-    rsi_values
+    fn reset(&mut self) {
+        *self = Rsi::new(self.period);
+    }
 }
 
 #[cfg(test)]
@@ -117,4 +135,15 @@ mod tests {
         let rsi_dec = rsi(&decreasing, 14);
         assert_eq!(rsi_dec[14], 0.0);
     }
+
+    #[test]
+    fn test_rsi_streaming() {
+        let prices: Vec<f64> = (0..40).map(|i| 50.0 + ((i * 11) % 7) as f64 - 0.2 * i as f64).collect();
+        let batch = rsi(&prices, 5);
+        let mut streaming = Rsi::new(5);
+        for (i, &p) in prices.iter().enumerate() {
+            let value = streaming.update(Bar::from_close(p));
+            assert_eq!(value.unwrap_or(f64::NAN).to_bits(), batch[i].to_bits());
+        }
+    }
 }
@@ -0,0 +1,108 @@
+use stats::RollingMinMax;
+
+use crate::indicator::{Bar, Indicator, bars};
+use crate::trend::ma::Sma;
+
+/// Stochastic oscillator lines, from 0 to 100
+#[derive(Debug, Clone)]
+pub struct StochasticOutput {
+    /// %K: where the close lies in the range of the last `k_period` bars
+    pub k: Vec<f64>,
+    /// %D: simple average of the last `d_period` values of %K
+    pub d: Vec<f64>,
+}
+
+/// Stochastic oscillator. `update` returns %K, from bar `k_period - 1`;
+/// %D follows `d_period - 1` bars later. A window without range puts the
+/// close at 50.
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    k_period: usize,
+    seen: usize,
+    highs: RollingMinMax,
+    lows: RollingMinMax,
+    d_line: Sma,
+    d: Option<f64>,
+}
+
+impl Stochastic {
+    pub fn new(k_period: usize, d_period: usize) -> Self {
+        Stochastic {
+            k_period,
+            seen: 0,
+            highs: RollingMinMax::new(k_period),
+            lows: RollingMinMax::new(k_period),
+            d_line: Sma::new(d_period),
+            d: None,
+        }
+    }
+
+    /// %D at the last bar, `None` until available
+    pub fn percent_d(&self) -> Option<f64> {
+        self.d
+    }
+}
+
+impl Indicator for Stochastic {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if self.k_period == 0 {
+            return None;
+        }
+        self.highs.push(bar.high);
+        self.lows.push(bar.low);
+        self.seen = (self.seen + 1).min(self.k_period);
+        if self.seen < self.k_period {
+            return None;
+        }
+
+        let high = self.highs.max().unwrap();
+        let low = self.lows.min().unwrap();
+        let k = if high > low { 100.0 * (bar.close - low) / (high - low) } else { 50.0 };
+        self.d = self.d_line.update(Bar::from_close(k));
+        Some(k)
+    }
+
+    fn reset(&mut self) {
+        self.seen = 0;
+        self.highs = RollingMinMax::new(self.k_period);
+        self.lows = RollingMinMax::new(self.k_period);
+        self.d_line.reset();
+        self.d = None;
+    }
+}
+
+/// %K over `k_period` bars and %D over `d_period` values of it at every
+/// bar, NaN where not yet available
+pub fn stochastic(highs: &[f64], lows: &[f64], closes: &[f64], k_period: usize, d_period: usize) -> StochasticOutput {
+    let mut indicator = Stochastic::new(k_period, d_period);
+    let mut output = StochasticOutput { k: Vec::with_capacity(closes.len()), d: Vec::with_capacity(closes.len()) };
+    for bar in bars(highs, lows, closes) {
+        output.k.push(indicator.update(bar).unwrap_or(f64::NAN));
+        output.d.push(indicator.percent_d().unwrap_or(f64::NAN));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stochastic() {
+        let highs = [10.0, 12.0, 11.0, 13.0, 12.0];
+        let lows = [8.0, 9.0, 9.0, 10.0, 11.0];
+        let closes = [9.0, 11.0, 10.0, 13.0, 11.0];
+        let out = stochastic(&highs, &lows, &closes, 3, 2);
+
+        assert!(out.k[1].is_nan() && out.d[2].is_nan());
+        // Range 8..12: close 10 at 50; range 9..13: close 13 at 100, 11 at 50
+        assert_eq!(out.k[2], 50.0);
+        assert_eq!(out.k[3], 100.0);
+        assert_eq!(out.k[4], 50.0);
+        assert_eq!(out.d[3], 75.0);
+        assert_eq!(out.d[4], 75.0);
+
+        let flat = stochastic(&[5.0; 4], &[5.0; 4], &[5.0; 4], 2, 1);
+        assert_eq!(flat.k[3], 50.0);
+    }
+}
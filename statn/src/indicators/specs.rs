@@ -24,6 +24,25 @@ pub enum IndicatorSpec {
     },
 }
 
+impl IndicatorSpec {
+    /// Short label of the indicator, e.g. `rsi_4_10` for the crossover of the
+    /// 4-bar and 10-bar RSIs
+    pub fn name(&self) -> String {
+        match self {
+            IndicatorSpec::Crossover { type_, short_lookback, long_lookback } => {
+                let type_ = match type_ {
+                    CrossoverType::Ma => "ma",
+                    CrossoverType::Rsi => "rsi",
+                    CrossoverType::Ema => "ema",
+                    CrossoverType::Macd => "macd",
+                    CrossoverType::Roc => "roc",
+                };
+                format!("{}_{}_{}", type_, short_lookback, long_lookback)
+            }
+        }
+    }
+}
+
 /// Computed indicators and targets for a dataset
 #[derive(Debug)]
 pub struct IndicatorData {
@@ -0,0 +1,175 @@
+use crate::indicator::{Bar, Indicator, bars};
+use crate::volatility::atr::true_range;
+
+/// Wilder's directional movement system: the +DI and -DI lines and the ADX
+/// trend strength, each from 0 to 100
+#[derive(Debug, Clone)]
+pub struct AdxOutput {
+    pub plus_di: Vec<f64>,
+    pub minus_di: Vec<f64>,
+    pub adx: Vec<f64>,
+}
+
+/// Average directional index over `period` bars, with Wilder's smoothing.
+/// The directional indicators are available from bar `period`, the ADX,
+/// which `update` returns, from bar `2 * period - 1`.
+#[derive(Debug, Clone)]
+pub struct Adx {
+    period: usize,
+    prior: Option<Bar>,
+    moves: usize,
+    // Wilder sums of the true range and the directional movements
+    tr: f64,
+    plus_dm: f64,
+    minus_dm: f64,
+    di: Option<(f64, f64)>,
+    dx_count: usize,
+    dx_sum: f64,
+    adx: Option<f64>,
+}
+
+impl Adx {
+    pub fn new(period: usize) -> Self {
+        Adx {
+            period,
+            prior: None,
+            moves: 0,
+            tr: 0.0,
+            plus_dm: 0.0,
+            minus_dm: 0.0,
+            di: None,
+            dx_count: 0,
+            dx_sum: 0.0,
+            adx: None,
+        }
+    }
+
+    /// +DI at the last bar, `None` until available
+    pub fn plus_di(&self) -> Option<f64> {
+        self.di.map(|(plus, _)| plus)
+    }
+
+    /// -DI at the last bar, `None` until available
+    pub fn minus_di(&self) -> Option<f64> {
+        self.di.map(|(_, minus)| minus)
+    }
+}
+
+impl Indicator for Adx {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        let prior = self.prior.replace(bar)?;
+        if self.period == 0 {
+            return None;
+        }
+        let p = self.period as f64;
+
+        let up = bar.high - prior.high;
+        let down = prior.low - bar.low;
+        let plus_dm = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus_dm = if down > up && down > 0.0 { down } else { 0.0 };
+        let tr = true_range(bar, Some(prior.close));
+
+        // Sum the first `period` moves, then smooth
+        self.moves += 1;
+        if self.moves <= self.period {
+            self.tr += tr;
+            self.plus_dm += plus_dm;
+            self.minus_dm += minus_dm;
+            if self.moves < self.period {
+                return None;
+            }
+        } else {
+            self.tr = self.tr - self.tr / p + tr;
+            self.plus_dm = self.plus_dm - self.plus_dm / p + plus_dm;
+            self.minus_dm = self.minus_dm - self.minus_dm / p + minus_dm;
+        }
+
+        let (plus_di, minus_di) = if self.tr > 0.0 {
+            (100.0 * self.plus_dm / self.tr, 100.0 * self.minus_dm / self.tr)
+        } else {
+            (0.0, 0.0)
+        };
+        self.di = Some((plus_di, minus_di));
+        let di_sum = plus_di + minus_di;
+        let dx = if di_sum > 0.0 { 100.0 * (plus_di - minus_di).abs() / di_sum } else { 0.0 };
+
+        self.adx = match self.adx {
+            Some(adx) => Some((adx * (p - 1.0) + dx) / p),
+            None => {
+                self.dx_count += 1;
+                self.dx_sum += dx;
+                (self.dx_count == self.period).then(|| self.dx_sum / p)
+            }
+        };
+        self.adx
+    }
+
+    fn reset(&mut self) {
+        *self = Adx::new(self.period);
+    }
+}
+
+/// +DI, -DI and ADX over `period` bars at every bar, NaN where not yet
+/// available
+pub fn adx(highs: &[f64], lows: &[f64], closes: &[f64], period: usize) -> AdxOutput {
+    let mut indicator = Adx::new(period);
+    let n = closes.len();
+    let mut output = AdxOutput {
+        plus_di: Vec::with_capacity(n),
+        minus_di: Vec::with_capacity(n),
+        adx: Vec::with_capacity(n),
+    };
+    for bar in bars(highs, lows, closes) {
+        output.adx.push(indicator.update(bar).unwrap_or(f64::NAN));
+        output.plus_di.push(indicator.plus_di().unwrap_or(f64::NAN));
+        output.minus_di.push(indicator.minus_di().unwrap_or(f64::NAN));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adx_steady_trend() {
+        // Each bar one higher: +DM 1, -DM 0 and true range 1.5, so +DI is
+        // 100 / 1.5, -DI 0 and ADX 100
+        let lows: Vec<f64> = (0..20).map(|i| i as f64).collect();
+        let highs: Vec<f64> = lows.iter().map(|l| l + 1.0).collect();
+        let closes: Vec<f64> = lows.iter().map(|l| l + 0.5).collect();
+        let out = adx(&highs, &lows, &closes, 4);
+
+        assert!(out.plus_di[3].is_nan() && out.adx[6].is_nan());
+        assert!((out.plus_di[4] - 100.0 / 1.5).abs() < 1e-12);
+        assert_eq!(out.minus_di[4], 0.0);
+        assert!((out.adx[7] - 100.0).abs() < 1e-12);
+        assert!((out.adx[19] - 100.0).abs() < 1e-12);
+
+        // Falling the same way mirrors the lines
+        let down = adx(
+            &highs.iter().rev().copied().collect::<Vec<_>>(),
+            &lows.iter().rev().copied().collect::<Vec<_>>(),
+            &closes.iter().rev().copied().collect::<Vec<_>>(),
+            4,
+        );
+        assert!((down.minus_di[10] - out.plus_di[10]).abs() < 1e-12);
+        assert_eq!(down.plus_di[10], 0.0);
+    }
+
+    #[test]
+    fn test_adx_range_bound_is_weak() {
+        let closes: Vec<f64> = (0..200).map(|i| 100.0 + (i as f64 * 0.9).sin() * 2.0).collect();
+        let highs: Vec<f64> = closes.iter().map(|c| c + 0.5).collect();
+        let lows: Vec<f64> = closes.iter().map(|c| c - 0.5).collect();
+        let out = adx(&highs, &lows, &closes, 14);
+        let trending: Vec<f64> = (0..200).map(|i| 100.0 + 0.8 * i as f64).collect();
+        let up = adx(
+            &trending.iter().map(|c| c + 0.5).collect::<Vec<_>>(),
+            &trending.iter().map(|c| c - 0.5).collect::<Vec<_>>(),
+            &trending,
+            14,
+        );
+        assert!(out.adx[199] < 40.0 && up.adx[199] > 90.0, "{} {}", out.adx[199], up.adx[199]);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::indicator::{Bar, Indicator, Window, compute_versioned};
+
+/// Last close less an exponential smoothing of the `lookback - 2` closes
+/// before it, as `matlib::jump` computes it
+#[derive(Debug, Clone)]
+pub struct Jump {
+    lookback: usize,
+    window: Window<f64>,
+}
+
+impl Jump {
+    pub fn new(lookback: usize) -> Self {
+        Jump { lookback, window: Window::new(lookback) }
+    }
+}
+
+impl Indicator for Jump {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if !self.window.push(bar.close) {
+            return None;
+        }
+        let alpha = 2.0 / self.lookback as f64;
+        let mut smoothed = self.window.get(0);
+        for i in 1..(self.lookback - 1) {
+            smoothed = alpha * self.window.get(i) + (1.0 - alpha) * smoothed;
+        }
+        Some(bar.close - smoothed)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Jump of the closes in one of the versions of `Versioned`, from bar
+/// `full_lookback - 1` on
+pub fn compute_jump(closes: &[f64], lookback: usize, full_lookback: usize, version: usize) -> Vec<f64> {
+    debug_assert_eq!(full_lookback, crate::indicator::full_lookback(lookback, version));
+    let bars: Vec<Bar> = closes.iter().map(|&c| Bar::from_close(c)).collect();
+    compute_versioned(Jump::new, &bars, lookback, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matlib::jump;
+
+    #[test]
+    fn test_jump_matches_matlib() {
+        let closes: Vec<f64> = (0..30).map(|i| ((i * 7) % 11) as f64).collect();
+        let values = Jump::new(6).compute_closes(&closes);
+        for (k, &value) in values.iter().enumerate().skip(5) {
+            assert_eq!(value, jump(6, k, &closes));
+        }
+    }
+}
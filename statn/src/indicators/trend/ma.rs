@@ -1,4 +1,11 @@
-use matlib::find_slope;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::indicator::{Bar, Indicator, compute_versioned};
+use crate::trend::slope::TrendSlope;
 
 /// Calculates the Simple Moving Average (SMA) for a given data slice and number of lags.
 ///
@@ -14,23 +21,7 @@ pub fn moving_average(data: &[f64], lags: usize) -> Vec<f64> {
     if lags == 0 || lags > data.len() {
         return vec![f64::NAN; data.len()];
     }
-
-    let mut sma = Vec::with_capacity(data.len());
-    
-    // Pad with NaN for the initial period where we don't have enough data
-    for _ in 0..lags - 1 {
-        sma.push(f64::NAN);
-    }
-
-    let mut sum: f64 = data.iter().take(lags).sum();
-    sma.push(sum / lags as f64);
-
-    for i in lags..data.len() {
-        sum = sum - data[i - lags] + data[i];
-        sma.push(sum / lags as f64);
-    }
-
-    sma
+    Sma::new(lags).compute_closes(data)
 }
 
 /// Calculates the Exponential Moving Average (EMA) for a given data slice and number of lags.
@@ -48,50 +39,253 @@ pub fn exponential_moving_average(data: &[f64], lags: usize) -> Vec<f64> {
     if lags == 0 || lags > data.len() {
         return vec![f64::NAN; data.len()];
     }
+    Ema::new(lags).compute_closes(data)
+}
 
-    let mut ema = Vec::with_capacity(data.len());
-    
-    // Pad with NaN for the initial period
-    for _ in 0..lags - 1 {
-        ema.push(f64::NAN);
+/// Simple moving average of the last `period` closes
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Sma { period, window: VecDeque::with_capacity(period), sum: 0.0 }
+    }
+}
+
+impl Indicator for Sma {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if self.period == 0 {
+            return None;
+        }
+        if self.window.len() == self.period {
+            let old = self.window.pop_front().unwrap();
+            self.sum = self.sum - old + bar.close;
+        } else {
+            self.sum += bar.close;
+        }
+        self.window.push_back(bar.close);
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.sum = 0.0;
+    }
+}
+
+/// Exponential moving average of the closes with multiplier 2 / (period + 1),
+/// started from the simple average of the first `period` closes
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    count: usize,
+    sum: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Ema { period, count: 0, sum: 0.0, value: None }
+    }
+}
+
+impl Indicator for Ema {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if self.period == 0 {
+            return None;
+        }
+        let close = bar.close;
+        self.value = match self.value {
+            Some(prior) => Some((close - prior) * (2.0 / (self.period as f64 + 1.0)) + prior),
+            None => {
+                self.count += 1;
+                self.sum += close;
+                (self.count == self.period).then(|| self.sum / self.period as f64)
+            }
+        };
+        self.value
+    }
+
+    fn reset(&mut self) {
+        *self = Ema::new(self.period);
+    }
+}
+
+/// Calculates the Weighted Moving Average (WMA), the latest of `lags` values
+/// weighted `lags` and the oldest 1.
+///
+/// # Returns
+///
+/// A Vec<f64> containing the WMA values. The first `lags - 1` values are NaN.
+pub fn weighted_moving_average(data: &[f64], lags: usize) -> Vec<f64> {
+    if lags == 0 || lags > data.len() {
+        return vec![f64::NAN; data.len()];
+    }
+    Wma::new(lags).compute_closes(data)
+}
+
+/// Calculates the Hull Moving Average (HMA): the WMA over sqrt(`lags`) bars
+/// of twice the WMA over `lags / 2` less the WMA over `lags`.
+///
+/// # Returns
+///
+/// A Vec<f64> containing the HMA values. The first
+/// `MaType::Hull.lookback(lags) - 1` values are NaN.
+pub fn hull_moving_average(data: &[f64], lags: usize) -> Vec<f64> {
+    if lags == 0 {
+        return vec![f64::NAN; data.len()];
+    }
+    Hma::new(lags).compute_closes(data)
+}
+
+/// Linearly weighted moving average of the last `period` closes
+#[derive(Debug, Clone)]
+pub struct Wma {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+    weighted: f64,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        Wma { period, window: VecDeque::with_capacity(period), sum: 0.0, weighted: 0.0 }
+    }
+}
+
+impl Indicator for Wma {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if self.period == 0 {
+            return None;
+        }
+        let x = bar.close;
+        if self.window.len() == self.period {
+            // Every weight drops by one, which removes the oldest value
+            let old = self.window.pop_front().unwrap();
+            self.weighted = self.weighted - self.sum + self.period as f64 * x;
+            self.sum = self.sum - old + x;
+        } else {
+            self.weighted += (self.window.len() + 1) as f64 * x;
+            self.sum += x;
+        }
+        self.window.push_back(x);
+        let n = self.period as f64;
+        (self.window.len() == self.period).then(|| self.weighted / (n * (n + 1.0) / 2.0))
+    }
+
+    fn reset(&mut self) {
+        *self = Wma::new(self.period);
+    }
+}
+
+/// Hull moving average of the closes over `period` bars
+#[derive(Debug, Clone)]
+pub struct Hma {
+    half: Wma,
+    full: Wma,
+    smooth: Wma,
+}
+
+impl Hma {
+    pub fn new(period: usize) -> Self {
+        Hma {
+            half: Wma::new((period / 2).max(1)),
+            full: Wma::new(period),
+            smooth: Wma::new(hull_root(period)),
+        }
     }
+}
 
-    // Initialize first EMA with SMA
-    let sum: f64 = data.iter().take(lags).sum();
-    let mut current_ema = sum / lags as f64;
-    ema.push(current_ema);
+fn hull_root(period: usize) -> usize {
+    ((period as f64).sqrt() as usize).max(1)
+}
 
-    // Multiplier: 2 / (N + 1)
-    let k = 2.0 / (lags as f64 + 1.0);
+impl Indicator for Hma {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        let half = self.half.update(bar);
+        let full = self.full.update(bar)?;
+        self.smooth.update(Bar::from_close(2.0 * half? - full))
+    }
 
-    for &price in data.iter().skip(lags) {
-        current_ema = (price - current_ema) * k + current_ema;
-        ema.push(current_ema);
+    fn reset(&mut self) {
+        self.half.reset();
+        self.full.reset();
+        self.smooth.reset();
     }
+}
 
-    ema
+/// Kind of moving average
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaType {
+    #[default]
+    Sma,
+    Ema,
+    Wma,
+    Hull,
 }
 
+impl MaType {
+    /// The moving average over `period` at every bar of `data`, NaN before
+    /// its first value
+    pub fn compute(self, data: &[f64], period: usize) -> Vec<f64> {
+        match self {
+            MaType::Sma => moving_average(data, period),
+            MaType::Ema => exponential_moving_average(data, period),
+            MaType::Wma => weighted_moving_average(data, period),
+            MaType::Hull => hull_moving_average(data, period),
+        }
+    }
+
+    /// Bars needed for the first value over `period`
+    pub fn lookback(self, period: usize) -> usize {
+        match self {
+            MaType::Hull => period + hull_root(period) - 1,
+            _ => period,
+        }
+    }
+}
+
+impl FromStr for MaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sma" | "ma" => Ok(MaType::Sma),
+            "ema" => Ok(MaType::Ema),
+            "wma" => Ok(MaType::Wma),
+            "hull" | "hma" => Ok(MaType::Hull),
+            _ => Err(format!("Unknown moving average '{}' (expected sma, ema, wma or hull)", s)),
+        }
+    }
+}
+
+impl fmt::Display for MaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MaType::Sma => "sma",
+            MaType::Ema => "ema",
+            MaType::Wma => "wma",
+            MaType::Hull => "hull",
+        })
+    }
+}
+
+/// Least-squares trend of the closes in one of the versions of
+/// `Versioned`, from bar `full_lookback - 1` on
 pub fn compute_trend(
     closes: &[f64],
     lookback: usize,
     full_lookback: usize,
     version: usize,
 ) -> Vec<f64> {
-    let nprices = closes.len();
-    let nind = nprices - full_lookback + 1;
-    let mut trend = vec![0.0; nind];
-
-    for (i, trd) in trend.iter_mut().enumerate().take(nind) {
-        let k = full_lookback - 1 + i;
-        *trd = match version {
-            0 => find_slope(lookback, closes, k),
-            1 => find_slope(lookback, closes, k) - find_slope(lookback, closes, k - lookback),
-            _ => find_slope(lookback, closes, k) - find_slope(full_lookback, closes, k),
-        };
-    }
-
-    trend
+    debug_assert_eq!(full_lookback, crate::indicator::full_lookback(lookback, version));
+    let bars: Vec<Bar> = closes.iter().map(|&c| Bar::from_close(c)).collect();
+    compute_versioned(TrendSlope::new, &bars, lookback, version)
 }
 
 /// Compute moving average crossover indicators
@@ -130,6 +324,7 @@ pub fn compute_indicators(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use matlib::find_slope;
 
     #[test]
     fn test_moving_average() {
@@ -158,4 +353,65 @@ mod tests {
         let sma = moving_average(&data, lags);
         assert!(sma[0].is_nan());
     }
+
+    #[test]
+    fn test_incremental_matches_batch() {
+        let data: Vec<f64> = (0..40).map(|i| 50.0 + ((i * 13) % 7) as f64 * 0.7).collect();
+        let mut sma = Sma::new(5);
+        let mut ema = Ema::new(5);
+        let batch_sma = moving_average(&data, 5);
+        let batch_ema = exponential_moving_average(&data, 5);
+        for (i, &x) in data.iter().enumerate() {
+            assert_eq!(sma.update(Bar::from_close(x)).unwrap_or(f64::NAN).to_bits(), batch_sma[i].to_bits());
+            assert_eq!(ema.update(Bar::from_close(x)).unwrap_or(f64::NAN).to_bits(), batch_ema[i].to_bits());
+        }
+        // (2 + 3 + 4) / 3, then 3 + (5 - 3) / 2
+        assert_eq!(Ema::new(3).compute_closes(&[2.0, 3.0, 4.0, 5.0])[2..], [3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_weighted_and_hull() {
+        // (1 * 1 + 2 * 2 + 3 * 3) / 6, then (2 + 6 + 15) / 6
+        let wma = weighted_moving_average(&[1.0, 2.0, 3.0, 5.0], 3);
+        assert!(wma[1].is_nan());
+        assert!((wma[2] - 14.0 / 6.0).abs() < 1e-12);
+        assert!((wma[3] - 23.0 / 6.0).abs() < 1e-12);
+
+        let data: Vec<f64> = (0..60).map(|i| 10.0 + ((i * 17) % 13) as f64 * 0.3).collect();
+        let wma = weighted_moving_average(&data, 7);
+        for (k, &value) in wma.iter().enumerate().skip(6) {
+            let direct: f64 = (0..7).map(|j| (7 - j) as f64 * data[k - j]).sum::<f64>() / 28.0;
+            assert!((value - direct).abs() < 1e-9);
+        }
+
+        // A straight line is followed without lag once the Hull average starts
+        let line: Vec<f64> = (0..40).map(|i| 2.0 * i as f64).collect();
+        let hull = hull_moving_average(&line, 9);
+        let first = MaType::Hull.lookback(9) - 1;
+        assert_eq!(first, 10);
+        assert!(hull[first - 1].is_nan());
+        assert!((hull[first] - line[first]).abs() < 1e-9);
+        assert!((hull[39] - line[39]).abs() < 1e-9);
+
+        assert_eq!("HMA".parse::<MaType>(), Ok(MaType::Hull));
+        assert_eq!(MaType::Ema.to_string().parse(), Ok(MaType::Ema));
+        assert!("kama".parse::<MaType>().is_err());
+    }
+
+    #[test]
+    fn test_compute_trend_versions() {
+        let closes: Vec<f64> = (0..60).map(|i| (i as f64 * 0.2).sin() * 3.0 + 0.1 * i as f64).collect();
+        for version in 0..4 {
+            let full = crate::indicator::full_lookback(5, version);
+            let trend = compute_trend(&closes, 5, full, version);
+            assert_eq!(trend.len(), closes.len() - full + 1);
+            let k = full - 1 + 3;
+            let expected = match version {
+                0 => find_slope(5, &closes, k),
+                1 => find_slope(5, &closes, k) - find_slope(5, &closes, k - 5),
+                _ => find_slope(5, &closes, k) - find_slope(full, &closes, k),
+            };
+            assert_eq!(trend[3], expected);
+        }
+    }
 }
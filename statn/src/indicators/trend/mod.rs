@@ -1,3 +1,9 @@
 pub mod ma;
 pub mod macd;
+pub mod slope;
+pub mod jump;
+pub mod adx;
 pub use ma::*;
+pub use slope::TrendSlope;
+pub use jump::{Jump, compute_jump};
+pub use adx::{Adx, AdxOutput, adx};
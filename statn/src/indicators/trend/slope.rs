@@ -0,0 +1,53 @@
+use crate::indicator::{Bar, Indicator, Window};
+
+/// Least-squares slope of the last `lookback` closes per bar, as
+/// `matlib::find_slope` computes it
+#[derive(Debug, Clone)]
+pub struct TrendSlope {
+    lookback: usize,
+    window: Window<f64>,
+}
+
+impl TrendSlope {
+    pub fn new(lookback: usize) -> Self {
+        TrendSlope { lookback, window: Window::new(lookback) }
+    }
+}
+
+impl Indicator for TrendSlope {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if !self.window.push(bar.close) {
+            return None;
+        }
+        let mut slope = 0.0;
+        let mut denom = 0.0;
+        for i in 0..self.lookback {
+            let coef = i as f64 - 0.5 * (self.lookback - 1) as f64;
+            denom += coef * coef;
+            slope += coef * self.window.get(i);
+        }
+        Some(slope / denom)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matlib::find_slope;
+
+    #[test]
+    fn test_trend_slope_matches_find_slope() {
+        let closes: Vec<f64> = (0..40).map(|i| (i as f64 * 0.3).sin() + 0.05 * i as f64).collect();
+        let slopes = TrendSlope::new(7).compute_closes(&closes);
+        assert!(slopes[..6].iter().all(|v| v.is_nan()));
+        for (k, &value) in slopes.iter().enumerate().skip(6) {
+            assert_eq!(value, find_slope(7, &closes, k));
+        }
+        let line: Vec<f64> = (0..5).map(|i| 2.0 * i as f64).collect();
+        assert_eq!(TrendSlope::new(5).compute_closes(&line)[4], 2.0);
+    }
+}
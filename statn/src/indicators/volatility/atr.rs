@@ -0,0 +1,110 @@
+use crate::indicator::{Bar, Indicator, Window, bars};
+
+/// True range of a bar: its high less low, widened to reach the prior close
+/// when the bar gapped away from it
+pub fn true_range(bar: Bar, prior_close: Option<f64>) -> f64 {
+    let term = bar.high - bar.low;
+    match prior_close {
+        Some(prior) => term.max(bar.high - prior).max(prior - bar.low),
+        None => term,
+    }
+}
+
+/// Average true range of the last `lookback` bars, as `finance_tools::atr`
+/// computes it: the first bar of the window counts its high less low only.
+/// `Atr::wilder` instead continues from the first average with Wilder's
+/// smoothing, the usual ATR of charting packages.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    lookback: usize,
+    wilder: bool,
+    window: Window<Bar>,
+    smoothed: Option<f64>,
+}
+
+impl Atr {
+    pub fn new(lookback: usize) -> Self {
+        Atr { lookback, wilder: false, window: Window::new(lookback), smoothed: None }
+    }
+
+    /// Wilder's ATR: the simple average of the first `lookback` true ranges,
+    /// then `(atr * (lookback - 1) + tr) / lookback` at each bar
+    pub fn wilder(lookback: usize) -> Self {
+        Atr { wilder: true, ..Atr::new(lookback) }
+    }
+}
+
+impl Indicator for Atr {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if let Some(prior) = self.smoothed {
+            let p = self.lookback as f64;
+            let tr = true_range(bar, Some(self.window.get(self.lookback - 1).close));
+            self.window.push(bar);
+            self.smoothed = Some((prior * (p - 1.0) + tr) / p);
+            return self.smoothed;
+        }
+        if !self.window.push(bar) {
+            return None;
+        }
+        let mut sum = 0.0;
+        for i in 0..self.lookback {
+            let prior_close = (i > 0).then(|| self.window.get(i - 1).close);
+            sum += true_range(self.window.get(i), prior_close);
+        }
+        let value = sum / self.lookback as f64;
+        if self.wilder {
+            self.smoothed = Some(value);
+        }
+        Some(value)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.smoothed = None;
+    }
+}
+
+/// Average true range over the last `lookback` bars at every bar, NaN for
+/// the first `lookback - 1`
+pub fn average_true_range(highs: &[f64], lows: &[f64], closes: &[f64], lookback: usize) -> Vec<f64> {
+    Atr::new(lookback).compute(&bars(highs, lows, closes))
+}
+
+/// Wilder's average true range at every bar, NaN for the first
+/// `lookback - 1`
+pub fn wilder_atr(highs: &[f64], lows: &[f64], closes: &[f64], lookback: usize) -> Vec<f64> {
+    Atr::wilder(lookback).compute(&bars(highs, lows, closes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finance_tools::atr;
+
+    #[test]
+    fn test_atr_matches_finance_tools() {
+        let closes: Vec<f64> = (0..30).map(|i| 100.0 + ((i * 5) % 9) as f64).collect();
+        let highs: Vec<f64> = closes.iter().map(|c| c + 1.5).collect();
+        let lows: Vec<f64> = closes.iter().map(|c| c - 0.5).collect();
+        let values = average_true_range(&highs, &lows, &closes, 5);
+        for (k, &value) in values.iter().enumerate().skip(4) {
+            assert_eq!(value, atr(5, &highs, &lows, &closes, k));
+        }
+    }
+
+    #[test]
+    fn test_wilder_atr() {
+        // True ranges 2, 3 (gap up from 10), 2, then 4 (gap down to 8)
+        let highs = [11.0, 13.0, 13.0, 10.0];
+        let lows = [9.0, 11.0, 11.0, 8.0];
+        let closes = [10.0, 12.0, 12.0, 9.0];
+        assert_eq!(true_range(Bar::new(13.0, 11.0, 12.0), Some(10.0)), 3.0);
+        let wilder = wilder_atr(&highs, &lows, &closes, 2);
+        let simple = average_true_range(&highs, &lows, &closes, 2);
+        assert!(wilder[0].is_nan());
+        assert_eq!(wilder[1], simple[1]);
+        assert_eq!(wilder[1], 2.5);
+        assert_eq!(wilder[2], (2.5 + 2.0) / 2.0);
+        assert_eq!(wilder[3], (2.25 + 4.0) / 2.0);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::indicator::{Bar, Indicator, Window, compute_versioned};
+
+/// Range of the closes in the recent half of the last `lookback` bars over
+/// the range in the older half, as `matlib::range_expansion` computes it
+#[derive(Debug, Clone)]
+pub struct RangeExpansion {
+    lookback: usize,
+    window: Window<f64>,
+}
+
+impl RangeExpansion {
+    pub fn new(lookback: usize) -> Self {
+        RangeExpansion { lookback, window: Window::new(lookback) }
+    }
+}
+
+impl Indicator for RangeExpansion {
+    fn update(&mut self, bar: Bar) -> Option<f64> {
+        if !self.window.push(bar.close) {
+            return None;
+        }
+        let range = |from: usize, to: usize| {
+            let (mut high, mut low) = (-1e60_f64, 1e60_f64);
+            for i in from..to {
+                high = high.max(self.window.get(i));
+                low = low.min(self.window.get(i));
+            }
+            high - low
+        };
+        let half = self.lookback / 2;
+        Some(range(half, self.lookback) / (range(0, half) + 1e-10))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Range expansion of the closes in one of the versions of `Versioned`, from bar
+/// `full_lookback - 1` on
+pub fn compute_expansion(closes: &[f64], lookback: usize, full_lookback: usize, version: usize) -> Vec<f64> {
+    debug_assert_eq!(full_lookback, crate::indicator::full_lookback(lookback, version));
+    let bars: Vec<Bar> = closes.iter().map(|&c| Bar::from_close(c)).collect();
+    compute_versioned(RangeExpansion::new, &bars, lookback, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matlib::range_expansion;
+
+    #[test]
+    fn test_expansion_matches_matlib() {
+        let closes: Vec<f64> = (0..30).map(|i| ((i * 7) % 13) as f64 * (1.0 + 0.1 * i as f64)).collect();
+        let values = RangeExpansion::new(8).compute_closes(&closes);
+        for (k, &value) in values.iter().enumerate().skip(7) {
+            assert_eq!(value, range_expansion(8, k, &closes));
+        }
+    }
+}
@@ -1,4 +1,8 @@
 #[allow(clippy::module_inception)]
 pub mod volatility;
 pub mod bollinger_bands;
+pub mod atr;
+pub mod expansion;
 pub use volatility::*;
+pub use atr::{Atr, average_true_range, true_range, wilder_atr};
+pub use expansion::{RangeExpansion, compute_expansion};
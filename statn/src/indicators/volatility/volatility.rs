@@ -1,5 +1,8 @@
-use finance_tools::atr;
+use crate::indicator::{bars, compute_versioned};
+use crate::volatility::atr::Atr;
 
+/// Average true range in one of the versions of `Versioned`, from bar
+/// `full_lookback - 1` on
 pub fn compute_volatility(
     highs: &[f64],
     lows: &[f64],
@@ -8,24 +11,31 @@ pub fn compute_volatility(
     full_lookback: usize,
     version: usize,
 ) -> Vec<f64> {
-    let nprices = closes.len();
-    let nind = nprices - full_lookback + 1;
-    let mut volatility = vec![0.0; nind];
+    debug_assert_eq!(full_lookback, crate::indicator::full_lookback(lookback, version));
+    compute_versioned(Atr::new, &bars(highs, lows, closes), lookback, version)
+}
 
-    for (i, vlt) in volatility.iter_mut().enumerate().take(nind) {
-        let k = full_lookback - 1 + i;
-        *vlt = match version {
-            0 => atr(lookback, highs, lows, closes, k),
-            1 => {
-                atr(lookback, highs, lows, closes, k)
-                    - atr(lookback, highs, lows, closes, k - lookback)
-            }
-            _ => {
-                atr(lookback, highs, lows, closes, k)
-                    - atr(full_lookback, highs, lows, closes, k)
-            }
-        };
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finance_tools::atr;
 
-    volatility
+    #[test]
+    fn test_compute_volatility_versions() {
+        let closes: Vec<f64> = (0..60).map(|i| 100.0 + ((i * 7) % 11) as f64).collect();
+        let highs: Vec<f64> = closes.iter().map(|c| c + 2.0).collect();
+        let lows: Vec<f64> = closes.iter().map(|c| c - 1.0).collect();
+        for version in 0..4 {
+            let full = crate::indicator::full_lookback(4, version);
+            let volatility = compute_volatility(&highs, &lows, &closes, 4, full, version);
+            assert_eq!(volatility.len(), closes.len() - full + 1);
+            let k = full - 1 + 5;
+            let expected = match version {
+                0 => atr(4, &highs, &lows, &closes, k),
+                1 => atr(4, &highs, &lows, &closes, k) - atr(4, &highs, &lows, &closes, k - 4),
+                _ => atr(4, &highs, &lows, &closes, k) - atr(full, &highs, &lows, &closes, k),
+            };
+            assert_eq!(volatility[5], expected);
+        }
+    }
 }
@@ -1,6 +1,11 @@
+#[cfg(feature = "backtest")]
+pub mod backtest;
 pub mod core;
 pub mod estimators;
+#[cfg(feature = "optimizer")]
 pub mod models;
+pub mod prelude;
+pub mod walkforward;
 // pub mod boot;
 // pub mod cscv;
 // pub mod dev_ma;
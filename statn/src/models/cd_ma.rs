@@ -1,11 +1,29 @@
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
 const RESULTS: bool = false;
 
 use serde::{Deserialize, Serialize};
 
-/// Coordinate Descent model for elastic net regularized regression
+/// Loss minimized by the coordinate descent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Family {
+    /// Squared error of the standardized target: elastic net regression
+    #[default]
+    Gaussian,
+    /// Binomial deviance of a logistic model of whether the target is
+    /// positive: elastic net classification
+    Binomial,
+}
+
+/// Coordinate Descent model for elastic net regularized regression, or
+/// logistic regression with the binomial family.
+///
+/// For the binomial family `ymean` holds the intercept and `yscale` is 1, so
+/// the prediction `beta . x_standardized * yscale + ymean` is the log-odds
+/// that the target is positive, and `explained` is the fraction of the null
+/// deviance explained.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoordinateDescent {
     // Public fields
@@ -16,6 +34,8 @@ pub struct CoordinateDescent {
     pub xscales: Vec<f64>,
     pub ymean: f64,
     pub yscale: f64,
+    #[serde(default)]
+    pub family: Family,
 
     // Private fields
     nvars: usize,
@@ -27,6 +47,10 @@ pub struct CoordinateDescent {
     #[serde(skip, default)]
     lambdas: Vec<f64>,
     #[serde(skip, default)]
+    lambda_intercept: Vec<f64>,
+    #[serde(skip, default)]
+    lambda_explained: Vec<f64>,
+    #[serde(skip, default)]
     x: Vec<f64>,
     #[serde(skip, default)]
     y: Vec<f64>,
@@ -63,6 +87,7 @@ impl CoordinateDescent {
             xscales: vec![0.0; nvars],
             ymean: 0.0,
             yscale: 0.0,
+            family: Family::Gaussian,
             lambda_beta: if n_lambda > 0 {
                 vec![0.0; n_lambda * nvars]
             } else {
@@ -73,6 +98,8 @@ impl CoordinateDescent {
             } else {
                 Vec::new()
             },
+            lambda_intercept: vec![0.0; n_lambda],
+            lambda_explained: vec![0.0; n_lambda],
             x: vec![0.0; ncases * nvars],
             y: vec![0.0; ncases],
             w: if weighted {
@@ -113,6 +140,50 @@ impl CoordinateDescent {
         cd
     }
 
+    /// Constructor of a logistic regression model (binomial family). The
+    /// case weights change with every iteration, so there are no covariance
+    /// updates.
+    pub fn binomial(nvars: usize, ncases: usize, weighted: bool, n_lambda: usize) -> Self {
+        CoordinateDescent {
+            family: Family::Binomial,
+            ..Self::new(nvars, ncases, weighted, false, n_lambda)
+        }
+    }
+
+    /// Prediction for one case of raw (unstandardized) predictors: the
+    /// target for the gaussian family, the probability that it is positive
+    /// for the binomial
+    pub fn predict(&self, x: &[f64]) -> f64 {
+        let score = self.score(x);
+        match self.family {
+            Family::Gaussian => score,
+            Family::Binomial => logistic(score),
+        }
+    }
+
+    /// Linear predictor for one case of raw predictors: the prediction for
+    /// the gaussian family, the log-odds for the binomial
+    pub fn score(&self, x: &[f64]) -> f64 {
+        let sum: f64 = (0..self.nvars)
+            .map(|ivar| self.beta[ivar] * (x[ivar] - self.xmeans[ivar]) / self.xscales[ivar])
+            .sum();
+        sum * self.yscale + self.ymean
+    }
+
+    /// Coefficients of the raw (unstandardized) predictors: the change in the
+    /// prediction, or the log-odds for the binomial family, per unit of each
+    pub fn raw_coefficients(&self) -> Vec<f64> {
+        (0..self.nvars).map(|ivar| self.beta[ivar] * self.yscale / self.xscales[ivar]).collect()
+    }
+
+    /// Normalized weight of a case: its share of the weights, or 1/n
+    fn case_weight(&self, icase: usize) -> f64 {
+        match self.w {
+            Some(ref w) => w[icase],
+            None => 1.0 / self.ncases as f64,
+        }
+    }
+
     /// Get and standardize the data
     pub fn get_data(
         &mut self,
@@ -170,6 +241,17 @@ impl CoordinateDescent {
             self.y[icase] = (yy[k] - self.ymean) / yscale;
         }
 
+        // The binomial family models the class, 1 if the target is positive;
+        // the intercept takes the place of the mean
+        if self.family == Family::Binomial {
+            for icase in 0..self.ncases {
+                let k = (icase + istart) % n;
+                self.y[icase] = if yy[k] > 0.0 { 1.0 } else { 0.0 };
+            }
+            self.ymean = 0.0;
+            self.yscale = 1.0;
+        }
+
         // Handle weights if present
         if let Some(ref mut w) = self.w 
             && let Some(ww_data) = ww {
@@ -264,6 +346,11 @@ impl CoordinateDescent {
         fast_test: bool,
         warm_start: bool,
     ) {
+        if self.family == Family::Binomial {
+            self.core_train_binomial(alpha, lambda, maxits, eps, warm_start);
+            return;
+        }
+
         let s_threshold = alpha * lambda;
         let mut do_active_only = false;
         let mut prior_crit = 1.0e60;
@@ -457,8 +544,189 @@ impl CoordinateDescent {
         self.explained = (ymean_square - crit) / ymean_square;
     }
 
+    /// `core_train` for the binomial family. Each outer iteration replaces
+    /// the deviance by its quadratic approximation at the current
+    /// coefficients (iteratively reweighted least squares), which coordinate
+    /// descent then minimizes with the intercept unpenalized.
+    fn core_train_binomial(
+        &mut self,
+        alpha: f64,
+        lambda: f64,
+        maxits: usize,
+        eps: f64,
+        warm_start: bool,
+    ) {
+        let s_threshold = alpha * lambda;
+        let case_weights: Vec<f64> = (0..self.ncases).map(|i| self.case_weight(i)).collect();
+        let ybar = self.positive_fraction();
+
+        if !warm_start {
+            self.beta.iter_mut().for_each(|b| *b = 0.0);
+            self.ymean = (ybar / (1.0 - ybar)).ln();
+        }
+
+        let nvars = self.nvars;
+        let mut irls_weights = vec![0.0; self.ncases];
+        let mut grad = vec![0.0; nvars];
+        let mut xsum = vec![0.0; nvars];
+        let mut xinner = vec![0.0; nvars * nvars];
+
+        for _outer in 0..maxits {
+            // Working weights and residuals of the working response
+            for icase in 0..self.ncases {
+                let p = logistic(self.linear_predictor(icase));
+                let variance = p * (1.0 - p);
+                irls_weights[icase] = case_weights[icase] * variance;
+                self.resid[icase] = (self.y[icase] - p) / variance;
+            }
+
+            // Weighted sums of the predictors, their cross products and their
+            // products with the residuals; the coordinate updates below keep
+            // `grad` and `grad_intercept` current without touching the cases
+            let weight_sum: f64 = irls_weights.iter().sum();
+            let mut grad_intercept = 0.0;
+            grad.iter_mut().for_each(|g| *g = 0.0);
+            xsum.iter_mut().for_each(|v| *v = 0.0);
+            xinner.iter_mut().for_each(|v| *v = 0.0);
+            for (icase, &weight) in irls_weights.iter().enumerate() {
+                let xrow = &self.x[icase * nvars..(icase + 1) * nvars];
+                let wr = weight * self.resid[icase];
+                grad_intercept += wr;
+                for ivar in 0..nvars {
+                    let wx = weight * xrow[ivar];
+                    grad[ivar] += wr * xrow[ivar];
+                    xsum[ivar] += wx;
+                    for jvar in 0..=ivar {
+                        xinner[ivar * nvars + jvar] += wx * xrow[jvar];
+                    }
+                }
+            }
+            for ivar in 0..nvars {
+                for jvar in 0..ivar {
+                    xinner[jvar * nvars + ivar] = xinner[ivar * nvars + jvar];
+                }
+            }
+
+            let prior_intercept = self.ymean;
+            let prior_beta = self.beta.clone();
+
+            // Sweeps over the nonzero coefficients alone alternate with full
+            // sweeps, as in the gaussian core
+            let mut do_active_only = false;
+            for _iter in 0..maxits {
+                let mut active_set_changed = false;
+
+                // Intercept, unpenalized
+                let shift = grad_intercept / weight_sum;
+                self.ymean += shift;
+                grad_intercept = 0.0;
+                for (g, &xs) in grad.iter_mut().zip(&xsum) {
+                    *g -= shift * xs;
+                }
+                let mut max_change = shift.abs();
+
+                for ivar in 0..nvars {
+                    if do_active_only && self.beta[ivar] == 0.0 {
+                        continue;
+                    }
+                    let xss = xinner[ivar * nvars + ivar];
+                    let argument = grad[ivar] + self.beta[ivar] * xss;
+
+                    let update_factor = xss + lambda * (1.0 - alpha);
+                    let new_beta = if argument > 0.0 && s_threshold < argument {
+                        (argument - s_threshold) / update_factor
+                    } else if argument < 0.0 && s_threshold < -argument {
+                        (argument + s_threshold) / update_factor
+                    } else {
+                        0.0
+                    };
+
+                    let correction = new_beta - self.beta[ivar];
+                    if correction != 0.0 {
+                        let xrow = &xinner[ivar * nvars..(ivar + 1) * nvars];
+                        for (g, &xi) in grad.iter_mut().zip(xrow) {
+                            *g -= correction * xi;
+                        }
+                        grad_intercept -= correction * xsum[ivar];
+                        if self.beta[ivar] == 0.0 || new_beta == 0.0 {
+                            active_set_changed = true;
+                        }
+                        self.beta[ivar] = new_beta;
+                        max_change = f64::max(max_change, correction.abs());
+                    }
+                }
+
+                let converged = max_change < eps;
+                if do_active_only {
+                    if converged {
+                        do_active_only = false;
+                    }
+                } else {
+                    if converged && !active_set_changed {
+                        break;
+                    }
+                    do_active_only = true;
+                }
+            }
+
+            let mut change = (self.ymean - prior_intercept).abs();
+            for (b, prior) in self.beta.iter().zip(&prior_beta) {
+                change = change.max((b - prior).abs());
+            }
+            if change < eps {
+                break;
+            }
+        }
+
+        // Fraction of the deviance of the intercept-only model explained
+        let mut deviance = 0.0;
+        let mut null_deviance = 0.0;
+        for (icase, &weight) in case_weights.iter().enumerate() {
+            let p = logistic(self.linear_predictor(icase));
+            deviance += weight * binomial_deviance(self.y[icase], p);
+            null_deviance += weight * binomial_deviance(self.y[icase], ybar);
+        }
+        self.explained = if null_deviance > 0.0 {
+            1.0 - deviance / null_deviance
+        } else {
+            0.0
+        };
+    }
+
+    /// Log-odds of a standardized training case under the binomial family
+    fn linear_predictor(&self, icase: usize) -> f64 {
+        let mut sum = self.ymean;
+        for ivar in 0..self.nvars {
+            sum += self.beta[ivar] * self.x[icase * self.nvars + ivar];
+        }
+        sum
+    }
+
+    /// Weighted fraction of positive training cases, kept off 0 and 1
+    fn positive_fraction(&self) -> f64 {
+        let mut ybar = 0.0;
+        for icase in 0..self.ncases {
+            ybar += self.case_weight(icase) * self.y[icase];
+        }
+        ybar.clamp(PROB_LIMIT, 1.0 - PROB_LIMIT)
+    }
+
     /// Get minimum lambda such that all betas remain at zero
     pub fn get_lambda_thresh(&self, alpha: f64) -> f64 {
+        if self.family == Family::Binomial {
+            // Gradient of the deviance at the intercept-only fit
+            let ybar = self.positive_fraction();
+            let mut thresh: f64 = 0.0;
+            for ivar in 0..self.nvars {
+                let mut sum = 0.0;
+                for icase in 0..self.ncases {
+                    sum += self.case_weight(icase) * self.x[icase * self.nvars + ivar] * (self.y[icase] - ybar);
+                }
+                thresh = thresh.max(sum.abs());
+            }
+            return thresh / (alpha + 1.0e-60);
+        }
+
         let mut thresh = 0.0;
         for ivar in 0..self.nvars {
             let mut sum = 0.0;
@@ -515,6 +783,8 @@ impl CoordinateDescent {
             for ivar in 0..self.nvars {
                 self.lambda_beta[ilambda * self.nvars + ivar] = self.beta[ivar];
             }
+            self.lambda_intercept[ilambda] = self.ymean;
+            self.lambda_explained[ilambda] = self.explained;
 
             if print_steps {
                 let n_active = self.beta.iter().filter(|&&b| b != 0.0).count();
@@ -530,6 +800,129 @@ impl CoordinateDescent {
             lambda *= lambda_factor;
         }
     }
+
+    /// Train down the lambda path from `max_lambda` with `lambda_train`, each
+    /// step warm-started from the one before, and keep the coefficients of
+    /// the step nearest `lambda` (the same grid as `cv_train`, so its lambda
+    /// is found exactly). Returns the index of that step.
+    pub fn path_train(
+        &mut self,
+        alpha: f64,
+        maxits: usize,
+        eps: f64,
+        fast_test: bool,
+        max_lambda: f64,
+        lambda: f64,
+    ) -> usize {
+        self.lambda_train(alpha, maxits, eps, fast_test, max_lambda, false);
+        let distance = |l: f64| (l.max(1.0e-60) / lambda.max(1.0e-60)).ln().abs();
+        let ibest = (0..self.lambdas.len())
+            .min_by(|&i, &j| distance(self.lambdas[i]).total_cmp(&distance(self.lambdas[j])))
+            .unwrap_or(0);
+        self.select_path_lambda(ibest);
+        ibest
+    }
+
+    /// Make the coefficients of step `ilambda` of the trained lambda path
+    /// the model's own
+    pub fn select_path_lambda(&mut self, ilambda: usize) {
+        let nvars = self.nvars;
+        self.beta.copy_from_slice(&self.lambda_beta[ilambda * nvars..(ilambda + 1) * nvars]);
+        self.ymean = self.lambda_intercept[ilambda];
+        self.explained = self.lambda_explained[ilambda];
+    }
+
+    /// Lambdas of the trained path, largest first
+    pub fn path_lambdas(&self) -> &[f64] {
+        &self.lambdas
+    }
+
+    /// Coefficients at step `ilambda` of the trained path
+    pub fn path_beta(&self, ilambda: usize) -> &[f64] {
+        &self.lambda_beta[ilambda * self.nvars..(ilambda + 1) * self.nvars]
+    }
+
+    /// Variables in the order they enter the trained path, each with the
+    /// largest lambda at which its coefficient is nonzero. Variables that
+    /// never enter are left out.
+    pub fn entry_order(&self) -> Vec<(usize, f64)> {
+        let mut entered = vec![false; self.nvars];
+        let mut order = Vec::new();
+        for (ilambda, &lambda) in self.lambdas.iter().enumerate() {
+            for (ivar, &b) in self.path_beta(ilambda).iter().enumerate() {
+                if b != 0.0 && !entered[ivar] {
+                    entered[ivar] = true;
+                    order.push((ivar, lambda));
+                }
+            }
+        }
+        order
+    }
+
+    /// Write the trained lambda path as a table: one row per lambda, largest
+    /// first, with its number of active variables, explained fraction and
+    /// the coefficient of every variable, then the order in which the
+    /// variables enter. `names` label the variables (x0, x1, ... beyond it).
+    pub fn write_coefficient_path<P: AsRef<Path>>(&self, path: P, names: &[String]) -> io::Result<()> {
+        let name = |ivar: usize| names.get(ivar).cloned().unwrap_or_else(|| format!("x{}", ivar));
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(file, "Coefficient path (coefficients of the standardized predictors)")?;
+        write!(file, "{:>12} {:>6} {:>10}", "Lambda", "Active", "Explained")?;
+        for ivar in 0..self.nvars {
+            write!(file, " {:>12}", name(ivar))?;
+        }
+        writeln!(file)?;
+        for (ilambda, &lambda) in self.lambdas.iter().enumerate() {
+            let coefs = self.path_beta(ilambda);
+            let n_active = coefs.iter().filter(|&&b| b != 0.0).count();
+            write!(file, "{:>12.6} {:>6} {:>10.4}", lambda, n_active, self.lambda_explained[ilambda])?;
+            for b in coefs {
+                write!(file, " {:>12.6}", b)?;
+            }
+            writeln!(file)?;
+        }
+
+        writeln!(file)?;
+        writeln!(file, "Entry order:")?;
+        writeln!(file, "{:>5} {:>12} {:>12}", "Rank", "Variable", "Lambda")?;
+        for (rank, (ivar, lambda)) in self.entry_order().into_iter().enumerate() {
+            writeln!(file, "{:>5} {:>12} {:>12.6}", rank + 1, name(ivar), lambda)?;
+        }
+        file.flush()
+    }
+}
+
+/// Probabilities are kept this far from 0 and 1, so that the working
+/// weights of a nearly separated case stay positive
+const PROB_LIMIT: f64 = 1.0e-5;
+
+/// Logistic function, the probability of log-odds `eta`, kept within
+/// `PROB_LIMIT` of 0 and 1
+fn logistic(eta: f64) -> f64 {
+    (1.0 / (1.0 + (-eta).exp())).clamp(PROB_LIMIT, 1.0 - PROB_LIMIT)
+}
+
+/// Deviance of one case of class `y` (0 or 1) predicted with probability `p`
+fn binomial_deviance(y: f64, p: f64) -> f64 {
+    -2.0 * (y * p.ln() + (1.0 - y) * (1.0 - p).ln())
+}
+
+/// Exponential time-decay case weights for `n` chronologically ordered cases.
+///
+/// The most recent (last) case has weight 1 and a case `half_life` cases
+/// older has weight 0.5. A non-positive half-life gives equal weights.
+/// `get_data` normalizes weights to sum to one, so only their ratios matter.
+pub fn time_decay_weights(n: usize, half_life: f64) -> Vec<f64> {
+    if half_life <= 0.0 {
+        return vec![1.0; n];
+    }
+    let decay = 0.5_f64.powf(1.0 / half_life);
+    let mut weights = vec![1.0; n];
+    for i in (0..n.saturating_sub(1)).rev() {
+        weights[i] = weights[i + 1] * decay;
+    }
+    weights
 }
 
 /// Cross-validation training routine
@@ -548,6 +941,42 @@ pub fn cv_train(
     maxits: usize,
     eps: f64,
     fast_test: bool,
+) -> f64 {
+    let mut lambda_se = vec![0.0; n_lambda];
+    cv_train_family(
+        Family::Gaussian, nvars, nfolds, xx, yy, ww, lambdas, lambda_oos, &mut lambda_se, false, covar_updates,
+        n_lambda, alpha, maxits, eps, fast_test,
+    )
+}
+
+/// Cross-validation training routine for either family. The out-of-sample
+/// criterion is the explained variance for the gaussian family and the
+/// fraction of the null deviance explained for the binomial, the null model
+/// predicting each fold's in-sample fraction of positive cases.
+///
+/// `lambda_se` receives the standard error of each lambda's criterion, from
+/// its spread over the folds. With `one_se` the largest lambda whose
+/// criterion is within one standard error of the best is chosen instead of
+/// the best (the one-standard-error rule), trading a little fit for a
+/// sparser, less overfitted model.
+#[allow(clippy::too_many_arguments)]
+pub fn cv_train_family(
+    family: Family,
+    nvars: usize,
+    nfolds: usize,
+    xx: &[f64],
+    yy: &[f64],
+    ww: Option<&[f64]>,
+    lambdas: &mut [f64],
+    lambda_oos: &mut [f64],
+    lambda_se: &mut [f64],
+    one_se: bool,
+    covar_updates: bool,
+    n_lambda: usize,
+    alpha: f64,
+    maxits: usize,
+    eps: f64,
+    fast_test: bool,
 ) -> f64 {
     let n = yy.len();
 
@@ -558,7 +987,11 @@ pub fn cv_train(
     let mut work = vec![0.0; n];
 
     // Use entire dataset to find max lambda
-    let mut cd = CoordinateDescent::new(nvars, n, ww.is_some(), covar_updates, n_lambda);
+    let new_model = |ncases: usize| match family {
+        Family::Gaussian => CoordinateDescent::new(nvars, ncases, ww.is_some(), covar_updates, n_lambda),
+        Family::Binomial => CoordinateDescent::binomial(nvars, ncases, ww.is_some(), n_lambda),
+    };
+    let mut cd = new_model(n);
     cd.get_data(0, n, xx, yy, ww);
     let max_lambda = cd.get_lambda_thresh(alpha);
 
@@ -584,17 +1017,21 @@ pub fn cv_train(
     }
 
     let mut yssum_squares = 0.0;
+    // Criterion of each fold alone, fold-major
+    let mut fold_crit = vec![0.0; nfolds * n_lambda];
 
     // Process folds
-    for _ifold in 0..nfolds {
-        let n_oos = (n - n_done) / (nfolds - _ifold);
+    for ifold in 0..nfolds {
+        let n_oos = (n - n_done) / (nfolds - ifold);
         let n_is = n - n_oos;
         let i_oos = (i_is + n_is) % n;
 
         // Train model with IS set
-        let mut cd_fold = CoordinateDescent::new(nvars, n_is, ww.is_some(), covar_updates, n_lambda);
+        let mut cd_fold = new_model(n_is);
         cd_fold.get_data(i_is, n, xx, yy, ww);
         cd_fold.lambda_train(alpha, maxits, eps, fast_test, max_lambda, false);
+        let ybar = cd_fold.positive_fraction();
+        let mut fold_yss = 0.0;
 
         // Compute OOS performance for each lambda
         for ilambda in 0..n_lambda {
@@ -610,23 +1047,36 @@ pub fn cv_train(
                         / cd_fold.xscales[ivar];
                 }
 
+                let case_weight = ww.map_or(1.0, |ww_data| ww_data[k]);
+                if family == Family::Binomial {
+                    let y = if yy[k] > 0.0 { 1.0 } else { 0.0 };
+                    let p = logistic(pred + cd_fold.lambda_intercept[ilambda]);
+                    if ilambda == 0 {
+                        fold_yss += case_weight * binomial_deviance(y, ybar);
+                    }
+                    sum += case_weight * binomial_deviance(y, p);
+                    continue;
+                }
+
                 let ynormalized = (yy[k] - cd_fold.ymean) / cd_fold.yscale;
                 let diff = ynormalized - pred;
 
                 if let Some(ww_data) = ww {
                     if ilambda == 0 {
-                        yssum_squares += ww_data[k] * ynormalized * ynormalized;
+                        fold_yss += ww_data[k] * ynormalized * ynormalized;
                     }
                     sum += ww_data[k] * diff * diff;
                 } else {
                     if ilambda == 0 {
-                        yssum_squares += ynormalized * ynormalized;
+                        fold_yss += ynormalized * ynormalized;
                     }
                     sum += diff * diff;
                 }
             }
             lambda_oos[ilambda] += sum;
+            fold_crit[ifold * n_lambda + ilambda] = if fold_yss > 0.0 { 1.0 - sum / fold_yss } else { 0.0 };
         }
+        yssum_squares += fold_yss;
 
         n_done += n_oos;
         i_is = (i_is + n_oos) % n;
@@ -644,6 +1094,24 @@ pub fn cv_train(
         }
     }
 
+    // Standard error of each criterion from its variation over the folds
+    for (ilambda, se) in lambda_se.iter_mut().enumerate().take(n_lambda) {
+        let crits = (0..nfolds).map(|ifold| fold_crit[ifold * n_lambda + ilambda]);
+        let mean = crits.clone().sum::<f64>() / nfolds as f64;
+        let var = crits.map(|c| (c - mean) * (c - mean)).sum::<f64>() / (nfolds - 1).max(1) as f64;
+        *se = (var / nfolds as f64).sqrt();
+    }
+
+    // The lambdas descend, so the first within one standard error of the
+    // best is the most regularized
+    if one_se {
+        let threshold = best - lambda_se[ibest];
+        if let Some(ilambda) = lambda_oos[..n_lambda].iter().position(|&val| val >= threshold) {
+            ibest = ilambda;
+            best = lambda_oos[ilambda];
+        }
+    }
+
     if RESULTS 
         && let Ok(mut file) = OpenOptions::new().create(true).append(true).open("CDtest.LOG") {
             let _ = writeln!(
@@ -654,4 +1122,189 @@ pub fn cv_train(
     }
 
     lambdas[ibest]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_decay_weights() {
+        let w = time_decay_weights(5, 2.0);
+        assert_eq!(w[4], 1.0);
+        assert!((w[2] - 0.5).abs() < 1e-12);
+        assert!((w[0] - 0.25).abs() < 1e-12);
+        assert_eq!(time_decay_weights(3, 0.0), vec![1.0; 3]);
+    }
+
+    #[test]
+    fn test_decay_weights_favor_recent_regime() {
+        // y = x in the first half and y = -x in the second half
+        let n = 200;
+        let xx: Vec<f64> = (0..n).map(|i| ((i * 37 % 101) as f64) / 50.0 - 1.0).collect();
+        let yy: Vec<f64> = (0..n).map(|i| if i < n / 2 { xx[i] } else { -xx[i] }).collect();
+
+        let weights = time_decay_weights(n, 10.0);
+        let mut cd = CoordinateDescent::new(1, n, true, true, 0);
+        cd.get_data(0, n, &xx, &yy, Some(&weights));
+        cd.core_train(1.0, 0.0, 1000, 1e-9, true, false);
+        assert!(cd.beta[0] < -0.9);
+    }
+
+    #[test]
+    fn test_path_train_warm_starts() {
+        // y depends strongly on x0, weakly on x1 and not on x2
+        let n = 200;
+        let mut xx = Vec::with_capacity(3 * n);
+        let mut yy = Vec::with_capacity(n);
+        for i in 0..n {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            let x1 = ((i * 53 % 97) as f64) / 48.0 - 1.0;
+            let x2 = ((i * 71 % 89) as f64) / 44.0 - 1.0;
+            xx.extend([x0, x1, x2]);
+            yy.push(2.0 * x0 + 0.5 * x1 + 0.1 * (((i * 13) % 7) as f64 - 3.0));
+        }
+
+        let n_lambda = 20;
+        let mut cd = CoordinateDescent::new(3, n, false, true, n_lambda);
+        cd.get_data(0, n, &xx, &yy, None);
+        let max_lambda = cd.get_lambda_thresh(0.5);
+        let ilambda = cd.path_train(0.5, 1000, 1e-10, true, max_lambda, 0.0);
+        assert_eq!(ilambda, n_lambda - 1);
+        assert_eq!(cd.path_lambdas().len(), n_lambda);
+        assert!(cd.path_beta(0).iter().all(|&b| b == 0.0));
+
+        // The strong predictor enters first, and the end of the warm-started
+        // path matches a cold fit at its lambda
+        let order = cd.entry_order();
+        assert_eq!(order[0].0, 0);
+        assert_eq!(order[1].0, 1);
+        assert!(order[0].1 > order[1].1);
+        let warm = cd.beta.clone();
+        let lambda = cd.path_lambdas()[ilambda];
+        cd.core_train(0.5, lambda, 1000, 1e-10, true, false);
+        for (w, c) in warm.iter().zip(&cd.beta) {
+            assert!((w - c).abs() < 1e-6);
+        }
+
+        // A step in the middle, found from its lambda
+        let mid = cd.path_lambdas()[7];
+        assert_eq!(cd.path_train(0.5, 1000, 1e-10, true, max_lambda, mid), 7);
+        assert_eq!(cd.beta, cd.path_beta(7));
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("path.log");
+        cd.write_coefficient_path(&file, &["strong".to_string(), "weak".to_string()]).unwrap();
+        let table = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(table.lines().filter(|l| l.trim_start().starts_with(|c: char| c.is_ascii_digit())).count(), n_lambda + order.len());
+        assert!(table.lines().nth(1).unwrap().ends_with("strong         weak           x2"));
+        assert!(table.contains("    1       strong"));
+    }
+
+    fn classification_data(n: usize) -> (Vec<f64>, Vec<f64>) {
+        // Two predictors; the class follows the first with some label noise,
+        // the second is unrelated
+        let mut xx = Vec::with_capacity(2 * n);
+        let mut yy = Vec::with_capacity(n);
+        for i in 0..n {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            let x1 = ((i * 53 % 97) as f64) / 48.0 - 1.0;
+            xx.extend([x0, x1]);
+            let flip = i % 7 == 0;
+            yy.push(if (x0 > 0.1) != flip { 0.02 } else { -0.01 });
+        }
+        (xx, yy)
+    }
+
+    #[test]
+    fn test_binomial_fit() {
+        let n = 400;
+        let (xx, yy) = classification_data(n);
+        let mut cd = CoordinateDescent::binomial(2, n, false, 0);
+        cd.get_data(0, n, &xx, &yy, None);
+        cd.core_train(1.0, 0.0, 1000, 1e-10, true, false);
+
+        assert!(cd.beta[0] > 1.0, "{:?}", cd.beta);
+        assert!(cd.beta[1].abs() < 0.3 * cd.beta[0]);
+        assert!(cd.explained > 0.2 && cd.explained < 1.0);
+        assert_eq!(cd.yscale, 1.0);
+
+        // The unpenalized fit is at a stationary point of the likelihood:
+        // residuals sum to zero overall and against each predictor
+        let mut grad = [0.0; 3];
+        for i in 0..n {
+            let r = cd.y[i] - logistic(cd.linear_predictor(i));
+            grad[0] += r;
+            grad[1] += r * cd.x[i * 2];
+            grad[2] += r * cd.x[i * 2 + 1];
+        }
+        assert!(grad.iter().all(|g| g.abs() / (n as f64) < 1e-6), "{:?}", grad);
+
+        assert!(cd.predict(&[0.9, 0.0]) > 0.5 && cd.predict(&[-0.9, 0.0]) < 0.5);
+
+        // At the threshold lambda every coefficient is zero, leaving the
+        // intercept at the log-odds of the base rate
+        let thresh = cd.get_lambda_thresh(0.5);
+        cd.core_train(0.5, 1.001 * thresh, 1000, 1e-10, true, false);
+        assert!(cd.beta.iter().all(|&b| b == 0.0));
+        let ybar = cd.positive_fraction();
+        assert!((cd.ymean - (ybar / (1.0 - ybar)).ln()).abs() < 1e-8);
+        assert!(cd.explained.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_binomial_cv() {
+        let n = 400;
+        let (xx, yy) = classification_data(n);
+        let n_lambda = 12;
+        let mut lambdas = vec![0.0; n_lambda];
+        let mut lambda_oos = vec![0.0; n_lambda];
+        let mut lambda_se = vec![0.0; n_lambda];
+        let lambda = cv_train_family(
+            Family::Binomial, 2, 5, &xx, &yy, None, &mut lambdas, &mut lambda_oos, &mut lambda_se, false, false,
+            n_lambda, 0.5, 1000, 1e-9, true,
+        );
+        // The largest lambda is the null model; smaller ones explain more
+        assert!(lambda < lambdas[0]);
+        assert!(lambda_oos[0].abs() < 0.05);
+        assert!(lambda_oos.iter().cloned().fold(f64::MIN, f64::max) > 0.2);
+    }
+
+    #[test]
+    fn test_one_se_rule() {
+        // A strong predictor and eight weak or useless ones, in few cases
+        let n = 120;
+        let nvars = 9;
+        let mut xx = Vec::with_capacity(nvars * n);
+        let mut yy = Vec::with_capacity(n);
+        for i in 0..n {
+            let row: Vec<f64> = (0..nvars).map(|j| (((i * (31 + 6 * j) + 7 * j) % 97) as f64) / 48.0 - 1.0).collect();
+            yy.push(row[0] + 0.05 * row[1] + 0.8 * (((i * 13) % 11) as f64 / 5.0 - 1.0));
+            xx.extend(row);
+        }
+
+        let n_lambda = 20;
+        let run = |one_se: bool| {
+            let mut lambdas = vec![0.0; n_lambda];
+            let mut lambda_oos = vec![0.0; n_lambda];
+            let mut lambda_se = vec![0.0; n_lambda];
+            let lambda = cv_train_family(
+                Family::Gaussian, nvars, 5, &xx, &yy, None, &mut lambdas, &mut lambda_oos, &mut lambda_se, one_se,
+                true, n_lambda, 0.5, 1000, 1e-9, true,
+            );
+            (lambda, lambdas, lambda_oos, lambda_se)
+        };
+        let (best, lambdas, oos, se) = run(false);
+        let (chosen, _, oos_1se, se_1se) = run(true);
+        assert_eq!((&oos, &se), (&oos_1se, &se_1se));
+        assert!(se.iter().all(|&s| s >= 0.0) && se[n_lambda - 1] > 0.0);
+
+        // The rule picks a larger lambda, whose criterion is within one
+        // standard error of the best, and no larger lambda is
+        let ibest = lambdas.iter().position(|&l| l == best).unwrap();
+        let ichosen = lambdas.iter().position(|&l| l == chosen).unwrap();
+        assert!(chosen > best);
+        assert!(oos[ichosen] >= oos[ibest] - se[ibest]);
+        assert!(oos[..ichosen].iter().all(|&v| v < oos[ibest] - se[ibest]));
+    }
+}
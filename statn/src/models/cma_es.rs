@@ -0,0 +1,318 @@
+//! Covariance matrix adaptation evolution strategy (CMA-ES).
+//!
+//! An alternative to `diff_ev` with the same criterion closure and bounds:
+//! it samples each generation from a multivariate normal distribution and
+//! adapts the mean, step size and covariance of that distribution to the
+//! best samples, so it follows narrow or rotated ridges of the criterion that
+//! differential evolution crosses slowly.
+
+use crate::core::matlib::mwc256::Mwc256;
+use crate::core::matlib::rands::unifrand;
+use crate::estimators::stochastic_bias::StocBias;
+use crate::models::differential_evolution::scale_to_bounds;
+
+/// Configuration of `cma_es`
+#[derive(Debug, Clone, Copy)]
+pub struct CmaEsConfig<'a> {
+    pub nvars: usize,
+    /// The first `nints` parameters are integers
+    pub nints: usize,
+    /// Passed to the criterion
+    pub mintrades: i32,
+    /// Samples per generation; 0 uses the usual `4 + 3 ln(nvars)`
+    pub popsize: usize,
+    /// Criterion evaluations after which the search stops
+    pub max_evals: usize,
+    /// Generations in a row without a new best after which the search stops
+    pub max_bad_gen: usize,
+    /// Initial step size, as a fraction of each parameter's range
+    pub sigma0: f64,
+    pub low_bounds: &'a [f64],
+    pub high_bounds: &'a [f64],
+    pub print_progress: bool,
+    /// Seed of the random numbers; `None` draws one
+    pub seed: Option<u32>,
+}
+
+/// Maximize a criterion with CMA-ES.
+///
+/// The search runs in the unit cube spanned by the bounds, starting at its
+/// center. Samples outside the cube are evaluated at the nearest point inside
+/// and ranked below every legal sample, by how far out they are, which pulls
+/// the distribution back in. Integer parameters are rounded as in `diff_ev`.
+/// `stoc_bias` collects returns during the first generation, whose samples
+/// are spread over the space like `diff_ev`'s initial population.
+///
+/// # Returns
+/// The best parameters found with the criterion value at the end, as
+/// `diff_ev` returns them.
+pub fn cma_es<F>(criter: F, config: CmaEsConfig, stoc_bias: &mut Option<StocBias>) -> Result<Vec<f64>, String>
+where
+    F: Fn(&[f64], i32) -> f64,
+{
+    let CmaEsConfig {
+        nvars: n,
+        nints,
+        mintrades,
+        popsize,
+        max_evals,
+        max_bad_gen,
+        sigma0,
+        low_bounds,
+        high_bounds,
+        print_progress,
+        seed,
+    } = config;
+    if n == 0 || low_bounds.len() != n || high_bounds.len() != n {
+        return Err("CMA-ES needs one low and one high bound per variable".to_string());
+    }
+    if low_bounds.iter().zip(high_bounds).any(|(lo, hi)| lo > hi) {
+        return Err("Low bounds must not exceed high bounds".to_string());
+    }
+    if sigma0.is_nan() || sigma0 <= 0.0 {
+        return Err("Initial step size must be positive".to_string());
+    }
+
+    let mut rng = Mwc256::with_seed(seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32));
+
+    // Strategy parameters (Hansen's defaults)
+    let nf = n as f64;
+    let lambda = if popsize > 0 { popsize.max(2) } else { 4 + (3.0 * nf.ln()) as usize };
+    let mu = lambda / 2;
+    let mut weights: Vec<f64> = (0..mu).map(|i| (mu as f64 + 0.5).ln() - ((i + 1) as f64).ln()).collect();
+    let sum_w: f64 = weights.iter().sum();
+    weights.iter_mut().for_each(|w| *w /= sum_w);
+    let mueff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+    let cc = (4.0 + mueff / nf) / (nf + 4.0 + 2.0 * mueff / nf);
+    let cs = (mueff + 2.0) / (nf + mueff + 5.0);
+    let c1 = 2.0 / ((nf + 1.3).powi(2) + mueff);
+    let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((nf + 2.0).powi(2) + mueff));
+    let damps = 1.0 + 2.0 * (((mueff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+    let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+    let mut mean = vec![0.5; n];
+    let mut sigma = sigma0;
+    let mut cov = identity(n);
+    let mut chol = identity(n);
+    let mut pc = vec![0.0; n];
+    let mut ps = vec![0.0; n];
+
+    let mut best = vec![0.0; n + 1];
+    let mut grand_best = f64::NEG_INFINITY;
+    let mut n_evals = 0;
+    let mut bad_generations = 0;
+    let mut generation: usize = 0;
+
+    let mut unit = vec![vec![0.0; n]; lambda];
+    let mut params = vec![0.0; n];
+    let mut inside = vec![0.0; n];
+    let mut fitness = vec![(0.0, 0); lambda];
+
+    while n_evals < max_evals {
+        if let Some(sb) = stoc_bias {
+            sb.set_collecting(generation == 0);
+        }
+
+        let mut improved = false;
+        for (k, x) in unit.iter_mut().enumerate() {
+            let z: Vec<f64> = (0..n).map(|_| rng.normal()).collect();
+            for i in 0..n {
+                x[i] = mean[i] + sigma * (0..=i).map(|j| chol[i * n + j] * z[j]).sum::<f64>();
+            }
+
+            // Evaluate at the nearest legal point; penalize the distance
+            let mut outside = 0.0;
+            for i in 0..n {
+                inside[i] = x[i].clamp(0.0, 1.0);
+                outside += (x[i] - inside[i]).abs();
+            }
+            scale_to_bounds(&inside, nints, low_bounds, high_bounds, &mut params);
+            let value = criter(&params, mintrades);
+            n_evals += 1;
+            fitness[k] = (if outside > 0.0 { -1.0e10 * (1.0 + outside) } else { value }, k);
+
+            if outside == 0.0 && value > grand_best {
+                grand_best = value;
+                best[..n].copy_from_slice(&params);
+                best[n] = value;
+                improved = true;
+            }
+        }
+        generation += 1;
+
+        if print_progress {
+            print!("\nGen {} Best={:.4} Sigma={:.5}", generation, grand_best, sigma);
+            for val in best.iter().take(n) {
+                print!(" {:.4}", val);
+            }
+        }
+
+        if improved {
+            bad_generations = 0;
+        } else {
+            bad_generations += 1;
+            if bad_generations > max_bad_gen {
+                break;
+            }
+        }
+
+        // Recombine the best half into the new mean
+        fitness.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let old_mean = mean.clone();
+        for i in 0..n {
+            mean[i] = (0..mu).map(|r| weights[r] * unit[fitness[r].1][i]).sum();
+        }
+        let step: Vec<f64> = (0..n).map(|i| (mean[i] - old_mean[i]) / sigma).collect();
+
+        // Evolution paths
+        let whitened = solve_lower(&chol, n, &step);
+        let norm_cs = (cs * (2.0 - cs) * mueff).sqrt();
+        for i in 0..n {
+            ps[i] = (1.0 - cs) * ps[i] + norm_cs * whitened[i];
+        }
+        let ps_norm = ps.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let hsig = ps_norm / (1.0 - (1.0 - cs).powi(2 * generation as i32)).sqrt() / chi_n < 1.4 + 2.0 / (nf + 1.0);
+        let norm_cc = if hsig { (cc * (2.0 - cc) * mueff).sqrt() } else { 0.0 };
+        for i in 0..n {
+            pc[i] = (1.0 - cc) * pc[i] + norm_cc * step[i];
+        }
+
+        // Covariance: rank-one update from the path, rank-mu from the best samples
+        let deltas: Vec<Vec<f64>> = (0..mu)
+            .map(|r| (0..n).map(|i| (unit[fitness[r].1][i] - old_mean[i]) / sigma).collect())
+            .collect();
+        let lost = if hsig { 0.0 } else { c1 * cc * (2.0 - cc) };
+        for i in 0..n {
+            for j in 0..=i {
+                let rank_mu: f64 = (0..mu).map(|r| weights[r] * deltas[r][i] * deltas[r][j]).sum();
+                let c = (1.0 - c1 - cmu + lost) * cov[i * n + j] + c1 * pc[i] * pc[j] + cmu * rank_mu;
+                cov[i * n + j] = c;
+                cov[j * n + i] = c;
+            }
+        }
+
+        sigma *= ((cs / damps) * (ps_norm / chi_n - 1.0)).exp();
+        chol = match cholesky(&cov, n) {
+            Some(l) => l,
+            None => {
+                // Numerical trouble: restart the shape, keep the mean
+                cov = identity(n);
+                pc.iter_mut().for_each(|v| *v = 0.0);
+                ps.iter_mut().for_each(|v| *v = 0.0);
+                identity(n)
+            }
+        };
+        if sigma < 1.0e-12 {
+            break;
+        }
+    }
+
+    if let Some(sb) = stoc_bias {
+        sb.set_collecting(false);
+    }
+    if grand_best == f64::NEG_INFINITY {
+        return Err("CMA-ES found no legal parameters".to_string());
+    }
+    Ok(best)
+}
+
+fn identity(n: usize) -> Vec<f64> {
+    let mut m = vec![0.0; n * n];
+    for i in 0..n {
+        m[i * n + i] = 1.0;
+    }
+    m
+}
+
+/// Lower triangular `L` with `L L' = a`, or `None` if `a` is not positive
+/// definite
+fn cholesky(a: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut l = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| l[i * n + k] * l[j * n + k]).sum();
+            if i == j {
+                let d = a[i * n + i] - sum;
+                if d <= 0.0 || !d.is_finite() {
+                    return None;
+                }
+                l[i * n + i] = d.sqrt();
+            } else {
+                l[i * n + j] = (a[i * n + j] - sum) / l[j * n + j];
+            }
+        }
+    }
+    Some(l)
+}
+
+/// Solve `L x = b` for lower triangular `L`
+fn solve_lower(l: &[f64], n: usize, b: &[f64]) -> Vec<f64> {
+    let mut x = vec![0.0; n];
+    for i in 0..n {
+        let sum: f64 = (0..i).map(|j| l[i * n + j] * x[j]).sum();
+        x[i] = (b[i] - sum) / l[i * n + i];
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config<'a>(low_bounds: &'a [f64], high_bounds: &'a [f64], nints: usize) -> CmaEsConfig<'a> {
+        CmaEsConfig {
+            nvars: low_bounds.len(),
+            nints,
+            mintrades: 1,
+            popsize: 0,
+            max_evals: 20000,
+            max_bad_gen: 50,
+            sigma0: 0.3,
+            low_bounds,
+            high_bounds,
+            print_progress: false,
+            seed: Some(11),
+        }
+    }
+
+    #[test]
+    fn test_cma_es_rotated_ridge() {
+        // Narrow ridge along x = y, hard for axis-wise search
+        let criter = |p: &[f64], _mintrades: i32| -> f64 {
+            let (u, v) = (p[0] + p[1], p[0] - p[1]);
+            10.0 - (u - 2.0).powi(2) - 100.0 * v.powi(2) - (p[2] - 0.5).powi(2)
+        };
+        let low = [-5.0, -5.0, -5.0];
+        let high = [5.0, 5.0, 5.0];
+        let best = cma_es(criter, config(&low, &high, 0), &mut None).unwrap();
+        assert!((best[0] - 1.0).abs() < 1e-3, "{:?}", best);
+        assert!((best[1] - 1.0).abs() < 1e-3);
+        assert!((best[3] - 10.0).abs() < 1e-6);
+
+        // Same seed, same run
+        assert_eq!(best, cma_es(criter, config(&low, &high, 0), &mut None).unwrap());
+    }
+
+    #[test]
+    fn test_cma_es_bounds_and_integers() {
+        // Optimum outside the box, on the high bound of both parameters
+        let criter = |p: &[f64], _mintrades: i32| -> f64 { p[0] + p[1] };
+        let low = [2.0, 0.0];
+        let high = [20.0, 1.5];
+        let best = cma_es(criter, config(&low, &high, 1), &mut None).unwrap();
+        assert_eq!(best[0], 20.0);
+        assert!(best[1] <= 1.5 && best[1] > 1.49);
+
+        assert!(cma_es(criter, config(&high, &low, 1), &mut None).is_err());
+    }
+
+    #[test]
+    fn test_cholesky() {
+        let a = [4.0, 2.0, 2.0, 3.0];
+        let l = cholesky(&a, 2).unwrap();
+        assert_eq!(l, vec![2.0, 0.0, 1.0, 2.0_f64.sqrt()]);
+        let x = solve_lower(&l, 2, &[2.0, 1.0 + 2.0_f64.sqrt()]);
+        assert!((x[0] - 1.0).abs() < 1e-12 && (x[1] - 1.0).abs() < 1e-12);
+        assert!(cholesky(&[1.0, 2.0, 2.0, 1.0], 2).is_none());
+    }
+}
@@ -1,8 +1,17 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::matlib::designs::{Design, DesignSampler};
+use crate::core::matlib::mwc256::Mwc256;
 use crate::core::matlib::paramcor::paramcor;
 use crate::core::matlib::rands::unifrand;
-use crate::estimators::brentmax::brentmax;
-use crate::estimators::glob_max::glob_max;
 use crate::estimators::stochastic_bias::StocBias;
+use crate::models::refine::{line_search, ParamBounds};
 
 /// Differential evolution optimization
 ///
@@ -21,6 +30,148 @@ pub struct DiffEvConfig<'a> {
     pub low_bounds: &'a [f64],
     pub high_bounds: &'a [f64],
     pub print_progress: bool,
+    /// Population diversity (see `population_diversity`) below which the worst
+    /// part of the population is re-randomized; 0 disables restarts
+    pub min_diversity: f64,
+    /// Fraction of the population, worst first, re-randomized on a restart
+    pub restart_frac: f64,
+    /// How the initial population is spread over the parameter space
+    pub init: Design,
+    /// Seed of the random numbers behind the initial population, mutation,
+    /// crossover, hill climbing and restarts. The same seed and criterion
+    /// replay a run exactly; `None` draws a seed, so every run differs.
+    pub seed: Option<u32>,
+    /// Save the optimizer state to a file every so many generations
+    pub checkpoint: Option<Checkpoint<'a>>,
+    /// Continue from a saved state instead of generating an initial
+    /// population. The stochastic bias is only collected on the initial
+    /// population, so a resumed run collects none.
+    pub resume: Option<DiffEvState>,
+    /// Stop criteria besides `max_bad_gen`
+    pub convergence: Convergence,
+}
+
+/// Optional stop criteria of `DiffEvConfig`; the default disables them all
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Convergence {
+    /// Generations over which the improvement of the best criterion is
+    /// measured; 0 disables the test
+    pub window: usize,
+    /// Stop when the best criterion improved by less than this fraction of
+    /// its absolute value over the last `window` generations
+    pub min_improvement: f64,
+    /// Stop when the standard deviation of the criterion over the population
+    /// falls below this; 0 disables the test
+    pub min_criterion_std: f64,
+    /// Stop after this much wall-clock time, saving a checkpoint first if one
+    /// is configured so the run can be resumed
+    pub max_time: Option<Duration>,
+}
+
+/// Why an optimization stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    /// More than `max_bad_gen` generations in a row without improvement
+    BadGenerations,
+    /// `max_evals` used up before the initial population had a valid
+    /// individual
+    MaxEvals,
+    /// Relative improvement over the window below `min_improvement`
+    SlowImprovement(f64),
+    /// Criterion standard deviation below `min_criterion_std`
+    CriterionSpread(f64),
+    /// Wall-clock limit reached
+    TimeLimit,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::BadGenerations => write!(f, "no improvement for max_bad_gen generations"),
+            StopReason::MaxEvals => write!(f, "max_evals reached without a valid initial individual"),
+            StopReason::SlowImprovement(rel) => write!(f, "relative improvement {:.3e} over the window", rel),
+            StopReason::CriterionSpread(std) => write!(f, "criterion standard deviation {:.3e}", std),
+            StopReason::TimeLimit => write!(f, "time limit"),
+        }
+    }
+}
+
+/// Result of `diff_ev_outcome`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEvOutcome {
+    /// Best parameters, criterion last
+    pub best: Vec<f64>,
+    pub stop: StopReason,
+    /// Generations completed (0 if the initial population failed)
+    pub generations: usize,
+}
+
+/// Where and how often `DiffEvConfig::checkpoint` saves the optimizer state
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint<'a> {
+    pub path: &'a Path,
+    /// Generations between saves; 0 never saves
+    pub every: usize,
+}
+
+/// Optimizer state at the end of a generation, enough to carry on the run
+/// exactly where it stopped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffEvState {
+    pub nvars: usize,
+    pub popsize: usize,
+    /// Generation the resumed run starts with
+    pub generation: usize,
+    pub bad_generations: usize,
+    pub n_tweaked: usize,
+    pub ibest: usize,
+    pub grand_best: f64,
+    /// Best parameters, criterion last
+    pub best: Vec<f64>,
+    /// `popsize` rows of `nvars` parameters and the criterion
+    pub population: Vec<f64>,
+    pub mintrades: i32,
+    rng_q: Vec<u32>,
+    rng_carry: u32,
+    rng_i: u8,
+}
+
+impl DiffEvState {
+    /// Read a state saved by a checkpoint
+    pub fn load(path: &Path) -> Result<DiffEvState, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read checkpoint {}: {}", path.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid checkpoint {}: {}", path.display(), e))
+    }
+
+    /// Write the state, through a temporary file so an interrupted save
+    /// leaves the previous checkpoint intact
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| format!("Cannot write checkpoint {}: {}", path.display(), e))
+    }
+
+    /// Check that the state belongs to a problem of this size
+    fn check(&self, nvars: usize, popsize: usize) -> Result<(), String> {
+        if self.nvars != nvars || self.popsize != popsize {
+            return Err(format!(
+                "Checkpoint has {} variables and population {}, run has {} and {}",
+                self.nvars, self.popsize, nvars, popsize
+            ));
+        }
+        if self.population.len() != popsize * (nvars + 1) || self.best.len() != nvars + 1 || self.ibest >= popsize {
+            return Err("Checkpoint population is inconsistent".to_string());
+        }
+        Ok(())
+    }
+
+    fn rng(&self) -> Result<Mwc256, String> {
+        Mwc256::from_state(&self.rng_q, self.rng_carry, self.rng_i)
+            .ok_or_else(|| "Checkpoint random number state is invalid".to_string())
+    }
 }
 
 /// Differential evolution optimization
@@ -39,6 +190,78 @@ pub fn diff_ev<F>(
 ) -> Result<Vec<f64>, String>
 where
     F: Fn(&[f64], i32) -> f64 + Copy,
+{
+    diff_ev_outcome(criter, config, stoc_bias).map(|outcome| outcome.best)
+}
+
+/// `diff_ev`, also telling why and after how many generations it stopped
+pub fn diff_ev_outcome<F>(
+    criter: F,
+    config: DiffEvConfig,
+    stoc_bias: &mut Option<StocBias>,
+) -> Result<DiffEvOutcome, String>
+where
+    F: Fn(&[f64], i32) -> f64 + Copy,
+{
+    let nvars = config.nvars;
+    let evaluate = |pop: &mut [f64], mintrades: i32| {
+        for individual in pop.chunks_exact_mut(nvars + 1) {
+            individual[nvars] = criter(&individual[..nvars], mintrades);
+        }
+    };
+    optimize(criter, evaluate, false, config, stoc_bias)
+}
+
+/// Differential evolution that evaluates the initial population and each
+/// generation's children on all cores.
+///
+/// `diff_ev` keeps one thread and a fixed order of criterion calls; both draw
+/// the same random numbers, so a seeded run finds the same result either way.
+/// A criterion that updates `stoc_bias` (or any other shared state) is not
+/// `Sync` and must use `diff_ev`.
+pub fn diff_ev_parallel<F>(
+    criter: F,
+    config: DiffEvConfig,
+    stoc_bias: &mut Option<StocBias>,
+) -> Result<Vec<f64>, String>
+where
+    F: Fn(&[f64], i32) -> f64 + Copy + Sync,
+{
+    diff_ev_parallel_outcome(criter, config, stoc_bias).map(|outcome| outcome.best)
+}
+
+/// `diff_ev_parallel`, also telling why and after how many generations it
+/// stopped
+pub fn diff_ev_parallel_outcome<F>(
+    criter: F,
+    config: DiffEvConfig,
+    stoc_bias: &mut Option<StocBias>,
+) -> Result<DiffEvOutcome, String>
+where
+    F: Fn(&[f64], i32) -> f64 + Copy + Sync,
+{
+    let nvars = config.nvars;
+    let evaluate = |pop: &mut [f64], mintrades: i32| {
+        pop.par_chunks_exact_mut(nvars + 1).for_each(|individual| {
+            individual[nvars] = criter(&individual[..nvars], mintrades);
+        });
+    };
+    optimize(criter, evaluate, true, config, stoc_bias)
+}
+
+/// Body of `diff_ev`. `evaluate` sets the criterion (last value) of every
+/// individual of a population, all at once if `parallel`; the hill climbing
+/// still calls `criter`.
+fn optimize<F, E>(
+    criter: F,
+    evaluate: E,
+    parallel: bool,
+    config: DiffEvConfig,
+    stoc_bias: &mut Option<StocBias>,
+) -> Result<DiffEvOutcome, String>
+where
+    F: Fn(&[f64], i32) -> f64 + Copy,
+    E: Fn(&mut [f64], i32),
 {
     let DiffEvConfig {
         nvars,
@@ -54,181 +277,211 @@ where
         low_bounds,
         high_bounds,
         print_progress,
+        min_diversity,
+        restart_frac,
+        init,
+        seed,
+        checkpoint,
+        resume,
+        convergence,
     } = config;
+    let start = Instant::now();
+
+    let mut rng = match &resume {
+        Some(state) => state.rng()?,
+        None => Mwc256::with_seed(seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32)),
+    };
 
     let dim = nvars + 1; // Each case is nvars variables plus criterion
     let mut pop1 = vec![0.0; dim * popsize];
     let mut pop2 = vec![0.0; dim * popsize];
     let mut best = vec![0.0; dim];
 
-    // Generate the initial population
-    let mut failures;
-    let mut n_evals;
-
-    if let Some(sb) = stoc_bias {
-        sb.set_collecting(true);
-    }
-
     let mut grand_best;
     let mut worstf;
     let mut avgf;
-    
-    // Implementing initialization with a while loop to handle retries
-    let mut ind = 0;
-    n_evals = 0;
-    failures = 0;
-    
-    // Initialize variables
-    grand_best = -1.0e60;
-    worstf = 1.0e60;
-    avgf = 0.0;
-    
-    while ind < popsize + overinit {
-        // Create a temporary scope for generating the individual
-        let value = {
-            let popptr_slice = if ind < popsize {
-                &mut pop1[ind * dim..(ind + 1) * dim]
+    let mut ibest;
+    let mut generation;
+    let mut bad_generations;
+    let mut n_tweaked;
+
+    if let Some(state) = resume {
+        // Carry on from a checkpoint instead of generating a population
+        state.check(nvars, popsize)?;
+        pop1.copy_from_slice(&state.population);
+        best.copy_from_slice(&state.best);
+        grand_best = state.grand_best;
+        mintrades = state.mintrades;
+        ibest = state.ibest;
+        generation = state.generation;
+        bad_generations = state.bad_generations;
+        n_tweaked = state.n_tweaked;
+    } else {
+        // Generate the initial population
+        let mut sampler = DesignSampler::new_with(init, nvars, popsize + overinit, &mut || rng.unifrand())?;
+        let mut unit = vec![0.0; nvars];
+        let mut failures;
+        let mut n_evals;
+
+        if let Some(sb) = stoc_bias {
+            sb.set_collecting(true);
+        }
+
+        // Implementing initialization with a while loop to handle retries
+        let mut ind = 0;
+        n_evals = 0;
+        failures = 0;
+
+        // Initialize variables
+        grand_best = -1.0e60;
+        worstf = 1.0e60;
+        avgf = 0.0;
+
+        // Candidates already evaluated, in order. Serial runs evaluate one at a
+        // time; parallel runs evaluate all the individuals still needed at once.
+        let mut pending: VecDeque<Vec<f64>> = VecDeque::new();
+
+        while ind < popsize + overinit {
+            if pending.is_empty() {
+                let n = if parallel { popsize + overinit - ind } else { 1 };
+                let mut candidates = vec![0.0; n * dim];
+                for candidate in candidates.chunks_exact_mut(dim) {
+                    sampler.fill_with(&mut unit, &mut || rng.unifrand());
+                    scale_to_bounds(&unit, nints, low_bounds, high_bounds, &mut candidate[0..nvars]);
+                }
+                evaluate(&mut candidates, mintrades);
+                pending.extend(candidates.chunks_exact(dim).map(|c| c.to_vec()));
+            }
+
+            let current_ind = pending.pop_front().expect("pending is refilled when empty");
+            let value = current_ind[nvars];
+            if ind < popsize {
+                pop1[ind * dim..(ind + 1) * dim].copy_from_slice(&current_ind);
             } else {
-                &mut pop2[0..dim]
-            };
+                pop2[0..dim].copy_from_slice(&current_ind);
+            }
+
+            n_evals += 1;
+
+            if ind == 0 {
+                grand_best = value;
+                worstf = value;
+                avgf = value;
+                best.copy_from_slice(&current_ind);
+            }
+
+            if value <= 0.0 {
+                if n_evals > max_evals {
+                     break; 
+                }
 
-            for i in 0..nvars {
-                if i < nints {
-                    popptr_slice[i] = low_bounds[i]
-                        + (unifrand() * (high_bounds[i] - low_bounds[i] + 1.0)).floor();
-                    if popptr_slice[i] > high_bounds[i] {
-                        popptr_slice[i] = high_bounds[i];
+                failures += 1;
+                if failures >= 500 {
+                    failures = 0;
+                    mintrades = mintrades * 9 / 10;
+                    if mintrades < 1 {
+                        mintrades = 1;
                     }
-                } else {
-                    popptr_slice[i] = low_bounds[i] + (unifrand() * (high_bounds[i] - low_bounds[i]));
+                    // Evaluated with the old minimum
+                    pending.clear();
                 }
+                continue; // Retry this index
+            } else {
+                failures = 0;
             }
 
-            let val = criter(&popptr_slice[0..nvars], mintrades);
-            popptr_slice[nvars] = val;
-            val
-        };
-        
-        n_evals += 1;
+            if value > grand_best {
+                best.copy_from_slice(&current_ind);
+                grand_best = value;
+            }
 
-        // We need to read the parameters again for updating best/printing/overinit
-        // To avoid borrowing issues, we can copy the current individual to a temp buffer
-        let mut current_ind = vec![0.0; dim];
-        if ind < popsize {
-            current_ind.copy_from_slice(&pop1[ind * dim..(ind + 1) * dim]);
-        } else {
-            current_ind.copy_from_slice(&pop2[0..dim]);
-        }
+            if value < worstf {
+                worstf = value;
+            }
 
-        if ind == 0 {
-            grand_best = value;
-            worstf = value;
-            avgf = value;
-            best.copy_from_slice(&current_ind);
-        }
+            avgf += value;
 
-        if value <= 0.0 {
-            if n_evals > max_evals {
-                 break; 
+            if print_progress {
+                let avg = if ind < popsize {
+                    avgf / (ind as f64 + 1.0)
+                } else {
+                    avgf / popsize as f64
+                };
+                print!(
+                    "\n{}: Val={:.4} Best={:.4} Worst={:.4} Avg={:.4}  (fail rate={:.1})",
+                    ind,
+                    value,
+                    grand_best,
+                    worstf,
+                    avg,
+                    n_evals as f64 / (ind as f64 + 1.0)
+                );
+                for val in current_ind.iter().take(nvars) {
+                    print!(" {:.4}", val);
+                }
             }
-            
-            failures += 1;
-            if failures >= 500 {
-                failures = 0;
-                mintrades = mintrades * 9 / 10;
-                if mintrades < 1 {
-                    mintrades = 1;
+
+            // Overinit logic: replace worst in pop1 if current is better
+            if ind >= popsize {
+                avgf = 0.0;
+                let mut min_idx = 0;
+                let mut current_worst = 1.0e60;
+
+                for i in 0..popsize {
+                    let dtemp = pop1[i * dim + nvars];
+                    avgf += dtemp;
+                    if i == 0 || dtemp < current_worst {
+                        min_idx = i;
+                        current_worst = dtemp;
+                    }
+                }
+                worstf = current_worst;
+
+                if value > worstf {
+                    // Replace worst
+                    let dest = &mut pop1[min_idx * dim..(min_idx + 1) * dim];
+                    dest.copy_from_slice(&current_ind);
+                    avgf += value - worstf;
                 }
             }
-            continue; // Retry this index
-        } else {
-            failures = 0;
-        }
 
-        if value > grand_best {
-            best.copy_from_slice(&current_ind);
-            grand_best = value;
+            ind += 1;
         }
 
-        if value < worstf {
-            worstf = value;
+        if n_evals > max_evals && grand_best <= 0.0 {
+             // Failed to find any valid individuals
+             // Return best (which might be garbage) or error?
+             // C++ returns whatever is in best.
+             return Ok(DiffEvOutcome { best, stop: StopReason::MaxEvals, generations: 0 });
         }
 
-        avgf += value;
-
-        if print_progress {
-            let avg = if ind < popsize {
-                avgf / (ind as f64 + 1.0)
-            } else {
-                avgf / popsize as f64
-            };
-            print!(
-                "\n{}: Val={:.4} Best={:.4} Worst={:.4} Avg={:.4}  (fail rate={:.1})",
-                ind,
-                value,
-                grand_best,
-                worstf,
-                avg,
-                n_evals as f64 / (ind as f64 + 1.0)
-            );
-            for val in current_ind.iter().take(nvars) {
-                print!(" {:.4}", val);
-            }
+        if let Some(sb) = stoc_bias {
+            sb.set_collecting(false);
         }
 
-        // Overinit logic: replace worst in pop1 if current is better
-        if ind >= popsize {
-            avgf = 0.0;
-            let mut min_idx = 0;
-            let mut current_worst = 1.0e60;
-
-            for i in 0..popsize {
-                let dtemp = pop1[i * dim + nvars];
-                avgf += dtemp;
-                if i == 0 || dtemp < current_worst {
-                    min_idx = i;
-                    current_worst = dtemp;
-                }
-            }
-            worstf = current_worst;
-
-            if value > worstf {
-                // Replace worst
-                let dest = &mut pop1[min_idx * dim..(min_idx + 1) * dim];
-                dest.copy_from_slice(&current_ind);
-                avgf += value - worstf;
+        // Find best in initial population
+        ibest = 0;
+        let mut value = pop1[nvars];
+        for ind in 1..popsize {
+            let val = pop1[ind * dim + nvars];
+            if val > value {
+                value = val;
+                ibest = ind;
             }
         }
 
-        ind += 1;
-    }
-    
-    if n_evals > max_evals && grand_best <= 0.0 {
-         // Failed to find any valid individuals
-         // Return best (which might be garbage) or error?
-         // C++ returns whatever is in best.
-         return Ok(best);
-    }
 
-    if let Some(sb) = stoc_bias {
-        sb.set_collecting(false);
+        generation = 1;
+        bad_generations = 0;
+        n_tweaked = 0;
     }
 
-    // Find best in initial population
-    let mut ibest = 0;
-    let mut value = pop1[nvars];
-    for ind in 1..popsize {
-        let val = pop1[ind * dim + nvars];
-        if val > value {
-            value = val;
-            ibest = ind;
-        }
-    }
-    
+    // Best criterion at the end of each of the last `convergence.window`
+    // generations, oldest first
+    let mut history = VecDeque::with_capacity(convergence.window + 1);
+    let stop;
+
     // Main loop
-    let mut generation = 1;
-    let mut bad_generations = 0;
-    let mut n_tweaked = 0;
     
     // We need to manage swapping populations.
     // Instead of pointers, we'll use indices or just swap the vectors.
@@ -249,15 +502,15 @@ where
             let mut k;
             
             loop {
-                i = (unifrand() * popsize as f64) as usize;
+                i = (rng.unifrand() * popsize as f64) as usize;
                 if i < popsize && i != ind { break; }
             }
             loop {
-                j = (unifrand() * popsize as f64) as usize;
+                j = (rng.unifrand() * popsize as f64) as usize;
                 if j < popsize && j != ind && j != i { break; }
             }
             loop {
-                k = (unifrand() * popsize as f64) as usize;
+                k = (rng.unifrand() * popsize as f64) as usize;
                 if k < popsize && k != ind && k != i && k != j { break; }
             }
 
@@ -273,7 +526,7 @@ where
             let dest_idx = ind * dim;
             
             // Create child
-            let _start_param = (unifrand() * nvars as f64) as usize;
+            let _start_param = (rng.unifrand() * nvars as f64) as usize;
             let mut used_mutated = false;
             
             // We construct the child in a temporary buffer first to avoid partial updates if we need to revert?
@@ -294,14 +547,28 @@ where
                 // j is the current parameter index being processed
             //}
             
-            let mut curr_param_idx = (unifrand() * nvars as f64) as usize;
+            let mut curr_param_idx = (rng.unifrand() * nvars as f64) as usize;
             if curr_param_idx >= nvars { curr_param_idx = nvars - 1; } // safety
             
             for v in (0..nvars).rev() {
-                 let should_mutate = (v == 0 && !used_mutated) || (unifrand() < pcross);
+                 // Integer parameters use uniform crossover: pcross is tuned for
+                 // the real parameters and would rarely move a lookback
+                 let p_mutate = if curr_param_idx < nints { 0.5 } else { pcross };
+                 let should_mutate = (v == 0 && !used_mutated) || (rng.unifrand() < p_mutate);
                  
                  if should_mutate {
-                     let val = pop1[p2_idx + curr_param_idx] + mutate_dev * (pop1[d1_idx + curr_param_idx] - pop1[d2_idx + curr_param_idx]);
+                     let diff = mutate_dev * (pop1[d1_idx + curr_param_idx] - pop1[d2_idx + curr_param_idx]);
+                     let val = if curr_param_idx < nints {
+                         mutate_integer(
+                             pop1[p2_idx + curr_param_idx],
+                             diff,
+                             low_bounds[curr_param_idx],
+                             high_bounds[curr_param_idx],
+                             &mut || rng.unifrand(),
+                         )
+                     } else {
+                         pop1[p2_idx + curr_param_idx] + diff
+                     };
                      pop2[dest_idx + curr_param_idx] = val;
                      used_mutated = true;
                  } else {
@@ -313,9 +580,15 @@ where
             
             // Ensure legal
             ensure_legal(nvars, nints, low_bounds, high_bounds, &mut pop2[dest_idx..dest_idx+nvars]);
-            
-            // Evaluate
-            let mut child_val = criter(&pop2[dest_idx..dest_idx+nvars], mintrades);
+        } // End of child creation
+
+        // Evaluate all children, then keep each one only if it beats its parent
+        evaluate(&mut pop2, mintrades);
+
+        for ind in 0..popsize {
+            let p1_idx = ind * dim;
+            let dest_idx = ind * dim;
+            let mut child_val = pop2[dest_idx + nvars];
             
             let parent_val = pop1[p1_idx + nvars];
             
@@ -335,201 +608,50 @@ where
             }
             
             // Hill climbing
-            if pclimb > 0.0 && ((ind == ibest && n_tweaked < nvars) || (unifrand() < pclimb)) {
+            if pclimb > 0.0 && ((ind == ibest && n_tweaked < nvars) || (rng.unifrand() < pclimb)) {
                 let k_var = if ind == ibest {
                     n_tweaked += 1;
                     generation % nvars
                 } else {
-                    (unifrand() * nvars as f64) as usize
+                    (rng.unifrand() * nvars as f64) as usize
                 };
                 
                 let k_var = if k_var >= nvars { nvars - 1 } else { k_var };
                 
-                // Optimization logic
-                if k_var < nints {
-                    // Integer optimization
-                    let ibase = pop2[dest_idx + k_var] as i32;
-                    let ilow = low_bounds[k_var] as i32;
-                    let ihigh = high_bounds[k_var] as i32;
-                    let mut success = false;
-                    
-                    if print_progress {
-                         print!("\nCriterion maximization of individual {} integer variable {} from {} = {:.6}", ind, k_var, ibase, child_val);
-                    }
-                    
-                    // Search up
-                    let mut ivar = ibase;
-                    while ivar < ihigh {
-                        ivar += 1;
-                        pop2[dest_idx + k_var] = ivar as f64;
-                        let test_val = criter(&pop2[dest_idx..dest_idx+nvars], mintrades);
-                        if print_progress {
-                            print!("\n  {} = {:.6}", ivar, test_val);
-                        }
-                        if test_val > child_val {
-                            child_val = test_val;
-                            // ibase = ivar; // Update base? C++ updates ibase
-                            success = true;
-                        } else {
-                            pop2[dest_idx + k_var] = if success { ivar as f64 } else { ibase as f64 }; // Revert if failed?
-                            // C++: dest_ptr[k] = ibase; break;
-                            // If we found a better one, ibase was updated?
-                            // C++: if (test_val > value) { value = test_val; ibase = ivar; success = 1; }
-                            // else { dest_ptr[k] = ibase; break; }
-                            // So if we improved, we keep it. If we fail to improve further, we revert to the LAST BEST (ibase).
-                            if success {
-                                 // We are already at ivar which was better? No, we just failed the NEXT step.
-                                 // So we should revert to the previous successful one.
-                                 // But wait, if test_val <= child_val, we revert to ibase (which is the best so far).
-                                 pop2[dest_idx + k_var] = (ivar - 1) as f64; // Actually ibase holds the best
-                                 // Wait, let's trace carefully.
-                                 // ibase starts as original.
-                                 // loop: ivar++
-                                 // if better: ibase = ivar.
-                                 // else: restore ibase. break.
-                                 // So ibase always holds the best value found so far.
-                            }
-                            pop2[dest_idx + k_var] = if success { 
-                                // If we had success, ibase is the new best.
-                                // But we didn't update ibase in this Rust scope yet.
-                                // Let's rewrite this block to be cleaner.
-                                ibase as f64 // This is wrong, ibase needs to be updated.
-                            } else {
-                                ibase as f64
-                            };
-                            break;
-                        }
-                        // If we are here, we improved. Update ibase.
-                        // But I can't update ibase easily if I don't have a mutable variable.
-                    }
-                    
-                    // Let's rewrite the integer search properly
-                    let mut current_best_int = ibase;
-                    let mut current_best_val = child_val;
-                    
-                    // Search Up
-                    let mut ivar = ibase;
-                    while ivar < ihigh {
-                        ivar += 1;
-                        pop2[dest_idx + k_var] = ivar as f64;
-                        let test_val = criter(&pop2[dest_idx..dest_idx+nvars], mintrades);
-                        if print_progress { print!("\n  {} = {:.6}", ivar, test_val); }
-                        
-                        if test_val > current_best_val {
-                            current_best_val = test_val;
-                            current_best_int = ivar;
-                            success = true;
-                        } else {
-                            break;
-                        }
-                    }
-                    
-                    // Restore best found so far (which is current_best_int)
-                    pop2[dest_idx + k_var] = current_best_int as f64;
-                    
-                    if !success {
-                        // Search Down
-                        ivar = ibase;
-                        while ivar > ilow {
-                            ivar -= 1;
-                            pop2[dest_idx + k_var] = ivar as f64;
-                            let test_val = criter(&pop2[dest_idx..dest_idx+nvars], mintrades);
-                            if print_progress { print!("\n  {} = {:.6}", ivar, test_val); }
-                            
-                            if test_val > current_best_val {
-                                current_best_val = test_val;
-                                current_best_int = ivar;
-                                success = true;
-                            } else {
-                                break;
-                            }
-                        }
-                        pop2[dest_idx + k_var] = current_best_int as f64;
-                    }
-                    
-                    child_val = current_best_val;
-                    
-                    if print_progress {
-                        if success {
-                            print!("\nSuccess at {:.0} = {:.6}", pop2[dest_idx + k_var], child_val);
-                        } else {
-                            print!("\nNo success at {:.0} = {:.6}", pop2[dest_idx + k_var], child_val);
-                        }
-                    }
+                let bounds = ParamBounds {
+                    low: low_bounds[k_var],
+                    high: high_bounds[k_var],
+                    integer: k_var < nints,
+                };
+                let old_value = child_val;
 
-                } else {
-                    // Real parameter optimization
-                    let local_base = pop2[dest_idx + k_var];
-                    let old_value = child_val;
-                    
-                    if print_progress {
-                        print!("\nCriterion maximization of individual {} real variable {} from {:.5} = {:.6}", ind, k_var, local_base, child_val);
-                    }
-                    
-                    let mut lower = local_base - 0.1 * (high_bounds[k_var] - low_bounds[k_var]);
-                    let mut upper = local_base + 0.1 * (high_bounds[k_var] - low_bounds[k_var]);
-                    
-                    if lower < low_bounds[k_var] {
-                        lower = low_bounds[k_var];
-                        upper = low_bounds[k_var] + 0.2 * (high_bounds[k_var] - low_bounds[k_var]);
-                    }
-                    if upper > high_bounds[k_var] {
-                        upper = high_bounds[k_var];
-                        lower = high_bounds[k_var] - 0.2 * (high_bounds[k_var] - low_bounds[k_var]);
-                    }
-                    
-                    // We need to pass a closure to glob_max and brentmax that modifies ONLY the k_var parameter
-                    // But we can't easily capture a mutable slice.
-                    // We can copy the parameters to a temporary vector.
-                    let temp_params = pop2[dest_idx..dest_idx+nvars].to_vec();
-                    
-                    let c_func = |param: f64| -> f64 {
-                        let mut my_params = temp_params.clone();
-                        my_params[k_var] = param;
-                        let penalty = ensure_legal(nvars, nints, low_bounds, high_bounds, &mut my_params);
-                        criter(&my_params, mintrades) - penalty
-                    };
-                    
-                    let mut x1 = 0.0;
-                    let mut y1 = 0.0;
-                    let mut x2 = local_base; // Start at current? No, glob_max outputs to x2
-                    let mut y2 = old_value;  // Pass current value? glob_max takes y2 as input if npts < 0
-                    let mut x3 = 0.0;
-                    let mut y3 = 0.0;
-                    
-                    // glob_max(low, high, npts, log_space, func, ...)
-                    // C++: glob_max ( lower , upper , 7 , 0 , c_func , &x1 , &y1 , &x2 , &y2 , &x3 , &y3 )
-                    glob_max(lower, upper, 7, false, c_func, &mut x1, &mut y1, &mut x2, &mut y2, &mut x3, &mut y3);
-                    
-                    // brentmax(itmax, eps, tol, func, xa, xb, xc, y)
-                    // C++: brentmax ( 5 , 1.e-8 , 0.0001 , c_func , &x1 , &x2 , &x3 , y2 )
-                    brentmax(5, 1.0e-8, 0.0001, c_func, &mut x1, &mut x2, &mut x3, y2);
-                    
-                    // Update value
-                    pop2[dest_idx + k_var] = x2;
-                    ensure_legal(nvars, nints, low_bounds, high_bounds, &mut pop2[dest_idx..dest_idx+nvars]);
-                    child_val = criter(&pop2[dest_idx..dest_idx+nvars], mintrades);
-                    
-                    if child_val > old_value {
-                        pop2[dest_idx + nvars] = child_val;
-                        if print_progress {
-                            print!("\nSuccess at {:.5} = {:.6}", pop2[dest_idx + k_var], child_val);
-                        }
-                    } else {
-                        pop2[dest_idx + k_var] = local_base;
-                        child_val = old_value;
-                        if print_progress {
-                            print!("\nNo success at {:.5} = {:.6}", pop2[dest_idx + k_var], child_val);
-                        }
-                    }
-                    
-                    if child_val > grand_best {
-                        grand_best = child_val;
-                        best.copy_from_slice(&pop2[dest_idx..dest_idx+dim]);
-                        ibest = ind;
-                        n_tweaked = 0;
-                        improved = true;
-                    }
+                if print_progress {
+                    print!(
+                        "\nCriterion maximization of individual {} {} variable {} from {:.5} = {:.6}",
+                        ind,
+                        if bounds.integer { "integer" } else { "real" },
+                        k_var,
+                        pop2[dest_idx + k_var],
+                        child_val
+                    );
+                }
+
+                child_val = line_search(&mut pop2[dest_idx..dest_idx + nvars], k_var, &bounds, |params| {
+                    criter(params, mintrades)
+                });
+                pop2[dest_idx + nvars] = child_val;
+
+                if print_progress {
+                    let outcome = if child_val > old_value { "Success" } else { "No success" };
+                    print!("\n{} at {:.5} = {:.6}", outcome, pop2[dest_idx + k_var], child_val);
+                }
+
+                if child_val > grand_best {
+                    grand_best = child_val;
+                    best.copy_from_slice(&pop2[dest_idx..dest_idx+dim]);
+                    ibest = ind;
+                    n_tweaked = 0;
+                    improved = true;
                 }
             }
             
@@ -540,8 +662,17 @@ where
             
         } // End of generation loop (ind)
 
+        let diversity = population_diversity(&pop2, nvars, low_bounds, high_bounds);
+
         if print_progress {
-            print!("\nGen {} Best={:.4} Worst={:.4} Avg={:.4}", generation, grand_best, worstf, avgf / popsize as f64);
+            print!(
+                "\nGen {} Best={:.4} Worst={:.4} Avg={:.4} Div={:.4}",
+                generation,
+                grand_best,
+                worstf,
+                avgf / popsize as f64,
+                diversity
+            );
             for val in best.iter().take(nvars) {
                 print!(" {:.4}", val);
             }
@@ -550,17 +681,86 @@ where
         if !improved {
             bad_generations += 1;
             if bad_generations > max_bad_gen {
+                stop = StopReason::BadGenerations;
                 break;
             }
         } else {
             bad_generations = 0;
         }
+
+        if convergence.window > 0 {
+            history.push_back(grand_best);
+            if history.len() > convergence.window {
+                let then = history.pop_front().unwrap();
+                let rel = (grand_best - then) / then.abs().max(1.0e-60);
+                if rel < convergence.min_improvement {
+                    stop = StopReason::SlowImprovement(rel);
+                    break;
+                }
+            }
+        }
+        if convergence.min_criterion_std > 0.0 {
+            let std = criterion_std(&pop2, nvars);
+            if std < convergence.min_criterion_std {
+                stop = StopReason::CriterionSpread(std);
+                break;
+            }
+        }
+
+        // The population has collapsed onto one point before max_bad_gen ended
+        // the search: scatter its worst members so it can still find other optima
+        if diversity < min_diversity {
+            let n_restart = restart(
+                &criter,
+                &mut pop2,
+                nvars,
+                nints,
+                low_bounds,
+                high_bounds,
+                restart_frac,
+                mintrades,
+                &mut || rng.unifrand(),
+            );
+            if print_progress {
+                print!(
+                    "\nDiversity {:.4} below {:.4}; re-randomized {} individuals",
+                    diversity, min_diversity, n_restart
+                );
+            }
+        }
         
         // Swap populations
         // We can just swap the contents of pop1 and pop2?
         // Or just swap the variable names?
         // In Rust, we can swap the vectors.
         std::mem::swap(&mut pop1, &mut pop2);
+
+        let out_of_time = convergence.max_time.is_some_and(|limit| start.elapsed() >= limit);
+        if let Some(checkpoint) = checkpoint
+            && (out_of_time || (checkpoint.every > 0 && generation % checkpoint.every == 0))
+        {
+            let (rng_q, rng_carry, rng_i) = rng.state();
+            DiffEvState {
+                nvars,
+                popsize,
+                generation: generation + 1,
+                bad_generations,
+                n_tweaked,
+                ibest,
+                grand_best,
+                best: best.clone(),
+                population: pop1.clone(),
+                mintrades,
+                rng_q,
+                rng_carry,
+                rng_i,
+            }
+            .save(checkpoint.path)?;
+        }
+        if out_of_time {
+            stop = StopReason::TimeLimit;
+            break;
+        }
         
         generation += 1;
     } // End of main loop
@@ -575,7 +775,144 @@ where
         let _ = crate::core::io::write::write_file("PARAMCOR.LOG", log_content);
     }
     
-    Ok(best)
+    Ok(DiffEvOutcome { best, stop, generations: generation })
+}
+
+/// Standard deviation of the criterion (last value of each individual) over
+/// a population
+fn criterion_std(pop: &[f64], nvars: usize) -> f64 {
+    let values: Vec<f64> = pop.chunks_exact(nvars + 1).map(|ind| ind[nvars]).collect();
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt()
+}
+
+/// Mean pairwise distance between the members of a population.
+///
+/// `pop` holds individuals of `nvars` parameters followed by their criterion.
+/// Each parameter is scaled by its range and the distance by the square root
+/// of `nvars`, so the result is 0 for a population of identical individuals and
+/// at most 1; a uniformly random population scores about 0.4.
+pub fn population_diversity(pop: &[f64], nvars: usize, low_bounds: &[f64], high_bounds: &[f64]) -> f64 {
+    let dim = nvars + 1;
+    let popsize = pop.len() / dim;
+    if popsize < 2 || nvars == 0 {
+        return 0.0;
+    }
+
+    let scaled: Vec<f64> = pop
+        .chunks_exact(dim)
+        .flat_map(|ind| {
+            (0..nvars).map(move |v| {
+                let range = high_bounds[v] - low_bounds[v];
+                if range > 0.0 {
+                    (ind[v] - low_bounds[v]) / range
+                } else {
+                    0.0
+                }
+            })
+        })
+        .collect();
+
+    let mut total = 0.0;
+    for i in 0..popsize {
+        let a = &scaled[i * nvars..(i + 1) * nvars];
+        for j in (i + 1)..popsize {
+            let b = &scaled[j * nvars..(j + 1) * nvars];
+            let sum_sq: f64 = a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum();
+            total += sum_sq.sqrt();
+        }
+    }
+    let npairs = (popsize * (popsize - 1) / 2) as f64;
+    total / npairs / (nvars as f64).sqrt()
+}
+
+/// Map a point of the unit hypercube to parameters within the bounds; the
+/// first `nints` are integers, each equally likely
+pub(crate) fn scale_to_bounds(unit: &[f64], nints: usize, low_bounds: &[f64], high_bounds: &[f64], params: &mut [f64]) {
+    for (i, (param, &u)) in params.iter_mut().zip(unit).enumerate() {
+        if i < nints {
+            *param = low_bounds[i] + (u * (high_bounds[i] - low_bounds[i] + 1.0)).floor();
+            if *param > high_bounds[i] {
+                *param = high_bounds[i];
+            }
+        } else {
+            *param = low_bounds[i] + u * (high_bounds[i] - low_bounds[i]);
+        }
+    }
+}
+
+/// Draw parameters uniformly within the bounds; the first `nints` are integers
+fn random_individual(
+    nints: usize,
+    low_bounds: &[f64],
+    high_bounds: &[f64],
+    params: &mut [f64],
+    unif: &mut impl FnMut() -> f64,
+) {
+    let unit: Vec<f64> = (0..params.len()).map(|_| unif()).collect();
+    scale_to_bounds(&unit, nints, low_bounds, high_bounds, params);
+}
+
+/// Re-randomize the worst `frac` of the population, never the best individual.
+///
+/// Returns the number of individuals replaced.
+#[allow(clippy::too_many_arguments)]
+fn restart<F>(
+    criter: &F,
+    pop: &mut [f64],
+    nvars: usize,
+    nints: usize,
+    low_bounds: &[f64],
+    high_bounds: &[f64],
+    frac: f64,
+    mintrades: i32,
+    unif: &mut impl FnMut() -> f64,
+) -> usize
+where
+    F: Fn(&[f64], i32) -> f64,
+{
+    let dim = nvars + 1;
+    let popsize = pop.len() / dim;
+    let n_restart = ((frac * popsize as f64).ceil() as usize).min(popsize.saturating_sub(1));
+
+    let mut order: Vec<usize> = (0..popsize).collect();
+    order.sort_by(|&a, &b| pop[a * dim + nvars].total_cmp(&pop[b * dim + nvars]));
+
+    for &ind in order.iter().take(n_restart) {
+        let individual = &mut pop[ind * dim..(ind + 1) * dim];
+        random_individual(nints, low_bounds, high_bounds, &mut individual[..nvars], unif);
+        individual[nvars] = criter(&individual[..nvars], mintrades);
+    }
+    n_restart
+}
+
+/// Mutate an integer parameter by a whole number of unit steps.
+///
+/// Rounding `base + diff` would leave the parameter unchanged whenever the
+/// scaled difference is under 0.5, which on a small range is most of the time.
+/// Instead the step is at least 1 in the direction of `diff`, with a geometric
+/// number of steps whose mean is `|diff|` (or 1). A step past a bound is
+/// reflected back inside. A zero difference leaves `base` unchanged.
+pub(crate) fn mutate_integer(base: f64, diff: f64, low: f64, high: f64, unif: &mut impl FnMut() -> f64) -> f64 {
+    if diff == 0.0 {
+        return base;
+    }
+    let p = 1.0 / diff.abs().max(1.0);
+    let steps = if p >= 1.0 {
+        1.0
+    } else {
+        // Inverse CDF of the geometric distribution on 1, 2, ...
+        1.0 + ((1.0 - unif()).ln() / (1.0 - p).ln()).floor()
+    };
+    let mut value = base + diff.signum() * steps;
+    if value > high {
+        value = high - (value - high);
+    }
+    if value < low {
+        value = low + (low - value);
+    }
+    value.clamp(low, high)
 }
 
 fn ensure_legal(
@@ -643,6 +980,13 @@ mod tests {
             low_bounds: &low_bounds,
             high_bounds: &high_bounds,
             print_progress: false,
+            min_diversity: 0.0,
+            restart_frac: 0.0,
+            init: Design::Uniform,
+            seed: None,
+            checkpoint: None,
+            resume: None,
+            convergence: Convergence::default(),
         };
         
         let result = diff_ev(
@@ -662,4 +1006,335 @@ mod tests {
             // assert!(best[i].abs() < 1.0, "Param {} should be close to 0, got {}", i, best[i]);
         }
     }
+
+    #[test]
+    fn test_diff_ev_parallel() {
+        let criter = |params: &[f64], _mintrades: i32| -> f64 { 10.0 - params.iter().map(|x| x * x).sum::<f64>() };
+        let low_bounds = vec![-5.0; 3];
+        let high_bounds = vec![5.0; 3];
+        let config = || DiffEvConfig {
+            nvars: 3,
+            nints: 0,
+            popsize: 30,
+            overinit: 10,
+            mintrades: 1,
+            max_evals: 10000,
+            max_bad_gen: 30,
+            mutate_dev: 0.5,
+            pcross: 0.5,
+            pclimb: 0.1,
+            low_bounds: &low_bounds,
+            high_bounds: &high_bounds,
+            print_progress: false,
+            min_diversity: 0.0,
+            restart_frac: 0.0,
+            init: Design::Uniform,
+            seed: None,
+            checkpoint: None,
+            resume: None,
+            convergence: Convergence::default(),
+        };
+
+        let best = diff_ev_parallel(criter, config(), &mut None).unwrap();
+        assert!(best[3] > 9.99, "best {:?}", best);
+        assert!(best[..3].iter().all(|x| x.abs() < 0.1));
+        assert_eq!(best[3], criter(&best[..3], 1));
+        assert!(diff_ev(criter, config(), &mut None).unwrap()[3] > 9.99);
+    }
+
+    #[test]
+    fn test_seed_replays_run() {
+        // Rastrigin-like, with a lookback, so every random draw matters
+        let criter = |params: &[f64], _mintrades: i32| -> f64 {
+            let x = params[1];
+            20.0 - (params[0] - 7.0).abs() - x * x + (6.0 * x).cos()
+        };
+        let low_bounds = vec![2.0, -3.0];
+        let high_bounds = vec![20.0, 3.0];
+        let config = |seed, init| DiffEvConfig {
+            nvars: 2,
+            nints: 1,
+            popsize: 20,
+            overinit: 5,
+            mintrades: 1,
+            max_evals: 10000,
+            max_bad_gen: 20,
+            mutate_dev: 0.5,
+            pcross: 0.5,
+            pclimb: 0.2,
+            low_bounds: &low_bounds,
+            high_bounds: &high_bounds,
+            print_progress: false,
+            min_diversity: 0.05,
+            restart_frac: 0.3,
+            init,
+            seed: Some(seed),
+            checkpoint: None,
+            resume: None,
+            convergence: Convergence::default(),
+        };
+        let serial = |seed, init| diff_ev(criter, config(seed, init), &mut None).unwrap();
+        let parallel = |seed, init| diff_ev_parallel(criter, config(seed, init), &mut None).unwrap();
+
+        for init in [Design::Uniform, Design::LatinHypercube, Design::Sobol] {
+            let first = serial(42, init);
+            assert_eq!(first, serial(42, init));
+            // Parallel evaluation draws the same random numbers
+            assert_eq!(first, parallel(42, init));
+        }
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let criter = |params: &[f64], _mintrades: i32| -> f64 {
+            let x = params[1];
+            20.0 - (params[0] - 7.0).abs() - x * x + (6.0 * x).cos()
+        };
+        let low_bounds = vec![2.0, -3.0];
+        let high_bounds = vec![20.0, 3.0];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("de.json");
+        let run = |checkpoint, resume| {
+            let config = DiffEvConfig {
+                nvars: 2,
+                nints: 1,
+                popsize: 20,
+                overinit: 5,
+                mintrades: 1,
+                max_evals: 10000,
+                max_bad_gen: 20,
+                mutate_dev: 0.5,
+                pcross: 0.5,
+                pclimb: 0.2,
+                low_bounds: &low_bounds,
+                high_bounds: &high_bounds,
+                print_progress: false,
+                min_diversity: 0.05,
+                restart_frac: 0.3,
+                init: Design::LatinHypercube,
+                seed: Some(42),
+                checkpoint,
+                resume,
+                convergence: Convergence::default(),
+            };
+            diff_ev(criter, config, &mut None)
+        };
+
+        let full = run(Some(Checkpoint { path: &path, every: 3 }), None).unwrap();
+        let state = DiffEvState::load(&path).unwrap();
+        assert!(state.generation > 1 && state.generation % 3 == 1);
+        // Carrying on from the last checkpoint ends where the full run ended
+        assert_eq!(run(None, Some(state.clone())).unwrap(), full);
+
+        let wrong_size = DiffEvState { nvars: 3, ..state };
+        assert!(run(None, Some(wrong_size)).is_err());
+    }
+
+    #[test]
+    fn test_convergence_criteria() {
+        let criter = |params: &[f64], _mintrades: i32| -> f64 {
+            10.0 - (params[0] - 7.0).abs() - params[1] * params[1]
+        };
+        let low_bounds = vec![2.0, -3.0];
+        let high_bounds = vec![20.0, 3.0];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("de.json");
+        let run = |convergence| {
+            let config = DiffEvConfig {
+                nvars: 2,
+                nints: 1,
+                popsize: 20,
+                overinit: 5,
+                mintrades: 1,
+                max_evals: 10000,
+                max_bad_gen: 1000,
+                mutate_dev: 0.5,
+                pcross: 0.5,
+                pclimb: 0.2,
+                low_bounds: &low_bounds,
+                high_bounds: &high_bounds,
+                print_progress: false,
+                min_diversity: 0.0,
+                restart_frac: 0.3,
+                init: Design::LatinHypercube,
+                seed: Some(3),
+                checkpoint: Some(Checkpoint { path: &path, every: 0 }),
+                resume: None,
+                convergence,
+            };
+            diff_ev_outcome(criter, config, &mut None).unwrap()
+        };
+
+        let slow = run(Convergence { window: 5, min_improvement: 1e-6, ..Convergence::default() });
+        assert!(matches!(slow.stop, StopReason::SlowImprovement(rel) if rel < 1e-6));
+        assert!(slow.generations > 5);
+        assert!((slow.best[2] - 10.0).abs() < 0.1);
+
+        let spread = run(Convergence { min_criterion_std: 1e9, ..Convergence::default() });
+        assert!(matches!(spread.stop, StopReason::CriterionSpread(_)));
+        assert_eq!(spread.generations, 1);
+        assert!(!path.exists());
+
+        // Out of time after the first generation, saved to resume from
+        let timed = run(Convergence { max_time: Some(Duration::ZERO), ..Convergence::default() });
+        assert_eq!((timed.stop, timed.generations), (StopReason::TimeLimit, 1));
+        assert_eq!(DiffEvState::load(&path).unwrap().generation, 2);
+    }
+
+    #[test]
+    fn test_population_diversity() {
+        let low = [0.0, -1.0];
+        let high = [10.0, 1.0];
+        let same = [5.0, 0.0, 1.0, 5.0, 0.0, 2.0, 5.0, 0.0, 3.0];
+        assert_eq!(population_diversity(&same, 2, &low, &high), 0.0);
+
+        // Opposite corners are the farthest apart
+        let corners = [0.0, -1.0, 1.0, 10.0, 1.0, 2.0];
+        assert!((population_diversity(&corners, 2, &low, &high) - 1.0).abs() < 1e-12);
+
+        // Scaling by the range makes parameters of different sizes comparable
+        let along_first = [0.0, 0.0, 0.0, 5.0, 0.0, 0.0];
+        let along_second = [5.0, -1.0, 0.0, 5.0, 0.0, 0.0];
+        assert!(
+            (population_diversity(&along_first, 2, &low, &high)
+                - population_diversity(&along_second, 2, &low, &high))
+            .abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_restart_keeps_best() {
+        let low = [0.0, 0.0];
+        let high = [0.4, 0.4];
+        // No random individual can beat the last one
+        let criter = |params: &[f64], _mintrades: i32| -> f64 { params[0] + params[1] };
+        let mut pop = Vec::new();
+        for value in [0.1, 0.2, 0.3, 0.4] {
+            pop.extend_from_slice(&[value, value, 2.0 * value]);
+        }
+        let n = restart(&criter, &mut pop, 2, 0, &low, &high, 0.5, 0, &mut unifrand);
+        assert_eq!(n, 2);
+        assert_eq!(&pop[6..], &[0.3, 0.3, 0.6, 0.4, 0.4, 0.8]);
+        for ind in pop.chunks_exact(3) {
+            assert_eq!(ind[2], ind[0] + ind[1]);
+        }
+
+        // Even a full restart leaves the best individual in place
+        assert_eq!(restart(&criter, &mut pop, 2, 0, &low, &high, 1.0, 0, &mut unifrand), 3);
+        assert_eq!(&pop[9..], &[0.4, 0.4, 0.8]);
+    }
+
+    #[test]
+    fn test_diff_ev_with_restarts() {
+        // A broad local peak at -3 and a narrow global one at 3, which a
+        // population collapsed onto the local peak cannot reach by mutation
+        let criter = |params: &[f64], _mintrades: i32| -> f64 {
+            let x = params[0];
+            1.0 + (-0.2 * (x + 3.0) * (x + 3.0)).exp() + 2.0 * (-100.0 * (x - 3.0) * (x - 3.0)).exp()
+        };
+        let low_bounds = vec![-5.0];
+        let high_bounds = vec![5.0];
+        let config = DiffEvConfig {
+            nvars: 1,
+            nints: 0,
+            popsize: 10,
+            overinit: 0,
+            mintrades: 1,
+            max_evals: 10000,
+            max_bad_gen: 50,
+            mutate_dev: 0.5,
+            pcross: 0.5,
+            pclimb: 0.0,
+            low_bounds: &low_bounds,
+            high_bounds: &high_bounds,
+            print_progress: false,
+            min_diversity: 0.05,
+            restart_frac: 0.5,
+            init: Design::Uniform,
+            seed: None,
+            checkpoint: None,
+            resume: None,
+            convergence: Convergence::default(),
+        };
+        let best = diff_ev(criter, config, &mut None).unwrap();
+        assert!((best[0] - 3.0).abs() < 0.05, "best x = {}", best[0]);
+        assert!(best[1] > 2.9);
+    }
+
+    #[test]
+    fn test_space_filling_init() {
+        // Record the initial population through the criterion calls
+        let evaluated = std::cell::RefCell::new(Vec::new());
+        let criter = |params: &[f64], _mintrades: i32| -> f64 {
+            evaluated.borrow_mut().push(params.to_vec());
+            1.0
+        };
+        let low_bounds = vec![2.0, -1.0, 0.0];
+        let high_bounds = vec![21.0, 1.0, 10.0];
+
+        for init in [Design::LatinHypercube, Design::Sobol] {
+            evaluated.borrow_mut().clear();
+            let config = DiffEvConfig {
+                nvars: 3,
+                nints: 1,
+                popsize: 20,
+                overinit: 0,
+                mintrades: 1,
+                max_evals: 10000,
+                max_bad_gen: 0,
+                mutate_dev: 0.5,
+                pcross: 0.5,
+                pclimb: 0.0,
+                low_bounds: &low_bounds,
+                high_bounds: &high_bounds,
+                print_progress: false,
+                min_diversity: 0.0,
+                restart_frac: 0.0,
+                init,
+                seed: None,
+                checkpoint: None,
+                resume: None,
+                convergence: Convergence::default(),
+            };
+            diff_ev(criter, config, &mut None).unwrap();
+
+            // Each twentieth of the real ranges, and for the Latin hypercube each
+            // of the 20 lookbacks, is hit once (Sobol: the first 16 points fill
+            // sixteenths)
+            let n = if init == Design::Sobol { 16 } else { 20 };
+            let initial = &evaluated.borrow()[..n];
+            for v in 0..3 {
+                let mut counts = vec![0; n];
+                for params in initial {
+                    let u = (params[v] - low_bounds[v]) / (high_bounds[v] - low_bounds[v] + if v == 0 { 1.0 } else { 0.0 });
+                    counts[(u * n as f64) as usize] += 1;
+                }
+                if v > 0 || init == Design::LatinHypercube {
+                    assert!(counts.iter().all(|&c| c == 1), "{:?} {}: {:?}", init, v, counts);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mutate_integer() {
+        // A small difference still moves the parameter, in its direction
+        for _ in 0..100 {
+            let up = mutate_integer(5.0, 0.2, 2.0, 10.0, &mut unifrand);
+            assert!((6.0..=10.0).contains(&up) && up.fract() == 0.0);
+            let down = mutate_integer(5.0, -0.2, 2.0, 10.0, &mut unifrand);
+            assert!((2.0..=4.0).contains(&down));
+        }
+        assert_eq!(mutate_integer(5.0, 0.0, 2.0, 10.0, &mut unifrand), 5.0);
+
+        // Steps past a bound are reflected
+        assert_eq!(mutate_integer(10.0, 0.5, 2.0, 10.0, &mut unifrand), 9.0);
+        assert_eq!(mutate_integer(2.0, -0.5, 2.0, 10.0, &mut unifrand), 3.0);
+
+        // The mean number of steps follows the scaled difference
+        let n = 20000;
+        let mean: f64 = (0..n).map(|_| mutate_integer(0.0, 4.0, 0.0, 1000.0, &mut unifrand)).sum::<f64>() / n as f64;
+        assert!((mean - 4.0).abs() < 0.2, "mean step {}", mean);
+    }
 }
@@ -0,0 +1,456 @@
+//! Gradient-boosted regression trees.
+//!
+//! A nonlinear alternative to the elastic net of `cd_ma`, taking the same
+//! case-by-variable matrix of indicators. Each tree is fit by least squares
+//! to the residuals of the trees before it and added with a small learning
+//! rate (Friedman's gradient boosting for squared error). With `subsample`
+//! below 1 each tree sees a random fraction of the cases, which decorrelates
+//! the trees (stochastic gradient boosting).
+//!
+//! Splits are searched on histograms: every variable is cut once, before
+//! training, at up to `n_bins - 1` of its quantiles, so a node's best split
+//! costs one pass over its cases per variable, however many distinct values
+//! the indicators take.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::matlib::mwc256::Mwc256;
+use crate::models::learner::Model;
+
+/// Settings of `GradientBoostedTrees::train`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GbtParams {
+    /// Number of trees
+    pub n_trees: usize,
+    /// Shrinkage of each tree's contribution, in (0, 1]
+    pub learning_rate: f64,
+    /// Levels of splits in a tree
+    pub max_depth: usize,
+    /// Fewest cases a leaf may hold
+    pub min_leaf: usize,
+    /// Fraction of the cases each tree is fit to, in (0, 1]
+    pub subsample: f64,
+    /// Most histogram bins per variable, 2 to 65536
+    pub n_bins: usize,
+    /// Seed of the subsamples
+    pub seed: u32,
+}
+
+impl Default for GbtParams {
+    fn default() -> Self {
+        GbtParams {
+            n_trees: 200,
+            learning_rate: 0.05,
+            max_depth: 3,
+            min_leaf: 20,
+            subsample: 0.5,
+            n_bins: 32,
+            seed: 1,
+        }
+    }
+}
+
+/// A node of a tree, indexing the tree's nodes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Node {
+    /// Contribution of the cases reaching the leaf, learning rate applied
+    Leaf(f64),
+    /// Cases with variable `var` at most `threshold` go left
+    Split { var: usize, threshold: f64, left: usize, right: usize },
+}
+
+/// A regression tree, its root first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn predict(&self, x: &[f64]) -> f64 {
+        let mut inode = 0;
+        loop {
+            match self.nodes[inode] {
+                Node::Leaf(value) => return value,
+                Node::Split { var, threshold, left, right } => {
+                    inode = if x[var] <= threshold { left } else { right };
+                }
+            }
+        }
+    }
+}
+
+/// Gradient-boosted regression trees trained by least squares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradientBoostedTrees {
+    pub params: GbtParams,
+    /// Mean of the training targets, the prediction before any tree
+    pub base: f64,
+    /// In-sample fraction of the target variance explained
+    pub explained: f64,
+    /// Standard deviation of the training targets
+    #[serde(default)]
+    pub target_std: f64,
+    /// Share of the total squared-error reduction of all splits made on each
+    /// variable
+    pub importance: Vec<f64>,
+    nvars: usize,
+    trees: Vec<Tree>,
+}
+
+impl GradientBoostedTrees {
+    /// Train on `ncases` rows of `nvars` predictors in `xx`, row by row, and
+    /// their targets `yy`
+    pub fn train(nvars: usize, ncases: usize, xx: &[f64], yy: &[f64], params: GbtParams) -> Result<Self, String> {
+        Self::train_weighted(nvars, ncases, xx, yy, None, params)
+    }
+
+    /// `train` with case weights `ww`: the squared errors, and so the leaf
+    /// values and the gains of the splits, are weighted. `min_leaf` still
+    /// counts cases.
+    pub fn train_weighted(
+        nvars: usize,
+        ncases: usize,
+        xx: &[f64],
+        yy: &[f64],
+        ww: Option<&[f64]>,
+        params: GbtParams,
+    ) -> Result<Self, String> {
+        if nvars == 0 || ncases == 0 {
+            return Err("No cases or variables to train on".to_string());
+        }
+        if xx.len() < ncases * nvars || yy.len() < ncases || ww.is_some_and(|w| w.len() < ncases) {
+            return Err(format!("Expected {} cases of {} variables", ncases, nvars));
+        }
+        if ww.is_some_and(|w| w[..ncases].iter().any(|&w| w < 0.0) || w[..ncases].iter().sum::<f64>() <= 0.0) {
+            return Err("Case weights must not be negative and must not all be 0".to_string());
+        }
+        if !(params.learning_rate > 0.0 && params.learning_rate <= 1.0) {
+            return Err(format!("Learning rate must be in (0, 1], got {}", params.learning_rate));
+        }
+        if !(params.subsample > 0.0 && params.subsample <= 1.0) {
+            return Err(format!("Subsample fraction must be in (0, 1], got {}", params.subsample));
+        }
+        if !(2..=65536).contains(&params.n_bins) {
+            return Err(format!("Number of bins must be 2 to 65536, got {}", params.n_bins));
+        }
+
+        let (cuts, bins) = bin_variables(nvars, ncases, xx, params.n_bins);
+        let weight = |i: usize| ww.map_or(1.0, |w| w[i]);
+        let wsum: f64 = (0..ncases).map(weight).sum();
+        let base = (0..ncases).map(|i| weight(i) * yy[i]).sum::<f64>() / wsum;
+        let mut fitted = vec![base; ncases];
+        let mut resid: Vec<f64> = yy[..ncases].iter().map(|&y| y - base).collect();
+        let mut importance = vec![0.0; nvars];
+        let mut trees = Vec::with_capacity(params.n_trees);
+
+        let mut rng = Mwc256::with_seed(params.seed);
+        let n_sample = ((params.subsample * ncases as f64).round() as usize).clamp(1, ncases);
+        let mut order: Vec<usize> = (0..ncases).collect();
+
+        for _ in 0..params.n_trees {
+            // Partial Fisher-Yates shuffle: the first n_sample cases of
+            // `order` are a sample without replacement
+            if n_sample < ncases {
+                for i in 0..n_sample {
+                    let j = i + (rng.rand32() as usize) % (ncases - i);
+                    order.swap(i, j);
+                }
+            }
+            let mut cases = order[..n_sample].to_vec();
+
+            let mut grower = Grower {
+                nvars,
+                cuts: &cuts,
+                bins: &bins,
+                resid: &resid,
+                weights: ww,
+                params: &params,
+                importance: &mut importance,
+                nodes: Vec::new(),
+            };
+            grower.grow(&mut cases, 0);
+            let tree = Tree { nodes: grower.nodes };
+
+            for icase in 0..ncases {
+                fitted[icase] += tree.predict(&xx[icase * nvars..(icase + 1) * nvars]);
+                resid[icase] = yy[icase] - fitted[icase];
+            }
+            trees.push(tree);
+        }
+
+        let total: f64 = (0..ncases).map(|i| weight(i) * (yy[i] - base).powi(2)).sum::<f64>() + 1.0e-60;
+        let explained = 1.0 - (0..ncases).map(|i| weight(i) * resid[i] * resid[i]).sum::<f64>() / total;
+        let target_std = (total / wsum).sqrt();
+        let gain: f64 = importance.iter().sum();
+        if gain > 0.0 {
+            for imp in importance.iter_mut() {
+                *imp /= gain;
+            }
+        }
+
+        Ok(GradientBoostedTrees { params, base, explained, target_std, importance, nvars, trees })
+    }
+
+    /// Prediction for one case of predictors
+    pub fn predict(&self, x: &[f64]) -> f64 {
+        self.base + self.trees.iter().map(|tree| tree.predict(&x[..self.nvars])).sum::<f64>()
+    }
+
+    /// Number of trees trained
+    pub fn n_trees(&self) -> usize {
+        self.trees.len()
+    }
+}
+
+impl Model for GradientBoostedTrees {
+    type Params = GbtParams;
+
+    fn fit(
+        nvars: usize,
+        ncases: usize,
+        xx: &[f64],
+        yy: &[f64],
+        ww: Option<&[f64]>,
+        params: &GbtParams,
+    ) -> Result<Self, String> {
+        Self::train_weighted(nvars, ncases, xx, yy, ww, *params)
+    }
+
+    fn predict(&self, x: &[f64]) -> f64 {
+        GradientBoostedTrees::predict(self, x)
+    }
+
+    fn score_scale(&self) -> f64 {
+        self.target_std
+    }
+
+    fn explained(&self) -> f64 {
+        self.explained
+    }
+
+    fn importances(&self) -> Vec<f64> {
+        self.importance.clone()
+    }
+}
+
+/// Cut points of each variable at its quantiles, and the bin of every value:
+/// the number of cut points below it, so a value is at most the cut point of
+/// its bin
+fn bin_variables(nvars: usize, ncases: usize, xx: &[f64], n_bins: usize) -> (Vec<Vec<f64>>, Vec<u16>) {
+    let mut cuts = Vec::with_capacity(nvars);
+    let mut bins = vec![0u16; ncases * nvars];
+    let mut sorted = vec![0.0; ncases];
+    for ivar in 0..nvars {
+        for icase in 0..ncases {
+            sorted[icase] = xx[icase * nvars + ivar];
+        }
+        sorted.sort_by(f64::total_cmp);
+        let mut var_cuts: Vec<f64> = (1..n_bins).map(|k| sorted[(k * ncases / n_bins).min(ncases - 1)]).collect();
+        var_cuts.dedup();
+        // The largest value would leave its bin's right side empty
+        if var_cuts.last() == sorted.last() {
+            var_cuts.pop();
+        }
+        for icase in 0..ncases {
+            let x = xx[icase * nvars + ivar];
+            bins[icase * nvars + ivar] = var_cuts.partition_point(|&c| c < x) as u16;
+        }
+        cuts.push(var_cuts);
+    }
+    (cuts, bins)
+}
+
+/// Builds one tree on the residuals of the trees before it
+struct Grower<'a> {
+    nvars: usize,
+    cuts: &'a [Vec<f64>],
+    bins: &'a [u16],
+    resid: &'a [f64],
+    weights: Option<&'a [f64]>,
+    params: &'a GbtParams,
+    importance: &'a mut [f64],
+    nodes: Vec<Node>,
+}
+
+impl Grower<'_> {
+    fn weight(&self, icase: usize) -> f64 {
+        self.weights.map_or(1.0, |w| w[icase])
+    }
+
+    /// Grow the subtree of `cases` at `depth`, returning its root's index
+    fn grow(&mut self, cases: &mut [usize], depth: usize) -> usize {
+        let inode = self.nodes.len();
+        let n = cases.len();
+        let wsum: f64 = cases.iter().map(|&i| self.weight(i)).sum();
+        let sum: f64 = cases.iter().map(|&i| self.weight(i) * self.resid[i]).sum();
+        let mean = if wsum > 0.0 { sum / wsum } else { 0.0 };
+        self.nodes.push(Node::Leaf(self.params.learning_rate * mean));
+        if depth >= self.params.max_depth || n < 2 * self.params.min_leaf.max(1) || wsum <= 0.0 {
+            return inode;
+        }
+
+        let Some((var, bin, gain)) = self.best_split(cases, sum, wsum) else {
+            return inode;
+        };
+        self.importance[var] += gain;
+
+        // Cases of the left side first
+        let mut nleft = 0;
+        for k in 0..n {
+            if self.bins[cases[k] * self.nvars + var] as usize <= bin {
+                cases.swap(k, nleft);
+                nleft += 1;
+            }
+        }
+        let (left_cases, right_cases) = cases.split_at_mut(nleft);
+        let left = self.grow(left_cases, depth + 1);
+        let right = self.grow(right_cases, depth + 1);
+        self.nodes[inode] = Node::Split { var, threshold: self.cuts[var][bin], left, right };
+        inode
+    }
+
+    /// Variable, last bin of the left side and squared-error reduction of the
+    /// best split of `cases`, whose weighted residuals sum to `sum` and
+    /// weights to `wsum`; `None` if no split leaves `min_leaf` cases on both
+    /// sides and reduces the error
+    fn best_split(&self, cases: &[usize], sum: f64, wsum: f64) -> Option<(usize, usize, f64)> {
+        let n = cases.len();
+        let min_leaf = self.params.min_leaf.max(1);
+        let parent = sum * sum / wsum;
+        let mut best: Option<(usize, usize, f64)> = None;
+        for ivar in 0..self.nvars {
+            let nbins = self.cuts[ivar].len() + 1;
+            if nbins < 2 {
+                continue;
+            }
+            let mut sums = vec![0.0; nbins];
+            let mut wsums = vec![0.0; nbins];
+            let mut counts = vec![0usize; nbins];
+            for &icase in cases {
+                let b = self.bins[icase * self.nvars + ivar] as usize;
+                let w = self.weight(icase);
+                sums[b] += w * self.resid[icase];
+                wsums[b] += w;
+                counts[b] += 1;
+            }
+            let (mut sum_left, mut w_left, mut n_left) = (0.0, 0.0, 0);
+            for b in 0..nbins - 1 {
+                sum_left += sums[b];
+                w_left += wsums[b];
+                n_left += counts[b];
+                let n_right = n - n_left;
+                if n_left < min_leaf {
+                    continue;
+                }
+                if n_right < min_leaf {
+                    break;
+                }
+                let (sum_right, w_right) = (sum - sum_left, wsum - w_left);
+                if w_left <= 0.0 || w_right <= 0.0 {
+                    continue;
+                }
+                let gain = sum_left * sum_left / w_left + sum_right * sum_right / w_right - parent;
+                if gain > best.map_or(1.0e-12 * parent.abs().max(1.0e-30), |(_, _, g)| g) {
+                    best = Some((ivar, b, gain));
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cases of two variables whose target is a step in the first and
+    /// quadratic in the second, which no linear model fits
+    fn nonlinear(ncases: usize) -> (Vec<f64>, Vec<f64>) {
+        let mut xx = Vec::with_capacity(2 * ncases);
+        let mut yy = Vec::with_capacity(ncases);
+        for i in 0..ncases {
+            let x0 = ((i * 37) % 101) as f64 / 50.0 - 1.0;
+            let x1 = ((i * 53) % 97) as f64 / 48.0 - 1.0;
+            xx.extend([x0, x1]);
+            yy.push(if x0 > 0.2 { 1.0 } else { -1.0 } + x1 * x1 + 0.05 * ((i % 7) as f64 - 3.0));
+        }
+        (xx, yy)
+    }
+
+    #[test]
+    fn test_fits_nonlinear_target() {
+        let (xx, yy) = nonlinear(500);
+        let params = GbtParams { n_trees: 150, learning_rate: 0.1, min_leaf: 5, ..GbtParams::default() };
+        let model = GradientBoostedTrees::train(2, 500, &xx, &yy, params).unwrap();
+        assert_eq!(model.n_trees(), 150);
+        assert!(model.explained > 0.95, "explained {}", model.explained);
+        assert!(model.importance[0] > model.importance[1]);
+        assert!((model.importance.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        // The step and the parabola away from the training cases
+        assert!(model.predict(&[0.8, 0.0]) - model.predict(&[-0.8, 0.0]) > 1.5);
+        assert!(model.predict(&[0.8, 0.9]) > model.predict(&[0.8, 0.0]) + 0.4);
+
+        // A linear fit explains far less
+        let mut cd = crate::models::cd_ma::CoordinateDescent::new(2, 500, false, true, 0);
+        cd.get_data(0, 500, &xx, &yy, None);
+        cd.core_train(1.0, 0.0, 1000, 1e-9, true, false);
+        assert!(Model::explained(&cd) < 0.8);
+    }
+
+    #[test]
+    fn test_seed_and_serde() {
+        let (xx, yy) = nonlinear(300);
+        let params = GbtParams { n_trees: 30, ..GbtParams::default() };
+        let a = GradientBoostedTrees::train(2, 300, &xx, &yy, params).unwrap();
+        let b = GradientBoostedTrees::train(2, 300, &xx, &yy, params).unwrap();
+        let c = GradientBoostedTrees::train(2, 300, &xx, &yy, GbtParams { seed: 2, ..params }).unwrap();
+        let x = [0.3, -0.4];
+        assert_eq!(a.predict(&x), b.predict(&x));
+        assert_ne!(a.trees, c.trees);
+
+        let json = serde_json::to_string(&a).unwrap();
+        let back: GradientBoostedTrees = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.predict(&x), a.predict(&x));
+
+        assert!(GradientBoostedTrees::train(2, 300, &xx, &yy, GbtParams { learning_rate: 0.0, ..params }).is_err());
+        assert!(GradientBoostedTrees::train(2, 300, &xx, &yy, GbtParams { subsample: 1.5, ..params }).is_err());
+        assert!(GradientBoostedTrees::train(2, 400, &xx, &yy, params).is_err());
+    }
+
+    #[test]
+    fn test_case_weights() {
+        // The first half of the cases follows one rule, the second the
+        // opposite; weights pick which one the trees learn
+        let ncases = 400;
+        let xx: Vec<f64> = (0..ncases).map(|i| ((i * 37) % 101) as f64 / 50.0 - 1.0).collect();
+        let yy: Vec<f64> = (0..ncases).map(|i| if i < 200 { xx[i] } else { -xx[i] }).collect();
+        let recent: Vec<f64> = (0..ncases).map(|i| if i < 200 { 0.0 } else { 1.0 }).collect();
+        let params = GbtParams { n_trees: 50, learning_rate: 0.2, subsample: 1.0, min_leaf: 5, ..GbtParams::default() };
+
+        let even = GradientBoostedTrees::train(1, ncases, &xx, &yy, params).unwrap();
+        let weighted = GradientBoostedTrees::train_weighted(1, ncases, &xx, &yy, Some(&recent), params).unwrap();
+        assert!(even.predict(&[0.8]).abs() < 0.2);
+        assert!(weighted.predict(&[0.8]) < -0.6);
+        assert!(weighted.explained > 0.9);
+        assert!((weighted.target_std - (yy[200..].iter().map(|y| y * y).sum::<f64>() / 200.0).sqrt()).abs() < 0.05);
+
+        // Weighting every case alike changes nothing
+        let ones = vec![3.0; ncases];
+        let same = GradientBoostedTrees::train_weighted(1, ncases, &xx, &yy, Some(&ones), params).unwrap();
+        assert!((same.predict(&[0.8]) - even.predict(&[0.8])).abs() < 1e-9);
+        assert!(GradientBoostedTrees::train_weighted(1, ncases, &xx, &yy, Some(&[-1.0; 400]), params).is_err());
+    }
+
+    #[test]
+    fn test_constant_variable_never_splits() {
+        let ncases = 200;
+        let xx: Vec<f64> = (0..ncases).flat_map(|i| [1.0, i as f64]).collect();
+        let yy: Vec<f64> = (0..ncases).map(|i| if i < 100 { 0.0 } else { 1.0 }).collect();
+        let params = GbtParams { n_trees: 10, max_depth: 1, subsample: 1.0, ..GbtParams::default() };
+        let model = GradientBoostedTrees::train(2, ncases, &xx, &yy, params).unwrap();
+        assert_eq!(model.importance, vec![0.0, 1.0]);
+        assert!(model.predict(&[1.0, 150.0]) > model.predict(&[1.0, 50.0]));
+    }
+}
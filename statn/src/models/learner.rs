@@ -0,0 +1,253 @@
+//! Common interface of the predictive models.
+//!
+//! Training, evaluation and backtesting code written against `Model` takes
+//! any learner, so the linear elastic net and nonlinear models such as
+//! gradient-boosted trees are fit, compared and traded on the same features.
+//! A new learner needs only an implementation of the trait.
+
+use crate::models::cd_ma::{CoordinateDescent, Family};
+
+/// A predictive model of a target from one case of predictors
+pub trait Model: Sized {
+    /// Settings of a fit
+    type Params;
+
+    /// Fit to `ncases` rows of `nvars` predictors in `xx`, row by row, and
+    /// their targets `yy`, with optional case weights `ww`
+    fn fit(
+        nvars: usize,
+        ncases: usize,
+        xx: &[f64],
+        yy: &[f64],
+        ww: Option<&[f64]>,
+        params: &Self::Params,
+    ) -> Result<Self, String>;
+
+    /// Prediction for one case of raw (unstandardized) predictors: the
+    /// target, or for a classifier the probability that it is positive
+    fn predict(&self, x: &[f64]) -> f64;
+
+    /// Score of one case whose sign is the side the model takes: the
+    /// prediction of a regression, the log-odds of a classifier
+    fn score(&self, x: &[f64]) -> f64 {
+        self.predict(x)
+    }
+
+    /// Unit of the score for signal thresholds: the standard deviation of the
+    /// training targets of a regression, 1 for log-odds
+    fn score_scale(&self) -> f64;
+
+    /// In-sample fraction of the target variance explained (of the deviance
+    /// for a classifier)
+    fn explained(&self) -> f64;
+
+    /// Weight of each predictor in the model, comparable across predictors:
+    /// coefficients of the standardized predictors of a linear model, shares
+    /// of the error reduction of trees
+    fn importances(&self) -> Vec<f64>;
+}
+
+/// Settings of an elastic-net fit at one lambda
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CdParams {
+    pub family: Family,
+    /// Mix of the lasso (1) and ridge (0) penalties, in (0, 1]
+    pub alpha: f64,
+    pub lambda: f64,
+    pub max_iterations: usize,
+    pub tolerance: f64,
+}
+
+impl Default for CdParams {
+    fn default() -> Self {
+        CdParams { family: Family::Gaussian, alpha: 0.5, lambda: 0.0, max_iterations: 1000, tolerance: 1e-7 }
+    }
+}
+
+impl Model for CoordinateDescent {
+    type Params = CdParams;
+
+    fn fit(
+        nvars: usize,
+        ncases: usize,
+        xx: &[f64],
+        yy: &[f64],
+        ww: Option<&[f64]>,
+        params: &CdParams,
+    ) -> Result<Self, String> {
+        if nvars == 0 || ncases == 0 {
+            return Err("No cases or variables to train on".to_string());
+        }
+        if xx.len() < ncases * nvars || yy.len() < ncases || ww.is_some_and(|w| w.len() < ncases) {
+            return Err(format!("Expected {} cases of {} variables", ncases, nvars));
+        }
+        let mut model = match params.family {
+            Family::Gaussian => CoordinateDescent::new(nvars, ncases, ww.is_some(), true, 0),
+            Family::Binomial => CoordinateDescent::binomial(nvars, ncases, ww.is_some(), 0),
+        };
+        model.get_data(0, ncases, xx, yy, ww);
+        model.core_train(params.alpha, params.lambda, params.max_iterations, params.tolerance, true, false);
+        Ok(model)
+    }
+
+    fn predict(&self, x: &[f64]) -> f64 {
+        CoordinateDescent::predict(self, x)
+    }
+
+    fn score(&self, x: &[f64]) -> f64 {
+        CoordinateDescent::score(self, x)
+    }
+
+    fn score_scale(&self) -> f64 {
+        self.yscale
+    }
+
+    fn explained(&self) -> f64 {
+        self.explained
+    }
+
+    fn importances(&self) -> Vec<f64> {
+        self.beta.clone()
+    }
+}
+
+/// Out-of-sample performance of each candidate setting of a learner
+#[derive(Debug, Clone, PartialEq)]
+pub struct CvSelection {
+    /// Index of the best candidate
+    pub best: usize,
+    /// Mean out-of-sample return per case of each candidate
+    pub oos: Vec<f64>,
+}
+
+/// Cross-validation of any `Model` over a grid of candidate settings.
+///
+/// The cases are split into `nfolds` contiguous folds, in their
+/// (chronological) order. Each candidate is fit to the cases outside a fold
+/// and takes the side of its `score` on every case of the fold, long if
+/// positive and short if negative; its criterion is the mean target so
+/// earned, weighted by `ww` if given. A criterion of positions rather than
+/// of squared error scores regressions and classifiers alike.
+pub fn cv_select<M: Model>(
+    nvars: usize,
+    ncases: usize,
+    xx: &[f64],
+    yy: &[f64],
+    ww: Option<&[f64]>,
+    nfolds: usize,
+    candidates: &[M::Params],
+) -> Result<CvSelection, String> {
+    if candidates.is_empty() {
+        return Err("No candidate settings to cross-validate".to_string());
+    }
+    if nfolds < 2 || ncases < 2 * nfolds {
+        return Err(format!("Cannot split {} cases into {} folds", ncases, nfolds));
+    }
+    if xx.len() < ncases * nvars || yy.len() < ncases || ww.is_some_and(|w| w.len() < ncases) {
+        return Err(format!("Expected {} cases of {} variables", ncases, nvars));
+    }
+
+    let weight = |i: usize| ww.map_or(1.0, |w| w[i]);
+    let mut earned = vec![0.0; candidates.len()];
+    let mut wsum = 0.0;
+    for ifold in 0..nfolds {
+        let (start, stop) = (ifold * ncases / nfolds, (ifold + 1) * ncases / nfolds);
+        let is_cases: Vec<usize> = (0..start).chain(stop..ncases).collect();
+        let is_xx: Vec<f64> = is_cases.iter().flat_map(|&i| &xx[i * nvars..(i + 1) * nvars]).copied().collect();
+        let is_yy: Vec<f64> = is_cases.iter().map(|&i| yy[i]).collect();
+        let is_ww: Option<Vec<f64>> = ww.map(|_| is_cases.iter().map(|&i| weight(i)).collect());
+
+        for (candidate, total) in candidates.iter().zip(earned.iter_mut()) {
+            let model = M::fit(nvars, is_cases.len(), &is_xx, &is_yy, is_ww.as_deref(), candidate)?;
+            for i in start..stop {
+                let score = model.score(&xx[i * nvars..(i + 1) * nvars]);
+                let side = if score > 0.0 { 1.0 } else if score < 0.0 { -1.0 } else { 0.0 };
+                *total += weight(i) * side * yy[i];
+            }
+        }
+        wsum += (start..stop).map(weight).sum::<f64>();
+    }
+
+    let oos: Vec<f64> = earned.iter().map(|&e| e / wsum.max(f64::MIN_POSITIVE)).collect();
+    let best = (0..oos.len()).fold(0, |best, i| if oos[i] > oos[best] { i } else { best });
+    Ok(CvSelection { best, oos })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::gbt::{GbtParams, GradientBoostedTrees};
+
+    /// Fit any model and return its predictions on the training cases
+    fn fit_and_predict<M: Model>(xx: &[f64], yy: &[f64], params: &M::Params) -> (M, Vec<f64>) {
+        let model = M::fit(2, yy.len(), xx, yy, None, params).unwrap();
+        let preds = xx.chunks_exact(2).map(|x| model.predict(x)).collect();
+        (model, preds)
+    }
+
+    #[test]
+    fn test_models_share_interface() {
+        let ncases = 200;
+        let mut xx = Vec::with_capacity(2 * ncases);
+        let mut yy = Vec::with_capacity(ncases);
+        for i in 0..ncases {
+            let x0 = ((i * 37) % 101) as f64 / 50.0 - 1.0;
+            let x1 = ((i * 53) % 97) as f64 / 48.0 - 1.0;
+            xx.extend([x0, x1]);
+            yy.push(2.0 * x0 + 0.1 * ((i % 5) as f64 - 2.0));
+        }
+
+        let (cd, cd_preds) =
+            fit_and_predict::<CoordinateDescent>(&xx, &yy, &CdParams { alpha: 1.0, ..CdParams::default() });
+        let (gbt, gbt_preds) = fit_and_predict::<GradientBoostedTrees>(&xx, &yy, &GbtParams::default());
+        for (model_preds, explained) in [(&cd_preds, cd.explained()), (&gbt_preds, gbt.explained())] {
+            assert!(explained > 0.9);
+            assert_eq!(model_preds[0] > model_preds[1], xx[0] > xx[2]);
+        }
+        // Both put nearly all their weight on x0
+        assert!(cd.importances()[0].abs() > 10.0 * cd.importances()[1].abs());
+        assert!(gbt.importances()[0] > 0.9);
+        assert!((cd.score_scale() - gbt.score_scale()).abs() < 1e-12);
+        assert_eq!(Model::score(&cd, &xx[..2]), cd_preds[0]);
+
+        // A classifier scores in log-odds and predicts probabilities
+        let params = CdParams { family: Family::Binomial, alpha: 1.0, lambda: 0.05, ..CdParams::default() };
+        let logit = CoordinateDescent::fit(2, ncases, &xx, &yy, None, &params).unwrap();
+        let (p, s) = (Model::predict(&logit, &[0.2, 0.0]), Model::score(&logit, &[0.2, 0.0]));
+        assert!(p > 0.5 && s > 0.0);
+        assert!((p - 1.0 / (1.0 + (-s).exp())).abs() < 1e-12);
+
+        assert!(CoordinateDescent::fit(2, ncases + 1, &xx, &yy, None, &CdParams::default()).is_err());
+    }
+
+    #[test]
+    fn test_cv_select() {
+        // The target follows x0 with some noise
+        let ncases = 200;
+        let mut xx = Vec::with_capacity(2 * ncases);
+        let mut yy = Vec::with_capacity(ncases);
+        for i in 0..ncases {
+            let x0 = ((i * 37) % 101) as f64 / 50.0 - 1.0;
+            let x1 = ((i * 53) % 97) as f64 / 48.0 - 1.0;
+            xx.extend([x0, x1]);
+            yy.push(x0 + 0.1 * ((i % 7) as f64 - 3.0));
+        }
+
+        // A lambda so large every coefficient is zero only trades the mean
+        let fit = CdParams { alpha: 1.0, lambda: 0.001, ..CdParams::default() };
+        let empty = CdParams { alpha: 1.0, lambda: 100.0, ..CdParams::default() };
+        let cv = cv_select::<CoordinateDescent>(2, ncases, &xx, &yy, None, 5, &[empty, fit]).unwrap();
+        assert_eq!(cv.best, 1);
+        assert!(cv.oos[0].abs() < 0.05);
+        assert!(cv.oos[1] > 0.3);
+
+        // Trees are selected the same way
+        let trees = [GbtParams { n_trees: 1, max_depth: 1, ..GbtParams::default() }, GbtParams::default()];
+        let cv = cv_select::<GradientBoostedTrees>(2, ncases, &xx, &yy, None, 5, &trees).unwrap();
+        assert_eq!(cv.oos.len(), 2);
+        assert!(cv.oos.iter().all(|&r| r > 0.0));
+
+        assert!(cv_select::<CoordinateDescent>(2, ncases, &xx, &yy, None, 5, &[]).is_err());
+        assert!(cv_select::<CoordinateDescent>(2, 8, &xx, &yy, None, 5, &[fit]).is_err());
+    }
+}
@@ -1,2 +1,9 @@
 pub mod cd_ma;
-pub mod differential_evolution;
\ No newline at end of file
+pub mod cma_es;
+pub mod differential_evolution;
+pub mod gbt;
+pub mod learner;
+pub mod multi_objective;
+pub mod pso;
+pub mod refine;
+pub mod search;
@@ -0,0 +1,331 @@
+//! Multi-objective differential evolution.
+//!
+//! `diff_ev` needs one scalar criterion, so competing goals such as mean
+//! return and drawdown must be folded into it with a fixed penalty.
+//! `diff_ev_pareto` keeps them apart: it evolves the population with the DE
+//! mutation and crossover of `diff_ev` and selects survivors by
+//! non-dominated sorting and crowding distance (NSGA-II), returning the
+//! Pareto set so the trade-off can be chosen afterwards.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::core::io::write::write_file;
+use crate::core::matlib::mwc256::Mwc256;
+use crate::core::matlib::rands::unifrand;
+use crate::models::differential_evolution::{mutate_integer, scale_to_bounds};
+
+/// Configuration of `diff_ev_pareto`
+#[derive(Debug, Clone, Copy)]
+pub struct ParetoConfig<'a> {
+    pub nvars: usize,
+    /// The first `nints` parameters are integers
+    pub nints: usize,
+    pub popsize: usize,
+    /// Generations run
+    pub max_gens: usize,
+    /// Passed to the criterion
+    pub mintrades: i32,
+    /// Scale of the difference vector of the mutation
+    pub mutate_dev: f64,
+    /// Probability of taking each parameter from the mutant
+    pub pcross: f64,
+    pub low_bounds: &'a [f64],
+    pub high_bounds: &'a [f64],
+    pub print_progress: bool,
+    /// Seed of the random numbers; `None` draws one
+    pub seed: Option<u32>,
+}
+
+/// Member of a Pareto set
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParetoPoint {
+    pub params: Vec<f64>,
+    /// Objective values, all maximized
+    pub objectives: Vec<f64>,
+}
+
+/// Whether `a` is at least as good as `b` in every objective and better in
+/// one (all maximized)
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+/// Split points into fronts: the first holds the non-dominated points, each
+/// next one the points dominated only by earlier fronts.
+pub fn non_dominated_sort(objectives: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated_by = vec![0; n];
+    let mut dominating: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if dominates(&objectives[i], &objectives[j]) {
+                dominating[i].push(j);
+                dominated_by[j] += 1;
+            } else if dominates(&objectives[j], &objectives[i]) {
+                dominating[j].push(i);
+                dominated_by[i] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut current: Vec<usize> = (0..n).filter(|&i| dominated_by[i] == 0).collect();
+    while !current.is_empty() {
+        let mut next = Vec::new();
+        for &i in &current {
+            for &j in &dominating[i] {
+                dominated_by[j] -= 1;
+                if dominated_by[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+        next.sort_unstable();
+        fronts.push(current);
+        current = next;
+    }
+    fronts
+}
+
+/// Crowding distance of each member of `front`: the sum over objectives of
+/// the normalized gap between its neighbours. The extremes of each objective
+/// get an infinite distance so they are always kept.
+pub fn crowding_distance(objectives: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distance = vec![0.0; front.len()];
+    let Some(first) = front.first() else {
+        return distance;
+    };
+    let value = |k: usize, m: usize| objectives[front[k]][m];
+    let last = front.len() - 1;
+    let mut order: Vec<usize> = (0..front.len()).collect();
+    for m in 0..objectives[*first].len() {
+        order.sort_by(|&a, &b| value(a, m).total_cmp(&value(b, m)));
+        let (low, high) = (value(order[0], m), value(order[last], m));
+        distance[order[0]] = f64::INFINITY;
+        distance[order[last]] = f64::INFINITY;
+        if high - low <= 0.0 || !(high - low).is_finite() {
+            continue;
+        }
+        for k in 1..last {
+            distance[order[k]] += (value(order[k + 1], m) - value(order[k - 1], m)) / (high - low);
+        }
+    }
+    distance
+}
+
+/// Maximize several objectives at once with NSGA-II style differential
+/// evolution.
+///
+/// `criter` returns the objective values of a parameter set, all to be
+/// maximized (negate a drawdown); a non-finite value marks the set as
+/// infeasible, dominated by every feasible one. Each generation every member
+/// makes one child by DE/rand/1 mutation and binomial crossover, integer
+/// parameters moving by whole steps as in `diff_ev`, and the best `popsize`
+/// of parents and children survive by front, then by crowding distance.
+///
+/// # Returns
+/// The non-dominated members of the final population, one per distinct
+/// vector of objective values, by the first objective from best to worst.
+pub fn diff_ev_pareto<F>(criter: F, config: ParetoConfig) -> Result<Vec<ParetoPoint>, String>
+where
+    F: Fn(&[f64], i32) -> Vec<f64>,
+{
+    let ParetoConfig {
+        nvars: n,
+        nints,
+        popsize,
+        max_gens,
+        mintrades,
+        mutate_dev,
+        pcross,
+        low_bounds,
+        high_bounds,
+        print_progress,
+        seed,
+    } = config;
+    if n == 0 || low_bounds.len() != n || high_bounds.len() != n {
+        return Err("Pareto search needs one low and one high bound per variable".to_string());
+    }
+    if low_bounds.iter().zip(high_bounds).any(|(lo, hi)| lo > hi) {
+        return Err("Low bounds must not exceed high bounds".to_string());
+    }
+    if popsize < 4 {
+        return Err("Pareto search needs a population of at least 4".to_string());
+    }
+
+    let mut rng = Mwc256::with_seed(seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32));
+    let evaluate = |params: &[f64]| -> Vec<f64> {
+        criter(params, mintrades)
+            .into_iter()
+            .map(|v| if v.is_finite() { v } else { f64::NEG_INFINITY })
+            .collect()
+    };
+
+    let mut population: Vec<Vec<f64>> = Vec::with_capacity(2 * popsize);
+    let mut objectives: Vec<Vec<f64>> = Vec::with_capacity(2 * popsize);
+    let mut params = vec![0.0; n];
+    for _ in 0..popsize {
+        let unit: Vec<f64> = (0..n).map(|_| rng.unifrand()).collect();
+        scale_to_bounds(&unit, nints, low_bounds, high_bounds, &mut params);
+        objectives.push(evaluate(&params));
+        population.push(params.clone());
+    }
+    let nobj = objectives[0].len();
+    if nobj == 0 || objectives.iter().any(|o| o.len() != nobj) {
+        return Err("The criterion must return the same number of objectives for every parameter set".to_string());
+    }
+
+    for generation in 1..=max_gens {
+        for i in 0..popsize {
+            let (a, b, c) = three_others(i, popsize, &mut rng);
+            let forced = (rng.unifrand() * n as f64) as usize % n;
+            let mut child = population[i].clone();
+            for k in 0..n {
+                if k != forced && rng.unifrand() >= pcross {
+                    continue;
+                }
+                let diff = mutate_dev * (population[b][k] - population[c][k]);
+                child[k] = if k < nints {
+                    mutate_integer(population[a][k], diff, low_bounds[k], high_bounds[k], &mut || rng.unifrand())
+                } else {
+                    (population[a][k] + diff).clamp(low_bounds[k], high_bounds[k])
+                };
+            }
+            let values = evaluate(&child);
+            if values.len() != nobj {
+                return Err("The criterion must return the same number of objectives for every parameter set".to_string());
+            }
+            objectives.push(values);
+            population.push(child);
+        }
+
+        // Keep the best fronts, the last one cut by crowding distance
+        let mut survivors = Vec::with_capacity(popsize);
+        for front in non_dominated_sort(&objectives) {
+            if survivors.len() + front.len() <= popsize {
+                survivors.extend(front);
+                continue;
+            }
+            let distance = crowding_distance(&objectives, &front);
+            let mut order: Vec<usize> = (0..front.len()).collect();
+            order.sort_by(|&a, &b| distance[b].total_cmp(&distance[a]));
+            survivors.extend(order.iter().take(popsize - survivors.len()).map(|&k| front[k]));
+            break;
+        }
+        population = survivors.iter().map(|&i| population[i].clone()).collect();
+        objectives = survivors.iter().map(|&i| objectives[i].clone()).collect();
+
+        if print_progress {
+            let front = non_dominated_sort(&objectives).swap_remove(0);
+            print!("\nGen {} Front={}", generation, front.len());
+        }
+    }
+
+    let mut points: Vec<ParetoPoint> = Vec::new();
+    for i in non_dominated_sort(&objectives).swap_remove(0) {
+        if objectives[i].iter().any(|v| v.is_infinite()) {
+            continue;
+        }
+        // Parameter sets on a plateau of the criterion tie exactly; one of
+        // them stands for all
+        if points.iter().all(|p| p.objectives != objectives[i]) {
+            points.push(ParetoPoint { params: population[i].clone(), objectives: objectives[i].clone() });
+        }
+    }
+    if points.is_empty() {
+        return Err("Pareto search found no feasible parameters".to_string());
+    }
+    points.sort_by(|a, b| b.objectives[0].total_cmp(&a.objectives[0]));
+    Ok(points)
+}
+
+/// Three distinct members other than `i`
+fn three_others(i: usize, popsize: usize, rng: &mut Mwc256) -> (usize, usize, usize) {
+    let mut pick = |taken: &[usize]| loop {
+        let j = (rng.unifrand() * popsize as f64) as usize % popsize;
+        if !taken.contains(&j) {
+            return j;
+        }
+    };
+    let a = pick(&[i]);
+    let b = pick(&[i, a]);
+    let c = pick(&[i, a, b]);
+    (a, b, c)
+}
+
+/// Pareto set as CSV: one column per parameter and objective, with the given
+/// names
+pub fn pareto_to_csv(points: &[ParetoPoint], param_names: &[&str], objective_names: &[&str]) -> String {
+    let mut csv = param_names.iter().chain(objective_names).copied().collect::<Vec<_>>().join(",");
+    csv.push('\n');
+    for point in points {
+        let row: Vec<String> = point.params.iter().chain(&point.objectives).map(|v| v.to_string()).collect();
+        writeln!(csv, "{}", row.join(",")).unwrap();
+    }
+    csv
+}
+
+pub fn write_pareto_csv<P: AsRef<Path>>(
+    path: P,
+    points: &[ParetoPoint],
+    param_names: &[&str],
+    objective_names: &[&str],
+) -> io::Result<()> {
+    write_file(path, pareto_to_csv(points, param_names, objective_names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorting_and_crowding() {
+        let objectives = vec![vec![1.0, 5.0], vec![2.0, 4.0], vec![1.0, 4.0], vec![3.0, 1.0], vec![0.0, 0.0]];
+        assert_eq!(non_dominated_sort(&objectives), vec![vec![0, 1, 3], vec![2], vec![4]]);
+        assert!(dominates(&[1.0, 1.0], &[1.0, 0.0]));
+        assert!(!dominates(&[1.0, 1.0], &[1.0, 1.0]));
+
+        let distance = crowding_distance(&objectives, &[0, 1, 3]);
+        assert!(distance[0].is_infinite() && distance[2].is_infinite());
+        // Neighbours of point 1: (3 - 1) / 2 + (5 - 1) / 4
+        assert!((distance[1] - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pareto_front_of_trade_off() {
+        // Return grows with x, "drawdown" with x and with |y|: the front is
+        // y = 0 with x anywhere in range
+        let criter = |p: &[f64], _mintrades: i32| -> Vec<f64> {
+            if p[0] > 9.0 {
+                return vec![f64::NAN, 0.0];
+            }
+            vec![p[0], -(p[0] * p[0]) - p[1].abs()]
+        };
+        let low = [0.0, -1.0];
+        let high = [10.0, 1.0];
+        let config = ParetoConfig {
+            nvars: 2,
+            nints: 1,
+            popsize: 40,
+            max_gens: 60,
+            mintrades: 1,
+            mutate_dev: 0.7,
+            pcross: 0.5,
+            low_bounds: &low,
+            high_bounds: &high,
+            print_progress: false,
+            seed: Some(9),
+        };
+        let front = diff_ev_pareto(criter, config).unwrap();
+        let xs: Vec<f64> = front.iter().map(|p| p.params[0]).collect();
+        assert_eq!(xs, vec![9.0, 8.0, 7.0, 6.0, 5.0, 4.0, 3.0, 2.0, 1.0, 0.0]);
+        assert!(front.iter().all(|p| p.params[1].abs() < 0.05), "{:?}", front);
+
+        let csv = pareto_to_csv(&front[..1], &["x", "y"], &["ret", "dd"]);
+        assert!(csv.starts_with("x,y,ret,dd\n9,"));
+    }
+}
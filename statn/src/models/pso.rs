@@ -0,0 +1,253 @@
+//! Particle swarm optimization (PSO).
+//!
+//! A second population-based search with the same criterion closure, bounds
+//! and integer parameters as `diff_ev`, to cross-check its results: each
+//! particle moves through the space with a velocity pulled toward the best
+//! point it has seen and the best point of the swarm.
+
+use std::str::FromStr;
+
+use crate::core::matlib::mwc256::Mwc256;
+use crate::core::matlib::rands::unifrand;
+use crate::estimators::stochastic_bias::StocBias;
+use crate::models::differential_evolution::scale_to_bounds;
+
+/// Largest velocity component, as a fraction of each parameter's range
+const MAX_VELOCITY: f64 = 0.5;
+
+/// How the velocity update is damped
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PsoVariant {
+    /// Inertia weight decreasing linearly from `start` to `end` over
+    /// `max_evals`, with both acceleration coefficients 2 (Shi and Eberhart).
+    /// A high start explores, a low end refines.
+    Inertia { start: f64, end: f64 },
+    /// Constriction factor 0.7298 with acceleration coefficients 2.05
+    /// (Clerc and Kennedy), which converges without a velocity schedule
+    #[default]
+    Constriction,
+}
+
+impl FromStr for PsoVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "constriction" => Ok(PsoVariant::Constriction),
+            "inertia" => Ok(PsoVariant::Inertia { start: 0.9, end: 0.4 }),
+            _ => Err(format!("Unknown PSO variant '{}' (expected constriction or inertia)", s)),
+        }
+    }
+}
+
+/// Configuration of `pso`
+#[derive(Debug, Clone, Copy)]
+pub struct PsoConfig<'a> {
+    pub nvars: usize,
+    /// The first `nints` parameters are integers
+    pub nints: usize,
+    /// Passed to the criterion
+    pub mintrades: i32,
+    /// Particles in the swarm
+    pub popsize: usize,
+    /// Criterion evaluations after which the search stops
+    pub max_evals: usize,
+    /// Iterations in a row without a new best after which the search stops
+    pub max_bad_gen: usize,
+    pub variant: PsoVariant,
+    pub low_bounds: &'a [f64],
+    pub high_bounds: &'a [f64],
+    pub print_progress: bool,
+    /// Seed of the random numbers; `None` draws one
+    pub seed: Option<u32>,
+}
+
+/// Maximize a criterion with a particle swarm.
+///
+/// The swarm moves in the unit cube spanned by the bounds; a particle that
+/// hits a face stops there and loses that velocity component. Positions map
+/// to parameters as `diff_ev`'s initial population does, so integer
+/// parameters take each value in their range over an equal share of the cube.
+/// `stoc_bias` collects returns while the initial swarm, spread uniformly
+/// over the space, is evaluated.
+///
+/// # Returns
+/// The best parameters found with the criterion value at the end, as
+/// `diff_ev` returns them.
+pub fn pso<F>(criter: F, config: PsoConfig, stoc_bias: &mut Option<StocBias>) -> Result<Vec<f64>, String>
+where
+    F: Fn(&[f64], i32) -> f64,
+{
+    let PsoConfig {
+        nvars: n,
+        nints,
+        mintrades,
+        popsize,
+        max_evals,
+        max_bad_gen,
+        variant,
+        low_bounds,
+        high_bounds,
+        print_progress,
+        seed,
+    } = config;
+    if n == 0 || low_bounds.len() != n || high_bounds.len() != n {
+        return Err("PSO needs one low and one high bound per variable".to_string());
+    }
+    if low_bounds.iter().zip(high_bounds).any(|(lo, hi)| lo > hi) {
+        return Err("Low bounds must not exceed high bounds".to_string());
+    }
+    if popsize < 2 {
+        return Err("PSO needs at least 2 particles".to_string());
+    }
+    if let PsoVariant::Inertia { start, end } = variant
+        && !((0.0..=1.0).contains(&start) && (0.0..=1.0).contains(&end))
+    {
+        return Err("Inertia weights must be between 0 and 1".to_string());
+    }
+
+    let mut rng = Mwc256::with_seed(seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32));
+
+    let mut position: Vec<Vec<f64>> = (0..popsize).map(|_| (0..n).map(|_| rng.unifrand()).collect()).collect();
+    let mut velocity: Vec<Vec<f64>> = (0..popsize)
+        .map(|_| (0..n).map(|_| MAX_VELOCITY * (2.0 * rng.unifrand() - 1.0)).collect())
+        .collect();
+    let mut personal = position.clone();
+    let mut personal_value = vec![f64::NEG_INFINITY; popsize];
+
+    let mut best = vec![0.0; n + 1];
+    let mut grand_best = f64::NEG_INFINITY;
+    let mut swarm_best = vec![0.0; n];
+    let mut params = vec![0.0; n];
+    let mut n_evals = 0;
+    let mut bad_generations = 0;
+    let mut generation: usize = 0;
+
+    while n_evals < max_evals {
+        if let Some(sb) = stoc_bias {
+            sb.set_collecting(generation == 0);
+        }
+
+        let mut improved = false;
+        for (k, x) in position.iter().enumerate() {
+            scale_to_bounds(x, nints, low_bounds, high_bounds, &mut params);
+            let value = criter(&params, mintrades);
+            n_evals += 1;
+            if value > personal_value[k] {
+                personal_value[k] = value;
+                personal[k].copy_from_slice(x);
+            }
+            if value > grand_best {
+                grand_best = value;
+                swarm_best.copy_from_slice(x);
+                best[..n].copy_from_slice(&params);
+                best[n] = value;
+                improved = true;
+            }
+            if n_evals >= max_evals {
+                break;
+            }
+        }
+        generation += 1;
+
+        if print_progress {
+            print!("\nIter {} Best={:.4}", generation, grand_best);
+            for val in best.iter().take(n) {
+                print!(" {:.4}", val);
+            }
+        }
+
+        if improved {
+            bad_generations = 0;
+        } else {
+            bad_generations += 1;
+            if bad_generations > max_bad_gen {
+                break;
+            }
+        }
+
+        let (damping, inertia, accel) = match variant {
+            PsoVariant::Inertia { start, end } => {
+                let progress = (n_evals as f64 / max_evals as f64).min(1.0);
+                (1.0, start + (end - start) * progress, 2.0)
+            }
+            PsoVariant::Constriction => (0.7298, 1.0, 2.05),
+        };
+        for k in 0..popsize {
+            for i in 0..n {
+                let pull_own = accel * rng.unifrand() * (personal[k][i] - position[k][i]);
+                let pull_swarm = accel * rng.unifrand() * (swarm_best[i] - position[k][i]);
+                let v = damping * (inertia * velocity[k][i] + pull_own + pull_swarm);
+                velocity[k][i] = v.clamp(-MAX_VELOCITY, MAX_VELOCITY);
+                position[k][i] += velocity[k][i];
+                if !(0.0..=1.0).contains(&position[k][i]) {
+                    position[k][i] = position[k][i].clamp(0.0, 1.0);
+                    velocity[k][i] = 0.0;
+                }
+            }
+        }
+    }
+
+    if let Some(sb) = stoc_bias {
+        sb.set_collecting(false);
+    }
+    if grand_best == f64::NEG_INFINITY {
+        return Err("PSO found no parameters with a finite criterion".to_string());
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config<'a>(low_bounds: &'a [f64], high_bounds: &'a [f64], nints: usize, variant: PsoVariant) -> PsoConfig<'a> {
+        PsoConfig {
+            nvars: low_bounds.len(),
+            nints,
+            mintrades: 1,
+            popsize: 30,
+            max_evals: 30000,
+            max_bad_gen: 100,
+            variant,
+            low_bounds,
+            high_bounds,
+            print_progress: false,
+            seed: Some(5),
+        }
+    }
+
+    #[test]
+    fn test_pso_variants_find_optimum() {
+        let criter = |p: &[f64], _mintrades: i32| -> f64 {
+            5.0 - (p[0] - 1.5).powi(2) - (p[1] + 2.0).powi(2) - (p[2] - 0.25).powi(2)
+        };
+        let low = [-5.0, -5.0, -5.0];
+        let high = [5.0, 5.0, 5.0];
+        for variant in ["constriction", "inertia"] {
+            let variant: PsoVariant = variant.parse().unwrap();
+            let best = pso(criter, config(&low, &high, 0, variant), &mut None).unwrap();
+            assert!((best[0] - 1.5).abs() < 1e-3, "{:?} {:?}", variant, best);
+            assert!((best[1] + 2.0).abs() < 1e-3);
+            assert!((best[3] - 5.0).abs() < 1e-5);
+            // Same seed, same run
+            assert_eq!(best, pso(criter, config(&low, &high, 0, variant), &mut None).unwrap());
+        }
+        assert!("swarm".parse::<PsoVariant>().is_err());
+    }
+
+    #[test]
+    fn test_pso_bounds_and_integers() {
+        // Integer optimum inside the range, real one on the high bound
+        let criter = |p: &[f64], _mintrades: i32| -> f64 { -(p[0] - 7.0).abs() + p[1] };
+        let low = [2.0, 0.0];
+        let high = [20.0, 1.5];
+        let best = pso(criter, config(&low, &high, 1, PsoVariant::default()), &mut None).unwrap();
+        assert_eq!(best[0], 7.0);
+        assert_eq!(best[1], 1.5);
+
+        assert!(pso(criter, config(&high, &low, 1, PsoVariant::default()), &mut None).is_err());
+        let bad = PsoVariant::Inertia { start: 1.5, end: 0.4 };
+        assert!(pso(criter, config(&low, &high, 1, bad), &mut None).is_err());
+    }
+}
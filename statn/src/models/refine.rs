@@ -0,0 +1,214 @@
+use crate::estimators::brentmax::brentmax;
+use crate::estimators::glob_max::glob_max;
+
+/// Fraction of a parameter's range searched on each side of its current value
+const WINDOW: f64 = 0.1;
+
+/// Search range of one parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamBounds {
+    pub low: f64,
+    pub high: f64,
+    /// Only whole values are tried
+    pub integer: bool,
+}
+
+impl ParamBounds {
+    /// Bounds in the form taken by `diff_ev`: the first `nints` parameters are integers
+    pub fn from_arrays(low_bounds: &[f64], high_bounds: &[f64], nints: usize) -> Vec<ParamBounds> {
+        low_bounds
+            .iter()
+            .zip(high_bounds)
+            .enumerate()
+            .map(|(i, (&low, &high))| ParamBounds { low, high, integer: i < nints })
+            .collect()
+    }
+}
+
+/// Maximize the criterion along parameter `index`, holding the others fixed.
+///
+/// This is the hill climb used inside `diff_ev`. An integer parameter is
+/// stepped up by 1 while the criterion improves, or down if the first step up
+/// does not help. A real parameter starts with a coarse search of 7 points
+/// (`glob_max`) within a tenth of its range on either side of its current
+/// value, extended past the window while the criterion keeps rising at its
+/// edge; Brent's method (`brentmax`) then refines the bracketed maximum.
+///
+/// `params[index]` is first brought within the bounds (and rounded if it is an
+/// integer); after that it changes only if the criterion improves. Returns the
+/// criterion at the final parameters.
+pub fn line_search<F>(params: &mut [f64], index: usize, bounds: &ParamBounds, criterion: F) -> f64
+where
+    F: Fn(&[f64]) -> f64,
+{
+    if bounds.integer {
+        params[index] = params[index].round().clamp(bounds.low, bounds.high);
+        integer_search(params, index, bounds, &criterion)
+    } else {
+        params[index] = params[index].clamp(bounds.low, bounds.high);
+        real_search(params, index, bounds, &criterion)
+    }
+}
+
+fn integer_search<F>(params: &mut [f64], index: usize, bounds: &ParamBounds, criterion: &F) -> f64
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let base = params[index];
+    let mut best_value = criterion(params);
+    let mut best = base;
+
+    for step in [1.0, -1.0] {
+        let mut trial = base + step;
+        while trial >= bounds.low && trial <= bounds.high {
+            params[index] = trial;
+            let value = criterion(params);
+            if value > best_value {
+                best_value = value;
+                best = trial;
+                trial += step;
+            } else {
+                break;
+            }
+        }
+        if best != base {
+            break;
+        }
+    }
+
+    params[index] = best;
+    best_value
+}
+
+fn real_search<F>(params: &mut [f64], index: usize, bounds: &ParamBounds, criterion: &F) -> f64
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let base = params[index];
+    let start_value = criterion(params);
+    let range = bounds.high - bounds.low;
+    if range <= 0.0 {
+        return start_value;
+    }
+
+    let mut lower = base - WINDOW * range;
+    let mut upper = base + WINDOW * range;
+    if lower < bounds.low {
+        lower = bounds.low;
+        upper = bounds.low + 2.0 * WINDOW * range;
+    }
+    if upper > bounds.high {
+        upper = bounds.high;
+        lower = bounds.high - 2.0 * WINDOW * range;
+    }
+
+    // Points outside the bounds are evaluated at the bound and heavily
+    // penalized, so the search stays inside
+    let trial = params.to_vec();
+    let c_func = |x: f64| -> f64 {
+        let mut trial = trial.clone();
+        trial[index] = x.clamp(bounds.low, bounds.high);
+        let excess = (x - bounds.high).max(0.0) + (bounds.low - x).max(0.0);
+        criterion(&trial) - 1.0e10 * excess
+    };
+
+    let (mut x1, mut y1, mut x2, mut y2, mut x3, mut y3) = (0.0, 0.0, base, start_value, 0.0, 0.0);
+    glob_max(lower, upper, 7, false, c_func, &mut x1, &mut y1, &mut x2, &mut y2, &mut x3, &mut y3);
+    brentmax(5, 1.0e-8, 0.0001, c_func, &mut x1, &mut x2, &mut x3, y2);
+
+    params[index] = x2.clamp(bounds.low, bounds.high);
+    let value = criterion(params);
+    if value > start_value {
+        value
+    } else {
+        params[index] = base;
+        start_value
+    }
+}
+
+/// Polish parameters from any source (a grid search, a manual pick) by line
+/// searches along each parameter in turn.
+///
+/// Stops after `max_passes` passes over all parameters, or as soon as a pass
+/// improves the criterion by no more than `tol`. Returns the final criterion.
+pub fn coordinate_ascent<F>(
+    params: &mut [f64],
+    bounds: &[ParamBounds],
+    criterion: F,
+    max_passes: usize,
+    tol: f64,
+) -> f64
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let mut value = criterion(params);
+    for _ in 0..max_passes {
+        let start = value;
+        for (index, b) in bounds.iter().enumerate() {
+            value = line_search(params, index, b, &criterion);
+        }
+        if value - start <= tol {
+            break;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn real(low: f64, high: f64) -> ParamBounds {
+        ParamBounds { low, high, integer: false }
+    }
+
+    #[test]
+    fn test_real_line_search() {
+        let criterion = |p: &[f64]| -(p[0] - 3.3).powi(2) - p[1] * p[1];
+        let mut params = [3.0, 1.0];
+        let value = line_search(&mut params, 0, &real(0.0, 10.0), criterion);
+        assert!((params[0] - 3.3).abs() < 1e-3, "{}", params[0]);
+        assert_eq!(params[1], 1.0);
+        assert!((value - criterion(&params)).abs() < 1e-12);
+
+        // The optimum is beyond the window: the coarse search follows the
+        // slope past the window's edge
+        let mut params = [8.0, 0.0];
+        line_search(&mut params, 0, &real(0.0, 10.0), criterion);
+        assert!((params[0] - 3.3).abs() < 1e-3, "{}", params[0]);
+    }
+
+    #[test]
+    fn test_never_worse() {
+        // Already at the maximum, at the upper bound
+        let criterion = |p: &[f64]| p[0];
+        let mut params = [10.0];
+        assert_eq!(line_search(&mut params, 0, &real(0.0, 10.0), criterion), 10.0);
+        assert_eq!(params[0], 10.0);
+    }
+
+    #[test]
+    fn test_integer_line_search() {
+        let bounds = ParamBounds { low: 2.0, high: 50.0, integer: true };
+        let criterion = |p: &[f64]| -(p[0] - 17.0).abs();
+        let mut params = [10.0];
+        assert_eq!(line_search(&mut params, 0, &bounds, criterion), 0.0);
+        assert_eq!(params[0], 17.0);
+
+        let mut params = [30.4];
+        line_search(&mut params, 0, &bounds, criterion);
+        assert_eq!(params[0], 17.0);
+    }
+
+    #[test]
+    fn test_coordinate_ascent() {
+        let bounds = ParamBounds::from_arrays(&[2.0, -5.0, -5.0], &[40.0, 5.0, 5.0], 1);
+        assert!(bounds[0].integer && !bounds[1].integer);
+        let criterion = |p: &[f64]| -(p[0] - 12.0).powi(2) - (p[1] - 1.0).powi(2) - (p[2] + 2.0).powi(2);
+        let mut params = [20.0, 0.0, 0.0];
+        let value = coordinate_ascent(&mut params, &bounds, criterion, 20, 1e-10);
+        assert_eq!(params[0], 12.0);
+        assert!((params[1] - 1.0).abs() < 1e-3 && (params[2] + 2.0).abs() < 1e-3, "{:?}", params);
+        assert!(value > -1e-5);
+    }
+}
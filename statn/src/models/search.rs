@@ -0,0 +1,213 @@
+//! Grid and random search baselines.
+//!
+//! Exhaustive and purely random searches with the criterion closure, bounds
+//! and integer parameters of `diff_ev`, to check on a given budget of
+//! criterion evaluations whether an evolutionary optimizer actually beats
+//! them on a low-dimensional parameter space.
+
+use crate::core::matlib::mwc256::Mwc256;
+use crate::core::matlib::rands::unifrand;
+use crate::models::differential_evolution::scale_to_bounds;
+
+/// Configuration of `grid_search` and `random_search`
+#[derive(Debug, Clone, Copy)]
+pub struct SearchConfig<'a> {
+    pub nvars: usize,
+    /// The first `nints` parameters are integers
+    pub nints: usize,
+    /// Passed to the criterion
+    pub mintrades: i32,
+    /// Criterion evaluations allowed
+    pub max_evals: usize,
+    pub low_bounds: &'a [f64],
+    pub high_bounds: &'a [f64],
+    /// Seed of `random_search`; `None` draws one
+    pub seed: Option<u32>,
+}
+
+impl SearchConfig<'_> {
+    fn check(&self) -> Result<(), String> {
+        if self.nvars == 0 || self.low_bounds.len() != self.nvars || self.high_bounds.len() != self.nvars {
+            return Err("Search needs one low and one high bound per variable".to_string());
+        }
+        if self.low_bounds.iter().zip(self.high_bounds).any(|(lo, hi)| lo > hi) {
+            return Err("Low bounds must not exceed high bounds".to_string());
+        }
+        if self.max_evals == 0 {
+            return Err("Search needs at least one evaluation".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Values tried along each parameter by `grid_search`.
+///
+/// Every axis gets the same number of points, the most whose grid fits in
+/// `max_evals`, equally spaced from the low to the high bound. An integer
+/// axis never has more points than integers in its range, and its points are
+/// rounded, so a short integer range is covered completely and leaves more
+/// of the budget to the other axes.
+pub fn grid_axes(config: &SearchConfig) -> Result<Vec<Vec<f64>>, String> {
+    config.check()?;
+    let n = config.nvars;
+    let span = |i: usize| {
+        if i < config.nints {
+            (config.high_bounds[i].floor() - config.low_bounds[i].ceil() + 1.0).max(1.0) as usize
+        } else if config.high_bounds[i] > config.low_bounds[i] {
+            usize::MAX
+        } else {
+            1
+        }
+    };
+    let size = |k: usize| (0..n).try_fold(1usize, |total, i| total.checked_mul(k.min(span(i))));
+    let mut k = 1;
+    while size(k + 1).is_some_and(|s| s <= config.max_evals) && size(k + 1) != size(k) {
+        k += 1;
+    }
+
+    Ok((0..n)
+        .map(|i| {
+            let (lo, hi) = (config.low_bounds[i], config.high_bounds[i]);
+            let points = k.min(span(i));
+            if i < config.nints {
+                let (lo, hi) = (lo.ceil(), hi.floor().max(lo.ceil()));
+                let mut axis: Vec<f64> = (0..points)
+                    .map(|j| if points == 1 { lo } else { (lo + (hi - lo) * j as f64 / (points - 1) as f64).round() })
+                    .collect();
+                axis.dedup();
+                axis
+            } else if points == 1 {
+                vec![0.5 * (lo + hi)]
+            } else {
+                (0..points).map(|j| lo + (hi - lo) * j as f64 / (points - 1) as f64).collect()
+            }
+        })
+        .collect())
+}
+
+/// Maximize a criterion by trying every point of the grid of `grid_axes`.
+///
+/// # Returns
+/// The best parameters with the criterion value at the end, as `diff_ev`
+/// returns them; the first best point in grid order wins a tie.
+pub fn grid_search<F>(criter: F, config: &SearchConfig) -> Result<Vec<f64>, String>
+where
+    F: Fn(&[f64], i32) -> f64,
+{
+    let axes = grid_axes(config)?;
+    let n = config.nvars;
+    let mut index = vec![0; n];
+    let mut params: Vec<f64> = axes.iter().map(|axis| axis[0]).collect();
+    let mut best = vec![0.0; n + 1];
+    let mut grand_best = f64::NEG_INFINITY;
+
+    loop {
+        let value = criter(&params, config.mintrades);
+        if value > grand_best {
+            grand_best = value;
+            best[..n].copy_from_slice(&params);
+            best[n] = value;
+        }
+
+        // Odometer step, last parameter fastest
+        let mut i = n;
+        loop {
+            if i == 0 {
+                return finish(best, grand_best);
+            }
+            i -= 1;
+            index[i] += 1;
+            if index[i] < axes[i].len() {
+                params[i] = axes[i][index[i]];
+                break;
+            }
+            index[i] = 0;
+            params[i] = axes[i][0];
+        }
+    }
+}
+
+/// Maximize a criterion at `max_evals` points drawn uniformly within the
+/// bounds, integer parameters each value equally likely as in `diff_ev`'s
+/// initial population.
+///
+/// # Returns
+/// The best parameters with the criterion value at the end, as `diff_ev`
+/// returns them.
+pub fn random_search<F>(criter: F, config: &SearchConfig) -> Result<Vec<f64>, String>
+where
+    F: Fn(&[f64], i32) -> f64,
+{
+    config.check()?;
+    let n = config.nvars;
+    let mut rng = Mwc256::with_seed(config.seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32));
+    let mut unit = vec![0.0; n];
+    let mut params = vec![0.0; n];
+    let mut best = vec![0.0; n + 1];
+    let mut grand_best = f64::NEG_INFINITY;
+
+    for _ in 0..config.max_evals {
+        unit.iter_mut().for_each(|u| *u = rng.unifrand());
+        scale_to_bounds(&unit, config.nints, config.low_bounds, config.high_bounds, &mut params);
+        let value = criter(&params, config.mintrades);
+        if value > grand_best {
+            grand_best = value;
+            best[..n].copy_from_slice(&params);
+            best[n] = value;
+        }
+    }
+    finish(best, grand_best)
+}
+
+fn finish(best: Vec<f64>, grand_best: f64) -> Result<Vec<f64>, String> {
+    if grand_best == f64::NEG_INFINITY {
+        return Err("Search found no parameters with a finite criterion".to_string());
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config<'a>(low_bounds: &'a [f64], high_bounds: &'a [f64], nints: usize, max_evals: usize) -> SearchConfig<'a> {
+        SearchConfig { nvars: low_bounds.len(), nints, mintrades: 1, max_evals, low_bounds, high_bounds, seed: Some(3) }
+    }
+
+    #[test]
+    fn test_grid_axes_fit_budget() {
+        // The short integer range is covered fully; the two other axes share
+        // the rest of the budget (5 x 6 x 6 = 180 <= 200)
+        let low = [2.0, 10.0, 0.0];
+        let high = [6.0, 100.0, 1.0];
+        let axes = grid_axes(&config(&low, &high, 2, 200)).unwrap();
+        assert_eq!(axes[0], vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(axes[1], vec![10.0, 28.0, 46.0, 64.0, 82.0, 100.0]);
+        assert_eq!(axes[2].len(), 6);
+        assert_eq!((axes[2][0], axes[2][5]), (0.0, 1.0));
+
+        let one = grid_axes(&config(&low, &high, 2, 1)).unwrap();
+        assert!(one.iter().all(|axis| axis.len() == 1));
+        assert!(grid_axes(&config(&high, &low, 2, 10)).is_err());
+    }
+
+    #[test]
+    fn test_searches_find_optimum() {
+        let criter = |p: &[f64], _mintrades: i32| -> f64 { 10.0 - (p[0] - 4.0).powi(2) - (p[1] - 0.3).abs() };
+        let low = [2.0, 0.0];
+        let high = [8.0, 1.0];
+
+        let best = grid_search(criter, &config(&low, &high, 1, 7 * 11)).unwrap();
+        assert_eq!(best[0], 4.0);
+        assert!((best[1] - 0.3).abs() < 1e-12);
+        assert!((best[2] - 10.0).abs() < 1e-12);
+
+        let best = random_search(criter, &config(&low, &high, 1, 2000)).unwrap();
+        assert_eq!(best[0], 4.0);
+        assert!((best[1] - 0.3).abs() < 0.01);
+        assert_eq!(best, random_search(criter, &config(&low, &high, 1, 2000)).unwrap());
+
+        let never = |_: &[f64], _: i32| f64::NEG_INFINITY;
+        assert!(grid_search(never, &config(&low, &high, 1, 10)).is_err());
+    }
+}
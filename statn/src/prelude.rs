@@ -0,0 +1,29 @@
+//! The stable API of statn, for tools and downstream code to build against.
+//!
+//! ```ignore
+//! use statn::prelude::*;
+//! ```
+//!
+//! Everything here keeps its name and meaning across releases. The deeper
+//! paths (`statn::core::io::...`, `statn::walkforward::...`) still work, but
+//! may be reorganized; the optimizers' line-search internals are no longer
+//! public. Where the names differ from the deep paths:
+//!
+//! | Prelude | Deep path |
+//! |---|---|
+//! | `MarketSeries` | `core::io::Bars` |
+//! | `Backtester` | `backtesting::backtest_signals_with` with a budget |
+//!
+//! `Backtester`, `Metric` and the backtesting types need the `backtest`
+//! feature (on by default).
+
+#[cfg(feature = "backtest")]
+pub use crate::backtest::{Backtester, Metric};
+pub use crate::core::bootstrap::{Bootstrap, Interval};
+pub use crate::core::io::{
+    read_bars_with_format, read_log_prices, Bars as MarketSeries, BarsFormat, InputKind, Provenance, SessionFilter,
+};
+pub use crate::core::transforms::{returns, ReturnKind};
+pub use crate::walkforward::{FoldWindow, WalkForward, WindowMode};
+#[cfg(feature = "backtest")]
+pub use backtesting::{BacktestOptions, SignalResult, TradeStats};
@@ -0,0 +1,210 @@
+//! Walkforward engine shared by the walkforward tools.
+//!
+//! A walkforward trains on a window of bars, skips an optional embargo, tests
+//! the following bars, then moves forward and repeats until the history is
+//! used up. `WalkForward` only lays out the folds; what is trained and how it
+//! is tested is up to the two closures given to `run`.
+
+use std::ops::Range;
+
+/// How the training window moves from fold to fold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowMode {
+    /// Fixed-length window of the most recent `n_train` bars
+    #[default]
+    Rolling,
+    /// Expanding window that always starts at the first bar; `n_train` is
+    /// only the length of the first training set
+    Anchored,
+}
+
+/// Layout of a walkforward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkForward {
+    /// Bars in the (first) training set
+    pub n_train: usize,
+    /// Bars in each test set; the last one may be shorter
+    pub n_test: usize,
+    /// Bars the windows move forward after each fold; 0 moves them by the
+    /// number of bars tested, so the test sets tile the history
+    pub step: usize,
+    pub window: WindowMode,
+    /// Bars skipped between the end of each training set and the start of its
+    /// test set, so lookbacks and targets of the two do not overlap
+    pub embargo: usize,
+}
+
+/// Bars of one fold, as indices into the full history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldWindow {
+    /// Position of the fold, from 0
+    pub index: usize,
+    pub train_start: usize,
+    /// One past the last training bar
+    pub train_end: usize,
+    pub test_start: usize,
+    /// One past the last test bar
+    pub test_end: usize,
+}
+
+impl FoldWindow {
+    pub fn train(&self) -> Range<usize> {
+        self.train_start..self.train_end
+    }
+
+    pub fn test(&self) -> Range<usize> {
+        self.test_start..self.test_end
+    }
+
+    /// Number of bars tested
+    pub fn n_test(&self) -> usize {
+        self.test_end - self.test_start
+    }
+}
+
+/// A fold with what was trained on it and the result of its test
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fold<M, R> {
+    pub window: FoldWindow,
+    pub model: M,
+    pub result: R,
+}
+
+impl WalkForward {
+    /// Rolling walkforward whose test sets tile the history, without embargo
+    pub fn new(n_train: usize, n_test: usize) -> Self {
+        WalkForward {
+            n_train,
+            n_test,
+            step: 0,
+            window: WindowMode::Rolling,
+            embargo: 0,
+        }
+    }
+
+    /// Check that at least one full fold fits in `nprices` bars
+    pub fn validate(&self, nprices: usize) -> Result<(), String> {
+        if self.n_train == 0 {
+            return Err("n_train must be positive".to_string());
+        }
+        if self.n_test == 0 {
+            return Err("n_test must be positive".to_string());
+        }
+        if self.n_train + self.embargo + self.n_test > nprices {
+            return Err("n_train + embargo + n_test must not exceed n_prices".to_string());
+        }
+        Ok(())
+    }
+
+    /// Folds over a history of `nprices` bars, in order.
+    ///
+    /// Folds are laid out while a test set can start before the end of the
+    /// history; the last test set is cut at the end.
+    pub fn windows(&self, nprices: usize) -> Vec<FoldWindow> {
+        let mut windows = Vec::new();
+        let mut train_start = 0;
+        let mut train_end = self.n_train;
+
+        while train_end + self.embargo < nprices {
+            let test_start = train_end + self.embargo;
+            let test_end = (test_start + self.n_test).min(nprices);
+            windows.push(FoldWindow {
+                index: windows.len(),
+                train_start,
+                train_end,
+                test_start,
+                test_end,
+            });
+
+            let advance = if self.step > 0 { self.step } else { test_end - test_start };
+            train_end += advance;
+            if self.window == WindowMode::Rolling {
+                train_start += advance;
+            }
+        }
+        windows
+    }
+
+    /// Run the walkforward: for each fold, `train` fits a model on the
+    /// training bars, then `test` evaluates it on the test bars.
+    ///
+    /// Both closures get the fold's window and index into the caller's own
+    /// data, so any number of series can be trained or tested on.
+    pub fn run<M, R>(
+        &self,
+        nprices: usize,
+        mut train: impl FnMut(&FoldWindow) -> M,
+        mut test: impl FnMut(&FoldWindow, &M) -> R,
+    ) -> Vec<Fold<M, R>> {
+        self.windows(nprices)
+            .into_iter()
+            .map(|window| {
+                let model = train(&window);
+                let result = test(&window, &model);
+                Fold { window, model, result }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_windows_tile_history() {
+        let windows = WalkForward::new(100, 70).windows(400);
+
+        assert_eq!(windows.len(), 5);
+        assert_eq!(windows[0].train(), 0..100);
+        assert_eq!(windows[0].test(), 100..170);
+        assert_eq!(windows[1].train(), 70..170);
+        // The last test set is cut at the end of the history
+        assert_eq!(windows[4].test(), 380..400);
+        assert_eq!(windows.iter().map(|w| w.n_test()).sum::<usize>(), 300);
+        assert!(windows.iter().enumerate().all(|(i, w)| w.index == i));
+    }
+
+    #[test]
+    fn test_anchored_embargo_and_step() {
+        let wf = WalkForward {
+            window: WindowMode::Anchored,
+            embargo: 30,
+            ..WalkForward::new(100, 70)
+        };
+        let windows = wf.windows(400);
+        for w in &windows {
+            assert_eq!(w.train_start, 0);
+            assert_eq!(w.test_start, w.train_end + 30);
+        }
+        assert_eq!(windows.iter().map(|w| w.n_test()).sum::<usize>(), 270);
+
+        // A step longer than the test set leaves untested bars between folds
+        let windows = WalkForward { step: 100, ..WalkForward::new(100, 70) }.windows(400);
+        assert_eq!(windows.iter().map(|w| w.test_start).collect::<Vec<_>>(), vec![100, 200, 300]);
+        assert_eq!(windows[1].train(), 100..200);
+    }
+
+    #[test]
+    fn test_run_passes_model_to_test() {
+        let prices: Vec<f64> = (0..300).map(|i| i as f64).collect();
+        let folds = WalkForward::new(100, 100).run(
+            prices.len(),
+            |w| prices[w.train()].iter().sum::<f64>() / w.train().len() as f64,
+            |w, mean| prices[w.test()].iter().map(|p| p - mean).sum::<f64>(),
+        );
+
+        assert_eq!(folds.len(), 2);
+        assert_eq!(folds[0].model, 49.5);
+        assert_eq!(folds[0].result, 100.0 * 100.0);
+        assert_eq!(folds[1].window.test(), 200..300);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(WalkForward::new(100, 0).validate(400).is_err());
+        assert!(WalkForward::new(0, 10).validate(400).is_err());
+        assert!(WalkForward::new(300, 100).validate(400).is_ok());
+        assert!(WalkForward { embargo: 1, ..WalkForward::new(300, 100) }.validate(400).is_err());
+    }
+}
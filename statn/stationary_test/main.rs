@@ -46,7 +46,7 @@ fn main() {
     // Read market prices
     println!("\nReading market file...");
     let market_data = read_market_data(filename);
-    let nprices = market_data.closes.len();
+    let nprices = market_data.close.len();
     println!("\nMarket price history read ({} lines)", nprices);
     println!("\n\nIndicator version {}", version);
 
@@ -59,7 +59,7 @@ fn main() {
     let gap_size = initialize_gap_sizes();
 
     // Compute and analyze trend
-    let trend = compute_trend(&market_data.closes, lookback, full_lookback, version);
+    let trend = compute_trend(&market_data.close, lookback, full_lookback, version);
     let (trend_min, trend_max) = find_min_max(&trend);
     let mut trend_sorted = trend.clone();
     qsortd(0, trend.len() - 1, &mut trend_sorted);
@@ -74,7 +74,7 @@ fn main() {
     print_gap_analysis(&gap_size, &gap_count_trend, "trend", lookback);
 
     // Compute and analyze volatility
-    let volatility = compute_volatility(&market_data.highs, &market_data.lows, &market_data.closes, lookback, full_lookback, version);
+    let volatility = compute_volatility(&market_data.high, &market_data.low, &market_data.close, lookback, full_lookback, version);
     let (volatility_min, volatility_max) = find_min_max(&volatility);
     let mut volatility_sorted = volatility.clone();
     qsortd(0, volatility.len() - 1, &mut volatility_sorted);
@@ -1,4 +1,7 @@
 use anyhow::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use statn::models::learner::Model;
 use try_cd_comb::*;
 
 fn main() -> Result<()> {
@@ -43,19 +46,6 @@ fn main() -> Result<()> {
         &specs,
     )?;
     
-    // Train model with cross-validation
-    let training_result = train_with_cv(
-        config.n_vars(),
-        n_train,
-        &train_data.data,
-        &train_data.targets,
-        config.alpha,
-        config.n_folds,
-        config.n_lambdas,
-        config.max_iterations,
-        config.tolerance,
-    )?;
-    
     // Compute test indicators and targets
     println!("Computing test indicators...");
     let test_data = compute_indicator_data(
@@ -64,33 +54,102 @@ fn main() -> Result<()> {
         config.n_test,
         &specs,
     )?;
+    // Test prices (log prices) corresponding to the test period
+    let test_prices_slice = &split.test_data[split.max_lookback..split.max_lookback + config.n_test];
     
-    // Evaluate model
-    let evaluation_result = evaluate_model(
-        &training_result.model,
-        &test_data.data,
-        &test_data.targets,
-        config.n_vars(),
-    )?;
+    let evaluation_result = match config.learner {
+        LearnerKind::ElasticNet => {
+            // Train model with cross-validation
+            let training_result = train_with_cv(
+                config.n_vars(),
+                n_train,
+                &train_data.data,
+                &train_data.targets,
+                config.alpha,
+                config.n_folds,
+                config.n_lambdas,
+                config.max_iterations,
+                config.tolerance,
+            )?;
+            
+            // Evaluate model
+            let evaluation_result = evaluate_model(
+                &training_result.model,
+                &test_data.data,
+                &test_data.targets,
+                config.n_vars(),
+            )?;
+            
+            // Write results
+            write_results(
+                &config.output_file,
+                &config,
+                &training_result,
+                &evaluation_result,
+                &specs,
+            )?;
+            
+            backtest_and_save(&config, &training_result.model, test_prices_slice, &test_data.data, &specs)?;
+            evaluation_result
+        }
+        LearnerKind::Gbt => {
+            let model = train_gbt(
+                config.n_vars(),
+                n_train,
+                &train_data.data,
+                &train_data.targets,
+                config.gbt,
+            )?;
+            
+            let evaluation_result = evaluate_model(
+                &model,
+                &test_data.data,
+                &test_data.targets,
+                config.n_vars(),
+            )?;
+            
+            write_gbt_results(
+                &config.output_file,
+                &config,
+                &model,
+                &evaluation_result,
+                &specs,
+            )?;
+            
+            backtest_and_save(&config, &model, test_prices_slice, &test_data.data, &specs)?;
+            evaluation_result
+        }
+    };
     
-    // Write results
-    write_results(
-        &config.output_file,
-        &config,
-        &training_result,
-        &evaluation_result,
-        &specs,
-    )?;
+    // Print summary
+    println!("\nSummary:");
+    println!(
+        "  In-sample explained variance: {:.3}%",
+        100.0 * evaluation_result.in_sample_explained
+    );
+    println!(
+        "  OOS total return: {:.5} ({:.3}%)",
+        evaluation_result.oos_return, evaluation_result.oos_return_pct
+    );
     
+    Ok(())
+}
+
+/// Backtest a trained model on the test period, write the backtest results
+/// and save the model next to the results file
+fn backtest_and_save<M: Model + Serialize + DeserializeOwned + Clone>(
+    config: &Config,
+    model: &M,
+    test_prices: &[f64],
+    test_data: &[f64],
+    specs: &[indicators::IndicatorSpec],
+) -> Result<()> {
     // Run backtest
     println!("Running backtest...");
-    // Extract test prices (log prices) corresponding to the test period
-    let test_prices_slice = &split.test_data[split.max_lookback..split.max_lookback + config.n_test];
-    
     let backtest_stats = run_backtest(
-        &training_result.model,
-        test_prices_slice,
-        &test_data.data,
+        model,
+        test_prices,
+        test_data,
         config.n_test,
         config.n_vars(),
         10000.0, // Initial budget
@@ -105,22 +164,10 @@ fn main() -> Result<()> {
     let model_path = config.output_file.parent().unwrap_or(std::path::Path::new(".")).join("model.json");
     println!("Saving model to {}...", model_path.display());
     let saved_model = SavedModel::new(
-        training_result.model.clone(), // Clone the model
-        specs.clone(),                 // Clone the specs
-        config.clone()                 // Clone the config
+        model.clone(),          // Clone the model
+        specs.to_vec(),         // Clone the specs
+        config.clone()          // Clone the config
     );
     saved_model.save(&model_path)?;
-    
-    // Print summary
-    println!("\nSummary:");
-    println!(
-        "  In-sample explained variance: {:.3}%",
-        100.0 * evaluation_result.in_sample_explained
-    );
-    println!(
-        "  OOS total return: {:.5} ({:.3}%)",
-        evaluation_result.oos_return, evaluation_result.oos_return_pct
-    );
-    
     Ok(())
 }
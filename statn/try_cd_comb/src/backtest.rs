@@ -1,11 +1,11 @@
 use anyhow::Result;
 use backtesting::core::backtest_signals;
 use backtesting::models::{SignalResult, TradeStats};
-use statn::models::cd_ma::CoordinateDescent;
+use statn::models::learner::Model;
 
 /// Generate trading signals from model predictions
-pub fn generate_signals(
-    model: &CoordinateDescent,
+pub fn generate_signals<M: Model>(
+    model: &M,
     indicator_data: &[f64],
     n_cases: usize,
     n_vars: usize,
@@ -15,16 +15,8 @@ pub fn generate_signals(
     for i in 0..n_cases {
         let xptr = &indicator_data[i * n_vars..(i + 1) * n_vars];
         
-        // Compute prediction
-        let pred: f64 = xptr
-            .iter()
-            .enumerate()
-            .map(|(ivar, &x)| {
-                model.beta[ivar] * (x - model.xmeans[ivar]) / model.xscales[ivar]
-            })
-            .sum();
-        
-        let pred = pred * model.yscale + model.ymean;
+        // Side of the trade from the model's score
+        let pred = model.score(xptr);
         
         // Generate signal: 1 = BUY (long), -1 = SELL (short), 0 = HOLD
         let signal = if pred > 0.0 {
@@ -42,8 +34,8 @@ pub fn generate_signals(
 }
 
 /// Run backtest on test data
-pub fn run_backtest(
-    model: &CoordinateDescent,
+pub fn run_backtest<M: Model>(
+    model: &M,
     test_prices: &[f64],
     test_data: &[f64],
     n_cases: usize,
@@ -1,7 +1,42 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+use statn::models::gbt::GbtParams;
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Model trained on the indicators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LearnerKind {
+    /// Linear elastic net by coordinate descent
+    #[default]
+    ElasticNet,
+    /// Gradient-boosted regression trees
+    Gbt,
+}
+
+impl FromStr for LearnerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "elastic-net" | "cd" => Ok(LearnerKind::ElasticNet),
+            "gbt" | "trees" => Ok(LearnerKind::Gbt),
+            _ => Err(format!("Unknown learner '{}' (expected elastic-net or gbt)", s)),
+        }
+    }
+}
+
+impl fmt::Display for LearnerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LearnerKind::ElasticNet => "elastic-net",
+            LearnerKind::Gbt => "gbt",
+        })
+    }
+}
 
 /// Configuration for CD_MA analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +83,14 @@ pub struct Config {
     /// Convergence tolerance
     #[serde(default = "default_tolerance")]
     pub tolerance: f64,
+
+    /// Model trained on the indicators: "elastic-net" or "gbt"
+    #[serde(default)]
+    pub learner: LearnerKind,
+
+    /// Settings of the gradient-boosted trees, as a [gbt] table
+    #[serde(default)]
+    pub gbt: GbtParams,
 }
 
 fn default_output_file() -> PathBuf {
@@ -105,6 +148,10 @@ pub struct Args {
 
 
     
+    /// Model trained on the indicators: elastic-net or gbt
+    #[arg(long)]
+    pub learner: Option<LearnerKind>,
+    
     /// Alpha parameter (0-1]
     #[arg(value_name = "ALPHA")]
     pub alpha: Option<f64>,
@@ -158,6 +205,8 @@ impl Config {
             n_lambdas: default_n_lambdas(),
             max_iterations: default_max_iterations(),
             tolerance: default_tolerance(),
+            learner: args.learner.unwrap_or_default(),
+            gbt: GbtParams::default(),
         };
         
         config.validate()?;
@@ -246,6 +295,8 @@ mod tests {
             n_lambdas: 50,
             max_iterations: 1000,
             tolerance: 1e-9,
+            learner: LearnerKind::ElasticNet,
+            gbt: GbtParams::default(),
         };
         
         assert!(config.validate().is_ok());
@@ -273,6 +324,8 @@ mod tests {
             n_lambdas: 50,
             max_iterations: 1000,
             tolerance: 1e-9,
+            learner: LearnerKind::ElasticNet,
+            gbt: GbtParams::default(),
         };
         
         assert_eq!(config.n_vars(), 200);
@@ -7,6 +7,8 @@ use crate::config::Config;
 use crate::indicators::IndicatorSpec;
 use crate::training::TrainingResult;
 use statn::models::cd_ma::CoordinateDescent;
+use statn::models::gbt::GradientBoostedTrees;
+use statn::models::learner::Model;
 use backtesting::models::TradeStats;
 
 /// Evaluation results
@@ -20,9 +22,76 @@ pub struct EvaluationResult {
     pub in_sample_explained: f64,
 }
 
+/// An indicator the model uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedIndicator {
+    /// Position of the indicator among the specs
+    pub index: usize,
+    pub name: String,
+    /// Coefficient of the standardized indicator, comparable across indicators
+    pub beta: f64,
+    /// Coefficient in the indicator's own units
+    pub raw_beta: f64,
+}
+
+/// Indicators with nonzero coefficients, largest standardized magnitude first
+pub fn selected_indicators(model: &CoordinateDescent, specs: &[IndicatorSpec]) -> Vec<SelectedIndicator> {
+    let raw = model.raw_coefficients();
+    let mut selected: Vec<SelectedIndicator> = specs
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| model.beta[index] != 0.0)
+        .map(|(index, spec)| SelectedIndicator {
+            index,
+            name: spec.name(),
+            beta: model.beta[index],
+            raw_beta: raw[index],
+        })
+        .collect();
+    selected.sort_by(|a, b| b.beta.abs().total_cmp(&a.beta.abs()));
+    selected
+}
+
+/// Ranked table of the selected indicators, with each one's share of the
+/// total standardized magnitude
+fn write_selection(
+    file: &mut impl Write,
+    selected: &[SelectedIndicator],
+    n_vars: usize,
+    lambda: f64,
+) -> std::io::Result<()> {
+    writeln!(
+        file,
+        "Selected Indicators ({} of {} nonzero at lambda {:.6}):",
+        selected.len(),
+        n_vars,
+        lambda
+    )?;
+    writeln!(
+        file,
+        "  {:>4} {:<16} {:>4} {:>12} {:>8} {:>14}",
+        "Rank", "Indicator", "Sign", "|Std beta|", "Share %", "Raw beta"
+    )?;
+    writeln!(file, "  {}", "-".repeat(63))?;
+    let total: f64 = selected.iter().map(|s| s.beta.abs()).sum();
+    for (rank, s) in selected.iter().enumerate() {
+        writeln!(
+            file,
+            "  {:>4} {:<16} {:>4} {:>12.4} {:>8.2} {:>14.6e}",
+            rank + 1,
+            s.name,
+            if s.beta > 0.0 { "+" } else { "-" },
+            s.beta.abs(),
+            100.0 * s.beta.abs() / total,
+            s.raw_beta
+        )?;
+    }
+    writeln!(file)
+}
+
 /// Evaluate model on test data
-pub fn evaluate_model(
-    model: &CoordinateDescent,
+pub fn evaluate_model<M: Model>(
+    model: &M,
     test_data: &[f64],
     test_targets: &[f64],
     n_vars: usize,
@@ -35,16 +104,8 @@ pub fn evaluate_model(
         .map(|i| {
             let xptr = &test_data[i * n_vars..(i + 1) * n_vars];
             
-            // Compute prediction
-            let pred: f64 = xptr
-                .iter()
-                .enumerate()
-                .map(|(ivar, &x)| {
-                    model.beta[ivar] * (x - model.xmeans[ivar]) / model.xscales[ivar]
-                })
-                .sum();
-            
-            let pred = pred * model.yscale + model.ymean;
+            // Side of the trade from the model's score
+            let pred = model.score(xptr);
             
             // Trading logic: long if pred > 0, short if pred < 0
             if pred > 0.0 {
@@ -64,7 +125,7 @@ pub fn evaluate_model(
     Ok(EvaluationResult {
         oos_return,
         oos_return_pct,
-        in_sample_explained: model.explained,
+        in_sample_explained: model.explained(),
     })
 }
 
@@ -74,7 +135,7 @@ pub fn write_results<P: AsRef<Path>>(
     config: &Config,
     training: &TrainingResult,
     evaluation: &EvaluationResult,
-    _specs: &[IndicatorSpec],
+    specs: &[IndicatorSpec],
 ) -> Result<()> {
     let mut file = OpenOptions::new()
         .create(true)
@@ -151,7 +212,7 @@ pub fn write_results<P: AsRef<Path>>(
         writeln!(file)?;
     }
 
-
+    write_selection(&mut file, &selected_indicators(&training.model, specs), specs.len(), training.lambda)?;
     
     // Out-of-sample results
     writeln!(file, "Out-of-Sample Results:")?;
@@ -165,6 +226,77 @@ pub fn write_results<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Write the results of gradient-boosted trees: their settings, the
+/// indicators ranked by importance, and the out-of-sample return
+pub fn write_gbt_results<P: AsRef<Path>>(
+    path: P,
+    config: &Config,
+    model: &GradientBoostedTrees,
+    evaluation: &EvaluationResult,
+    specs: &[IndicatorSpec],
+) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path.as_ref())?;
+
+    writeln!(file, "CD_COMB - Gradient-Boosted Trees on the Indicators")?;
+    writeln!(file, "{}", "=".repeat(60))?;
+    writeln!(file)?;
+
+    writeln!(file, "Configuration:")?;
+    writeln!(file, "  Lookback increment: {}", config.lookback_inc)?;
+    writeln!(file, "  Number of long-term lookbacks: {}", config.n_long)?;
+    writeln!(file, "  Number of short-term lookbacks: {}", config.n_short)?;
+    writeln!(file, "  Number of indicators: {}", config.n_vars())?;
+    writeln!(file, "  Test cases: {}", config.n_test)?;
+    writeln!(file, "  Trees: {}", model.n_trees())?;
+    writeln!(file, "  Learning rate: {}", model.params.learning_rate)?;
+    writeln!(file, "  Max depth: {}", model.params.max_depth)?;
+    writeln!(file, "  Min cases per leaf: {}", model.params.min_leaf)?;
+    writeln!(file, "  Subsample: {}", model.params.subsample)?;
+    writeln!(file)?;
+
+    writeln!(
+        file,
+        "In-sample explained variance: {:.3}%",
+        100.0 * evaluation.in_sample_explained
+    )?;
+    writeln!(file)?;
+
+    // Indicators the trees split on, most important first
+    let mut ranked: Vec<(&IndicatorSpec, f64)> = specs
+        .iter()
+        .zip(&model.importance)
+        .filter(|&(_, &imp)| imp > 0.0)
+        .map(|(spec, &imp)| (spec, imp))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    writeln!(
+        file,
+        "Indicator Importance ({} of {} split on):",
+        ranked.len(),
+        specs.len()
+    )?;
+    writeln!(file, "  {:>4} {:<16} {:>12}", "Rank", "Indicator", "Share %")?;
+    writeln!(file, "  {}", "-".repeat(34))?;
+    for (rank, (spec, imp)) in ranked.iter().enumerate() {
+        writeln!(file, "  {:>4} {:<16} {:>12.2}", rank + 1, spec.name(), 100.0 * imp)?;
+    }
+    writeln!(file)?;
+
+    writeln!(file, "Out-of-Sample Results:")?;
+    writeln!(
+        file,
+        "  Total return: {:.5} ({:.3}%)",
+        evaluation.oos_return, evaluation.oos_return_pct
+    )?;
+
+    println!("\nResults written to {}", path.as_ref().display());
+    Ok(())
+}
+
 /// Write backtest results to a separate file
 pub fn write_backtest_results<P: AsRef<Path>>(
     path: P,
@@ -189,6 +321,11 @@ pub fn write_backtest_results<P: AsRef<Path>>(
     writeln!(file, "Total Costs:      ${:.2}", stats.total_costs)?;
     writeln!(file, "Max Drawdown:     {:.2}%", stats.max_drawdown)?;
     writeln!(file, "Sharpe Ratio:     {:.4}", stats.sharpe_ratio)?;
+    writeln!(file, "Sortino Ratio:    {:.4}", stats.sortino_ratio)?;
+    writeln!(file, "Calmar Ratio:     {:.4}", stats.calmar_ratio)?;
+    writeln!(file, "MAR Ratio:        {:.4}", stats.mar_ratio)?;
+    writeln!(file, "Ulcer Index:      {:.4}", stats.ulcer_index)?;
+    writeln!(file, "Avg DD Duration:  {:.1} bars", stats.avg_drawdown_duration)?;
     writeln!(file)?;
 
     writeln!(file, "Trade Statistics")?;
@@ -249,4 +386,21 @@ mod tests {
         let result = evaluate_model(&model, &test_data, &test_targets, n_vars);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_selected_indicators() {
+        use crate::indicators::{CrossoverType, generate_specs};
+
+        let mut model = CoordinateDescent::new(4, 10, false, true, 0);
+        model.beta = vec![0.0, 0.2, 0.0, -0.1];
+        model.xscales = vec![1.0, 4.0, 1.0, 2.0];
+        model.yscale = 0.02;
+        let specs = generate_specs(6, 1, 2, &[CrossoverType::Ma, CrossoverType::Rsi]);
+
+        let selected = selected_indicators(&model, &specs);
+        let names: Vec<&str> = selected.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["ma_4_6", "rsi_4_6"]);
+        assert!((selected[0].raw_beta - 0.2 * 0.02 / 4.0).abs() < 1e-15);
+        assert!((selected[1].raw_beta + 0.1 * 0.02 / 2.0).abs() < 1e-15);
+    }
 }
@@ -6,10 +6,10 @@ pub mod evaluation;
 pub mod backtest;
 pub mod model_io;
 
-pub use config::Config;
+pub use config::{Config, LearnerKind};
 pub use data::{load_prices, split_train_test};
 pub use indicators::{generate_specs, compute_indicator_data};
-pub use training::train_with_cv;
-pub use evaluation::{evaluate_model, write_results, write_backtest_results};
+pub use training::{train_gbt, train_model, train_model_cv, train_with_cv};
+pub use evaluation::{evaluate_model, write_gbt_results, write_results, write_backtest_results};
 pub use backtest::{generate_signals, run_backtest};
 pub use model_io::SavedModel;
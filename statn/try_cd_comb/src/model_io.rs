@@ -2,26 +2,28 @@ use anyhow::{Context, Result};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use statn::models::cd_ma::CoordinateDescent;
 use crate::indicators::IndicatorSpec;
 use crate::config::Config;
 
-/// Container for a saved model
+/// Container for a saved model, the elastic net unless another learner is
+/// named
 #[derive(Serialize, Deserialize)]
-pub struct SavedModel {
+pub struct SavedModel<M = CoordinateDescent> {
     /// Trained model weights and stats
-    pub model: CoordinateDescent,
+    pub model: M,
     /// Specifications for indicators used in the model
     pub specs: Vec<IndicatorSpec>,
     /// Configuration used to generate the model (optional metadata)
     pub config: Config,
 }
 
-impl SavedModel {
+impl<M: Serialize + DeserializeOwned> SavedModel<M> {
     /// Create a new SavedModel container
-    pub fn new(model: CoordinateDescent, specs: Vec<IndicatorSpec>, config: Config) -> Self {
+    pub fn new(model: M, specs: Vec<IndicatorSpec>, config: Config) -> Self {
         Self {
             model,
             specs,
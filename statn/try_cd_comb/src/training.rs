@@ -1,5 +1,7 @@
 use anyhow::Result;
 use statn::models::cd_ma::{CoordinateDescent, cv_train};
+use statn::models::gbt::{GbtParams, GradientBoostedTrees};
+use statn::models::learner::{CdParams, CvSelection, Model, cv_select};
 
 /// Result of model training
 pub struct TrainingResult {
@@ -14,6 +16,10 @@ pub struct TrainingResult {
 }
 
 /// Train model with cross-validation to find optimal lambda
+///
+/// Specific to the elastic net: the cross-validation walks its warm-started
+/// lambda path. Other learners are fit with `train_model`, or chosen from
+/// candidate settings with `train_model_cv`.
 pub fn train_with_cv(
     n_vars: usize,
     n_cases: usize,
@@ -55,9 +61,8 @@ pub fn train_with_cv(
     
     // Train final model with optimal lambda
     println!("Training final model...");
-    let mut model = CoordinateDescent::new(n_vars, n_cases, false, true, 0);
-    model.get_data(0, n_cases, data, targets, None);
-    model.core_train(alpha, lambda, max_iterations, 1e-7, true, false);
+    let params = CdParams { alpha, lambda, max_iterations, tolerance: 1e-7, ..CdParams::default() };
+    let model: CoordinateDescent = train_model(n_vars, n_cases, data, targets, &params)?;
     
     println!("In-sample explained variance: {:.3}%", 100.0 * model.explained);
     
@@ -69,6 +74,55 @@ pub fn train_with_cv(
     })
 }
 
+/// Fit any learner to the training cases
+pub fn train_model<M: Model>(
+    n_vars: usize,
+    n_cases: usize,
+    data: &[f64],
+    targets: &[f64],
+    params: &M::Params,
+) -> Result<M> {
+    M::fit(n_vars, n_cases, data, targets, None, params).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// Fit any learner with the candidate settings that earn the most out of
+/// sample in `n_folds`-fold cross-validation (see `cv_select`), on all cases
+pub fn train_model_cv<M: Model>(
+    n_vars: usize,
+    n_cases: usize,
+    data: &[f64],
+    targets: &[f64],
+    n_folds: usize,
+    candidates: &[M::Params],
+) -> Result<(M, CvSelection)> {
+    println!("Running {}-fold cross-validation of {} candidate settings...", n_folds, candidates.len());
+    let cv = cv_select::<M>(n_vars, n_cases, data, targets, None, n_folds, candidates)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    for (i, oos) in cv.oos.iter().enumerate() {
+        println!("Candidate {}: OOS return per case {:.6}{}", i, oos, if i == cv.best { " (best)" } else { "" });
+    }
+    let model = M::fit(n_vars, n_cases, data, targets, None, &candidates[cv.best]).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok((model, cv))
+}
+
+/// Train gradient-boosted regression trees on the same cases, for comparing
+/// a nonlinear fit with the elastic net
+pub fn train_gbt(
+    n_vars: usize,
+    n_cases: usize,
+    data: &[f64],
+    targets: &[f64],
+    params: GbtParams,
+) -> Result<GradientBoostedTrees> {
+    println!(
+        "Training {} gradient-boosted trees of depth {} (learning rate {})...",
+        params.n_trees, params.max_depth, params.learning_rate
+    );
+    let model: GradientBoostedTrees = train_model(n_vars, n_cases, data, targets, &params)?;
+    println!("In-sample explained variance: {:.3}%", 100.0 * model.explained);
+    Ok(model)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,4 +150,53 @@ mod tests {
         let result = result.unwrap();
         assert_eq!(result.lambda, 0.0);
     }
+
+    #[test]
+    fn test_train_gbt() {
+        let n_vars = 2;
+        let n_cases = 300;
+        let mut data = Vec::with_capacity(n_vars * n_cases);
+        let mut targets = Vec::with_capacity(n_cases);
+        for i in 0..n_cases {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            let x1 = ((i * 53 % 97) as f64) / 48.0 - 1.0;
+            data.extend([x0, x1]);
+            targets.push(x0.abs() - 0.5);
+        }
+
+        // The elastic net finds nothing linear in |x0|; the trees do
+        let linear = train_with_cv(n_vars, n_cases, &data, &targets, 0.5, 5, 10, 1000, 1e-9).unwrap();
+        let params = GbtParams { n_trees: 100, learning_rate: 0.1, min_leaf: 5, ..GbtParams::default() };
+        let trees = train_gbt(n_vars, n_cases, &data, &targets, params).unwrap();
+        assert!(linear.model.explained < 0.1);
+        assert!(trees.explained > 0.9);
+
+        let bad = GbtParams { n_bins: 1, ..params };
+        assert!(train_gbt(n_vars, n_cases, &data, &targets, bad).is_err());
+    }
+
+    #[test]
+    fn test_train_model_cv() {
+        let n_vars = 2;
+        let n_cases = 300;
+        let mut data = Vec::with_capacity(n_vars * n_cases);
+        let mut targets = Vec::with_capacity(n_cases);
+        for i in 0..n_cases {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            let x1 = ((i * 53 % 97) as f64) / 48.0 - 1.0;
+            data.extend([x0, x1]);
+            targets.push(x0.abs() - 0.5);
+        }
+
+        // Trees deep enough to split x0 twice find the sign of |x0| - 0.5
+        let stump = GbtParams { n_trees: 1, max_depth: 1, ..GbtParams::default() };
+        let deep = GbtParams { n_trees: 100, learning_rate: 0.1, min_leaf: 5, ..GbtParams::default() };
+        let (model, cv) =
+            train_model_cv::<GradientBoostedTrees>(n_vars, n_cases, &data, &targets, 5, &[stump, deep]).unwrap();
+        assert_eq!(cv.best, 1);
+        assert!(cv.oos[1] > cv.oos[0]);
+        assert_eq!(model.n_trees(), 100);
+
+        assert!(train_model_cv::<GradientBoostedTrees>(n_vars, n_cases, &data, &targets, 5, &[]).is_err());
+    }
 }
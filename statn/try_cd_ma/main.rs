@@ -1,17 +1,46 @@
 use anyhow::Result;
 use clap::Parser;
+use statn::core::io::BarsFormat;
+use std::io::Write;
 use try_cd_ma::*;
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Predict { model_file, data_file, data_format, output }) => {
+            predict(&model_file, &data_file, data_format, output.as_deref())
+        }
+        None => train(cli.config.expect("clap requires the training arguments without a subcommand")),
+    }
+}
+
+/// Score a market file with a saved model
+fn predict(model_file: &str, data_file: &str, data_format: Option<BarsFormat>, output: Option<&str>) -> Result<()> {
+    let saved = load_model(model_file)?;
+    let bars = load_bars_with_format(data_file, data_format).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let predictions = predict_prices(&saved, &bars.log_close())?;
+    let dates = &bars.dates()[saved.max_lookback..];
+
+    match output {
+        Some(path) => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            write_predictions(&mut file, dates, &predictions, saved.model.family)?;
+            file.flush()?;
+            eprintln!("{} predictions written to {}", predictions.len(), path);
+        }
+        None => write_predictions(&mut std::io::stdout().lock(), dates, &predictions, saved.model.family)?,
+    }
+    Ok(())
+}
+
+fn train(config: Config) -> Result<()> {
     println!("CD_MA - Moving Average Crossover Indicator Selection\n");
     
-    // Load configuration
-    let config = Config::parse();
     config.validate()?;
     
     // Load market data
     println!("Loading market data...");
-    let prices = load_prices(std::path::Path::new(&config.data_file))
+    let prices = load_prices_with_format(&config.data_file, config.data_format)
         .map_err(|e| anyhow::anyhow!("{}", e))?;
     
     // Split into training and test sets
@@ -26,13 +55,16 @@ fn main() -> Result<()> {
         config.lookback_inc,
         config.n_long,
         config.n_short,
+        &config.ma_types,
     );
     println!("MA indicators: {}", config.n_ma_vars());
 
     println!("Total indicators: {}", specs.len());
     
-    // Compute training indicators
-    let n_train = split.train_data.len() - split.max_lookback - 1;
+    // Compute training indicators, leaving out the last cases whose target
+    // would look past the training data
+    let target = config.target_spec();
+    let n_train = split.train_data.len() - split.max_lookback - target.bars_ahead();
     
     // Validate sufficient training data
     if n_train < config.n_vars() + 10 {
@@ -49,20 +81,41 @@ fn main() -> Result<()> {
         split.max_lookback,
         n_train,
         &specs,
+        &target,
     )?;
     
     // Train model with cross-validation
-    let training_result = train_with_cv(
-        config.n_vars(),
-        n_train,
-        &train_data.data,
-        &train_data.targets,
-        config.alpha,
-        config.n_folds,
-        config.n_lambdas,
-        config.max_iterations,
-        config.tolerance,
-    )?;
+    let training_result = if config.half_life_grid.is_empty() {
+        train_with_cv(
+            config.n_vars(),
+            n_train,
+            &train_data.data,
+            &train_data.targets,
+            config.alpha,
+            config.n_folds,
+            config.n_lambdas,
+            config.one_se,
+            config.max_iterations,
+            config.tolerance,
+            config.half_life,
+            config.family(),
+        )?
+    } else {
+        train_with_decay_selection(
+            config.n_vars(),
+            n_train,
+            &train_data.data,
+            &train_data.targets,
+            config.alpha,
+            config.n_folds,
+            config.n_lambdas,
+            config.one_se,
+            config.max_iterations,
+            config.tolerance,
+            &config.half_life_grid,
+            config.family(),
+        )?
+    };
     
     // Compute test indicators and targets
     println!("Computing test indicators...");
@@ -71,15 +124,58 @@ fn main() -> Result<()> {
         split.max_lookback,
         config.n_test,
         &specs,
+        &target,
     )?;
     
     // Evaluate model
-    let evaluation_result = evaluate_model(
+    let mut evaluation_result = evaluate_model(
         &training_result.model,
         &test_data.data,
-        &test_data.targets,
+        &test_data.returns,
         config.n_vars(),
     )?;
+    if config.classify {
+        let metrics = classification_metrics(
+            &training_result.model,
+            &test_data.data,
+            &test_data.targets,
+            config.n_vars(),
+        );
+        println!("OOS AUC: {:.4}, accuracy: {:.3}%", metrics.auc, 100.0 * metrics.accuracy);
+        evaluation_result.classification = Some(metrics);
+    }
+
+    // Average the models of several alphas
+    if !config.ensemble_alphas.is_empty() {
+        println!("\n{}", "=".repeat(60));
+        println!("Ensemble over alphas {:?}", config.ensemble_alphas);
+        println!("{}", "=".repeat(60));
+        let ensemble = train_ensemble(
+            config.n_vars(),
+            n_train,
+            &train_data.data,
+            &train_data.targets,
+            &config.ensemble_alphas,
+            config.ensemble_bootstrap,
+            config.n_folds,
+            config.n_lambdas,
+            config.one_se,
+            config.max_iterations,
+            config.tolerance,
+            training_result.half_life,
+            config.family(),
+        )?;
+        evaluation_result.ensemble =
+            Some(evaluate_ensemble(&ensemble, &test_data.data, &test_data.returns, config.n_vars()));
+    }
+
+    // Retrain periodically over the test period
+    if config.walkforward_step > 0 {
+        println!("\n{}", "=".repeat(60));
+        println!("Walkforward (retraining every {} test cases)", config.walkforward_step);
+        println!("{}", "=".repeat(60));
+        evaluation_result.walkforward = Some(run_walkforward(&config, &prices, &specs)?);
+    }
     
     // Run backtest on test data
     println!("\n{}", "=".repeat(60));
@@ -94,25 +190,38 @@ fn main() -> Result<()> {
         .map(|&log_price| log_price.exp())
         .collect();
     
-    let initial_capital = 100_000.0;
-    let transaction_cost = 0.1; // 0.1% transaction cost
+    let backtest_settings = config.backtest_settings();
     
     let backtest_result = try_cd_ma::run_backtest(
         &training_result.model,
         &test_prices_actual,
         &test_data.data,
         config.n_vars(),
-        initial_capital,
-        transaction_cost,
+        &backtest_settings,
     )?;
     
     // Write backtest results
     let backtest_output = format!("{}backtest_results.txt", config.output_path);
-    try_cd_ma::write_backtest_results(&backtest_output, &backtest_result)?;
+    try_cd_ma::write_backtest_results(&backtest_output, &backtest_result, &backtest_settings)?;
     
-    // Write results
-
-    // Note: Model saving removed due to serialization requirements
+    // Save the model for the predict command
+    let model_output = format!("{}CD_MA_MODEL.json", config.output_path);
+    let saved = SavedModel {
+        model: training_result.model.clone(),
+        lambda: training_result.lambda,
+        specs: specs.clone(),
+        max_lookback: split.max_lookback,
+        config: config.clone(),
+    };
+    save_model(&model_output, &saved)?;
+    
+    // Write the lambda path of the final model
+    if !training_result.model.path_lambdas().is_empty() {
+        let path_output = format!("{}CD_MA_PATH.LOG", config.output_path);
+        let names: Vec<String> = specs.iter().map(|spec| spec.name()).collect();
+        training_result.model.write_coefficient_path(&path_output, &names)?;
+        println!("Coefficient path written to {}", path_output);
+    }
     
     // Write results
     let results_path = format!("{}CD_MA.LOG", config.output_path);
@@ -130,13 +239,33 @@ fn main() -> Result<()> {
     println!("{}", "=".repeat(60));
     println!("\nModel Performance:");
     println!(
-        "  In-sample explained variance: {:.3}%",
+        "  In-sample explained {}: {:.3}%",
+        if config.classify { "deviance" } else { "variance" },
         100.0 * evaluation_result.in_sample_explained
     );
     println!(
         "  OOS total return: {:.5} ({:.3}%)",
         evaluation_result.oos_return, evaluation_result.oos_return_pct
     );
+    if let Some(ensemble) = &evaluation_result.ensemble {
+        println!(
+            "  Ensemble OOS total return ({} models): {:.5} ({:.3}%)",
+            ensemble.members.len(),
+            ensemble.oos_return,
+            ensemble.oos_return_pct
+        );
+    }
+    if let Some(walk) = &evaluation_result.walkforward {
+        println!(
+            "  Walkforward OOS total return ({} refits): {:.5} ({:.3}%)",
+            walk.refits.len(),
+            walk.oos_return,
+            walk.oos_return_pct
+        );
+    }
+    if let Some(metrics) = evaluation_result.classification {
+        println!("  OOS AUC: {:.4}, accuracy: {:.3}%", metrics.auc, 100.0 * metrics.accuracy);
+    }
     
     println!("\nBacktest Performance:");
     println!(
@@ -159,6 +288,14 @@ fn main() -> Result<()> {
         "  Sharpe ratio: {:.3}",
         backtest_result.sharpe_ratio
     );
+    println!(
+        "  Sortino ratio: {:.3}, Calmar: {:.3}, MAR: {:.3}",
+        backtest_result.sortino_ratio, backtest_result.calmar_ratio, backtest_result.mar_ratio
+    );
+    println!(
+        "  Ulcer index: {:.3}, avg drawdown duration: {:.1} bars",
+        backtest_result.ulcer_index, backtest_result.avg_drawdown_duration
+    );
     
     Ok(())
 }
\ No newline at end of file
@@ -1,34 +1,88 @@
 use anyhow::Result;
-use backtesting::{backtest_signals, SignalResult, TradeStats};
-use statn::models::cd_ma::CoordinateDescent;
+use backtesting::{backtest_signals_with, BacktestOptions, SignalResult, Slippage, TradeStats};
+use statn::models::learner::Model;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::Path;
 
+/// How the backtest turns predictions into positions, and what trading costs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacktestSettings {
+    pub initial_capital: f64,
+    /// Transaction cost percentage (e.g., 0.1 for 0.1%)
+    pub transaction_cost_pct: f64,
+    /// Slippage at entries and exits
+    pub slippage: Slippage,
+    /// Trade only when the prediction is more than this many standard
+    /// deviations of the training target from neutral
+    pub signal_threshold: f64,
+    /// Stay flat instead of going short
+    pub long_only: bool,
+}
+
+impl Default for BacktestSettings {
+    fn default() -> Self {
+        BacktestSettings {
+            initial_capital: 100_000.0,
+            transaction_cost_pct: 0.1,
+            slippage: Slippage::None,
+            signal_threshold: 0.0,
+            long_only: false,
+        }
+    }
+}
+
+/// Position for a prediction `pred` of the model's linear predictor (the
+/// predicted return of a regression, the log-odds of a classifier): long
+/// above `threshold`, short below `-threshold` unless long only, else flat
+pub fn position(pred: f64, threshold: f64, long_only: bool) -> i32 {
+    if pred > threshold {
+        1
+    } else if pred < -threshold && !long_only {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Units of position traded over `positions`, from flat at the start to
+/// flat at the end; a reversal trades 2
+pub fn turnover(positions: &[i32]) -> f64 {
+    let mut last = 0;
+    let mut traded = 0;
+    for &pos in positions.iter().chain(std::iter::once(&0)) {
+        traded += (pos - last).abs();
+        last = pos;
+    }
+    traded as f64
+}
+
 /// Run backtesting on test data using the trained model
 /// 
 /// # Arguments
-/// * `model` - Trained coordinate descent model
+/// * `model` - Trained model; its score is compared with the threshold
 /// * `test_prices` - Test price data (in regular space, not log)
 /// * `test_data` - Pre-computed indicator data (standardized)
 /// * `n_vars` - Number of variables per case
-/// * `initial_capital` - Initial capital for backtesting
-/// * `transaction_cost` - Transaction cost percentage (e.g., 0.1 for 0.1%)
+/// * `settings` - Signal threshold, position mode, capital and costs
 /// 
 /// # Returns
 /// TradeStats with detailed trade logs and metrics
-pub fn run_backtest(
-    model: &CoordinateDescent,
+pub fn run_backtest<M: Model>(
+    model: &M,
     test_prices: &[f64],
     test_data: &[f64],
     n_vars: usize,
-    initial_capital: f64,
-    transaction_cost: f64,
+    settings: &BacktestSettings,
 ) -> Result<TradeStats> {
     println!("\nRunning backtest on test data...");
     
     let n_test = test_prices.len();
     
+    // The threshold in units of the prediction: the training target's
+    // standard deviation for a regression, the log-odds for a classifier
+    let threshold = settings.signal_threshold * model.score_scale();
+    
     // Generate signals for each test case
     let mut signals = Vec::with_capacity(n_test);
     
@@ -43,26 +97,9 @@ pub fn run_backtest(
         let xptr = &test_data[i * n_vars..(i + 1) * n_vars];
         
         // Compute prediction
-        let pred: f64 = xptr
-            .iter()
-            .enumerate()
-            .map(|(ivar, &x)| {
-                model.beta[ivar] * (x - model.xmeans[ivar]) / model.xscales[ivar]
-            })
-            .sum();
-        
-        let pred = pred * model.yscale + model.ymean;
-        
-        // Trading logic: long if pred > 0, short if pred < 0
-        let signal = if pred > 0.0 {
-            1
-        } else if pred < 0.0 {
-            -1
-        } else {
-            0
-        };
+        let pred = model.score(xptr);
         
-        signals.push(signal);
+        signals.push(position(pred, threshold, settings.long_only));
     }
     
     // Convert prices to log space for backtesting library
@@ -79,14 +116,27 @@ pub fn run_backtest(
     };
     
     // Run backtest
-    let result = backtest_signals(&signal_result, initial_capital, transaction_cost);
+    let options = BacktestOptions {
+        transaction_cost_pct: settings.transaction_cost_pct,
+        slippage: settings.slippage,
+        // Flat in the threshold's dead zone, and on sell signals long only
+        target_positions: true,
+        ..Default::default()
+    };
+    let result = backtest_signals_with(&signal_result, settings.initial_capital, &options);
     
     println!("Backtest completed:");
     println!("  Total trades: {}", result.num_trades);
+    println!("  Turnover: {:.0}", turnover(&result.position_history));
     println!("  Total return: {:.2}%", result.roi_percent);
     println!("  Win rate: {:.2}%", result.win_rate);
     println!("  Max drawdown: {:.2}%", result.max_drawdown);
     println!("  Sharpe ratio: {:.3}", result.sharpe_ratio);
+    println!("  Sortino ratio: {:.3}", result.sortino_ratio);
+    println!("  Calmar ratio: {:.3}", result.calmar_ratio);
+    println!("  MAR ratio: {:.3}", result.mar_ratio);
+    println!("  Ulcer index: {:.3}", result.ulcer_index);
+    println!("  Avg drawdown duration: {:.1} bars", result.avg_drawdown_duration);
     
     Ok(result)
 }
@@ -95,6 +145,7 @@ pub fn run_backtest(
 pub fn write_backtest_results<P: AsRef<Path>>(
     path: P,
     result: &TradeStats,
+    settings: &BacktestSettings,
 ) -> Result<()> {
     // Create parent directory if it doesn't exist
     if let Some(parent) = path.as_ref().parent() {
@@ -111,6 +162,22 @@ pub fn write_backtest_results<P: AsRef<Path>>(
     writeln!(file, "{}", "=".repeat(60))?;
     writeln!(file)?;
     
+    // How the predictions were traded
+    writeln!(file, "Settings:")?;
+    writeln!(file, "  Positions: {}", if settings.long_only { "long only" } else { "long/short" })?;
+    writeln!(file, "  Signal Threshold: {} std", settings.signal_threshold)?;
+    writeln!(file, "  Transaction Cost: {}%", settings.transaction_cost_pct)?;
+    let slippage = match settings.slippage {
+        Slippage::None => "none".to_string(),
+        Slippage::FixedTicks { ticks, tick_size } => format!("{} ticks of {}", ticks, tick_size),
+        Slippage::Percent { pct } => format!("{}%", pct),
+        Slippage::VolumeDependent { base_pct, impact_pct } => {
+            format!("{}% + {}% * sqrt(order / bar volume)", base_pct, impact_pct)
+        }
+    };
+    writeln!(file, "  Slippage: {}", slippage)?;
+    writeln!(file)?;
+    
     // Performance metrics
     writeln!(file, "Performance Metrics:")?;
     writeln!(file, "  Total Return: {:.2}%", result.roi_percent)?;
@@ -120,7 +187,18 @@ pub fn write_backtest_results<P: AsRef<Path>>(
     writeln!(file, "  Win Rate: {:.2}%", result.win_rate)?;
     writeln!(file, "  Max Drawdown: {:.2}%", result.max_drawdown)?;
     writeln!(file, "  Sharpe Ratio: {:.3}", result.sharpe_ratio)?;
+    writeln!(file, "  Sortino Ratio: {:.3}", result.sortino_ratio)?;
+    writeln!(file, "  Calmar Ratio: {:.3}", result.calmar_ratio)?;
+    writeln!(file, "  MAR Ratio: {:.3}", result.mar_ratio)?;
+    writeln!(file, "  Ulcer Index: {:.3}", result.ulcer_index)?;
+    writeln!(file, "  Avg Drawdown Duration: {:.1} bars", result.avg_drawdown_duration)?;
     writeln!(file, "  Total Costs: ${:.2}", result.total_costs)?;
+    writeln!(file, "  Total Slippage: ${:.2}", result.total_slippage)?;
+    let traded = turnover(&result.position_history);
+    let n_bars = result.position_history.len().max(1);
+    writeln!(file, "  Turnover: {:.0} ({:.4} per bar)", traded, traded / n_bars as f64)?;
+    writeln!(file, "  Time in Market: {:.2}%",
+             100.0 * result.position_history.iter().filter(|&&p| p != 0).count() as f64 / n_bars as f64)?;
     writeln!(file)?;
     
     // Trade log
@@ -178,10 +256,59 @@ mod tests {
         let test_prices = vec![100.0, 101.0, 102.0, 101.5, 103.0, 102.0, 104.0, 105.0, 104.5, 106.0];
         let test_data = vec![0.0; n_vars * n_cases];
         
-        let initial_capital = 100_000.0;
-        let transaction_cost = 0.1;
+        let settings = BacktestSettings::default();
         
-        let result = run_backtest(&model, &test_prices, &test_data, n_vars, initial_capital, transaction_cost);
+        let result = run_backtest(&model, &test_prices, &test_data, n_vars, &settings);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_threshold_and_long_only() {
+        assert_eq!(position(0.3, 0.0, false), 1);
+        assert_eq!(position(-0.3, 0.0, false), -1);
+        assert_eq!(position(0.3, 0.5, false), 0);
+        assert_eq!(position(-0.6, 0.5, false), -1);
+        assert_eq!(position(-0.6, 0.5, true), 0);
+
+        // Flat to long, reversed to short, closed at the end
+        assert_eq!(turnover(&[0, 1, 1, -1, -1]), 4.0);
+        assert_eq!(turnover(&[]), 0.0);
+
+        // Predictions alternate between +/-0.5 target deviations of 0.01: a
+        // threshold of 1 std never trades, long only never goes short
+        let n_vars = 1;
+        let mut model = CoordinateDescent::new(n_vars, 8, false, true, 0);
+        model.beta = vec![0.5];
+        model.xmeans = vec![0.0];
+        model.xscales = vec![1.0];
+        model.ymean = 0.0;
+        model.yscale = 0.01;
+        let test_data = vec![1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0];
+        let test_prices = vec![100.0, 101.0, 100.0, 102.0, 101.0, 103.0, 102.0, 104.0];
+
+        let all = run_backtest(&model, &test_prices, &test_data, n_vars, &BacktestSettings::default()).unwrap();
+        assert!(all.position_history.contains(&-1));
+
+        let settings = BacktestSettings { signal_threshold: 1.0, ..BacktestSettings::default() };
+        let none = run_backtest(&model, &test_prices, &test_data, n_vars, &settings).unwrap();
+        assert_eq!(none.num_trades, 0);
+        assert_eq!(turnover(&none.position_history), 0.0);
+
+        let settings = BacktestSettings { long_only: true, transaction_cost_pct: 0.0, ..BacktestSettings::default() };
+        let long = run_backtest(&model, &test_prices, &test_data, n_vars, &settings).unwrap();
+        assert!(long.position_history.iter().all(|&p| p >= 0));
+        assert_eq!(long.total_costs, 0.0);
+        // Each long is closed by the sell prediction that follows it
+        assert_eq!(long.trades.len(), 4);
+        assert!(long.trades.iter().all(|t| t.exit_index == t.entry_index + 1));
+
+        // A long held over several bars is marked to market, so its dip
+        // from 110 to 90 is the drawdown
+        let test_data = vec![1.0, 1.0, 1.0, 1.0, -1.0];
+        let test_prices = vec![100.0, 110.0, 90.0, 105.0, 100.0];
+        let held = run_backtest(&model, &test_prices, &test_data, n_vars, &settings).unwrap();
+        assert_eq!(held.trades.len(), 1);
+        assert!((held.budget_history[2] - 90_000.0).abs() < 1e-6);
+        assert!((held.max_drawdown - 100.0 * 20.0 / 110.0).abs() < 1e-9);
+    }
 }
\ No newline at end of file
@@ -1,9 +1,11 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::Parser;
 use std::path::PathBuf;
-use try_cd_ma::{Config, load_prices, generate_specs, compute_indicator_data, CDMAStrategy};
-use statn::models::cd_ma::CoordinateDescent;
-use backtesting::{BacktestConfig, run_backtest, generate_text_report};
+use try_cd_ma::{
+    BacktestSettings, Config, CDMAStrategy, compute_indicator_data, generate_specs, load_model, load_prices,
+    write_backtest_results,
+};
+use backtesting::{BacktestOptions, SignalResult, backtest_signals_with};
 
 /// Command-line arguments for backtesting
 #[derive(Parser, Debug)]
@@ -14,7 +16,7 @@ struct Args {
     #[arg(short, long)]
     config: PathBuf,
 
-    /// Path to saved model file (JSON)
+    /// Path to the model file saved by training (CD_MA_MODEL.json)
     #[arg(short, long)]
     model: PathBuf,
 
@@ -46,10 +48,7 @@ fn main() -> Result<()> {
 
     // Load model
     println!("Loading model from {}...", args.model.display());
-    let model_file = std::fs::File::open(&args.model)
-        .with_context(|| format!("Failed to open model file: {}", args.model.display()))?;
-    let model: CoordinateDescent = serde_json::from_reader(model_file)
-        .with_context(|| "Failed to parse model file")?;
+    let model = load_model(&args.model)?.model;
 
     // Load market data
     println!("Loading market data from {}...", args.data.display());
@@ -61,17 +60,21 @@ fn main() -> Result<()> {
         config.lookback_inc,
         config.n_long,
         config.n_short,
+        &config.ma_types,
     );
     println!("MA indicators: {}", config.n_ma_vars());
 
     println!("Total indicators: {}", specs.len());
+    if model.beta.len() != specs.len() {
+        anyhow::bail!("Model has {} coefficients but the configuration {} indicators", model.beta.len(), specs.len());
+    }
 
     // Compute indicators for the new data
     // We use the entire dataset for testing here
-    let n_cases = prices.len() - config.max_lookback();
-    if n_cases == 0 {
+    if prices.len() <= config.max_lookback() {
         anyhow::bail!("Insufficient data for backtesting");
     }
+    let n_cases = prices.len() - config.max_lookback();
 
     println!("Computing indicators for {} cases...", n_cases);
     let test_data = compute_indicator_data(
@@ -79,6 +82,7 @@ fn main() -> Result<()> {
         config.max_lookback(),
         n_cases,
         &specs,
+        &config.target_spec(),
     )?;
 
     // Create strategy
@@ -92,29 +96,38 @@ fn main() -> Result<()> {
         config.max_lookback(),
     );
 
-    // Run backtest
-    let backtest_config = BacktestConfig {
+    // Run backtest. The signals are positions, so a flat prediction
+    // closes the trade; the backtester takes the log prices as they are
+    let settings = BacktestSettings {
         initial_capital: args.initial_capital,
-        transaction_cost: args.transaction_cost,
+        transaction_cost_pct: 100.0 * args.transaction_cost,
+        ..BacktestSettings::default()
+    };
+    let signal_result = SignalResult {
+        signals: strategy.signals(prices.len()),
+        prices,
+        long_lookback: 0,
+        short_pct: 0.0,
+        short_thresh: 0.0,
+        long_thresh: 0.0,
+    };
+    let options = BacktestOptions {
+        transaction_cost_pct: settings.transaction_cost_pct,
+        slippage: settings.slippage,
+        target_positions: true,
+        ..Default::default()
     };
 
     println!("Running backtest...");
-    // We pass the raw prices (converted to non-log if needed, but here we assume log prices are OK for signal generation,
-    // but for equity calculation we might want real prices.
-    // The current backtesting package assumes prices are tradeable prices.
-    // try_cd_ma uses log prices.
-    // Let's convert log prices back to real prices for the backtester.
-    let real_prices: Vec<f64> = prices.iter().map(|&p| p.exp()).collect();
-    
-    let result = run_backtest(&strategy, &real_prices, &backtest_config)?;
+    let result = backtest_signals_with(&signal_result, settings.initial_capital, &options);
 
     // Write report
     println!("Writing report to {}...", args.output.display());
-    generate_text_report(&result, &args.output)?;
-    
+    write_backtest_results(&args.output, &result, &settings)?;
+
     println!("\nBacktest completed successfully.");
-    println!("Total Return: {:.4}", result.metrics.get("Total Return").unwrap_or(&0.0));
-    println!("Sharpe Ratio: {:.4}", result.metrics.get("Sharpe Ratio").unwrap_or(&0.0));
+    println!("Total Return: {:.2}%", result.roi_percent);
+    println!("Sharpe Ratio: {:.4}", result.sharpe_ratio);
 
     Ok(())
 }
@@ -1,10 +1,54 @@
 use anyhow::Result;
-use clap::Parser;
-use serde::Deserialize;
+use backtesting::Slippage;
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use statn::core::io::BarsFormat;
+use indicators::trend::ma::MaType;
 
+use statn::models::cd_ma::Family;
+
+use crate::backtest::BacktestSettings;
+use crate::targets::{TargetKind, TargetSpec};
+
+
+/// Command line: train on a market file, or score one with a saved model
+#[derive(Debug, Parser)]
+#[command(name = "try_cd_ma")]
+#[command(about = "Moving Average Crossover Indicator Selection using Coordinate Descent")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Training configuration, when no subcommand is given
+    #[command(flatten)]
+    pub config: Option<Config>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Score a market file with a saved model
+    Predict {
+        /// Model saved by a training run (CD_MA_MODEL.json)
+        #[arg(value_name = "MODEL_FILE")]
+        model_file: String,
+
+        /// Market data file to score
+        #[arg(value_name = "DATA_FILE")]
+        data_file: String,
+
+        /// Market data file format, "text" or "parquet" (default: from the extension)
+        #[arg(long)]
+        data_format: Option<BarsFormat>,
+
+        /// File to write the predictions to (default: standard output)
+        #[arg(long)]
+        output: Option<String>,
+    },
+}
 
 /// Configuration for CD_MA analysis
-#[derive(Debug, Clone, Deserialize, Parser)]
+#[derive(Debug, Clone, Serialize, Deserialize, Parser)]
 #[command(name = "try_cd_ma")]
 #[command(about = "Moving Average Crossover Indicator Selection using Coordinate Descent")]
 pub struct Config {
@@ -21,6 +65,44 @@ pub struct Config {
     #[arg(long, default_value_t = 5)]
     pub n_short: usize,
     
+    /// Moving averages whose crossovers enter the pool, e.g. sma,ema,wma,hull
+    #[arg(long, value_delimiter = ',', default_value = "sma")]
+    #[serde(default = "default_ma_types")]
+    pub ma_types: Vec<MaType>,
+    
+    /// Training target: next-bar, horizon, vol-scaled or triple-barrier
+    #[arg(long, default_value = "next-bar")]
+    #[serde(default)]
+    pub target: TargetKind,
+
+    /// Bars ahead for the horizon and vol-scaled targets, and the
+    /// triple-barrier timeout
+    #[arg(long, default_value_t = 1)]
+    #[serde(default = "default_target_horizon")]
+    pub target_horizon: usize,
+
+    /// Bars of returns whose standard deviation scales the vol-scaled target
+    /// and the barriers
+    #[arg(long, default_value_t = 20)]
+    #[serde(default = "default_vol_lookback")]
+    pub vol_lookback: usize,
+
+    /// Triple-barrier profit-take, in standard deviations of the horizon return
+    #[arg(long, default_value_t = 2.0)]
+    #[serde(default = "default_barrier")]
+    pub profit_take: f64,
+
+    /// Triple-barrier stop, in standard deviations of the horizon return
+    #[arg(long, default_value_t = 2.0)]
+    #[serde(default = "default_barrier")]
+    pub stop_loss: f64,
+
+    /// Train a logistic classifier of the direction of the target instead of
+    /// regressing on it
+    #[arg(long)]
+    #[serde(default)]
+    pub classify: bool,
+    
     /// Alpha parameter for elastic net (0-1]
     #[arg(long, default_value_t = 0.5)]
     pub alpha: f64,
@@ -28,6 +110,11 @@ pub struct Config {
     /// Path to market data file (YYYYMMDD Price format)
     #[arg(value_name = "DATA_FILE")]
     pub data_file: String,
+
+    /// Market data file format, "text" or "parquet" (default: from the extension)
+    #[arg(long)]
+    #[serde(default)]
+    pub data_format: Option<BarsFormat>,
     
     /// Path to output results file
     #[arg(long, default_value = "results/")]
@@ -44,6 +131,12 @@ pub struct Config {
     /// Number of lambda values to test
     #[arg(long, default_value_t = 50)]
     pub n_lambdas: usize,
+
+    /// Choose the largest lambda within one standard error of the best
+    /// cross-validated one, for a sparser model
+    #[arg(long)]
+    #[serde(default)]
+    pub one_se: bool,
     
     /// Maximum iterations for coordinate descent
     #[arg(long, default_value_t = 1000)]
@@ -52,7 +145,67 @@ pub struct Config {
     /// Convergence tolerance
     #[arg(long, default_value_t = 1e-9)]
     pub tolerance: f64,
-    
+
+    /// Half-life in cases of exponential time-decay training weights (0 = unweighted)
+    #[arg(long, default_value_t = 0.0)]
+    #[serde(default)]
+    pub half_life: f64,
+
+    /// Candidate half-lives to choose from on a recent holdout, e.g. 0,250,500,1000.
+    /// Overrides --half-life when given.
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub half_life_grid: Vec<f64>,
+
+    /// Retrain every this many test cases and report the walkforward
+    /// out-of-sample return (0 = train once)
+    #[arg(long, default_value_t = 0)]
+    #[serde(default)]
+    pub walkforward_step: usize,
+
+    /// Most recent cases each walkforward refit trains on (0 = all history)
+    #[arg(long, default_value_t = 0)]
+    #[serde(default)]
+    pub train_window: usize,
+
+    /// Elastic-net alphas of an ensemble of models whose averaged
+    /// predictions are also evaluated, e.g. 0.1,0.5,0.9 (empty = no ensemble)
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub ensemble_alphas: Vec<f64>,
+
+    /// Bootstrap samples, and so models, per ensemble alpha (0 = one model
+    /// per alpha on all training cases)
+    #[arg(long, default_value_t = 0)]
+    #[serde(default)]
+    pub ensemble_bootstrap: usize,
+
+    /// Backtest trades only when the prediction is more than this many
+    /// standard deviations of the training target from neutral
+    #[arg(long, default_value_t = 0.0)]
+    #[serde(default)]
+    pub signal_threshold: f64,
+
+    /// Backtest long positions only, staying flat instead of going short
+    #[arg(long)]
+    #[serde(default)]
+    pub long_only: bool,
+
+    /// Backtest transaction cost as a percentage (e.g., 0.1 for 0.1%)
+    #[arg(long, default_value_t = 0.1)]
+    #[serde(default = "default_transaction_cost")]
+    pub transaction_cost: f64,
+
+    /// Backtest slippage: none, pct:<pct>, ticks:<ticks>:<tick_size> or
+    /// volume:<base_pct>:<impact_pct>
+    #[arg(long, default_value = "none")]
+    #[serde(default)]
+    pub slippage: Slippage,
+
+    /// Backtest initial capital
+    #[arg(long, default_value_t = 100_000.0)]
+    #[serde(default = "default_initial_capital")]
+    pub initial_capital: f64,
 }
 
 impl Config {
@@ -74,6 +227,22 @@ impl Config {
             anyhow::bail!("n_short must be greater than 0");
         }
         
+        if self.ma_types.is_empty() {
+            anyhow::bail!("At least one moving average type is needed");
+        }
+        
+        if self.target_horizon == 0 {
+            anyhow::bail!("target_horizon must be greater than 0");
+        }
+        
+        if self.vol_lookback < 2 {
+            anyhow::bail!("vol_lookback must be at least 2");
+        }
+        
+        if self.profit_take <= 0.0 || self.stop_loss <= 0.0 {
+            anyhow::bail!("Barrier widths must be positive");
+        }
+        
         if self.n_test == 0 {
             anyhow::bail!("n_test must be greater than 0");
         }
@@ -81,6 +250,26 @@ impl Config {
         if self.n_folds < 2 {
             anyhow::bail!("n_folds must be at least 2");
         }
+
+        if self.half_life < 0.0 || self.half_life_grid.iter().any(|&h| h < 0.0) {
+            anyhow::bail!("Time-decay half-lives must not be negative");
+        }
+
+        if self.ensemble_alphas.iter().any(|&a| a <= 0.0 || a > 1.0) {
+            anyhow::bail!("Ensemble alphas must be in range (0, 1]");
+        }
+
+        if self.signal_threshold < 0.0 {
+            anyhow::bail!("signal_threshold must not be negative");
+        }
+
+        if self.transaction_cost < 0.0 {
+            anyhow::bail!("transaction_cost must not be negative");
+        }
+
+        if self.initial_capital <= 0.0 {
+            anyhow::bail!("initial_capital must be positive");
+        }
         
         Ok(())
     }
@@ -94,20 +283,74 @@ impl Config {
     
     /// Get total number of indicator variables
     pub fn n_vars(&self) -> usize {
-        self.n_long * self.n_short
+        self.n_ma_vars()
     }
     
     /// Get number of MA indicator variables
     pub fn n_ma_vars(&self) -> usize {
-        self.n_long * self.n_short
+        self.n_long * self.n_short * self.ma_types.len()
     }
     
 
     
-    /// Get maximum lookback period
+    /// Get maximum lookback period: the bars the longest moving average of
+    /// any type needs
     pub fn max_lookback(&self) -> usize {
-        self.n_long * self.lookback_inc
+        let long = self.n_long * self.lookback_inc;
+        let ma = self.ma_types.iter().map(|t| t.lookback(long)).max().unwrap_or(long);
+        ma.max(self.target_spec().bars_behind())
+    }
+
+    /// Loss the model is trained with
+    pub fn family(&self) -> Family {
+        if self.classify { Family::Binomial } else { Family::Gaussian }
     }
+
+    /// How the backtest trades the predictions
+    pub fn backtest_settings(&self) -> BacktestSettings {
+        BacktestSettings {
+            initial_capital: self.initial_capital,
+            transaction_cost_pct: self.transaction_cost,
+            slippage: self.slippage,
+            signal_threshold: self.signal_threshold,
+            long_only: self.long_only,
+        }
+    }
+
+    /// The training target asked for
+    pub fn target_spec(&self) -> TargetSpec {
+        TargetSpec {
+            kind: self.target,
+            horizon: self.target_horizon,
+            vol_lookback: self.vol_lookback,
+            profit_take: self.profit_take,
+            stop_loss: self.stop_loss,
+        }
+    }
+}
+
+fn default_ma_types() -> Vec<MaType> {
+    vec![MaType::Sma]
+}
+
+fn default_target_horizon() -> usize {
+    1
+}
+
+fn default_vol_lookback() -> usize {
+    20
+}
+
+fn default_barrier() -> f64 {
+    2.0
+}
+
+fn default_transaction_cost() -> f64 {
+    0.1
+}
+
+fn default_initial_capital() -> f64 {
+    100_000.0
 }
 
 #[cfg(test)]
@@ -120,14 +363,34 @@ mod tests {
             lookback_inc: 10,
             n_long: 20,
             n_short: 10,
+            ma_types: vec![MaType::Sma],
+            target: TargetKind::NextBar,
+            target_horizon: 1,
+            vol_lookback: 20,
+            profit_take: 2.0,
+            stop_loss: 2.0,
+            classify: false,
             alpha: 0.5,
             data_file: "test.txt".to_string(),
+            data_format: None,
             output_path: "output.log".to_string(),
             n_test: 252,
             n_folds: 10,
             n_lambdas: 50,
+            one_se: false,
             max_iterations: 1000,
             tolerance: 1e-9,
+            half_life: 0.0,
+            half_life_grid: Vec::new(),
+            walkforward_step: 0,
+            train_window: 0,
+            ensemble_alphas: Vec::new(),
+            ensemble_bootstrap: 0,
+            signal_threshold: 0.0,
+            long_only: false,
+            transaction_cost: 0.1,
+            slippage: Slippage::None,
+            initial_capital: 100_000.0,
         };
         
         assert!(config.validate().is_ok());
@@ -137,6 +400,14 @@ mod tests {
         
         config.alpha = 0.0;
         assert!(config.validate().is_err());
+
+        config.alpha = 0.5;
+        config.half_life_grid = vec![0.0, -10.0];
+        assert!(config.validate().is_err());
+
+        config.half_life_grid = Vec::new();
+        config.target_horizon = 0;
+        assert!(config.validate().is_err());
     }
     
     #[test]
@@ -145,17 +416,70 @@ mod tests {
             lookback_inc: 10,
             n_long: 20,
             n_short: 10,
+            ma_types: vec![MaType::Sma],
+            target: TargetKind::NextBar,
+            target_horizon: 1,
+            vol_lookback: 20,
+            profit_take: 2.0,
+            stop_loss: 2.0,
+            classify: false,
             alpha: 0.5,
             data_file: "test.txt".to_string(),
+            data_format: None,
             output_path: "output.log".to_string(),
             n_test: 252,
             n_folds: 10,
             n_lambdas: 50,
+            one_se: false,
             max_iterations: 1000,
             tolerance: 1e-9,
+            half_life: 0.0,
+            half_life_grid: Vec::new(),
+            walkforward_step: 0,
+            train_window: 0,
+            ensemble_alphas: Vec::new(),
+            ensemble_bootstrap: 0,
+            signal_threshold: 0.0,
+            long_only: false,
+            transaction_cost: 0.1,
+            slippage: Slippage::None,
+            initial_capital: 100_000.0,
         };
         
         assert_eq!(config.n_vars(), 200);
         assert_eq!(config.max_lookback(), 200);
+
+        // Hull averages need sqrt(lookback) more bars to start
+        let config = Config { ma_types: vec![MaType::Sma, MaType::Hull], ..config };
+        assert_eq!(config.n_vars(), 400);
+        assert_eq!(config.max_lookback(), 213);
+
+        // Volatility targets need their lookback too
+        let config = Config { n_long: 1, ma_types: vec![MaType::Sma], target: TargetKind::VolScaled, ..config };
+        assert_eq!(config.max_lookback(), 20);
+        assert_eq!(config.target_spec().bars_behind(), 20);
+        assert_eq!(config.family(), Family::Gaussian);
+        assert_eq!(Config { classify: true, ..config }.family(), Family::Binomial);
+    }
+
+    #[test]
+    fn test_cli() {
+        let cli = Cli::try_parse_from(["try_cd_ma", "--n-long", "3", "prices.txt"]).unwrap();
+        assert!(cli.command.is_none());
+        let config = cli.config.unwrap();
+        assert_eq!((config.n_long, config.data_file.as_str()), (3, "prices.txt"));
+
+        let cli = Cli::try_parse_from(["try_cd_ma", "predict", "model.json", "new.txt", "--output", "p.txt"]).unwrap();
+        assert!(cli.config.is_none());
+        match cli.command {
+            Some(Command::Predict { model_file, data_file, output, .. }) => {
+                assert_eq!((model_file.as_str(), data_file.as_str()), ("model.json", "new.txt"));
+                assert_eq!(output.as_deref(), Some("p.txt"));
+            }
+            None => panic!("Expected the predict command"),
+        }
+
+        assert!(Cli::try_parse_from(["try_cd_ma"]).is_err());
+        assert!(Cli::try_parse_from(["try_cd_ma", "--n-long", "3", "predict", "model.json", "new.txt"]).is_err());
     }
 }
\ No newline at end of file
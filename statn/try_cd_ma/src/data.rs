@@ -1,11 +1,44 @@
+use std::path::Path;
+
+use statn::core::io::{read_bars_with_format, Bars, BarsFormat};
+
 // Re-export from shared I/O modules
 pub use statn::core::io::{
-    read_price_file as load_prices,
     split_train_test,
     compute_targets,
     DataSplit,
 };
 
+/// Load the log close prices of a market file (YYYYMMDD Price, or OHLC[V]),
+/// or of a Parquet file if named .parquet
+pub fn load_prices<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, String> {
+    load_prices_with_format(path, None)
+}
+
+/// Load the log close prices of a market file in the given format, or the
+/// format given by its extension if `format` is `None`
+pub fn load_prices_with_format<P: AsRef<Path>>(
+    path: P,
+    format: Option<BarsFormat>,
+) -> Result<Vec<f64>, String> {
+    Ok(load_bars_with_format(path, format)?.log_close())
+}
+
+/// Load the bars of a market file in the given format, or the format given
+/// by its extension if `format` is `None`. Parquet needs the `parquet`
+/// feature of this crate.
+pub fn load_bars_with_format<P: AsRef<Path>>(path: P, format: Option<BarsFormat>) -> Result<Bars, String> {
+    let format = format.unwrap_or_else(|| BarsFormat::from_path(&path));
+    #[cfg(not(feature = "parquet"))]
+    if format == BarsFormat::Parquet {
+        return Err(format!(
+            "Cannot read {}: Parquet support requires try_cd_ma built with `--features parquet`",
+            path.as_ref().display()
+        ));
+    }
+    read_bars_with_format(path, Some(format))
+}
+
 /// Market data structure
 #[derive(Debug, Clone)]
 pub struct MarketData {
@@ -30,6 +63,13 @@ mod tests {
         assert_eq!(prices.len(), 3);
         assert!((prices[0] - 100.0_f64.ln()).abs() < 1e-10);
     }
+
+    #[cfg(not(feature = "parquet"))]
+    #[test]
+    fn test_parquet_needs_feature() {
+        let err = load_prices("prices.parquet").unwrap_err();
+        assert!(err.contains("--features parquet"), "{}", err);
+    }
     
     #[test]
     fn test_split_train_test() {
@@ -1,12 +1,16 @@
-use anyhow::Result;
-use std::fs::OpenOptions;
-use std::io::Write;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
 
 use crate::config::Config;
-use crate::indicators::IndicatorSpec;
-use crate::training::TrainingResult;
-use statn::models::cd_ma::CoordinateDescent;
+use crate::indicators::{IndicatorSpec, compute_all_indicators};
+use crate::targets::TargetKind;
+use crate::training::{ClassificationMetrics, EnsembleResult, TrainingResult};
+use crate::walkforward::WalkforwardResult;
+use statn::models::cd_ma::{CoordinateDescent, Family};
+use statn::models::learner::Model;
 
 /// Evaluation results
 #[derive(Debug)]
@@ -15,13 +19,142 @@ pub struct EvaluationResult {
     pub oos_return: f64,
     /// Out-of-sample return percentage
     pub oos_return_pct: f64,
-    /// In-sample explained variance
+    /// In-sample explained variance, or deviance explained for a classifier
     pub in_sample_explained: f64,
+    /// Out-of-sample AUC and accuracy of a classifier
+    pub classification: Option<ClassificationMetrics>,
+    /// Out-of-sample record of periodic retraining, if asked for
+    pub walkforward: Option<WalkforwardResult>,
+    /// Out-of-sample returns of an ensemble and its members, if asked for
+    pub ensemble: Option<EnsembleEvaluation>,
 }
 
-/// Evaluate model on test data
-pub fn evaluate_model(
-    model: &CoordinateDescent,
+/// Out-of-sample return of one ensemble member
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberReturn {
+    pub alpha: f64,
+    pub lambda: f64,
+    /// Bootstrap sample of the member, `None` for all cases
+    pub sample: Option<usize>,
+    /// Nonzero coefficients of the member
+    pub n_active: usize,
+    /// Out-of-sample total return (log) of trading the member alone
+    pub oos_return: f64,
+}
+
+/// Out-of-sample performance of an ensemble
+#[derive(Debug, Clone)]
+pub struct EnsembleEvaluation {
+    /// Out-of-sample total return (log) of trading the averaged predictions
+    pub oos_return: f64,
+    /// Out-of-sample return percentage
+    pub oos_return_pct: f64,
+    pub members: Vec<MemberReturn>,
+}
+
+/// Trade the averaged predictions of an ensemble, and each member's own, on
+/// the test cases
+pub fn evaluate_ensemble(
+    ensemble: &EnsembleResult,
+    test_data: &[f64],
+    test_targets: &[f64],
+    n_vars: usize,
+) -> EnsembleEvaluation {
+    let family = ensemble.family();
+    let cases = || test_data.chunks_exact(n_vars).zip(test_targets);
+    let oos_return: f64 = cases().map(|(x, &ret)| signal(ensemble.predict(x), family) as f64 * ret).sum();
+    let oos_return_pct = 100.0 * (oos_return.exp() - 1.0);
+    let members = ensemble
+        .members
+        .iter()
+        .map(|m| MemberReturn {
+            alpha: m.alpha,
+            lambda: m.lambda,
+            sample: m.sample,
+            n_active: m.model.beta.iter().filter(|&&b| b != 0.0).count(),
+            oos_return: cases().map(|(x, &ret)| signal(m.model.predict(x), family) as f64 * ret).sum(),
+        })
+        .collect();
+    println!(
+        "Ensemble OOS total return ({} models): {:.5} ({:.3}%)",
+        ensemble.members.len(),
+        oos_return,
+        oos_return_pct
+    );
+    EnsembleEvaluation { oos_return, oos_return_pct, members }
+}
+
+/// An indicator the model uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedIndicator {
+    /// Position of the indicator among the specs
+    pub index: usize,
+    pub name: String,
+    /// Coefficient of the standardized indicator, comparable across indicators
+    pub beta: f64,
+    /// Coefficient in the indicator's own units
+    pub raw_beta: f64,
+}
+
+/// Indicators with nonzero coefficients, largest standardized magnitude first
+pub fn selected_indicators(model: &CoordinateDescent, specs: &[IndicatorSpec]) -> Vec<SelectedIndicator> {
+    let raw = model.raw_coefficients();
+    let mut selected: Vec<SelectedIndicator> = specs
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| model.beta[index] != 0.0)
+        .map(|(index, spec)| SelectedIndicator {
+            index,
+            name: spec.name(),
+            beta: model.beta[index],
+            raw_beta: raw[index],
+        })
+        .collect();
+    selected.sort_by(|a, b| b.beta.abs().total_cmp(&a.beta.abs()));
+    selected
+}
+
+/// Ranked table of the selected indicators, with each one's share of the
+/// total standardized magnitude
+fn write_selection(
+    file: &mut impl Write,
+    selected: &[SelectedIndicator],
+    n_vars: usize,
+    lambda: f64,
+) -> std::io::Result<()> {
+    writeln!(
+        file,
+        "Selected Indicators ({} of {} nonzero at lambda {:.6}):",
+        selected.len(),
+        n_vars,
+        lambda
+    )?;
+    writeln!(
+        file,
+        "  {:>4} {:<16} {:>4} {:>12} {:>8} {:>14}",
+        "Rank", "Indicator", "Sign", "|Std beta|", "Share %", "Raw beta"
+    )?;
+    writeln!(file, "  {}", "-".repeat(63))?;
+    let total: f64 = selected.iter().map(|s| s.beta.abs()).sum();
+    for (rank, s) in selected.iter().enumerate() {
+        writeln!(
+            file,
+            "  {:>4} {:<16} {:>4} {:>12.4} {:>8.2} {:>14.6e}",
+            rank + 1,
+            s.name,
+            if s.beta > 0.0 { "+" } else { "-" },
+            s.beta.abs(),
+            100.0 * s.beta.abs() / total,
+            s.raw_beta
+        )?;
+    }
+    writeln!(file)
+}
+
+/// Evaluate model on test data, going long when its score is positive and
+/// short when negative
+pub fn evaluate_model<M: Model>(
+    model: &M,
     test_data: &[f64],
     test_targets: &[f64],
     n_vars: usize,
@@ -35,15 +168,7 @@ pub fn evaluate_model(
             let xptr = &test_data[i * n_vars..(i + 1) * n_vars];
             
             // Compute prediction
-            let pred: f64 = xptr
-                .iter()
-                .enumerate()
-                .map(|(ivar, &x)| {
-                    model.beta[ivar] * (x - model.xmeans[ivar]) / model.xscales[ivar]
-                })
-                .sum();
-            
-            let pred = pred * model.yscale + model.ymean;
+            let pred = model.score(xptr);
             
             // Trading logic: long if pred > 0, short if pred < 0
             if pred > 0.0 {
@@ -63,17 +188,117 @@ pub fn evaluate_model(
     Ok(EvaluationResult {
         oos_return,
         oos_return_pct,
-        in_sample_explained: model.explained,
+        in_sample_explained: model.explained(),
+        classification: None,
+        walkforward: None,
+        ensemble: None,
     })
 }
 
+/// A trained model with what scoring new prices needs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedModel {
+    /// Trained model: coefficients, predictor means and scales, and family
+    pub model: CoordinateDescent,
+    /// Lambda the model was trained at
+    pub lambda: f64,
+    /// Indicators the coefficients belong to, in order
+    pub specs: Vec<IndicatorSpec>,
+    /// Bars of history needed before the first bar that can be scored
+    pub max_lookback: usize,
+    /// Configuration the model was trained with
+    pub config: Config,
+}
+
+/// Save a trained model to a JSON file
+pub fn save_model<P: AsRef<Path>>(path: P, saved: &SavedModel) -> Result<()> {
+    let path = path.as_ref();
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create model file: {}", path.display()))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), saved)
+        .with_context(|| "Failed to serialize model to JSON")?;
+    println!("Model saved to {}", path.display());
+    Ok(())
+}
+
+/// Load a model saved by `save_model`
+pub fn load_model<P: AsRef<Path>>(path: P) -> Result<SavedModel> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open model file: {}", path.display()))?;
+    let saved: SavedModel = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| "Failed to deserialize model from JSON")?;
+    if saved.model.beta.len() != saved.specs.len() {
+        anyhow::bail!(
+            "Model has {} coefficients but {} indicators",
+            saved.model.beta.len(),
+            saved.specs.len()
+        );
+    }
+    Ok(saved)
+}
+
+/// Prediction of a saved model at every bar of `prices` (log prices) from
+/// `max_lookback` on: the predicted target, or the probability that it is
+/// positive for a classifier
+pub fn predict_prices(saved: &SavedModel, prices: &[f64]) -> Result<Vec<f64>> {
+    if prices.len() <= saved.max_lookback {
+        anyhow::bail!(
+            "Need more than {} prices to score, got {}",
+            saved.max_lookback,
+            prices.len()
+        );
+    }
+    let n_cases = prices.len() - saved.max_lookback;
+    let n_vars = saved.specs.len();
+    let data = compute_all_indicators(prices, saved.max_lookback, n_cases, &saved.specs)?;
+    Ok((0..n_cases)
+        .map(|i| saved.model.predict(&data[i * n_vars..(i + 1) * n_vars]))
+        .collect())
+}
+
+/// Write one line per scored bar: its date, the prediction and the position
+/// it calls for (1 long, -1 short, 0 flat)
+pub fn write_predictions(
+    out: &mut impl Write,
+    dates: &[u32],
+    predictions: &[f64],
+    family: Family,
+) -> std::io::Result<()> {
+    let header = match family {
+        Family::Gaussian => "Prediction",
+        Family::Binomial => "P(up)",
+    };
+    writeln!(out, "{:<10} {:>14} {:>8}", "Date", header, "Signal")?;
+    for (date, &pred) in dates.iter().zip(predictions) {
+        writeln!(out, "{:<10} {:>14.6} {:>8}", date, pred, signal(pred, family))?;
+    }
+    Ok(())
+}
+
+/// Position a prediction of `CoordinateDescent::predict` calls for: 1 long, -1 short, 0
+/// flat. A classifier's probability is compared with one half.
+pub(crate) fn signal(prediction: f64, family: Family) -> i32 {
+    let neutral = match family {
+        Family::Gaussian => 0.0,
+        Family::Binomial => 0.5,
+    };
+    if prediction > neutral {
+        1
+    } else if prediction < neutral {
+        -1
+    } else {
+        0
+    }
+}
+
 /// Write results to file
 pub fn write_results<P: AsRef<Path>>(
     path: P,
     config: &Config,
     training: &TrainingResult,
     evaluation: &EvaluationResult,
-    _specs: &[IndicatorSpec],
+    specs: &[IndicatorSpec],
 ) -> Result<()> {
     // Create parent directory if it doesn't exist
     if let Some(parent) = path.as_ref().parent() {
@@ -96,33 +321,71 @@ pub fn write_results<P: AsRef<Path>>(
     writeln!(file, "  Number of long-term lookbacks: {}", config.n_long)?;
     writeln!(file, "  Number of short-term lookbacks: {}", config.n_short)?;
     writeln!(file, "  Alpha: {:.4}", config.alpha)?;
+    let ma_types: Vec<String> = config.ma_types.iter().map(|t| t.to_string()).collect();
+    writeln!(file, "  MA types: {}", ma_types.join(", "))?;
     writeln!(file, "  MA indicators: {}", config.n_ma_vars())?;
 
     writeln!(file, "  Total indicators: {}", config.n_vars())?;
+    match config.target {
+        TargetKind::NextBar => writeln!(file, "  Target: next-bar return")?,
+        TargetKind::TripleBarrier => writeln!(
+            file,
+            "  Target: triple-barrier over {} bars (profit-take {}, stop {} sd, volatility over {} bars)",
+            config.target_horizon, config.profit_take, config.stop_loss, config.vol_lookback
+        )?,
+        kind => writeln!(file, "  Target: {} return over {} bars", kind, config.target_horizon)?,
+    }
+    if config.classify {
+        writeln!(file, "  Model: logistic classifier of the target's direction")?;
+    }
     writeln!(file, "  Test cases: {}", config.n_test)?;
+    if training.half_life > 0.0 {
+        writeln!(file, "  Time-decay half-life: {} cases", training.half_life)?;
+    } else {
+        writeln!(file, "  Time-decay half-life: none (equal weights)")?;
+    }
     writeln!(file)?;
+
+    // Half-life selection
+    if !training.half_life_scores.is_empty() {
+        writeln!(file, "Time-Decay Half-Life Selection (recent holdout):")?;
+        let score = if config.classify { "AUC" } else { "Explained" };
+        writeln!(file, "  {:>10} {:>15}", "Half-life", score)?;
+        writeln!(file, "  {}", "-".repeat(27))?;
+        for (half_life, score) in &training.half_life_scores {
+            writeln!(file, "  {:>10} {:>15.4}", half_life, score)?;
+        }
+        writeln!(file)?;
+    }
     
     // Cross-validation results
     if config.alpha > 0.0 {
         writeln!(file, "Cross-Validation Results:")?;
-        writeln!(file, "  Optimal lambda: {:.6}", training.lambda)?;
+        if training.one_se {
+            writeln!(file, "  Lambda within one standard error of the best: {:.6}", training.lambda)?;
+        } else {
+            writeln!(file, "  Optimal lambda: {:.6}", training.lambda)?;
+        }
         writeln!(file)?;
-        writeln!(file, "  {:>10} {:>15}", "Lambda", "OOS Explained")?;
-        writeln!(file, "  {}", "-".repeat(27))?;
+        writeln!(file, "  {:>10} {:>15} {:>12}", "Lambda", "OOS Explained", "Std Error")?;
+        writeln!(file, "  {}", "-".repeat(40))?;
         for i in 0..training.lambdas.len() {
+            let chosen = if training.lambdas[i] == training.lambda { " *" } else { "" };
             writeln!(
                 file,
-                "  {:>10.4} {:>15.4}",
-                training.lambdas[i], training.lambda_oos[i]
+                "  {:>10.4} {:>15.4} {:>12.4}{}",
+                training.lambdas[i], training.lambda_oos[i], training.lambda_se[i], chosen
             )?;
         }
         writeln!(file)?;
     }
     
     // Beta coefficients for MA indicators
+    let explained = if config.classify { "deviance" } else { "variance" };
     writeln!(
         file,
-        "MA Beta Coefficients (In-sample explained variance: {:.3}%):",
+        "MA Beta Coefficients (In-sample explained {}: {:.3}%):",
+        explained,
         100.0 * evaluation.in_sample_explained
     )?;
     writeln!(
@@ -151,6 +414,13 @@ pub fn write_results<P: AsRef<Path>>(
     // RSI beta coefficients if enabled
 
     
+    write_selection(&mut file, &selected_indicators(&training.model, specs), specs.len(), training.lambda)?;
+    
+    if let Some(metrics) = training.classification {
+        writeln!(file, "In-sample AUC: {:.4}, accuracy: {:.3}%", metrics.auc, 100.0 * metrics.accuracy)?;
+        writeln!(file)?;
+    }
+    
     // Out-of-sample results
     writeln!(file, "Out-of-Sample Results:")?;
     writeln!(
@@ -158,6 +428,57 @@ pub fn write_results<P: AsRef<Path>>(
         "  Total return: {:.5} ({:.3}%)",
         evaluation.oos_return, evaluation.oos_return_pct
     )?;
+    if let Some(metrics) = evaluation.classification {
+        writeln!(file, "  AUC: {:.4}", metrics.auc)?;
+        writeln!(file, "  Accuracy: {:.3}%", 100.0 * metrics.accuracy)?;
+    }
+
+    if let Some(walk) = &evaluation.walkforward {
+        writeln!(file)?;
+        writeln!(file, "Walkforward Results (retrained every {} test cases):", config.walkforward_step)?;
+        writeln!(
+            file,
+            "  {:>10} {:>8} {:>8} {:>12} {:>8}",
+            "First case", "Cases", "Trained", "Lambda", "Active"
+        )?;
+        writeln!(file, "  {}", "-".repeat(50))?;
+        for refit in &walk.refits {
+            writeln!(
+                file,
+                "  {:>10} {:>8} {:>8} {:>12.6} {:>8}",
+                refit.first_test_case, refit.n_test_cases, refit.n_train, refit.lambda, refit.n_active
+            )?;
+        }
+        writeln!(
+            file,
+            "  Total return: {:.5} ({:.3}%)",
+            walk.oos_return, walk.oos_return_pct
+        )?;
+    }
+
+    if let Some(ensemble) = &evaluation.ensemble {
+        writeln!(file)?;
+        writeln!(file, "Ensemble Results ({} models, predictions averaged):", ensemble.members.len())?;
+        writeln!(
+            file,
+            "  {:>6} {:>7} {:>12} {:>8} {:>12}",
+            "Alpha", "Sample", "Lambda", "Active", "OOS return"
+        )?;
+        writeln!(file, "  {}", "-".repeat(49))?;
+        for m in &ensemble.members {
+            let sample = m.sample.map_or("all".to_string(), |s| (s + 1).to_string());
+            writeln!(
+                file,
+                "  {:>6} {:>7} {:>12.6} {:>8} {:>12.5}",
+                m.alpha, sample, m.lambda, m.n_active, m.oos_return
+            )?;
+        }
+        writeln!(
+            file,
+            "  Ensemble total return: {:.5} ({:.3}%)",
+            ensemble.oos_return, ensemble.oos_return_pct
+        )?;
+    }
     
     println!("\nResults written to {}", path.as_ref().display());
     Ok(())
@@ -188,4 +509,92 @@ mod tests {
         let result = evaluate_model(&model, &test_data, &test_targets, n_vars);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_evaluate_any_model() {
+        use statn::models::gbt::{GbtParams, GradientBoostedTrees};
+
+        // Returns follow the sign of x0, so both learners trade every case right
+        let n_cases = 200;
+        let data: Vec<f64> = (0..n_cases).map(|i| ((i * 37) % 101) as f64 / 50.0 - 1.0).collect();
+        let targets: Vec<f64> = data.iter().map(|&x| if x > 0.0 { 0.01 } else { -0.01 }).collect();
+        let best: f64 = targets.iter().map(|r| r.abs()).sum();
+
+        let params = GbtParams { n_trees: 50, learning_rate: 0.2, min_leaf: 5, ..GbtParams::default() };
+        let trees = GradientBoostedTrees::fit(1, n_cases, &data, &targets, None, &params).unwrap();
+        let linear = CoordinateDescent::fit(1, n_cases, &data, &targets, None, &Default::default()).unwrap();
+        for result in [
+            evaluate_model(&trees, &data, &targets, 1).unwrap(),
+            evaluate_model(&linear, &data, &targets, 1).unwrap(),
+        ] {
+            assert!((result.oos_return - best).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_save_load_predict() {
+        use clap::Parser;
+        use crate::indicators::generate_specs;
+        use crate::training::train_with_cv;
+
+        let prices: Vec<f64> = (0..300).map(|i| 4.0 + 0.01 * (i as f64 * 0.3).sin() + 0.0002 * i as f64).collect();
+        let specs = generate_specs(2, 3, 2, &[crate::MaType::Sma]);
+        let max_lookback = 6;
+        let n_cases = prices.len() - max_lookback - 1;
+        let data = compute_all_indicators(&prices, max_lookback, n_cases, &specs).unwrap();
+        let targets = statn::core::io::compute_targets(&prices, max_lookback, n_cases);
+        let training =
+            train_with_cv(specs.len(), n_cases, &data, &targets, 0.5, 5, 10, false, 1000, 1e-9, 0.0, Family::Gaussian)
+                .unwrap();
+
+        let config = Config::parse_from(["try_cd_ma", "--n-long", "3", "--n-short", "2", "prices.txt"]);
+        let saved = SavedModel { model: training.model, lambda: training.lambda, specs, max_lookback, config };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.json");
+        save_model(&path, &saved).unwrap();
+        let loaded = load_model(&path).unwrap();
+        assert_eq!(loaded.specs, saved.specs);
+        assert_eq!((loaded.lambda, loaded.max_lookback), (saved.lambda, max_lookback));
+        assert_eq!(loaded.config.n_long, 3);
+
+        // The loaded model scores every bar after the lookback as the
+        // trained one does
+        let predictions = predict_prices(&loaded, &prices).unwrap();
+        assert_eq!(predictions.len(), prices.len() - max_lookback);
+        for (i, &pred) in predictions.iter().take(n_cases).enumerate() {
+            let expected = saved.model.predict(&data[i * 6..(i + 1) * 6]);
+            assert!((pred - expected).abs() < 1e-12);
+        }
+        assert!(predict_prices(&loaded, &prices[..max_lookback]).is_err());
+
+        let mut out = Vec::new();
+        write_predictions(&mut out, &[20240102, 20240103], &[0.002, -0.001], Family::Gaussian).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.lines().nth(1).unwrap().ends_with(" 1"));
+        assert!(text.lines().nth(2).unwrap().ends_with(" -1"));
+    }
+
+    #[test]
+    fn test_selected_indicators() {
+        let mut model = CoordinateDescent::new(3, 10, false, true, 0);
+        model.beta = vec![0.1, 0.0, -0.3];
+        model.xscales = vec![2.0, 1.0, 0.5];
+        model.yscale = 0.01;
+        let specs = crate::indicators::generate_specs(4, 1, 3, &[crate::MaType::Sma]);
+
+        let selected = selected_indicators(&model, &specs);
+        assert_eq!(selected.len(), 2);
+        assert_eq!((selected[0].index, selected[0].name.as_str()), (2, "sma_3_4"));
+        assert_eq!(selected[0].beta, -0.3);
+        assert!((selected[0].raw_beta - (-0.3 * 0.01 / 0.5)).abs() < 1e-15);
+        assert_eq!(selected[1].index, 0);
+
+        let mut out = Vec::new();
+        write_selection(&mut out, &selected, 3, 0.002).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("Selected Indicators (2 of 3 nonzero at lambda 0.002000):"));
+        let first = text.lines().nth(3).unwrap();
+        assert!(first.contains("sma_3_4") && first.contains(" - ") && first.contains("75.00"));
+    }
 }
\ No newline at end of file
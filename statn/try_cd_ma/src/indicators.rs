@@ -1,48 +1,73 @@
 use anyhow::Result;
 use indicators::trend::ma::compute_indicators as compute_ma_indicator;
+pub use indicators::trend::ma::MaType;
+use serde::{Deserialize, Serialize};
 use statn::core::io::compute_targets;
 
+use crate::targets::TargetSpec;
+
 /// Specification for a single indicator
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IndicatorSpec {
-    /// Moving average crossover indicator
+    /// Moving average crossover indicator: the short-term average less the
+    /// long-term one, both of `ma_type`
     MovingAverage {
+        ma_type: MaType,
         short_lookback: usize,
         long_lookback: usize,
     },
 }
 
+impl IndicatorSpec {
+    /// Short label of the indicator, e.g. `sma_4_10` for the crossover of the
+    /// 4-bar and 10-bar simple moving averages
+    pub fn name(&self) -> String {
+        match self {
+            IndicatorSpec::MovingAverage { ma_type, short_lookback, long_lookback } => {
+                format!("{}_{}_{}", ma_type, short_lookback, long_lookback)
+            }
+        }
+    }
+}
+
 /// Computed indicators and targets for a dataset
 #[derive(Debug)]
 pub struct IndicatorData {
     /// Indicator matrix: n_cases x n_vars
     pub data: Vec<f64>,
-    /// Target returns: n_cases
+    /// Training targets: n_cases
     pub targets: Vec<f64>,
+    /// Next-bar log returns, which trading on the predictions earns: n_cases
+    pub returns: Vec<f64>,
     /// Number of cases
     pub n_cases: usize,
     /// Number of variables (indicators)
     pub n_vars: usize,
 }
 
-/// Generate all indicator specifications based on configuration
+/// Generate all indicator specifications based on configuration: the same
+/// grid of lookbacks for each moving average type in turn
 pub fn generate_specs(
     lookback_inc: usize,
     n_long: usize,
     n_short: usize,
+    ma_types: &[MaType],
 ) -> Vec<IndicatorSpec> {
     let mut specs = Vec::new();
     
     // Generate MA crossover indicators
-    for ilong in 0..n_long {
-        let long_lookback = (ilong + 1) * lookback_inc;
-        for ishort in 0..n_short {
-            let short_lookback = long_lookback * (ishort + 1) / (n_short + 1);
-            let short_lookback = short_lookback.max(1);
-            specs.push(IndicatorSpec::MovingAverage {
-                short_lookback,
-                long_lookback,
-            });
+    for &ma_type in ma_types {
+        for ilong in 0..n_long {
+            let long_lookback = (ilong + 1) * lookback_inc;
+            for ishort in 0..n_short {
+                let short_lookback = long_lookback * (ishort + 1) / (n_short + 1);
+                let short_lookback = short_lookback.max(1);
+                specs.push(IndicatorSpec::MovingAverage {
+                    ma_type,
+                    short_lookback,
+                    long_lookback,
+                });
+            }
         }
     }
         
@@ -61,7 +86,7 @@ pub fn compute_all_indicators(
     
     for (k, spec) in specs.iter().enumerate() {
         let indicators = match spec {
-            IndicatorSpec::MovingAverage { short_lookback, long_lookback } => {
+            IndicatorSpec::MovingAverage { ma_type: MaType::Sma, short_lookback, long_lookback } => {
                 compute_ma_indicator(
                     n_cases,
                     prices,
@@ -70,6 +95,13 @@ pub fn compute_all_indicators(
                     *long_lookback,
                 )
             }
+            IndicatorSpec::MovingAverage { ma_type, short_lookback, long_lookback } => {
+                let short_ma = ma_type.compute(prices, *short_lookback);
+                let long_ma = ma_type.compute(prices, *long_lookback);
+                (start_idx..start_idx + n_cases)
+                    .map(|k| short_ma[k] - long_ma[k])
+                    .collect()
+            }
         };
         
         for i in 0..n_cases {
@@ -86,14 +118,17 @@ pub fn compute_indicator_data(
     start_idx: usize,
     n_cases: usize,
     specs: &[IndicatorSpec],
+    target: &TargetSpec,
 ) -> Result<IndicatorData> {
     let data = compute_all_indicators(prices, start_idx, n_cases, specs)?;
-    let targets = compute_targets(prices, start_idx, n_cases);
+    let targets = target.compute(prices, start_idx, n_cases);
+    let returns = compute_targets(prices, start_idx, n_cases);
     let n_vars = specs.len();
     
     Ok(IndicatorData {
         data,
         targets,
+        returns,
         n_cases,
         n_vars,
     })
@@ -105,12 +140,12 @@ mod tests {
     
     #[test]
     fn test_generate_specs() {
-        let specs = generate_specs(10, 3, 2);
+        let specs = generate_specs(10, 3, 2, &[MaType::Sma]);
         assert_eq!(specs.len(), 6); // 3 * 2
         
         // Check first spec is MA
         match &specs[0] {
-            IndicatorSpec::MovingAverage { short_lookback, long_lookback } => {
+            IndicatorSpec::MovingAverage { short_lookback, long_lookback, .. } => {
                 assert_eq!(*long_lookback, 10);
                 assert!(*short_lookback > 0);
             }
@@ -129,7 +164,24 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_smoothed_ma_specs() {
+        let specs = generate_specs(4, 2, 1, &[MaType::Sma, MaType::Ema, MaType::Hull]);
+        assert_eq!(specs.len(), 6);
+        assert!(matches!(specs[2], IndicatorSpec::MovingAverage { ma_type: MaType::Ema, .. }));
+        assert_eq!(specs[5].name(), "hull_4_8");
 
+        // Every type is ready from the lookback its longest average needs
+        let prices: Vec<f64> = (0..60).map(|i| 100.0 + (i as f64 * 0.4).sin()).collect();
+        let start_idx = MaType::Hull.lookback(8);
+        let data = compute_all_indicators(&prices, start_idx, 20, &specs).unwrap();
+        assert!(data.iter().all(|v| v.is_finite()));
+
+        // The Hull crossover is the difference of the two averages
+        let short = MaType::Hull.compute(&prices, 4);
+        let long = MaType::Hull.compute(&prices, 8);
+        assert_eq!(data[5 * 6 + 5], short[start_idx + 5] - long[start_idx + 5]);
+    }
     
     #[test]
     fn test_compute_targets() {
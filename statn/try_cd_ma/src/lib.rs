@@ -1,13 +1,25 @@
 pub mod config;
 pub mod data;
 pub mod indicators;
+pub mod targets;
 pub mod training;
 pub mod evaluation;
+pub mod walkforward;
 pub mod backtest;
+pub mod strategy;
 
-pub use config::Config;
-pub use data::{load_prices, split_train_test};
-pub use indicators::{generate_specs, compute_indicator_data};
-pub use training::train_with_cv;
-pub use evaluation::{evaluate_model, write_results};
-pub use backtest::{run_backtest, write_backtest_results};
\ No newline at end of file
+pub use config::{Cli, Command, Config};
+pub use data::{load_bars_with_format, load_prices, load_prices_with_format, split_train_test};
+pub use indicators::{generate_specs, compute_indicator_data, MaType};
+pub use targets::{TargetKind, TargetSpec};
+pub use training::{
+    ClassificationMetrics, EnsembleMember, EnsembleResult, classification_metrics, train_ensemble, train_model_cv,
+    train_with_cv, train_with_decay_selection,
+};
+pub use evaluation::{
+    EnsembleEvaluation, MemberReturn, SavedModel, evaluate_ensemble, evaluate_model, load_model, predict_prices, save_model, write_predictions, write_results,
+};
+pub use walkforward::{Refit, WalkforwardResult, run_walkforward};
+pub use backtest::{BacktestSettings, position, run_backtest, turnover, write_backtest_results};
+pub use strategy::CDMAStrategy;
+pub use backtesting::Slippage;
\ No newline at end of file
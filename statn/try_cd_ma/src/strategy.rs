@@ -1,6 +1,7 @@
-use backtesting::Strategy;
 use statn::models::cd_ma::CoordinateDescent;
 
+use crate::backtest::position;
+
 /// Strategy wrapper for Coordinate Descent Moving Average model
 pub struct CDMAStrategy {
     /// Trained model
@@ -11,8 +12,8 @@ pub struct CDMAStrategy {
     /// Number of variables per case
     n_vars: usize,
     /// Offset to map backtest index to data index
-    /// Backtest index starts at 0 for the first test case
-    /// Data index might be offset if we only computed indicators for the test set
+    /// Backtest index starts at 0 for the first price
+    /// Data index is offset when indicators start at max_lookback
     offset: usize,
 }
 
@@ -31,50 +32,46 @@ impl CDMAStrategy {
             offset,
         }
     }
-}
 
-impl Strategy for CDMAStrategy {
-    fn signal(&self, _prices: &[f64], index: usize) -> f64 {
-        // Map backtest index to data index
-        // If backtest starts at index 0 of prices, and we have indicators for all prices,
-        // then index corresponds to data index.
-        // However, we usually only have indicators starting from max_lookback.
-        // The backtester iterates from 0 to n-1.
-        // If we pass the full price history to backtester, index 0 is the first price.
-        // But we can't trade until max_lookback.
-        // So we should return 0.0 until we have data.
-        
+    /// Position at bar `index` of the prices: long if the model's score is
+    /// positive, short if negative, flat before the indicators start
+    pub fn signal(&self, index: usize) -> i32 {
         if index < self.offset {
-            return 0.0;
+            return 0;
         }
-        
+
         let data_idx = index - self.offset;
-        
+
         // Check bounds
         if (data_idx + 1) * self.n_vars > self.data.len() {
-            return 0.0;
+            return 0;
         }
-        
+
         let xptr = &self.data[data_idx * self.n_vars..(data_idx + 1) * self.n_vars];
-        
-        // Compute prediction
-        let pred: f64 = xptr
-            .iter()
-            .enumerate()
-            .map(|(ivar, &x)| {
-                self.model.beta[ivar] * (x - self.model.xmeans[ivar]) / self.model.xscales[ivar]
-            })
-            .sum();
-        
-        let pred = pred * self.model.yscale + self.model.ymean;
-        
-        // Trading logic: long if pred > 0, short if pred < 0
-        if pred > 0.0 {
-            1.0
-        } else if pred < 0.0 {
-            -1.0
-        } else {
-            0.0
-        }
+        position(self.model.score(xptr), 0.0, false)
+    }
+
+    /// Positions at the first `n_bars` bars, for `backtest_signals_with` with
+    /// `target_positions`
+    pub fn signals(&self, n_bars: usize) -> Vec<i32> {
+        (0..n_bars).map(|i| self.signal(i)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals() {
+        let mut model = CoordinateDescent::new(1, 3, false, true, 0);
+        model.beta = vec![1.0];
+        model.xmeans = vec![0.0];
+        model.xscales = vec![1.0];
+        model.yscale = 1.0;
+
+        // Flat before the indicators start and after they end
+        let strategy = CDMAStrategy::new(model, vec![0.5, -0.5, 0.0], 1, 2);
+        assert_eq!(strategy.signals(6), vec![0, 0, 1, -1, 0, 0]);
     }
 }
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// What the model is trained to predict at each case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetKind {
+    /// Log return to the next bar
+    #[default]
+    NextBar,
+    /// Log return over the next `horizon` bars
+    Horizon,
+    /// Log return over the next `horizon` bars in units of the recent
+    /// volatility over that horizon
+    VolScaled,
+    /// +1 if the profit-take barrier is touched first within `horizon` bars,
+    /// -1 if the stop is, 0 at the timeout
+    TripleBarrier,
+}
+
+impl FromStr for TargetKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "next-bar" | "next" => Ok(TargetKind::NextBar),
+            "horizon" => Ok(TargetKind::Horizon),
+            "vol-scaled" => Ok(TargetKind::VolScaled),
+            "triple-barrier" | "barrier" => Ok(TargetKind::TripleBarrier),
+            _ => Err(format!(
+                "Unknown target '{}' (expected next-bar, horizon, vol-scaled or triple-barrier)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TargetKind::NextBar => "next-bar",
+            TargetKind::Horizon => "horizon",
+            TargetKind::VolScaled => "vol-scaled",
+            TargetKind::TripleBarrier => "triple-barrier",
+        })
+    }
+}
+
+/// A training target and its settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetSpec {
+    pub kind: TargetKind,
+    /// Bars ahead the return is measured over, or the triple-barrier timeout
+    pub horizon: usize,
+    /// Bars of one-bar returns whose standard deviation is the volatility
+    pub vol_lookback: usize,
+    /// Profit-take barrier in standard deviations of the horizon return
+    pub profit_take: f64,
+    /// Stop barrier in standard deviations of the horizon return
+    pub stop_loss: f64,
+}
+
+impl Default for TargetSpec {
+    fn default() -> Self {
+        TargetSpec { kind: TargetKind::NextBar, horizon: 1, vol_lookback: 20, profit_take: 2.0, stop_loss: 2.0 }
+    }
+}
+
+impl TargetSpec {
+    /// Bars after a case that its target looks at
+    pub fn bars_ahead(&self) -> usize {
+        match self.kind {
+            TargetKind::NextBar => 1,
+            _ => self.horizon.max(1),
+        }
+    }
+
+    /// Bars before a case that its target looks at
+    pub fn bars_behind(&self) -> usize {
+        match self.kind {
+            TargetKind::VolScaled | TargetKind::TripleBarrier => self.vol_lookback,
+            _ => 0,
+        }
+    }
+
+    /// Target of each case from `start_idx` on. A horizon running past the
+    /// end of `prices` is cut short at the last price.
+    pub fn compute(&self, prices: &[f64], start_idx: usize, n_cases: usize) -> Vec<f64> {
+        (start_idx..start_idx + n_cases)
+            .map(|idx| {
+                let end = (idx + self.bars_ahead()).min(prices.len() - 1);
+                let ret = prices[end] - prices[idx];
+                match self.kind {
+                    TargetKind::NextBar | TargetKind::Horizon => ret,
+                    TargetKind::VolScaled => {
+                        let width = recent_volatility(prices, idx, self.vol_lookback) * ((end - idx) as f64).sqrt();
+                        if width > 0.0 { ret / width } else { 0.0 }
+                    }
+                    TargetKind::TripleBarrier => self.barrier_label(prices, idx, end),
+                }
+            })
+            .collect()
+    }
+
+    fn barrier_label(&self, prices: &[f64], idx: usize, end: usize) -> f64 {
+        let width = recent_volatility(prices, idx, self.vol_lookback) * ((end - idx) as f64).sqrt();
+        if width <= 0.0 {
+            return 0.0;
+        }
+        let upper = prices[idx] + self.profit_take * width;
+        let lower = prices[idx] - self.stop_loss * width;
+        for &price in &prices[idx + 1..=end] {
+            if price >= upper {
+                return 1.0;
+            }
+            if price <= lower {
+                return -1.0;
+            }
+        }
+        0.0
+    }
+}
+
+/// Sample standard deviation of the last `lookback` one-bar changes of
+/// `prices` up to `idx`, fewer near the start; 0 with fewer than 2
+fn recent_volatility(prices: &[f64], idx: usize, lookback: usize) -> f64 {
+    let first = (idx + 1).saturating_sub(lookback).max(1);
+    let changes: Vec<f64> = (first..=idx).map(|j| prices[j] - prices[j - 1]).collect();
+    let n = changes.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean = changes.iter().sum::<f64>() / n as f64;
+    let var = changes.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    var.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use statn::core::io::compute_targets;
+
+    fn zigzag() -> Vec<f64> {
+        // Alternating changes of +0.02 and -0.01, then a jump and a drop
+        let mut prices = vec![0.0];
+        for i in 0..30 {
+            let last = *prices.last().unwrap();
+            prices.push(last + if i % 2 == 0 { 0.02 } else { -0.01 });
+        }
+        let last = *prices.last().unwrap();
+        prices.extend([last + 0.2, last - 0.2, last - 0.3]);
+        prices
+    }
+
+    #[test]
+    fn test_return_targets() {
+        let prices = zigzag();
+        let next = TargetSpec::default().compute(&prices, 10, 5);
+        assert_eq!(next, compute_targets(&prices, 10, 5));
+
+        let spec = TargetSpec { kind: TargetKind::Horizon, horizon: 3, ..TargetSpec::default() };
+        let ahead = spec.compute(&prices, 10, 5);
+        assert!((ahead[0] - (prices[13] - prices[10])).abs() < 1e-15);
+        // Cut short at the end of the prices
+        let last = spec.compute(&prices, prices.len() - 2, 1);
+        assert_eq!(last[0], prices[prices.len() - 1] - prices[prices.len() - 2]);
+        assert_eq!(spec.bars_ahead(), 3);
+
+        // Scaled by the standard deviation of the alternating changes
+        let spec = TargetSpec { kind: TargetKind::VolScaled, horizon: 2, vol_lookback: 10, ..TargetSpec::default() };
+        let scaled = spec.compute(&prices, 20, 1);
+        let sd = recent_volatility(&prices, 20, 10);
+        assert!((sd - (0.015_f64.powi(2) * 10.0 / 9.0).sqrt()).abs() < 1e-12);
+        assert!((scaled[0] - (prices[22] - prices[20]) / (sd * 2.0_f64.sqrt())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_triple_barrier() {
+        let prices = zigzag();
+        let spec = TargetSpec {
+            kind: TargetKind::TripleBarrier,
+            horizon: 4,
+            vol_lookback: 10,
+            profit_take: 1.5,
+            stop_loss: 1.5,
+        };
+        // Within the zigzag neither barrier is touched
+        assert_eq!(spec.compute(&prices, 10, 1), vec![0.0]);
+        // The jump touches the profit-take, the drop after it the stop
+        let n = prices.len();
+        assert_eq!(spec.compute(&prices, n - 4, 1), vec![1.0]);
+        assert_eq!(spec.compute(&prices, n - 3, 1), vec![-1.0]);
+        // A flat market has no volatility to set barriers with
+        assert_eq!(spec.compute(&[1.0; 10], 5, 2), vec![0.0, 0.0]);
+
+        assert_eq!("triple_barrier".parse::<TargetKind>(), Ok(TargetKind::TripleBarrier));
+        assert_eq!(TargetKind::VolScaled.to_string().parse(), Ok(TargetKind::VolScaled));
+        assert!("sharpe".parse::<TargetKind>().is_err());
+    }
+}
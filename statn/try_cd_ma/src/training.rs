@@ -1,5 +1,7 @@
 use anyhow::Result;
-use statn::models::cd_ma::{CoordinateDescent, cv_train};
+use matlib::Mwc256;
+use statn::models::cd_ma::{CoordinateDescent, Family, cv_train_family, time_decay_weights};
+use statn::models::learner::{CdParams, CvSelection, Model, cv_select};
 
 /// Result of model training
 pub struct TrainingResult {
@@ -11,9 +13,65 @@ pub struct TrainingResult {
     pub lambdas: Vec<f64>,
     /// Out-of-sample performance for each lambda
     pub lambda_oos: Vec<f64>,
+    /// Standard error over the folds of each lambda's out-of-sample performance
+    pub lambda_se: Vec<f64>,
+    /// Whether lambda was chosen by the one-standard-error rule
+    pub one_se: bool,
+    /// Half-life in cases of the time-decay weights (0 = unweighted)
+    pub half_life: f64,
+    /// Validation score of each candidate half-life, if one was selected:
+    /// explained variance, or AUC for a classifier
+    pub half_life_scores: Vec<(f64, f64)>,
+    /// In-sample AUC and accuracy of a classifier
+    pub classification: Option<ClassificationMetrics>,
 }
 
-/// Train model with cross-validation to find optimal lambda
+/// How well a classifier separates up moves from the rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassificationMetrics {
+    /// Area under the ROC curve of the predicted probabilities
+    pub auc: f64,
+    /// Fraction of cases whose class is predicted with probability above 0.5
+    pub accuracy: f64,
+}
+
+/// AUC and accuracy of a trained classifier on a set of cases, the class of
+/// a case being whether its target is positive
+pub fn classification_metrics(
+    model: &CoordinateDescent,
+    data: &[f64],
+    targets: &[f64],
+    n_vars: usize,
+) -> ClassificationMetrics {
+    let mut probs: Vec<f64> = targets
+        .iter()
+        .enumerate()
+        .map(|(i, _)| model.predict(&data[i * n_vars..(i + 1) * n_vars]))
+        .collect();
+    let mut labels: Vec<f64> = targets.iter().map(|&y| if y > 0.0 { 1.0 } else { -1.0 }).collect();
+    let correct = probs.iter().zip(&labels).filter(|&(&p, &label)| (p > 0.5) == (label > 0.0)).count();
+    let accuracy = correct as f64 / targets.len().max(1) as f64;
+    let auc = stats::roc_area(&mut probs, &mut labels, false);
+    ClassificationMetrics { auc, accuracy }
+}
+
+/// Train model with cross-validation to find optimal lambda.
+///
+/// With a positive `half_life` the cases (in chronological order) are given
+/// exponential time-decay weights, so recent cases count more in both the
+/// cross-validation and the final fit. The binomial `family` trains a
+/// logistic classifier of whether the target is positive, cross-validated by
+/// deviance explained and reported with its in-sample AUC and accuracy.
+///
+/// With `one_se` the largest lambda whose cross-validated performance is
+/// within one standard error of the best is taken instead of the best, which
+/// guards small samples against overfitting.
+///
+/// The final model is trained down the same lambda path as the
+/// cross-validation, warm-starting each step from the one before, and keeps
+/// the whole path for `write_coefficient_path`. The lambda path makes this
+/// specific to the elastic net; evaluation and backtests take any `Model`,
+/// and `train_model_cv` cross-validates any `Model` over candidate settings.
 #[allow(clippy::too_many_arguments)]
 pub fn train_with_cv(
     n_vars: usize,
@@ -23,27 +81,37 @@ pub fn train_with_cv(
     alpha: f64,
     n_folds: usize,
     n_lambdas: usize,
+    one_se: bool,
     max_iterations: usize,
     tolerance: f64,
+    half_life: f64,
+    family: Family,
 ) -> Result<TrainingResult> {
+    let weights = (half_life > 0.0).then(|| time_decay_weights(n_cases, half_life));
+    let weights = weights.as_deref();
+
     println!("Running {}-fold cross-validation...", n_folds);
     
     let mut lambdas = vec![0.0; n_lambdas];
     let mut lambda_oos = vec![0.0; n_lambdas];
+    let mut lambda_se = vec![0.0; n_lambdas];
     
     let lambda = if alpha <= 0.0 {
         println!("Alpha <= 0, using lambda = 0 (no regularization)");
         0.0
     } else {
-        cv_train(
+        cv_train_family(
+            family,
             n_vars,
             n_folds,
             data,
             targets,
-            None,
+            weights,
             &mut lambdas,
             &mut lambda_oos,
-            true,  // covar_updates
+            &mut lambda_se,
+            one_se,
+            family == Family::Gaussian,  // covar_updates
             n_lambdas,
             alpha,
             max_iterations,
@@ -52,24 +120,257 @@ pub fn train_with_cv(
         )
     };
     
-    println!("Optimal lambda: {:.6}", lambda);
+    if one_se && alpha > 0.0 {
+        println!("Lambda within one standard error of the best: {:.6}", lambda);
+    } else {
+        println!("Optimal lambda: {:.6}", lambda);
+    }
     
     // Train final model with optimal lambda
     println!("Training final model...");
-    let mut model = CoordinateDescent::new(n_vars, n_cases, false, true, 0);
-    model.get_data(0, n_cases, data, targets, None);
-    model.core_train(alpha, lambda, max_iterations, 1e-7, true, false);
+    let path_len = if alpha > 0.0 { n_lambdas } else { 0 };
+    let mut model = match family {
+        Family::Gaussian => CoordinateDescent::new(n_vars, n_cases, weights.is_some(), true, path_len),
+        Family::Binomial => CoordinateDescent::binomial(n_vars, n_cases, weights.is_some(), path_len),
+    };
+    model.get_data(0, n_cases, data, targets, weights);
+    if path_len > 1 {
+        let max_lambda = model.get_lambda_thresh(alpha);
+        model.path_train(alpha, max_iterations, 1e-7, true, max_lambda, lambda);
+    } else {
+        model.core_train(alpha, lambda, max_iterations, 1e-7, true, false);
+    }
     
-    println!("In-sample explained variance: {:.3}%", 100.0 * model.explained);
+    let classification = (family == Family::Binomial).then(|| {
+        let metrics = classification_metrics(&model, data, targets, n_vars);
+        println!("In-sample deviance explained: {:.3}%", 100.0 * model.explained);
+        println!("In-sample AUC: {:.4}, accuracy: {:.3}%", metrics.auc, 100.0 * metrics.accuracy);
+        metrics
+    });
+    if classification.is_none() {
+        println!("In-sample explained variance: {:.3}%", 100.0 * model.explained);
+    }
     
     Ok(TrainingResult {
         model,
         lambda,
         lambdas,
         lambda_oos,
+        lambda_se,
+        one_se,
+        half_life,
+        half_life_scores: Vec::new(),
+        classification,
     })
 }
 
+/// Fit any learner with the candidate settings that earn the most out of
+/// sample in `n_folds`-fold cross-validation (see `cv_select`), on all cases
+pub fn train_model_cv<M: Model>(
+    n_vars: usize,
+    n_cases: usize,
+    data: &[f64],
+    targets: &[f64],
+    n_folds: usize,
+    candidates: &[M::Params],
+) -> Result<(M, CvSelection)> {
+    println!("Running {}-fold cross-validation of {} candidate settings...", n_folds, candidates.len());
+    let cv = cv_select::<M>(n_vars, n_cases, data, targets, None, n_folds, candidates)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    for (i, oos) in cv.oos.iter().enumerate() {
+        println!("Candidate {}: OOS return per case {:.6}{}", i, oos, if i == cv.best { " (best)" } else { "" });
+    }
+    let model = M::fit(n_vars, n_cases, data, targets, None, &candidates[cv.best]).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok((model, cv))
+}
+
+/// Explained variance of a trained model's predictions on a set of cases
+fn explained_variance(model: &CoordinateDescent, data: &[f64], targets: &[f64], n_vars: usize) -> f64 {
+    let mean = targets.iter().sum::<f64>() / targets.len() as f64;
+    let mut total = 1.0e-60;
+    let mut resid = 0.0;
+    for (i, &y) in targets.iter().enumerate() {
+        let pred: f64 = data[i * n_vars..(i + 1) * n_vars]
+            .iter()
+            .enumerate()
+            .map(|(ivar, &x)| model.beta[ivar] * (x - model.xmeans[ivar]) / model.xscales[ivar])
+            .sum::<f64>()
+            * model.yscale
+            + model.ymean;
+        total += (y - mean) * (y - mean);
+        resid += (y - pred) * (y - pred);
+    }
+    1.0 - resid / total
+}
+
+/// Choose the time-decay half-life from a list of candidates, then train.
+///
+/// The most recent `1 / n_folds` of the cases is held out. Each candidate is
+/// trained with `train_with_cv` on the earlier cases and scored by the
+/// unweighted explained variance of its predictions on the held-out cases (the
+/// AUC for a classifier), so all candidates are judged on the same data. The final model is trained on
+/// all cases with the winning half-life. A candidate of 0 means no decay.
+#[allow(clippy::too_many_arguments)]
+pub fn train_with_decay_selection(
+    n_vars: usize,
+    n_cases: usize,
+    data: &[f64],
+    targets: &[f64],
+    alpha: f64,
+    n_folds: usize,
+    n_lambdas: usize,
+    one_se: bool,
+    max_iterations: usize,
+    tolerance: f64,
+    half_lives: &[f64],
+    family: Family,
+) -> Result<TrainingResult> {
+    if half_lives.is_empty() {
+        anyhow::bail!("No candidate half-lives given");
+    }
+    let n_val = n_cases / n_folds.max(2);
+    let n_fit = n_cases - n_val;
+    if n_val < 2 || n_fit < n_vars + 10 {
+        anyhow::bail!("Too few training cases to select a half-life");
+    }
+
+    let mut scores = Vec::with_capacity(half_lives.len());
+    for &half_life in half_lives {
+        println!("\nTrying time-decay half-life {}", half_life);
+        let fit = train_with_cv(
+            n_vars,
+            n_fit,
+            &data[..n_fit * n_vars],
+            &targets[..n_fit],
+            alpha,
+            n_folds,
+            n_lambdas,
+            one_se,
+            max_iterations,
+            tolerance,
+            half_life,
+            family,
+        )?;
+        let val_data = &data[n_fit * n_vars..n_cases * n_vars];
+        let val_targets = &targets[n_fit..n_cases];
+        let score = match family {
+            Family::Gaussian => {
+                let score = explained_variance(&fit.model, val_data, val_targets, n_vars);
+                println!("Validation explained variance: {:.3}%", 100.0 * score);
+                score
+            }
+            Family::Binomial => {
+                let score = classification_metrics(&fit.model, val_data, val_targets, n_vars).auc;
+                println!("Validation AUC: {:.4}", score);
+                score
+            }
+        };
+        scores.push((half_life, score));
+    }
+
+    let (best, _) = scores
+        .iter()
+        .copied()
+        .fold((half_lives[0], f64::NEG_INFINITY), |acc, s| if s.1 > acc.1 { s } else { acc });
+    println!("\nSelected time-decay half-life: {}", best);
+
+    let mut result = train_with_cv(
+        n_vars, n_cases, data, targets, alpha, n_folds, n_lambdas, one_se, max_iterations, tolerance, best, family,
+    )?;
+    result.half_life_scores = scores;
+    Ok(result)
+}
+
+/// Seed of the bootstrap samples of `train_ensemble`, so that runs repeat
+const ENSEMBLE_SEED: u32 = 20240917;
+
+/// One model of an ensemble
+pub struct EnsembleMember {
+    pub alpha: f64,
+    /// Lambda chosen by cross-validation for this alpha
+    pub lambda: f64,
+    /// Bootstrap sample the model was fit to, `None` for all cases
+    pub sample: Option<usize>,
+    pub model: CoordinateDescent,
+}
+
+/// Models trained across elastic-net alphas, predicting by their average
+pub struct EnsembleResult {
+    pub members: Vec<EnsembleMember>,
+}
+
+impl EnsembleResult {
+    /// Mean of the members' predictions for the case `x`: a predicted
+    /// return, or the probability of an up move for classifiers
+    pub fn predict(&self, x: &[f64]) -> f64 {
+        self.members.iter().map(|m| m.model.predict(x)).sum::<f64>() / self.members.len() as f64
+    }
+
+    /// Loss the members were trained with
+    pub fn family(&self) -> Family {
+        self.members[0].model.family
+    }
+}
+
+/// Train one model per elastic-net alpha, each with its own cross-validated
+/// lambda as in `train_with_cv`, and average their predictions.
+///
+/// With `n_bootstrap` > 0 each alpha instead contributes `n_bootstrap` models
+/// at its lambda, each fit to a bootstrap sample of the cases. A sample is
+/// drawn with replacement and given to the fit as case weights (the number of
+/// draws times any time-decay weight), so the cases keep their order.
+#[allow(clippy::too_many_arguments)]
+pub fn train_ensemble(
+    n_vars: usize,
+    n_cases: usize,
+    data: &[f64],
+    targets: &[f64],
+    alphas: &[f64],
+    n_bootstrap: usize,
+    n_folds: usize,
+    n_lambdas: usize,
+    one_se: bool,
+    max_iterations: usize,
+    tolerance: f64,
+    half_life: f64,
+    family: Family,
+) -> Result<EnsembleResult> {
+    if alphas.is_empty() {
+        anyhow::bail!("No ensemble alphas given");
+    }
+    let decay = time_decay_weights(n_cases, half_life);
+    let mut rng = Mwc256::with_seed(ENSEMBLE_SEED);
+    let mut members = Vec::with_capacity(alphas.len() * n_bootstrap.max(1));
+
+    for &alpha in alphas {
+        println!("\nEnsemble alpha {}", alpha);
+        let fit = train_with_cv(
+            n_vars, n_cases, data, targets, alpha, n_folds, n_lambdas, one_se, max_iterations, tolerance, half_life,
+            family,
+        )?;
+        if n_bootstrap == 0 {
+            members.push(EnsembleMember { alpha, lambda: fit.lambda, sample: None, model: fit.model });
+            continue;
+        }
+
+        println!("Fitting {} bootstrap samples at lambda {:.6}", n_bootstrap, fit.lambda);
+        for sample in 0..n_bootstrap {
+            let mut weights = vec![0.0; n_cases];
+            for _ in 0..n_cases {
+                let k = (rng.rand32() as usize) % n_cases;
+                weights[k] += decay[k];
+            }
+            let params = CdParams { family, alpha, lambda: fit.lambda, max_iterations, tolerance: 1e-7 };
+            let model = CoordinateDescent::fit(n_vars, n_cases, data, targets, Some(&weights), &params)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            members.push(EnsembleMember { alpha, lambda: fit.lambda, sample: Some(sample), model });
+        }
+    }
+
+    println!("\nEnsemble of {} models", members.len());
+    Ok(EnsembleResult { members })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,12 +390,174 @@ mod tests {
             0.0,  // Zero alpha
             5,
             10,
+            false,
             100,
             1e-6,
+            0.0,
+            Family::Gaussian,
         );
         
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.lambda, 0.0);
+        assert!(result.classification.is_none());
+        assert!(result.model.path_lambdas().is_empty());
+    }
+
+    #[test]
+    fn test_final_fit_on_cv_path() {
+        let n_vars = 2;
+        let n_cases = 200;
+        let mut data = Vec::with_capacity(n_vars * n_cases);
+        let mut targets = Vec::with_capacity(n_cases);
+        for i in 0..n_cases {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            let x1 = ((i * 53 % 97) as f64) / 48.0 - 1.0;
+            data.extend([x0, x1]);
+            targets.push(x0 + 0.05 * (((i * 13) % 7) as f64 - 3.0));
+        }
+
+        let result = train_with_cv(
+            n_vars, n_cases, &data, &targets, 0.5, 5, 10, false, 1000, 1e-9, 0.0, Family::Gaussian,
+        )
+        .unwrap();
+        // The chosen lambda is a step of the final model's path, and the
+        // model carries that step's coefficients
+        let path = result.model.path_lambdas();
+        assert_eq!(path, &result.lambdas[..]);
+        let ilambda = path.iter().position(|&l| l == result.lambda).unwrap();
+        assert_eq!(result.model.beta, result.model.path_beta(ilambda));
+        assert_eq!(result.model.entry_order()[0].0, 0);
+        assert_eq!(result.lambda_se.len(), 10);
+
+        // The one-standard-error rule never picks a smaller lambda
+        let sparse = train_with_cv(
+            n_vars, n_cases, &data, &targets, 0.5, 5, 10, true, 1000, 1e-9, 0.0, Family::Gaussian,
+        )
+        .unwrap();
+        assert!(sparse.one_se);
+        assert!(sparse.lambda >= result.lambda);
+        let ibest = path.iter().position(|&l| l == result.lambda).unwrap();
+        let isparse = path.iter().position(|&l| l == sparse.lambda).unwrap();
+        assert!(sparse.lambda_oos[isparse] >= result.lambda_oos[ibest] - result.lambda_se[ibest]);
+    }
+
+    #[test]
+    fn test_train_classifier() {
+        // Up moves follow the first predictor, apart from every fifth case;
+        // the second predictor is noise
+        let n_vars = 2;
+        let n_cases = 300;
+        let mut data = Vec::with_capacity(n_vars * n_cases);
+        let mut targets = Vec::with_capacity(n_cases);
+        for i in 0..n_cases {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            data.extend([x0, ((i * 53 % 97) as f64) / 48.0 - 1.0]);
+            targets.push(if (x0 > 0.0) != (i % 5 == 0) { 0.01 } else { -0.01 });
+        }
+
+        let result = train_with_cv(
+            n_vars, n_cases, &data, &targets, 0.5, 5, 10, false, 1000, 1e-9, 0.0, Family::Binomial,
+        )
+        .unwrap();
+        assert_eq!(result.model.family, Family::Binomial);
+        assert!(result.model.beta[0] > 0.0);
+        let metrics = result.classification.unwrap();
+        assert!(metrics.auc > 0.75 && metrics.auc <= 1.0, "{:?}", metrics);
+        assert!(metrics.accuracy > 0.75 && metrics.accuracy <= 0.85, "{:?}", metrics);
+    }
+
+    #[test]
+    fn test_train_model_cv() {
+        // The classifier data of `test_train_classifier`: taking the side of
+        // a fit that uses x0 earns about 0.6 of a move per case
+        let n_vars = 2;
+        let n_cases = 300;
+        let mut data = Vec::with_capacity(n_vars * n_cases);
+        let mut targets = Vec::with_capacity(n_cases);
+        for i in 0..n_cases {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            data.extend([x0, ((i * 53 % 97) as f64) / 48.0 - 1.0]);
+            targets.push(if (x0 > 0.0) != (i % 5 == 0) { 0.01 } else { -0.01 });
+        }
+
+        let candidates: Vec<CdParams> = [10.0, 0.01]
+            .iter()
+            .map(|&lambda| CdParams { family: Family::Binomial, alpha: 1.0, lambda, ..CdParams::default() })
+            .collect();
+        let (model, cv) =
+            train_model_cv::<CoordinateDescent>(n_vars, n_cases, &data, &targets, 5, &candidates).unwrap();
+        assert_eq!(cv.best, 1);
+        assert!(cv.oos[1] > 0.005, "{:?}", cv);
+        assert!(cv.oos[0] < cv.oos[1]);
+        assert_eq!(model.family, Family::Binomial);
+        assert!(model.beta[0] > 0.0);
+    }
+
+    #[test]
+    fn test_decay_selection_prefers_recent_regime() {
+        // The sign of the relationship flips two thirds of the way through,
+        // so only a short half-life tracks the regime the holdout comes from
+        let n_vars = 1;
+        let n_cases = 300;
+        let data: Vec<f64> = (0..n_cases).map(|i| ((i * 37 % 101) as f64) / 50.0 - 1.0).collect();
+        let targets: Vec<f64> = (0..n_cases)
+            .map(|i| if i < 200 { data[i] } else { -data[i] })
+            .collect();
+
+        let result = train_with_decay_selection(
+            n_vars, n_cases, &data, &targets, 0.5, 5, 10, false, 1000, 1e-9, &[0.0, 20.0], Family::Gaussian,
+        )
+        .unwrap();
+        assert_eq!(result.half_life, 20.0);
+        assert_eq!(result.half_life_scores.len(), 2);
+        assert!(result.model.beta[0] < 0.0);
+    }
+
+    #[test]
+    fn test_train_ensemble() {
+        let n_vars = 2;
+        let n_cases = 200;
+        let mut data = Vec::with_capacity(n_vars * n_cases);
+        let mut targets = Vec::with_capacity(n_cases);
+        for i in 0..n_cases {
+            let x0 = ((i * 37 % 101) as f64) / 50.0 - 1.0;
+            let x1 = ((i * 53 % 97) as f64) / 48.0 - 1.0;
+            data.extend([x0, x1]);
+            targets.push(x0 + 0.05 * (((i * 13) % 7) as f64 - 3.0));
+        }
+        let alphas = [0.2, 0.9];
+
+        // One model per alpha, each the model train_with_cv gives
+        let ensemble = train_ensemble(
+            n_vars, n_cases, &data, &targets, &alphas, 0, 5, 10, false, 1000, 1e-9, 0.0, Family::Gaussian,
+        )
+        .unwrap();
+        assert_eq!(ensemble.members.len(), 2);
+        let single = train_with_cv(
+            n_vars, n_cases, &data, &targets, 0.9, 5, 10, false, 1000, 1e-9, 0.0, Family::Gaussian,
+        )
+        .unwrap();
+        assert_eq!(ensemble.members[1].model.beta, single.model.beta);
+        let x = [0.5, -0.2];
+        let mean = (ensemble.members[0].model.predict(&x) + single.model.predict(&x)) / 2.0;
+        assert!((ensemble.predict(&x) - mean).abs() < 1e-12);
+
+        // Bootstrapped members differ from each other but all find x0
+        let bagged = train_ensemble(
+            n_vars, n_cases, &data, &targets, &alphas, 3, 5, 10, false, 1000, 1e-9, 0.0, Family::Gaussian,
+        )
+        .unwrap();
+        assert_eq!(bagged.members.len(), 6);
+        assert_eq!(bagged.members[3].sample, Some(0));
+        assert_eq!(bagged.members[3].lambda, single.lambda);
+        assert_ne!(bagged.members[0].model.beta, bagged.members[1].model.beta);
+        assert!(bagged.members.iter().all(|m| m.model.beta[0] > m.model.beta[1].abs()));
+        assert!(bagged.predict(&[1.0, 0.0]) > bagged.predict(&[-1.0, 0.0]));
+
+        assert!(train_ensemble(
+            n_vars, n_cases, &data, &targets, &[], 0, 5, 10, false, 1000, 1e-9, 0.0, Family::Gaussian,
+        )
+        .is_err());
     }
-}
\ No newline at end of file
+}
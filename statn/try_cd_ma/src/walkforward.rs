@@ -0,0 +1,235 @@
+//! Walkforward retraining over the test period.
+//!
+//! A single fit on the training period ages through the test period: a
+//! trader would refit as new bars arrive. Here the test cases are split into
+//! blocks of `step` cases. Each block is predicted by a model trained, with
+//! the usual cross-validation, on the cases whose targets are fully known at
+//! the block's first bar: all earlier history, or only the most recent
+//! `window` cases. The predictions of the blocks are concatenated into one
+//! out-of-sample record. The first block is trained on exactly the cases of
+//! the fixed train/test split.
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::evaluation::signal;
+use crate::indicators::{IndicatorSpec, compute_all_indicators};
+use crate::training::{train_with_cv, train_with_decay_selection};
+use statn::core::io::compute_targets;
+
+/// One refit of the walkforward
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refit {
+    /// Index among the test cases of the first case this model predicts
+    pub first_test_case: usize,
+    /// Number of test cases this model predicts
+    pub n_test_cases: usize,
+    /// Number of cases the model was trained on
+    pub n_train: usize,
+    /// Lambda chosen by the cross-validation
+    pub lambda: f64,
+    /// Nonzero coefficients of the model
+    pub n_active: usize,
+}
+
+/// Out-of-sample record of the walkforward
+#[derive(Debug, Clone)]
+pub struct WalkforwardResult {
+    /// Prediction for each test case, from the model in force at that case
+    pub predictions: Vec<f64>,
+    /// Next-bar log return of each test case
+    pub returns: Vec<f64>,
+    pub refits: Vec<Refit>,
+    /// Out-of-sample total return (log) of trading the predictions
+    pub oos_return: f64,
+    /// Out-of-sample return percentage
+    pub oos_return_pct: f64,
+}
+
+/// Walk the last `config.n_test` cases of the log `prices` forward, retraining
+/// every `config.walkforward_step` cases on up to `config.train_window` of
+/// the preceding ones (all of them if 0)
+pub fn run_walkforward(config: &Config, prices: &[f64], specs: &[IndicatorSpec]) -> Result<WalkforwardResult> {
+    let step = config.walkforward_step;
+    if step == 0 {
+        anyhow::bail!("The walkforward step must be greater than 0");
+    }
+    let max_lookback = config.max_lookback();
+    if prices.len() < max_lookback + config.n_test + 1 {
+        anyhow::bail!(
+            "Insufficient data: need at least {} prices, got {}",
+            max_lookback + config.n_test + 1,
+            prices.len()
+        );
+    }
+
+    // Every case with a next-bar return, from the first with full history;
+    // the test cases are the last n_test of them
+    let target = config.target_spec();
+    let n_vars = specs.len();
+    let n_cases = prices.len() - 1 - max_lookback;
+    let first_test = n_cases - config.n_test;
+    let data = compute_all_indicators(prices, max_lookback, n_cases, specs)?;
+    let targets = target.compute(prices, max_lookback, n_cases);
+    let returns = compute_targets(prices, max_lookback, n_cases);
+
+    let mut predictions = Vec::with_capacity(config.n_test);
+    let mut refits = Vec::new();
+    let mut start = first_test;
+    while start < n_cases {
+        let end = (start + step).min(n_cases);
+
+        // Cases whose targets are known by the bar of the first case predicted
+        let train_end = (start + 1).saturating_sub(target.bars_ahead());
+        let train_start = if config.train_window > 0 { train_end.saturating_sub(config.train_window) } else { 0 };
+        let n_train = train_end - train_start;
+        if n_train < n_vars + 10 {
+            anyhow::bail!(
+                "Insufficient walkforward training data: need at least {} cases, got {}",
+                n_vars + 10,
+                n_train
+            );
+        }
+
+        println!(
+            "\nWalkforward refit {}: training on {} cases for test cases {}-{}",
+            refits.len() + 1,
+            n_train,
+            start - first_test,
+            end - first_test - 1
+        );
+        let train_data = &data[train_start * n_vars..train_end * n_vars];
+        let train_targets = &targets[train_start..train_end];
+        let training = if config.half_life_grid.is_empty() {
+            train_with_cv(
+                n_vars,
+                n_train,
+                train_data,
+                train_targets,
+                config.alpha,
+                config.n_folds,
+                config.n_lambdas,
+                config.one_se,
+                config.max_iterations,
+                config.tolerance,
+                config.half_life,
+                config.family(),
+            )?
+        } else {
+            train_with_decay_selection(
+                n_vars,
+                n_train,
+                train_data,
+                train_targets,
+                config.alpha,
+                config.n_folds,
+                config.n_lambdas,
+                config.one_se,
+                config.max_iterations,
+                config.tolerance,
+                &config.half_life_grid,
+                config.family(),
+            )?
+        };
+
+        for icase in start..end {
+            predictions.push(training.model.predict(&data[icase * n_vars..(icase + 1) * n_vars]));
+        }
+        refits.push(Refit {
+            first_test_case: start - first_test,
+            n_test_cases: end - start,
+            n_train,
+            lambda: training.lambda,
+            n_active: training.model.beta.iter().filter(|&&b| b != 0.0).count(),
+        });
+        start = end;
+    }
+
+    let returns = returns[first_test..].to_vec();
+    let family = config.family();
+    let oos_return: f64 = predictions
+        .iter()
+        .zip(&returns)
+        .map(|(&pred, &ret)| signal(pred, family) as f64 * ret)
+        .sum();
+    let oos_return_pct = 100.0 * (oos_return.exp() - 1.0);
+    println!(
+        "\nWalkforward OOS total return over {} refits: {:.5} ({:.3}%)",
+        refits.len(),
+        oos_return,
+        oos_return_pct
+    );
+
+    Ok(WalkforwardResult { predictions, returns, refits, oos_return, oos_return_pct })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::evaluate_model;
+    use crate::indicators::{compute_indicator_data, generate_specs};
+    use clap::Parser;
+    use statn::core::io::split_train_test;
+
+    fn prices() -> Vec<f64> {
+        (0..400)
+            .map(|i| 4.0 + 0.02 * (i as f64 * 0.21).sin() + 0.01 * (i as f64 * 0.05).cos() + 0.0001 * i as f64)
+            .collect()
+    }
+
+    fn config(step: usize, window: usize) -> Config {
+        let mut config = Config::parse_from([
+            "try_cd_ma", "--n-long", "3", "--n-short", "2", "--n-test", "50", "--n-folds", "5", "--n-lambdas", "10",
+            "prices.txt",
+        ]);
+        config.walkforward_step = step;
+        config.train_window = window;
+        config
+    }
+
+    #[test]
+    fn test_single_block_matches_fixed_split() {
+        // A step as long as the test period refits once, on the cases of the
+        // fixed split, so it reproduces the fixed evaluation
+        let prices = prices();
+        let config = config(50, 0);
+        let specs = generate_specs(config.lookback_inc, config.n_long, config.n_short, &config.ma_types);
+        let walk = run_walkforward(&config, &prices, &specs).unwrap();
+        assert_eq!(walk.refits.len(), 1);
+        assert_eq!(walk.predictions.len(), 50);
+
+        let split = split_train_test(&prices, config.max_lookback(), config.n_test).unwrap();
+        let target = config.target_spec();
+        let n_train = split.train_data.len() - split.max_lookback - target.bars_ahead();
+        assert_eq!(walk.refits[0].n_train, n_train);
+        let train = compute_indicator_data(&split.train_data, split.max_lookback, n_train, &specs, &target).unwrap();
+        let training = train_with_cv(
+            specs.len(), n_train, &train.data, &train.targets, config.alpha, config.n_folds, config.n_lambdas,
+            false, config.max_iterations, config.tolerance, 0.0, config.family(),
+        )
+        .unwrap();
+        let test = compute_indicator_data(&split.test_data, split.max_lookback, config.n_test, &specs, &target).unwrap();
+        assert_eq!(walk.returns, test.returns);
+        let fixed = evaluate_model(&training.model, &test.data, &test.returns, specs.len()).unwrap();
+        assert!((walk.oos_return - fixed.oos_return).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_periodic_refits() {
+        let prices = prices();
+        let config = config(20, 120);
+        let specs = generate_specs(config.lookback_inc, config.n_long, config.n_short, &config.ma_types);
+        let walk = run_walkforward(&config, &prices, &specs).unwrap();
+
+        // Blocks of 20, 20 and 10 cases, each trained on the window
+        let blocks: Vec<(usize, usize, usize)> =
+            walk.refits.iter().map(|r| (r.first_test_case, r.n_test_cases, r.n_train)).collect();
+        assert_eq!(blocks, [(0, 20, 120), (20, 20, 120), (40, 10, 120)]);
+        assert_eq!(walk.predictions.len(), 50);
+        assert_eq!(walk.returns.len(), 50);
+
+        let mut too_short = config;
+        too_short.train_window = 5;
+        assert!(run_walkforward(&too_short, &prices, &specs).is_err());
+    }
+}
@@ -1,5 +1,12 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::str::FromStr;
+use try_diff_ev::{
+    AccountingBasis, Allocation, Column, FillModel, FixtureKind, Slippage, StopLevel, SystemSpec, DEFAULT_ANCHOR, DEFAULT_FIXTURE_BARS, DEFAULT_FIXTURE_SEED,
+};
+use statn::core::io::SessionFilter;
+use statn::core::matlib::designs::Design;
+use statn::models::pso::PsoVariant;
 
 /// Trading system using moving average crossover with differential evolution
 #[derive(Parser, Debug)]
@@ -17,6 +24,15 @@ pub enum Commands {
         /// Path to market data file
         #[arg(short, long)]
         data_file: PathBuf,
+
+        /// Field delimiter of the market data file (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data file, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
         
         /// Maximum lookback period
         #[arg(short = 'l', long, default_value_t = 6)]
@@ -37,12 +53,112 @@ pub enum Commands {
         /// Minimum trades required
         #[arg(short = 'm', long, default_value_t = 1)]
         min_trades: i32,
+
+        /// Weight of a smooth quadratic penalty for missing the minimum trades
+        /// (0 = reject any parameter set below the minimum)
+        #[arg(long, default_value_t = 0.0)]
+        trade_penalty: f64,
+
+        /// Penalty per unit of turnover (total absolute position change),
+        /// in units of total log return
+        #[arg(long, default_value_t = 0.0)]
+        turnover_penalty: f64,
+
+        /// Bars a position is held at least before it may be closed or reversed
+        #[arg(long, default_value_t = 0)]
+        min_hold: usize,
+
+        /// Bars the system stays flat after closing a position before opening another
+        #[arg(long, default_value_t = 0)]
+        cooldown: usize,
+
+        /// Optimize the minimum hold and the cooldown as two more integer
+        /// parameters, from --min-hold and --cooldown up to --max-lookback bars
+        #[arg(long)]
+        optimize_pacing: bool,
+
+        /// Trading windows of intraday data as HHMM-HHMM, comma separated
+        /// (e.g. "0945-1545"); positions change only inside them
+        #[arg(long)]
+        session: Option<SessionFilter>,
+
+        /// Do not trade on Saturdays and Sundays
+        #[arg(long)]
+        skip_weekends: bool,
+
+        /// Optimizer: "de" (differential evolution), "cmaes", "pso", or the
+        /// "grid" and "random" search baselines. Checkpoints, --resume and
+        /// the convergence options apply to "de" only
+        #[arg(long, default_value = "de")]
+        optimizer: Optimizer,
+
+        /// Velocity update of --optimizer pso: "constriction" or "inertia"
+        #[arg(long, default_value = "constriction")]
+        pso_variant: PsoVariant,
+
+        /// Criterion evaluations of --optimizer grid or random
+        #[arg(long, default_value_t = 20000)]
+        search_evals: usize,
+
+        /// Initial population design: "uniform", "lhs" (Latin hypercube) or "sobol"
+        #[arg(long, default_value = "lhs")]
+        init: Design,
+
+        /// Population diversity (0-1) below which the worst part of the
+        /// population is re-randomized (0 = never)
+        #[arg(long, default_value_t = 0.02)]
+        min_diversity: f64,
+
+        /// Fraction of the population re-randomized when diversity collapses
+        #[arg(long, default_value_t = 0.2)]
+        restart_frac: f64,
+
+        /// Maximum number of re-runs with adjusted minimum trades and bounds
+        /// when the optimum is degenerate (0 = only report it)
+        #[arg(long, default_value_t = 2)]
+        max_reruns: usize,
+
+        /// Seed of the optimizer's random numbers; the same seed replays a
+        /// run exactly (default: a new seed, printed)
+        #[arg(long)]
+        seed: Option<u32>,
+
+        /// Save the optimizer state every N generations (0 = never), so a
+        /// long run can be resumed with --resume
+        #[arg(long, default_value_t = 0)]
+        checkpoint_every: usize,
+
+        /// Checkpoint file, in the output directory
+        #[arg(long, default_value = "de_checkpoint.json")]
+        checkpoint: PathBuf,
+
+        /// Continue the optimization from the checkpoint file
+        #[arg(long)]
+        resume: bool,
+
+        /// Stop when the best criterion improves by less than this fraction
+        /// over --improvement-window generations
+        #[arg(long, default_value_t = 0.0)]
+        min_improvement: f64,
+
+        /// Generations over which --min-improvement is measured (0 = off)
+        #[arg(long, default_value_t = 0)]
+        improvement_window: usize,
+
+        /// Stop when the standard deviation of the criterion over the
+        /// population falls below this (0 = off)
+        #[arg(long, default_value_t = 0.0)]
+        min_criterion_std: f64,
+
+        /// Stop after this many minutes, saving a checkpoint to resume from
+        #[arg(long)]
+        max_minutes: Option<f64>,
         
         /// Training data percentage (0.0 - 1.0)
         #[arg(long, default_value_t = 0.7)]
         train_pct: f64,
         
-        /// Output file for optimized parameters
+        /// Output file for the sensitivity curves (a .csv and .json of the same name are written beside it)
         #[arg(short, long, default_value = "sensitivity_log.log")]
         sensitivity_log: PathBuf,
 
@@ -50,7 +166,7 @@ pub enum Commands {
         #[arg(short = 'P', long, default_value = "params.txt")]
         params_file: PathBuf,
         
-        /// Signal generator type ("original" or "log_diff")
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
         #[arg(long, default_value = "original")]
         generator: String,
         
@@ -63,11 +179,86 @@ pub enum Commands {
         verbose: bool,
     },
     
+    /// Optimize mean return and drawdown together, writing the Pareto set
+    /// of parameters instead of a single best
+    Pareto {
+        /// Path to market data file
+        #[arg(short, long)]
+        data_file: PathBuf,
+
+        /// Field delimiter of the market data file (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data file, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
+
+        /// Maximum lookback period
+        #[arg(short = 'l', long, default_value_t = 6)]
+        max_lookback: usize,
+
+        /// Maximum threshold (×10000)
+        #[arg(short = 't', long, default_value_t = 57.8112)]
+        max_thresh: f64,
+
+        /// Population size
+        #[arg(short, long, default_value_t = 100)]
+        popsize: usize,
+
+        /// Generations
+        #[arg(short = 'g', long, default_value_t = 200)]
+        max_gens: usize,
+
+        /// Minimum trades required
+        #[arg(short = 'm', long, default_value_t = 1)]
+        min_trades: i32,
+
+        /// Training data percentage (0.0 - 1.0)
+        #[arg(long, default_value_t = 0.7)]
+        train_pct: f64,
+
+        /// Seed of the optimizer's random numbers (default: a new seed, printed)
+        #[arg(long)]
+        seed: Option<u32>,
+
+        /// Output file for the Pareto set, in the output directory
+        #[arg(long, default_value = "pareto_front.csv")]
+        front_file: PathBuf,
+
+        /// Periods of the mean return and drawdown: "trade", "bar" (marked to
+        /// market every bar) or "daily" (marked at the last bar of each day)
+        #[arg(long, default_value = "bar")]
+        accounting: AccountingBasis,
+
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
+        #[arg(long, default_value = "original")]
+        generator: String,
+
+        /// Output directory
+        #[arg(short = 'D', long, default_value = "results/")]
+        output_dir: PathBuf,
+
+        /// Enable verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
     /// Generate signals and backtest using optimized parameters
     Predict {
         /// Path to market data file
         #[arg(short, long)]
         data_file: PathBuf,
+
+        /// Field delimiter of the market data file (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data file, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
         
         /// File containing optimized parameters
         #[arg(short, long, default_value = "params.txt")]
@@ -80,6 +271,64 @@ pub enum Commands {
         /// Transaction cost percentage
         #[arg(short = 'c', long, default_value_t = 0.1)]
         transaction_cost: f64,
+
+        /// Maker/taker fee schedule replacing --transaction-cost: a preset
+        /// (e.g. "bybit-spot", "bybit-perp-vip1") or a name in --fee-schedules
+        #[arg(long)]
+        fees: Option<String>,
+
+        /// TOML file of custom fee schedules, [[schedule]] tables with name,
+        /// maker_pct and taker_pct
+        #[arg(long)]
+        fee_schedules: Option<PathBuf>,
+
+        /// Slippage at entries and exits: "none", "ticks:<ticks>:<tick_size>",
+        /// "pct:<pct>" or "volume:<base_pct>:<impact_pct>" (needs a Volume column)
+        #[arg(long, default_value = "none")]
+        slippage: Slippage,
+
+        /// Trading windows of intraday data as HHMM-HHMM, comma separated
+        /// (e.g. "0945-1545"); signals outside them are ignored and orders
+        /// fill only inside them
+        #[arg(long)]
+        session: Option<SessionFilter>,
+
+        /// Do not trade on Saturdays and Sundays
+        #[arg(long)]
+        skip_weekends: bool,
+
+        /// Stop-loss of every position: "pct:<pct>" of the entry price or
+        /// "atr:<period>:<multiple>" of the average true range
+        #[arg(long)]
+        stop_loss: Option<StopLevel>,
+
+        /// Take-profit of every position, as for --stop-loss
+        #[arg(long)]
+        take_profit: Option<StopLevel>,
+
+        /// Trailing stop of every position, behind the best price since the
+        /// entry: "pct:<pct>" of that price or "atr:<period>:<multiple>" of
+        /// the average true range at the entry
+        #[arg(long)]
+        trailing_stop: Option<StopLevel>,
+
+        /// How orders fill: "close" (the signal bar's close), "open" (the
+        /// next bar's open), "mid" (halfway through the next bar's range),
+        /// "limit:<pct>" or "stop:<pct>" (an order that far from the signal
+        /// bar's close, filled within the next bar's range or lapsing)
+        #[arg(long, default_value = "close")]
+        fill: FillModel,
+
+        /// Periods of the Sharpe ratio: "trade" (each trade's return),
+        /// "bar" (marked to market every bar) or "daily" (marked at the last
+        /// bar of each day, read from the dates)
+        #[arg(long, default_value = "trade")]
+        accounting: AccountingBasis,
+
+        /// Numbers of best and of worst trades the outlier analysis leaves
+        /// out of the return
+        #[arg(long, value_delimiter = ',', default_value = "1,3,5")]
+        outlier_trades: Vec<usize>,
         
         /// Training data percentage (0.0 - 1.0)
         #[arg(long, default_value_t = 0.7)]
@@ -89,11 +338,282 @@ pub enum Commands {
         #[arg(short = 'D', long, default_value = "results/")]
         output_dir: PathBuf,
         
-        /// Signal generator type ("original" or "log_diff")
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
         #[arg(long, default_value = "log_diff")]
         generator: String,
+
+        /// Repeat the signal generation and backtest on other threads and with
+        /// a shifted heap, and fail unless every run is bit-identical
+        #[arg(long)]
+        audit: bool,
+
+        /// Also write the backtest as normalized, date-shifted returns and
+        /// trades, without prices or provenance, for sharing
+        #[arg(long)]
+        anonymize: bool,
+
+        /// First date (YYYYMMDD) the anonymized dates are shifted to
+        #[arg(long, default_value = DEFAULT_ANCHOR)]
+        anchor_date: String,
         
         #[arg(short, long)]
         verbose: bool,
     },
+
+    /// Perturb each saved parameter up and down and re-backtest on the most
+    /// recent bars, reporting the worst-case degradation
+    StressParams {
+        /// Path to market data file
+        #[arg(short, long)]
+        data_file: PathBuf,
+
+        /// Field delimiter of the market data file (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data file, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
+
+        /// File containing optimized parameters
+        #[arg(short, long, default_value = "params.txt")]
+        params_file: PathBuf,
+
+        /// Number of most recent bars to backtest on
+        #[arg(short, long, default_value_t = 252)]
+        window: usize,
+
+        /// Perturbation sizes in percent, each applied up and down
+        #[arg(long, value_delimiter = ',', default_value = "5,10,20")]
+        steps: Vec<f64>,
+
+        /// Exit with status 2 if any perturbation lowers the ROI by more than
+        /// this many percentage points
+        #[arg(long)]
+        max_degradation: Option<f64>,
+
+        /// Initial budget for backtesting
+        #[arg(short, long, default_value_t = 10000.0)]
+        budget: f64,
+
+        /// Transaction cost percentage
+        #[arg(short = 'c', long, default_value_t = 0.1)]
+        transaction_cost: f64,
+
+        /// Slippage at entries and exits (see predict)
+        #[arg(long, default_value = "none")]
+        slippage: Slippage,
+
+        /// Output directory
+        #[arg(short = 'D', long, default_value = "results/")]
+        output_dir: PathBuf,
+
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
+        #[arg(long, default_value = "log_diff")]
+        generator: String,
+    },
+
+    /// Replay the saved parameters through labeled historical stress
+    /// windows and report the return, drawdown and exposure in each
+    Scenarios {
+        /// Path to market data file, with dates
+        #[arg(short, long)]
+        data_file: PathBuf,
+
+        /// Field delimiter of the market data file (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data file, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
+
+        /// File containing optimized parameters
+        #[arg(short, long, default_value = "params.txt")]
+        params_file: PathBuf,
+
+        /// TOML file of [[scenario]] tables with name, start and end dates
+        #[arg(short, long)]
+        scenarios: PathBuf,
+
+        /// Initial budget for backtesting
+        #[arg(short, long, default_value_t = 10000.0)]
+        budget: f64,
+
+        /// Transaction cost percentage
+        #[arg(short = 'c', long, default_value_t = 0.1)]
+        transaction_cost: f64,
+
+        /// Slippage at entries and exits (see predict)
+        #[arg(long, default_value = "none")]
+        slippage: Slippage,
+
+        /// Markdown model report the results are appended to, e.g.
+        /// model_report/REPORT.md (created if missing)
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// Output directory
+        #[arg(short = 'D', long, default_value = "results/")]
+        output_dir: PathBuf,
+
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
+        #[arg(long, default_value = "log_diff")]
+        generator: String,
+    },
+
+    /// Compare a journal of real fills with the signals the parameters
+    /// generate: missed and unexpected trades, timing lag and slippage
+    Reconcile {
+        /// Path to market data file, with a date in the first column
+        #[arg(short, long)]
+        data_file: PathBuf,
+
+        /// Field delimiter of the market data file (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data file, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
+
+        /// File containing optimized parameters
+        #[arg(short, long, default_value = "params.txt")]
+        params_file: PathBuf,
+
+        /// CSV journal of executed fills (timestamp,side,qty,price,fee)
+        #[arg(short, long)]
+        journal: PathBuf,
+
+        /// Largest timing difference, in bars, between a signal and its execution
+        #[arg(long, default_value_t = 1)]
+        max_lag: usize,
+
+        /// Output directory
+        #[arg(short = 'D', long, default_value = "results/")]
+        output_dir: PathBuf,
+
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
+        #[arg(long, default_value = "log_diff")]
+        generator: String,
+    },
+
+    /// Allocate capital across several optimized systems from the
+    /// correlation of their out-of-sample returns, and backtest the book
+    Portfolio {
+        /// A system as PARAMS_FILE=DATA_FILE (repeat for each system); the
+        /// paths are used as given
+        #[arg(short, long = "system", required = true)]
+        systems: Vec<SystemSpec>,
+
+        /// Field delimiter of the market data files (detected if not given)
+        #[arg(long)]
+        delimiter: Option<char>,
+
+        /// Price column of the market data files, by header name or 0-based
+        /// index (default: Adj Close/Close/Price from the header, else the last column)
+        #[arg(long)]
+        price_column: Option<Column>,
+
+        /// Allocation: "equal", "equal-risk" (risk parity) or "mean-variance"
+        #[arg(short, long, default_value = "equal-risk")]
+        allocation: Allocation,
+
+        /// Risk aversion of --allocation mean-variance
+        #[arg(long)]
+        risk_aversion: Option<f64>,
+
+        /// Training data percentage of each system (0.0 - 1.0); only the
+        /// returns after it are used
+        #[arg(long, default_value_t = 0.7)]
+        train_pct: f64,
+
+        /// Fraction of the common out-of-sample bars on which the weights
+        /// are estimated; the book is backtested on the rest
+        #[arg(long, default_value_t = 0.5)]
+        estimate_pct: f64,
+
+        /// Output directory
+        #[arg(short = 'D', long, default_value = "results/")]
+        output_dir: PathBuf,
+
+        /// Signal generator type ("original", "log_diff", "rsi", "bollinger", "macd" or "donchian")
+        #[arg(long, default_value = "log_diff")]
+        generator: String,
+    },
+
+    /// Write synthetic market data with known properties (trending,
+    /// mean-reverting, random walk, regime switch) and check that their
+    /// statistics fall in the expected ranges
+    GenFixtures {
+        /// Fixture to write, repeated for several (default: all of them)
+        #[arg(short, long = "kind")]
+        kinds: Vec<FixtureKind>,
+
+        /// Bars of each fixture
+        #[arg(short, long, default_value_t = DEFAULT_FIXTURE_BARS)]
+        bars: usize,
+
+        /// Random seed; the same seed always writes the same data
+        #[arg(short, long, default_value_t = DEFAULT_FIXTURE_SEED)]
+        seed: u32,
+
+        /// Output directory
+        #[arg(short = 'D', long, default_value = "fixtures/")]
+        output_dir: PathBuf,
+    },
+
+    /// Replay a tick archive through resampling, signal generation,
+    /// backtest, MCPT and bootstrap, checking the invariants of each stage
+    SelfTest {
+        /// Tick archive (`timestamp_ms,price,volume[,side]` lines, as
+        /// data_streamer writes them; default: a bundled archive drawn from
+        /// the trending fixture)
+        #[arg(short, long)]
+        ticks: Option<PathBuf>,
+
+        /// Length of the resampled bars in seconds
+        #[arg(short, long, default_value_t = 60)]
+        bar_seconds: i64,
+
+        /// Number of MCPT replications
+        #[arg(short, long, default_value_t = 200)]
+        nreps: usize,
+
+        /// Minutes of the bundled archive
+        #[arg(long, default_value_t = DEFAULT_FIXTURE_BARS)]
+        minutes: usize,
+
+        /// Seed of the bundled archive
+        #[arg(short, long, default_value_t = DEFAULT_FIXTURE_SEED)]
+        seed: u32,
+    },
+}
+
+/// Optimizer run by the optimize command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Optimizer {
+    DiffEv,
+    CmaEs,
+    Pso,
+    Grid,
+    Random,
+}
+
+impl FromStr for Optimizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "de" | "diff_ev" => Ok(Optimizer::DiffEv),
+            "cmaes" | "cma-es" | "cma_es" => Ok(Optimizer::CmaEs),
+            "pso" => Ok(Optimizer::Pso),
+            "grid" => Ok(Optimizer::Grid),
+            "random" => Ok(Optimizer::Random),
+            _ => Err(format!("Unknown optimizer '{}' (expected de, cmaes, pso, grid or random)", s)),
+        }
+    }
 }
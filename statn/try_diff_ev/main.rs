@@ -1,14 +1,26 @@
 use std::fs::File;
 use std::io::Write;
 use std::process;
+use std::time::Duration;
 
-use statn::estimators::sensitivity::sensitivity;
+use statn::estimators::criteria::{CompositeCriterion, CriterionGuard, TradeCountPenalty};
+use statn::estimators::sensitivity::{sensitivity_sweep, SensitivityConfig};
 use statn::estimators::StocBias;
-use statn::models::differential_evolution::diff_ev;
+use statn::core::matlib::rands::unifrand;
+use statn::models::cma_es::{cma_es, CmaEsConfig};
+use statn::models::differential_evolution::{diff_ev_outcome, Checkpoint, Convergence, DiffEvState};
+use statn::models::multi_objective::{diff_ev_pareto, pareto_to_csv, ParetoConfig};
+use statn::models::pso::{pso, PsoConfig};
+use statn::models::search::{grid_search, random_search, SearchConfig};
+use statn::core::io::{read_provenance, SessionFilter};
 
 use try_diff_ev::{
-    backtest_signals, criter, criter_enhanced, generate_signals,
-    load_market_data, load_parameters, save_parameters, visualise_signals, MarketData,
+    anonymize, audit_determinism, backtest_signals_with, criter, criter_bollinger, criter_donchian, criter_enhanced, criter_macd, criter_rsi, diagnose, DiagnosticLimits, GeneratorRegistry,
+    load_market_data_with_format, load_parameters, outlier_analysis, read_fills, reconcile,
+    save_parameters_with_provenance, stress_params, MarketDataFormat, Slippage, StressOptions, StressReport,
+    fixtures_manifest, generate_fixture, return_drawdown, align_returns, backtest_book, oos_returns, Allocation, param_bounds, param_columns, param_names,
+    backtest_as_positions, hold_outside_session, read_scenarios, run_scenarios, scenarios_to_csv, scenarios_to_markdown, BacktestOptions, FeeSchedule, MarketData, Pacing, Stops, PACING_PARAM_NAMES,
+    BOOTSTRAP_REPS, BOOTSTRAP_SEED, bundled_ticks, read_ticks, run_self_test,
 };
 
 // Include entrypoint helper module
@@ -16,7 +28,10 @@ use try_diff_ev::{
 mod entrypoint_helper;
 
 use clap::Parser;
-use entrypoint_helper::{Cli, Commands};
+use entrypoint_helper::{Cli, Commands, Optimizer};
+use try_diff_ev::FixtureKind;
+#[cfg(feature = "plotting")]
+use try_diff_ev::{plot_sensitivity, visualise_signals};
 
 
 
@@ -29,11 +44,35 @@ fn main() {
     match cli.command {
         Commands::Optimize {
             data_file,
+            delimiter,
+            price_column,
             max_lookback,
             max_thresh,
             popsize,
             max_gens,
             min_trades,
+            trade_penalty,
+            turnover_penalty,
+            min_hold,
+            cooldown,
+            optimize_pacing,
+            session,
+            skip_weekends,
+            optimizer,
+            pso_variant,
+            search_evals,
+            init,
+            min_diversity,
+            restart_frac,
+            max_reruns,
+            seed,
+            checkpoint_every,
+            checkpoint,
+            resume,
+            min_improvement,
+            improvement_window,
+            min_criterion_std,
+            max_minutes,
             train_pct,
             params_file,
             sensitivity_log,
@@ -44,17 +83,22 @@ fn main() {
             println!("\n=== OPTIMIZATION MODE ===");
             println!("Data file: {}", data_file.display());
             println!("Max lookback: {}", max_lookback);
-            println!("Output: {}\n", output_dir.join(&params_file).display());
+            println!("Output: {}", output_dir.join(&params_file).display());
+            let seed = seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32);
+            println!("Seed: {} (replay with --seed {})\n", seed, seed);
             
             // Load market data
-            let market_data = match load_market_data(&data_file, max_lookback) {
+            let format = MarketDataFormat { delimiter, price_column };
+            let mut market_data = match load_market_data_with_format(&data_file, max_lookback, &format) {
                 Ok(data) => data,
                 Err(e) => {
                     eprintln!("Error: {}", e);
                     process::exit(1);
                 }
             };
-            println!("Loaded {} prices\n", market_data.prices.len());
+            println!("Loaded {} prices", market_data.prices.len());
+            restrict_to_session(&mut market_data, session, skip_weekends);
+            println!();
             
             // Create output directory
             if let Err(e) = std::fs::create_dir_all(&output_dir) {
@@ -72,132 +116,460 @@ fn main() {
             println!("Training on first {} prices ({:.1}%)", split_idx, train_pct * 100.0);
             
             // Create training market data
-            let train_data = MarketData {
-                prices: market_data.prices[..split_idx].to_vec(),
-                max_lookback: market_data.max_lookback,
-            };
-            
-            let low_bounds = vec![2.0, 0.01, 0.0, 0.0];
-            let high_bounds = vec![max_lookback as f64, 99.0, max_thresh, max_thresh];
+            let train_data = market_data.slice(0..split_idx);
+            println!("Training data: {}", train_data.provenance);
             
-            let mut stoc_bias_opt = StocBias::new(train_data.prices.len() - max_lookback);
-            if stoc_bias_opt.is_none() {
-                eprintln!("Insufficient memory for StocBias");
-                process::exit(1);
+            let (mut low_bounds, mut high_bounds) = param_bounds(&generator, max_lookback, max_thresh);
+            // Upper bounds may be widened on a re-run, but never past these
+            let mut high_limits = match generator.as_str() {
+                "rsi" => vec![max_lookback as f64, 50.0, 95.0, 70.0],
+                "bollinger" => vec![max_lookback as f64, 2.0, f64::INFINITY, f64::INFINITY],
+                "macd" => vec![max_lookback as f64, 99.0, max_lookback as f64, f64::INFINITY],
+                "donchian" => vec![max_lookback as f64, 100.0, f64::INFINITY, f64::INFINITY],
+                _ => vec![max_lookback as f64, 99.0, f64::INFINITY, f64::INFINITY],
+            };
+            // The minimum hold and cooldown follow the generator's four
+            // parameters, searched or fixed
+            let nvars = if optimize_pacing { 6 } else { 4 };
+            if optimize_pacing {
+                let highest = [max_lookback.max(min_hold) as f64, max_lookback.max(cooldown) as f64];
+                low_bounds.extend([min_hold as f64, cooldown as f64]);
+                high_bounds.extend(highest);
+                high_limits.extend(highest);
             }
+            let paced = |params: &[f64]| -> [f64; 6] {
+                let mut full = [0.0, 0.0, 0.0, 0.0, min_hold as f64, cooldown as f64];
+                full[..nvars].copy_from_slice(&params[..nvars]);
+                full
+            };
+            let limits = DiagnosticLimits::default();
             
-            let sb_ptr = stoc_bias_opt.as_mut().unwrap() as *mut StocBias;
-            let criter_wrapper = |params: &[f64], mintrades: i32| -> f64 {
-                unsafe {
-                    let mut sb_ref = Some(&mut *sb_ptr);
-                    match generator.as_str() {
-                        "log_diff" | "enhanced" => criter_enhanced(params, mintrades, &train_data, &mut sb_ref),
-                        _ => criter(params, mintrades, &train_data, &mut sb_ref),
+            let criterion = CompositeCriterion {
+                min_trades,
+                trade_penalty: if trade_penalty > 0.0 {
+                    TradeCountPenalty::Quadratic(trade_penalty)
+                } else {
+                    TradeCountPenalty::Hard
+                },
+                turnover_lambda: turnover_penalty,
+            };
+
+            let checkpoint_path = output_dir.join(&checkpoint);
+            // Only the first run resumes; re-runs start afresh
+            let mut resume_state = if resume {
+                match DiffEvState::load(&checkpoint_path) {
+                    Ok(state) => {
+                        println!("Resuming from {} at generation {}", checkpoint_path.display(), state.generation);
+                        Some(state)
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(1);
                     }
                 }
+            } else {
+                None
             };
+
+            let mut run_min_trades = min_trades;
+            let mut n_reruns = 0;
+            // Counts NaN and infinite criteria over all runs
+            let guard = CriterionGuard::new();
+            let (params, stoc_bias_opt) = loop {
+                let mut stoc_bias_opt = StocBias::new(train_data.prices.len() - max_lookback);
+                if stoc_bias_opt.is_none() {
+                    eprintln!("Insufficient memory for StocBias");
+                    process::exit(1);
+                }
+
+                let sb_ptr = stoc_bias_opt.as_mut().unwrap() as *mut StocBias;
+                let criter_wrapper = guard.wrap(|params: &[f64], mintrades: i32| -> f64 {
+                    let params = &paced(params);
+                    unsafe {
+                        let mut sb_ref = Some(&mut *sb_ptr);
+                        match generator.as_str() {
+                            "log_diff" | "enhanced" => criter_enhanced(params, mintrades, &train_data, &criterion, &mut sb_ref),
+                            "rsi" => criter_rsi(params, mintrades, &train_data, &criterion, &mut sb_ref),
+                            "bollinger" => criter_bollinger(params, mintrades, &train_data, &criterion, &mut sb_ref),
+                            "macd" => criter_macd(params, mintrades, &train_data, &criterion, &mut sb_ref),
+                            "donchian" => criter_donchian(params, mintrades, &train_data, &criterion, &mut sb_ref),
+                            _ => criter(params, mintrades, &train_data, &criterion, &mut sb_ref),
+                        }
+                    }
+                });
+                
+                let result = if optimizer == Optimizer::CmaEs {
+                    println!("Running CMA-ES...");
+                    let config = CmaEsConfig {
+                        nvars,
+                        nints: 1,
+                        mintrades: run_min_trades,
+                        popsize: 0,
+                        max_evals: 10000000,
+                        max_bad_gen: popsize,
+                        sigma0: 0.3,
+                        low_bounds: &low_bounds,
+                        high_bounds: &high_bounds,
+                        print_progress: verbose,
+                        seed: Some(seed),
+                    };
+                    cma_es(criter_wrapper, config, &mut stoc_bias_opt)
+                } else if optimizer == Optimizer::Pso {
+                    println!("Running particle swarm ({:?})...", pso_variant);
+                    let config = PsoConfig {
+                        nvars,
+                        nints: 1,
+                        mintrades: run_min_trades,
+                        popsize: 100,
+                        max_evals: 100 * max_gens.max(100),
+                        max_bad_gen: popsize,
+                        variant: pso_variant,
+                        low_bounds: &low_bounds,
+                        high_bounds: &high_bounds,
+                        print_progress: verbose,
+                        seed: Some(seed),
+                    };
+                    pso(criter_wrapper, config, &mut stoc_bias_opt)
+                } else if matches!(optimizer, Optimizer::Grid | Optimizer::Random) {
+                    let config = SearchConfig {
+                        nvars,
+                        nints: 1,
+                        mintrades: run_min_trades,
+                        max_evals: search_evals,
+                        low_bounds: &low_bounds,
+                        high_bounds: &high_bounds,
+                        seed: Some(seed),
+                    };
+                    // The baselines collect stochastic bias over all their
+                    // evaluations, which are all spread over the space
+                    if let Some(sb) = stoc_bias_opt.as_mut() {
+                        sb.set_collecting(true);
+                    }
+                    let best = if optimizer == Optimizer::Grid {
+                        println!("Running grid search...");
+                        grid_search(criter_wrapper, &config)
+                    } else {
+                        println!("Running random search...");
+                        random_search(criter_wrapper, &config)
+                    };
+                    if let Some(sb) = stoc_bias_opt.as_mut() {
+                        sb.set_collecting(false);
+                    }
+                    best
+                } else {
+                    println!("Running differential evolution...");
+
+                    let config = statn::models::differential_evolution::DiffEvConfig {
+                        nvars,
+                        nints: 1,
+                        popsize: 100,
+                        overinit: max_gens,
+                        mintrades: run_min_trades,
+                        max_evals: 10000000,
+                        max_bad_gen: popsize,
+                        mutate_dev: 0.2,
+                        pcross: 0.2,
+                        pclimb: 0.3,
+                        low_bounds: &low_bounds,
+                        high_bounds: &high_bounds,
+                        print_progress: verbose,
+                        min_diversity,
+                        restart_frac,
+                        init,
+                        seed: Some(seed),
+                        checkpoint: Some(Checkpoint { path: &checkpoint_path, every: checkpoint_every }),
+                        resume: resume_state.take(),
+                        convergence: Convergence {
+                            window: improvement_window,
+                            min_improvement,
+                            min_criterion_std,
+                            max_time: max_minutes.map(|m| Duration::from_secs_f64(m * 60.0)),
+                        },
+                    };
+
+                    diff_ev_outcome(criter_wrapper, config, &mut stoc_bias_opt).map(|outcome| {
+                        println!("\nStopped after {} generations: {}", outcome.generations, outcome.stop);
+                        outcome.best
+                    })
+                };
+
+                let params = match result {
+                    Ok(params) => params,
+                    Err(e) => {
+                        eprintln!("Optimization error: {}", e);
+                        process::exit(1);
+                    }
+                };
+
+                // Check for a spurious optimum before reporting it
+                let diagnosis = diagnose(&generator, &paced(&params), &train_data, &low_bounds, &high_bounds, &limits);
+                if !diagnosis.is_degenerate() {
+                    break (params, stoc_bias_opt);
+                }
+                println!(
+                    "\nDegenerate optimum (exposure {:.1}%, {} trades, largest trade {:.1}% of gross profit):",
+                    100.0 * diagnosis.exposure,
+                    diagnosis.n_trades,
+                    100.0 * diagnosis.largest_trade_share
+                );
+                for issue in &diagnosis.issues {
+                    println!("  - {}", issue);
+                }
+
+                let nbars = train_data.prices.len() - max_lookback;
+                let plan = if n_reruns < max_reruns {
+                    diagnosis.rerun_plan(run_min_trades, nbars, &low_bounds, &high_bounds, &high_limits, &limits)
+                } else {
+                    None
+                };
+                match plan {
+                    Some(plan) => {
+                        n_reruns += 1;
+                        println!(
+                            "Re-running ({} of {}) with min trades {} and upper bounds {:?}\n",
+                            n_reruns, max_reruns, plan.min_trades, plan.high_bounds
+                        );
+                        run_min_trades = plan.min_trades;
+                        high_bounds = plan.high_bounds;
+                    }
+                    None => {
+                        println!("WARNING: no further adjustment possible; reporting the result despite the issues above");
+                        break (params, stoc_bias_opt);
+                    }
+                }
+            };
+            
+            println!("\n=== RESULTS ===");
+            println!("Best performance: {:.4}", params[nvars]);
+            let counts = guard.counts();
+            println!("Criterion:        {}", counts);
+            if counts.non_finite() > 0 {
+                println!("WARNING: the criterion was NaN or infinite for some parameters; those were treated as rejected");
+            }
+            // Whole bars of hold and cooldown, as the systems use them
+            let mut saved = paced(&params).to_vec();
+            saved[4] = saved[4].round();
+            saved[5] = saved[5].round();
+            if !Pacing::from_params(&saved).is_active() {
+                saved.truncate(4);
+            }
+            println!("\nOptimal parameters:");
+            for (name, value) in param_names(&generator).iter().chain(&PACING_PARAM_NAMES).zip(&saved) {
+                println!("  {:<15} {:.4}", format!("{}:", name), value);
+            }
             
+            if let Some(ref sb) = stoc_bias_opt {
+                let (is_mean, oos_mean, bias) = sb.compute();
+                println!("\nBias estimates:");
+                println!("  In-sample:      {:.4}", is_mean);
+                println!("  Out-of-sample:  {:.4}", oos_mean);
+                println!("  Bias:           {:.4}", bias);
+                println!("  Expected:       {:.4}", params[nvars] - bias);
+            }
+            
+            // Save parameters
+            let output_path = output_dir.join(&params_file);
+            if let Err(e) = save_parameters_with_provenance(&output_path, &saved, Some(&train_data.provenance)) {
+                eprintln!("Error saving parameters: {}", e);
+            } else {
+                println!("\n✓ Parameters saved to: {}", output_path.display());
+            }
             
-            println!("Running differential evolution...");
+            // Sensitivity analysis
+            println!("\nRunning sensitivity analysis...");
             
-            let config = statn::models::differential_evolution::DiffEvConfig {
-                nvars: 4,
+            let sens_config = SensitivityConfig {
+                nvars,
                 nints: 1,
-                popsize: 100,
-                overinit: max_gens,
-                mintrades: min_trades,
-                max_evals: 10000000,
-                max_bad_gen: popsize,
-                mutate_dev: 0.2,
-                pcross: 0.2,
-                pclimb: 0.3,
+                npoints: 30,
+                nres: 80,
+                mintrades: run_min_trades,
+                best: &params,
                 low_bounds: &low_bounds,
                 high_bounds: &high_bounds,
-                print_progress: verbose,
             };
             
-            let result = diff_ev(
-                criter_wrapper,
-                config,
-                &mut stoc_bias_opt,
-            );
-            
-            match result {
-                Ok(params) => {
-                    println!("\n=== RESULTS ===");
-                    println!("Best performance: {:.4}", params[4]);
-                    println!("\nOptimal parameters:");
-                    println!("  Long lookback:  {:.4}", params[0]);
-                    println!("  Short %:        {:.4}", params[1]);
-                    println!("  Short thresh:   {:.4}", params[2]);
-                    println!("  Long thresh:    {:.4}", params[3]);
-                    
-                    if let Some(ref sb) = stoc_bias_opt {
-                        let (is_mean, oos_mean, bias) = sb.compute();
-                        println!("\nBias estimates:");
-                        println!("  In-sample:      {:.4}", is_mean);
-                        println!("  Out-of-sample:  {:.4}", oos_mean);
-                        println!("  Bias:           {:.4}", bias);
-                        println!("  Expected:       {:.4}", params[4] - bias);
-                    }
-                    
-                    // Save parameters
-                    let output_path = output_dir.join(&params_file);
-                    if let Err(e) = save_parameters(&output_path, &params[0..4]) {
-                        eprintln!("Error saving parameters: {}", e);
-                    } else {
-                        println!("\n✓ Parameters saved to: {}", output_path.display());
+            let mut sens = sensitivity_sweep(
+                |p, m| {
+                    let p = &paced(p);
+                    match generator.as_str() {
+                        "log_diff" | "enhanced" => criter_enhanced(p, m, &train_data, &criterion, &mut None),
+                        "rsi" => criter_rsi(p, m, &train_data, &criterion, &mut None),
+                        "bollinger" => criter_bollinger(p, m, &train_data, &criterion, &mut None),
+                        "macd" => criter_macd(p, m, &train_data, &criterion, &mut None),
+                        "donchian" => criter_donchian(p, m, &train_data, &criterion, &mut None),
+                        _ => criter(p, m, &train_data, &criterion, &mut None),
                     }
-                    
-                    // Sensitivity analysis
-                    println!("\nRunning sensitivity analysis...");
-                    
-                    let sens_config = statn::estimators::sensitivity::SensitivityConfig {
-                        nvars: 4,
-                        nints: 1,
-                        npoints: 30,
-                        nres: 80,
-                        mintrades: min_trades,
-                        best: &params,
-                        low_bounds: &low_bounds,
-                        high_bounds: &high_bounds,
-                    };
-                    
-                    let _ = sensitivity(
-                        |p, m| match generator.as_str() {
-                            "log_diff" | "enhanced" => criter_enhanced(p, m, &train_data, &mut None),
-                            _ => criter(p, m, &train_data, &mut None),
-                        },
-                        4, 1, 30, 80, min_trades, &params,
-                        &low_bounds, &high_bounds,
-                        &output_dir.join(&sensitivity_log),
-                    );
-                    println!("✓ Sensitivity saved to SENS.LOG");
+                },
+                &sens_config,
+            );
+            sens.provenance = Some(train_data.provenance.clone());
+            let sens_log = output_dir.join(&sensitivity_log);
+            let written = std::fs::write(&sens_log, sens.histogram(sens_config.nres))
+                .and_then(|_| sens.write_csv(sens_log.with_extension("csv")))
+                .and_then(|_| sens.write_json(sens_log.with_extension("json")));
+            match written {
+                Ok(()) => println!(
+                    "✓ Sensitivity saved to {} (.csv, .json)",
+                    sens_log.display()
+                ),
+                Err(e) => eprintln!("Error saving sensitivity: {}", e),
+            }
+            #[cfg(feature = "plotting")]
+            {
+                let sens_chart = output_dir.join("sensitivity.png");
+                let names: Vec<&str> = param_names(&generator).iter().chain(&PACING_PARAM_NAMES).copied().collect();
+                if let Err(e) = plot_sensitivity(&sens, &names, &sens_chart) {
+                    eprintln!("Failed to create sensitivity chart: {}", e);
+                } else {
+                    println!("✓ Sensitivity chart saved to: {}", sens_chart.display());
                 }
+            }
+        }
+        
+        Commands::Pareto {
+            data_file,
+            delimiter,
+            price_column,
+            max_lookback,
+            max_thresh,
+            popsize,
+            max_gens,
+            min_trades,
+            train_pct,
+            seed,
+            front_file,
+            accounting,
+            generator,
+            output_dir,
+            verbose,
+        } => {
+            println!("\n=== PARETO MODE ===");
+            println!("Data file: {}", data_file.display());
+            println!("Accounting: {}", accounting.label());
+            let seed = seed.unwrap_or_else(|| (unifrand() * u32::MAX as f64) as u32);
+            println!("Seed: {} (replay with --seed {})\n", seed, seed);
+
+            let format = MarketDataFormat { delimiter, price_column };
+            let market_data = match load_market_data_with_format(&data_file, max_lookback, &format) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let split_idx = (market_data.prices.len() as f64 * train_pct) as usize;
+            if split_idx < max_lookback + 10 {
+                eprintln!("Training set too small: {} prices", split_idx);
+                process::exit(1);
+            }
+            let train_data = market_data.slice(0..split_idx);
+            println!("Training data: {}", train_data.provenance);
+
+            let (low_bounds, high_bounds) = param_bounds(&generator, max_lookback, max_thresh);
+            let config = ParetoConfig {
+                nvars: 4,
+                nints: 1,
+                popsize,
+                max_gens,
+                mintrades: min_trades,
+                mutate_dev: 0.7,
+                pcross: 0.5,
+                low_bounds: &low_bounds,
+                high_bounds: &high_bounds,
+                print_progress: verbose,
+                seed: Some(seed),
+            };
+            println!("Running multi-objective differential evolution...");
+            let front = match diff_ev_pareto(|p, m| return_drawdown(p, m, &train_data, &generator, accounting), config) {
+                Ok(front) => front,
                 Err(e) => {
                     eprintln!("Optimization error: {}", e);
                     process::exit(1);
                 }
+            };
+
+            println!("\nPareto set ({} parameter sets):", front.len());
+            let header = match generator.as_str() {
+                "rsi" => ["Period", "Oversold", "Overbought", "Exit"],
+                "bollinger" => ["Lookback", "Exit k", "Short k", "Long k"],
+                "macd" => ["Slow", "Fast %", "Signal", "Thresh"],
+                "donchian" => ["Channel", "Exit %", "Stop", "Thresh"],
+                _ => ["Lookback", "Short %", "Short thr", "Long thr"],
+            };
+            println!(
+                "{:>8} {:>8} {:>12} {:>12} {:>12} {:>12}",
+                header[0], header[1], header[2], header[3], "Mean ret", "Max DD"
+            );
+            for point in &front {
+                let p = &point.params;
+                println!(
+                    "{:>8} {:>8.2} {:>12.4} {:>12.4} {:>12.6} {:>12.4}",
+                    p[0], p[1], p[2], p[3], point.objectives[0], -point.objectives[1]
+                );
+            }
+
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
+                eprintln!("Error creating output directory: {}", e);
+                process::exit(1);
+            }
+            let front_path = output_dir.join(&front_file);
+            let csv = pareto_to_csv(&front, &param_columns(&generator), &["mean_return", "neg_max_drawdown"]);
+            let header = format!("{}\n# accounting: {}", train_data.provenance.comment(), accounting);
+            match std::fs::write(&front_path, format!("{}\n{}", header, csv)) {
+                Ok(()) => println!("\n✓ Pareto set saved to: {}", front_path.display()),
+                Err(e) => eprintln!("Failed to write Pareto set: {}", e),
             }
         }
-        
+
         Commands::Predict {
             data_file,
+            delimiter,
+            price_column,
             params_file,
             budget,
             transaction_cost,
+            fees,
+            fee_schedules,
+            slippage,
+            session,
+            skip_weekends,
+            stop_loss,
+            take_profit,
+            trailing_stop,
+            fill,
+            accounting,
+            outlier_trades,
             train_pct,
             output_dir,
             generator,
+            audit,
+            anonymize: export_anonymized,
+            anchor_date,
             verbose,
         } => {
             println!("\n=== PREDICTION MODE ===");
             println!("Data file: {}", data_file.display());
             println!("Parameters: {}", params_file.display());
             println!("Budget: ${:.2}\n", budget);
+
+            let fees = match fees.map(|name| FeeSchedule::resolve(&name, fee_schedules.as_deref())).transpose() {
+                Ok(fees) => fees,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            if let Some(fees) = &fees {
+                println!("Fees: {} (maker {}%, taker {}%)", fees.name, fees.maker_pct, fees.taker_pct);
+            }
             
             // Load parameters
-            let params = match load_parameters(&output_dir.join(params_file)) {
+            let params_path = output_dir.join(params_file);
+            if let Some(fitted) = read_provenance(&params_path) {
+                println!("Fitted on: {}", fitted);
+            }
+            let params = match load_parameters(&params_path) {
                 Ok(p) => p,
                 Err(e) => {
                     eprintln!("Error loading parameters: {}", e);
@@ -211,21 +583,24 @@ fn main() {
             }
             
             println!("Parameters:");
-            println!("  Long lookback:  {:.4}", params[0]);
-            println!("  Short %:        {:.4}", params[1]);
-            println!("  Short thresh:   {:.4}", params[2]);
-            println!("  Long thresh:    {:.4}\n", params[3]);
+            for (name, value) in param_names(&generator).iter().chain(&PACING_PARAM_NAMES).zip(&params) {
+                println!("  {:<15} {:.4}", format!("{}:", name), value);
+            }
+            println!();
             
             // Load market data (use a reasonable max_lookback)
             let max_lookback = (params[0] as usize).max(100);
-            let market_data = match load_market_data(&data_file, max_lookback) {
+            let format = MarketDataFormat { delimiter, price_column };
+            let mut market_data = match load_market_data_with_format(&data_file, max_lookback, &format) {
                 Ok(data) => data,
                 Err(e) => {
                     eprintln!("Error: {}", e);
                     process::exit(1);
                 }
             };
-            println!("Loaded {} prices\n", market_data.prices.len());
+            println!("Loaded {} prices", market_data.prices.len());
+            restrict_to_session(&mut market_data, session, skip_weekends);
+            println!("Data: {}\n", market_data.provenance);
             
             // Create output directory
             if let Err(e) = std::fs::create_dir_all(&output_dir) {
@@ -235,12 +610,15 @@ fn main() {
             
             // Generate signals
             println!("Using signal generator: {}", generator);
-            let result = generate_signals(
-                &generator,
-                &market_data.prices,
-                (params[0] + 1.0e-10) as usize,
-                params[1], params[2], params[3],
-            );
+            let signal_generator = match GeneratorRegistry::default().build(&generator, &params) {
+                Ok(g) => g,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let mut result = signal_generator.generate(&market_data.prices);
+            result.signals = hold_outside_session(&result.signals, &market_data.tradable);
             
             // Slice for backtesting (unseen data)
             let split_idx = (market_data.prices.len() as f64 * train_pct) as usize;
@@ -277,7 +655,53 @@ fn main() {
             }
             
             // Backtest
-            let stats = backtest_signals(&test_result, budget, transaction_cost);
+            let volumes = market_data.volumes.get(split_idx..).filter(|v| !v.is_empty());
+            if matches!(slippage, Slippage::VolumeDependent { .. }) && volumes.is_none() {
+                println!("WARNING: no Volume column; volume-dependent slippage uses its base rate only");
+            }
+            let options = BacktestOptions {
+                transaction_cost_pct: transaction_cost,
+                fees: fees.as_ref(),
+                slippage,
+                volumes,
+                tradable: market_data.tradable.get(split_idx..).filter(|t| !t.is_empty()),
+                stops: Stops { stop_loss, take_profit, trailing: trailing_stop },
+                highs: market_data.highs.get(split_idx..).filter(|h| !h.is_empty()),
+                lows: market_data.lows.get(split_idx..).filter(|l| !l.is_empty()),
+                fill,
+                opens: market_data.opens.get(split_idx..).filter(|o| !o.is_empty()),
+                accounting,
+                dates: market_data.dates.get(split_idx..).filter(|d| !d.is_empty()),
+                target_positions: backtest_as_positions(signal_generator.as_ref(), &market_data.tradable),
+            };
+            let stats = backtest_signals_with(&test_result, budget, &options);
+
+            if audit {
+                // At least two threads, so shared state is exercised concurrently
+                let threads = std::thread::available_parallelism().map_or(4, |n| n.get()).max(2);
+                let report = audit_determinism(
+                    || {
+                        let result = signal_generator.generate(&market_data.prices);
+                        let signals = hold_outside_session(&result.signals, &market_data.tradable);
+                        let test_result = try_diff_ev::SignalResult {
+                            prices: result.prices[split_idx..].to_vec(),
+                            signals: signals[split_idx..].to_vec(),
+                            ..result
+                        };
+                        backtest_signals_with(&test_result, budget, &options)
+                    },
+                    threads,
+                );
+                if report.is_deterministic() {
+                    println!("✓ Determinism audit: {} repeated runs bit-identical ({} threads)\n", report.runs, threads);
+                } else {
+                    eprintln!("Determinism audit FAILED:");
+                    for mismatch in &report.mismatches {
+                        eprintln!("  - {}", mismatch);
+                    }
+                    process::exit(3);
+                }
+            }
             
             println!("=== BACKTEST RESULTS ===");
             println!("Initial Budget:    ${:.2}", stats.initial_budget);
@@ -290,63 +714,578 @@ fn main() {
             println!("  Losing Trades:   {}", stats.num_losses);
             println!("  Win Rate:        {:.2}%", stats.win_rate);
             println!("  Total Costs:     ${:.2}", stats.total_costs);
+            println!("  Total Slippage:  ${:.2}", stats.total_slippage);
             println!("\nRisk Metrics:");
             println!("  Max Drawdown:    {:.2}%", stats.max_drawdown);
-            println!("  Sharpe Ratio:    {:.4}", stats.sharpe_ratio);
+            println!("  Sharpe Ratio:    {:.4} ({})", stats.sharpe_ratio, stats.accounting_basis.label());
+            println!("  Sortino Ratio:   {:.4}", stats.sortino_ratio);
+            println!("  Calmar Ratio:    {:.4}", stats.calmar_ratio);
+            println!("  MAR Ratio:       {:.4}", stats.mar_ratio);
+            println!("  Ulcer Index:     {:.4}", stats.ulcer_index);
+            println!("  Avg DD Duration: {:.1} bars", stats.avg_drawdown_duration);
+
+            // Dependence of the return on the outlying trades
+            if stats.trades.len() > 1 {
+                let outliers = outlier_analysis(&stats.trades, &outlier_trades, 0.9, BOOTSTRAP_REPS, BOOTSTRAP_SEED);
+                println!("\nOutlier Dependence (compounded trade returns):");
+                for line in outliers.to_string().lines() {
+                    println!("  {}", line);
+                }
+            }
             
             // Write trade log to file
             let log_path = output_dir.join("trade_log.txt");
             match File::create(&log_path) {
                 Ok(mut file) => {
+                    writeln!(file, "{}", market_data.provenance.comment()).unwrap();
                     writeln!(file, "=== TRADE LOG ===").unwrap();
-                    writeln!(file, "{:<5} {:<8} {:<10} {:<10} {:<10} {:<10} {:<8}", 
-                             "Type", "Entry Idx", "Entry Price", "Exit Idx", "Exit Price", "P&L", "Return").unwrap();
-                    writeln!(file, "{}", "-".repeat(70)).unwrap();
+                    writeln!(file, "{:<5} {:<8} {:<10} {:<10} {:<10} {:<10} {:<8} {:<6}", 
+                             "Type", "Entry Idx", "Entry Price", "Exit Idx", "Exit Price", "P&L", "Return", "Exit").unwrap();
+                    writeln!(file, "{}", "-".repeat(77)).unwrap();
                     
                     for trade in &stats.trades {
-                        writeln!(file, "{:<5} {:<8} {:<10.4} {:<10} {:<10.4} {:<10.2} {:>7.2}%",
+                        writeln!(file, "{:<5} {:<8} {:<10.4} {:<10} {:<10.4} {:<10.2} {:>7.2}% {:<6}",
                                  trade.trade_type,
                                  trade.entry_index + split_idx,
                                  trade.entry_price,
                                  trade.exit_index + split_idx,
                                  trade.exit_price,
                                  trade.pnl,
-                                 trade.return_pct).unwrap();
+                                 trade.return_pct,
+                                 trade.exit_reason.label()).unwrap();
                     }
                     println!("\n✓ Trade log saved to: {}", log_path.display());
                 }
                 Err(e) => eprintln!("Failed to write trade log: {}", e),
             }
 
+            if export_anonymized {
+                let dates = market_data.dates.get(split_idx..).filter(|d| d.len() == stats.budget_history.len());
+                let written = anonymize(&stats, dates, &anchor_date).and_then(|series| {
+                    let returns_path = output_dir.join("anonymized_returns.csv");
+                    let trades_path = output_dir.join("anonymized_trades.csv");
+                    std::fs::write(&returns_path, series.returns_csv())
+                        .and_then(|_| std::fs::write(&trades_path, series.trades_csv()))
+                        .map(|_| (returns_path, trades_path))
+                        .map_err(|e| e.to_string())
+                });
+                match written {
+                    Ok((returns_path, trades_path)) => println!(
+                        "✓ Anonymized export saved to: {} and {}",
+                        returns_path.display(),
+                        trades_path.display()
+                    ),
+                    Err(e) => eprintln!("Failed to write anonymized export: {}", e),
+                }
+            }
+
             // Print detailed trade log if verbose
             if verbose {
                 println!("\n=== TRADE LOG ===");
-                println!("{:<5} {:<8} {:<10} {:<10} {:<10} {:<10} {:<8}", 
-                         "Type", "Entry Idx", "Entry Price", "Exit Idx", "Exit Price", "P&L", "Return");
-                println!("{}", "-".repeat(70));
+                println!("{:<5} {:<8} {:<10} {:<10} {:<10} {:<10} {:<8} {:<6}", 
+                         "Type", "Entry Idx", "Entry Price", "Exit Idx", "Exit Price", "P&L", "Return", "Exit");
+                println!("{}", "-".repeat(77));
                 
                 for trade in &stats.trades {
-                    println!("{:<5} {:<8} {:<10.4} {:<10} {:<10.4} {:<10.2} {:>7.2}%",
+                    println!("{:<5} {:<8} {:<10.4} {:<10} {:<10.4} {:<10.2} {:>7.2}% {:<6}",
                              trade.trade_type,
                              trade.entry_index + split_idx, // Adjust index to global
                              trade.entry_price,
                              trade.exit_index + split_idx, // Adjust index to global
                              trade.exit_price,
                              trade.pnl,
-                             trade.return_pct);
+                             trade.return_pct,
+                             trade.exit_reason.label());
                 }
-                println!("{}", "-".repeat(70));
+                println!("{}", "-".repeat(77));
             }
 
             // Visualize
-            let chart_path = output_dir.join("signal_chart.png");
-            if let Err(e) = visualise_signals(&test_result, Some(&stats), &chart_path) {
-                eprintln!("Failed to create chart: {}", e);
-            } else {
-                println!("\n✓ Chart saved to: {}", chart_path.display());
+            #[cfg(feature = "plotting")]
+            {
+                let chart_path = output_dir.join("signal_chart.png");
+                if let Err(e) = visualise_signals(&test_result, Some(&stats), &chart_path) {
+                    eprintln!("Failed to create chart: {}", e);
+                } else {
+                    println!("\n✓ Chart saved to: {}", chart_path.display());
+                }
+            }
+        }
+
+        Commands::StressParams {
+            data_file,
+            delimiter,
+            price_column,
+            params_file,
+            window,
+            steps,
+            max_degradation,
+            budget,
+            transaction_cost,
+            slippage,
+            output_dir,
+            generator,
+        } => {
+            println!("\n=== PARAMETER STRESS TEST ===");
+            println!("Data file: {}", data_file.display());
+            println!("Parameters: {}", params_file.display());
+
+            let params = match load_parameters(output_dir.join(params_file)) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("Error loading parameters: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            let max_lookback = (params.first().copied().unwrap_or(0.0) as usize).max(100);
+            let format = MarketDataFormat { delimiter, price_column };
+            let market_data = match load_market_data_with_format(&data_file, max_lookback, &format) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            println!("Backtesting on the last {} of {} prices\n", window, market_data.prices.len());
+
+            let options = StressOptions {
+                generator,
+                window,
+                budget,
+                transaction_cost_pct: transaction_cost,
+                slippage,
+                steps,
+            };
+            let report = match stress_params(&market_data, &params, &options) {
+                Ok(report) => StressReport { provenance: Some(market_data.provenance.clone()), ..report },
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            let names = param_names(&report.generator);
+            println!("Baseline ROI: {:.2}%\n", report.baseline_roi);
+            println!("{:<14} {:>8} {:>10} {:>10} {:>18}", "Parameter", "Change", "Value", "ROI", "Degradation (pts)");
+            println!("{}", "-".repeat(64));
+            for case in &report.cases {
+                println!(
+                    "{:<14} {:>+7.1}% {:>10.4} {:>9.2}% {:>18.2}",
+                    names[case.param],
+                    case.change_pct,
+                    case.params[case.param],
+                    case.roi,
+                    case.degradation
+                );
+            }
+
+            println!("\nWorst case per parameter:");
+            for (param, name) in names.iter().enumerate() {
+                if let Some(case) = report.worst_for(param) {
+                    println!(
+                        "  {:<14} {:>+6.1}%: {:>8.2}% ROI ({:+.2} pts)",
+                        name,
+                        case.change_pct,
+                        case.roi,
+                        case.roi - report.baseline_roi
+                    );
+                }
+            }
+
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
+                eprintln!("Error creating output directory: {}", e);
+                process::exit(1);
+            }
+            let csv_path = output_dir.join("stress_params.csv");
+            match std::fs::write(&csv_path, report.to_csv()) {
+                Ok(()) => println!("\n✓ Stress test saved to: {}", csv_path.display()),
+                Err(e) => eprintln!("Failed to write stress test: {}", e),
+            }
+
+            if let Some(worst) = report.worst() {
+                println!(
+                    "\nWorst-case degradation: {:.2} ROI points ({} {:+.1}%)",
+                    worst.degradation, names[worst.param], worst.change_pct
+                );
+                if let Some(limit) = max_degradation
+                    && worst.degradation > limit
+                {
+                    eprintln!("FAIL: worst-case degradation exceeds {:.2} ROI points", limit);
+                    process::exit(2);
+                }
             }
         }
+
+        Commands::Scenarios {
+            data_file,
+            delimiter,
+            price_column,
+            params_file,
+            scenarios,
+            budget,
+            transaction_cost,
+            slippage,
+            report,
+            output_dir,
+            generator,
+        } => {
+            println!("\n=== SCENARIO ANALYSIS ===");
+            println!("Data file: {}", data_file.display());
+            println!("Parameters: {}", params_file.display());
+
+            let scenarios = match read_scenarios(&scenarios) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let params = match load_parameters(output_dir.join(&params_file)) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("Error loading parameters: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            let max_lookback = (params.first().copied().unwrap_or(0.0) as usize).max(100);
+            let format = MarketDataFormat { delimiter, price_column };
+            let market_data = match load_market_data_with_format(&data_file, max_lookback, &format) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            println!("Data: {}\n", market_data.provenance);
+
+            // Signals over the full history, so indicators are warm when a window starts
+            let signal_generator = match GeneratorRegistry::default().build(&generator, &params) {
+                Ok(g) => g,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let mut result = signal_generator.generate(&market_data.prices);
+            result.signals = hold_outside_session(&result.signals, &market_data.tradable);
+            let options = BacktestOptions {
+                transaction_cost_pct: transaction_cost,
+                slippage,
+                volumes: Some(market_data.volumes.as_slice()).filter(|v| !v.is_empty()),
+                tradable: Some(market_data.tradable.as_slice()).filter(|t| !t.is_empty()),
+                target_positions: backtest_as_positions(signal_generator.as_ref(), &market_data.tradable),
+                ..Default::default()
+            };
+            let results = match run_scenarios(&result, &market_data.dates, &scenarios, budget, &options) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            println!(
+                "{:<24} {:>23} {:>6} {:>9} {:>9} {:>9} {:>7}",
+                "Scenario", "Window", "Bars", "Return", "Max DD", "Exposure", "Trades"
+            );
+            println!("{}", "-".repeat(93));
+            for r in &results {
+                let window = format!("{} to {}", r.scenario.start, r.scenario.end);
+                if r.bars == 0 {
+                    println!("{:<24} {:>23} {:>6}   no data", r.scenario.name, window, 0);
+                    continue;
+                }
+                println!(
+                    "{:<24} {:>23} {:>6} {:>8.2}% {:>8.2}% {:>8.1}% {:>7}",
+                    r.scenario.name, window, r.bars, r.return_pct, r.max_drawdown_pct, r.exposure_pct, r.num_trades
+                );
+            }
+
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
+                eprintln!("Error creating output directory: {}", e);
+                process::exit(1);
+            }
+            let csv_path = output_dir.join("scenarios.csv");
+            let csv = format!("{}\n{}", market_data.provenance.comment(), scenarios_to_csv(&results));
+            match std::fs::write(&csv_path, csv) {
+                Ok(()) => println!("\n✓ Scenarios saved to: {}", csv_path.display()),
+                Err(e) => eprintln!("Failed to write scenarios: {}", e),
+            }
+
+            if let Some(report) = report {
+                let heading = format!(
+                    "Generator `{}`, parameters {}, on {}.",
+                    generator,
+                    params_file.display(),
+                    market_data.provenance
+                );
+                let appended = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&report)
+                    .and_then(|mut f| f.write_all(scenarios_to_markdown(&results, &heading).as_bytes()));
+                match appended {
+                    Ok(()) => println!("✓ Appended to report: {}", report.display()),
+                    Err(e) => eprintln!("Failed to append to report {}: {}", report.display(), e),
+                }
+            }
+        }
+
+        Commands::Reconcile {
+            data_file,
+            delimiter,
+            price_column,
+            params_file,
+            journal,
+            max_lag,
+            output_dir,
+            generator,
+        } => {
+            println!("\n=== RECONCILIATION MODE ===");
+            println!("Data file: {}", data_file.display());
+            println!("Parameters: {}", params_file.display());
+            println!("Journal: {}\n", journal.display());
+
+            let params = match load_parameters(output_dir.join(params_file)) {
+                Ok(p) if p.len() >= 4 => p,
+                Ok(_) => {
+                    eprintln!("Parameters file must contain at least 4 values");
+                    process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Error loading parameters: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            let max_lookback = (params[0] as usize).max(100);
+            let format = MarketDataFormat { delimiter, price_column };
+            let market_data = match load_market_data_with_format(&data_file, max_lookback, &format) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let fills = match read_fills(&journal) {
+                Ok(fills) => fills,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            println!("Loaded {} prices and {} fills\n", market_data.prices.len(), fills.len());
+
+            let result = match GeneratorRegistry::default().build(&generator, &params) {
+                Ok(g) => g.generate(&market_data.prices),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let rec = match reconcile(&result, &market_data.dates, &fills, max_lag) {
+                Ok(rec) => rec,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let report = rec.report();
+            print!("{}", report);
+
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
+                eprintln!("Error creating output directory: {}", e);
+                process::exit(1);
+            }
+            let report_path = output_dir.join("reconciliation.txt");
+            match std::fs::write(&report_path, format!("{}\n{}", market_data.provenance.comment(), report)) {
+                Ok(()) => println!("\n✓ Reconciliation saved to: {}", report_path.display()),
+                Err(e) => eprintln!("Failed to write reconciliation: {}", e),
+            }
+        }
+
+        Commands::Portfolio {
+            systems,
+            delimiter,
+            price_column,
+            allocation,
+            risk_aversion,
+            train_pct,
+            estimate_pct,
+            output_dir,
+            generator,
+        } => {
+            println!("\n=== PORTFOLIO MODE ===");
+            let allocation = match (allocation, risk_aversion) {
+                (Allocation::MeanVariance { .. }, Some(risk_aversion)) => Allocation::MeanVariance { risk_aversion },
+                (allocation, _) => allocation,
+            };
+
+            let format = MarketDataFormat { delimiter, price_column };
+            let mut names = Vec::new();
+            let mut series = Vec::new();
+            let mut provenance = Vec::new();
+            for system in &systems {
+                let params = match load_parameters(&system.params_file) {
+                    Ok(p) if p.len() >= 4 => p,
+                    Ok(_) => {
+                        eprintln!("{}: parameters file must contain at least 4 values", system.params_file.display());
+                        process::exit(1);
+                    }
+                    Err(e) => {
+                        eprintln!("Error loading parameters: {}", e);
+                        process::exit(1);
+                    }
+                };
+                let max_lookback = (params[0] as usize).max(100);
+                let market_data = match load_market_data_with_format(&system.data_file, max_lookback, &format) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(1);
+                    }
+                };
+                let stem = system.params_file.file_stem().and_then(|s| s.to_str()).unwrap_or("params");
+                let name = format!("{}/{}", market_data.provenance.symbol, stem);
+                let (dates, returns) = oos_returns(&params, &market_data, &generator, train_pct);
+                println!("{}: {} out-of-sample bars ({})", name, returns.len(), market_data.provenance);
+                names.push(name);
+                series.push((dates, returns));
+                provenance.push(market_data.provenance);
+            }
+
+            let (dates, returns) = match align_returns(&series) {
+                Ok(aligned) => aligned,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            let estimate_bars = (returns[0].len() as f64 * estimate_pct) as usize;
+            println!("\n{} common bars\n", returns[0].len());
+            let report = match backtest_book(&names, &returns, allocation, estimate_bars) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            print!("{}", report);
+
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
+                eprintln!("Error creating output directory: {}", e);
+                process::exit(1);
+            }
+            let comments: String = provenance.iter().map(|p| format!("{}\n", p.comment())).collect();
+            let report_path = output_dir.join("portfolio.txt");
+            match std::fs::write(&report_path, format!("{}{}", comments, report)) {
+                Ok(()) => println!("\n✓ Portfolio report saved to: {}", report_path.display()),
+                Err(e) => eprintln!("Failed to write portfolio report: {}", e),
+            }
+
+            let mut csv = format!("{}bar,date,{},book\n", comments, names.join(","));
+            for (k, book) in report.returns.iter().enumerate() {
+                let t = estimate_bars + k;
+                csv.push_str(&format!("{},{}", t, dates.get(t).map_or("", String::as_str)));
+                for r in &returns {
+                    csv.push_str(&format!(",{:.8}", r[t]));
+                }
+                csv.push_str(&format!(",{:.8}\n", book));
+            }
+            let returns_path = output_dir.join("portfolio_returns.csv");
+            match std::fs::write(&returns_path, csv) {
+                Ok(()) => println!("✓ Book returns saved to: {}", returns_path.display()),
+                Err(e) => eprintln!("Failed to write book returns: {}", e),
+            }
+        }
+
+        Commands::GenFixtures { kinds, bars, seed, output_dir } => {
+            println!("\n=== SYNTHETIC FIXTURES ===");
+            let kinds = if kinds.is_empty() { FixtureKind::ALL.to_vec() } else { kinds };
+            let fixtures = match kinds.iter().map(|&kind| generate_fixture(kind, bars, seed)).collect::<Result<Vec<_>, _>>() {
+                Ok(fixtures) => fixtures,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
+                eprintln!("Error creating output directory: {}", e);
+                process::exit(1);
+            }
+
+            let mut failed = 0;
+            for fixture in &fixtures {
+                let path = output_dir.join(&fixture.file);
+                if let Err(e) = std::fs::write(&path, fixture.to_market_file()) {
+                    eprintln!("Failed to write {}: {}", path.display(), e);
+                    process::exit(1);
+                }
+                println!("\n{} ({} bars, seed {}): {}", fixture.kind, fixture.bars, fixture.seed, path.display());
+                for metric in &fixture.expected {
+                    let status = if metric.holds() { "ok" } else { "OUT OF RANGE" };
+                    println!(
+                        "  {:<22} {:>12.6}  expected {:>10.6} to {:>10.6}  {}",
+                        metric.name, metric.value, metric.low, metric.high, status
+                    );
+                }
+                failed += fixture.failures().len();
+            }
+
+            let manifest_path = output_dir.join("fixtures.toml");
+            match fixtures_manifest(&fixtures).and_then(|m| std::fs::write(&manifest_path, m).map_err(|e| e.to_string())) {
+                Ok(()) => println!("\n✓ Ground truth and expected ranges saved to: {}", manifest_path.display()),
+                Err(e) => eprintln!("Failed to write fixtures manifest: {}", e),
+            }
+            if failed > 0 {
+                eprintln!("FAIL: {} statistics outside their expected ranges", failed);
+                process::exit(2);
+            }
+        }
+
+        Commands::SelfTest { ticks, bar_seconds, nreps, minutes, seed } => {
+            println!("\n=== PIPELINE SELF-TEST ===");
+            let archive = match &ticks {
+                Some(path) => read_ticks(path),
+                None => bundled_ticks(minutes, seed),
+            };
+            let report = match archive.and_then(|t| run_self_test(&t, bar_seconds * 1000, nreps)) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            };
+            match &ticks {
+                Some(path) => println!("Archive: {}", path.display()),
+                None => println!("Archive: bundled, {} minutes of the trending fixture (seed {})", minutes, seed),
+            }
+            println!("\n{}", report);
+            let failures = report.failures();
+            if !failures.is_empty() {
+                eprintln!("FAIL: {} of {} invariants broken", failures.len(), report.checks.len());
+                process::exit(2);
+            }
+            println!("✓ All {} invariants hold", report.checks.len());
+        }
     }
     
     println!("\n✓ Completed successfully!");
 }
+
+/// Restrict `data` to the trading session of the --session and
+/// --skip-weekends options, printing how many bars it leaves; exits on an
+/// error
+fn restrict_to_session(data: &mut MarketData, session: Option<SessionFilter>, skip_weekends: bool) {
+    let session = SessionFilter { skip_weekends, ..session.unwrap_or_default() };
+    if let Err(e) = data.restrict_to_session(&session) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    if session.is_active() {
+        let tradable = data.tradable.iter().filter(|&&t| t).count();
+        println!("Session: {} of {} bars tradable", tradable, data.tradable.len());
+    }
+}
@@ -1,4 +1,8 @@
 //! Backtesting module for simulating trading strategies.
 //! This module now delegates to the general `backtesting` library.
 
-pub use backtesting::{backtest_signals, TradeLog, TradeStats};
+pub use backtesting::{
+    anonymize, AccountingBasis, audit_determinism, backtest_book, backtest_signals, backtest_signals_with, backtest_signals_with_slippage,
+    outlier_analysis, read_fills, reconcile, Allocation, AnonymizedSeries, AuditReport, BacktestOptions, BookReport, ExitReason, FeeSchedule, FillModel, Reconciliation, Slippage, StopLevel, Stops,
+    OutlierReport, TradeLog, TradeStats, BOOTSTRAP_REPS, BOOTSTRAP_SEED, DEFAULT_ANCHOR,
+};
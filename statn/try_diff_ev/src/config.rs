@@ -1,5 +1,6 @@
 //! Configuration structures for the trading system.
 
+use crate::backtest::Slippage;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -28,8 +29,16 @@ pub struct Config {
 /// Market data configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketConfig {
-    /// Path to the market data file (YYYYMMDD Price format)
+    /// Path to the market data file (YYYYMMDD Price format, or delimited with a header)
     pub data_file: PathBuf,
+
+    /// Field delimiter of the data file; detected if not given
+    #[serde(default)]
+    pub delimiter: Option<char>,
+
+    /// Price column of the data file, by header name or 0-based index
+    #[serde(default)]
+    pub price_column: Option<String>,
     
     /// Maximum lookback period for moving averages
     pub max_lookback: usize,
@@ -52,6 +61,28 @@ pub struct OptimizationConfig {
     /// Minimum number of trades required
     #[serde(default = "default_min_trades")]
     pub min_trades: i32,
+
+    /// Weight of a smooth quadratic penalty for missing `min_trades`
+    /// (0 = reject any parameter set below the minimum)
+    #[serde(default)]
+    pub trade_penalty: f64,
+
+    /// Penalty per unit of turnover, in units of total log return
+    #[serde(default)]
+    pub turnover_penalty: f64,
+
+    /// Initial population design: "uniform", "lhs" (Latin hypercube) or "sobol"
+    #[serde(default = "default_init")]
+    pub init: String,
+
+    /// Population diversity (0-1) below which the worst part of the
+    /// population is re-randomized (0 = never)
+    #[serde(default = "default_min_diversity")]
+    pub min_diversity: f64,
+
+    /// Fraction of the population re-randomized when diversity collapses
+    #[serde(default = "default_restart_frac")]
+    pub restart_frac: f64,
     
     /// Enable verbose output during optimization
     #[serde(default)]
@@ -74,6 +105,10 @@ pub struct BacktestConfig {
     /// Transaction cost as percentage (e.g., 0.1 for 0.1%)
     #[serde(default = "default_transaction_cost")]
     pub transaction_cost_pct: f64,
+
+    /// Slippage at entries and exits
+    #[serde(default)]
+    pub slippage: Slippage,
 }
 
 /// Output configuration.
@@ -92,6 +127,9 @@ pub struct OutputConfig {
 fn default_popsize() -> usize { 300 }
 fn default_max_gens() -> usize { 10000 }
 fn default_min_trades() -> i32 { 20 }
+fn default_init() -> String { "lhs".to_string() }
+fn default_min_diversity() -> f64 { 0.02 }
+fn default_restart_frac() -> f64 { 0.2 }
 fn default_initial_budget() -> f64 { 10000.0 }
 fn default_transaction_cost() -> f64 { 0.1 }
 fn default_output_dir() -> PathBuf { PathBuf::from(".") }
@@ -102,6 +140,11 @@ impl Default for OptimizationConfig {
             popsize: default_popsize(),
             max_gens: default_max_gens(),
             min_trades: default_min_trades(),
+            trade_penalty: 0.0,
+            turnover_penalty: 0.0,
+            init: default_init(),
+            min_diversity: default_min_diversity(),
+            restart_frac: default_restart_frac(),
             verbose: false,
             params_file: None,
         }
@@ -114,6 +157,7 @@ impl Default for BacktestConfig {
             params_file: PathBuf::from("params.txt"),
             initial_budget: default_initial_budget(),
             transaction_cost_pct: default_transaction_cost(),
+            slippage: Slippage::None,
         }
     }
 }
@@ -0,0 +1,249 @@
+//! Checks for degenerate optimization results.
+//!
+//! Differential evolution reports the best criterion it found, but the best
+//! can be spurious: a system that is almost never in the market, one whose
+//! profit comes from a single trade, or one whose parameters sit at the edge of
+//! the search range. These checks flag such results and suggest how to re-run.
+
+use std::fmt;
+
+use crate::evaluators::{evaluate, system_for};
+use crate::io::MarketData;
+
+/// Thresholds for flagging a result as degenerate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiagnosticLimits {
+    /// Smallest acceptable fraction of bars with a position
+    pub min_exposure: f64,
+    /// Largest acceptable share of gross profit from a single trade
+    pub max_trade_share: f64,
+    /// A parameter within this fraction of its range from a bound is pinned
+    pub bound_tolerance: f64,
+}
+
+impl Default for DiagnosticLimits {
+    fn default() -> Self {
+        Self {
+            min_exposure: 0.05,
+            max_trade_share: 0.5,
+            bound_tolerance: 0.01,
+        }
+    }
+}
+
+/// One way in which a result is degenerate
+#[derive(Debug, Clone, PartialEq)]
+pub enum Degeneracy {
+    /// The system is in the market for only this fraction of bars
+    LowExposure(f64),
+    /// One trade makes this share of the gross profit
+    DominantTrade(f64),
+    /// Parameter `param` is at its upper (true) or lower (false) bound
+    PinnedAtBound { param: usize, upper: bool },
+}
+
+impl fmt::Display for Degeneracy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Degeneracy::LowExposure(exposure) => {
+                write!(f, "near-zero exposure: in the market {:.1}% of bars", 100.0 * exposure)
+            }
+            Degeneracy::DominantTrade(share) => {
+                write!(f, "one trade makes {:.1}% of the gross profit", 100.0 * share)
+            }
+            Degeneracy::PinnedAtBound { param, upper } => write!(
+                f,
+                "parameter {} is pinned at its {} bound",
+                param,
+                if *upper { "upper" } else { "lower" }
+            ),
+        }
+    }
+}
+
+/// Exposure and trade statistics of a parameter set, with any degeneracies
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    /// Fraction of bars with a position
+    pub exposure: f64,
+    /// Number of trades (runs of bars with the same nonzero position)
+    pub n_trades: usize,
+    /// Share of the gross profit made by the most profitable trade
+    pub largest_trade_share: f64,
+    pub issues: Vec<Degeneracy>,
+}
+
+/// Adjusted settings for another optimization run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerunPlan {
+    pub min_trades: i32,
+    pub high_bounds: Vec<f64>,
+}
+
+/// Replay the system with `params` on `data` and check the result.
+///
+/// `generator` selects the test system as in the optimizer (see `system_for`).
+pub fn diagnose(
+    generator: &str,
+    params: &[f64],
+    data: &MarketData,
+    low_bounds: &[f64],
+    high_bounds: &[f64],
+    limits: &DiagnosticLimits,
+) -> Diagnosis {
+    let nbars = data.prices.len() - data.max_lookback;
+    let mut returns = vec![0.0; nbars];
+    let mut positions = vec![0.0; nbars];
+    evaluate(system_for(generator), params, data, Some(&mut returns), Some(&mut positions));
+
+    let exposure = positions.iter().filter(|&&p| p != 0.0).count() as f64 / nbars.max(1) as f64;
+    let trades = trade_profits(&returns, &positions);
+    let gross_profit: f64 = trades.iter().filter(|&&t| t > 0.0).sum();
+    let largest = trades.iter().copied().fold(0.0, f64::max);
+    let largest_trade_share = if gross_profit > 0.0 { largest / gross_profit } else { 0.0 };
+
+    let mut issues = Vec::new();
+    if exposure < limits.min_exposure {
+        issues.push(Degeneracy::LowExposure(exposure));
+    }
+    if largest_trade_share > limits.max_trade_share {
+        issues.push(Degeneracy::DominantTrade(largest_trade_share));
+    }
+    for (param, ((&p, &low), &high)) in params.iter().zip(low_bounds).zip(high_bounds).enumerate() {
+        let tolerance = limits.bound_tolerance * (high - low);
+        if high - p <= tolerance {
+            issues.push(Degeneracy::PinnedAtBound { param, upper: true });
+        } else if p - low <= tolerance {
+            issues.push(Degeneracy::PinnedAtBound { param, upper: false });
+        }
+    }
+
+    Diagnosis {
+        exposure,
+        n_trades: trades.len(),
+        largest_trade_share,
+        issues,
+    }
+}
+
+/// Profit of each trade, a trade being a run of bars with the same nonzero position
+fn trade_profits(returns: &[f64], positions: &[f64]) -> Vec<f64> {
+    let mut trades = Vec::new();
+    let mut prior = 0.0;
+    for (&ret, &pos) in returns.iter().zip(positions) {
+        if pos != 0.0 {
+            if pos != prior {
+                trades.push(0.0);
+            }
+            if let Some(last) = trades.last_mut() {
+                *last += ret;
+            }
+        }
+        prior = pos;
+    }
+    trades
+}
+
+impl Diagnosis {
+    pub fn is_degenerate(&self) -> bool {
+        !self.issues.is_empty()
+    }
+
+    /// Settings for a re-run that addresses the issues, or `None` if nothing
+    /// can be adjusted.
+    ///
+    /// Low exposure or a dominant trade raises `min_trades` to at least double
+    /// the current value and at least the number of bars that gives the minimum
+    /// exposure. A parameter pinned at its upper bound gets that bound widened by
+    /// half its range, but never beyond `high_limits`. Lower bounds are natural
+    /// limits here (a zero threshold, the shortest lookback) and are only reported.
+    pub fn rerun_plan(
+        &self,
+        min_trades: i32,
+        nbars: usize,
+        low_bounds: &[f64],
+        high_bounds: &[f64],
+        high_limits: &[f64],
+        limits: &DiagnosticLimits,
+    ) -> Option<RerunPlan> {
+        let mut plan = RerunPlan {
+            min_trades,
+            high_bounds: high_bounds.to_vec(),
+        };
+
+        for issue in &self.issues {
+            match *issue {
+                Degeneracy::LowExposure(_) | Degeneracy::DominantTrade(_) => {
+                    let needed = (limits.min_exposure * nbars as f64).ceil() as i32;
+                    plan.min_trades = plan.min_trades.max(2 * min_trades.max(1)).max(needed);
+                }
+                Degeneracy::PinnedAtBound { param, upper: true } => {
+                    let range = high_bounds[param] - low_bounds[param];
+                    plan.high_bounds[param] = (high_bounds[param] + 0.5 * range).min(high_limits[param]);
+                }
+                Degeneracy::PinnedAtBound { upper: false, .. } => {}
+            }
+        }
+
+        let changed = plan.min_trades != min_trades || plan.high_bounds != high_bounds;
+        changed.then_some(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> MarketData {
+        MarketData {
+            prices: (0..400)
+                .map(|i| (100.0 + 0.05 * i as f64 + 3.0 * (i as f64 * 0.15).sin()).ln())
+                .collect(),
+            volumes: Vec::new(),
+            dates: Vec::new(),
+            max_lookback: 20,
+            provenance: Default::default(),
+            tradable: Vec::new(),
+            highs: Vec::new(),
+            lows: Vec::new(),
+            opens: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_trade_profits() {
+        let returns = [0.0, 1.0, 2.0, -1.0, 0.0, 3.0];
+        let positions = [0.0, 1.0, 1.0, -1.0, 0.0, 1.0];
+        assert_eq!(trade_profits(&returns, &positions), vec![3.0, -1.0, 3.0]);
+    }
+
+    #[test]
+    fn test_flags_never_trading() {
+        let low = [2.0, 0.01, 0.0, 0.0];
+        let high = [20.0, 99.0, 5000.0, 5000.0];
+        // Huge thresholds keep the system out of the market
+        let params = [10.0, 50.0, 5000.0, 5000.0];
+        let diagnosis = diagnose("original", &params, &data(), &low, &high, &DiagnosticLimits::default());
+        assert_eq!(diagnosis.exposure, 0.0);
+        assert!(diagnosis.issues.contains(&Degeneracy::LowExposure(0.0)));
+        assert!(diagnosis.issues.contains(&Degeneracy::PinnedAtBound { param: 2, upper: true }));
+
+        let plan = diagnosis
+            .rerun_plan(1, 380, &low, &high, &[20.0, 99.0, f64::INFINITY, f64::INFINITY], &DiagnosticLimits::default())
+            .unwrap();
+        assert_eq!(plan.min_trades, 19);
+        assert_eq!(plan.high_bounds[2], 7500.0);
+    }
+
+    #[test]
+    fn test_active_system_is_clean() {
+        let low = [2.0, 0.01, 0.0, 0.0];
+        let high = [20.0, 99.0, 50.0, 50.0];
+        let params = [10.0, 50.0, 10.0, 10.0];
+        let diagnosis = diagnose("original", &params, &data(), &low, &high, &DiagnosticLimits::default());
+        assert!(diagnosis.exposure > 0.5);
+        assert!(diagnosis.n_trades > 1);
+        assert!(!diagnosis.is_degenerate(), "{:?}", diagnosis.issues);
+        assert!(diagnosis.rerun_plan(1, 380, &low, &high, &high, &DiagnosticLimits::default()).is_none());
+    }
+}
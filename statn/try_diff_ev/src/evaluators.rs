@@ -1,98 +1,292 @@
 use crate::io::MarketData;
+use crate::signals_generators::{hold_outside_session, Pacing};
 use crate::test_system::test_system;
+use crate::test_system_bollinger::test_system_bollinger;
+use crate::test_system_donchian::test_system_donchian;
 use crate::test_system_enhanced::test_system_enhanced;
+use crate::test_system_macd::test_system_macd;
+use crate::test_system_rsi::test_system_rsi;
+use backtesting::{period_returns, AccountingBasis};
+use statn::estimators::criteria::CompositeCriterion;
 use statn::estimators::StocBias;
 
-/// Criterion function for optimization
+/// Criterion function for optimization.
+///
+/// The total return is penalized by `criterion` for turnover (total absolute
+/// position change) and for having fewer than `mintrades` bars in the market.
+/// With the default `CompositeCriterion` this is the plain hard gate.
 pub fn criter(
     params: &[f64],
     mintrades: i32,
     data: &MarketData,
+    criterion: &CompositeCriterion,
     stoc_bias: &mut Option<&mut StocBias>,
 ) -> f64 {
-    let long_term = (params[0] + 1.0e-10) as usize;
-    let short_pct = params[1];
-    let short_thresh = params[2];
-    let long_thresh = params[3];
-
-    let (ret_val, ntrades) = if let Some(sb) = stoc_bias {
-        let returns = sb.returns_mut();
-        test_system(
-            &data.prices,
-            data.max_lookback,
-            long_term,
-            short_pct,
-            short_thresh,
-            long_thresh,
-            Some(returns),
-        )
-    } else {
-        test_system(
-            &data.prices,
-            data.max_lookback,
-            long_term,
-            short_pct,
-            short_thresh,
-            long_thresh,
-            None,
-        )
-    };
+    let returns = stoc_bias.as_mut().map(|sb| sb.returns_mut());
+    let (ret_val, ntrades, turnover) = evaluate(test_system, params, data, returns, None);
 
     if let Some(sb) = stoc_bias
         && ret_val > 0.0 {
             sb.process();
         }
 
-    if ntrades >= mintrades {
-        ret_val
-    } else {
-        -1.0e20
-    }
+    criterion.with_min_trades(mintrades).apply(ret_val, ntrades, turnover)
 }
 
-/// Criterion function for optimization (Enhanced Version)
+/// Criterion function for optimization (Enhanced Version), penalized as in `criter`
 pub fn criter_enhanced(
     params: &[f64],
     mintrades: i32,
     data: &MarketData,
+    criterion: &CompositeCriterion,
+    stoc_bias: &mut Option<&mut StocBias>,
+) -> f64 {
+    let returns = stoc_bias.as_mut().map(|sb| sb.returns_mut());
+    let (ret_val, ntrades, turnover) = evaluate(test_system_enhanced, params, data, returns, None);
+
+    if let Some(sb) = stoc_bias
+        && ret_val > 0.0 {
+            sb.process();
+        }
+
+    criterion.with_min_trades(mintrades).apply(ret_val, ntrades, turnover)
+}
+
+/// Criterion function for optimization (RSI mean reversion), penalized as in
+/// `criter`
+pub fn criter_rsi(
+    params: &[f64],
+    mintrades: i32,
+    data: &MarketData,
+    criterion: &CompositeCriterion,
     stoc_bias: &mut Option<&mut StocBias>,
 ) -> f64 {
-    let long_term = (params[0] + 1.0e-10) as usize;
-    let short_pct = params[1];
-    let short_thresh = params[2];
-    let long_thresh = params[3];
-
-    let (ret_val, ntrades) = if let Some(sb) = stoc_bias {
-        let returns = sb.returns_mut();
-        test_system_enhanced(
-            &data.prices,
-            data.max_lookback,
-            long_term,
-            short_pct,
-            short_thresh,
-            long_thresh,
-            Some(returns),
-        )
-    } else {
-        test_system_enhanced(
-            &data.prices,
-            data.max_lookback,
-            long_term,
-            short_pct,
-            short_thresh,
-            long_thresh,
-            None,
-        )
-    };
+    let returns = stoc_bias.as_mut().map(|sb| sb.returns_mut());
+    let (ret_val, ntrades, turnover) = evaluate(test_system_rsi, params, data, returns, None);
 
     if let Some(sb) = stoc_bias
         && ret_val > 0.0 {
             sb.process();
         }
 
-    if ntrades >= mintrades {
-        ret_val
-    } else {
-        -1.0e20
+    criterion.with_min_trades(mintrades).apply(ret_val, ntrades, turnover)
+}
+
+/// Criterion function for optimization (Bollinger-band breakout), penalized
+/// as in `criter`
+pub fn criter_bollinger(
+    params: &[f64],
+    mintrades: i32,
+    data: &MarketData,
+    criterion: &CompositeCriterion,
+    stoc_bias: &mut Option<&mut StocBias>,
+) -> f64 {
+    let returns = stoc_bias.as_mut().map(|sb| sb.returns_mut());
+    let (ret_val, ntrades, turnover) = evaluate(test_system_bollinger, params, data, returns, None);
+
+    if let Some(sb) = stoc_bias
+        && ret_val > 0.0 {
+            sb.process();
+        }
+
+    criterion.with_min_trades(mintrades).apply(ret_val, ntrades, turnover)
+}
+
+/// Criterion function for optimization (MACD signal-line crossover),
+/// penalized as in `criter`
+pub fn criter_macd(
+    params: &[f64],
+    mintrades: i32,
+    data: &MarketData,
+    criterion: &CompositeCriterion,
+    stoc_bias: &mut Option<&mut StocBias>,
+) -> f64 {
+    let returns = stoc_bias.as_mut().map(|sb| sb.returns_mut());
+    let (ret_val, ntrades, turnover) = evaluate(test_system_macd, params, data, returns, None);
+
+    if let Some(sb) = stoc_bias
+        && ret_val > 0.0 {
+            sb.process();
+        }
+
+    criterion.with_min_trades(mintrades).apply(ret_val, ntrades, turnover)
+}
+
+/// Criterion function for optimization (Donchian-channel breakout),
+/// penalized as in `criter`
+pub fn criter_donchian(
+    params: &[f64],
+    mintrades: i32,
+    data: &MarketData,
+    criterion: &CompositeCriterion,
+    stoc_bias: &mut Option<&mut StocBias>,
+) -> f64 {
+    let returns = stoc_bias.as_mut().map(|sb| sb.returns_mut());
+    let (ret_val, ntrades, turnover) = evaluate(test_system_donchian, params, data, returns, None);
+
+    if let Some(sb) = stoc_bias
+        && ret_val > 0.0 {
+            sb.process();
+        }
+
+    criterion.with_min_trades(mintrades).apply(ret_val, ntrades, turnover)
+}
+
+/// Test system of a signal generator, with the signature of `test_system`
+pub type TestSystem =
+    fn(&[f64], usize, usize, f64, f64, f64, Option<&mut [f64]>, Option<&mut [f64]>) -> (f64, i32, f64);
+
+/// Test system the optimizer runs for `generator`: "log_diff" or "enhanced"
+/// for the enhanced crossover, "rsi" for RSI mean reversion, "bollinger" for
+/// the Bollinger-band breakout, "macd" for the MACD crossover, "donchian" for
+/// the Donchian-channel breakout, anything else for the original crossover
+pub fn system_for(generator: &str) -> TestSystem {
+    match generator {
+        "log_diff" | "enhanced" => test_system_enhanced,
+        "rsi" => test_system_rsi,
+        "bollinger" => test_system_bollinger,
+        "macd" => test_system_macd,
+        "donchian" => test_system_donchian,
+        _ => test_system,
+    }
+}
+
+/// Run `system` on `data` with the four parameters of `params`, held to the
+/// `Pacing` of its fifth and sixth values if it has them. Positions change
+/// only on the bars `data.tradable` allows.
+///
+/// Returns what `test_system` returns, with the same `returns` and
+/// `positions` outputs.
+pub fn evaluate(
+    system: TestSystem,
+    params: &[f64],
+    data: &MarketData,
+    mut returns: Option<&mut [f64]>,
+    mut positions: Option<&mut [f64]>,
+) -> (f64, i32, f64) {
+    let lookback = (params[0] + 1.0e-10) as usize;
+    let pacing = Pacing::from_params(params);
+    if !pacing.is_active() && data.tradable.is_empty() {
+        return system(&data.prices, data.max_lookback, lookback, params[1], params[2], params[3], returns, positions);
+    }
+
+    let prices = &data.prices;
+    let mut desired = vec![0.0; prices.len() - data.max_lookback];
+    system(prices, data.max_lookback, lookback, params[1], params[2], params[3], None, Some(&mut desired));
+    let mut held = pacing.apply(&desired.iter().map(|&p| p as i32).collect::<Vec<i32>>());
+    if !data.tradable.is_empty() {
+        // Position k is decided at bar max_lookback - 1 + k
+        held = hold_outside_session(&held, &data.tradable[data.max_lookback - 1..]);
+    }
+
+    let (mut sum, mut ntrades, mut turnover, mut position) = (0.0, 0, 0.0, 0.0);
+    for (k, &pos) in held.iter().enumerate() {
+        let i = data.max_lookback - 1 + k;
+        let pos = pos as f64;
+        let ret = pos * (prices[i + 1] - prices[i]);
+        if pos != 0.0 {
+            ntrades += 1;
+        }
+        turnover += f64::abs(pos - position);
+        position = pos;
+        if let Some(slot) = returns.as_deref_mut().and_then(|r| r.get_mut(k)) {
+            *slot = ret;
+        }
+        if let Some(slot) = positions.as_deref_mut().and_then(|p| p.get_mut(k)) {
+            *slot = pos;
+        }
+        sum += ret;
+    }
+    (sum, ntrades, turnover)
+}
+
+/// Lower and upper bounds of the four parameters of `generator` in the
+/// optimizer. The crossovers search lookbacks up to `max_lookback` and
+/// thresholds up to `max_thresh`, as do MACD with its slow period, fast %
+/// and threshold and Donchian with its channel and threshold; RSI and
+/// Bollinger search periods up to `max_lookback` and fixed ranges of levels
+/// or band widths.
+pub fn param_bounds(generator: &str, max_lookback: usize, max_thresh: f64) -> (Vec<f64>, Vec<f64>) {
+    match generator {
+        "rsi" => (vec![2.0, 5.0, 50.0, 30.0], vec![max_lookback as f64, 50.0, 95.0, 70.0]),
+        "bollinger" => (vec![2.0, 0.0, 0.5, 0.5], vec![max_lookback as f64, 2.0, 4.0, 4.0]),
+        "macd" => (vec![3.0, 1.0, 2.0, 0.0], vec![max_lookback as f64, 99.0, 50.0, max_thresh]),
+        "donchian" => (vec![2.0, 1.0, 0.0, 0.0], vec![max_lookback as f64, 100.0, 5.0, max_thresh]),
+        _ => (vec![2.0, 0.01, 0.0, 0.0], vec![max_lookback as f64, 99.0, max_thresh, max_thresh]),
+    }
+}
+
+/// Log return of the system at each bar from `data.max_lookback` on, with the
+/// number of bars in the market. Return `k` is earned from bar
+/// `max_lookback + k - 1` to bar `max_lookback + k`, on the position taken at
+/// the close of the first.
+pub fn bar_returns(params: &[f64], data: &MarketData, generator: &str) -> (Vec<f64>, i32) {
+    let mut returns = vec![0.0; data.prices.len() - data.max_lookback];
+    let (_, ntrades, _) = evaluate(system_for(generator), params, data, Some(&mut returns), None);
+    (returns, ntrades)
+}
+
+/// Objectives of multi-objective optimization: mean log return per period of
+/// `basis` and the negated maximum drawdown of the cumulative log return
+/// over those periods, both to be maximized. Parameter sets with fewer than
+/// `mintrades` bars in the market are infeasible (NaN).
+pub fn return_drawdown(params: &[f64], mintrades: i32, data: &MarketData, generator: &str, basis: AccountingBasis) -> Vec<f64> {
+    let n = data.prices.len() - data.max_lookback;
+    let (mut bar_returns, mut positions) = (vec![0.0; n], vec![0.0; n]);
+    let (_, ntrades, _) = evaluate(system_for(generator), params, data, Some(&mut bar_returns), Some(&mut positions));
+    if ntrades < mintrades {
+        return vec![f64::NAN, f64::NAN];
+    }
+    // Return k ends at bar max_lookback + k
+    let dates = data.dates.get(data.max_lookback..).filter(|d| !d.is_empty());
+    let returns = period_returns(&bar_returns, &positions, dates, basis);
+    if returns.is_empty() {
+        return vec![0.0, 0.0];
+    }
+
+    let (mut equity, mut peak, mut max_drawdown) = (0.0, 0.0_f64, 0.0_f64);
+    for r in &returns {
+        equity += r;
+        peak = peak.max(equity);
+        max_drawdown = max_drawdown.max(peak - equity);
+    }
+    vec![equity / returns.len() as f64, -max_drawdown]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use statn::core::io::Provenance;
+
+    #[test]
+    fn test_return_drawdown() {
+        // Bar returns 1, 1, -2, -3, 1: long, long, long into the drop, short
+        // into the rebound, long
+        let prices = vec![0.0, 1.0, 2.0, 3.0, 1.0, 4.0, 5.0];
+        let data = MarketData { prices, volumes: Vec::new(), dates: Vec::new(), max_lookback: 2, provenance: Provenance::default(), tradable: Vec::new(), highs: Vec::new(), lows: Vec::new(), opens: Vec::new() };
+        let objectives = return_drawdown(&[2.0, 50.0, 0.0, 0.0], 1, &data, "original", AccountingBasis::Bar);
+        assert_eq!(objectives, vec![-0.4, -5.0]);
+        assert!(return_drawdown(&[2.0, 50.0, 0.0, 0.0], 100, &data, "original", AccountingBasis::Bar)[0].is_nan());
+        // Per trade: the long earns 0 and the short -3 before the final long's 1
+        let objectives = return_drawdown(&[2.0, 50.0, 0.0, 0.0], 1, &data, "original", AccountingBasis::Trade);
+        assert_eq!(objectives, vec![-2.0 / 3.0, -3.0]);
+        // Bars 4 and 5 on one day: the -2 and -3 earned into them are netted
+        let dated = MarketData { dates: ["1", "1", "2", "3", "4", "4", "5"].iter().map(|d| format!("2024010{}", d)).collect(), ..data.clone() };
+        let objectives = return_drawdown(&[2.0, 50.0, 0.0, 0.0], 1, &dated, "original", AccountingBasis::Daily);
+        assert_eq!(objectives, vec![-0.5, -5.0]);
+
+        // Held at least four bars, the long is never reversed
+        let (returns, ntrades) = bar_returns(&[2.0, 50.0, 0.0, 0.0, 4.0, 0.0], &data, "original");
+        assert_eq!((returns, ntrades), (vec![1.0, 1.0, -2.0, 3.0, 1.0], 5));
+        // With a two-bar cooldown the reversal closes the long and waits
+        let (returns, ntrades) = bar_returns(&[2.0, 50.0, 0.0, 0.0, 0.0, 2.0], &data, "original");
+        assert_eq!((returns, ntrades), (vec![1.0, 1.0, -2.0, 0.0, 0.0], 3));
+
+        // Outside the session at bars 1 and 4: flat until bar 2 and the
+        // reversal is skipped
+        let session = MarketData { tradable: vec![true, false, true, true, false, true, true], ..data.clone() };
+        let (returns, ntrades) = bar_returns(&[2.0, 50.0, 0.0, 0.0], &session, "original");
+        assert_eq!((returns, ntrades), (vec![0.0, 1.0, -2.0, 3.0, 1.0], 4));
+        assert_eq!(session.slice(1..4).tradable, vec![false, true, true]);
     }
 }
@@ -0,0 +1,329 @@
+//! Deterministic synthetic market data with known properties.
+//!
+//! Each fixture is a short price series drawn from a process whose
+//! parameters are known (a trend, a mean-reverting level, a random walk, or
+//! alternating regimes), labeled with those parameters and with the ranges
+//! its return statistics must fall in. The tests use them as ground truth,
+//! and `try_diff_ev gen-fixtures` writes them out so users can check an
+//! installation against the same numbers.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use statn::core::matlib::mwc256::Mwc256;
+
+/// Default number of bars of a fixture
+pub const DEFAULT_FIXTURE_BARS: usize = 1000;
+
+/// Default seed of the fixtures
+pub const DEFAULT_FIXTURE_SEED: u32 = 42;
+
+/// Standard deviation of the noise of each bar's log return
+const SIGMA: f64 = 0.01;
+
+/// Drift per bar of the trending fixture and of the regimes, in log return
+const DRIFT: f64 = 0.2 * SIGMA;
+
+/// Autoregressive coefficient of the mean-reverting log price
+const PHI: f64 = 0.8;
+
+/// Process a fixture is drawn from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureKind {
+    /// Random walk with a positive drift
+    Trending,
+    /// AR(1) log price around a fixed level
+    MeanReverting,
+    /// Random walk without drift
+    RandomWalk,
+    /// Alternating blocks of a calm uptrend and a volatile downtrend
+    RegimeSwitch,
+}
+
+impl FixtureKind {
+    pub const ALL: [FixtureKind; 4] =
+        [FixtureKind::Trending, FixtureKind::MeanReverting, FixtureKind::RandomWalk, FixtureKind::RegimeSwitch];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FixtureKind::Trending => "trending",
+            FixtureKind::MeanReverting => "mean-reverting",
+            FixtureKind::RandomWalk => "random-walk",
+            FixtureKind::RegimeSwitch => "regime-switch",
+        }
+    }
+}
+
+impl fmt::Display for FixtureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FixtureKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FixtureKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s.to_ascii_lowercase().replace('_', "-"))
+            .ok_or_else(|| {
+                format!("Unknown fixture '{}', expected trending, mean-reverting, random-walk or regime-switch", s)
+            })
+    }
+}
+
+/// A return statistic of a fixture with the range it must fall in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricRange {
+    pub name: String,
+    pub low: f64,
+    pub high: f64,
+    /// Value measured on the generated series
+    pub value: f64,
+}
+
+impl MetricRange {
+    pub fn holds(&self) -> bool {
+        self.value >= self.low && self.value <= self.high
+    }
+}
+
+/// A block of bars in one regime, `start..end`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Regime {
+    pub start: usize,
+    pub end: usize,
+    pub label: String,
+}
+
+/// A generated series with its ground truth
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fixture {
+    pub kind: String,
+    pub seed: u32,
+    pub bars: usize,
+    /// Name of the market data file `gen-fixtures` writes
+    pub file: String,
+    /// Parameters of the process, per bar of log price
+    pub truth: BTreeMap<String, f64>,
+    /// Regimes of the regime-switch fixture, empty for the others
+    pub regimes: Vec<Regime>,
+    pub expected: Vec<MetricRange>,
+    /// Log prices, as `load_market_data` returns them
+    #[serde(skip)]
+    pub prices: Vec<f64>,
+    #[serde(skip)]
+    pub dates: Vec<String>,
+}
+
+impl Fixture {
+    /// Expected ranges the measured values fall outside of
+    pub fn failures(&self) -> Vec<&MetricRange> {
+        self.expected.iter().filter(|m| !m.holds()).collect()
+    }
+
+    /// Market data file contents: one `YYYYMMDD price` line per bar
+    pub fn to_market_file(&self) -> String {
+        self.dates.iter().zip(&self.prices).map(|(date, p)| format!("{} {:.6}\n", date, p.exp())).collect()
+    }
+}
+
+/// Generate a fixture of `bars` bars (at least 40) from `seed`.
+///
+/// The same kind, length and seed always give the same series. Prices start
+/// at 100 on 2020-01-01 with one bar a day.
+pub fn generate_fixture(kind: FixtureKind, bars: usize, seed: u32) -> Result<Fixture, String> {
+    if bars < 40 {
+        return Err(format!("A fixture needs at least 40 bars, got {}", bars));
+    }
+    let mut rng = Mwc256::with_seed(seed);
+    let start = 100.0_f64.ln();
+    let mut prices = Vec::with_capacity(bars);
+    let mut regimes = Vec::new();
+    let truth: Vec<(&str, f64)>;
+
+    match kind {
+        FixtureKind::Trending | FixtureKind::RandomWalk => {
+            let drift = if kind == FixtureKind::Trending { DRIFT } else { 0.0 };
+            prices.push(start);
+            for i in 1..bars {
+                prices.push(prices[i - 1] + drift + SIGMA * rng.normal());
+            }
+            truth = vec![("drift", drift), ("volatility", SIGMA)];
+        }
+        FixtureKind::MeanReverting => {
+            let mut deviation = 0.0;
+            for _ in 0..bars {
+                prices.push(start + deviation);
+                deviation = PHI * deviation + SIGMA * rng.normal();
+            }
+            truth = vec![("level", start), ("phi", PHI), ("half_life", 0.5_f64.ln() / PHI.ln()), ("noise", SIGMA)];
+        }
+        FixtureKind::RegimeSwitch => {
+            let block = bars / 4;
+            prices.push(start);
+            for i in 1..bars {
+                let (drift, sigma) = if (i / block).is_multiple_of(2) { (DRIFT, SIGMA) } else { (-DRIFT, 2.0 * SIGMA) };
+                prices.push(prices[i - 1] + drift + sigma * rng.normal());
+            }
+            for (k, start) in (0..bars).step_by(block).enumerate() {
+                let label = if k.is_multiple_of(2) { "calm-up" } else { "volatile-down" };
+                regimes.push(Regime { start, end: (start + block).min(bars), label: label.to_string() });
+            }
+            truth = vec![
+                ("calm_drift", DRIFT),
+                ("calm_volatility", SIGMA),
+                ("volatile_drift", -DRIFT),
+                ("volatile_volatility", 2.0 * SIGMA),
+                ("block", block as f64),
+            ];
+        }
+    }
+
+    let returns: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+    let n = returns.len() as f64;
+    // Ranges reach 4 standard errors either side at the default length
+    let se = |scale: f64| 4.0 * scale * (DEFAULT_FIXTURE_BARS as f64 / n).sqrt();
+    let range = |name: &str, low: f64, high: f64, value: f64| MetricRange { name: name.to_string(), low, high, value };
+    let expected = match kind {
+        FixtureKind::Trending | FixtureKind::RandomWalk => {
+            let drift = if kind == FixtureKind::Trending { DRIFT } else { 0.0 };
+            vec![
+                range("mean_return", drift - se(SIGMA / 31.6), drift + se(SIGMA / 31.6), mean(&returns)),
+                range("volatility", SIGMA * (1.0 - se(0.0224)), SIGMA * (1.0 + se(0.0224)), std_dev(&returns)),
+                range("autocorrelation", -se(0.0316), se(0.0316), autocorrelation(&returns)),
+                range("variance_ratio_10", 1.0 - se(0.107), 1.0 + se(0.107), variance_ratio(&returns, 10)),
+            ]
+        }
+        FixtureKind::MeanReverting => {
+            // Returns of an AR(1) level: lag-1 autocorrelation -(1 - phi) / 2,
+            // variance ratio (1 - phi^q) / (q (1 - phi))
+            let ac = -(1.0 - PHI) / 2.0;
+            let vr = (1.0 - PHI.powi(10)) / (10.0 * (1.0 - PHI));
+            vec![
+                range("autocorrelation", ac - se(0.0316), ac + se(0.0316), autocorrelation(&returns)),
+                range("variance_ratio_10", vr - se(0.06), vr + se(0.06), variance_ratio(&returns, 10)),
+                range("total_return", -0.1, 0.1, prices[bars - 1] - prices[0]),
+            ]
+        }
+        FixtureKind::RegimeSwitch => {
+            let (calm, volatile): (Vec<f64>, Vec<f64>) = {
+                let (mut calm, mut volatile) = (Vec::new(), Vec::new());
+                for (i, &r) in returns.iter().enumerate() {
+                    if ((i + 1) / (bars / 4)).is_multiple_of(2) { calm.push(r) } else { volatile.push(r) }
+                }
+                (calm, volatile)
+            };
+            let ratio = std_dev(&volatile) / std_dev(&calm);
+            vec![
+                range("volatility_ratio", 2.0 * (1.0 - se(0.045)), 2.0 * (1.0 + se(0.045)), ratio),
+                range("calm_mean_return", DRIFT - se(SIGMA / 22.4), DRIFT + se(SIGMA / 22.4), mean(&calm)),
+                range("volatile_mean_return", -DRIFT - se(SIGMA / 11.2), -DRIFT + se(SIGMA / 11.2), mean(&volatile)),
+            ]
+        }
+    };
+
+    Ok(Fixture {
+        kind: kind.name().to_string(),
+        seed,
+        bars,
+        file: format!("{}.txt", kind.name()),
+        truth: truth.into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+        regimes,
+        expected,
+        prices,
+        dates: daily_dates(2020, 1, 1, bars),
+    })
+}
+
+/// TOML describing `fixtures`: for each, its file, process parameters,
+/// regimes and expected ranges with the measured values
+pub fn fixtures_manifest(fixtures: &[Fixture]) -> Result<String, String> {
+    #[derive(Serialize)]
+    struct Manifest<'a> {
+        fixture: &'a [Fixture],
+    }
+    toml::to_string(&Manifest { fixture: fixtures }).map_err(|e| format!("Cannot write the fixtures manifest: {}", e))
+}
+
+fn mean(x: &[f64]) -> f64 {
+    x.iter().sum::<f64>() / x.len() as f64
+}
+
+fn std_dev(x: &[f64]) -> f64 {
+    let m = mean(x);
+    (x.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (x.len() - 1) as f64).sqrt()
+}
+
+/// Lag-1 autocorrelation
+fn autocorrelation(x: &[f64]) -> f64 {
+    let m = mean(x);
+    let cov: f64 = x.windows(2).map(|w| (w[0] - m) * (w[1] - m)).sum();
+    cov / x.iter().map(|v| (v - m).powi(2)).sum::<f64>()
+}
+
+/// Variance of overlapping `q`-bar returns over `q` times that of one bar
+fn variance_ratio(x: &[f64], q: usize) -> f64 {
+    let m = mean(x);
+    let var1 = x.iter().map(|v| (v - m).powi(2)).sum::<f64>() / x.len() as f64;
+    let sums: Vec<f64> = x.windows(q).map(|w| w.iter().sum::<f64>() - q as f64 * m).collect();
+    let var_q = sums.iter().map(|s| s * s).sum::<f64>() / sums.len() as f64;
+    var_q / (q as f64 * var1)
+}
+
+/// `count` consecutive calendar days as YYYYMMDD
+fn daily_dates(mut year: u32, mut month: u32, mut day: u32, count: usize) -> Vec<String> {
+    let mut dates = Vec::with_capacity(count);
+    for _ in 0..count {
+        dates.push(format!("{:04}{:02}{:02}", year, month, day));
+        let leap = (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+        let days = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        day += 1;
+        if day > days {
+            day = 1;
+            month += 1;
+            if month > 12 {
+                month = 1;
+                year += 1;
+            }
+        }
+    }
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_meet_expectations() {
+        for kind in FixtureKind::ALL {
+            for seed in [1, 42, 1234] {
+                let fixture = generate_fixture(kind, DEFAULT_FIXTURE_BARS, seed).unwrap();
+                assert!(fixture.failures().is_empty(), "{} seed {}: {:?}", kind, seed, fixture.failures());
+                assert_eq!(fixture.prices.len(), DEFAULT_FIXTURE_BARS);
+            }
+            assert_eq!(generate_fixture(kind, 200, 7), generate_fixture(kind, 200, 7));
+            assert_eq!(kind.name().parse::<FixtureKind>(), Ok(kind));
+        }
+        let regimes = generate_fixture(FixtureKind::RegimeSwitch, 1000, 1).unwrap().regimes;
+        assert_eq!(regimes.len(), 4);
+        assert_eq!((regimes[1].start, regimes[1].end, regimes[1].label.as_str()), (250, 500, "volatile-down"));
+        assert!(generate_fixture(FixtureKind::Trending, 10, 1).is_err());
+
+        let fixture = generate_fixture(FixtureKind::MeanReverting, 40, 1).unwrap();
+        let manifest = fixtures_manifest(std::slice::from_ref(&fixture)).unwrap();
+        assert!(manifest.contains("file = \"mean-reverting.txt\"") && manifest.contains("phi = 0.8"));
+        assert!(manifest.contains("name = \"autocorrelation\""));
+        assert!(fixture.to_market_file().starts_with("20200101 100.000000\n"));
+        assert_eq!(daily_dates(2020, 2, 28, 3), vec!["20200228", "20200229", "20200301"]);
+    }
+}
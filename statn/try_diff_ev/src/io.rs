@@ -3,20 +3,180 @@
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::ops::Range;
+use std::str::FromStr;
+
+use statn::core::io::{Provenance, SessionFilter};
 
 /// Market data structure.
 #[derive(Debug, Clone)]
 pub struct MarketData {
     /// Price series (in log space)
     pub prices: Vec<f64>,
+    /// Volume for each price, read from a `Volume` header column; empty if
+    /// the file has none
+    pub volumes: Vec<f64>,
+    /// Date or time of each price, the first field of its line; empty if the
+    /// price is the first column
+    pub dates: Vec<String>,
     /// Maximum lookback period
     pub max_lookback: usize,
+    /// Source file and hash of the prices, written with every result
+    pub provenance: Provenance,
+    /// Whether each bar is inside the trading session; empty if every bar is
+    pub tradable: Vec<bool>,
+    /// High and low of each bar (in log space), read from `High` and `Low`
+    /// header columns when the price column is `Close`; empty otherwise
+    pub highs: Vec<f64>,
+    pub lows: Vec<f64>,
+    /// Open of each bar (in log space), read from an `Open` header column
+    /// along with the highs and lows; empty otherwise
+    pub opens: Vec<f64>,
+}
+
+impl MarketData {
+    /// Prices (and their volumes and dates) in `range`, with the provenance
+    /// of that part of the data
+    pub fn slice(&self, range: Range<usize>) -> MarketData {
+        let prices = self.prices[range.clone()].to_vec();
+        let dates = self.dates.get(range.clone()).unwrap_or_default().to_vec();
+        let provenance = Provenance::of_series(&prices, &dates, &self.provenance.source)
+            .with_symbol(&self.provenance.symbol);
+        MarketData {
+            prices,
+            volumes: self.volumes.get(range.clone()).unwrap_or_default().to_vec(),
+            dates,
+            max_lookback: self.max_lookback,
+            provenance,
+            tradable: self.tradable.get(range.clone()).unwrap_or_default().to_vec(),
+            highs: self.highs.get(range.clone()).unwrap_or_default().to_vec(),
+            lows: self.lows.get(range.clone()).unwrap_or_default().to_vec(),
+            opens: self.opens.get(range).unwrap_or_default().to_vec(),
+        }
+    }
+
+    /// Restrict trading to the bars `session` allows, read from the dates.
+    /// An inactive filter leaves every bar tradable.
+    pub fn restrict_to_session(&mut self, session: &SessionFilter) -> Result<(), String> {
+        if !session.is_active() {
+            self.tradable.clear();
+            return Ok(());
+        }
+        if self.dates.len() != self.prices.len() {
+            return Err("A trading session needs a date and time field on every line of the market data".to_string());
+        }
+        self.tradable = session.mask(&self.dates)?;
+        Ok(())
+    }
 }
 
-/// Load market data from a file.
+/// Column of a delimited market data file, by header name or 0-based index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Empty column name".to_string());
+        }
+        Ok(match s.parse::<usize>() {
+            Ok(index) => Column::Index(index),
+            Err(_) => Column::Name(s.to_string()),
+        })
+    }
+}
+
+/// Layout of a market data file.
 ///
-/// Expected format: YYYYMMDD price1 price2 price3 price4
-/// The last column is used as the closing price.
+/// The default detects everything: the delimiter from the first line, whether
+/// that line is a header, and the price column.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MarketDataFormat {
+    /// Field delimiter. If `None`, the first of ',', ';', tab and '|' found in
+    /// the first line is used, and whitespace if there is none. A space means
+    /// any run of whitespace.
+    pub delimiter: Option<char>,
+    /// Column holding the price. If `None`, the first of "Adj Close", "Close"
+    /// and "Price" in the header (ignoring case), or the last column if there
+    /// is no header.
+    pub price_column: Option<Column>,
+}
+
+/// Header names tried, in order, when no price column is given
+const PRICE_COLUMNS: [&str; 3] = ["adj close", "close", "price"];
+
+/// Delimiters recognized by detection, in order of preference
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+fn detect_delimiter(line: &str) -> char {
+    DELIMITERS.iter().copied().find(|&d| line.contains(d)).unwrap_or(' ')
+}
+
+fn split_fields(line: &str, delimiter: char) -> Vec<&str> {
+    if delimiter == ' ' {
+        line.split_whitespace().collect()
+    } else {
+        line.split(delimiter).map(|f| f.trim().trim_matches('"')).collect()
+    }
+}
+
+/// Index of the price column given the first line of the file, and whether
+/// that line is a header
+fn resolve_price_column(first: &[&str], column: Option<&Column>) -> Result<(usize, bool), String> {
+    // A header is a first line without a single numeric field
+    let is_header = first.iter().all(|f| f.parse::<f64>().is_err());
+
+    let index = match column {
+        Some(Column::Index(index)) => *index,
+        Some(Column::Name(name)) => {
+            if !is_header {
+                return Err(format!("Price column '{}' given by name but the file has no header", name));
+            }
+            first
+                .iter()
+                .position(|f| f.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("No column '{}' in header: {}", name, first.join(", ")))?
+        }
+        None if is_header => PRICE_COLUMNS
+            .iter()
+            .find_map(|name| first.iter().position(|f| f.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| format!("No price column found in header: {}", first.join(", ")))?,
+        None => first.len().saturating_sub(1),
+    };
+
+    if index >= first.len() {
+        return Err(format!(
+            "Price column {} out of range: first line has {} columns",
+            index,
+            first.len()
+        ));
+    }
+    Ok((index, is_header))
+}
+
+/// Delimiter and columns of a market data file, resolved from its first line
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    delimiter: char,
+    price_col: usize,
+    volume_col: Option<usize>,
+    /// High and low columns
+    range_cols: Option<(usize, usize)>,
+    /// Open column, read only with the range
+    open_col: Option<usize>,
+}
+
+/// Load market data from a file, detecting its format.
+///
+/// Reads both the legacy whitespace format (YYYYMMDD price1 ... priceN, last
+/// column used as the close) and delimited exports with a header such as
+/// Date,Open,High,Low,Close,Volume. See `MarketDataFormat` for the detection.
 ///
 /// # Arguments
 /// * `path` - Path to the market data file
@@ -27,30 +187,93 @@ pub struct MarketData {
 pub fn load_market_data<P: AsRef<Path>>(
     path: P,
     max_lookback: usize,
+) -> Result<MarketData, String> {
+    load_market_data_with_format(path, max_lookback, &MarketDataFormat::default())
+}
+
+/// Load market data from a file with an explicit delimiter and/or price column.
+///
+/// Lines whose price does not parse as a positive number (such as "null" rows
+/// in some exports) are skipped.
+pub fn load_market_data_with_format<P: AsRef<Path>>(
+    path: P,
+    max_lookback: usize,
+    format: &MarketDataFormat,
 ) -> Result<MarketData, String> {
     let file = File::open(path.as_ref())
         .map_err(|e| format!("Cannot open market file '{}': {}", path.as_ref().display(), e))?;
     
     let reader = io::BufReader::new(file);
     let mut prices = Vec::new();
+    let mut volumes = Vec::new();
+    let mut dates = Vec::new();
+    let mut highs = Vec::new();
+    let mut lows = Vec::new();
+    let mut opens = Vec::new();
+    let mut layout: Option<Layout> = None;
     
     for (line_num, line) in reader.lines().enumerate() {
         let line = line.map_err(|e| format!("Error reading line {}: {}", line_num + 1, e))?;
+        // Strip a byte order mark some spreadsheet exports start with
+        let line = line.trim_start_matches('\u{feff}');
         
         // Skip empty lines
         if line.trim().is_empty() {
             continue;
         }
         
-        // Parse line: YYYYMMDD price1 price2 price3 price4
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            // Take the last column as the close price
-            if let Ok(price) = parts[parts.len() - 1].parse::<f64>()
-                && price > 0.0 {
-                    prices.push(price.ln()); // Store in log space
+        let Layout { delimiter, price_col, volume_col, range_cols, open_col } = match layout {
+            Some(layout) => layout,
+            None => {
+                let delimiter = format.delimiter.unwrap_or_else(|| detect_delimiter(line));
+                let first = split_fields(line, delimiter);
+                let (price_col, is_header) = resolve_price_column(&first, format.price_column.as_ref())?;
+                let column = |name: &str| first.iter().position(|f| f.eq_ignore_ascii_case(name));
+                let volume_col = if is_header { column("volume") } else { None };
+                // The range of adjusted prices is not in the file
+                let range_cols = if is_header && first[price_col].eq_ignore_ascii_case("close") {
+                    column("high").zip(column("low"))
+                } else {
+                    None
+                };
+                let open_col = range_cols.and(column("open"));
+                let resolved = Layout { delimiter, price_col, volume_col, range_cols, open_col };
+                layout = Some(resolved);
+                if is_header {
+                    continue;
                 }
-        }
+                resolved
+            }
+        };
+        
+        let parts = split_fields(line, delimiter);
+        if parts.len() >= 2
+            && let Some(field) = parts.get(price_col)
+            && let Ok(price) = field.parse::<f64>()
+            && price > 0.0 {
+                prices.push(price.ln()); // Store in log space
+                if price_col > 0 {
+                    dates.push(parts[0].to_string());
+                }
+                if let Some(volume_col) = volume_col {
+                    // A missing or invalid volume counts as unknown
+                    volumes.push(parts.get(volume_col).and_then(|v| v.parse().ok()).unwrap_or(0.0));
+                }
+                if let Some((high_col, low_col)) = range_cols {
+                    // A missing or invalid high or low is the price itself
+                    let extreme = |col: usize, bound: fn(f64, f64) -> f64| {
+                        parts.get(col).and_then(|v| v.parse::<f64>().ok()).filter(|&v| v > 0.0).map_or(price, |v| bound(v, price))
+                    };
+                    let (high, low) = (extreme(high_col, f64::max), extreme(low_col, f64::min));
+                    highs.push(high.ln());
+                    lows.push(low.ln());
+                    if let Some(open_col) = open_col {
+                        // Likewise for the open, kept within the range
+                        let open = parts.get(open_col).and_then(|v| v.parse::<f64>().ok()).filter(|&v| v > 0.0).unwrap_or(price);
+                        opens.push(open.clamp(low, high).ln());
+                    }
+                }
+            }
     }
     
     if prices.is_empty() {
@@ -65,9 +288,17 @@ pub fn load_market_data<P: AsRef<Path>>(
         ));
     }
     
+    let provenance = Provenance::of_series(&prices, &dates, &path.as_ref().display().to_string());
     Ok(MarketData {
         prices,
+        volumes,
+        dates,
         max_lookback,
+        provenance,
+        tradable: Vec::new(),
+        highs,
+        lows,
+        opens,
     })
 }
 
@@ -95,7 +326,8 @@ pub fn load_parameters<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, String> {
         let line = line.map_err(|e| format!("Error reading line {}: {}", line_num + 1, e))?;
         
         let trimmed = line.trim();
-        if !trimmed.is_empty() {
+        // Comments, such as the provenance line, are skipped
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
             let val = trimmed
                 .parse::<f64>()
                 .map_err(|e| format!("Parse error on line {}: {}", line_num + 1, e))?;
@@ -122,8 +354,21 @@ pub fn load_parameters<P: AsRef<Path>>(path: P) -> Result<Vec<f64>, String> {
 /// # Returns
 /// Result indicating success or error
 pub fn save_parameters<P: AsRef<Path>>(path: P, params: &[f64]) -> Result<(), String> {
+    save_parameters_with_provenance(path, params, None)
+}
+
+/// Save trading parameters after a comment line with the provenance of the
+/// data they were fitted on, if given
+pub fn save_parameters_with_provenance<P: AsRef<Path>>(
+    path: P,
+    params: &[f64],
+    provenance: Option<&Provenance>,
+) -> Result<(), String> {
     let mut buffer = String::new();
     use std::fmt::Write;
+    if let Some(provenance) = provenance {
+        writeln!(buffer, "{}", provenance.comment()).map_err(|e| format!("Write error: {}", e))?;
+    }
     for param in params {
         writeln!(buffer, "{}", param)
             .map_err(|e| format!("Write error: {}", e))?;
@@ -153,6 +398,68 @@ mod tests {
         assert_eq!(params[1], 57.8);
     }
     
+    fn write_lines(lines: &[&str]) -> NamedTempFile {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        for line in lines {
+            writeln!(temp_file, "{}", line).unwrap();
+        }
+        temp_file
+    }
+
+    #[test]
+    fn test_load_legacy_format() {
+        let file = write_lines(&["20200101 100.0 105.0 95.0 102.5", "", "20200102 102.5 108.0 100.0 106.0"]);
+        let data = load_market_data(file.path(), 1).unwrap();
+        assert_eq!(data.prices, vec![102.5_f64.ln(), 106.0_f64.ln()]);
+        assert!(data.volumes.is_empty());
+        assert_eq!(data.dates, vec!["20200101", "20200102"]);
+    }
+
+    #[test]
+    fn test_load_csv_with_header() {
+        // Yahoo style: detected comma delimiter, "Adj Close" preferred, null rows skipped
+        let file = write_lines(&[
+            "Date,Open,High,Low,Close,Adj Close,Volume",
+            "2020-01-02,10,11,9,10.5,10.0,1000",
+            "2020-01-03,null,null,null,null,null,null",
+            "2020-01-06,10.5,12,10,11.5,11.0,1200",
+        ]);
+        let data = load_market_data(file.path(), 1).unwrap();
+        assert_eq!(data.prices, vec![10.0_f64.ln(), 11.0_f64.ln()]);
+        assert_eq!(data.volumes, vec![1000.0, 1200.0]);
+        assert_eq!(data.dates, vec!["2020-01-02", "2020-01-06"]);
+        assert!(data.highs.is_empty() && data.lows.is_empty());
+
+        let format = MarketDataFormat { price_column: Some("close".parse().unwrap()), ..Default::default() };
+        let data = load_market_data_with_format(file.path(), 1, &format).unwrap();
+        assert_eq!(data.prices, vec![10.5_f64.ln(), 11.5_f64.ln()]);
+        assert_eq!(data.highs, vec![11.0_f64.ln(), 12.0_f64.ln()]);
+        assert_eq!(data.lows, vec![9.0_f64.ln(), 10.0_f64.ln()]);
+        assert_eq!(data.opens, vec![10.0_f64.ln(), 10.5_f64.ln()]);
+    }
+
+    #[test]
+    fn test_load_headerless_by_index() {
+        // Binance kline style: no header, close in column 4
+        let file = write_lines(&[
+            "1577836800000;7195.2;7255.0;7175.1;7200.8;1000;0",
+            "1577923200000;7200.7;7212.5;6924.7;6965.7;1200;0",
+        ]);
+        let format = MarketDataFormat { price_column: Some(Column::Index(4)), ..Default::default() };
+        let data = load_market_data_with_format(file.path(), 1, &format).unwrap();
+        assert_eq!(data.prices, vec![7200.8_f64.ln(), 6965.7_f64.ln()]);
+
+        let by_name = MarketDataFormat { price_column: Some(Column::Name("Close".to_string())), ..Default::default() };
+        assert!(load_market_data_with_format(file.path(), 1, &by_name).is_err());
+    }
+
+    #[test]
+    fn test_missing_price_column() {
+        let file = write_lines(&["Date\tOpen\tVolume", "2020-01-02\t10\t100"]);
+        let err = load_market_data(file.path(), 1).unwrap_err();
+        assert!(err.contains("No price column"));
+    }
+
     #[test]
     fn test_save_parameters() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -163,4 +470,22 @@ mod tests {
         let loaded = load_parameters(temp_file.path()).unwrap();
         assert_eq!(loaded, params);
     }
+
+    #[test]
+    fn test_provenance_follows_data() {
+        let file = write_lines(&["20200101 100.0", "20200102 101.0", "20200103 102.0"]);
+        let data = load_market_data(file.path(), 1).unwrap();
+        assert_eq!((data.provenance.first.as_str(), data.provenance.last.as_str()), ("20200101", "20200103"));
+
+        let head = data.slice(0..2);
+        assert_eq!((head.provenance.count, head.provenance.last.as_str()), (2, "20200102"));
+        assert_eq!(head.provenance.source, data.provenance.source);
+        assert_ne!(head.provenance.hash, data.provenance.hash);
+
+        // Parameters keep the provenance of the data they were fitted on
+        let params_file = NamedTempFile::new().unwrap();
+        save_parameters_with_provenance(params_file.path(), &[6.0, 57.8, 30.1, 0.0], Some(&head.provenance)).unwrap();
+        assert_eq!(load_parameters(params_file.path()).unwrap().len(), 4);
+        assert_eq!(statn::core::io::read_provenance(params_file.path()), Some(head.provenance));
+    }
 }
@@ -7,24 +7,63 @@
 //!
 //! - `config` - Configuration structures using serde
 //! - `io` - File I/O utilities for loading/saving data
+//! - `diagnostics` - Checks for degenerate optimization results
 //! - `signals` - Generate BUY/SELL/HOLD signals from price data
 //! - `backtest` - Simulate trading with transaction costs and track performance
-//! - `visualization` - Create charts showing price and trading signals
+//! - `portfolio` - Combine the out-of-sample returns of several optimized
+//!   systems into one book
+//! - `stress` - Re-backtest perturbed parameters as a health check
+//! - `selftest` - Run ticks through resampling, signals, backtest, MCPT and
+//!   bootstrap, checking the invariants of each stage
+//! - `visualization` - Create charts showing price and trading signals, and
+//!   parameter sensitivity curves (`plotting` feature, on by default)
 
 pub mod backtest;
 pub mod config;
+pub mod diagnostics;
 pub mod evaluators;
+pub mod fixtures;
 pub mod io;
+pub mod portfolio;
+pub mod scenarios;
+pub mod selftest;
 pub mod signals_generators;
+pub mod stress;
 pub mod test_system;
+pub mod test_system_bollinger;
+pub mod test_system_donchian;
 pub mod test_system_enhanced;
+pub mod test_system_macd;
+pub mod test_system_rsi;
+#[cfg(feature = "plotting")]
 pub mod visualization;
 
 // Re-export commonly used types and functions
-pub use backtest::{backtest_signals, TradeStats};
+pub use backtest::{
+    anonymize, AccountingBasis, audit_determinism, backtest_book, backtest_signals, backtest_signals_with, backtest_signals_with_slippage,
+    outlier_analysis, read_fills, reconcile, Allocation, AnonymizedSeries, AuditReport, BacktestOptions, BookReport, ExitReason, FeeSchedule, FillModel, Reconciliation, Slippage, StopLevel, Stops,
+    OutlierReport, TradeStats, BOOTSTRAP_REPS, BOOTSTRAP_SEED, DEFAULT_ANCHOR,
+};
 pub use config::Config;
-pub use evaluators::{criter, criter_enhanced};
-pub use io::{load_market_data, load_parameters, save_parameters, MarketData};
-pub use signals_generators::{generate_signals, SignalResult};
+pub use diagnostics::{diagnose, DiagnosticLimits, Diagnosis};
+pub use evaluators::{
+    bar_returns, criter, criter_bollinger, criter_donchian, criter_enhanced, criter_macd, criter_rsi, evaluate, param_bounds, return_drawdown, system_for, TestSystem,
+};
+pub use fixtures::{fixtures_manifest, generate_fixture, Fixture, FixtureKind, MetricRange, DEFAULT_FIXTURE_BARS, DEFAULT_FIXTURE_SEED};
+pub use io::{
+    load_market_data, load_market_data_with_format, load_parameters, save_parameters,
+    save_parameters_with_provenance, Column, MarketData, MarketDataFormat,
+};
+pub use portfolio::{align_returns, oos_returns, SystemSpec};
+pub use scenarios::{read_scenarios, run_scenarios, scenarios_to_csv, scenarios_to_markdown, Scenario, ScenarioResult};
+pub use signals_generators::{
+    backtest_as_positions, generate_signals, hold_outside_session, BollingerBreakout, BollingerParams, CrossoverParams, DonchianBreakout, DonchianParams, GeneratorRegistry, LogDiffCrossover, MacdCrossover, MacdParams, Paced, Pacing, RatioCrossover, RsiParams, RsiReversion, SignalGenerator,
+    SignalResult,
+};
+pub use selftest::{bundled_ticks, read_ticks, resample, run_self_test, Check, SelfTestReport, Tick, TickBar};
+pub use stress::{
+    param_columns, param_names, stress_params, StressOptions, StressReport, BOLLINGER_PARAM_NAMES, DONCHIAN_PARAM_NAMES, MACD_PARAM_NAMES, PACING_PARAM_NAMES, PARAM_NAMES, RSI_PARAM_NAMES,
+};
 pub use test_system_enhanced::test_system_enhanced;
-pub use visualization::visualise_signals;
+#[cfg(feature = "plotting")]
+pub use visualization::{plot_sensitivity, visualise_signals};
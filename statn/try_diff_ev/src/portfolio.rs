@@ -0,0 +1,112 @@
+//! Out-of-sample returns of several optimized systems, aligned on common
+//! bars for `backtest_book`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::evaluators::bar_returns;
+use crate::io::MarketData;
+
+/// An optimized system: a parameters file and the market data it trades
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemSpec {
+    pub params_file: PathBuf,
+    pub data_file: PathBuf,
+}
+
+impl FromStr for SystemSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((params, data)) if !params.is_empty() && !data.is_empty() => {
+                Ok(SystemSpec { params_file: params.into(), data_file: data.into() })
+            }
+            _ => Err(format!("Expected PARAMS_FILE=DATA_FILE, got '{}'", s)),
+        }
+    }
+}
+
+/// Simple return of the system at each bar after the first `train_pct` of
+/// the data, with the date of each bar (empty if the data has no dates).
+///
+/// Only returns on positions decided in the held-out part count, as in the
+/// predict command's backtest.
+pub fn oos_returns(params: &[f64], data: &MarketData, generator: &str, train_pct: f64) -> (Vec<String>, Vec<f64>) {
+    let (returns, _) = bar_returns(params, data, generator);
+    let split = (data.prices.len() as f64 * train_pct) as usize;
+    // Return k is decided at bar max_lookback + k - 1
+    let first = (split + 1).saturating_sub(data.max_lookback).min(returns.len());
+    let dates = if data.dates.len() == data.prices.len() {
+        (first..returns.len()).map(|k| data.dates[data.max_lookback + k].clone()).collect()
+    } else {
+        Vec::new()
+    };
+    (dates, returns[first..].iter().map(|r| r.exp_m1()).collect())
+}
+
+/// Returns of several systems on the bars they have in common.
+///
+/// If every series has dates, the bars are matched by date, in the order of
+/// the first series; otherwise the series are aligned on their last bar and
+/// cut to the shortest. The dates of the common bars come back with them
+/// (empty when aligned by position).
+pub fn align_returns(series: &[(Vec<String>, Vec<f64>)]) -> Result<(Vec<String>, Vec<Vec<f64>>), String> {
+    if series.is_empty() {
+        return Err("No return series to align".to_string());
+    }
+    let (aligned_dates, aligned) = if series.iter().all(|(dates, returns)| !dates.is_empty() && dates.len() == returns.len()) {
+        let lookups: Vec<HashMap<&str, f64>> = series
+            .iter()
+            .map(|(dates, returns)| dates.iter().map(String::as_str).zip(returns.iter().copied()).collect())
+            .collect();
+        let common: Vec<&String> =
+            series[0].0.iter().filter(|d| lookups.iter().all(|lookup| lookup.contains_key(d.as_str()))).collect();
+        let aligned = lookups.iter().map(|lookup| common.iter().map(|d| lookup[d.as_str()]).collect()).collect();
+        (common.into_iter().cloned().collect(), aligned)
+    } else {
+        let n = series.iter().map(|(_, returns)| returns.len()).min().unwrap_or(0);
+        (Vec::new(), series.iter().map(|(_, returns)| returns[returns.len() - n..].to_vec()).collect::<Vec<Vec<f64>>>())
+    };
+    if aligned[0].is_empty() {
+        return Err("The systems have no bars in common".to_string());
+    }
+    Ok((aligned_dates, aligned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use statn::core::io::Provenance;
+
+    #[test]
+    fn test_oos_returns_and_alignment() {
+        let prices = vec![0.0, 1.0, 2.0, 3.0, 1.0, 4.0, 5.0];
+        let dates: Vec<String> = (1..=7).map(|d| format!("2024010{}", d)).collect();
+        let data = MarketData { prices, volumes: Vec::new(), dates, max_lookback: 2, provenance: Provenance::default(), tradable: Vec::new(), highs: Vec::new(), lows: Vec::new(), opens: Vec::new() };
+        // Log returns 1, 1, -2, -3, 1 (see test_return_drawdown); the last
+        // three are decided on the held-out bars 3 to 5
+        let (dates, returns) = oos_returns(&[2.0, 50.0, 0.0, 0.0], &data, "original", 0.5);
+        assert_eq!(dates, vec!["20240105", "20240106", "20240107"]);
+        assert_eq!(returns, vec![(-2.0_f64).exp_m1(), (-3.0_f64).exp_m1(), 1.0_f64.exp_m1()]);
+
+        let series = vec![
+            (vec!["a".to_string(), "b".to_string(), "c".to_string()], vec![1.0, 2.0, 3.0]),
+            (vec!["c".to_string(), "a".to_string()], vec![30.0, 10.0]),
+        ];
+        let (common, aligned) = align_returns(&series).unwrap();
+        assert_eq!(common, vec!["a", "c"]);
+        assert_eq!(aligned, vec![vec![1.0, 3.0], vec![10.0, 30.0]]);
+
+        let undated = vec![(Vec::new(), vec![1.0, 2.0, 3.0]), series[1].clone()];
+        assert_eq!(align_returns(&undated).unwrap().1, vec![vec![2.0, 3.0], vec![30.0, 10.0]]);
+        assert!(align_returns(&[series[0].clone(), (vec!["z".to_string()], vec![1.0])]).is_err());
+
+        assert_eq!(
+            "out/params.txt=data/XAG.csv".parse::<SystemSpec>(),
+            Ok(SystemSpec { params_file: "out/params.txt".into(), data_file: "data/XAG.csv".into() })
+        );
+        assert!("params.txt".parse::<SystemSpec>().is_err());
+    }
+}
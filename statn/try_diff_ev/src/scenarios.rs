@@ -0,0 +1,210 @@
+//! Replay of a fitted strategy through labeled historical stress windows.
+//!
+//! Scenarios are date ranges named in a TOML file, such as the 2008 crisis
+//! or the March 2020 crash. Signals are generated on the full history so
+//! indicators are warmed up, then each window is backtested on its own,
+//! starting flat.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::backtest::{backtest_signals_with, BacktestOptions};
+use crate::signals_generators::SignalResult;
+
+/// A named window of history, both ends included
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    /// First day, as YYYY-MM-DD or YYYYMMDD
+    pub start: String,
+    /// Last day, as YYYY-MM-DD or YYYYMMDD
+    pub end: String,
+}
+
+/// Scenarios of a TOML file, one `[[scenario]]` table each
+#[derive(Debug, Deserialize)]
+struct ScenarioFile {
+    #[serde(default)]
+    scenario: Vec<Scenario>,
+}
+
+/// YYYYMMDD of a date or time field, from its first eight digits
+fn day_of(date: &str) -> Option<u32> {
+    let digits: String = date.chars().filter(|c| c.is_ascii_digit()).take(8).collect();
+    if digits.len() == 8 { digits.parse().ok() } else { None }
+}
+
+/// Scenarios defined in TOML `text`:
+///
+/// ```toml
+/// [[scenario]]
+/// name = "COVID crash"
+/// start = "2020-02-19"
+/// end = "2020-03-23"
+/// ```
+pub fn parse_scenarios(text: &str) -> Result<Vec<Scenario>, String> {
+    let file: ScenarioFile = toml::from_str(text).map_err(|e| format!("Invalid scenarios: {}", e))?;
+    if file.scenario.is_empty() {
+        return Err("No [[scenario]] tables".to_string());
+    }
+    for s in &file.scenario {
+        match (day_of(&s.start), day_of(&s.end)) {
+            (Some(start), Some(end)) if start <= end => {}
+            (Some(_), Some(_)) => return Err(format!("Scenario '{}' ends before it starts", s.name)),
+            _ => return Err(format!("Scenario '{}' has an invalid date", s.name)),
+        }
+    }
+    Ok(file.scenario)
+}
+
+/// Scenarios of the TOML file at `path` (see `parse_scenarios`)
+pub fn read_scenarios<P: AsRef<Path>>(path: P) -> Result<Vec<Scenario>, String> {
+    let text = std::fs::read_to_string(path.as_ref())
+        .map_err(|e| format!("Cannot read scenarios {}: {}", path.as_ref().display(), e))?;
+    parse_scenarios(&text)
+}
+
+/// Backtest of one scenario; the figures are zero when the data has no
+/// bars in its window
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioResult {
+    pub scenario: Scenario,
+    /// Bars of the data inside the window
+    pub bars: usize,
+    /// Return over the window, in percent
+    pub return_pct: f64,
+    /// Largest drawdown within the window, in percent
+    pub max_drawdown_pct: f64,
+    /// Share of the bars with a position, in percent
+    pub exposure_pct: f64,
+    pub num_trades: usize,
+}
+
+/// Backtest the signals of `result` over each scenario's window of `dates`
+/// (aligned with the prices), with `options` aligned with them as well.
+pub fn run_scenarios(
+    result: &SignalResult,
+    dates: &[String],
+    scenarios: &[Scenario],
+    budget: f64,
+    options: &BacktestOptions,
+) -> Result<Vec<ScenarioResult>, String> {
+    if dates.len() != result.prices.len() {
+        return Err("Scenarios need a date for every price".to_string());
+    }
+    let days: Vec<Option<u32>> = dates.iter().map(|d| day_of(d)).collect();
+
+    Ok(scenarios
+        .iter()
+        .map(|scenario| {
+            let (start, end) = (day_of(&scenario.start), day_of(&scenario.end));
+            let inside = |day: &Option<u32>| day.is_some() && *day >= start && *day <= end;
+            let window = days.iter().position(inside).map(|first| {
+                let last = days.iter().rposition(inside).unwrap_or(first);
+                first..last + 1
+            });
+            let Some(range) = window else {
+                return ScenarioResult {
+                    scenario: scenario.clone(),
+                    bars: 0,
+                    return_pct: 0.0,
+                    max_drawdown_pct: 0.0,
+                    exposure_pct: 0.0,
+                    num_trades: 0,
+                };
+            };
+
+            let slice = SignalResult {
+                prices: result.prices[range.clone()].to_vec(),
+                signals: result.signals[range.clone()].to_vec(),
+                ..*result
+            };
+            let stats = backtest_signals_with(&slice, budget, &options.slice(range.clone()));
+            let exposed = stats.position_history.iter().filter(|&&p| p != 0).count();
+            ScenarioResult {
+                scenario: scenario.clone(),
+                bars: range.len(),
+                return_pct: stats.roi_percent,
+                max_drawdown_pct: stats.max_drawdown,
+                exposure_pct: 100.0 * exposed as f64 / range.len() as f64,
+                num_trades: stats.num_trades,
+            }
+        })
+        .collect())
+}
+
+/// One row per scenario: `scenario,start,end,bars,return_pct,max_drawdown_pct,exposure_pct,trades`
+pub fn scenarios_to_csv(results: &[ScenarioResult]) -> String {
+    let mut csv = "scenario,start,end,bars,return_pct,max_drawdown_pct,exposure_pct,trades\n".to_string();
+    for r in results {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            r.scenario.name, r.scenario.start, r.scenario.end, r.bars, r.return_pct, r.max_drawdown_pct, r.exposure_pct, r.num_trades
+        ));
+    }
+    csv
+}
+
+/// Markdown section of the model report, `heading` describing the strategy
+pub fn scenarios_to_markdown(results: &[ScenarioResult], heading: &str) -> String {
+    let mut md = format!("\n## Scenario Analysis\n{}\n\n", heading);
+    md.push_str("| Scenario | Window | Bars | Return | Max drawdown | Exposure | Trades |\n");
+    md.push_str("|---|---|---:|---:|---:|---:|---:|\n");
+    for r in results {
+        if r.bars == 0 {
+            md.push_str(&format!("| {} | {} to {} | 0 | no data | | | |\n", r.scenario.name, r.scenario.start, r.scenario.end));
+            continue;
+        }
+        md.push_str(&format!(
+            "| {} | {} to {} | {} | {:.2}% | {:.2}% | {:.1}% | {} |\n",
+            r.scenario.name, r.scenario.start, r.scenario.end, r.bars, r.return_pct, r.max_drawdown_pct, r.exposure_pct, r.num_trades
+        ));
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scenarios() {
+        let text = "[[scenario]]\nname = \"Crash\"\nstart = \"2020-02-19\"\nend = \"20200323\"\n";
+        let scenarios = parse_scenarios(text).unwrap();
+        assert_eq!(scenarios[0].name, "Crash");
+        assert!(parse_scenarios("[[scenario]]\nname = \"x\"\nstart = \"2020-03-01\"\nend = \"2020-02-01\"\n").is_err());
+        assert!(parse_scenarios("[[scenario]]\nname = \"x\"\nstart = \"March\"\nend = \"2020-02-01\"\n").is_err());
+        assert!(parse_scenarios("").is_err());
+    }
+
+    #[test]
+    fn test_run_scenarios() {
+        let prices: Vec<f64> = [100.0, 101.0, 95.0, 90.0, 92.0, 96.0].iter().map(|p: &f64| p.ln()).collect();
+        let dates: Vec<String> = (1..=6).map(|d| format!("2020-03-0{}", d)).collect();
+        let result = SignalResult {
+            prices,
+            signals: vec![1, 1, 0, 0, 0, 0],
+            long_lookback: 0,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        let scenarios = vec![
+            Scenario { name: "Drop".to_string(), start: "2020-03-02".to_string(), end: "2020-03-04".to_string() },
+            Scenario { name: "Later".to_string(), start: "2021-01-01".to_string(), end: "2021-02-01".to_string() },
+        ];
+        let results = run_scenarios(&result, &dates, &scenarios, 1000.0, &BacktestOptions::default()).unwrap();
+        // Long from 101, held down to 90 over bars 2 to 4
+        assert_eq!(results[0].bars, 3);
+        assert!((results[0].return_pct - (90.0 / 101.0 - 1.0) * 100.0).abs() < 1e-9);
+        assert!(results[0].max_drawdown_pct > 0.0);
+        assert!((results[0].exposure_pct - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(results[1].bars, 0);
+
+        let md = scenarios_to_markdown(&results, "test");
+        assert!(md.contains("| Drop | 2020-03-02 to 2020-03-04 | 3 |") && md.contains("no data"));
+        assert_eq!(scenarios_to_csv(&results).lines().count(), 3);
+        assert!(run_scenarios(&result, &dates[..2], &scenarios, 1000.0, &BacktestOptions::default()).is_err());
+    }
+}
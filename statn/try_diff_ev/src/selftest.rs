@@ -0,0 +1,358 @@
+//! End-to-end check of the pipeline on a tick archive.
+//!
+//! The ticks are resampled to bars, a fixed crossover system generates
+//! signals on the bars, the signals are backtested, their positions are
+//! permutation-tested and the mean bar return is bootstrapped. Each stage
+//! records the invariants it must satisfy, so `try_diff_ev self-test` proves
+//! the pipeline works on a machine without any market data: by default it
+//! replays a tick archive drawn from the trending fixture.
+
+use std::fmt;
+use std::path::Path;
+
+use montecarlo_permutation_test::mcpt_signals::run_mcpt_signals;
+use statn::core::matlib::mwc256::Mwc256;
+use statn::prelude::{Bootstrap, Interval};
+
+use crate::{audit_determinism, backtest_signals_with, generate_fixture, generate_signals, BacktestOptions, FixtureKind};
+
+/// Start of the bundled archive, 2024-01-01 00:00 UTC in epoch milliseconds
+const ARCHIVE_START_MS: i64 = 1_704_067_200_000;
+
+/// Ticks in each minute of the bundled archive
+const TICKS_PER_MINUTE: usize = 8;
+
+/// Generator and parameters of the system under test
+const GENERATOR: &str = "log_diff";
+const LONG_LOOKBACK: usize = 20;
+const SHORT_PCT: f64 = 25.0;
+const SHORT_THRESH: f64 = 0.0;
+const LONG_THRESH: f64 = 0.0;
+
+const BUDGET: f64 = 10_000.0;
+
+/// One trade of a tick archive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    pub timestamp_ms: i64,
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// OHLCV bar of the ticks in `[timestamp_ms, timestamp_ms + bar_ms)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TickBar {
+    pub timestamp_ms: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// Read a tick archive as `data_streamer` writes it: `timestamp_ms,price,volume`
+/// lines with an optional trailing side, skipping blank lines, `#` comments
+/// and a header
+pub fn read_ticks(path: &Path) -> Result<Vec<Tick>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let mut ticks = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let tick = match fields.as_slice() {
+            [timestamp, price, volume, ..] => match (timestamp.parse(), price.parse(), volume.parse()) {
+                (Ok(timestamp_ms), Ok(price), Ok(volume)) => Some(Tick { timestamp_ms, price, volume }),
+                _ => None,
+            },
+            _ => None,
+        };
+        match tick {
+            Some(tick) if tick.price > 0.0 => ticks.push(tick),
+            Some(_) => return Err(format!("Non-positive price on line {} of {}", i + 1, path.display())),
+            None if ticks.is_empty() && i == 0 => {}
+            None => return Err(format!("Invalid tick on line {} of {}", i + 1, path.display())),
+        }
+    }
+    Ok(ticks)
+}
+
+/// Tick archive drawn from the trending fixture: one minute per fixture bar,
+/// wandering from the previous close to the bar's close, which is the last
+/// tick of the minute
+pub fn bundled_ticks(minutes: usize, seed: u32) -> Result<Vec<Tick>, String> {
+    let fixture = generate_fixture(FixtureKind::Trending, minutes, seed)?;
+    let mut rng = Mwc256::with_seed(seed);
+    let mut ticks = Vec::with_capacity(minutes * TICKS_PER_MINUTE);
+    for (i, &close) in fixture.prices.iter().enumerate() {
+        let open = if i == 0 { close } else { fixture.prices[i - 1] };
+        for k in 0..TICKS_PER_MINUTE {
+            let frac = (k + 1) as f64 / TICKS_PER_MINUTE as f64;
+            let noise = if k + 1 == TICKS_PER_MINUTE { 0.0 } else { 0.002 * rng.normal() };
+            ticks.push(Tick {
+                timestamp_ms: ARCHIVE_START_MS + (i * 60_000 + k * 60_000 / TICKS_PER_MINUTE) as i64,
+                price: (open + frac * (close - open) + noise).exp(),
+                volume: 1.0 + 9.0 * rng.unifrand(),
+            });
+        }
+    }
+    Ok(ticks)
+}
+
+/// Resample ticks to bars of `bar_ms` milliseconds aligned on the epoch.
+/// Ticks are taken in time order, those of the same time in archive order;
+/// intervals without ticks have no bar.
+pub fn resample(ticks: &[Tick], bar_ms: i64) -> Vec<TickBar> {
+    let mut sorted = ticks.to_vec();
+    sorted.sort_by_key(|t| t.timestamp_ms);
+    let mut bars: Vec<TickBar> = Vec::new();
+    for tick in sorted {
+        let start = tick.timestamp_ms.div_euclid(bar_ms) * bar_ms;
+        match bars.last_mut() {
+            Some(bar) if bar.timestamp_ms == start => {
+                bar.high = bar.high.max(tick.price);
+                bar.low = bar.low.min(tick.price);
+                bar.close = tick.price;
+                bar.volume += tick.volume;
+            }
+            _ => bars.push(TickBar {
+                timestamp_ms: start,
+                open: tick.price,
+                high: tick.price,
+                low: tick.price,
+                close: tick.price,
+                volume: tick.volume,
+            }),
+        }
+    }
+    bars
+}
+
+/// An invariant of one stage of the pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub stage: &'static str,
+    pub invariant: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Results of each stage and the invariants checked along the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    pub ticks: usize,
+    pub bars: usize,
+    pub trades: usize,
+    pub roi_percent: f64,
+    pub sharpe_ratio: f64,
+    pub nreps: usize,
+    pub p_value: f64,
+    pub skill: f64,
+    /// Mean log return per bar of the positions, with its bootstrap interval
+    pub mean_return: Interval,
+    pub checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    pub fn failures(&self) -> Vec<&Check> {
+        self.checks.iter().filter(|c| !c.passed).collect()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Run the pipeline on `ticks` resampled to bars of `bar_ms` milliseconds,
+/// with `nreps` MCPT replications. Fails if a stage cannot run at all, e.g.
+/// on too few bars; a stage that runs but breaks an invariant is reported as
+/// a failed check instead.
+pub fn run_self_test(ticks: &[Tick], bar_ms: i64, nreps: usize) -> Result<SelfTestReport, String> {
+    if bar_ms <= 0 {
+        return Err(format!("Bar length must be positive, got {} ms", bar_ms));
+    }
+    let mut checks = Vec::new();
+    let mut check = |stage, invariant, passed, detail: String| checks.push(Check { stage, invariant, passed, detail });
+
+    // Resample
+    let bars = resample(ticks, bar_ms);
+    if bars.len() < 2 * LONG_LOOKBACK {
+        return Err(format!("{} bars is too few; at least {} are needed", bars.len(), 2 * LONG_LOOKBACK));
+    }
+    let buckets = {
+        let mut b: Vec<i64> = ticks.iter().map(|t| t.timestamp_ms.div_euclid(bar_ms)).collect();
+        b.sort_unstable();
+        b.dedup();
+        b.len()
+    };
+    check("resample", "one bar per interval with ticks", bars.len() == buckets, format!("{} bars, {} intervals", bars.len(), buckets));
+    let bad_range = bars.iter().filter(|b| !(b.low <= b.open.min(b.close) && b.high >= b.open.max(b.close))).count();
+    check("resample", "low <= open, close <= high", bad_range == 0, format!("{} bars out of range", bad_range));
+    let tick_volume: f64 = ticks.iter().map(|t| t.volume).sum();
+    let bar_volume: f64 = bars.iter().map(|b| b.volume).sum();
+    check(
+        "resample",
+        "bar volume sums to tick volume",
+        (bar_volume - tick_volume).abs() <= 1e-9 * tick_volume.abs().max(1.0),
+        format!("{:.6} vs {:.6}", bar_volume, tick_volume),
+    );
+    let prices: Vec<f64> = bars.iter().map(|b| b.close.ln()).collect();
+
+    // Signals
+    let signals = generate_signals(GENERATOR, &prices, LONG_LOOKBACK, SHORT_PCT, SHORT_THRESH, LONG_THRESH)?;
+    check(
+        "signals",
+        "one signal of -1, 0 or 1 per bar",
+        signals.signals.len() == prices.len() && signals.signals.iter().all(|s| (-1..=1).contains(s)),
+        format!("{} signals for {} bars", signals.signals.len(), prices.len()),
+    );
+
+    // Backtest
+    let options = BacktestOptions::default();
+    let stats = backtest_signals_with(&signals, BUDGET, &options);
+    check(
+        "backtest",
+        "budget and position histories cover every bar",
+        stats.budget_history.len() == prices.len() && stats.position_history.len() == prices.len(),
+        format!("{} and {} entries", stats.budget_history.len(), stats.position_history.len()),
+    );
+    check(
+        "backtest",
+        "final budget is finite and positive",
+        stats.final_budget.is_finite() && stats.final_budget > 0.0,
+        format!("{:.2}", stats.final_budget),
+    );
+    let audit = audit_determinism(|| backtest_signals_with(&signals, BUDGET, &options), 2);
+    check("backtest", "deterministic across runs and threads", audit.is_deterministic(), audit.mismatches.join("; "));
+
+    // The position recorded at a bar was held from the bar before
+    let mut positions: Vec<f64> = stats.position_history[1..].iter().map(|&p| p as f64).collect();
+    positions.push(0.0);
+    let bar_returns: Vec<f64> = prices.windows(2).zip(&positions).map(|(w, p)| p * (w[1] - w[0])).collect();
+    let total_return: f64 = bar_returns.iter().sum();
+
+    // MCPT
+    let mcpt = run_mcpt_signals(nreps, &positions, prices.clone())?;
+    let p_value = mcpt.distribution().p_value();
+    check(
+        "mcpt",
+        "one replication per rep",
+        mcpt.replications.len() == nreps,
+        format!("{} replications", mcpt.replications.len()),
+    );
+    check("mcpt", "p-value in (0, 1]", p_value > 0.0 && p_value <= 1.0, format!("{:.4}", p_value));
+    check(
+        "mcpt",
+        "original criterion is the positions' return",
+        (mcpt.original().criterion - total_return).abs() < 1e-9,
+        format!("{:.6} vs {:.6}", mcpt.original().criterion, total_return),
+    );
+
+    // Bootstrap
+    let mean = |x: &[f64]| x.iter().sum::<f64>() / x.len() as f64;
+    let mean_return = Bootstrap::default().interval(&bar_returns, mean)?;
+    check(
+        "bootstrap",
+        "interval contains the estimate",
+        mean_return.low <= mean_return.estimate && mean_return.estimate <= mean_return.high,
+        format!("{:.6} in [{:.6}, {:.6}]", mean_return.estimate, mean_return.low, mean_return.high),
+    );
+
+    Ok(SelfTestReport {
+        ticks: ticks.len(),
+        bars: bars.len(),
+        trades: stats.num_trades,
+        roi_percent: stats.roi_percent,
+        sharpe_ratio: stats.sharpe_ratio,
+        nreps,
+        p_value,
+        skill: mcpt.skill(),
+        mean_return,
+        checks,
+    })
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Ticks:            {}", self.ticks)?;
+        writeln!(f, "Bars:             {}", self.bars)?;
+        writeln!(f, "Trades:           {}", self.trades)?;
+        writeln!(f, "ROI:              {:.2}%", self.roi_percent)?;
+        writeln!(f, "Sharpe ratio:     {:.4}", self.sharpe_ratio)?;
+        writeln!(f, "MCPT p-value:     {:.4} ({} replications)", self.p_value, self.nreps)?;
+        writeln!(f, "MCPT skill:       {:.4}", self.skill)?;
+        writeln!(
+            f,
+            "Mean bar return:  {:.6}  90% CI [{:.6}, {:.6}]",
+            self.mean_return.estimate, self.mean_return.low, self.mean_return.high
+        )?;
+        writeln!(f)?;
+        for check in &self.checks {
+            writeln!(
+                f,
+                "  {:<4} {:<10} {:<48} {}",
+                if check.passed { "ok" } else { "FAIL" },
+                check.stage,
+                check.invariant,
+                check.detail
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_resample() {
+        let tick = |timestamp_ms, price, volume| Tick { timestamp_ms, price, volume };
+        // Out of order, with an empty minute
+        let ticks = [tick(60_500, 11.0, 1.0), tick(1_000, 10.0, 2.0), tick(59_999, 9.0, 1.0), tick(180_000, 12.0, 3.0)];
+        let bars = resample(&ticks, 60_000);
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[0], TickBar { timestamp_ms: 0, open: 10.0, high: 10.0, low: 9.0, close: 9.0, volume: 3.0 });
+        assert_eq!(bars[1].timestamp_ms, 60_000);
+        assert_eq!(bars[2].timestamp_ms, 180_000);
+    }
+
+    #[test]
+    fn test_read_ticks() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp,price,volume,side\n1704067200000,42000.5,0.01,Buy\n\n1704067200100,42001,0.2").unwrap();
+        let ticks = read_ticks(file.path()).unwrap();
+        assert_eq!(ticks, vec![
+            Tick { timestamp_ms: 1704067200000, price: 42000.5, volume: 0.01 },
+            Tick { timestamp_ms: 1704067200100, price: 42001.0, volume: 0.2 },
+        ]);
+
+        writeln!(file, "1704067200200,0,1").unwrap();
+        assert!(read_ticks(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_self_test_on_bundled_archive() {
+        let ticks = bundled_ticks(300, 42).unwrap();
+        let fixture = generate_fixture(FixtureKind::Trending, 300, 42).unwrap();
+        // Each minute closes on the fixture's bar
+        let bars = resample(&ticks, 60_000);
+        assert_eq!(bars.len(), 300);
+        assert!(bars.iter().zip(&fixture.prices).all(|(b, p)| (b.close.ln() - p).abs() < 1e-9));
+
+        let report = run_self_test(&ticks, 60_000, 50).unwrap();
+        assert!(report.passed(), "{}", report);
+        assert_eq!((report.ticks, report.bars, report.nreps), (300 * TICKS_PER_MINUTE, 300, 50));
+        assert!(report.trades > 0);
+        // Seeded throughout: the same report every run
+        assert_eq!(run_self_test(&ticks, 60_000, 50).unwrap(), report);
+
+        // Five-minute bars of the same archive
+        assert_eq!(run_self_test(&ticks, 300_000, 20).unwrap().bars, 60);
+        assert!(run_self_test(&ticks[..100], 60_000, 20).is_err());
+        assert!(run_self_test(&ticks, 0, 20).is_err());
+    }
+}
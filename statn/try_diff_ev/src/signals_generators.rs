@@ -1,14 +1,447 @@
 //! Signal generation module for moving average crossover strategy.
 //! Contains multiple signal generator implementations.
+//!
+//! Generators implement `SignalGenerator`; a `GeneratorRegistry` builds them
+//! by name from an optimized parameter vector, so other crates can add their
+//! own without changing this one.
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use indicators::oscillators::macd::{macd_histogram, MacdConfig};
+use indicators::oscillators::rsi::rsi;
 
 pub use backtesting::SignalResult;
 
 // SignalResult is now imported from backtesting crate.
 
+/// Turns a price series into BUY (1), SELL (-1) and HOLD (0) signals.
+///
+/// Generators are shared between threads, e.g. by the determinism audit.
+pub trait SignalGenerator: Send + Sync {
+    fn generate(&self, prices: &[f64]) -> SignalResult;
+
+    /// Whether the signals are positions, a 0 meaning flat rather than hold,
+    /// as the generator's optimization criterion reads them. Backtest them
+    /// with `BacktestOptions::target_positions` set to this.
+    fn signals_are_positions(&self) -> bool {
+        false
+    }
+}
+
+impl<F: Fn(&[f64]) -> SignalResult + Send + Sync> SignalGenerator for F {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        self(prices)
+    }
+}
+
+/// Parameters of the moving average crossover generators, in the order the
+/// optimizer saves them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossoverParams {
+    pub long_lookback: usize,
+    pub short_pct: f64,
+    /// Thresholds ×10000
+    pub short_thresh: f64,
+    pub long_thresh: f64,
+}
+
+impl CrossoverParams {
+    /// From a parameter vector: long lookback, short %, short and long
+    /// thresholds
+    pub fn from_params(params: &[f64]) -> Result<Self, String> {
+        if params.len() < 4 {
+            return Err(format!("Crossover generators need 4 parameters, got {}", params.len()));
+        }
+        let long_lookback = (params[0] + 1.0e-10) as usize;
+        if long_lookback < 2 {
+            return Err(format!("Long lookback must be at least 2, got {}", params[0]));
+        }
+        Ok(CrossoverParams { long_lookback, short_pct: params[1], short_thresh: params[2], long_thresh: params[3] })
+    }
+}
+
+/// `generate_signals_original` as a `SignalGenerator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatioCrossover(pub CrossoverParams);
+
+impl SignalGenerator for RatioCrossover {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let p = &self.0;
+        generate_signals_original(prices, p.long_lookback, p.short_pct, p.short_thresh, p.long_thresh)
+    }
+}
+
+/// `generate_signals_log_diff` as a `SignalGenerator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogDiffCrossover(pub CrossoverParams);
+
+impl SignalGenerator for LogDiffCrossover {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let p = &self.0;
+        generate_signals_log_diff(prices, p.long_lookback, p.short_pct, p.short_thresh, p.long_thresh)
+    }
+}
+
+/// Parameters of the RSI mean-reversion generator, in the order the
+/// optimizer saves them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RsiParams {
+    /// Bars of Wilder's smoothing
+    pub period: usize,
+    /// Go long when the RSI falls below this level
+    pub oversold: f64,
+    /// Go short when the RSI rises above this level
+    pub overbought: f64,
+    /// Close a long once the RSI is back above this level, and a short once
+    /// it is back below `100 - exit`
+    pub exit: f64,
+}
+
+impl RsiParams {
+    /// From a parameter vector: period, oversold, overbought and exit levels
+    pub fn from_params(params: &[f64]) -> Result<Self, String> {
+        if params.len() < 4 {
+            return Err(format!("The RSI generator needs 4 parameters, got {}", params.len()));
+        }
+        let period = (params[0] + 1.0e-10) as usize;
+        if period < 2 {
+            return Err(format!("RSI period must be at least 2, got {}", params[0]));
+        }
+        Ok(RsiParams { period, oversold: params[1], overbought: params[2], exit: params[3] })
+    }
+}
+
+/// `generate_signals_rsi` as a `SignalGenerator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RsiReversion(pub RsiParams);
+
+impl SignalGenerator for RsiReversion {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let p = &self.0;
+        generate_signals_rsi(prices, p.period, p.oversold, p.overbought, p.exit)
+    }
+
+    fn signals_are_positions(&self) -> bool {
+        true
+    }
+}
+
+/// Parameters of the Bollinger-band breakout generator, in the order the
+/// optimizer saves them. Band widths are in standard deviations from the
+/// moving average.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BollingerParams {
+    /// Bars of the moving average and standard deviation
+    pub lookback: usize,
+    /// Close a position once the price is back within this width
+    pub exit_k: f64,
+    /// Go short when the price breaks below the band this wide
+    pub short_k: f64,
+    /// Go long when the price breaks above the band this wide
+    pub long_k: f64,
+}
+
+impl BollingerParams {
+    /// From a parameter vector: lookback, exit, short and long band widths
+    pub fn from_params(params: &[f64]) -> Result<Self, String> {
+        if params.len() < 4 {
+            return Err(format!("The Bollinger generator needs 4 parameters, got {}", params.len()));
+        }
+        let lookback = (params[0] + 1.0e-10) as usize;
+        if lookback < 2 {
+            return Err(format!("Band lookback must be at least 2, got {}", params[0]));
+        }
+        Ok(BollingerParams { lookback, exit_k: params[1], short_k: params[2], long_k: params[3] })
+    }
+}
+
+/// `generate_signals_bollinger` as a `SignalGenerator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BollingerBreakout(pub BollingerParams);
+
+impl SignalGenerator for BollingerBreakout {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let p = &self.0;
+        generate_signals_bollinger(prices, p.lookback, p.exit_k, p.short_k, p.long_k)
+    }
+
+    fn signals_are_positions(&self) -> bool {
+        true
+    }
+}
+
+/// Parameters of the MACD signal-line crossover generator, in the order the
+/// optimizer saves them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdParams {
+    /// Bars of the slow EMA
+    pub slow_period: usize,
+    /// Fast EMA period as a percentage of the slow one
+    pub fast_pct: f64,
+    /// Bars of the signal-line EMA of the MACD line
+    pub signal_period: usize,
+    /// Histogram (MACD line less signal line) beyond which a position is
+    /// taken, in ×10000 format
+    pub thresh: f64,
+}
+
+impl MacdParams {
+    /// From a parameter vector: slow period, fast %, signal period, threshold
+    pub fn from_params(params: &[f64]) -> Result<Self, String> {
+        if params.len() < 4 {
+            return Err(format!("The MACD generator needs 4 parameters, got {}", params.len()));
+        }
+        let slow_period = (params[0] + 1.0e-10) as usize;
+        if slow_period < 2 {
+            return Err(format!("Slow period must be at least 2, got {}", params[0]));
+        }
+        let signal_period = ((params[2] + 1.0e-10) as usize).max(1);
+        Ok(MacdParams { slow_period, fast_pct: params[1], signal_period, thresh: params[3] })
+    }
+}
+
+/// `generate_signals_macd` as a `SignalGenerator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdCrossover(pub MacdParams);
+
+impl SignalGenerator for MacdCrossover {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let p = &self.0;
+        generate_signals_macd(prices, p.slow_period, p.fast_pct, p.signal_period, p.thresh)
+    }
+
+    fn signals_are_positions(&self) -> bool {
+        true
+    }
+}
+
+/// Parameters of the Donchian-channel breakout generator, in the order the
+/// optimizer saves them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DonchianParams {
+    /// Bars of the entry channel and of the average true range
+    pub lookback: usize,
+    /// Exit channel length as a percentage of the entry channel
+    pub exit_pct: f64,
+    /// Trailing stop distance in average true ranges (0 = no stop)
+    pub stop_atr: f64,
+    /// Margin beyond the channel a breakout needs, in ×10000 format
+    pub thresh: f64,
+}
+
+impl DonchianParams {
+    /// From a parameter vector: lookback, exit %, stop ATRs, threshold
+    pub fn from_params(params: &[f64]) -> Result<Self, String> {
+        if params.len() < 4 {
+            return Err(format!("The Donchian generator needs 4 parameters, got {}", params.len()));
+        }
+        let lookback = (params[0] + 1.0e-10) as usize;
+        if lookback < 2 {
+            return Err(format!("Channel lookback must be at least 2, got {}", params[0]));
+        }
+        Ok(DonchianParams { lookback, exit_pct: params[1], stop_atr: params[2], thresh: params[3] })
+    }
+}
+
+/// `generate_signals_donchian` as a `SignalGenerator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DonchianBreakout(pub DonchianParams);
+
+impl SignalGenerator for DonchianBreakout {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let p = &self.0;
+        generate_signals_donchian(prices, p.lookback, p.exit_pct, p.stop_atr, p.thresh)
+    }
+
+    fn signals_are_positions(&self) -> bool {
+        true
+    }
+}
+
+/// Limits on how often a system may trade, so an optimizer cannot profit
+/// from churning in and out of the market bar by bar.
+///
+/// Both are counts of bars and the default of zero leaves a system as it is.
+/// A parameter vector carries them as a fifth and sixth value after the
+/// generator's four.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pacing {
+    /// Bars a position is held at least before it may be closed or reversed
+    pub min_hold: usize,
+    /// Bars the system stays flat after closing a position before opening
+    /// another; a reversal closes first and waits too
+    pub cooldown: usize,
+}
+
+impl Pacing {
+    /// From the fifth and sixth values of a parameter vector (rounded to
+    /// whole bars), none if the vector has only the generator's four
+    pub fn from_params(params: &[f64]) -> Self {
+        let bars = |i: usize| params.get(i).map_or(0, |&p| p.max(0.0).round() as usize);
+        Pacing { min_hold: bars(4), cooldown: bars(5) }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.min_hold > 0 || self.cooldown > 0
+    }
+
+    /// The positions (1, 0 or -1 per bar) a system holds when it wants
+    /// `desired` but is held to the pacing limits
+    pub fn apply(&self, desired: &[i32]) -> Vec<i32> {
+        let mut position = 0;
+        let mut held = 0;
+        // Bars flat since the last exit; no wait before the first entry
+        let mut flat = usize::MAX;
+        desired
+            .iter()
+            .map(|&d| {
+                let want = if position != 0 && held < self.min_hold { position } else { d };
+                if want != position {
+                    if position != 0 {
+                        position = 0;
+                        held = 0;
+                        flat = 0;
+                    }
+                    if want != 0 && flat >= self.cooldown {
+                        position = want;
+                    }
+                }
+                if position != 0 {
+                    held += 1;
+                } else {
+                    flat = flat.saturating_add(1);
+                }
+                position
+            })
+            .collect()
+    }
+}
+
+/// Signals read as positions, changed only on the bars `tradable` allows
+/// and carried through the others (flat until the first tradable bar). Bars
+/// past the end of `tradable` may trade.
+///
+/// The result is positions, as the optimization criterion reads it under a
+/// session: backtest it with `BacktestOptions::target_positions`, so a 0 on a
+/// tradable bar goes flat.
+pub fn hold_outside_session(signals: &[i32], tradable: &[bool]) -> Vec<i32> {
+    let mut position = 0;
+    signals
+        .iter()
+        .enumerate()
+        .map(|(i, &signal)| {
+            if tradable.get(i).copied().unwrap_or(true) {
+                position = signal;
+            }
+            position
+        })
+        .collect()
+}
+
+/// Whether a backtest reads the signals of `generator` as positions, with
+/// `tradable` the session mask they were held to by `hold_outside_session`
+/// (empty without a session). Inside a session every generator's signals are
+/// positions; without one the generator decides. Predict, scenarios and
+/// stress-params all set `BacktestOptions::target_positions` from this.
+pub fn backtest_as_positions(generator: &dyn SignalGenerator, tradable: &[bool]) -> bool {
+    generator.signals_are_positions() || !tradable.is_empty()
+}
+
+/// A generator whose signals, read as positions, are held to `pacing`.
+///
+/// Its signals are positions whatever the inner generator's are, so the
+/// flat bars of a cooldown close the position in a backtest.
+pub struct Paced {
+    pub inner: Box<dyn SignalGenerator>,
+    pub pacing: Pacing,
+}
+
+impl SignalGenerator for Paced {
+    fn generate(&self, prices: &[f64]) -> SignalResult {
+        let mut result = self.inner.generate(prices);
+        result.signals = self.pacing.apply(&result.signals);
+        result
+    }
+
+    fn signals_are_positions(&self) -> bool {
+        true
+    }
+}
+
+/// Builds a generator from a parameter vector
+pub type GeneratorFactory = Box<dyn Fn(&[f64]) -> Result<Box<dyn SignalGenerator>, String> + Send + Sync>;
+
+/// Signal generators by name.
+///
+/// The default registry has the built-in generators: "original",
+/// "log_diff" (alias "enhanced"), "rsi", "bollinger", "macd" and "donchian". `register`
+/// adds a generator or replaces one of the same name. `build` applies the
+/// `Pacing` of a parameter vector with six values to any of them.
+pub struct GeneratorRegistry {
+    factories: BTreeMap<String, GeneratorFactory>,
+}
+
+impl GeneratorRegistry {
+    /// A registry without any generator
+    pub fn empty() -> Self {
+        GeneratorRegistry { factories: BTreeMap::new() }
+    }
+
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&[f64]) -> Result<Box<dyn SignalGenerator>, String> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Registered names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    /// The generator `name` with the given parameters
+    pub fn build(&self, name: &str, params: &[f64]) -> Result<Box<dyn SignalGenerator>, String> {
+        let factory = self.factories.get(name).ok_or_else(|| {
+            format!("Unknown signal generator '{}' (expected one of: {})", name, self.names().join(", "))
+        })?;
+        let generator = factory(params)?;
+        let pacing = Pacing::from_params(params);
+        Ok(if pacing.is_active() { Box::new(Paced { inner: generator, pacing }) } else { generator })
+    }
+}
+
+impl Default for GeneratorRegistry {
+    fn default() -> Self {
+        let mut registry = GeneratorRegistry::empty();
+        registry.register("original", |p| Ok(Box::new(RatioCrossover(CrossoverParams::from_params(p)?))));
+        for name in ["log_diff", "enhanced"] {
+            registry.register(name, |p| Ok(Box::new(LogDiffCrossover(CrossoverParams::from_params(p)?))));
+        }
+        registry.register("rsi", |p| Ok(Box::new(RsiReversion(RsiParams::from_params(p)?))));
+        registry.register("bollinger", |p| Ok(Box::new(BollingerBreakout(BollingerParams::from_params(p)?))));
+        registry.register("macd", |p| Ok(Box::new(MacdCrossover(MacdParams::from_params(p)?))));
+        registry.register("donchian", |p| Ok(Box::new(DonchianBreakout(DonchianParams::from_params(p)?))));
+        registry
+    }
+}
+
+/// The default `GeneratorRegistry`, built on first use
+static DEFAULT_REGISTRY: LazyLock<GeneratorRegistry> = LazyLock::new(GeneratorRegistry::default);
+
 /// Dispatch function to select signal generator by name.
 ///
-/// * `generator_type` - Name of the generator ("original" or "log_diff").
-/// * ... other args ...
+/// * `generator_type` - Name of the generator in the default
+///   `GeneratorRegistry`; an unknown name falls back to "original".
+/// * ... other args ... - The four parameters in the optimizer's order (see
+///   the `from_params` of each generator's parameters).
+///
+/// Returns an error if the parameters are invalid for the generator, e.g. a
+/// lookback below 2.
 pub fn generate_signals(
     generator_type: &str,
     prices: &[f64],
@@ -16,15 +449,15 @@ pub fn generate_signals(
     short_pct: f64,
     short_thresh: f64,
     long_thresh: f64,
-) -> SignalResult {
-    match generator_type {
-        "log_diff" | "enhanced" => generate_signals_log_diff(prices, long_lookback, short_pct, short_thresh, long_thresh),
-        "original" => generate_signals_original(prices, long_lookback, short_pct, short_thresh, long_thresh),
-        _ => {
-            eprintln!("Warning: Unknown generator type '{}', defaulting to 'original'", generator_type);
-            generate_signals_original(prices, long_lookback, short_pct, short_thresh, long_thresh)
-        }
-    }
+) -> Result<SignalResult, String> {
+    let name = if DEFAULT_REGISTRY.contains(generator_type) {
+        generator_type
+    } else {
+        eprintln!("Warning: Unknown generator type '{}', defaulting to 'original'", generator_type);
+        "original"
+    };
+    let params = [long_lookback as f64, short_pct, short_thresh, long_thresh];
+    Ok(DEFAULT_REGISTRY.build(name, &params)?.generate(prices))
 }
 
 /// Original signal generator (Ratio of log-prices).
@@ -139,3 +572,492 @@ pub fn generate_signals_log_diff(
         long_thresh: long_thresh * 10000.0,
     }
 }
+
+/// Position of the RSI mean-reversion system at each bar, from the RSI up
+/// to and including that bar: long below `oversold` until the RSI is back
+/// above `exit`, short above `overbought` until it is back below
+/// `100 - exit`, flat before the RSI is defined.
+pub fn rsi_positions(prices: &[f64], period: usize, oversold: f64, overbought: f64, exit: f64) -> Vec<i32> {
+    let mut position = 0;
+    rsi(prices, period)
+        .iter()
+        .map(|&r| {
+            if r.is_nan() {
+                return 0;
+            }
+            if r < oversold {
+                position = 1;
+            } else if r > overbought {
+                position = -1;
+            } else if (position == 1 && r >= exit) || (position == -1 && r <= 100.0 - exit) {
+                position = 0;
+            }
+            position
+        })
+        .collect()
+}
+
+/// RSI mean-reversion signal generator.
+///
+/// Logic: buy when the market is oversold, sell when it is overbought, and
+/// go flat once the RSI has reverted past the exit level (see
+/// `rsi_positions`). The `SignalResult` carries the period in
+/// `long_lookback` and the oversold, overbought and exit levels in
+/// `short_pct`, `short_thresh` and `long_thresh`.
+///
+/// The signals are positions: backtest them with
+/// `BacktestOptions::target_positions`, or a 0 holds instead of closing.
+pub fn generate_signals_rsi(prices: &[f64], period: usize, oversold: f64, overbought: f64, exit: f64) -> SignalResult {
+    SignalResult {
+        prices: prices.to_vec(),
+        signals: rsi_positions(prices, period, oversold, overbought, exit),
+        long_lookback: period,
+        short_pct: oversold,
+        short_thresh: overbought,
+        long_thresh: exit,
+    }
+}
+
+/// Position of the Bollinger-band breakout system at each bar, from the
+/// bands up to and including that bar: long above the band `long_k`
+/// standard deviations over the moving average, short below the band
+/// `short_k` under it, and flat again once the price is back within
+/// `exit_k` of the average (below `average + exit_k * sd` for a long, above
+/// `average - exit_k * sd` for a short). Flat before the bands are defined.
+///
+/// The bands are those of `bollinger_bands` (population standard deviation),
+/// kept up to date with running sums so the optimizer can afford them.
+pub fn bollinger_positions(prices: &[f64], lookback: usize, exit_k: f64, short_k: f64, long_k: f64) -> Vec<i32> {
+    let mut position = 0;
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    let n = lookback as f64;
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| {
+            sum += price;
+            sum_sq += price * price;
+            if i >= lookback {
+                let old = prices[i - lookback];
+                sum -= old;
+                sum_sq -= old * old;
+            }
+            if lookback == 0 || i + 1 < lookback {
+                return 0;
+            }
+            let mean = sum / n;
+            let sd = (sum_sq / n - mean * mean).max(0.0).sqrt();
+            if price > mean + long_k * sd {
+                position = 1;
+            } else if price < mean - short_k * sd {
+                position = -1;
+            } else if (position == 1 && price < mean + exit_k * sd) || (position == -1 && price > mean - exit_k * sd) {
+                position = 0;
+            }
+            position
+        })
+        .collect()
+}
+
+/// Bollinger-band breakout signal generator.
+///
+/// Logic: buy when the price breaks out above the upper band, sell when it
+/// breaks out below the lower band, and go flat once it is back near the
+/// moving average (see `bollinger_positions`). The `SignalResult` carries the
+/// lookback in `long_lookback` and the exit, short and long band widths in
+/// `short_pct`, `short_thresh` and `long_thresh`.
+///
+/// The signals are positions: backtest them with
+/// `BacktestOptions::target_positions`, or a 0 holds instead of closing.
+pub fn generate_signals_bollinger(prices: &[f64], lookback: usize, exit_k: f64, short_k: f64, long_k: f64) -> SignalResult {
+    SignalResult {
+        prices: prices.to_vec(),
+        signals: bollinger_positions(prices, lookback, exit_k, short_k, long_k),
+        long_lookback: lookback,
+        short_pct: exit_k,
+        short_thresh: short_k,
+        long_thresh: long_k,
+    }
+}
+
+/// Fast EMA period of a MACD: `fast_pct` percent of the slow period, at
+/// least 1 bar
+pub fn macd_fast_period(slow_period: usize, fast_pct: f64) -> usize {
+    ((slow_period as f64 * fast_pct / 100.0) as usize).max(1)
+}
+
+/// Position of the MACD system at each bar: long while the histogram (MACD
+/// line less its signal line) is above `thresh / 10000`, short while it is
+/// below `-thresh / 10000`, flat in between and before the signal line is
+/// defined.
+pub fn macd_positions(prices: &[f64], slow_period: usize, fast_pct: f64, signal_period: usize, thresh: f64) -> Vec<i32> {
+    let config =
+        MacdConfig { fast_period: macd_fast_period(slow_period, fast_pct), slow_period, signal_period: signal_period.max(1) };
+    let thresh = thresh / 10000.0;
+    macd_histogram(prices, config)
+        .iter()
+        .map(|&h| {
+            if h > thresh {
+                1
+            } else if h < -thresh {
+                -1
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+/// MACD signal-line crossover generator.
+///
+/// Logic: buy when the MACD line crosses above its signal line by more than
+/// the threshold, sell when it crosses below by more than the threshold (see
+/// `macd_positions`). The `SignalResult` carries the slow period in
+/// `long_lookback`, the fast % in `short_pct`, the signal period in
+/// `short_thresh` and the threshold in `long_thresh`.
+///
+/// The signals are positions: backtest them with
+/// `BacktestOptions::target_positions`, or a 0 holds instead of closing.
+pub fn generate_signals_macd(prices: &[f64], slow_period: usize, fast_pct: f64, signal_period: usize, thresh: f64) -> SignalResult {
+    SignalResult {
+        prices: prices.to_vec(),
+        signals: macd_positions(prices, slow_period, fast_pct, signal_period, thresh),
+        long_lookback: slow_period,
+        short_pct: fast_pct,
+        short_thresh: signal_period as f64,
+        long_thresh: thresh,
+    }
+}
+
+/// Exit channel length of a Donchian system: `exit_pct` percent of the entry
+/// channel, at least 1 bar
+pub fn donchian_exit_period(lookback: usize, exit_pct: f64) -> usize {
+    ((lookback as f64 * exit_pct / 100.0) as usize).max(1)
+}
+
+/// Highest (`highest`) or lowest price of the `window` bars ending at each
+/// bar, NaN before the first full window, with a monotonic queue so each
+/// bar is handled once
+fn rolling_extreme(prices: &[f64], window: usize, highest: bool) -> Vec<f64> {
+    let mut queue = std::collections::VecDeque::new();
+    let beats = |a: f64, b: f64| if highest { a >= b } else { a <= b };
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| {
+            while queue.back().is_some_and(|&j: &usize| beats(price, prices[j])) {
+                queue.pop_back();
+            }
+            queue.push_back(i);
+            if queue.front().is_some_and(|&j| j + window <= i) {
+                queue.pop_front();
+            }
+            if i + 1 < window { f64::NAN } else { prices[queue[0]] }
+        })
+        .collect()
+}
+
+/// Position of the Donchian-channel breakout system at each bar: long when
+/// the price closes more than `thresh / 10000` above the highest close of
+/// the previous `lookback` bars, short when it closes that far below the
+/// lowest. A long is closed when the price falls below the lowest close of
+/// the previous exit-channel bars (see `donchian_exit_period`) or, if
+/// `stop_atr` is positive, more than `stop_atr` average true ranges below
+/// the highest close since entry; a short likewise. A breakout the other
+/// way closes and reverses at once. Flat for the first `lookback` bars.
+///
+/// With closes only, the true range of a bar is its absolute change and its
+/// average is taken over `lookback` bars.
+pub fn donchian_positions(prices: &[f64], lookback: usize, exit_pct: f64, stop_atr: f64, thresh: f64) -> Vec<i32> {
+    let lookback = lookback.max(1);
+    let exit_period = donchian_exit_period(lookback, exit_pct);
+    let (entry_high, entry_low) = (rolling_extreme(prices, lookback, true), rolling_extreme(prices, lookback, false));
+    let (exit_high, exit_low) = (rolling_extreme(prices, exit_period, true), rolling_extreme(prices, exit_period, false));
+    let thresh = thresh / 10000.0;
+
+    let mut position = 0;
+    // Highest close of a long or lowest of a short since entry
+    let mut extreme = 0.0_f64;
+    let mut range_sum = 0.0;
+    (0..prices.len())
+        .map(|i| {
+            let price = prices[i];
+            if i > 0 {
+                range_sum += (price - prices[i - 1]).abs();
+            }
+            if i > lookback {
+                range_sum -= (prices[i - lookback] - prices[i - lookback - 1]).abs();
+            }
+            if i < lookback {
+                return 0;
+            }
+            let stop = stop_atr * range_sum / lookback as f64;
+
+            if position == 1 {
+                extreme = extreme.max(price);
+                if price < exit_low[i - 1] || (stop_atr > 0.0 && price < extreme - stop) {
+                    position = 0;
+                }
+            } else if position == -1 {
+                extreme = extreme.min(price);
+                if price > exit_high[i - 1] || (stop_atr > 0.0 && price > extreme + stop) {
+                    position = 0;
+                }
+            }
+            if position != 1 && price > entry_high[i - 1] + thresh {
+                position = 1;
+                extreme = price;
+            } else if position != -1 && price < entry_low[i - 1] - thresh {
+                position = -1;
+                extreme = price;
+            }
+            position
+        })
+        .collect()
+}
+
+/// Donchian-channel breakout signal generator.
+///
+/// Logic: buy when the price breaks above its recent highs, sell when it
+/// breaks below its recent lows, and go flat on a shorter opposite breakout
+/// or a trailing stop (see `donchian_positions`). The `SignalResult` carries
+/// the lookback in `long_lookback` and the exit %, stop ATRs and threshold
+/// in `short_pct`, `short_thresh` and `long_thresh`.
+///
+/// The signals are positions: backtest them with
+/// `BacktestOptions::target_positions`, or a 0 holds instead of closing.
+pub fn generate_signals_donchian(prices: &[f64], lookback: usize, exit_pct: f64, stop_atr: f64, thresh: f64) -> SignalResult {
+    SignalResult {
+        prices: prices.to_vec(),
+        signals: donchian_positions(prices, lookback, exit_pct, stop_atr, thresh),
+        long_lookback: lookback,
+        short_pct: exit_pct,
+        short_thresh: stop_atr,
+        long_thresh: thresh,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backtesting::{backtest_signals_with, BacktestOptions, ExitReason, TradeStats};
+
+    /// Backtest of `generator` from a budget of 1000, with its signals read
+    /// as it says
+    fn backtest(generator: &dyn SignalGenerator, prices: &[f64]) -> TradeStats {
+        let options = BacktestOptions { target_positions: generator.signals_are_positions(), ..Default::default() };
+        backtest_signals_with(&generator.generate(prices), 1000.0, &options)
+    }
+
+    /// Entry and exit bars of the trades of `backtest`
+    fn backtest_trades(generator: &dyn SignalGenerator, prices: &[f64]) -> Vec<(usize, usize, ExitReason)> {
+        backtest(generator, prices)
+            .trades
+            .iter()
+            .map(|t| (t.entry_index, t.exit_index, t.exit_reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_registry_builds_and_extends() {
+        let prices: Vec<f64> = (0..60).map(|i| (100.0 + 10.0 * (i as f64 / 6.0).sin()).ln()).collect();
+        let params = [10.0, 30.0, 5.0, 5.0];
+
+        let registry = GeneratorRegistry::default();
+        assert_eq!(registry.names(), vec!["bollinger", "donchian", "enhanced", "log_diff", "macd", "original", "rsi"]);
+        for name in registry.names() {
+            let built = registry.build(name, &params).unwrap().generate(&prices);
+            assert_eq!(built.signals, generate_signals(name, &prices, 10, 30.0, 5.0, 5.0).unwrap().signals);
+        }
+        assert!(registry.build("momentum", &params).is_err());
+        assert_eq!(generate_signals("momentum", &prices, 10, 30.0, 5.0, 5.0).unwrap().signals, registry.build("original", &params).unwrap().generate(&prices).signals);
+        assert!(registry.build("original", &params[..3]).is_err());
+        assert!(generate_signals("original", &prices, 1, 30.0, 5.0, 5.0).is_err());
+
+        // A custom generator plugged in from outside: always long
+        let mut registry = GeneratorRegistry::default();
+        registry.register("always_long", |_| {
+            Ok(Box::new(|prices: &[f64]| SignalResult {
+                prices: prices.to_vec(),
+                signals: vec![1; prices.len()],
+                long_lookback: 1,
+                short_pct: 0.0,
+                short_thresh: 0.0,
+                long_thresh: 0.0,
+            }))
+        });
+        let result = registry.build("always_long", &[]).unwrap().generate(&prices);
+        assert!(result.signals.iter().all(|&s| s == 1));
+    }
+
+    #[test]
+    fn test_pacing() {
+        let desired = [1, -1, 1, 1, 0, 0, -1, -1, 1, 0];
+        assert_eq!(Pacing::default().apply(&desired), desired);
+        // Each position is held three bars
+        let min_hold = Pacing { min_hold: 3, cooldown: 0 };
+        assert_eq!(min_hold.apply(&desired), vec![1, 1, 1, 1, 0, 0, -1, -1, -1, 0]);
+        // Two flat bars after every exit, a reversal included
+        let cooldown = Pacing { min_hold: 0, cooldown: 2 };
+        assert_eq!(cooldown.apply(&desired), vec![1, 0, 0, 1, 0, 0, -1, -1, 0, 0]);
+        // Outside the session the position is carried, flat before the first
+        // tradable bar
+        let tradable = [false, true, false, false, true, true, false, true];
+        assert_eq!(hold_outside_session(&desired, &tradable), vec![0, -1, -1, -1, 0, 0, 0, -1, 1, 0]);
+        // The backtest carries the long through the session break and closes
+        // it on the first tradable bar after
+        let tradable = [true, true, false, false, true, true];
+        let session = SignalResult {
+            prices: vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5],
+            signals: hold_outside_session(&[1, 1, 0, 0, 0, 0], &tradable),
+            long_lookback: 1,
+            short_pct: 0.0,
+            short_thresh: 0.0,
+            long_thresh: 0.0,
+        };
+        let options = BacktestOptions { tradable: Some(&tradable), target_positions: true, ..Default::default() };
+        let trade = &backtest_signals_with(&session, 1000.0, &options).trades[0];
+        assert_eq!((trade.entry_index, trade.exit_index, trade.exit_reason), (0, 4, ExitReason::Signal));
+        // So a crossover's signals are positions under a session only
+        let crossover = GeneratorRegistry::default().build("original", &[10.0, 50.0, 0.0, 0.0]).unwrap();
+        assert!(!backtest_as_positions(crossover.as_ref(), &[]));
+        assert!(backtest_as_positions(crossover.as_ref(), &tradable));
+
+        assert_eq!(Pacing::from_params(&[10.0, 50.0, 0.0, 0.0]), Pacing::default());
+        assert_eq!(Pacing::from_params(&[10.0, 50.0, 0.0, 0.0, 2.6, 1.0]), Pacing { min_hold: 3, cooldown: 1 });
+        let prices: Vec<f64> = (0..40).map(|i| ((i as f64) * 0.7).sin()).collect();
+        let params = [2.0, 30.0, 70.0, 50.0];
+        let registry = GeneratorRegistry::default();
+        let plain = registry.build("rsi", &params).unwrap().generate(&prices);
+        let paced = registry.build("rsi", &[2.0, 30.0, 70.0, 50.0, 4.0, 2.0]).unwrap().generate(&prices);
+        assert_eq!(paced.signals, Pacing { min_hold: 4, cooldown: 2 }.apply(&plain.signals));
+
+        // A backtest closes the position for the cooldown, even of a
+        // generator whose 0 holds, and reopens after it
+        let mut registry = GeneratorRegistry::empty();
+        registry.register("flip", |_| {
+            Ok(Box::new(|prices: &[f64]| SignalResult {
+                prices: prices.to_vec(),
+                signals: (0..prices.len()).map(|i| if i < 4 { 1 } else { -1 }).collect(),
+                long_lookback: 1,
+                short_pct: 0.0,
+                short_thresh: 0.0,
+                long_thresh: 0.0,
+            }))
+        });
+        let paced = registry.build("flip", &[0.0, 0.0, 0.0, 0.0, 0.0, 2.0]).unwrap();
+        assert!(paced.signals_are_positions());
+        let trades = backtest_trades(paced.as_ref(), &prices[..10]);
+        assert_eq!(trades, vec![(0, 4, ExitReason::Signal), (6, 9, ExitReason::EndOfData)]);
+        let plain = registry.build("flip", &[0.0; 4]).unwrap();
+        let trades = backtest_trades(plain.as_ref(), &prices[..10]);
+        assert_eq!(trades, vec![(0, 4, ExitReason::Signal), (4, 9, ExitReason::EndOfData)]);
+    }
+
+    #[test]
+    fn test_rsi_positions() {
+        // Falls, rebounds, rallies, falls back; RSI(2) from bar 2 is
+        // 0, 0, 33, 52, 84, 93, 97, 98, 65, 27, 13
+        let mut prices = vec![10.0];
+        for change in [-1.0, -1.0, -1.0, 0.5, 0.3, 1.0, 1.0, 1.0, 1.0, -0.5, -1.0, -1.0] {
+            prices.push(prices[prices.len() - 1] + change);
+        }
+        let positions = rsi_positions(&prices, 2, 30.0, 70.0, 50.0);
+        // Long when oversold, held until the RSI is back above 50; short when
+        // overbought, held through 65, reversed when oversold again
+        assert_eq!(positions, vec![0, 0, 1, 1, 1, 0, -1, -1, -1, -1, -1, 1, 1]);
+        assert_eq!(rsi_positions(&prices, 2, 30.0, 70.0, 30.0)[4], 0);
+
+        let generator = GeneratorRegistry::default().build("rsi", &[2.0, 30.0, 70.0, 50.0]).unwrap();
+        let result = generator.generate(&prices);
+        assert_eq!(result.signals, positions);
+        // The backtest closes the long when the RSI is back above 50, not at
+        // the short
+        let trades = backtest_trades(generator.as_ref(), &prices);
+        assert_eq!(trades[..2], [(2, 5, ExitReason::Signal), (6, 11, ExitReason::Signal)]);
+        // The short, held by repeated -1s, is marked to market through the
+        // rally, which is the deepest drawdown
+        let stats = backtest(generator.as_ref(), &prices);
+        let budget = 1000.0 * (prices[5] - prices[2]).exp();
+        for i in 7..=10 {
+            assert!((stats.budget_history[i] - budget * (prices[6] - prices[i]).exp()).abs() < 1e-6);
+        }
+        assert!((stats.max_drawdown - 100.0 * (1.0 - (prices[5] - prices[2] + prices[6] - prices[9]).exp())).abs() < 1e-9);
+        assert_eq!(result.signals, generate_signals("rsi", &prices, 2, 30.0, 70.0, 50.0).unwrap().signals);
+    }
+
+    #[test]
+    fn test_bollinger_positions() {
+        // Flat, a breakout up, a drift back below the average, a breakdown
+        let prices = [1.0, 1.0, 1.0, 1.0, 2.0, 2.1, 1.6, 1.0, 0.0, 0.1];
+        let positions = bollinger_positions(&prices, 4, 0.0, 1.0, 1.0);
+        // Bars 4-7 have mean 1.25, 1.525, 1.675, 1.675 and sd 0.43, 0.53,
+        // 0.43, 0.43: long on the jump, closed below the average at bar 6,
+        // short below the lower band at bar 7
+        assert_eq!(positions, vec![0, 0, 0, 0, 1, 1, 0, -1, -1, -1]);
+        // Two standard deviations is too wide for the jump
+        assert_eq!(bollinger_positions(&prices, 4, 0.0, 1.0, 2.0)[4], 0);
+
+        let generator = GeneratorRegistry::default().build("bollinger", &[4.0, 0.0, 1.0, 1.0]).unwrap();
+        let result = generator.generate(&prices);
+        assert_eq!(result.signals, positions);
+        assert_eq!(backtest_trades(generator.as_ref(), &prices)[0], (4, 6, ExitReason::Signal));
+        assert_eq!(result.signals, generate_signals("bollinger", &prices, 4, 0.0, 1.0, 1.0).unwrap().signals);
+    }
+
+    #[test]
+    fn test_macd_positions() {
+        // Flat, a rally, then a selloff
+        let mut prices = vec![0.0; 15];
+        prices.extend((1..=15).map(|i| i as f64 * 0.01));
+        prices.extend((1..=30).map(|i| 0.15 - i as f64 * 0.01));
+        let positions = macd_positions(&prices, 10, 50.0, 4, 0.01);
+        // The MACD line leads its signal line both ways, at once
+        let expected: Vec<i32> = [vec![0; 15], vec![1; 15], vec![-1; 30]].concat();
+        assert_eq!(positions, expected);
+        // A wide threshold keeps it out of the market
+        assert!(macd_positions(&prices, 10, 50.0, 4, 1000.0).iter().all(|&p| p == 0));
+        assert_eq!(macd_fast_period(10, 50.0), 5);
+        assert_eq!(macd_fast_period(10, 1.0), 1);
+
+        let result = GeneratorRegistry::default().build("macd", &[10.0, 50.0, 4.0, 0.01]).unwrap().generate(&prices);
+        assert_eq!(result.signals, positions);
+        assert_eq!(result.signals, generate_signals("macd", &prices, 10, 50.0, 4.0, 0.01).unwrap().signals);
+
+        // The backtest is flat inside the dead zone: the long is closed before
+        // the short opens, and the short as the histogram fades on a plateau
+        let prices: Vec<f64> = [vec![0.0; 15], (1..=15).map(|i| i as f64 * 0.01).collect(), vec![0.15; 60]].concat();
+        let generator = GeneratorRegistry::default().build("macd", &[10.0, 50.0, 4.0, 10.0]).unwrap();
+        let trades = backtest_trades(generator.as_ref(), &prices);
+        assert_eq!(trades, vec![(16, 29, ExitReason::Signal), (31, 44, ExitReason::Signal)]);
+    }
+
+    #[test]
+    fn test_donchian_positions() {
+        // A breakout up, a pause, then a collapse through the channel
+        let prices = [0.0, 0.1, 0.0, 0.1, 0.5, 0.6, 0.7, 0.65, 0.3, 0.2, 0.1];
+        let positions = donchian_positions(&prices, 3, 100.0, 0.0, 0.0);
+        // Long above the 3-bar high at bar 4, held through the pause at bar 7,
+        // reversed below the 3-bar low at bar 8
+        assert_eq!(positions, vec![0, 0, 0, 0, 1, 1, 1, 1, -1, -1, -1]);
+        // Half an ATR (0.083 over bars 5-7) below the 0.7 peak stops the long
+        // out at bar 7; so does a one-bar exit channel
+        assert_eq!(donchian_positions(&prices, 3, 100.0, 0.5, 0.0)[7], 0);
+        assert_eq!(donchian_positions(&prices, 3, 34.0, 0.0, 0.0)[7], 0);
+        assert_eq!(donchian_positions(&prices, 3, 100.0, 1.0, 0.0)[7], 1);
+        // A breakout must clear the channel by the threshold
+        assert_eq!(donchian_positions(&prices, 3, 100.0, 0.0, 5000.0)[4], 0);
+        assert_eq!(rolling_extreme(&prices, 3, true)[2..6], [0.1, 0.1, 0.5, 0.6]);
+        assert_eq!(rolling_extreme(&prices, 3, false)[7..], [0.6, 0.3, 0.2, 0.1]);
+        assert_eq!(donchian_exit_period(3, 34.0), 1);
+
+        let result = GeneratorRegistry::default().build("donchian", &[3.0, 100.0, 0.0, 0.0]).unwrap().generate(&prices);
+        assert_eq!(result.signals, positions);
+        // The backtest closes the long at the stop and at the exit channel
+        for params in [[3.0, 100.0, 0.5, 0.0], [3.0, 34.0, 0.0, 0.0]] {
+            let generator = GeneratorRegistry::default().build("donchian", &params).unwrap();
+            assert_eq!(backtest_trades(generator.as_ref(), &prices)[0], (4, 7, ExitReason::Signal));
+        }
+        assert_eq!(result.signals, generate_signals("donchian", &prices, 3, 100.0, 0.0, 0.0).unwrap().signals);
+    }
+}
@@ -0,0 +1,294 @@
+//! Perturbation stress test of a saved parameter set.
+//!
+//! Each parameter is moved up and down by a few percent while the others stay
+//! put, and the system is re-backtested on the most recent bars. The worst
+//! drop in return shows how close the live parameters sit to a cliff.
+
+use crate::backtest::{backtest_signals_with, BacktestOptions, Slippage};
+use crate::io::MarketData;
+use crate::signals_generators::{backtest_as_positions, hold_outside_session, GeneratorRegistry, Pacing, SignalResult};
+use statn::core::io::Provenance;
+
+/// Names of the four trading parameters, in params file order
+pub const PARAM_NAMES: [&str; 4] = ["Long lookback", "Short %", "Short thresh", "Long thresh"];
+
+/// Names of the parameters of the RSI generator
+pub const RSI_PARAM_NAMES: [&str; 4] = ["RSI period", "Oversold", "Overbought", "Exit"];
+
+/// Names of the parameters of the Bollinger-band generator
+pub const BOLLINGER_PARAM_NAMES: [&str; 4] = ["Band lookback", "Exit width", "Short width", "Long width"];
+
+/// Names of the parameters of the MACD generator
+pub const MACD_PARAM_NAMES: [&str; 4] = ["Slow period", "Fast %", "Signal period", "Threshold"];
+
+/// Names of the parameters of the Donchian generator
+pub const DONCHIAN_PARAM_NAMES: [&str; 4] = ["Entry channel", "Exit %", "Stop ATRs", "Threshold"];
+
+/// Names of the fifth and sixth parameters, the `Pacing` of any generator
+pub const PACING_PARAM_NAMES: [&str; 2] = ["Min hold", "Cooldown"];
+
+/// Names of the four parameters of `generator`
+pub fn param_names(generator: &str) -> [&'static str; 4] {
+    match generator {
+        "rsi" => RSI_PARAM_NAMES,
+        "bollinger" => BOLLINGER_PARAM_NAMES,
+        "macd" => MACD_PARAM_NAMES,
+        "donchian" => DONCHIAN_PARAM_NAMES,
+        _ => PARAM_NAMES,
+    }
+}
+
+/// CSV column names of the four parameters of `generator`
+pub fn param_columns(generator: &str) -> [&'static str; 4] {
+    match generator {
+        "rsi" => ["rsi_period", "oversold", "overbought", "exit"],
+        "bollinger" => ["band_lookback", "exit_k", "short_k", "long_k"],
+        "macd" => ["slow_period", "fast_pct", "signal_period", "thresh"],
+        "donchian" => ["channel_lookback", "exit_pct", "stop_atr", "thresh"],
+        _ => ["long_lookback", "short_pct", "short_thresh", "long_thresh"],
+    }
+}
+
+/// Default perturbation sizes, in percent of each parameter
+pub const DEFAULT_STEPS: [f64; 3] = [5.0, 10.0, 20.0];
+
+/// Settings of a stress test
+#[derive(Debug, Clone)]
+pub struct StressOptions {
+    /// Signal generator name in the default `GeneratorRegistry`
+    pub generator: String,
+    /// Number of most recent bars backtested
+    pub window: usize,
+    pub budget: f64,
+    pub transaction_cost_pct: f64,
+    pub slippage: Slippage,
+    /// Perturbation sizes in percent; each is applied up and down
+    pub steps: Vec<f64>,
+}
+
+/// Backtest of one perturbed parameter set
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressCase {
+    /// Index of the perturbed parameter
+    pub param: usize,
+    /// Signed perturbation in percent
+    pub change_pct: f64,
+    /// Perturbed parameter set
+    pub params: [f64; 4],
+    /// ROI of the backtest, in percent
+    pub roi: f64,
+    /// Baseline ROI minus this ROI (positive is worse)
+    pub degradation: f64,
+}
+
+/// Result of `stress_params`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressReport {
+    /// Unperturbed parameters
+    pub params: [f64; 4],
+    /// ROI of the unperturbed parameters, in percent
+    pub baseline_roi: f64,
+    pub cases: Vec<StressCase>,
+    /// Data the cases were backtested on, written with the CSV if set
+    pub provenance: Option<Provenance>,
+    /// Signal generator the parameters belong to
+    pub generator: String,
+}
+
+impl StressReport {
+    /// Case with the largest degradation
+    pub fn worst(&self) -> Option<&StressCase> {
+        self.cases.iter().max_by(|a, b| a.degradation.total_cmp(&b.degradation))
+    }
+
+    /// Case with the largest degradation among perturbations of `param`
+    pub fn worst_for(&self, param: usize) -> Option<&StressCase> {
+        self.cases
+            .iter()
+            .filter(|c| c.param == param)
+            .max_by(|a, b| a.degradation.total_cmp(&b.degradation))
+    }
+
+    /// One row per case: `param,change_pct,long_lookback,short_pct,short_thresh,long_thresh,roi,degradation`
+    /// (with the generator's parameter columns, see `param_columns`), after
+    /// the provenance comment if set
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.provenance.as_ref().map(|p| p.comment() + "\n").unwrap_or_default();
+        csv.push_str(&format!("param,change_pct,{},roi,degradation\n", param_columns(&self.generator).join(",")));
+        let names = param_names(&self.generator);
+        let mut row = |name: &str, change_pct: f64, params: &[f64; 4], roi: f64, degradation: f64| {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                name, change_pct, params[0], params[1], params[2], params[3], roi, degradation
+            ));
+        };
+        row("Baseline", 0.0, &self.params, self.baseline_roi, 0.0);
+        for case in &self.cases {
+            row(names[case.param], case.change_pct, &case.params, case.roi, case.degradation);
+        }
+        csv
+    }
+}
+
+/// Move parameter `param` by `change_pct` percent, keeping it valid.
+///
+/// The lookback moves by at least one bar and stays at 2 or more; the short
+/// percentage stays within (0, 99] and the thresholds non-negative. A zero
+/// parameter is unchanged by a relative move.
+pub fn perturb(params: &[f64; 4], param: usize, change_pct: f64) -> [f64; 4] {
+    let mut perturbed = *params;
+    let value = params[param] * (1.0 + change_pct / 100.0);
+    perturbed[param] = match param {
+        0 => {
+            let lookback = params[0].round();
+            let mut moved = value.round();
+            if moved == lookback && change_pct != 0.0 {
+                moved += change_pct.signum();
+            }
+            moved.max(2.0)
+        }
+        1 => value.clamp(0.01, 99.0),
+        _ => value.max(0.0),
+    };
+    perturbed
+}
+
+/// ROI in percent of a parameter set backtested on the last `window` bars.
+///
+/// Signals are generated on the full series so the moving averages are warmed
+/// up when the window starts.
+fn window_roi(
+    registry: &GeneratorRegistry,
+    data: &MarketData,
+    params: &[f64; 4],
+    pacing: &Pacing,
+    options: &StressOptions,
+) -> Result<f64, String> {
+    let paced = [params[0], params[1], params[2], params[3], pacing.min_hold as f64, pacing.cooldown as f64];
+    let generator = registry.build(&options.generator, &paced)?;
+    let result = generator.generate(&data.prices);
+    let signals = hold_outside_session(&result.signals, &data.tradable);
+    let start = data.prices.len() - options.window;
+    let window = SignalResult {
+        prices: result.prices[start..].to_vec(),
+        signals: signals[start..].to_vec(),
+        ..result
+    };
+    let backtest = BacktestOptions {
+        transaction_cost_pct: options.transaction_cost_pct,
+        slippage: options.slippage,
+        volumes: data.volumes.get(start..).filter(|v| !v.is_empty()),
+        tradable: data.tradable.get(start..).filter(|t| !t.is_empty()),
+        target_positions: backtest_as_positions(generator.as_ref(), &data.tradable),
+        ..Default::default()
+    };
+    Ok(backtest_signals_with(&window, options.budget, &backtest).roi_percent)
+}
+
+/// Backtest `params` and every perturbation of one parameter by each step,
+/// up and down, on the last `options.window` bars.
+///
+/// Only the generator's four parameters are perturbed; a `Pacing` saved
+/// after them applies unchanged to every case.
+///
+/// # Arguments
+/// * `data` - Log prices, with volumes and a session mask if any
+/// * `params` - Saved parameters (at least 4)
+/// * `options` - Stress test settings
+pub fn stress_params(
+    data: &MarketData,
+    params: &[f64],
+    options: &StressOptions,
+) -> Result<StressReport, String> {
+    if params.len() < 4 {
+        return Err("Parameters file must contain at least 4 values".to_string());
+    }
+    if options.window < 2 || options.window >= data.prices.len() {
+        return Err(format!(
+            "Window of {} bars must be at least 2 and less than the {} prices",
+            options.window,
+            data.prices.len()
+        ));
+    }
+
+    let registry = GeneratorRegistry::default();
+    let pacing = Pacing::from_params(params);
+    let params = [params[0], params[1], params[2], params[3]];
+    let baseline_roi = window_roi(&registry, data, &params, &pacing, options)?;
+
+    let mut cases = Vec::new();
+    for param in 0..params.len() {
+        for &step in &options.steps {
+            for change_pct in [-step, step] {
+                let perturbed = perturb(&params, param, change_pct);
+                let roi = window_roi(&registry, data, &perturbed, &pacing, options)?;
+                cases.push(StressCase {
+                    param,
+                    change_pct,
+                    params: perturbed,
+                    roi,
+                    degradation: baseline_roi - roi,
+                });
+            }
+        }
+    }
+
+    Ok(StressReport { params, baseline_roi, cases, provenance: None, generator: options.generator.clone() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perturb() {
+        let params = [10.0, 50.0, 2.0, 0.0];
+        assert_eq!(perturb(&params, 0, 20.0), [12.0, 50.0, 2.0, 0.0]);
+        // A 5% move of 10 bars still moves the lookback by one
+        assert_eq!(perturb(&params, 0, -5.0)[0], 9.0);
+        assert_eq!(perturb(&[2.0, 50.0, 2.0, 0.0], 0, -20.0)[0], 2.0);
+        assert_eq!(perturb(&params, 1, -10.0)[1], 45.0);
+        assert_eq!(perturb(&[10.0, 90.0, 2.0, 0.0], 1, 20.0)[1], 99.0);
+        assert_eq!(perturb(&params, 3, 20.0)[3], 0.0);
+    }
+
+    #[test]
+    fn test_stress_params() {
+        let prices: Vec<f64> = (0..600)
+            .map(|i| (100.0 + 0.05 * i as f64 + 3.0 * (i as f64 * 0.15).sin()).ln())
+            .collect();
+        let options = StressOptions {
+            generator: "original".to_string(),
+            window: 200,
+            budget: 10000.0,
+            transaction_cost_pct: 0.1,
+            slippage: Slippage::None,
+            steps: DEFAULT_STEPS.to_vec(),
+        };
+        let data = MarketData {
+            prices,
+            volumes: Vec::new(),
+            dates: Vec::new(),
+            max_lookback: 100,
+            provenance: Provenance::default(),
+            tradable: Vec::new(),
+            highs: Vec::new(),
+            lows: Vec::new(),
+            opens: Vec::new(),
+        };
+        let params = [20.0, 30.0, 0.0, 0.0];
+        let report = stress_params(&data, &params, &options).unwrap();
+
+        // Two directions per step per parameter
+        assert_eq!(report.cases.len(), 4 * 3 * 2);
+        let worst = report.worst().unwrap();
+        assert!(report.cases.iter().all(|c| c.degradation <= worst.degradation));
+        assert_eq!(report.worst_for(2).unwrap().param, 2);
+        // Zero thresholds cannot move, so they do not change the result
+        assert!(report.cases.iter().filter(|c| c.param >= 2).all(|c| c.degradation == 0.0));
+        assert_eq!(report.to_csv().lines().count(), 2 + report.cases.len());
+
+        let too_long = StressOptions { window: 600, ..options };
+        assert!(stress_params(&data, &params, &too_long).is_err());
+    }
+}
@@ -1,5 +1,10 @@
 
 /// Evaluate a thresholded moving-average crossover system
+///
+/// Returns (total return, bars with a position, total absolute position change).
+/// If given, `returns` and `positions` receive the return and position (-1, 0
+/// or 1) of each bar from `max_lookback - 1` on.
+#[allow(clippy::too_many_arguments)]
 pub fn test_system(
     prices: &[f64],
     max_lookback: usize,
@@ -8,7 +13,8 @@ pub fn test_system(
     short_thresh: f64,
     long_thresh: f64,
     returns: Option<&mut [f64]>,
-) -> (f64, i32) {
+    mut positions: Option<&mut [f64]>,
+) -> (f64, i32, f64) {
     let ncases = prices.len();
     let short_term = (0.01 * short_pct * long_term as f64) as usize;
     let short_term = short_term.max(1).min(long_term - 1);
@@ -18,15 +24,17 @@ pub fn test_system(
 
     let mut sum = 0.0;
     let mut ntrades = 0;
+    let mut position = 0.0;
+    let mut turnover = 0.0;
     
-    let process_trade = |i: usize, prices: &[f64], short_mean: f64, long_mean: f64| -> (f64, bool) {
+    let process_trade = |i: usize, prices: &[f64], short_mean: f64, long_mean: f64| -> (f64, f64) {
         let change = short_mean / long_mean - 1.0;
         if change > long_thresh {
-            (prices[i+1] - prices[i], true)
+            (prices[i+1] - prices[i], 1.0)
         } else if change < -short_thresh {
-            (prices[i] - prices[i+1], true)
+            (prices[i] - prices[i+1], -1.0)
         } else {
-            (0.0, false)
+            (0.0, 0.0)
         }
     };
     
@@ -37,8 +45,14 @@ pub fn test_system(
                 let short_mean: f64 = prices[i + 1 - short_term..=i].iter().sum::<f64>() / short_term as f64;
                 let long_mean: f64 = prices[i + 1 - long_term..=i].iter().sum::<f64>() / long_term as f64;
                 
-                let (ret, traded) = process_trade(i, prices, short_mean, long_mean);
-                if traded { ntrades += 1; }
+                let (ret, pos) = process_trade(i, prices, short_mean, long_mean);
+                if pos != 0.0 { ntrades += 1; }
+                turnover += f64::abs(pos - position);
+                position = pos;
+                if let Some(p) = positions.as_deref_mut()
+                    && let Some(slot) = p.get_mut(i + 1 - max_lookback) {
+                        *slot = pos;
+                    }
                 sum += ret;
                 if ret_idx < ret_slice.len() {
                     ret_slice[ret_idx] = ret;
@@ -51,13 +65,19 @@ pub fn test_system(
                 let short_mean: f64 = prices[i + 1 - short_term..=i].iter().sum::<f64>() / short_term as f64;
                 let long_mean: f64 = prices[i + 1 - long_term..=i].iter().sum::<f64>() / long_term as f64;
                 
-                let (ret, traded) = process_trade(i, prices, short_mean, long_mean);
-                if traded { ntrades += 1; }
+                let (ret, pos) = process_trade(i, prices, short_mean, long_mean);
+                if pos != 0.0 { ntrades += 1; }
+                turnover += f64::abs(pos - position);
+                position = pos;
+                if let Some(p) = positions.as_deref_mut()
+                    && let Some(slot) = p.get_mut(i + 1 - max_lookback) {
+                        *slot = pos;
+                    }
                 sum += ret;
             }
         }
     }
 
-    (sum, ntrades)
+    (sum, ntrades, turnover)
 }
 
@@ -0,0 +1,44 @@
+use crate::signals_generators::bollinger_positions;
+
+/// Evaluate the Bollinger-band breakout system (see `bollinger_positions`)
+///
+/// Takes the lookback, exit, short and long band widths in the places of
+/// `test_system`'s four parameters and returns the same (total return, bars
+/// with a position, total absolute position change), with the same
+/// `returns` and `positions` outputs.
+#[allow(clippy::too_many_arguments)]
+pub fn test_system_bollinger(
+    prices: &[f64],
+    max_lookback: usize,
+    lookback: usize,
+    exit_k: f64,
+    short_k: f64,
+    long_k: f64,
+    mut returns: Option<&mut [f64]>,
+    mut positions: Option<&mut [f64]>,
+) -> (f64, i32, f64) {
+    let held = bollinger_positions(prices, lookback, exit_k, short_k, long_k);
+
+    let mut sum = 0.0;
+    let mut ntrades = 0;
+    let mut position = 0.0;
+    let mut turnover = 0.0;
+    for i in (max_lookback - 1)..(prices.len() - 1) {
+        let pos = held[i] as f64;
+        let ret = pos * (prices[i + 1] - prices[i]);
+        if pos != 0.0 {
+            ntrades += 1;
+        }
+        turnover += f64::abs(pos - position);
+        position = pos;
+        if let Some(slot) = returns.as_deref_mut().and_then(|r| r.get_mut(i + 1 - max_lookback)) {
+            *slot = ret;
+        }
+        if let Some(slot) = positions.as_deref_mut().and_then(|p| p.get_mut(i + 1 - max_lookback)) {
+            *slot = pos;
+        }
+        sum += ret;
+    }
+
+    (sum, ntrades, turnover)
+}
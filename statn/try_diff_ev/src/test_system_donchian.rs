@@ -0,0 +1,44 @@
+use crate::signals_generators::donchian_positions;
+
+/// Evaluate the Donchian-channel breakout system (see `donchian_positions`)
+///
+/// Takes the lookback, exit %, stop ATRs and threshold in the places of
+/// `test_system`'s four parameters and returns the same (total return, bars
+/// with a position, total absolute position change), with the same
+/// `returns` and `positions` outputs.
+#[allow(clippy::too_many_arguments)]
+pub fn test_system_donchian(
+    prices: &[f64],
+    max_lookback: usize,
+    lookback: usize,
+    exit_pct: f64,
+    stop_atr: f64,
+    thresh: f64,
+    mut returns: Option<&mut [f64]>,
+    mut positions: Option<&mut [f64]>,
+) -> (f64, i32, f64) {
+    let held = donchian_positions(prices, lookback, exit_pct, stop_atr, thresh);
+
+    let mut sum = 0.0;
+    let mut ntrades = 0;
+    let mut position = 0.0;
+    let mut turnover = 0.0;
+    for i in (max_lookback - 1)..(prices.len() - 1) {
+        let pos = held[i] as f64;
+        let ret = pos * (prices[i + 1] - prices[i]);
+        if pos != 0.0 {
+            ntrades += 1;
+        }
+        turnover += f64::abs(pos - position);
+        position = pos;
+        if let Some(slot) = returns.as_deref_mut().and_then(|r| r.get_mut(i + 1 - max_lookback)) {
+            *slot = ret;
+        }
+        if let Some(slot) = positions.as_deref_mut().and_then(|p| p.get_mut(i + 1 - max_lookback)) {
+            *slot = pos;
+        }
+        sum += ret;
+    }
+
+    (sum, ntrades, turnover)
+}
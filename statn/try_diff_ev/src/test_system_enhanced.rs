@@ -2,6 +2,8 @@
 /// Evaluate a thresholded moving-average crossover system (Enhanced Version)
 ///
 /// Uses log-difference (short_mean - long_mean) instead of ratio.
+/// Returns the same (total return, bars with a position, turnover) as `test_system`.
+#[allow(clippy::too_many_arguments)]
 pub fn test_system_enhanced(
     prices: &[f64],
     max_lookback: usize,
@@ -10,7 +12,8 @@ pub fn test_system_enhanced(
     short_thresh: f64,
     long_thresh: f64,
     returns: Option<&mut [f64]>,
-) -> (f64, i32) {
+    mut positions: Option<&mut [f64]>,
+) -> (f64, i32, f64) {
     let ncases = prices.len();
     let short_term = (0.01 * short_pct * long_term as f64) as usize;
     let short_term = short_term.max(1).min(long_term - 1);
@@ -22,17 +25,19 @@ pub fn test_system_enhanced(
 
     let mut sum = 0.0;
     let mut ntrades = 0;
+    let mut position = 0.0;
+    let mut turnover = 0.0;
     
-    let process_trade = |i: usize, prices: &[f64], short_mean: f64, long_mean: f64| -> (f64, bool) {
+    let process_trade = |i: usize, prices: &[f64], short_mean: f64, long_mean: f64| -> (f64, f64) {
         // Correct logic for log-prices: difference instead of ratio
         let change = short_mean - long_mean;
         
         if change > long_thresh {
-            (prices[i+1] - prices[i], true)
+            (prices[i+1] - prices[i], 1.0)
         } else if change < -short_thresh {
-            (prices[i] - prices[i+1], true)
+            (prices[i] - prices[i+1], -1.0)
         } else {
-            (0.0, false)
+            (0.0, 0.0)
         }
     };
     
@@ -43,8 +48,14 @@ pub fn test_system_enhanced(
                 let short_mean: f64 = prices[i + 1 - short_term..=i].iter().sum::<f64>() / short_term as f64;
                 let long_mean: f64 = prices[i + 1 - long_term..=i].iter().sum::<f64>() / long_term as f64;
                 
-                let (ret, traded) = process_trade(i, prices, short_mean, long_mean);
-                if traded { ntrades += 1; }
+                let (ret, pos) = process_trade(i, prices, short_mean, long_mean);
+                if pos != 0.0 { ntrades += 1; }
+                turnover += f64::abs(pos - position);
+                position = pos;
+                if let Some(p) = positions.as_deref_mut()
+                    && let Some(slot) = p.get_mut(i + 1 - max_lookback) {
+                        *slot = pos;
+                    }
                 sum += ret;
                 if ret_idx < ret_slice.len() {
                     ret_slice[ret_idx] = ret;
@@ -57,12 +68,18 @@ pub fn test_system_enhanced(
                 let short_mean: f64 = prices[i + 1 - short_term..=i].iter().sum::<f64>() / short_term as f64;
                 let long_mean: f64 = prices[i + 1 - long_term..=i].iter().sum::<f64>() / long_term as f64;
                 
-                let (ret, traded) = process_trade(i, prices, short_mean, long_mean);
-                if traded { ntrades += 1; }
+                let (ret, pos) = process_trade(i, prices, short_mean, long_mean);
+                if pos != 0.0 { ntrades += 1; }
+                turnover += f64::abs(pos - position);
+                position = pos;
+                if let Some(p) = positions.as_deref_mut()
+                    && let Some(slot) = p.get_mut(i + 1 - max_lookback) {
+                        *slot = pos;
+                    }
                 sum += ret;
             }
         }
     }
 
-    (sum, ntrades)
+    (sum, ntrades, turnover)
 }
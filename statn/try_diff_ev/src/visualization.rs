@@ -1,8 +1,9 @@
-//! Visualization module for plotting trading signals.
+//! Visualization module for plotting trading signals and sensitivity curves.
 
 use crate::backtest::TradeStats;
 use crate::signals_generators::SignalResult;
 use plotters::prelude::*;
+use statn::estimators::sensitivity::SensitivityResult;
 use std::path::Path;
 
 /// Visualise the price series together with BUY/SELL markers.
@@ -96,3 +97,70 @@ pub fn visualise_signals<P: AsRef<Path>>(
     chart.configure_series_labels().border_style(BLACK).draw()?;
     Ok(())
 }
+
+/// Plot the sensitivity curve of each parameter, one panel per parameter.
+///
+/// The criterion is drawn against the parameter value, with a dashed
+/// vertical line at the optimum.
+///
+/// # Arguments
+/// * `result` - Sensitivity curves from `sensitivity_sweep`
+/// * `names` - Parameter names for the panel captions; missing names are numbered
+/// * `output_path` - Path where the chart PNG will be saved
+pub fn plot_sensitivity<P: AsRef<Path>>(
+    result: &SensitivityResult,
+    names: &[&str],
+    output_path: P,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ncols = 2;
+    let nrows = result.sweeps.len().div_ceil(ncols).max(1);
+    let root = BitMapBackend::new(output_path.as_ref(), (1280, 360 * nrows as u32)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    for (sweep, area) in result.sweeps.iter().zip(root.split_evenly((nrows, ncols)).iter()) {
+        let caption = match names.get(sweep.param) {
+            Some(name) => name.to_string(),
+            None => format!("Parameter {}", sweep.param + 1),
+        };
+
+        let x_min = sweep.values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let x_max = sweep.values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let y_min = sweep.criteria.iter().cloned().fold(f64::INFINITY, f64::min);
+        let y_max = sweep.criteria.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        // Flat curves still need a non-empty range
+        let x_pad = if x_max > x_min { 0.0 } else { 0.5 };
+        let y_pad = 0.05 * (y_max - y_min).max(1.0e-9);
+
+        let mut chart = ChartBuilder::on(area)
+            .caption(caption, ("sans-serif", 20).into_font())
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_min - x_pad..x_max + x_pad, y_min - y_pad..y_max + y_pad)?;
+
+        chart.configure_mesh().draw()?;
+
+        chart.draw_series(LineSeries::new(
+            sweep.values.iter().cloned().zip(sweep.criteria.iter().cloned()),
+            &BLUE,
+        ))?;
+        chart.draw_series(
+            sweep
+                .values
+                .iter()
+                .zip(&sweep.criteria)
+                .map(|(&x, &y)| Circle::new((x, y), 3, BLUE.filled())),
+        )?;
+
+        // Mark the optimum
+        chart.draw_series(DashedLineSeries::new(
+            vec![(sweep.optimum, y_min - y_pad), (sweep.optimum, y_max + y_pad)],
+            5,
+            5,
+            ShapeStyle::from(&RED),
+        ))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
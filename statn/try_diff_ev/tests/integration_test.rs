@@ -17,7 +17,8 @@ fn test_backtest_integration() {
         50.0,  // short_pct
         10.0,  // short_thresh
         10.0,  // long_thresh
-    );
+    )
+    .unwrap();
     
     // Run backtest
     let stats = backtest_signals(&result, 10000.0, 0.1);
@@ -56,7 +57,8 @@ fn test_backtest_with_log_diff_generator() {
         50.0,  // short_pct
         10.0,  // short_thresh
         10.0,  // long_thresh
-    );
+    )
+    .unwrap();
     
     // Run backtest
     let stats = backtest_signals(&result, 10000.0, 0.1);
@@ -89,7 +91,8 @@ fn test_trade_logging() {
         50.0,  // short_pct
         5.0,   // short_thresh
         5.0,   // long_thresh
-    );
+    )
+    .unwrap();
     
     // Run backtest
     let stats = backtest_signals(&result, 10000.0, 0.1);
@@ -113,3 +116,28 @@ fn test_trade_logging() {
              stats.trades[0].entry_price,
              stats.trades[0].exit_price);
 }
+
+#[test]
+fn test_generators_on_fixtures() {
+    use try_diff_ev::{generate_fixture, system_for, FixtureKind, DEFAULT_FIXTURE_BARS, DEFAULT_FIXTURE_SEED};
+
+    let fixture = |kind| generate_fixture(kind, DEFAULT_FIXTURE_BARS, DEFAULT_FIXTURE_SEED).unwrap().prices;
+    let trending = fixture(FixtureKind::Trending);
+    let mean_reverting = fixture(FixtureKind::MeanReverting);
+
+    // A slow crossover follows the trend and is whipsawed by mean reversion
+    let crossover = |prices: &[f64]| system_for("log_diff")(prices, 50, 50, 20.0, 0.0, 0.0, None, None).0;
+    assert!(crossover(&trending) > 0.5);
+    assert!(crossover(&mean_reverting) < -0.5);
+
+    // RSI(2) mean reversion is the other way round
+    let rsi = |prices: &[f64]| system_for("rsi")(prices, 20, 2, 30.0, 70.0, 50.0, None, None).0;
+    assert!(rsi(&mean_reverting) > 0.5);
+    assert!(rsi(&trending) < 0.0);
+
+    // So does a 50-bar Donchian breakout with a 3 ATR stop, and it is
+    // whipsawed the same way
+    let donchian = |prices: &[f64]| system_for("donchian")(prices, 50, 50, 50.0, 3.0, 0.0, None, None).0;
+    assert!(donchian(&trending) > 0.5);
+    assert!(donchian(&mean_reverting) < -0.5);
+}